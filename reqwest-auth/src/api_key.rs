@@ -0,0 +1,136 @@
+//! `ApiKeys` adds the API key of each host to its requests.
+use http::{HeaderName, HeaderValue};
+use reqwest_middleware::{RequestBuilder, RequestInitialiser};
+
+use crate::secret::Secret;
+
+/// Where an API key is put in the requests, see [`ApiKeys`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApiKeyPlacement {
+    /// In a header, such as `x-api-key`.
+    Header(HeaderName),
+    /// In a query parameter, such as `api_key`.
+    Query(String),
+    /// In an `Authorization: Bearer` header.
+    Bearer,
+}
+
+#[derive(Clone, Debug)]
+struct ApiKey {
+    // Lowercase host, or domain for `*.` patterns.
+    host: String,
+    wildcard: bool,
+    key: Secret<String>,
+    placement: ApiKeyPlacement,
+}
+
+impl ApiKey {
+    fn matches(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        if !self.wildcard {
+            return host == self.host;
+        }
+        match host.strip_suffix(&self.host) {
+            Some(subdomain) => subdomain.len() > 1 && subdomain.ends_with('.'),
+            None => false,
+        }
+    }
+}
+
+/// `ApiKeys` adds API keys to requests according to their host, so that an application calling
+/// several APIs sends each its own key, and none to other hosts.
+///
+/// Keys are registered for a host, such as `api.example.com`, or for all the subdomains of a
+/// domain with a `*.example.com` pattern, which doesn't match `example.com` itself. Exact hosts
+/// take precedence over patterns, and longer patterns over shorter ones, whatever their
+/// registration order. Requests to other hosts are left untouched.
+///
+/// Used as a [`RequestInitialiser`], the key is added as the request starts being built. It is
+/// not sent again should the server redirect to another host, except in a custom header:
+/// disable redirects, or only use [`ApiKeyPlacement::Header`] with hosts which don't redirect
+/// elsewhere.
+///
+/// ```
+/// use http::HeaderName;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{ApiKeyPlacement, ApiKeys};
+///
+/// let keys = ApiKeys::new()
+///     .with_key(
+///         "api.weather.example",
+///         "w3ather",
+///         ApiKeyPlacement::Header(HeaderName::from_static("x-api-key")),
+///     )
+///     .with_key("*.maps.example", "m4ps", ApiKeyPlacement::Query("key".into()))
+///     .with_key("api.payments.example", "pay", ApiKeyPlacement::Bearer);
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with_init(keys)
+///     .build();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ApiKeys {
+    keys: Vec<ApiKey>,
+}
+
+impl ApiKeys {
+    /// Construct `ApiKeys` without any key.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `key` at `placement` to the requests to `host`, a host name or a `*.` pattern.
+    pub fn with_key(
+        mut self,
+        host: impl Into<String>,
+        key: impl Into<String>,
+        placement: ApiKeyPlacement,
+    ) -> Self {
+        let host = host.into().to_ascii_lowercase();
+        let (host, wildcard) = match host.strip_prefix("*.") {
+            Some(domain) => (domain.to_owned(), true),
+            None => (host, false),
+        };
+        self.keys.push(ApiKey {
+            host,
+            wildcard,
+            key: Secret::new(key.into()),
+            placement,
+        });
+        self
+    }
+
+    /// The key to use for `host`, if any, and its placement.
+    pub fn key(&self, host: &str) -> Option<(&str, &ApiKeyPlacement)> {
+        self.keys
+            .iter()
+            .filter(|key| key.matches(host))
+            .max_by_key(|key| (!key.wildcard, key.host.len()))
+            .map(|key| (key.key.expose_secret().as_str(), &key.placement))
+    }
+}
+
+impl RequestInitialiser for ApiKeys {
+    fn init(&self, req: RequestBuilder) -> RequestBuilder {
+        // Initialisers run on fresh builders, so cloning one to find its host is cheap.
+        let host = req
+            .try_clone()
+            .and_then(|clone| clone.build().ok())
+            .and_then(|built| built.url().host_str().map(str::to_owned));
+        let (key, placement) = match host.as_deref().and_then(|host| self.key(host)) {
+            Some(key) => key,
+            None => return req,
+        };
+        match placement {
+            ApiKeyPlacement::Header(name) => match HeaderValue::from_str(key) {
+                Ok(mut value) => {
+                    value.set_sensitive(true);
+                    req.header(name, value)
+                }
+                // Let the request fail as it is built, like invalid headers do.
+                Err(_) => req.header(name, key),
+            },
+            ApiKeyPlacement::Query(name) => req.query(&[(name, key)]),
+            ApiKeyPlacement::Bearer => req.bearer_auth(key),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! `AuthRetryMiddleware` refreshes rejected credentials and replays requests once.
+use std::sync::Arc;
+
+use http::header::AUTHORIZATION;
+use http::{Extensions, StatusCode};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, ResendCount, Result};
+
+use crate::token::TokenProvider;
+
+/// Extension disabling [`AuthRetryMiddleware`] for a single request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DisableAuthRetry;
+
+/// Marks requests already replayed by an [`AuthRetryMiddleware`], so that stacked middlewares
+/// don't replay them again.
+#[derive(Clone, Copy, Debug)]
+struct AuthRetried;
+
+type RejectedFn = dyn Fn(&Response) -> bool + Send + Sync + 'static;
+
+/// `AuthRetryMiddleware` replays requests whose credentials were rejected, after discarding them
+/// with [`TokenProvider::invalidate`].
+///
+/// It goes before the middleware adding the credentials, such as a [`BearerAuthMiddleware`]
+/// sharing the same provider, so that the replayed request is authorized again with a fresh
+/// token. Responses are considered rejections when their status is `401 Unauthorized`, see
+/// [`with_rejected`](Self::with_rejected) to also match e.g. `403 Forbidden` responses.
+///
+/// A request is replayed at most once, even with several `AuthRetryMiddleware`, and only if its
+/// body can be cloned. Requests which already have an `Authorization` header, and those with the
+/// [`DisableAuthRetry`] extension, are left untouched.
+///
+/// ```
+/// use std::sync::Arc;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{AuthRetryMiddleware, BearerAuthMiddleware, FileToken, TokenProvider};
+///
+/// let token: Arc<dyn TokenProvider> = Arc::new(FileToken::new("/var/run/secrets/api-token"));
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(AuthRetryMiddleware::new_with_arc(token.clone()))
+///     .with(BearerAuthMiddleware::new_with_arc(token))
+///     .build();
+/// ```
+///
+/// [`BearerAuthMiddleware`]: crate::BearerAuthMiddleware
+pub struct AuthRetryMiddleware {
+    provider: Arc<dyn TokenProvider>,
+    rejected: Box<RejectedFn>,
+}
+
+impl AuthRetryMiddleware {
+    /// Construct `AuthRetryMiddleware` invalidating the tokens of `provider`.
+    pub fn new<P: TokenProvider>(provider: P) -> Self {
+        Self::new_with_arc(Arc::new(provider))
+    }
+
+    /// Construct `AuthRetryMiddleware` invalidating the tokens of a shared `provider`.
+    pub fn new_with_arc(provider: Arc<dyn TokenProvider>) -> Self {
+        Self {
+            provider,
+            rejected: Box::new(|res| res.status() == StatusCode::UNAUTHORIZED),
+        }
+    }
+
+    /// Consider the responses matching `rejected` as rejections of the credentials, instead of
+    /// `401 Unauthorized` ones.
+    pub fn with_rejected<F>(mut self, rejected: F) -> Self
+    where
+        F: Fn(&Response) -> bool + Send + Sync + 'static,
+    {
+        self.rejected = Box::new(rejected);
+        self
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for AuthRetryMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if req.headers().contains_key(AUTHORIZATION)
+            || extensions.get::<DisableAuthRetry>().is_some()
+            || extensions.get::<AuthRetried>().is_some()
+        {
+            return next.run(req, extensions).await;
+        }
+
+        let retry_req = req.try_clone();
+        let res = next.clone().run(req, extensions).await?;
+        let retry_req = match retry_req {
+            Some(retry_req) if (self.rejected)(&res) => retry_req,
+            _ => return Ok(res),
+        };
+        tracing::debug!(
+            "Credentials rejected with status {}, replaying request with fresh ones",
+            res.status()
+        );
+        self.provider.invalidate();
+        extensions.insert(AuthRetried);
+        ResendCount::increment(extensions);
+        next.run(retry_req, extensions).await
+    }
+}
@@ -0,0 +1,63 @@
+//! Parsing of `WWW-Authenticate` challenges.
+use std::collections::HashMap;
+
+use http::header::WWW_AUTHENTICATE;
+use http::HeaderMap;
+
+/// The parameters of the `WWW-Authenticate` challenges of `headers` using `scheme`, compared
+/// case-insensitively.
+pub(crate) fn challenges<'a>(
+    headers: &'a HeaderMap,
+    scheme: &'a str,
+) -> impl Iterator<Item = HashMap<String, String>> + 'a {
+    headers
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(move |value| {
+            let (name, params) = value.trim().split_once(' ')?;
+            if !name.eq_ignore_ascii_case(scheme) {
+                return None;
+            }
+            Some(auth_params(params))
+        })
+}
+
+/// Parses comma separated `key=value` pairs, where values may be quoted strings.
+pub(crate) fn auth_params(params: &str) -> HashMap<String, String> {
+    let mut parsed = HashMap::new();
+    let mut rest = params.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_ascii_lowercase();
+        let after = after.trim_start();
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after.find(',').unwrap_or(after.len());
+                (after[..end].trim().to_owned(), &after[end..])
+            }
+        };
+        parsed.insert(key, value);
+        rest = after.trim_start().trim_start_matches(',');
+    }
+    parsed
+}
@@ -0,0 +1,243 @@
+//! Middleware adding and verifying `Content-Digest` headers.
+use std::fmt;
+
+use base64::Engine;
+use bytes::Bytes;
+use http::header::CONTENT_ENCODING;
+use http::{Extensions, HeaderMap, HeaderName, HeaderValue};
+use http_body_util::BodyExt;
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use ring::digest;
+use thiserror::Error;
+
+const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+const REPR_DIGEST: HeaderName = HeaderName::from_static("repr-digest");
+
+/// A hash algorithm of [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530) digest fields.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DigestAlgorithm {
+    /// SHA-256, `sha-256`.
+    #[default]
+    Sha256,
+    /// SHA-512, `sha-512`.
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// The name of the algorithm in digest fields.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha-256",
+            Self::Sha512 => "sha-512",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha-256" => Some(Self::Sha256),
+            "sha-512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> digest::Digest {
+        let algorithm = match self {
+            Self::Sha256 => &digest::SHA256,
+            Self::Sha512 => &digest::SHA512,
+        };
+        digest::digest(algorithm, data)
+    }
+
+    /// The field value for `data`, e.g. `sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:`.
+    fn field_value(&self, data: &[u8]) -> String {
+        format!(
+            "{}=:{}:",
+            self.name(),
+            base64::engine::general_purpose::STANDARD.encode(self.digest(data))
+        )
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned by [`VerifyDigestMiddleware`] when a response body doesn't match its digest.
+#[derive(Debug, Error)]
+#[error("The body of the response from {url} doesn't match its {field} {algorithm} digest")]
+pub struct DigestMismatch {
+    /// The URL of the response.
+    pub url: Url,
+    /// The digest field that didn't match, `content-digest` or `repr-digest`.
+    pub field: HeaderName,
+    /// The algorithm of the digest that didn't match.
+    pub algorithm: DigestAlgorithm,
+}
+
+/// Error returned by [`VerifyDigestMiddleware`] when a digest is
+/// [required](VerifyDigestMiddleware::require_digest) but the response has none it supports.
+#[derive(Debug, Error)]
+#[error("The response from {url} has no supported digest")]
+pub struct MissingDigest {
+    /// The URL of the response.
+    pub url: Url,
+}
+
+/// `ContentDigestMiddleware` adds a `Content-Digest` header to requests with a body, so that
+/// servers can check the body wasn't altered on the way.
+///
+/// Streaming bodies are read into memory to be hashed before the request is sent. Requests
+/// which already have a `Content-Digest` header are left untouched.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{ContentDigestMiddleware, DigestAlgorithm, VerifyDigestMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(ContentDigestMiddleware::new(DigestAlgorithm::Sha512))
+///     .with(VerifyDigestMiddleware::new())
+///     .build();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ContentDigestMiddleware {
+    algorithm: DigestAlgorithm,
+}
+
+impl ContentDigestMiddleware {
+    /// Construct `ContentDigestMiddleware` hashing bodies with `algorithm`.
+    pub fn new(algorithm: DigestAlgorithm) -> Self {
+        Self { algorithm }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ContentDigestMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !req.headers().contains_key(CONTENT_DIGEST) {
+            if let Some(body) = req.body_mut().take() {
+                let body = match body.as_bytes() {
+                    Some(bytes) => Bytes::copy_from_slice(bytes),
+                    None => body.collect().await?.to_bytes(),
+                };
+                let value = HeaderValue::from_str(&self.algorithm.field_value(&body))
+                    .expect("digest field values are valid headers");
+                req.headers_mut().insert(CONTENT_DIGEST, value);
+                *req.body_mut() = Some(body.into());
+            }
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// `VerifyDigestMiddleware` checks the body of responses against their `Content-Digest` and
+/// `Repr-Digest` headers, failing with [`DigestMismatch`] when they differ.
+///
+/// Responses with a digest are read in full before being returned. Digests using algorithms
+/// other than SHA-256 and SHA-512 are ignored, as are responses without digest unless
+/// [`require_digest`](Self::require_digest) is set.
+///
+/// `Repr-Digest` covers the body before any content coding, so it is only checked for responses
+/// without a `Content-Encoding`. Conversely, reqwest's automatic decompression changes the body
+/// that `Content-Digest` covers, so servers should send `Repr-Digest` for compressed responses.
+#[derive(Clone, Debug, Default)]
+pub struct VerifyDigestMiddleware {
+    required: bool,
+}
+
+impl VerifyDigestMiddleware {
+    /// Construct `VerifyDigestMiddleware`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail with [`MissingDigest`] for responses without a supported digest.
+    pub fn require_digest(mut self) -> Self {
+        self.required = true;
+        self
+    }
+}
+
+/// The supported digests in the `field` header of `headers`.
+fn digests(headers: &HeaderMap, field: &HeaderName) -> Vec<(DigestAlgorithm, Vec<u8>)> {
+    headers
+        .get_all(field)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|member| {
+            let (name, value) = member.split_once('=')?;
+            let algorithm = DigestAlgorithm::from_name(name.trim())?;
+            let value = value.trim().strip_prefix(':')?.strip_suffix(':')?;
+            let digest = base64::engine::general_purpose::STANDARD
+                .decode(value)
+                .ok()?;
+            Some((algorithm, digest))
+        })
+        .collect()
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for VerifyDigestMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let res = next.run(req, extensions).await?;
+
+        let mut fields = vec![CONTENT_DIGEST];
+        if !res.headers().contains_key(CONTENT_ENCODING) {
+            fields.push(REPR_DIGEST);
+        }
+        let expected: Vec<_> = fields
+            .into_iter()
+            .flat_map(|field| {
+                digests(res.headers(), &field)
+                    .into_iter()
+                    .map(move |(algorithm, digest)| (field.clone(), algorithm, digest))
+            })
+            .collect();
+        if expected.is_empty() {
+            if self.required {
+                return Err(Error::middleware(MissingDigest {
+                    url: res.url().clone(),
+                }));
+            }
+            return Ok(res);
+        }
+
+        let url = res.url().clone();
+        let (parts, body) = http::Response::from(res).into_parts();
+        let body = body.collect().await?.to_bytes();
+        for (field, algorithm, digest) in expected {
+            if algorithm.digest(&body).as_ref() != digest.as_slice() {
+                return Err(Error::middleware(DigestMismatch {
+                    url,
+                    field,
+                    algorithm,
+                }));
+            }
+        }
+
+        let mut res = http::Response::builder()
+            .url(url)
+            .body(body)
+            .expect("response is valid");
+        *res.status_mut() = parts.status;
+        *res.version_mut() = parts.version;
+        *res.headers_mut() = parts.headers;
+        res.extensions_mut().extend(parts.extensions);
+        Ok(Response::from(res))
+    }
+}
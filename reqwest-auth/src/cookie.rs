@@ -0,0 +1,133 @@
+//! `CookieMiddleware` sends and stores cookies.
+use std::sync::Arc;
+
+use http::header::{COOKIE, SET_COOKIE};
+use http::{Extensions, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+use crate::cookie_store::CookieStore;
+use crate::jar::Cookie;
+
+/// `CookieMiddleware` stores the cookies set by responses in a [`CookieStore`] and sends them
+/// back with the following requests they match.
+///
+/// Unlike reqwest's own cookie support, the store is pluggable: cookies can be kept in memory
+/// with [`MemoryCookieStore`], persisted with [`FileCookieStore`] or stored anywhere else. A
+/// single request can use a different store through the [`CookieStoreOverride`] extension.
+///
+/// Cookies already present in the `Cookie` header of a request are kept, the stored cookies are
+/// appended to them. Redirects followed by reqwest itself don't go through the middleware, so
+/// cookies set by intermediate responses are only stored when redirects are disabled with
+/// [`reqwest::redirect::Policy::none`].
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{CookieMiddleware, MemoryCookieStore};
+///
+/// let cookies = MemoryCookieStore::new();
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(CookieMiddleware::new(cookies.clone()))
+///     .build();
+/// ```
+///
+/// [`MemoryCookieStore`]: crate::MemoryCookieStore
+/// [`FileCookieStore`]: crate::FileCookieStore
+pub struct CookieMiddleware {
+    store: Arc<dyn CookieStore>,
+}
+
+impl CookieMiddleware {
+    /// Construct `CookieMiddleware` keeping cookies in `store`.
+    pub fn new<S: CookieStore>(store: S) -> Self {
+        Self::new_with_arc(Arc::new(store))
+    }
+
+    /// Construct `CookieMiddleware` keeping cookies in a shared `store`.
+    pub fn new_with_arc(store: Arc<dyn CookieStore>) -> Self {
+        Self { store }
+    }
+}
+
+/// Request extension making [`CookieMiddleware`] use another [`CookieStore`] for a single
+/// request, e.g. to act on behalf of a different user.
+///
+/// ```no_run
+/// # use reqwest_middleware::{ClientWithMiddleware, Result};
+/// use reqwest_auth::{CookieStoreOverride, MemoryCookieStore};
+///
+/// # async fn example(client: ClientWithMiddleware) -> Result<()> {
+/// let alice = MemoryCookieStore::new();
+/// client
+///     .get("https://example.com/inbox")
+///     .with_extension(CookieStoreOverride::new(alice.clone()))
+///     .send()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CookieStoreOverride(pub Arc<dyn CookieStore>);
+
+impl CookieStoreOverride {
+    /// Use `store` for the request.
+    pub fn new<S: CookieStore>(store: S) -> Self {
+        Self(Arc::new(store))
+    }
+}
+
+impl std::fmt::Debug for CookieStoreOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CookieStoreOverride").finish()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for CookieMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let store = match extensions.get::<CookieStoreOverride>() {
+            Some(store) => store.0.clone(),
+            None => self.store.clone(),
+        };
+
+        match store.cookies(req.url()).await {
+            Ok(cookies) if !cookies.is_empty() => {
+                let mut header = cookies
+                    .iter()
+                    .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                if let Some(existing) = req.headers().get(COOKIE).and_then(|v| v.to_str().ok()) {
+                    header = format!("{}; {}", existing, header);
+                }
+                if let Ok(value) = HeaderValue::from_str(&header) {
+                    req.headers_mut().insert(COOKIE, value);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to read cookies: {:#}", e),
+        }
+
+        let res = next.run(req, extensions).await?;
+
+        let cookies: Vec<_> = res
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| Cookie::parse(value, res.url()))
+            .collect();
+        if !cookies.is_empty() {
+            if let Err(e) = store.set_cookies(cookies).await {
+                tracing::warn!("Failed to store cookies: {:#}", e);
+            }
+        }
+        Ok(res)
+    }
+}
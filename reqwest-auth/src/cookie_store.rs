@@ -0,0 +1,152 @@
+//! The storage backend of [`CookieMiddleware`](crate::CookieMiddleware).
+use std::sync::{Arc, Mutex};
+
+use reqwest::Url;
+
+use crate::jar::{Cookie, CookieJar};
+
+/// Storage for the cookies of a [`CookieMiddleware`](crate::CookieMiddleware).
+///
+/// [`MemoryCookieStore`] and [`FileCookieStore`] are provided; other backends, e.g. a database
+/// shared between workers, can be plugged in by implementing this trait, typically on top of a
+/// [`CookieJar`]. Errors returned by a store are logged and otherwise ignored, so a failing store
+/// never fails a request.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait CookieStore: 'static + Send + Sync {
+    /// Returns the unexpired cookies to send with a request to `url`, in the order they should
+    /// appear in the `Cookie` header.
+    async fn cookies(&self, url: &Url) -> anyhow::Result<Vec<Cookie>>;
+
+    /// Stores cookies set by a response. An expired cookie deletes the stored cookie with the
+    /// same name, domain and path.
+    async fn set_cookies(&self, cookies: Vec<Cookie>) -> anyhow::Result<()>;
+}
+
+/// A [`CookieStore`] keeping cookies in memory.
+///
+/// Clones share the same cookies, so a clone can be kept around to inspect or edit the jar of a
+/// client.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryCookieStore {
+    jar: Arc<Mutex<CookieJar>>,
+}
+
+impl MemoryCookieStore {
+    /// Construct an empty `MemoryCookieStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct a `MemoryCookieStore` holding the cookies of `jar`.
+    pub fn from_jar(jar: CookieJar) -> Self {
+        Self {
+            jar: Arc::new(Mutex::new(jar)),
+        }
+    }
+
+    /// A copy of the cookies currently in the store.
+    pub fn jar(&self) -> CookieJar {
+        self.jar.lock().expect("cookie jar lock poisoned").clone()
+    }
+
+    /// Adds a cookie to the store.
+    pub fn insert(&self, cookie: Cookie) {
+        self.jar
+            .lock()
+            .expect("cookie jar lock poisoned")
+            .insert(cookie);
+    }
+
+    /// Removes all cookies from the store.
+    pub fn clear(&self) {
+        self.jar.lock().expect("cookie jar lock poisoned").clear();
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl CookieStore for MemoryCookieStore {
+    async fn cookies(&self, url: &Url) -> anyhow::Result<Vec<Cookie>> {
+        Ok(self
+            .jar
+            .lock()
+            .expect("cookie jar lock poisoned")
+            .matching(url))
+    }
+
+    async fn set_cookies(&self, cookies: Vec<Cookie>) -> anyhow::Result<()> {
+        self.jar
+            .lock()
+            .expect("cookie jar lock poisoned")
+            .extend(cookies);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::FileCookieStore;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file {
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    use futures::lock::Mutex;
+    use reqwest::Url;
+
+    use super::CookieStore;
+    use crate::jar::{Cookie, CookieJar};
+
+    /// A [`CookieStore`] persisting cookies to a JSON file, so that logins survive restarts.
+    ///
+    /// The file holds a serialized [`CookieJar`] and is rewritten atomically whenever a response
+    /// sets cookies. Session cookies are persisted too, as a restarted process is usually meant to
+    /// resume the same session.
+    #[derive(Debug)]
+    pub struct FileCookieStore {
+        path: PathBuf,
+        jar: Mutex<CookieJar>,
+    }
+
+    impl FileCookieStore {
+        /// Construct a `FileCookieStore` backed by the file at `path`, loading the cookies it
+        /// already holds. The file is created on the first write if it doesn't exist.
+        pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+            let path = path.into();
+            let jar = match std::fs::read(&path) {
+                Ok(json) => serde_json::from_slice(&json)
+                    .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?,
+                Err(e) if e.kind() == ErrorKind::NotFound => CookieJar::new(),
+                Err(e) => return Err(e),
+            };
+            Ok(Self {
+                path,
+                jar: Mutex::new(jar),
+            })
+        }
+
+        /// A copy of the cookies currently in the store.
+        pub async fn jar(&self) -> CookieJar {
+            self.jar.lock().await.clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CookieStore for FileCookieStore {
+        async fn cookies(&self, url: &Url) -> anyhow::Result<Vec<Cookie>> {
+            Ok(self.jar.lock().await.matching(url))
+        }
+
+        async fn set_cookies(&self, cookies: Vec<Cookie>) -> anyhow::Result<()> {
+            // Hold the lock while writing so that concurrent writes can't land out of order.
+            let mut jar = self.jar.lock().await;
+            jar.extend(cookies);
+            let json = serde_json::to_vec(&*jar)?;
+            let tmp = self.path.with_extension("tmp");
+            tokio::fs::write(&tmp, json).await?;
+            tokio::fs::rename(&tmp, &self.path).await?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! `CsrfMiddleware` obtains anti-forgery tokens and adds them to requests.
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use http::header::{CONTENT_TYPE, SET_COOKIE};
+use http::{Extensions, HeaderName, HeaderValue, Method, StatusCode};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+
+use crate::jar::Cookie;
+
+type BodyExtractor = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Where [`CsrfMiddleware`] finds the CSRF token in responses.
+#[derive(Clone)]
+pub enum CsrfSource {
+    /// A response header, e.g. `X-CSRF-Token`.
+    Header(HeaderName),
+    /// A cookie set by the server, e.g. `XSRF-TOKEN`.
+    Cookie(String),
+    /// The body of the response to the token URL, e.g. a `<meta>` tag of an HTML page. Only
+    /// used for the responses fetched from the [token URL](CsrfMiddleware::with_token_url).
+    Body(BodyExtractor),
+}
+
+impl CsrfSource {
+    /// Extract the token from the body of the token URL's response with `extract`.
+    pub fn body<F>(extract: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        Self::Body(Arc::new(extract))
+    }
+
+    /// Finds the token in the headers of `res`.
+    fn find_in(&self, res: &Response) -> Option<String> {
+        match self {
+            Self::Header(name) => res
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+            Self::Cookie(name) => res
+                .headers()
+                .get_all(SET_COOKIE)
+                .iter()
+                .filter_map(|value| Cookie::parse(value.to_str().ok()?, res.url()))
+                .find(|cookie| &cookie.name == name && !cookie.value.is_empty())
+                .map(|cookie| cookie.value),
+            Self::Body(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for CsrfSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            Self::Cookie(name) => f.debug_tuple("Cookie").field(name).finish(),
+            Self::Body(_) => f.debug_tuple("Body").finish(),
+        }
+    }
+}
+
+/// Where [`CsrfMiddleware`] puts the CSRF token in requests.
+#[derive(Clone, Debug)]
+pub enum CsrfTarget {
+    /// A request header.
+    Header(HeaderName),
+    /// A field appended to `application/x-www-form-urlencoded` bodies. Requests with other
+    /// bodies are left untouched.
+    FormField(String),
+}
+
+/// `CsrfMiddleware` adds a CSRF (or XSRF) token to state-changing requests, for APIs which
+/// require one obtained from a prior response.
+///
+/// The token is taken from any response going through the middleware according to its
+/// [`CsrfSource`], and cached. When a request needs a token and none is known yet, it is fetched
+/// with a `GET` request to the [token URL](Self::with_token_url), if any, sent through the rest of
+/// the middleware chain. The token is then added to the request as configured with
+/// [`with_target`](Self::with_target), by default in the `X-CSRF-Token` header.
+///
+/// When the server rejects a request with one of the
+/// [expired statuses](Self::with_expired_statuses), 403 and 419 by default, the token is
+/// refreshed and the request is sent once more, provided its body can be cloned.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{CookieMiddleware, CsrfMiddleware, CsrfSource, CsrfTarget, MemoryCookieStore};
+///
+/// let csrf = CsrfMiddleware::new(CsrfSource::Cookie("XSRF-TOKEN".into()))
+///     .with_token_url("https://example.com/".parse().unwrap())
+///     .with_target(CsrfTarget::Header(http::HeaderName::from_static("x-xsrf-token")));
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(csrf)
+///     .with(CookieMiddleware::new(MemoryCookieStore::new()))
+///     .build();
+/// ```
+#[derive(Debug)]
+pub struct CsrfMiddleware {
+    source: CsrfSource,
+    target: CsrfTarget,
+    token_url: Option<Url>,
+    methods: Vec<Method>,
+    expired_statuses: Vec<StatusCode>,
+    token: Mutex<Option<String>>,
+}
+
+impl CsrfMiddleware {
+    /// Construct `CsrfMiddleware` taking tokens from `source`.
+    pub fn new(source: CsrfSource) -> Self {
+        Self {
+            source,
+            target: CsrfTarget::Header(HeaderName::from_static("x-csrf-token")),
+            token_url: None,
+            methods: vec![Method::POST, Method::PUT, Method::PATCH, Method::DELETE],
+            expired_statuses: vec![StatusCode::FORBIDDEN, StatusCode::from_u16(419).unwrap()],
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Set where the token is added to requests.
+    pub fn with_target(mut self, target: CsrfTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the URL to fetch a token from when none is known.
+    pub fn with_token_url(mut self, url: Url) -> Self {
+        self.token_url = Some(url);
+        self
+    }
+
+    /// Set the request methods which need a token, by default `POST`, `PUT`, `PATCH` and
+    /// `DELETE`.
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Set the response statuses meaning that the token has expired.
+    pub fn with_expired_statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.expired_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Forgets the current token, so that the next request needing one fetches a new one.
+    pub fn clear(&self) {
+        *self.token.lock().expect("csrf token lock poisoned") = None;
+    }
+
+    fn cached(&self) -> Option<String> {
+        self.token.lock().expect("csrf token lock poisoned").clone()
+    }
+
+    fn observe(&self, res: &Response) {
+        if let Some(token) = self.source.find_in(res) {
+            *self.token.lock().expect("csrf token lock poisoned") = Some(token);
+        }
+    }
+
+    /// Fetches a token from the token URL, through the rest of the middleware chain.
+    async fn fetch(&self, extensions: &Extensions, next: Next<'_>) -> Result<Option<String>> {
+        let url = match &self.token_url {
+            Some(url) => url.clone(),
+            None => return Ok(None),
+        };
+        tracing::debug!("Fetching CSRF token from {}", url);
+        let res = next
+            .run(Request::new(Method::GET, url), &mut extensions.clone())
+            .await?;
+        let token = match &self.source {
+            CsrfSource::Body(extract) => extract(&res.text().await?),
+            source => source.find_in(&res),
+        };
+        if token.is_some() {
+            *self.token.lock().expect("csrf token lock poisoned") = token.clone();
+        }
+        Ok(token)
+    }
+
+    fn inject(&self, req: &mut Request, token: &str) -> Result<()> {
+        match &self.target {
+            CsrfTarget::Header(name) => {
+                let value = HeaderValue::from_str(token).map_err(Error::middleware)?;
+                req.headers_mut().insert(name.clone(), value);
+            }
+            CsrfTarget::FormField(field) => {
+                let is_form = req
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+                let body = req.body().and_then(|body| body.as_bytes());
+                if let (true, Some(body)) = (is_form, body) {
+                    let mut body = body.to_vec();
+                    if !body.is_empty() {
+                        body.push(b'&');
+                    }
+                    let field = form_urlencoded::Serializer::new(String::new())
+                        .append_pair(field, token)
+                        .finish();
+                    body.extend_from_slice(field.as_bytes());
+                    *req.body_mut() = Some(body.into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for CsrfMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !self.methods.contains(req.method()) {
+            let res = next.run(req, extensions).await?;
+            self.observe(&res);
+            return Ok(res);
+        }
+
+        // Keep a copy of the request without token to retry with a fresh one.
+        let retry_req = req.try_clone();
+        let token = match self.cached() {
+            Some(token) => Some(token),
+            None => self.fetch(extensions, next.clone()).await?,
+        };
+        if let Some(token) = &token {
+            self.inject(&mut req, token)?;
+        }
+        let res = next.clone().run(req, extensions).await?;
+        self.observe(&res);
+
+        let mut retry_req = match retry_req {
+            Some(retry_req) if self.expired_statuses.contains(&res.status()) => retry_req,
+            _ => return Ok(res),
+        };
+        // The rejection may have come with a new token, otherwise fetch one.
+        let fresh = match self.cached() {
+            Some(fresh) if Some(&fresh) != token.as_ref() => Some(fresh),
+            _ => {
+                self.clear();
+                self.fetch(extensions, next.clone()).await?
+            }
+        };
+        match fresh {
+            Some(fresh) if Some(&fresh) != token.as_ref() => {
+                tracing::debug!("CSRF token rejected with {}, retrying", res.status());
+                self.inject(&mut retry_req, &fresh)?;
+                let res = next.run(retry_req, extensions).await?;
+                self.observe(&res);
+                Ok(res)
+            }
+            _ => Ok(res),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! `DigestAuthMiddleware` answers HTTP Digest authentication challenges.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use http::header::AUTHORIZATION;
+use http::{Extensions, HeaderMap, HeaderValue, Method, StatusCode};
+use md5::{Digest, Md5};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::challenge::{auth_params, challenges};
+use crate::hex;
+use crate::secret::Secret;
+
+const AUTHENTICATION_INFO: &str = "authentication-info";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "MD5" => Some(Self::Md5),
+            "MD5-SESS" => Some(Self::Md5Sess),
+            "SHA-256" => Some(Self::Sha256),
+            "SHA-256-SESS" => Some(Self::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Md5Sess => "MD5-sess",
+            Self::Sha256 => "SHA-256",
+            Self::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn hash(&self, data: &str) -> String {
+        match self {
+            Self::Md5 | Self::Md5Sess => hex(&Md5::digest(data.as_bytes())),
+            Self::Sha256 | Self::Sha256Sess => {
+                hex(ring::digest::digest(&ring::digest::SHA256, data.as_bytes()).as_ref())
+            }
+        }
+    }
+
+    fn is_session(&self) -> bool {
+        matches!(self, Self::Md5Sess | Self::Sha256Sess)
+    }
+}
+
+/// A challenge of a `WWW-Authenticate: Digest` header, along with the number of requests made
+/// with its nonce.
+#[derive(Clone, Debug)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    qop: bool,
+    stale: bool,
+    nonce_count: u32,
+}
+
+impl Challenge {
+    /// Parses the supported digest challenges of `headers`, preferring SHA-256 over MD5.
+    fn parse(headers: &HeaderMap) -> Option<Self> {
+        challenges(headers, "digest")
+            .filter_map(|params| {
+                let qop = match params.get("qop") {
+                    Some(qop) => {
+                        // Only `auth` is supported, not `auth-int`.
+                        if !qop.split(',').any(|qop| qop.trim() == "auth") {
+                            return None;
+                        }
+                        true
+                    }
+                    None => false,
+                };
+                Some(Self {
+                    realm: params.get("realm")?.clone(),
+                    nonce: params.get("nonce")?.clone(),
+                    opaque: params.get("opaque").cloned(),
+                    algorithm: match params.get("algorithm") {
+                        Some(algorithm) => Algorithm::parse(algorithm)?,
+                        None => Algorithm::Md5,
+                    },
+                    qop,
+                    stale: params
+                        .get("stale")
+                        .is_some_and(|stale| stale.eq_ignore_ascii_case("true")),
+                    nonce_count: 0,
+                })
+            })
+            .max_by_key(|challenge| {
+                !matches!(challenge.algorithm, Algorithm::Md5 | Algorithm::Md5Sess)
+            })
+    }
+
+    /// Builds the `Authorization` header of a request, counting one more use of the nonce.
+    fn authorize(
+        &mut self,
+        method: &Method,
+        url: &Url,
+        username: &str,
+        password: &str,
+    ) -> Result<HeaderValue> {
+        self.nonce_count += 1;
+        let mut cnonce = [0; 16];
+        SystemRandom::new()
+            .fill(&mut cnonce)
+            .map_err(|_| anyhow::anyhow!("Failed to generate a client nonce"))?;
+        let cnonce = hex(&cnonce);
+        let nc = format!("{:08x}", self.nonce_count);
+        let uri = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_owned(),
+        };
+
+        let h = |data: &str| self.algorithm.hash(data);
+        let mut ha1 = h(&format!("{}:{}:{}", username, self.realm, password));
+        if self.algorithm.is_session() {
+            ha1 = h(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+        let ha2 = h(&format!("{}:{}", method, uri));
+        let response = if self.qop {
+            h(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, cnonce, ha2
+            ))
+        } else {
+            h(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+
+        let mut header = format!(
+            r#"Digest username="{}", realm="{}", nonce="{}", uri="{}", algorithm={}, response="{}""#,
+            quote(username),
+            quote(&self.realm),
+            quote(&self.nonce),
+            quote(&uri),
+            self.algorithm.name(),
+            response
+        );
+        if self.qop {
+            header.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce));
+        }
+        if let Some(opaque) = &self.opaque {
+            header.push_str(&format!(", opaque=\"{}\"", quote(opaque)));
+        }
+        let mut header = HeaderValue::from_str(&header).map_err(Error::middleware)?;
+        header.set_sensitive(true);
+        Ok(header)
+    }
+}
+
+/// Escapes a value for a quoted string.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// `DigestAuthMiddleware` authenticates requests with [HTTP Digest
+/// authentication](https://datatracker.ietf.org/doc/html/rfc7616), which reqwest doesn't
+/// support out of the box.
+///
+/// When a server answers `401 Unauthorized` with a `Digest` challenge, the request is sent again
+/// with an `Authorization` header answering it, provided its body can be cloned. The challenge
+/// is then remembered per origin, so the following requests are authorized upfront with an
+/// increasing nonce count. A challenge marked `stale`, meaning that only the nonce expired, is
+/// answered again; other rejections of the credentials are returned as is.
+///
+/// The `MD5`, `MD5-sess`, `SHA-256` and `SHA-256-sess` algorithms are supported with the `auth`
+/// quality of protection, SHA-256 being preferred when the server offers both.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::DigestAuthMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(DigestAuthMiddleware::new("Mufasa", "Circle of Life"))
+///     .build();
+/// ```
+#[derive(Debug)]
+pub struct DigestAuthMiddleware {
+    username: String,
+    password: Secret<String>,
+    challenges: Mutex<HashMap<String, Challenge>>,
+}
+
+impl DigestAuthMiddleware {
+    /// Construct `DigestAuthMiddleware` authenticating as `username`.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: Secret::new(password.into()),
+            challenges: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Authorizes `req` with the remembered challenge of `origin`, if any.
+    fn authorize(&self, origin: &str, req: &mut Request) -> Result<bool> {
+        let mut challenges = self.challenges.lock().expect("challenges lock poisoned");
+        let challenge = match challenges.get_mut(origin) {
+            Some(challenge) => challenge,
+            None => return Ok(false),
+        };
+        let header = challenge.authorize(
+            req.method(),
+            req.url(),
+            &self.username,
+            self.password.expose_secret(),
+        )?;
+        req.headers_mut().insert(AUTHORIZATION, header);
+        Ok(true)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for DigestAuthMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if req.headers().contains_key(AUTHORIZATION) {
+            return next.run(req, extensions).await;
+        }
+
+        let origin = req.url().origin().ascii_serialization();
+        let retry_req = req.try_clone();
+        let authorized = self.authorize(&origin, &mut req)?;
+        let res = next.clone().run(req, extensions).await?;
+
+        if res.status() != StatusCode::UNAUTHORIZED {
+            // The server may hand out the nonce to use for the next request.
+            let next_nonce = res
+                .headers()
+                .get(AUTHENTICATION_INFO)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| auth_params(value).remove("nextnonce"));
+            if let Some(next_nonce) = next_nonce {
+                let mut challenges = self.challenges.lock().expect("challenges lock poisoned");
+                if let Some(challenge) = challenges.get_mut(&origin) {
+                    challenge.nonce = next_nonce;
+                    challenge.nonce_count = 0;
+                }
+            }
+            return Ok(res);
+        }
+
+        let (challenge, mut retry_req) = match (Challenge::parse(res.headers()), retry_req) {
+            // Credentials which were already rejected are only retried for a stale nonce.
+            (Some(challenge), Some(retry_req)) if !authorized || challenge.stale => {
+                (challenge, retry_req)
+            }
+            _ => return Ok(res),
+        };
+        tracing::debug!("Answering digest challenge for realm {}", challenge.realm);
+        self.challenges
+            .lock()
+            .expect("challenges lock poisoned")
+            .insert(origin.clone(), challenge);
+        self.authorize(&origin, &mut retry_req)?;
+        next.run(retry_req, extensions).await
+    }
+}
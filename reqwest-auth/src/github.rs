@@ -0,0 +1,343 @@
+//! GitHub App authentication and handling of GitHub's rate limits.
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use http::header::{ACCEPT, RETRY_AFTER, USER_AGENT};
+use http::{Extensions, StatusCode};
+use reqwest::{Client, Request, Response, Url};
+use reqwest_middleware::{Middleware, Next, Result};
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::oauth2::TokenRequestFailed;
+use crate::secret::Secret;
+use crate::token::TokenProvider;
+
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_JSON: &str = "application/vnd.github+json";
+
+/// Error returned by [`GitHubAppToken::new`] for private keys which aren't PEM encoded RSA keys.
+#[derive(Debug, Error)]
+#[error("Invalid GitHub App private key: {0}")]
+pub struct InvalidPrivateKey(String);
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iat: u64,
+    exp: u64,
+    iss: &'a str,
+}
+
+#[derive(Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: String,
+}
+
+/// A [`TokenProvider`] authenticating as a [GitHub
+/// App](https://docs.github.com/en/apps/creating-github-apps/authenticating-with-a-github-app/about-authentication-with-a-github-app)
+/// installation, for use with [`BearerAuthMiddleware`](crate::BearerAuthMiddleware).
+///
+/// A JSON Web Token signed with the app's private key is exchanged for an installation access
+/// token, which is cached until shortly before it expires, see [`with_refresh_margin`].
+/// Concurrent requests needing a new token wait for a single exchange.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{BearerAuthMiddleware, GitHubAppToken, GitHubRateLimitMiddleware};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let private_key = std::fs::read_to_string("my-app.private-key.pem")?;
+/// let token = GitHubAppToken::new(reqwest::Client::new(), "Iv1.8a61f9b3a7aba766", 42, &private_key)?;
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(GitHubRateLimitMiddleware::new())
+///     .with(BearerAuthMiddleware::new(token))
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`with_refresh_margin`]: Self::with_refresh_margin
+pub struct GitHubAppToken {
+    client: Client,
+    app_id: String,
+    installation_id: u64,
+    key: RsaKeyPair,
+    api_url: String,
+    refresh_margin: Duration,
+    token: Mutex<Option<(Secret<String>, Instant)>>,
+    // Held while exchanging the JWT, so that concurrent requests share the exchange.
+    refreshing: futures::lock::Mutex<()>,
+}
+
+impl GitHubAppToken {
+    /// Construct `GitHubAppToken` for the installation `installation_id` of the app `app_id`,
+    /// its client ID or numeric app ID. `private_key` is the PEM encoded key generated for the
+    /// app, and `client` is used to call the GitHub API.
+    pub fn new(
+        client: Client,
+        app_id: impl Into<String>,
+        installation_id: u64,
+        private_key: &str,
+    ) -> std::result::Result<Self, InvalidPrivateKey> {
+        Ok(Self {
+            client,
+            app_id: app_id.into(),
+            installation_id,
+            key: parse_private_key(private_key)?,
+            api_url: GITHUB_API.to_owned(),
+            refresh_margin: Duration::from_secs(5 * 60),
+            token: Mutex::new(None),
+            refreshing: futures::lock::Mutex::new(()),
+        })
+    }
+
+    /// Set the URL of the GitHub API, e.g. `https://github.example.com/api/v3` for GitHub
+    /// Enterprise Server. Defaults to `https://api.github.com`.
+    pub fn with_api_url(mut self, url: Url) -> Self {
+        self.api_url = url.as_str().trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Set how long before their expiry tokens are renewed, 5 minutes by default.
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Mints a JSON Web Token authenticating as the app itself as of `time`, for the endpoints
+    /// which don't accept installation tokens. It is valid for 10 minutes.
+    pub fn jwt(&self, time: SystemTime) -> anyhow::Result<Secret<String>> {
+        let now = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        // GitHub recommends backdating tokens to allow for clock drift.
+        let claims = Claims {
+            iat: now.saturating_sub(60),
+            exp: now + 9 * 60,
+            iss: &self.app_id,
+        };
+        let base64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let message = format!(
+            "{}.{}",
+            base64.encode(br#"{"alg":"RS256","typ":"JWT"}"#),
+            base64.encode(serde_json::to_vec(&claims)?)
+        );
+        let mut signature = vec![0; self.key.public().modulus_len()];
+        self.key
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to sign the GitHub App JWT"))?;
+        Ok(Secret::new(format!(
+            "{}.{}",
+            message,
+            base64.encode(signature)
+        )))
+    }
+
+    /// Returns the cached token if it isn't about to expire.
+    fn cached(&self) -> Option<Secret<String>> {
+        let token = self.token.lock().expect("token lock poisoned");
+        token
+            .as_ref()
+            .filter(|(_, expires_at)| Instant::now() + self.refresh_margin < *expires_at)
+            .map(|(token, _)| token.clone())
+    }
+
+    async fn request_token(&self) -> anyhow::Result<(Secret<String>, Instant)> {
+        let url: Url = format!(
+            "{}/app/installations/{}/access_tokens",
+            self.api_url, self.installation_id
+        )
+        .parse()?;
+        tracing::debug!("Requesting installation token from {}", url);
+        let jwt = self.jwt(SystemTime::now())?;
+        let requested_at = Instant::now();
+        let res = self
+            .client
+            .post(url.clone())
+            .bearer_auth(jwt.expose_secret())
+            .header(ACCEPT, GITHUB_JSON)
+            // The GitHub API rejects requests without user agent.
+            .header(USER_AGENT, "reqwest-auth")
+            .send()
+            .await?;
+        let status = res.status();
+        let body = res.bytes().await?;
+        let token: InstallationToken = match serde_json::from_slice(&body) {
+            Ok(token) if status.is_success() => token,
+            _ => {
+                return Err(TokenRequestFailed {
+                    url,
+                    status,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                }
+                .into())
+            }
+        };
+        // Installation tokens last an hour, should `expires_at` not be understood.
+        let lifetime = chrono::DateTime::parse_from_rfc3339(&token.expires_at)
+            .ok()
+            .and_then(|expires_at| {
+                (expires_at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                    .to_std()
+                    .ok()
+            })
+            .unwrap_or(Duration::from_secs(60 * 60));
+        Ok((Secret::new(token.token), requested_at + lifetime))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenProvider for GitHubAppToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        if let Some(token) = self.cached() {
+            return Ok(token);
+        }
+        let _refreshing = self.refreshing.lock().await;
+        // Another request may have renewed the token while we were waiting.
+        if let Some(token) = self.cached() {
+            return Ok(token);
+        }
+        let (token, expires_at) = self.request_token().await?;
+        *self.token.lock().expect("token lock poisoned") = Some((token.clone(), expires_at));
+        Ok(token)
+    }
+
+    fn invalidate(&self) {
+        *self.token.lock().expect("token lock poisoned") = None;
+    }
+}
+
+/// Parses a PEM encoded RSA private key, in PKCS#1 format as generated by GitHub or in PKCS#8
+/// format.
+fn parse_private_key(pem: &str) -> std::result::Result<RsaKeyPair, InvalidPrivateKey> {
+    let pem = pem.trim();
+    let (label, rest) = pem
+        .strip_prefix("-----BEGIN ")
+        .and_then(|rest| rest.split_once("-----"))
+        .ok_or_else(|| InvalidPrivateKey("not PEM encoded".to_owned()))?;
+    let body = rest
+        .trim_end()
+        .strip_suffix(&format!("-----END {}-----", label))
+        .ok_or_else(|| InvalidPrivateKey("not PEM encoded".to_owned()))?;
+    let der = base64::engine::general_purpose::STANDARD
+        .decode(body.split_whitespace().collect::<String>())
+        .map_err(|err| InvalidPrivateKey(err.to_string()))?;
+    match label {
+        "RSA PRIVATE KEY" => RsaKeyPair::from_der(&der),
+        "PRIVATE KEY" => RsaKeyPair::from_pkcs8(&der),
+        label => return Err(InvalidPrivateKey(format!("unexpected {} PEM block", label))),
+    }
+    .map_err(|err| InvalidPrivateKey(err.to_string()))
+}
+
+/// `GitHubRateLimitMiddleware` waits out GitHub's rate limits and sends the rejected request
+/// again, provided its body can be cloned.
+///
+/// Responses with a `403 Forbidden` or `429 Too Many Requests` status are retried after the
+/// delay given by their `Retry-After` header, as sent for [secondary rate
+/// limits](https://docs.github.com/en/rest/using-the-rest-api/rate-limits-for-the-rest-api#about-secondary-rate-limits),
+/// or after the `X-RateLimit-Reset` time once the primary rate limit is exhausted. Delays
+/// longer than [`with_max_wait`] are not waited for, the response is returned instead.
+///
+/// [`with_max_wait`]: Self::with_max_wait
+#[derive(Clone, Debug)]
+pub struct GitHubRateLimitMiddleware {
+    max_wait: Duration,
+    max_retries: u32,
+}
+
+impl Default for GitHubRateLimitMiddleware {
+    fn default() -> Self {
+        Self {
+            max_wait: Duration::from_secs(60),
+            max_retries: 3,
+        }
+    }
+}
+
+impl GitHubRateLimitMiddleware {
+    /// Construct `GitHubRateLimitMiddleware`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the longest delay waited for before retrying, 60 seconds by default.
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Set how many times a request is retried, 3 by default.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+/// How long to wait before retrying a rate limited response, if it is one.
+fn rate_limit_delay(res: &Response) -> Option<Duration> {
+    if !matches!(
+        res.status(),
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return None;
+    }
+    let header = |name: &str| {
+        res.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    if let Some(secs) = header(RETRY_AFTER.as_str()) {
+        return Some(Duration::from_secs(secs));
+    }
+    if header("x-ratelimit-remaining") == Some(0) {
+        let reset = UNIX_EPOCH.checked_add(Duration::from_secs(header("x-ratelimit-reset")?))?;
+        return Some(reset.duration_since(SystemTime::now()).unwrap_or_default());
+    }
+    None
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for GitHubRateLimitMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let mut retries = 0;
+        loop {
+            let retry_req = req.try_clone();
+            let res = next.clone().run(req, extensions).await?;
+            match (rate_limit_delay(&res), retry_req) {
+                (Some(delay), Some(retry_req))
+                    if delay <= self.max_wait && retries < self.max_retries =>
+                {
+                    tracing::debug!("Rate limited by GitHub, retrying in {:?}", delay);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    tokio::time::sleep(delay).await;
+                    #[cfg(target_arch = "wasm32")]
+                    wasm_timer::Delay::new(delay)
+                        .await
+                        .expect("failed sleeping");
+                    retries += 1;
+                    req = retry_req;
+                }
+                _ => return Ok(res),
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! `HawkMiddleware` authenticates requests with the Hawk scheme.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use http::{Extensions, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::signing::SigningKeys;
+
+/// `HawkMiddleware` authenticates requests with
+/// [Hawk](https://github.com/mozilla/hawk/blob/main/API.md), a MAC based scheme using a key
+/// shared with the server and the `sha256` algorithm.
+///
+/// Each request gets an `Authorization: Hawk` header with a fresh timestamp and nonce. The hash
+/// of buffered bodies is included so that the server can validate the payload; streaming
+/// bodies are sent without hash. Requests which already have an `Authorization` header are left
+/// untouched.
+///
+/// The key can be rotated through the [`SigningKeys`] handle, see
+/// [`HmacSigningMiddleware`](crate::HmacSigningMiddleware).
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{HawkMiddleware, SigningKey};
+///
+/// let key = SigningKey::new("dh37fgj492je", "werxhqb98rpaxn39848xrunpaw3489ruxnpa98w4rxn");
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(HawkMiddleware::new(key).with_ext("some-app-ext-data"))
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct HawkMiddleware {
+    keys: SigningKeys,
+    ext: Option<String>,
+}
+
+impl HawkMiddleware {
+    /// Construct `HawkMiddleware` authenticating with `keys`.
+    pub fn new(keys: impl Into<SigningKeys>) -> Self {
+        Self {
+            keys: keys.into(),
+            ext: None,
+        }
+    }
+
+    /// Set the application specific data sent in the `ext` attribute.
+    pub fn with_ext(mut self, ext: impl Into<String>) -> Self {
+        self.ext = Some(ext.into());
+        self
+    }
+
+    /// The keys requests are authenticated with, to rotate them.
+    pub fn keys(&self) -> SigningKeys {
+        self.keys.clone()
+    }
+
+    /// Adds the `Authorization` header to `req`, using the timestamp `time` and `nonce`.
+    pub fn sign(&self, req: &mut Request, time: SystemTime, nonce: &str) -> Result<()> {
+        let timestamp = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let url = req.url();
+        let resource = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_owned(),
+        };
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let port = url.port_or_known_default().unwrap_or_default();
+        let hash = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| payload_hash(req, body));
+        let ext = self.ext.as_deref().unwrap_or_default();
+
+        let normalized = format!(
+            "hawk.1.header\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+            timestamp,
+            nonce,
+            req.method(),
+            resource,
+            host,
+            port,
+            hash.as_deref().unwrap_or_default(),
+            escape(ext)
+        );
+        let key = self.keys.current();
+        let mac = base64::engine::general_purpose::STANDARD.encode(key.sign(normalized.as_bytes()));
+
+        let mut header = format!(
+            r#"Hawk id="{}", ts="{}", nonce="{}""#,
+            escape(key.id()),
+            timestamp,
+            escape(nonce)
+        );
+        if let Some(hash) = &hash {
+            header.push_str(&format!(r#", hash="{}""#, hash));
+        }
+        if self.ext.is_some() {
+            header.push_str(&format!(r#", ext="{}""#, escape(ext)));
+        }
+        header.push_str(&format!(r#", mac="{}""#, mac));
+        let mut header = HeaderValue::from_str(&header).map_err(Error::middleware)?;
+        header.set_sensitive(true);
+        req.headers_mut().insert(AUTHORIZATION, header);
+        Ok(())
+    }
+}
+
+/// The Hawk payload hash of `body`, covering its content type.
+fn payload_hash(req: &Request, body: &[u8]) -> String {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let mut payload = format!("hawk.1.payload\n{}\n", content_type).into_bytes();
+    payload.extend_from_slice(body);
+    payload.push(b'\n');
+    base64::engine::general_purpose::STANDARD.encode(digest::digest(&digest::SHA256, &payload))
+}
+
+/// Escapes backslashes and double quotes, as Hawk does for `ext` and header attributes.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for HawkMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !req.headers().contains_key(AUTHORIZATION) {
+            let mut nonce = [0; 6];
+            SystemRandom::new()
+                .fill(&mut nonce)
+                .map_err(|_| anyhow::anyhow!("Failed to generate a nonce"))?;
+            let nonce = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(nonce);
+            self.sign(&mut req, SystemTime::now(), &nonce)?;
+        }
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,230 @@
+//! Cookies and the [`CookieJar`] holding them.
+use std::cmp::Reverse;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// A cookie set by a server with a `Set-Cookie` header.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    /// The name of the cookie.
+    pub name: String,
+    /// The value of the cookie.
+    pub value: String,
+    /// The domain the cookie is sent to, lowercase and without a leading dot.
+    pub domain: String,
+    /// Whether the cookie is only sent to `domain` itself rather than to its subdomains too,
+    /// which is the case when the server didn't set a `Domain` attribute.
+    pub host_only: bool,
+    /// The path the cookie is sent to, along with the paths below it.
+    pub path: String,
+    /// Whether the cookie is only sent over HTTPS.
+    pub secure: bool,
+    /// Whether the cookie was marked `HttpOnly`. This has no effect outside of browsers but is
+    /// kept so that jars can be exported faithfully.
+    pub http_only: bool,
+    /// When the cookie expires, or `None` for a session cookie.
+    pub expires: Option<SystemTime>,
+}
+
+impl Cookie {
+    /// Parses the value of a `Set-Cookie` header received in response to a request to `url`,
+    /// following [RFC 6265](https://datatracker.ietf.org/doc/html/rfc6265#section-5.2).
+    ///
+    /// Returns `None` if the header is malformed, or if `url` isn't allowed to set the cookie:
+    /// the `Domain` attribute must match the host of `url` and can't be a public suffix, and
+    /// `Secure` cookies can only be set over HTTPS.
+    pub fn parse(set_cookie: &str, url: &Url) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut attributes = set_cookie.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_owned(),
+            value: value.trim().trim_matches('"').to_owned(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+        let mut max_age = None;
+        for attribute in attributes {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "expires" => cookie.expires = httpdate::parse_http_date(value).ok(),
+                "max-age" => max_age = value.parse::<i64>().ok().or(max_age),
+                "domain" => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    if domain.is_empty() {
+                        continue;
+                    }
+                    if !domain_matches(&host, &domain) {
+                        return None;
+                    }
+                    // Servers can't set cookies for a whole public suffix such as `co.uk`.
+                    if psl::suffix_str(&domain) == Some(domain.as_str()) {
+                        if domain != host {
+                            return None;
+                        }
+                        continue;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_owned(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+        if cookie.secure && url.scheme() != "https" {
+            return None;
+        }
+        // Max-Age takes precedence over Expires.
+        match max_age {
+            Some(secs) if secs <= 0 => cookie.expires = Some(SystemTime::UNIX_EPOCH),
+            Some(secs) => {
+                // Huge values overflow `SystemTime`, those cookies expire in 2106 instead.
+                let expires = SystemTime::now()
+                    .checked_add(Duration::from_secs(secs as u64))
+                    .unwrap_or(SystemTime::UNIX_EPOCH + Duration::from_secs(u32::MAX as u64));
+                cookie.expires = Some(expires);
+            }
+            None => {}
+        }
+        Some(cookie)
+    }
+
+    /// Returns true if the cookie should be sent with a request to `url`.
+    pub fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+        domain_ok
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+    }
+
+    /// Returns true if the cookie has expired at `now`. Session cookies never expire.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+/// A set of cookies, replacing each other by name, domain and path.
+///
+/// This is the state of [`MemoryCookieStore`](crate::MemoryCookieStore) and
+/// [`FileCookieStore`](crate::FileCookieStore), and can be used to implement other
+/// [`CookieStore`](crate::CookieStore)s. It serializes with serde.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Construct an empty `CookieJar`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `cookie`, replacing the cookie with the same name, domain and path if any. An already
+    /// expired cookie only removes the one it replaces, which is how servers delete cookies.
+    pub fn insert(&mut self, cookie: Cookie) {
+        let now = SystemTime::now();
+        self.cookies.retain(|c| {
+            !c.is_expired(now)
+                && (c.name != cookie.name || c.domain != cookie.domain || c.path != cookie.path)
+        });
+        if !cookie.is_expired(now) {
+            self.cookies.push(cookie);
+        }
+    }
+
+    /// Returns the unexpired cookies to send with a request to `url`, those with longer paths
+    /// first.
+    pub fn matching(&self, url: &Url) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        let mut cookies: Vec<_> = self
+            .cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .cloned()
+            .collect();
+        // The sort is stable, so cookies with the same path keep their creation order.
+        cookies.sort_by_key(|cookie| Reverse(cookie.path.len()));
+        cookies
+    }
+
+    /// Iterates over all cookies in the jar, including expired ones that haven't been removed
+    /// yet.
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.iter()
+    }
+
+    /// Removes all cookies.
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+
+    /// The number of cookies in the jar.
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    /// Returns true if the jar holds no cookies.
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+}
+
+impl Extend<Cookie> for CookieJar {
+    fn extend<I: IntoIterator<Item = Cookie>>(&mut self, cookies: I) {
+        for cookie in cookies {
+            self.insert(cookie);
+        }
+    }
+}
+
+/// Whether `host` is `domain` or one of its subdomains. IP addresses only match themselves.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.parse::<IpAddr>().is_err()
+        && host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Whether `path` is `cookie_path` or below it.
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    match path.strip_prefix(cookie_path) {
+        Some(rest) => cookie_path.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The path a cookie without a `Path` attribute applies to: the "directory" of the request path.
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(i) => url.path()[..i].to_owned(),
+    }
+}
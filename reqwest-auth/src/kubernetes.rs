@@ -0,0 +1,80 @@
+//! `KubernetesToken` authenticates in-cluster requests to the Kubernetes API server.
+use std::path::PathBuf;
+
+use reqwest::Url;
+
+use crate::secret::Secret;
+use crate::token::{FileToken, TokenProvider};
+
+/// A [`TokenProvider`] returning the service account token projected into Kubernetes pods, to
+/// call the API server from within the cluster with
+/// [`BearerAuthMiddleware`](crate::BearerAuthMiddleware).
+///
+/// The kubelet rotates projected tokens before they expire by replacing the file, which is read
+/// again whenever it is modified, as with [`FileToken`].
+///
+/// The certificate of the API server is signed by the cluster's certificate authority, mounted
+/// at [`CA_PATH`](Self::CA_PATH). The client calling the API server must trust it, for instance
+/// by loading it with `reqwest::Certificate::from_pem` and adding it with
+/// `reqwest::ClientBuilder::add_root_certificate`.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{BearerAuthMiddleware, KubernetesToken};
+///
+/// let api_server = KubernetesToken::api_server_url().expect("not running in a cluster");
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(BearerAuthMiddleware::new(KubernetesToken::new()))
+///     .build();
+/// # let _ = (api_server, client);
+/// ```
+#[derive(Debug)]
+pub struct KubernetesToken(FileToken);
+
+impl KubernetesToken {
+    /// The path of the service account token in pods.
+    pub const TOKEN_PATH: &'static str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+    /// The path of the certificate authority of the cluster, PEM encoded, in pods.
+    pub const CA_PATH: &'static str = "/var/run/secrets/kubernetes.io/serviceaccount/ca.crt";
+
+    /// Construct `KubernetesToken` reading the token at [`TOKEN_PATH`](Self::TOKEN_PATH).
+    pub fn new() -> Self {
+        Self::with_path(Self::TOKEN_PATH)
+    }
+
+    /// Construct `KubernetesToken` reading the token at `path`, for tokens projected elsewhere.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self(FileToken::new(path))
+    }
+
+    /// The URL of the API server, from the `KUBERNETES_SERVICE_HOST` and
+    /// `KUBERNETES_SERVICE_PORT` environment variables set in pods.
+    pub fn api_server_url() -> Option<Url> {
+        let host = std::env::var("KUBERNETES_SERVICE_HOST").ok()?;
+        let port = std::env::var("KUBERNETES_SERVICE_PORT").ok()?;
+        let url = if host.contains(':') {
+            format!("https://[{}]:{}", host, port)
+        } else {
+            format!("https://{}:{}", host, port)
+        };
+        url.parse().ok()
+    }
+}
+
+impl Default for KubernetesToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for KubernetesToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        self.0.token().await
+    }
+
+    fn invalidate(&self) {
+        self.0.invalidate()
+    }
+}
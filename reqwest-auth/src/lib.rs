@@ -0,0 +1,123 @@
+//! Cookie, session and authentication middleware built on [`reqwest_middleware`].
+//!
+//! Use [`CookieMiddleware`] to keep the cookies set by servers in a [`CookieStore`], in memory
+//! with [`MemoryCookieStore`], in a JSON file with [`FileCookieStore`] or in any other backend.
+//! A [`Session`] bundles cookies, default headers and credentials into an identity which can be
+//! persisted, and [`SessionExt::with_session`] derives a client acting as a session.
+//!
+//! [`CsrfMiddleware`] adds the CSRF tokens some APIs require to state-changing requests, and
+//! [`OAuth2Middleware`] authenticates requests with OAuth 2.0 access tokens.
+//! [`BearerAuthMiddleware`] adds bearer tokens from any [`TokenProvider`], such as a
+//! [`FileToken`] rotated on disk or the [`KubernetesToken`] of a pod, and
+//! [`RegistryAuthMiddleware`] implements the token authentication of container registries.
+//! [`AuthRetryMiddleware`] replays requests whose token was rejected with a fresh one.
+//! [`BasicAuthMiddleware`] adds HTTP Basic authentication with passwords from a
+//! [`TokenProvider`]. [`Netrc`] applies the credentials of `.netrc` files as curl does, and
+//! [`ApiKeys`] the API key of each host. [`NonceMiddleware`] adds unique nonces and timestamps
+//! for APIs rejecting replayed requests.
+//!
+//! ## Feature flags
+//!
+//! * `cloud-metadata`: [`GcpMetadataToken`] and [`AzureManagedIdentityToken`] to obtain tokens
+//!   from the instance metadata services of cloud platforms, and with `sigv4`,
+//!   [`ImdsCredentials`] for the credentials of EC2 instances.
+//! * `content-digest`: [`ContentDigestMiddleware`] and [`VerifyDigestMiddleware`] to add and
+//!   check RFC 9530 `Content-Digest` headers.
+//! * `digest-auth`: [`DigestAuthMiddleware`] for HTTP Digest authentication.
+//! * `github`: [`GitHubAppToken`] to authenticate as a GitHub App installation and
+//!   [`GitHubRateLimitMiddleware`] to wait out GitHub's rate limits.
+//! * `keyring`: [`KeyringToken`] to read tokens and passwords from the credential store of the
+//!   operating system.
+//! * `hmac`: [`HmacSigningMiddleware`] and [`HawkMiddleware`] to sign requests with a shared
+//!   secret.
+//! * `sigv4`: [`SigV4Middleware`] to sign requests to AWS services.
+//!
+//! ## Example
+//!
+//! ```
+//! use reqwest_middleware::ClientBuilder;
+//! use reqwest_auth::{CookieMiddleware, MemoryCookieStore};
+//!
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(CookieMiddleware::new(MemoryCookieStore::new()))
+//!     .build();
+//! ```
+
+mod api_key;
+mod auth_retry;
+mod challenge;
+#[cfg(feature = "content-digest")]
+mod content_digest;
+mod cookie;
+mod cookie_store;
+mod csrf;
+#[cfg(feature = "digest-auth")]
+mod digest_auth;
+#[cfg(feature = "github")]
+mod github;
+#[cfg(feature = "hmac")]
+mod hawk;
+mod jar;
+#[cfg(not(target_arch = "wasm32"))]
+mod kubernetes;
+#[cfg(feature = "cloud-metadata")]
+mod metadata;
+mod netrc;
+mod nonce;
+mod oauth2;
+#[cfg(all(feature = "keyring", not(target_arch = "wasm32")))]
+mod os_keyring;
+mod registry;
+mod secret;
+mod session;
+#[cfg(feature = "hmac")]
+mod signing;
+#[cfg(feature = "sigv4")]
+mod sigv4;
+mod token;
+
+pub use api_key::{ApiKeyPlacement, ApiKeys};
+pub use auth_retry::{AuthRetryMiddleware, DisableAuthRetry};
+#[cfg(feature = "content-digest")]
+pub use content_digest::{
+    ContentDigestMiddleware, DigestAlgorithm, DigestMismatch, MissingDigest, VerifyDigestMiddleware,
+};
+pub use cookie::{CookieMiddleware, CookieStoreOverride};
+#[cfg(not(target_arch = "wasm32"))]
+pub use cookie_store::FileCookieStore;
+pub use cookie_store::{CookieStore, MemoryCookieStore};
+pub use csrf::{CsrfMiddleware, CsrfSource, CsrfTarget};
+#[cfg(feature = "digest-auth")]
+pub use digest_auth::DigestAuthMiddleware;
+#[cfg(feature = "github")]
+pub use github::{GitHubAppToken, GitHubRateLimitMiddleware, InvalidPrivateKey};
+#[cfg(feature = "hmac")]
+pub use hawk::HawkMiddleware;
+pub use jar::{Cookie, CookieJar};
+#[cfg(not(target_arch = "wasm32"))]
+pub use kubernetes::KubernetesToken;
+#[cfg(all(feature = "cloud-metadata", feature = "sigv4"))]
+pub use metadata::ImdsCredentials;
+#[cfg(feature = "cloud-metadata")]
+pub use metadata::{AzureManagedIdentityToken, GcpMetadataToken};
+pub use netrc::Netrc;
+pub use nonce::{MonotonicNonce, NonceMiddleware, NonceSource};
+pub use oauth2::{OAuth2Middleware, TokenRequestFailed};
+#[cfg(all(feature = "keyring", not(target_arch = "wasm32")))]
+pub use os_keyring::KeyringToken;
+pub use registry::RegistryAuthMiddleware;
+pub use secret::Secret;
+pub use session::{Session, SessionAuth, SessionExt, SessionMiddleware};
+#[cfg(feature = "hmac")]
+pub use signing::{HmacSigningMiddleware, SigningKey, SigningKeys, UnsignableBody};
+#[cfg(feature = "sigv4")]
+pub use sigv4::{AwsCredentials, AwsCredentialsProvider, SigV4Middleware};
+#[cfg(not(target_arch = "wasm32"))]
+pub use token::FileToken;
+pub use token::{BasicAuthMiddleware, BearerAuthMiddleware, EnvToken, StaticToken, TokenProvider};
+
+/// Lowercase hexadecimal encoding of `bytes`.
+#[cfg(any(feature = "digest-auth", feature = "hmac", feature = "sigv4"))]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
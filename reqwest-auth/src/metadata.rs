@@ -0,0 +1,439 @@
+//! Credential providers for the instance metadata services of cloud platforms.
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+
+use crate::oauth2::TokenRequestFailed;
+use crate::secret::Secret;
+use crate::token::TokenProvider;
+
+const GCP_METADATA: &str = "http://metadata.google.internal";
+const IMDS: &str = "http://169.254.169.254";
+
+/// How long before their expiry credentials are renewed.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Credentials cached until shortly before they expire.
+struct Cached<T> {
+    value: Mutex<Option<(T, Instant)>>,
+    // Held while renewing the credentials, so that concurrent requests share the renewal.
+    refreshing: futures::lock::Mutex<()>,
+}
+
+impl<T: Clone> Cached<T> {
+    fn new() -> Self {
+        Self {
+            value: Mutex::new(None),
+            refreshing: futures::lock::Mutex::new(()),
+        }
+    }
+
+    fn valid(&self) -> Option<T> {
+        let value = self.value.lock().expect("credentials lock poisoned");
+        value
+            .as_ref()
+            .filter(|(_, expires_at)| Instant::now() + REFRESH_MARGIN < *expires_at)
+            .map(|(value, _)| value.clone())
+    }
+
+    fn clear(&self) {
+        *self.value.lock().expect("credentials lock poisoned") = None;
+    }
+
+    /// Returns the cached value, or the one returned by `renew` along with its lifetime.
+    async fn get<F>(&self, renew: F) -> anyhow::Result<T>
+    where
+        F: Future<Output = anyhow::Result<(T, Duration)>>,
+    {
+        if let Some(value) = self.valid() {
+            return Ok(value);
+        }
+        let _refreshing = self.refreshing.lock().await;
+        // Another request may have renewed the value while we were waiting.
+        if let Some(value) = self.valid() {
+            return Ok(value);
+        }
+        let requested_at = Instant::now();
+        let (value, lifetime) = renew.await?;
+        *self.value.lock().expect("credentials lock poisoned") =
+            Some((value.clone(), requested_at + lifetime));
+        Ok(value)
+    }
+}
+
+/// Sends `req`, returning the body of successful responses.
+async fn fetch(req: RequestBuilder) -> anyhow::Result<String> {
+    let res = req.send().await?;
+    let url = res.url().clone();
+    let status = res.status();
+    let body = res.text().await?;
+    if !status.is_success() {
+        return Err(TokenRequestFailed { url, status, body }.into());
+    }
+    Ok(body)
+}
+
+/// Parses `body`, failing with [`TokenRequestFailed`] like an unsuccessful response would.
+fn parse<'a, T: Deserialize<'a>>(url: &Url, body: &'a str) -> anyhow::Result<T> {
+    serde_json::from_str(body).map_err(|_| {
+        TokenRequestFailed {
+            url: url.clone(),
+            status: http::StatusCode::OK,
+            body: body.to_owned(),
+        }
+        .into()
+    })
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    access_token: String,
+    expires_in: Seconds,
+}
+
+/// A number of seconds, which Azure sends as a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Seconds {
+    Number(u64),
+    String(String),
+}
+
+impl Seconds {
+    fn duration(&self) -> Duration {
+        let secs = match self {
+            Self::Number(secs) => *secs,
+            Self::String(secs) => secs.parse().unwrap_or_default(),
+        };
+        Duration::from_secs(secs)
+    }
+}
+
+/// A [`TokenProvider`] returning the access tokens of a Google Cloud service account, from the
+/// [metadata server](https://cloud.google.com/compute/docs/access/authenticate-workloads) of
+/// Compute Engine, GKE, Cloud Run and Cloud Functions.
+///
+/// Tokens are cached until a minute before they expire. The metadata server must be reached
+/// directly, so `client` shouldn't go through a proxy.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{BearerAuthMiddleware, GcpMetadataToken};
+///
+/// let metadata = reqwest::Client::builder().no_proxy().build().unwrap();
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(BearerAuthMiddleware::new(GcpMetadataToken::new(metadata)))
+///     .build();
+/// ```
+pub struct GcpMetadataToken {
+    client: Client,
+    endpoint: String,
+    account: String,
+    scopes: Vec<String>,
+    token: Cached<Secret<String>>,
+}
+
+impl GcpMetadataToken {
+    /// Construct `GcpMetadataToken` for the default service account of the instance, calling
+    /// the metadata server with `client`.
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            endpoint: GCP_METADATA.to_owned(),
+            account: "default".to_owned(),
+            scopes: Vec::new(),
+            token: Cached::new(),
+        }
+    }
+
+    /// Use the service account `account`, by email, instead of the default one.
+    pub fn with_account(mut self, account: impl Into<String>) -> Self {
+        self.account = account.into();
+        self
+    }
+
+    /// Request tokens with `scopes` instead of those of the instance.
+    pub fn with_scopes<S: Into<String>>(mut self, scopes: impl IntoIterator<Item = S>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the URL of the metadata server, `http://metadata.google.internal` by default.
+    pub fn with_endpoint(mut self, endpoint: Url) -> Self {
+        self.endpoint = endpoint.as_str().trim_end_matches('/').to_owned();
+        self
+    }
+
+    async fn request_token(&self) -> anyhow::Result<(Secret<String>, Duration)> {
+        let mut url: Url = format!(
+            "{}/computeMetadata/v1/instance/service-accounts/{}/token",
+            self.endpoint, self.account
+        )
+        .parse()?;
+        if !self.scopes.is_empty() {
+            url.query_pairs_mut()
+                .append_pair("scopes", &self.scopes.join(","));
+        }
+        tracing::debug!("Requesting access token from {}", url);
+        let body = fetch(
+            self.client
+                .get(url.clone())
+                .header("metadata-flavor", "Google"),
+        )
+        .await?;
+        let token: AccessToken = parse(&url, &body)?;
+        Ok((Secret::new(token.access_token), token.expires_in.duration()))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenProvider for GcpMetadataToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        self.token.get(self.request_token()).await
+    }
+
+    fn invalidate(&self) {
+        self.token.clear()
+    }
+}
+
+/// A [`TokenProvider`] returning the access tokens of an Azure [managed
+/// identity](https://learn.microsoft.com/en-us/entra/identity/managed-identities-azure-resources/how-to-use-vm-token)
+/// for a resource, such as `https://storage.azure.com/`.
+///
+/// Tokens are requested from the instance metadata service of virtual machines, or from the
+/// endpoint given by the `IDENTITY_ENDPOINT` and `IDENTITY_HEADER` environment variables in App
+/// Service and Azure Functions. They are cached until a minute before they expire. The metadata
+/// service must be reached directly, so `client` shouldn't go through a proxy.
+pub struct AzureManagedIdentityToken {
+    client: Client,
+    resource: String,
+    client_id: Option<String>,
+    endpoint: Option<String>,
+    token: Cached<Secret<String>>,
+}
+
+impl AzureManagedIdentityToken {
+    /// Construct `AzureManagedIdentityToken` for `resource`, calling the metadata service with
+    /// `client`.
+    pub fn new(client: Client, resource: impl Into<String>) -> Self {
+        Self {
+            client,
+            resource: resource.into(),
+            client_id: None,
+            endpoint: None,
+            token: Cached::new(),
+        }
+    }
+
+    /// Use the user-assigned identity with the client ID `client_id`, instead of the
+    /// system-assigned one.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Set the URL of the instance metadata service, `http://169.254.169.254` by default. The
+    /// environment variables of App Service are then ignored.
+    pub fn with_endpoint(mut self, endpoint: Url) -> Self {
+        self.endpoint = Some(endpoint.as_str().trim_end_matches('/').to_owned());
+        self
+    }
+
+    async fn request_token(&self) -> anyhow::Result<(Secret<String>, Duration)> {
+        let app_service = match (
+            &self.endpoint,
+            std::env::var("IDENTITY_ENDPOINT"),
+            std::env::var("IDENTITY_HEADER"),
+        ) {
+            (None, Ok(endpoint), Ok(header)) => Some((endpoint, header)),
+            _ => None,
+        };
+        let (mut url, api_version): (Url, _) = match &app_service {
+            Some((endpoint, _)) => (endpoint.parse()?, "2019-08-01"),
+            None => (
+                format!(
+                    "{}/metadata/identity/oauth2/token",
+                    self.endpoint.as_deref().unwrap_or(IMDS)
+                )
+                .parse()?,
+                "2018-02-01",
+            ),
+        };
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("api-version", api_version);
+            query.append_pair("resource", &self.resource);
+            if let Some(client_id) = &self.client_id {
+                query.append_pair("client_id", client_id);
+            }
+        }
+        tracing::debug!("Requesting managed identity token from {}", url);
+        let req = match &app_service {
+            Some((_, header)) => self
+                .client
+                .get(url.clone())
+                .header("x-identity-header", header),
+            None => self.client.get(url.clone()).header("metadata", "true"),
+        };
+        let body = fetch(req).await?;
+        let token: AccessToken = parse(&url, &body)?;
+        Ok((Secret::new(token.access_token), token.expires_in.duration()))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenProvider for AzureManagedIdentityToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        self.token.get(self.request_token()).await
+    }
+
+    fn invalidate(&self) {
+        self.token.clear()
+    }
+}
+
+#[cfg(feature = "sigv4")]
+pub use aws::ImdsCredentials;
+
+#[cfg(feature = "sigv4")]
+mod aws {
+    use std::time::Duration;
+
+    use reqwest::{Client, Url};
+    use serde::Deserialize;
+
+    use super::{fetch, parse, Cached, IMDS};
+    use crate::secret::Secret;
+    use crate::sigv4::{AwsCredentials, AwsCredentialsProvider};
+
+    /// The lifetime requested for IMDSv2 session tokens, the longest allowed.
+    const SESSION_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct RoleCredentials {
+        access_key_id: String,
+        secret_access_key: String,
+        token: String,
+        expiration: String,
+    }
+
+    /// An [`AwsCredentialsProvider`] returning the temporary credentials of the IAM role of an
+    /// EC2 instance, from the [instance metadata
+    /// service](https://docs.aws.amazon.com/AWSEC2/latest/UserGuide/instancedata-data-retrieval.html)
+    /// with IMDSv2 session tokens.
+    ///
+    /// Session tokens and credentials are cached until a minute before they expire. The
+    /// metadata service must be reached directly, so `client` shouldn't go through a proxy.
+    ///
+    /// ```
+    /// use reqwest_middleware::ClientBuilder;
+    /// use reqwest_auth::{ImdsCredentials, SigV4Middleware};
+    ///
+    /// let metadata = reqwest::Client::builder().no_proxy().build().unwrap();
+    /// let client = ClientBuilder::new(reqwest::Client::new())
+    ///     .with(SigV4Middleware::new(ImdsCredentials::new(metadata), "eu-west-1", "s3"))
+    ///     .build();
+    /// ```
+    pub struct ImdsCredentials {
+        client: Client,
+        endpoint: String,
+        role: Option<String>,
+        session: Cached<Secret<String>>,
+        credentials: Cached<AwsCredentials>,
+    }
+
+    impl ImdsCredentials {
+        /// Construct `ImdsCredentials` calling the metadata service with `client`.
+        pub fn new(client: Client) -> Self {
+            Self {
+                client,
+                endpoint: IMDS.to_owned(),
+                role: None,
+                session: Cached::new(),
+                credentials: Cached::new(),
+            }
+        }
+
+        /// Use the credentials of `role`, instead of looking up the role of the instance
+        /// profile.
+        pub fn with_role(mut self, role: impl Into<String>) -> Self {
+            self.role = Some(role.into());
+            self
+        }
+
+        /// Set the URL of the metadata service, `http://169.254.169.254` by default.
+        pub fn with_endpoint(mut self, endpoint: Url) -> Self {
+            self.endpoint = endpoint.as_str().trim_end_matches('/').to_owned();
+            self
+        }
+
+        async fn request_session(&self) -> anyhow::Result<(Secret<String>, Duration)> {
+            let token = fetch(
+                self.client
+                    .put(format!("{}/latest/api/token", self.endpoint))
+                    .header(
+                        "x-aws-ec2-metadata-token-ttl-seconds",
+                        SESSION_TTL.as_secs(),
+                    ),
+            )
+            .await?;
+            Ok((Secret::new(token), SESSION_TTL))
+        }
+
+        async fn request_credentials(&self) -> anyhow::Result<(AwsCredentials, Duration)> {
+            let session = self.session.get(self.request_session()).await?;
+            let get = |url: &str| {
+                self.client
+                    .get(url)
+                    .header("x-aws-ec2-metadata-token", session.expose_secret())
+            };
+            let roles_url = format!(
+                "{}/latest/meta-data/iam/security-credentials/",
+                self.endpoint
+            );
+            let role = match &self.role {
+                Some(role) => role.clone(),
+                None => fetch(get(&roles_url))
+                    .await?
+                    .lines()
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("The instance has no IAM role"))?
+                    .to_owned(),
+            };
+            let url: Url = format!("{}{}", roles_url, role).parse()?;
+            tracing::debug!("Requesting credentials from {}", url);
+            let body = fetch(get(url.as_str())).await?;
+            let credentials: RoleCredentials = parse(&url, &body)?;
+            // Credentials are renewed well before they expire, should `Expiration` not be
+            // understood.
+            let lifetime = chrono::DateTime::parse_from_rfc3339(&credentials.expiration)
+                .ok()
+                .and_then(|expiration| {
+                    (expiration.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                        .to_std()
+                        .ok()
+                })
+                .unwrap_or(Duration::from_secs(15 * 60));
+            Ok((
+                AwsCredentials::new(credentials.access_key_id, credentials.secret_access_key)
+                    .with_session_token(credentials.token),
+                lifetime,
+            ))
+        }
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+    #[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+    impl AwsCredentialsProvider for ImdsCredentials {
+        async fn credentials(&self) -> anyhow::Result<AwsCredentials> {
+            self.credentials.get(self.request_credentials()).await
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! `Netrc` applies the credentials of `.netrc` files.
+use reqwest_middleware::{RequestBuilder, RequestInitialiser};
+
+use crate::secret::Secret;
+
+#[derive(Clone, Debug, Default)]
+struct Machine {
+    login: Option<String>,
+    password: Option<Secret<String>>,
+}
+
+/// The credentials of a [`.netrc`](https://everything.curl.dev/usingcurl/netrc.html) file,
+/// applied to requests as HTTP Basic authentication as curl does.
+///
+/// Used as a [`RequestInitialiser`], `Netrc` adds an `Authorization` header to requests for the
+/// hosts of its `machine` entries, or any host if it has a `default` entry. The first entry
+/// matching the host is used, whatever the port. The header is added as the request starts
+/// being built, so requests authenticated in another way should be made with a client without
+/// this initialiser.
+///
+/// The `machine`, `default`, `login` and `password` tokens are supported. `account` tokens are
+/// ignored, as are `macdef` macros, up to the empty line ending them, and comments starting with
+/// `#`. Tokens may be double quoted to contain whitespace, with `\"`, `\\`, `\n`, `\r` and `\t`
+/// escapes.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::Netrc;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with_init(Netrc::from_env()?)
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Netrc {
+    machines: Vec<(String, Machine)>,
+    default: Option<Machine>,
+}
+
+impl Netrc {
+    /// Parses the contents of a `.netrc` file. Unknown tokens are skipped, like curl does.
+    pub fn parse(contents: &str) -> Self {
+        let mut netrc = Self::default();
+        // The entry being parsed, `None` as host for the default entry.
+        let mut entry: Option<(Option<String>, Machine)> = None;
+        let mut tokens = tokens(contents).into_iter();
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "machine" | "default" => {
+                    netrc.push(entry.take());
+                    let host = match token.as_str() {
+                        "machine" => match tokens.next() {
+                            Some(host) => Some(host),
+                            None => break,
+                        },
+                        _ => None,
+                    };
+                    entry = Some((host, Machine::default()));
+                }
+                "login" => {
+                    let login = tokens.next();
+                    if let Some((_, machine)) = &mut entry {
+                        machine.login = login;
+                    }
+                }
+                "password" => {
+                    let password = tokens.next().map(Secret::new);
+                    if let Some((_, machine)) = &mut entry {
+                        machine.password = password;
+                    }
+                }
+                "account" | "macdef" => {
+                    tokens.next();
+                }
+                _ => {}
+            }
+        }
+        netrc.push(entry);
+        netrc
+    }
+
+    fn push(&mut self, entry: Option<(Option<String>, Machine)>) {
+        match entry {
+            Some((Some(host), machine)) => self.machines.push((host, machine)),
+            Some((None, machine)) => {
+                self.default.get_or_insert(machine);
+            }
+            None => {}
+        }
+    }
+
+    /// The login and password to use for `host`, if any.
+    pub fn credentials(&self, host: &str) -> Option<(&str, Option<&str>)> {
+        let machine = self
+            .machines
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(host))
+            .map(|(_, machine)| machine)
+            .or(self.default.as_ref())?;
+        Some((
+            machine.login.as_deref().unwrap_or_default(),
+            machine
+                .password
+                .as_ref()
+                .map(|p| p.expose_secret().as_str()),
+        ))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Netrc {
+    /// Reads the `.netrc` file at `path`.
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        std::fs::read_to_string(path).map(|contents| Self::parse(&contents))
+    }
+
+    /// Reads the file named by the `NETRC` environment variable, or else `.netrc` in the home
+    /// directory (`_netrc` also being looked for on Windows). A missing home file gives empty
+    /// credentials.
+    pub fn from_env() -> std::io::Result<Self> {
+        if let Some(path) = std::env::var_os("NETRC") {
+            return Self::load(path);
+        }
+        let home = match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            Some(home) => std::path::PathBuf::from(home),
+            None => return Ok(Self::default()),
+        };
+        let names: &[&str] = if cfg!(windows) {
+            &[".netrc", "_netrc"]
+        } else {
+            &[".netrc"]
+        };
+        for name in names {
+            match Self::load(home.join(name)) {
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                result => return result,
+            }
+        }
+        Ok(Self::default())
+    }
+}
+
+/// Splits `.netrc` contents into tokens, leaving out comments and macro definitions.
+fn tokens(contents: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut in_macdef = false;
+    for line in contents.lines() {
+        if in_macdef {
+            // Macro definitions end with an empty line.
+            in_macdef = !line.trim().is_empty();
+            continue;
+        }
+        let mut chars = line.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let token = match chars.peek() {
+                None | Some('#') => break,
+                Some('"') => {
+                    chars.next();
+                    let mut token = String::new();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => match chars.next() {
+                                Some('n') => token.push('\n'),
+                                Some('r') => token.push('\r'),
+                                Some('t') => token.push('\t'),
+                                Some(c) => token.push(c),
+                                None => {}
+                            },
+                            c => token.push(c),
+                        }
+                    }
+                    token
+                }
+                Some(_) => {
+                    let mut token = String::new();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                        token.push(c);
+                    }
+                    token
+                }
+            };
+            tokens.push(token);
+            if tokens.len() >= 2 && tokens[tokens.len() - 2] == "macdef" {
+                // The rest of the line and the following ones are the macro.
+                in_macdef = true;
+                break;
+            }
+        }
+    }
+    tokens
+}
+
+impl RequestInitialiser for Netrc {
+    fn init(&self, req: RequestBuilder) -> RequestBuilder {
+        // Initialisers run on fresh builders, so cloning one to find its host is cheap.
+        let host = req
+            .try_clone()
+            .and_then(|clone| clone.build().ok())
+            .and_then(|built| built.url().host_str().map(str::to_owned));
+        match host.as_deref().and_then(|host| self.credentials(host)) {
+            Some((login, password)) => req.basic_auth(login, password),
+            None => req,
+        }
+    }
+}
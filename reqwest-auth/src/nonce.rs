@@ -0,0 +1,156 @@
+//! `NonceMiddleware` adds a unique nonce and a timestamp to requests.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http::{Extensions, HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+
+/// A source of nonces for [`NonceMiddleware`], which must never return the same nonce twice.
+///
+/// [`MonotonicNonce`] is used by default. Closures returning strings are sources too, for
+/// instance to use UUIDs.
+pub trait NonceSource: 'static + Send + Sync {
+    /// Returns a nonce not returned before.
+    fn next(&self) -> String;
+}
+
+impl<F> NonceSource for F
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    fn next(&self) -> String {
+        (self)()
+    }
+}
+
+/// A [`NonceSource`] returning increasing numbers, starting from the current time in
+/// microseconds since the Unix epoch.
+///
+/// Nonces keep increasing across restarts, as many APIs require, provided fewer than a million
+/// requests are sent per second. Each nonce is greater than the previous one even when
+/// requests are sent concurrently or the system clock goes back.
+#[derive(Debug, Default)]
+pub struct MonotonicNonce {
+    last: AtomicU64,
+}
+
+impl MonotonicNonce {
+    /// Construct `MonotonicNonce`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NonceSource for MonotonicNonce {
+    fn next(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_micros() as u64)
+            .unwrap_or_default();
+        let mut last = self.last.load(Ordering::Relaxed);
+        loop {
+            let next = now.max(last + 1);
+            match self
+                .last
+                .compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return next.to_string(),
+                Err(current) => last = current,
+            }
+        }
+    }
+}
+
+/// `NonceMiddleware` adds a unique nonce (`X-Nonce` by default) and the current timestamp
+/// (`X-Timestamp`, in seconds since the Unix epoch) to requests, for APIs rejecting replayed
+/// requests, such as those of exchanges.
+///
+/// Nonces come from a [`MonotonicNonce`] unless another [`NonceSource`] is given. Each time a
+/// request goes through the middleware it gets a new nonce, so it should be added after any
+/// retry middleware, for every attempt to be accepted, and before any middleware signing the
+/// headers.
+///
+/// ```
+/// use http::HeaderName;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::NonceMiddleware;
+///
+/// let nonce = NonceMiddleware::new()
+///     .with_nonce_header(HeaderName::from_static("api-nonce"))
+///     .with_timestamp_millis();
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(nonce).build();
+/// ```
+#[derive(Clone)]
+pub struct NonceMiddleware {
+    source: Arc<dyn NonceSource>,
+    nonce_header: HeaderName,
+    timestamp_header: HeaderName,
+    millis: bool,
+}
+
+impl NonceMiddleware {
+    /// Construct `NonceMiddleware` taking nonces from a [`MonotonicNonce`].
+    pub fn new() -> Self {
+        Self::with_source(MonotonicNonce::new())
+    }
+
+    /// Construct `NonceMiddleware` taking nonces from `source`.
+    pub fn with_source<S: NonceSource>(source: S) -> Self {
+        Self {
+            source: Arc::new(source),
+            nonce_header: HeaderName::from_static("x-nonce"),
+            timestamp_header: HeaderName::from_static("x-timestamp"),
+            millis: false,
+        }
+    }
+
+    /// Set the header holding the nonce.
+    pub fn with_nonce_header(mut self, name: HeaderName) -> Self {
+        self.nonce_header = name;
+        self
+    }
+
+    /// Set the header holding the timestamp.
+    pub fn with_timestamp_header(mut self, name: HeaderName) -> Self {
+        self.timestamp_header = name;
+        self
+    }
+
+    /// Give timestamps in milliseconds rather than seconds.
+    pub fn with_timestamp_millis(mut self) -> Self {
+        self.millis = true;
+        self
+    }
+}
+
+impl Default for NonceMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for NonceMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(Error::middleware)?;
+        let timestamp = match self.millis {
+            true => elapsed.as_millis() as u64,
+            false => elapsed.as_secs(),
+        };
+        let nonce = HeaderValue::from_str(&self.source.next()).map_err(Error::middleware)?;
+        let headers = req.headers_mut();
+        headers.insert(self.nonce_header.clone(), nonce);
+        headers.insert(self.timestamp_header.clone(), HeaderValue::from(timestamp));
+        next.run(req, extensions).await
+    }
+}
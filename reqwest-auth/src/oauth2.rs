@@ -0,0 +1,217 @@
+//! `OAuth2Middleware` authenticates requests with client credentials grant access tokens.
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use http::{Extensions, HeaderValue, StatusCode};
+use reqwest::{Client, Request, Response, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::secret::Secret;
+
+/// Error returned by [`OAuth2Middleware`] and the other token providers when the token endpoint
+/// doesn't return an access token.
+#[derive(Debug, Error)]
+#[error("Token request to {url} failed with {status}: {body}")]
+pub struct TokenRequestFailed {
+    /// The URL of the token endpoint.
+    pub url: Url,
+    /// The status of the token endpoint's response.
+    pub status: StatusCode,
+    /// The body of the token endpoint's response.
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+struct AccessToken {
+    value: Secret<String>,
+    expires_at: Option<Instant>,
+}
+
+/// `OAuth2Middleware` obtains access tokens from an OAuth 2.0 token endpoint with the client
+/// credentials grant, and adds them to requests as `Authorization: Bearer` headers.
+///
+/// Tokens are cached until shortly before they expire, see [`with_refresh_margin`], so that
+/// they are renewed before requests start failing. Concurrent requests needing a new token wait
+/// for a single call to the token endpoint. When a request is rejected with `401 Unauthorized`,
+/// the token is renewed and the request is sent once more, provided its body can be cloned.
+///
+/// Requests which already have an `Authorization` header are left untouched.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::OAuth2Middleware;
+///
+/// let oauth2 = OAuth2Middleware::new(
+///     reqwest::Client::new(),
+///     "https://auth.example.com/oauth/token".parse().unwrap(),
+///     "client-id",
+///     "client-secret",
+/// )
+/// .with_scopes(["payments:read"]);
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(oauth2).build();
+/// ```
+///
+/// [`with_refresh_margin`]: Self::with_refresh_margin
+pub struct OAuth2Middleware {
+    client: Client,
+    token_url: Url,
+    client_id: String,
+    client_secret: Secret<String>,
+    scopes: Vec<String>,
+    refresh_margin: Duration,
+    token: Mutex<Option<AccessToken>>,
+    // Held while calling the token endpoint, so that concurrent requests share the call.
+    refreshing: futures::lock::Mutex<()>,
+}
+
+impl OAuth2Middleware {
+    /// Construct `OAuth2Middleware` calling the token endpoint at `token_url` with `client`.
+    pub fn new(
+        client: Client,
+        token_url: Url,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            token_url,
+            client_id: client_id.into(),
+            client_secret: Secret::new(client_secret.into()),
+            scopes: Vec::new(),
+            refresh_margin: Duration::from_secs(30),
+            token: Mutex::new(None),
+            refreshing: futures::lock::Mutex::new(()),
+        }
+    }
+
+    /// Set the scopes requested for the tokens.
+    pub fn with_scopes<S: Into<String>>(mut self, scopes: impl IntoIterator<Item = S>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set how long before their expiry tokens are renewed, 30 seconds by default.
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Returns the cached token if it isn't about to expire.
+    fn cached(&self) -> Option<Secret<String>> {
+        let token = self.token.lock().expect("token lock poisoned");
+        token
+            .as_ref()
+            .filter(|token| {
+                token
+                    .expires_at
+                    .is_none_or(|expires_at| Instant::now() + self.refresh_margin < expires_at)
+            })
+            .map(|token| token.value.clone())
+    }
+
+    /// Returns a valid token, calling the token endpoint if needed. A cached token equal to
+    /// `rejected` is renewed.
+    async fn token(&self, rejected: Option<&str>) -> Result<Secret<String>> {
+        match self.cached() {
+            Some(token) if Some(token.expose_secret().as_str()) != rejected => return Ok(token),
+            _ => {}
+        }
+        let _refreshing = self.refreshing.lock().await;
+        // Another request may have renewed the token while we were waiting.
+        match self.cached() {
+            Some(token) if Some(token.expose_secret().as_str()) != rejected => return Ok(token),
+            _ => {}
+        }
+        let token = self.request_token().await?;
+        let value = token.value.clone();
+        *self.token.lock().expect("token lock poisoned") = Some(token);
+        Ok(value)
+    }
+
+    async fn request_token(&self) -> Result<AccessToken> {
+        tracing::debug!("Requesting access token from {}", self.token_url);
+        let form = {
+            let mut form = form_urlencoded::Serializer::new(String::new());
+            form.append_pair("grant_type", "client_credentials");
+            if !self.scopes.is_empty() {
+                form.append_pair("scope", &self.scopes.join(" "));
+            }
+            form.finish()
+        };
+        let requested_at = Instant::now();
+        let res = self
+            .client
+            .post(self.token_url.clone())
+            .basic_auth(&self.client_id, Some(self.client_secret.expose_secret()))
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(form)
+            .send()
+            .await?;
+        let status = res.status();
+        let body = res.bytes().await?;
+        let token: TokenResponse = match serde_json::from_slice(&body) {
+            Ok(token) if status.is_success() => token,
+            _ => {
+                return Err(Error::middleware(TokenRequestFailed {
+                    url: self.token_url.clone(),
+                    status,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                }))
+            }
+        };
+        Ok(AccessToken {
+            value: Secret::new(token.access_token),
+            expires_at: token
+                .expires_in
+                .map(|secs| requested_at + Duration::from_secs(secs)),
+        })
+    }
+}
+
+fn bearer(token: &Secret<String>) -> Result<HeaderValue> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", token.expose_secret()))
+        .map_err(Error::middleware)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for OAuth2Middleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if req.headers().contains_key(AUTHORIZATION) {
+            return next.run(req, extensions).await;
+        }
+
+        let retry_req = req.try_clone();
+        let token = self.token(None).await?;
+        req.headers_mut().insert(AUTHORIZATION, bearer(&token)?);
+        let res = next.clone().run(req, extensions).await?;
+
+        match retry_req {
+            Some(mut retry_req) if res.status() == StatusCode::UNAUTHORIZED => {
+                tracing::debug!("Access token rejected, renewing it");
+                let token = self.token(Some(token.expose_secret())).await?;
+                retry_req
+                    .headers_mut()
+                    .insert(AUTHORIZATION, bearer(&token)?);
+                next.run(retry_req, extensions).await
+            }
+            _ => Ok(res),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! `KeyringToken` reads tokens from the credential store of the operating system.
+use std::sync::Mutex;
+
+use anyhow::Context;
+use keyring::Entry;
+
+use crate::secret::Secret;
+use crate::token::TokenProvider;
+
+/// A [`TokenProvider`] reading the token, or password, stored under a service and user name in
+/// the credential store of the operating system: the Secret Service on Linux, the Keychain on
+/// macOS and the Credential Manager on Windows.
+///
+/// This lets command line tools keep their credentials out of plaintext configuration files. A
+/// login command can [`store`](Self::store) the token, which is read from the store on the first
+/// request and kept in memory afterwards, until [invalidated](TokenProvider::invalidate). Use
+/// it with [`BearerAuthMiddleware`](crate::BearerAuthMiddleware) for tokens, or with
+/// [`BasicAuthMiddleware`](crate::BasicAuthMiddleware) for passwords.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{BearerAuthMiddleware, KeyringToken};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let token = KeyringToken::new("my-cli", "alice");
+/// token.store("s3cr3t")?;
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(BearerAuthMiddleware::new(token))
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct KeyringToken {
+    service: String,
+    user: String,
+    cached: Mutex<Option<Secret<String>>>,
+}
+
+impl KeyringToken {
+    /// Construct `KeyringToken` for the entry of `user` in `service`.
+    pub fn new(service: impl Into<String>, user: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            user: user.into(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    fn entry(&self) -> anyhow::Result<Entry> {
+        Entry::new(&self.service, &self.user).with_context(|| self.context())
+    }
+
+    fn context(&self) -> String {
+        format!(
+            "Failed to access keyring entry of {} for {}",
+            self.user, self.service
+        )
+    }
+
+    /// Saves `token` in the credential store, replacing the current one.
+    pub fn store(&self, token: &str) -> anyhow::Result<()> {
+        self.entry()?
+            .set_password(token)
+            .with_context(|| self.context())?;
+        *self.cached.lock().expect("token lock poisoned") = Some(Secret::from(token));
+        Ok(())
+    }
+
+    /// Removes the token from the credential store, e.g. on logout.
+    pub fn delete(&self) -> anyhow::Result<()> {
+        self.invalidate();
+        match self.entry()?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err).with_context(|| self.context()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for KeyringToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        if let Some(token) = &*self.cached.lock().expect("token lock poisoned") {
+            return Ok(token.clone());
+        }
+        let entry = self.entry()?;
+        // Credential stores are accessed synchronously, and may prompt the user.
+        let token = tokio::task::spawn_blocking(move || entry.get_password())
+            .await?
+            .map(Secret::new)
+            .with_context(|| self.context())?;
+        *self.cached.lock().expect("token lock poisoned") = Some(token.clone());
+        Ok(token)
+    }
+
+    fn invalidate(&self) {
+        *self.cached.lock().expect("token lock poisoned") = None;
+    }
+}
@@ -0,0 +1,259 @@
+//! `RegistryAuthMiddleware` answers the bearer token challenges of container registries.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use http::header::AUTHORIZATION;
+use http::{Extensions, HeaderValue, StatusCode};
+use reqwest::{Client, Request, Response, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use serde::Deserialize;
+
+use crate::challenge::challenges;
+use crate::oauth2::TokenRequestFailed;
+use crate::secret::Secret;
+
+/// The lifetime of tokens which don't state theirs, as set by the token authentication
+/// specification.
+const DEFAULT_EXPIRES_IN: u64 = 60;
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+/// A `WWW-Authenticate: Bearer` challenge of a registry.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Challenge {
+    realm: String,
+    service: Option<String>,
+    scope: Option<String>,
+}
+
+impl Challenge {
+    fn parse(res: &Response) -> Option<Self> {
+        challenges(res.headers(), "bearer").find_map(|mut params| {
+            Some(Self {
+                realm: params.remove("realm")?,
+                service: params.remove("service"),
+                scope: params.remove("scope"),
+            })
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RegistryToken {
+    value: Secret<String>,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Tokens {
+    by_challenge: HashMap<Challenge, RegistryToken>,
+    // The last challenge of each repository, to authorize its next requests upfront.
+    by_repository: HashMap<String, Challenge>,
+}
+
+/// `RegistryAuthMiddleware` implements the [token
+/// authentication](https://distribution.github.io/distribution/spec/auth/token/) flow of Docker
+/// and OCI container registries.
+///
+/// When a registry answers `401 Unauthorized` with a `WWW-Authenticate: Bearer` challenge, a
+/// token for the challenge's service and scope is requested from its realm and the request is
+/// sent again with it, provided its body can be cloned. Tokens are cached per scope until they
+/// expire, and the following requests to the same repository are authorized upfront. A request
+/// needing a broader scope, such as pushing to a repository pulled from before, gets a new
+/// challenge and token.
+///
+/// Tokens are requested anonymously unless [credentials](Self::with_credentials) are set, which
+/// is enough to pull public images from most registries. Requests which already have an
+/// `Authorization` header are left untouched.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::RegistryAuthMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(RegistryAuthMiddleware::new(reqwest::Client::new()).with_credentials("user", "token"))
+///     .build();
+/// ```
+pub struct RegistryAuthMiddleware {
+    client: Client,
+    credentials: Option<(String, Secret<String>)>,
+    tokens: Mutex<Tokens>,
+}
+
+impl RegistryAuthMiddleware {
+    /// Construct `RegistryAuthMiddleware` requesting tokens with `client`.
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            credentials: None,
+            tokens: Mutex::new(Tokens::default()),
+        }
+    }
+
+    /// Authenticate token requests as `username`, with HTTP Basic authentication.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), Secret::new(password.into())));
+        self
+    }
+
+    /// Returns the token cached for the last challenge of `repository`.
+    fn cached_for_repository(&self, repository: &str) -> Option<Secret<String>> {
+        let tokens = self.tokens.lock().expect("tokens lock poisoned");
+        let challenge = tokens.by_repository.get(repository)?;
+        tokens
+            .by_challenge
+            .get(challenge)
+            .filter(|token| Instant::now() < token.expires_at)
+            .map(|token| token.value.clone())
+    }
+
+    /// Returns a token answering `challenge`, requesting one from its realm unless a token
+    /// other than `rejected` is cached.
+    async fn token(
+        &self,
+        challenge: &Challenge,
+        rejected: Option<&Secret<String>>,
+    ) -> Result<Secret<String>> {
+        let cached = {
+            let tokens = self.tokens.lock().expect("tokens lock poisoned");
+            tokens
+                .by_challenge
+                .get(challenge)
+                .filter(|token| Instant::now() < token.expires_at)
+                .map(|token| token.value.clone())
+        };
+        match cached {
+            Some(token) if rejected.map(Secret::expose_secret) != Some(token.expose_secret()) => {
+                return Ok(token)
+            }
+            _ => {}
+        }
+
+        let mut url = Url::parse(&challenge.realm).map_err(Error::middleware)?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(service) = &challenge.service {
+                query.append_pair("service", service);
+            }
+            // Registries expect each scope of a challenge in its own parameter.
+            for scope in challenge.scope.iter().flat_map(|scope| scope.split(' ')) {
+                query.append_pair("scope", scope);
+            }
+        }
+        tracing::debug!("Requesting registry token from {}", url);
+        let mut token_req = self.client.get(url.clone());
+        if let Some((username, password)) = &self.credentials {
+            token_req = token_req.basic_auth(username, Some(password.expose_secret()));
+        }
+        let requested_at = Instant::now();
+        let res = token_req.send().await?;
+        let status = res.status();
+        let body = res.bytes().await?;
+        let token = match serde_json::from_slice::<TokenResponse>(&body) {
+            Ok(TokenResponse {
+                token,
+                access_token,
+                expires_in,
+            }) if status.is_success() => token.or(access_token).map(|value| (value, expires_in)),
+            _ => None,
+        };
+        let (value, expires_in) = match token {
+            Some((value, expires_in)) => (Secret::new(value), expires_in),
+            None => {
+                return Err(Error::middleware(TokenRequestFailed {
+                    url,
+                    status,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                }))
+            }
+        };
+        let expires_in = Duration::from_secs(expires_in.unwrap_or(DEFAULT_EXPIRES_IN));
+        self.tokens
+            .lock()
+            .expect("tokens lock poisoned")
+            .by_challenge
+            .insert(
+                challenge.clone(),
+                RegistryToken {
+                    value: value.clone(),
+                    expires_at: requested_at + expires_in,
+                },
+            );
+        Ok(value)
+    }
+}
+
+/// The repository a registry URL refers to, e.g. `https://ghcr.io/library/alpine` for
+/// `https://ghcr.io/v2/library/alpine/manifests/latest`, or its origin for other URLs.
+fn repository(url: &Url) -> String {
+    let origin = url.origin().ascii_serialization();
+    let name = url.path().strip_prefix("/v2/").and_then(|path| {
+        ["/manifests/", "/blobs/", "/tags/", "/referrers/"]
+            .iter()
+            .filter_map(|endpoint| path.rfind(endpoint))
+            .max()
+            .map(|end| &path[..end])
+    });
+    match name {
+        Some(name) => format!("{}/{}", origin, name),
+        None => origin,
+    }
+}
+
+fn bearer(token: &Secret<String>) -> Result<HeaderValue> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", token.expose_secret()))
+        .map_err(Error::middleware)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RegistryAuthMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if req.headers().contains_key(AUTHORIZATION) {
+            return next.run(req, extensions).await;
+        }
+
+        let repository = repository(req.url());
+        let retry_req = req.try_clone();
+        let sent = self.cached_for_repository(&repository);
+        if let Some(token) = &sent {
+            req.headers_mut().insert(AUTHORIZATION, bearer(token)?);
+        }
+        let res = next.clone().run(req, extensions).await?;
+
+        if res.status() != StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+        let (challenge, mut retry_req) = match (Challenge::parse(&res), retry_req) {
+            (Some(challenge), Some(retry_req)) => (challenge, retry_req),
+            _ => return Ok(res),
+        };
+        let token = self.token(&challenge, sent.as_ref()).await?;
+        self.tokens
+            .lock()
+            .expect("tokens lock poisoned")
+            .by_repository
+            .insert(repository, challenge);
+        retry_req
+            .headers_mut()
+            .insert(AUTHORIZATION, bearer(&token)?);
+        next.run(retry_req, extensions).await
+    }
+}
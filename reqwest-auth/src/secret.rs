@@ -0,0 +1,49 @@
+//! `Secret` keeps credentials out of logs and memory.
+use std::fmt;
+
+use zeroize::Zeroize;
+
+/// A value which isn't shown by its `Debug` implementation, such as an access token or a
+/// password.
+///
+/// The value is only reachable through [`expose_secret`](Self::expose_secret), which makes the
+/// places where it is used easy to audit. It is overwritten with zeros when dropped, so that it
+/// doesn't linger in freed memory; copies made through `expose_secret` are not.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    /// Wrap `value`.
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// The wrapped value.
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
@@ -0,0 +1,277 @@
+//! `Session` bundles the cookies, default headers and credentials of one identity.
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use http::header::AUTHORIZATION;
+use http::{Extensions, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cookie::CookieMiddleware;
+use crate::cookie_store::MemoryCookieStore;
+use crate::jar::CookieJar;
+
+/// Credentials sent by a [`Session`] with each request.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionAuth {
+    /// HTTP basic authentication.
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    /// A bearer token.
+    Bearer(String),
+}
+
+impl SessionAuth {
+    fn header_value(&self) -> Option<HeaderValue> {
+        let value = match self {
+            Self::Basic { username, password } => {
+                let credentials = format!("{}:{}", username, password.as_deref().unwrap_or(""));
+                format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(credentials)
+                )
+            }
+            Self::Bearer(token) => format!("Bearer {}", token),
+        };
+        let mut value = HeaderValue::from_str(&value).ok()?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+}
+
+/// A named identity: a cookie jar, default headers and optional credentials, which can be
+/// persisted and resumed later.
+///
+/// A `Session` is a handle, clones share the same state, so the cookies set by responses and
+/// any change made through one clone are visible to all clients using the session. Use
+/// [`SessionExt::with_session`] to derive a client acting as the session from an existing
+/// client, which lets a single client juggle many logged in identities:
+///
+/// ```no_run
+/// # use reqwest_middleware::Result;
+/// use reqwest_auth::{Session, SessionAuth, SessionExt};
+/// use reqwest_middleware::ClientBuilder;
+///
+/// # async fn example() -> Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new()).build();
+///
+/// let alice = Session::new("alice");
+/// alice.set_auth(Some(SessionAuth::Bearer("alice-token".into())));
+/// client.with_session(&alice).get("https://example.com/inbox").send().await?;
+///
+/// // Keep the cookies and credentials around for the next run.
+/// alice.save("alice.json").unwrap();
+/// let alice = Session::load("alice.json").unwrap();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Session {
+    name: Arc<str>,
+    cookies: MemoryCookieStore,
+    state: Arc<Mutex<SessionState>>,
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    headers: HeaderMap,
+    auth: Option<SessionAuth>,
+}
+
+/// The persisted form of a [`Session`].
+#[derive(Serialize, Deserialize)]
+struct SessionData {
+    name: String,
+    cookies: CookieJar,
+    headers: Vec<(String, String)>,
+    auth: Option<SessionAuth>,
+}
+
+impl Session {
+    /// Construct an empty `Session` called `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into().into(),
+            cookies: MemoryCookieStore::new(),
+            state: Arc::default(),
+        }
+    }
+
+    /// The name of the session.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The cookie jar of the session.
+    pub fn cookies(&self) -> &MemoryCookieStore {
+        &self.cookies
+    }
+
+    /// Sets a header sent with every request of the session, unless the request sets it
+    /// itself.
+    pub fn set_header(&self, name: HeaderName, value: HeaderValue) {
+        self.state
+            .lock()
+            .expect("session lock poisoned")
+            .headers
+            .insert(name, value);
+    }
+
+    /// Stops sending the default header `name`.
+    pub fn remove_header(&self, name: &HeaderName) {
+        self.state
+            .lock()
+            .expect("session lock poisoned")
+            .headers
+            .remove(name);
+    }
+
+    /// The headers sent with every request of the session.
+    pub fn headers(&self) -> HeaderMap {
+        self.state
+            .lock()
+            .expect("session lock poisoned")
+            .headers
+            .clone()
+    }
+
+    /// Sets the credentials sent with requests which have no `Authorization` header of their
+    /// own, or stops sending credentials if `None`.
+    pub fn set_auth(&self, auth: Option<SessionAuth>) {
+        self.state.lock().expect("session lock poisoned").auth = auth;
+    }
+
+    /// The credentials of the session.
+    pub fn auth(&self) -> Option<SessionAuth> {
+        self.state
+            .lock()
+            .expect("session lock poisoned")
+            .auth
+            .clone()
+    }
+
+    /// Serializes the session, including its credentials and cookies, to JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let state = self.state.lock().expect("session lock poisoned");
+        let data = SessionData {
+            name: self.name.to_string(),
+            cookies: self.cookies.jar(),
+            headers: state
+                .headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.as_str().to_owned(), value.to_str().ok()?.to_owned()))
+                })
+                .collect(),
+            auth: state.auth.clone(),
+        };
+        serde_json::to_string(&data)
+    }
+
+    /// Resumes a session serialized with [`to_json`](Self::to_json). Headers which are no
+    /// longer valid are dropped.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let data: SessionData = serde_json::from_str(json)?;
+        let headers = data
+            .headers
+            .into_iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(&value).ok()?,
+                ))
+            })
+            .collect();
+        Ok(Self {
+            name: data.name.into(),
+            cookies: MemoryCookieStore::from_jar(data.cookies),
+            state: Arc::new(Mutex::new(SessionState {
+                headers,
+                auth: data.auth,
+            })),
+        })
+    }
+
+    /// Writes the session to the file at `path`. The file holds credentials and should be
+    /// protected accordingly.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let json = self
+            .to_json()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let tmp = path.as_ref().with_extension("tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Resumes a session written with [`save`](Self::save).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// `SessionMiddleware` makes requests on behalf of a [`Session`]: it adds the session's default
+/// headers and credentials and keeps cookies in the session's jar.
+///
+/// It is usually added through [`SessionExt::with_session`].
+pub struct SessionMiddleware {
+    session: Session,
+    cookies: CookieMiddleware,
+}
+
+impl SessionMiddleware {
+    /// Construct `SessionMiddleware` acting as `session`.
+    pub fn new(session: Session) -> Self {
+        Self {
+            cookies: CookieMiddleware::new(session.cookies.clone()),
+            session,
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for SessionMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let (headers, auth) = {
+            let state = self.session.state.lock().expect("session lock poisoned");
+            (state.headers.clone(), state.auth.clone())
+        };
+        for (name, value) in headers.iter() {
+            if !req.headers().contains_key(name) {
+                req.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
+        if !req.headers().contains_key(AUTHORIZATION) {
+            if let Some(value) = auth.as_ref().and_then(SessionAuth::header_value) {
+                req.headers_mut().insert(AUTHORIZATION, value);
+            }
+        }
+        self.cookies.handle(req, extensions, next).await
+    }
+}
+
+/// Extension trait deriving clients which act as a [`Session`].
+pub trait SessionExt {
+    /// Returns a client running the same middleware as `self` followed by a
+    /// [`SessionMiddleware`] for `session`.
+    fn with_session(&self, session: &Session) -> ClientWithMiddleware;
+}
+
+impl SessionExt for ClientWithMiddleware {
+    fn with_session(&self, session: &Session) -> ClientWithMiddleware {
+        ClientBuilder::from_client(self.clone())
+            .with(SessionMiddleware::new(session.clone()))
+            .build()
+    }
+}
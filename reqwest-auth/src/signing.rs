@@ -0,0 +1,206 @@
+//! `HmacSigningMiddleware` signs requests with a shared secret.
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use http::{Extensions, HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use ring::{digest, hmac};
+use thiserror::Error;
+
+use crate::hex;
+use crate::secret::Secret;
+
+/// Error returned by [`HmacSigningMiddleware`] for requests whose body is a stream, which can't
+/// be hashed before being sent.
+#[derive(Debug, Error)]
+#[error("Request bodies must be buffered to be signed")]
+pub struct UnsignableBody;
+
+/// A shared secret and the identifier the server knows it by.
+#[derive(Clone, Debug)]
+pub struct SigningKey {
+    id: String,
+    secret: Secret<Vec<u8>>,
+}
+
+impl SigningKey {
+    /// Construct `SigningKey` called `id`.
+    pub fn new(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            id: id.into(),
+            secret: Secret::new(secret.into()),
+        }
+    }
+
+    /// The identifier of the key.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn sign(&self, data: &[u8]) -> hmac::Tag {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.secret.expose_secret());
+        hmac::sign(&key, data)
+    }
+}
+
+/// The current [`SigningKey`] of a signing middleware, which can be rotated while the
+/// middleware is in use.
+///
+/// `SigningKeys` is a handle, clones share the same key:
+///
+/// ```
+/// use reqwest_auth::{HmacSigningMiddleware, SigningKey, SigningKeys};
+///
+/// let keys = SigningKeys::new(SigningKey::new("key-1", "first secret"));
+/// let signing = HmacSigningMiddleware::new(keys.clone());
+/// // Later on, once the server knows about the new key.
+/// keys.rotate(SigningKey::new("key-2", "second secret"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SigningKeys(Arc<RwLock<SigningKey>>);
+
+impl SigningKeys {
+    /// Construct `SigningKeys` starting with `key`.
+    pub fn new(key: SigningKey) -> Self {
+        Self(Arc::new(RwLock::new(key)))
+    }
+
+    /// Sign the following requests with `key`.
+    pub fn rotate(&self, key: SigningKey) {
+        *self.0.write().expect("signing keys lock poisoned") = key;
+    }
+
+    /// The key requests are currently signed with.
+    pub fn current(&self) -> SigningKey {
+        self.0.read().expect("signing keys lock poisoned").clone()
+    }
+}
+
+impl From<SigningKey> for SigningKeys {
+    fn from(key: SigningKey) -> Self {
+        Self::new(key)
+    }
+}
+
+/// `HmacSigningMiddleware` signs requests with an HMAC-SHA256 of their method, path, timestamp
+/// and body, for APIs authenticating clients with a shared secret.
+///
+/// The signed string is made of the following lines, joined with `\n`:
+/// * the request method, e.g. `POST`;
+/// * the path and query of the URL, e.g. `/v1/payments?dry_run=true`;
+/// * the timestamp, in seconds since the Unix epoch;
+/// * the lowercase hexadecimal SHA-256 of the body, empty bodies included.
+///
+/// Three headers are added: the timestamp (`X-Timestamp` by default), the identifier of the
+/// [`SigningKey`] (`X-Key-Id`) and the base64 encoded signature (`X-Signature`). Streaming
+/// bodies can't be hashed ahead of sending them and fail with [`UnsignableBody`].
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{HmacSigningMiddleware, SigningKey};
+///
+/// let signing = HmacSigningMiddleware::new(SigningKey::new("client-1", "shared secret"))
+///     .with_signature_header(http::HeaderName::from_static("x-hmac"));
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(signing).build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct HmacSigningMiddleware {
+    keys: SigningKeys,
+    signature_header: HeaderName,
+    timestamp_header: HeaderName,
+    key_id_header: HeaderName,
+}
+
+impl HmacSigningMiddleware {
+    /// Construct `HmacSigningMiddleware` signing requests with `keys`.
+    pub fn new(keys: impl Into<SigningKeys>) -> Self {
+        Self {
+            keys: keys.into(),
+            signature_header: HeaderName::from_static("x-signature"),
+            timestamp_header: HeaderName::from_static("x-timestamp"),
+            key_id_header: HeaderName::from_static("x-key-id"),
+        }
+    }
+
+    /// Set the header holding the signature.
+    pub fn with_signature_header(mut self, name: HeaderName) -> Self {
+        self.signature_header = name;
+        self
+    }
+
+    /// Set the header holding the timestamp.
+    pub fn with_timestamp_header(mut self, name: HeaderName) -> Self {
+        self.timestamp_header = name;
+        self
+    }
+
+    /// Set the header holding the key identifier.
+    pub fn with_key_id_header(mut self, name: HeaderName) -> Self {
+        self.key_id_header = name;
+        self
+    }
+
+    /// The keys requests are signed with, to rotate them.
+    pub fn keys(&self) -> SigningKeys {
+        self.keys.clone()
+    }
+
+    /// Signs `req` as of `time`.
+    pub fn sign(&self, req: &mut Request, time: SystemTime) -> Result<()> {
+        let body_hash = match req.body().map(|body| body.as_bytes()) {
+            None => digest::digest(&digest::SHA256, b""),
+            Some(Some(body)) => digest::digest(&digest::SHA256, body),
+            Some(None) => return Err(Error::middleware(UnsignableBody)),
+        };
+        let timestamp = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+            .to_string();
+        let path = match req.url().query() {
+            Some(query) => format!("{}?{}", req.url().path(), query),
+            None => req.url().path().to_owned(),
+        };
+        let canonical = format!(
+            "{}\n{}\n{}\n{}",
+            req.method(),
+            path,
+            timestamp,
+            hex(body_hash.as_ref())
+        );
+
+        let key = self.keys.current();
+        let signature =
+            base64::engine::general_purpose::STANDARD.encode(key.sign(canonical.as_bytes()));
+        let headers = req.headers_mut();
+        headers.insert(
+            self.timestamp_header.clone(),
+            HeaderValue::from_str(&timestamp).expect("timestamps are valid headers"),
+        );
+        headers.insert(
+            self.key_id_header.clone(),
+            HeaderValue::from_str(key.id()).map_err(Error::middleware)?,
+        );
+        headers.insert(
+            self.signature_header.clone(),
+            HeaderValue::from_str(&signature).expect("base64 is a valid header"),
+        );
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for HmacSigningMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        self.sign(&mut req, SystemTime::now())?;
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,335 @@
+//! `SigV4Middleware` signs requests with AWS Signature Version 4.
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use http::{Extensions, HeaderValue};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use ring::{digest, hmac};
+
+use crate::hex;
+use crate::secret::Secret;
+
+/// Characters encoded by SigV4's `UriEncode`: everything but unreserved characters.
+const URI_ENCODE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// AWS credentials used by [`SigV4Middleware`].
+#[derive(Clone, Debug)]
+pub struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: Secret<String>,
+    session_token: Option<Secret<String>>,
+}
+
+impl AwsCredentials {
+    /// Construct long-term `AwsCredentials`.
+    pub fn new(access_key_id: impl Into<String>, secret_access_key: impl Into<String>) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: Secret::new(secret_access_key.into()),
+            session_token: None,
+        }
+    }
+
+    /// Set the session token of temporary credentials, sent in the `X-Amz-Security-Token`
+    /// header.
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.session_token = Some(Secret::new(session_token.into()));
+        self
+    }
+}
+
+/// A source of [`AwsCredentials`] for [`SigV4Middleware`].
+///
+/// `AwsCredentials` are a provider of themselves, for long-term credentials. Temporary
+/// credentials, such as those of an instance profile, can be obtained and renewed by
+/// implementing this trait. The provider is asked for credentials for every request, so
+/// implementations should cache them.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait AwsCredentialsProvider: 'static + Send + Sync {
+    /// Returns the credentials to sign the next request with.
+    async fn credentials(&self) -> anyhow::Result<AwsCredentials>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl AwsCredentialsProvider for AwsCredentials {
+    async fn credentials(&self) -> anyhow::Result<AwsCredentials> {
+        Ok(self.clone())
+    }
+}
+
+/// `SigV4Middleware` signs requests with [AWS Signature Version
+/// 4](https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_aws-signing.html), so that
+/// calls to AWS services such as S3 or API Gateway can go through a middleware client.
+///
+/// The `Host` and `Content-Type` headers and all `X-Amz-*` headers are signed. Buffered bodies
+/// are hashed; streaming bodies can't be read ahead of sending them and are signed as
+/// `UNSIGNED-PAYLOAD`, which S3 accepts but most other services don't. For S3 the payload hash
+/// is also sent in the `X-Amz-Content-Sha256` header, as the service requires.
+///
+/// Signatures are only valid for a few minutes, so add this middleware after any retry
+/// middleware to sign each attempt anew. Requests which already have an `Authorization` header
+/// are left untouched.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{AwsCredentials, SigV4Middleware};
+///
+/// let credentials = AwsCredentials::new("AKIDEXAMPLE", "secret-access-key");
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(SigV4Middleware::new(credentials, "eu-west-1", "s3"))
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct SigV4Middleware {
+    credentials: Arc<dyn AwsCredentialsProvider>,
+    region: String,
+    service: String,
+    unsigned_payload: bool,
+}
+
+impl SigV4Middleware {
+    /// Construct `SigV4Middleware` signing requests to `service` in `region` with the
+    /// credentials of `credentials`.
+    pub fn new(
+        credentials: impl AwsCredentialsProvider,
+        region: impl Into<String>,
+        service: impl Into<String>,
+    ) -> Self {
+        Self::new_with_arc(Arc::new(credentials), region, service)
+    }
+
+    /// Construct `SigV4Middleware` with a shared credentials provider.
+    pub fn new_with_arc(
+        credentials: Arc<dyn AwsCredentialsProvider>,
+        region: impl Into<String>,
+        service: impl Into<String>,
+    ) -> Self {
+        Self {
+            credentials,
+            region: region.into(),
+            service: service.into(),
+            unsigned_payload: false,
+        }
+    }
+
+    /// Sign all payloads as `UNSIGNED-PAYLOAD` instead of hashing them, which saves hashing
+    /// large uploads to S3.
+    pub fn with_unsigned_payload(mut self) -> Self {
+        self.unsigned_payload = true;
+        self
+    }
+
+    /// Signs `req` as of `time` with credentials from the provider, adding the `Authorization`,
+    /// `X-Amz-Date` and, as needed, `X-Amz-Security-Token` and `X-Amz-Content-Sha256` headers.
+    pub async fn sign(&self, req: &mut Request, time: SystemTime) -> Result<()> {
+        let credentials = self.credentials.credentials().await?;
+        let timestamp = format_timestamp(time);
+        let date = &timestamp[..8];
+
+        let payload_hash = match req.body().map(|body| body.as_bytes()) {
+            _ if self.unsigned_payload => UNSIGNED_PAYLOAD.to_owned(),
+            None => hex_sha256(b""),
+            Some(Some(body)) => hex_sha256(body),
+            Some(None) => UNSIGNED_PAYLOAD.to_owned(),
+        };
+
+        let headers = req.headers_mut();
+        headers.insert("x-amz-date", header_value(&timestamp)?);
+        if let Some(token) = &credentials.session_token {
+            let mut token = header_value(token.expose_secret())?;
+            token.set_sensitive(true);
+            headers.insert("x-amz-security-token", token);
+        }
+        if self.service == "s3" {
+            headers.insert("x-amz-content-sha256", header_value(&payload_hash)?);
+        }
+
+        let (canonical_request, signed_headers) = self.canonical_request(req, &payload_hash);
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex_sha256(canonical_request.as_bytes())
+        );
+
+        let secret = format!("AWS4{}", credentials.secret_access_key.expose_secret());
+        let key = [date, &self.region, &self.service, "aws4_request"]
+            .iter()
+            .fold(secret.into_bytes(), |key, part| {
+                hmac_sha256(&key, part.as_bytes())
+            });
+        let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let mut authorization = header_value(&format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ))?;
+        authorization.set_sensitive(true);
+        req.headers_mut().insert(AUTHORIZATION, authorization);
+        Ok(())
+    }
+
+    /// Builds the canonical request of `req`, returning it along with the list of signed
+    /// headers.
+    fn canonical_request(&self, req: &Request, payload_hash: &str) -> (String, String) {
+        let url = req.url();
+
+        // S3 object keys are encoded once, other services encode each path segment twice.
+        let path = url
+            .path()
+            .split('/')
+            .map(|segment| {
+                let decoded = percent_decode_str(segment).decode_utf8_lossy();
+                let encoded = utf8_percent_encode(&decoded, URI_ENCODE).to_string();
+                if self.service == "s3" {
+                    encoded
+                } else {
+                    utf8_percent_encode(&encoded, URI_ENCODE).to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let mut query: Vec<(String, String)> = url
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let encode = |s: &str| {
+                    utf8_percent_encode(&percent_decode_str(s).decode_utf8_lossy(), URI_ENCODE)
+                        .to_string()
+                };
+                (encode(key), encode(value))
+            })
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_owned(),
+        };
+        let mut headers: Vec<(String, String)> = vec![("host".to_owned(), host)];
+        for (name, value) in req.headers() {
+            if *name == CONTENT_TYPE || name.as_str().starts_with("x-amz-") {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                // Sequential spaces are collapsed, as are those of multiple values.
+                let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                match headers.iter_mut().find(|(n, _)| n == name.as_str()) {
+                    Some((_, values)) => {
+                        values.push(',');
+                        values.push_str(&value);
+                    }
+                    None => headers.push((name.as_str().to_owned(), value)),
+                }
+            }
+        }
+        headers.sort();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            req.method(),
+            path,
+            query,
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+        (canonical_request, signed_headers)
+    }
+}
+
+impl fmt::Debug for SigV4Middleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigV4Middleware")
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .field("unsigned_payload", &self.unsigned_payload)
+            .finish()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for SigV4Middleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !req.headers().contains_key(AUTHORIZATION) {
+            self.sign(&mut req, SystemTime::now()).await?;
+        }
+        next.run(req, extensions).await
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(value).map_err(Error::middleware)
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, data).as_ref().to_vec()
+}
+
+/// Formats `time` as an ISO 8601 basic format timestamp, e.g. `20150830T123600Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,248 @@
+//! `BearerAuthMiddleware` authenticates requests with tokens from a [`TokenProvider`].
+use std::sync::Arc;
+
+use anyhow::Context;
+use base64::Engine;
+use http::header::AUTHORIZATION;
+use http::{Extensions, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+
+use crate::secret::Secret;
+
+/// A source of bearer tokens for [`BearerAuthMiddleware`], or passwords for
+/// [`BasicAuthMiddleware`].
+///
+/// [`StaticToken`], [`EnvToken`] and [`FileToken`] are provided; other sources, such as a
+/// secrets manager or an identity provider, can be plugged in by implementing this trait. The
+/// provider is asked for a token for every request, so implementations should cache tokens
+/// which are expensive to obtain.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait TokenProvider: 'static + Send + Sync {
+    /// Returns the token to send with the next request.
+    async fn token(&self) -> anyhow::Result<Secret<String>>;
+
+    /// Discards any cached token, after the server rejected it, so that the next call to
+    /// [`token`](Self::token) obtains a fresh one. Does nothing by default.
+    ///
+    /// See [`AuthRetryMiddleware`](crate::AuthRetryMiddleware).
+    fn invalidate(&self) {}
+}
+
+/// A [`TokenProvider`] always returning the same token.
+#[derive(Clone, Debug)]
+pub struct StaticToken(Secret<String>);
+
+impl StaticToken {
+    /// Construct `StaticToken` returning `token`.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(Secret::new(token.into()))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenProvider for StaticToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A [`TokenProvider`] reading the token from an environment variable for every request, so
+/// that changes to the variable are picked up.
+#[derive(Clone, Debug)]
+pub struct EnvToken {
+    var: String,
+}
+
+impl EnvToken {
+    /// Construct `EnvToken` reading the environment variable `var`.
+    pub fn new(var: impl Into<String>) -> Self {
+        Self { var: var.into() }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenProvider for EnvToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        let token = std::env::var(&self.var)
+            .with_context(|| format!("Failed to read token from ${}", self.var))?;
+        Ok(Secret::new(token))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::FileToken;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file {
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+    use std::time::SystemTime;
+
+    use anyhow::Context;
+
+    use super::TokenProvider;
+    use crate::secret::Secret;
+
+    /// A [`TokenProvider`] reading the token from a file, which is read again whenever it is
+    /// modified.
+    ///
+    /// This suits tokens rotated on disk by another process, like Kubernetes projected service
+    /// account tokens. Leading and trailing whitespace is trimmed.
+    #[derive(Debug)]
+    pub struct FileToken {
+        path: PathBuf,
+        cached: Mutex<Option<(SystemTime, Secret<String>)>>,
+    }
+
+    impl FileToken {
+        /// Construct `FileToken` reading the file at `path`.
+        pub fn new(path: impl Into<PathBuf>) -> Self {
+            Self {
+                path: path.into(),
+                cached: Mutex::new(None),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TokenProvider for FileToken {
+        async fn token(&self) -> anyhow::Result<Secret<String>> {
+            let context = || format!("Failed to read token from {}", self.path.display());
+            let modified = tokio::fs::metadata(&self.path)
+                .await
+                .and_then(|metadata| metadata.modified())
+                .with_context(context)?;
+            if let Some((read_at, token)) = &*self.cached.lock().expect("token lock poisoned") {
+                if *read_at == modified {
+                    return Ok(token.clone());
+                }
+            }
+            let token = tokio::fs::read_to_string(&self.path)
+                .await
+                .with_context(context)?;
+            let token = Secret::new(token.trim().to_owned());
+            *self.cached.lock().expect("token lock poisoned") = Some((modified, token.clone()));
+            Ok(token)
+        }
+
+        fn invalidate(&self) {
+            *self.cached.lock().expect("token lock poisoned") = None;
+        }
+    }
+}
+
+/// `BearerAuthMiddleware` adds `Authorization: Bearer` headers with tokens from a
+/// [`TokenProvider`].
+///
+/// Requests which already have an `Authorization` header are left untouched. Errors of the
+/// provider fail the request.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{BearerAuthMiddleware, FileToken};
+///
+/// let token = FileToken::new("/var/run/secrets/tokens/api-token");
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(BearerAuthMiddleware::new(token))
+///     .build();
+/// ```
+pub struct BearerAuthMiddleware {
+    provider: Arc<dyn TokenProvider>,
+}
+
+impl BearerAuthMiddleware {
+    /// Construct `BearerAuthMiddleware` taking tokens from `provider`.
+    pub fn new<P: TokenProvider>(provider: P) -> Self {
+        Self::new_with_arc(Arc::new(provider))
+    }
+
+    /// Construct `BearerAuthMiddleware` taking tokens from a shared `provider`.
+    pub fn new_with_arc(provider: Arc<dyn TokenProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for BearerAuthMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !req.headers().contains_key(AUTHORIZATION) {
+            let token = self.provider.token().await?;
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token.expose_secret()))
+                .map_err(Error::middleware)?;
+            value.set_sensitive(true);
+            req.headers_mut().insert(AUTHORIZATION, value);
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// `BasicAuthMiddleware` adds HTTP Basic `Authorization` headers, with the password of a user
+/// from a [`TokenProvider`].
+///
+/// Requests which already have an `Authorization` header are left untouched. Errors of the
+/// provider fail the request.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_auth::{BasicAuthMiddleware, EnvToken};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(BasicAuthMiddleware::new("alice", EnvToken::new("API_PASSWORD")))
+///     .build();
+/// ```
+pub struct BasicAuthMiddleware {
+    username: String,
+    provider: Arc<dyn TokenProvider>,
+}
+
+impl BasicAuthMiddleware {
+    /// Construct `BasicAuthMiddleware` authenticating as `username` with passwords from
+    /// `provider`.
+    pub fn new<P: TokenProvider>(username: impl Into<String>, provider: P) -> Self {
+        Self::new_with_arc(username, Arc::new(provider))
+    }
+
+    /// Construct `BasicAuthMiddleware` authenticating as `username` with passwords from a shared
+    /// `provider`.
+    pub fn new_with_arc(username: impl Into<String>, provider: Arc<dyn TokenProvider>) -> Self {
+        Self {
+            username: username.into(),
+            provider,
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for BasicAuthMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !req.headers().contains_key(AUTHORIZATION) {
+            let password = self.provider.token().await?;
+            let credentials =
+                Secret::new(format!("{}:{}", self.username, password.expose_secret()));
+            let mut value = HeaderValue::from_str(&format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(credentials.expose_secret())
+            ))
+            .map_err(Error::middleware)?;
+            value.set_sensitive(true);
+            req.headers_mut().insert(AUTHORIZATION, value);
+        }
+        next.run(req, extensions).await
+    }
+}
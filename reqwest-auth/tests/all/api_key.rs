@@ -0,0 +1,70 @@
+use http::HeaderName;
+use reqwest::Client;
+use reqwest_auth::{ApiKeyPlacement, ApiKeys};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, method, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn keys() -> ApiKeys {
+    ApiKeys::new()
+        .with_key("*.example.com", "wildcard", ApiKeyPlacement::Bearer)
+        .with_key(
+            "api.example.com",
+            "exact",
+            ApiKeyPlacement::Header(HeaderName::from_static("x-api-key")),
+        )
+        .with_key("*.eu.example.com", "eu", ApiKeyPlacement::Bearer)
+        .with_key("127.0.0.1", "local", ApiKeyPlacement::Query("key".into()))
+}
+
+#[test]
+fn assert_most_specific_key_is_chosen() {
+    let keys = keys();
+    assert_eq!(keys.key("API.example.com").unwrap().0, "exact");
+    assert_eq!(keys.key("www.example.com").unwrap().0, "wildcard");
+    assert_eq!(keys.key("a.eu.example.com").unwrap().0, "eu");
+    assert_eq!(keys.key("example.com"), None);
+    assert_eq!(keys.key("notexample.com"), None);
+    assert_eq!(keys.key("api.example.com.evil.test"), None);
+}
+
+#[tokio::test]
+async fn assert_key_is_added_for_its_host_only() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(query_param("key", "local"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new()).with_init(keys()).build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    // `localhost` has no key.
+    let uri = server.uri().replace("127.0.0.1", "localhost");
+    let req = client.get(uri).build().unwrap();
+    assert_eq!(req.url().query(), None);
+    assert!(req.headers().get("authorization").is_none());
+}
+
+#[tokio::test]
+async fn assert_header_key_is_added() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("x-api-key", "s3cr3t"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let keys = ApiKeys::new().with_key(
+        "127.0.0.1",
+        "s3cr3t",
+        ApiKeyPlacement::Header(HeaderName::from_static("x-api-key")),
+    );
+    let client = ClientBuilder::new(Client::new()).with_init(keys).build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
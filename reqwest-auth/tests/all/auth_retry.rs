@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest_auth::{
+    AuthRetryMiddleware, BearerAuthMiddleware, DisableAuthRetry, Secret, TokenProvider,
+};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Returns `stale` until invalidated, and `fresh` afterwards.
+#[derive(Default)]
+struct RotatingToken {
+    invalidated: AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for RotatingToken {
+    async fn token(&self) -> anyhow::Result<Secret<String>> {
+        Ok(match self.invalidated.load(Ordering::SeqCst) {
+            false => "stale".into(),
+            true => "fresh".into(),
+        })
+    }
+
+    fn invalidate(&self) {
+        self.invalidated.store(true, Ordering::SeqCst);
+    }
+}
+
+fn client(provider: Arc<RotatingToken>) -> reqwest_middleware::ClientWithMiddleware {
+    ClientBuilder::new(Client::new())
+        .with(AuthRetryMiddleware::new_with_arc(provider.clone()))
+        .with(BearerAuthMiddleware::new_with_arc(provider))
+        .build()
+}
+
+#[tokio::test]
+async fn assert_rejected_token_is_refreshed_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("authorization", "Bearer stale"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(header("authorization", "Bearer fresh"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let res = client(Arc::default())
+        .get(server.uri())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn assert_request_is_replayed_at_most_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let res = client(Arc::default())
+        .get(server.uri())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn assert_retry_can_be_disabled_per_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let provider = Arc::new(RotatingToken::default());
+    let res = client(provider.clone())
+        .get(server.uri())
+        .with_extension(DisableAuthRetry)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    assert!(!provider.invalidated.load(Ordering::SeqCst));
+}
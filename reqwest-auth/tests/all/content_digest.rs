@@ -0,0 +1,78 @@
+use reqwest::Client;
+use reqwest_auth::{
+    ContentDigestMiddleware, DigestAlgorithm, DigestMismatch, MissingDigest, VerifyDigestMiddleware,
+};
+use reqwest_middleware::{ClientBuilder, Error};
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const HELLO_SHA256: &str = "sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:";
+
+#[tokio::test]
+async fn assert_request_digest_is_added() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header("content-digest", HELLO_SHA256))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(ContentDigestMiddleware::new(DigestAlgorithm::Sha256))
+        .build();
+    let res = client
+        .post(server.uri())
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn assert_response_digest_is_verified() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-digest", HELLO_SHA256)
+                .set_body_string("hello"),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("repr-digest", HELLO_SHA256)
+                .set_body_string("tampered"),
+        )
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(VerifyDigestMiddleware::new())
+        .build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "hello");
+
+    let err = client.get(server.uri()).send().await.unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<DigestMismatch>()));
+}
+
+#[tokio::test]
+async fn assert_digest_can_be_required() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(VerifyDigestMiddleware::new().require_digest())
+        .build();
+    let err = client.get(server.uri()).send().await.unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<MissingDigest>()));
+}
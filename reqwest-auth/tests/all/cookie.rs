@@ -0,0 +1,101 @@
+use std::time::SystemTime;
+
+use reqwest::{Client, Url};
+use reqwest_auth::{Cookie, CookieMiddleware, CookieStoreOverride, MemoryCookieStore};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_cookies_are_sent_back() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .append_header("Set-Cookie", "session=abc; Path=/")
+                .append_header("Set-Cookie", "theme=dark; Path=/settings"),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/inbox"))
+        .and(header("cookie", "session=abc"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let cookies = MemoryCookieStore::new();
+    let client = ClientBuilder::new(Client::new())
+        .with(CookieMiddleware::new(cookies.clone()))
+        .build();
+
+    client
+        .post(format!("{}/login", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(cookies.jar().len(), 2);
+    let res = client
+        .get(format!("{}/inbox", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn assert_override_store_is_used() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).append_header("Set-Cookie", "user=bob"))
+        .mount(&server)
+        .await;
+
+    let default_store = MemoryCookieStore::new();
+    let bob = MemoryCookieStore::new();
+    let client = ClientBuilder::new(Client::new())
+        .with(CookieMiddleware::new(default_store.clone()))
+        .build();
+
+    client
+        .get(server.uri())
+        .with_extension(CookieStoreOverride::new(bob.clone()))
+        .send()
+        .await
+        .unwrap();
+    assert!(default_store.jar().is_empty());
+    assert_eq!(bob.jar().len(), 1);
+}
+
+#[test]
+fn assert_domain_path_and_secure_matching() {
+    let url = Url::parse("https://www.example.com/account/login").unwrap();
+
+    let cookie = Cookie::parse("id=1; Domain=.example.com", &url).unwrap();
+    assert!(cookie.matches(&Url::parse("https://api.example.com/account/").unwrap()));
+    assert!(!cookie.matches(&Url::parse("https://example.org/account/").unwrap()));
+
+    // Without a Path attribute the cookie applies to the directory of the request.
+    assert!(!cookie.matches(&Url::parse("https://www.example.com/").unwrap()));
+    assert!(!cookie.matches(&Url::parse("https://www.example.com/accounts").unwrap()));
+
+    let cookie = Cookie::parse("id=1; Path=/; Secure", &url).unwrap();
+    assert!(!cookie.matches(&Url::parse("http://www.example.com/").unwrap()));
+    assert!(!cookie.matches(&Url::parse("https://api.example.com/").unwrap()));
+
+    assert!(Cookie::parse("id=1; Domain=other.com", &url).is_none());
+    assert!(Cookie::parse("id=1; Domain=com", &url).is_none());
+}
+
+#[test]
+fn assert_huge_max_age_doesnt_overflow() {
+    let url = Url::parse("https://www.example.com/").unwrap();
+
+    let cookie = Cookie::parse("id=1; Max-Age=9223372036854775807", &url).unwrap();
+    assert!(!cookie.is_expired(SystemTime::now()));
+
+    let cookie = Cookie::parse("id=1; Max-Age=0", &url).unwrap();
+    assert!(cookie.is_expired(SystemTime::now()));
+}
@@ -0,0 +1,108 @@
+use http::StatusCode;
+use reqwest::Client;
+use reqwest_auth::{CsrfMiddleware, CsrfSource, CsrfTarget};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{body_string, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn csrf(server: &MockServer) -> CsrfMiddleware {
+    CsrfMiddleware::new(CsrfSource::Header("x-csrf-token".parse().unwrap()))
+        .with_token_url(format!("{}/token", server.uri()).parse().unwrap())
+}
+
+#[tokio::test]
+async fn assert_token_is_fetched_once_and_injected() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).insert_header("x-csrf-token", "t1"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/submit"))
+        .and(header("x-csrf-token", "t1"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(csrf(&server))
+        .build();
+    for _ in 0..2 {
+        let res = client
+            .post(format!("{}/submit", server.uri()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn assert_expired_token_is_refreshed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).insert_header("x-csrf-token", "old"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).insert_header("x-csrf-token", "new"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(header("x-csrf-token", "old"))
+        .respond_with(ResponseTemplate::new(419))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(header("x-csrf-token", "new"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(csrf(&server))
+        .build();
+    let res = client
+        .post(format!("{}/submit", server.uri()))
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn assert_token_is_added_to_forms() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).insert_header("x-csrf-token", "a b"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_string("name=alice&_token=a+b"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(csrf(&server).with_target(CsrfTarget::FormField("_token".into())))
+        .build();
+    let res = client
+        .post(format!("{}/submit", server.uri()))
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("name=alice")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,62 @@
+use reqwest::{Client, StatusCode};
+use reqwest_auth::DigestAuthMiddleware;
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header_regex, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CHALLENGE: &str = r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=SHA-256, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+
+async fn server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401).insert_header("www-authenticate", CHALLENGE))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn assert_challenge_is_answered_and_remembered() {
+    let server = server().await;
+    for nc in ["00000001", "00000002"] {
+        Mock::given(method("GET"))
+            .and(header_regex(
+                "authorization",
+                &format!(
+                    r#"^Digest username="Mufasa", realm="http-auth@example.org", .*algorithm=SHA-256, response="[0-9a-f]{{64}}", qop=auth, nc={}, cnonce="[0-9a-f]+", opaque="FQhe"#,
+                    nc
+                ),
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let client = ClientBuilder::new(Client::new())
+        .with(DigestAuthMiddleware::new("Mufasa", "Circle of Life"))
+        .build();
+    for _ in 0..2 {
+        let res = client
+            .get(format!("{}/dir/index.html", server.uri()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    // The second request was authorized upfront.
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn assert_rejected_credentials_are_not_retried_forever() {
+    let server = server().await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(DigestAuthMiddleware::new("Mufasa", "wrong"))
+        .build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
@@ -0,0 +1,32 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use reqwest::{Method, Request};
+use reqwest_auth::{HawkMiddleware, SigningKey};
+
+// The example of the Hawk specification.
+#[test]
+fn assert_authorization_matches_specification() {
+    let key = SigningKey::new(
+        "dh37fgj492je",
+        "werxhqb98rpaxn39848xrunpaw3489ruxnpa98w4rxn",
+    );
+    let hawk = HawkMiddleware::new(key).with_ext("some-app-ext-data");
+    let time = UNIX_EPOCH + Duration::from_secs(1_353_832_234);
+    let url = "http://example.com:8000/resource/1?b=1&a=2";
+
+    let mut req = Request::new(Method::GET, url.parse().unwrap());
+    hawk.sign(&mut req, time, "j4h3g2").unwrap();
+    assert_eq!(
+        req.headers()["authorization"],
+        r#"Hawk id="dh37fgj492je", ts="1353832234", nonce="j4h3g2", ext="some-app-ext-data", mac="6R4rV5iE+NPoym+WwjeHzjAGXUtLNIxmo1vpMofpLAE=""#
+    );
+
+    let mut req = Request::new(Method::POST, url.parse().unwrap());
+    req.headers_mut()
+        .insert("content-type", "text/plain".parse().unwrap());
+    *req.body_mut() = Some("Thank you for flying Hawk".into());
+    hawk.sign(&mut req, time, "j4h3g2").unwrap();
+    let authorization = req.headers()["authorization"].to_str().unwrap();
+    assert!(authorization.contains(r#"hash="Yi9LfIIFRtBEPt74PVmbTF/xVAwPn7ub15ePICfgnuY=""#));
+    assert!(authorization.ends_with(r#"mac="aSe1DERmZuRl3pI36/9BdZmnErTw3sNzOOAUlfeKjVw=""#));
+}
@@ -0,0 +1,24 @@
+mod api_key;
+mod auth_retry;
+#[cfg(feature = "content-digest")]
+mod content_digest;
+mod cookie;
+mod csrf;
+#[cfg(feature = "digest-auth")]
+mod digest_auth;
+#[cfg(feature = "github")]
+mod github;
+#[cfg(feature = "hmac")]
+mod hawk;
+#[cfg(feature = "cloud-metadata")]
+mod metadata;
+mod netrc;
+mod nonce;
+mod oauth2;
+mod registry;
+mod session;
+#[cfg(feature = "hmac")]
+mod signing;
+#[cfg(feature = "sigv4")]
+mod sigv4;
+mod token;
@@ -0,0 +1,97 @@
+use reqwest::{Client, StatusCode};
+use reqwest_auth::{BearerAuthMiddleware, GcpMetadataToken};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_gcp_token_is_cached() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/computeMetadata/v1/instance/service-accounts/default/token",
+        ))
+        .and(query_param("scopes", "a,b"))
+        .and(header("metadata-flavor", "Google"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"access_token":"ya29.token","expires_in":3599,"token_type":"Bearer"}"#,
+            "application/json",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api"))
+        .and(header("authorization", "Bearer ya29.token"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let token = GcpMetadataToken::new(Client::new())
+        .with_scopes(["a", "b"])
+        .with_endpoint(server.uri().parse().unwrap());
+    let client = ClientBuilder::new(Client::new())
+        .with(BearerAuthMiddleware::new(token))
+        .build();
+    for _ in 0..2 {
+        let res = client
+            .get(format!("{}/api", server.uri()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
+
+#[cfg(feature = "sigv4")]
+#[tokio::test]
+async fn assert_imds_credentials_use_session_token() {
+    use reqwest_auth::{AwsCredentialsProvider, ImdsCredentials, SigV4Middleware};
+
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/latest/api/token"))
+        .and(header("x-aws-ec2-metadata-token-ttl-seconds", "21600"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("session-token"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/latest/meta-data/iam/security-credentials/"))
+        .and(header("x-aws-ec2-metadata-token", "session-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("my-role"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/latest/meta-data/iam/security-credentials/my-role"))
+        .and(header("x-aws-ec2-metadata-token", "session-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"Code":"Success","Type":"AWS-HMAC","AccessKeyId":"ASIAEXAMPLE","SecretAccessKey":"secret","Token":"role-session","Expiration":"2099-01-01T00:00:00Z"}"#,
+            "application/json",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let credentials =
+        ImdsCredentials::new(Client::new()).with_endpoint(server.uri().parse().unwrap());
+    for _ in 0..2 {
+        credentials.credentials().await.unwrap();
+    }
+    let signer = SigV4Middleware::new(credentials, "eu-west-1", "s3");
+    let mut req = reqwest::Request::new(
+        reqwest::Method::GET,
+        "https://bucket.s3.amazonaws.com/key".parse().unwrap(),
+    );
+    signer
+        .sign(&mut req, std::time::SystemTime::now())
+        .await
+        .unwrap();
+    assert_eq!(req.headers()["x-amz-security-token"], "role-session");
+    assert!(req.headers()["authorization"]
+        .to_str()
+        .unwrap()
+        .contains("Credential=ASIAEXAMPLE/"));
+}
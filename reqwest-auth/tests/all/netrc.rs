@@ -0,0 +1,67 @@
+use reqwest::{Client, StatusCode};
+use reqwest_auth::Netrc;
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{basic_auth, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const NETRC: &str = r#"# Personal credentials
+machine api.example.com login alice password "s3cret pass"
+machine ftp.example.com
+    login bob
+    account ignored
+    password b#b  # not a comment
+macdef init
+cd /pub
+machine macro.example.com login nobody
+
+machine "Quoted.Example.com" login "c\"arol" password "tab\there"
+default login anonymous password guest@example.com
+machine late.example.com login late password late
+"#;
+
+#[test]
+fn assert_netrc_quirks_are_parsed() {
+    let netrc = Netrc::parse(NETRC);
+    assert_eq!(
+        netrc.credentials("api.example.com"),
+        Some(("alice", Some("s3cret pass")))
+    );
+    assert_eq!(
+        netrc.credentials("ftp.example.com"),
+        Some(("bob", Some("b#b")))
+    );
+    assert_eq!(
+        netrc.credentials("quoted.example.com"),
+        Some(("c\"arol", Some("tab\there")))
+    );
+    // Lines up to the end of a macro are part of it.
+    assert_eq!(
+        netrc.credentials("macro.example.com"),
+        Some(("anonymous", Some("guest@example.com")))
+    );
+    assert_eq!(
+        netrc.credentials("late.example.com"),
+        Some(("late", Some("late")))
+    );
+    assert_eq!(
+        netrc.credentials("other.example.com"),
+        Some(("anonymous", Some("guest@example.com")))
+    );
+    assert_eq!(Netrc::parse("machine a login b").credentials("c"), None);
+}
+
+#[tokio::test]
+async fn assert_matching_host_gets_basic_auth() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(basic_auth("alice", "secret"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let netrc = Netrc::parse("machine 127.0.0.1 login alice password secret");
+    let client = ClientBuilder::new(Client::new()).with_init(netrc).build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,47 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest_auth::{MonotonicNonce, NonceMiddleware, NonceSource};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, header_exists, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn assert_monotonic_nonces_are_unique_across_threads() {
+    let source = Arc::new(MonotonicNonce::new());
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let source = source.clone();
+            std::thread::spawn(move || {
+                (0..1000)
+                    .map(|_| source.next().parse::<u64>().unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let mut seen = HashSet::new();
+    for thread in threads {
+        let nonces = thread.join().unwrap();
+        assert!(nonces.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(nonces.into_iter().all(|nonce| seen.insert(nonce)));
+    }
+}
+
+#[tokio::test]
+async fn assert_nonce_and_timestamp_are_added() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("x-nonce", "n-1"))
+        .and(header_exists("x-timestamp"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(NonceMiddleware::with_source(|| "n-1".to_owned()))
+        .build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
@@ -0,0 +1,105 @@
+use futures::future::join_all;
+use reqwest::{Client, StatusCode};
+use reqwest_auth::{OAuth2Middleware, TokenRequestFailed};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use wiremock::matchers::{body_string_contains, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> ClientWithMiddleware {
+    let oauth2 = OAuth2Middleware::new(
+        Client::new(),
+        format!("{}/token", server.uri()).parse().unwrap(),
+        "id",
+        "secret",
+    )
+    .with_scopes(["read", "write"]);
+    ClientBuilder::new(Client::new()).with(oauth2).build()
+}
+
+fn token(access_token: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_raw(
+        format!(
+            r#"{{"access_token":"{}","token_type":"Bearer","expires_in":3600}}"#,
+            access_token
+        ),
+        "application/json",
+    )
+}
+
+#[tokio::test]
+async fn assert_concurrent_requests_share_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("grant_type=client_credentials"))
+        .and(body_string_contains("scope=read+write"))
+        .respond_with(token("t1"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api"))
+        .and(header("authorization", "Bearer t1"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(100)
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let url = format!("{}/api", server.uri());
+    let responses = join_all((0..100).map(|_| client.get(&url).send())).await;
+    for res in responses {
+        assert_eq!(res.unwrap().status(), StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn assert_rejected_token_is_renewed_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(token("revoked"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(token("fresh"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(header("authorization", "Bearer fresh"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let res = client(&server)
+        .get(format!("{}/api", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn assert_token_errors_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(r#"{"error":"invalid_client"}"#))
+        .mount(&server)
+        .await;
+
+    let err = client(&server)
+        .get(format!("{}/api", server.uri()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<TokenRequestFailed>()));
+}
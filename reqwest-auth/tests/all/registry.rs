@@ -0,0 +1,52 @@
+use reqwest::{Client, StatusCode};
+use reqwest_auth::RegistryAuthMiddleware;
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, header_exists, method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_challenge_token_is_cached_per_repository() {
+    let server = MockServer::start().await;
+    let challenge = format!(
+        r#"Bearer realm="{}/token",service="registry.test",scope="repository:library/alpine:pull""#,
+        server.uri()
+    );
+    Mock::given(method("GET"))
+        .and(path_regex("^/v2/library/alpine/"))
+        .and(header("authorization", "Bearer t1"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex("^/v2/"))
+        .respond_with(ResponseTemplate::new(401).insert_header("www-authenticate", challenge))
+        .expect(1)
+        .with_priority(10)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/token"))
+        .and(query_param("service", "registry.test"))
+        .and(query_param("scope", "repository:library/alpine:pull"))
+        .and(header_exists("authorization"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(r#"{"token":"t1","expires_in":300}"#, "application/json"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(RegistryAuthMiddleware::new(Client::new()).with_credentials("user", "password"))
+        .build();
+    for endpoint in ["manifests/latest", "manifests/3.19", "blobs/sha256:abcd"] {
+        let res = client
+            .get(format!("{}/v2/library/alpine/{}", server.uri(), endpoint))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,78 @@
+use http::{HeaderName, HeaderValue};
+use reqwest::Client;
+use reqwest_auth::{Session, SessionAuth, SessionExt};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_sessions_are_independent() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .and(header("authorization", "Bearer alice-token"))
+        .respond_with(ResponseTemplate::new(200).append_header("Set-Cookie", "user=alice"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/inbox"))
+        .and(header("cookie", "user=alice"))
+        .and(header("x-tenant", "acme"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new()).build();
+    let alice = Session::new("alice");
+    alice.set_auth(Some(SessionAuth::Bearer("alice-token".into())));
+    alice.set_header(
+        HeaderName::from_static("x-tenant"),
+        HeaderValue::from_static("acme"),
+    );
+    let bob = Session::new("bob");
+
+    let alice_client = client.with_session(&alice);
+    alice_client
+        .post(format!("{}/login", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    let res = alice_client
+        .get(format!("{}/inbox", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    let res = client
+        .with_session(&bob)
+        .get(format!("{}/inbox", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 404);
+}
+
+#[test]
+fn assert_sessions_can_be_resumed() {
+    let session = Session::new("alice");
+    session.set_auth(Some(SessionAuth::Basic {
+        username: "alice".into(),
+        password: Some("hunter2".into()),
+    }));
+    session.set_header(
+        HeaderName::from_static("x-tenant"),
+        HeaderValue::from_static("acme"),
+    );
+    let url = "https://example.com/".parse().unwrap();
+    session
+        .cookies()
+        .insert(reqwest_auth::Cookie::parse("user=alice", &url).unwrap());
+
+    let resumed = Session::from_json(&session.to_json().unwrap()).unwrap();
+    assert_eq!(resumed.name(), "alice");
+    assert_eq!(resumed.auth(), session.auth());
+    assert_eq!(resumed.headers(), session.headers());
+    assert_eq!(resumed.cookies().jar(), session.cookies().jar());
+}
@@ -0,0 +1,38 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use reqwest::{Method, Request};
+use reqwest_auth::{HmacSigningMiddleware, SigningKey};
+
+#[test]
+fn assert_requests_are_signed_with_current_key() {
+    let signing = HmacSigningMiddleware::new(SigningKey::new("key-1", "secret"));
+    let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let request = || {
+        let mut req = Request::new(
+            Method::POST,
+            "https://api.example.com/v1/payments?dry_run=true"
+                .parse()
+                .unwrap(),
+        );
+        *req.body_mut() = Some("{}".into());
+        req
+    };
+
+    let mut req = request();
+    signing.sign(&mut req, time).unwrap();
+    assert_eq!(req.headers()["x-timestamp"], "1700000000");
+    assert_eq!(req.headers()["x-key-id"], "key-1");
+    assert_eq!(
+        req.headers()["x-signature"],
+        "QBXuASfzxBgoKKgtWo9Q9VKbUn8m6wqdZ+d9ut1wPO8="
+    );
+
+    signing.keys().rotate(SigningKey::new("key-2", "rotated"));
+    let mut req = request();
+    signing.sign(&mut req, time).unwrap();
+    assert_eq!(req.headers()["x-key-id"], "key-2");
+    assert_eq!(
+        req.headers()["x-signature"],
+        "Um0vis8suBJExqzMR3rTyWA0HhOcj3rEoP6ZjFCJXsI="
+    );
+}
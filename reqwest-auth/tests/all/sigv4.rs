@@ -0,0 +1,73 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::{Method, Request};
+use reqwest_auth::{AwsCredentials, SigV4Middleware};
+
+const ACCESS_KEY_ID: &str = "AKIDEXAMPLE";
+const SECRET_ACCESS_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+
+fn time() -> SystemTime {
+    // 2015-08-30T12:36:00Z, as in the AWS SigV4 test suite.
+    UNIX_EPOCH + Duration::from_secs(1_440_938_160)
+}
+
+fn authorization(req: &Request) -> &str {
+    req.headers()["authorization"].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn assert_signature_matches_aws_test_suite() {
+    let signer = SigV4Middleware::new(
+        AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY),
+        "us-east-1",
+        "service",
+    );
+
+    let mut req = Request::new(
+        Method::GET,
+        "https://example.amazonaws.com/".parse().unwrap(),
+    );
+    signer.sign(&mut req, time()).await.unwrap();
+    assert_eq!(req.headers()["x-amz-date"], "20150830T123600Z");
+    assert_eq!(
+        authorization(&req),
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+         SignedHeaders=host;x-amz-date, \
+         Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+
+    let mut req = Request::new(
+        Method::GET,
+        "https://example.amazonaws.com/?Param2=value2&Param1=value1"
+            .parse()
+            .unwrap(),
+    );
+    signer.sign(&mut req, time()).await.unwrap();
+    assert!(authorization(&req)
+        .ends_with("Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"));
+}
+
+#[tokio::test]
+async fn assert_s3_requests_carry_payload_hash_and_session_token() {
+    let credentials =
+        AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY).with_session_token("session");
+    let signer = SigV4Middleware::new(credentials, "eu-west-1", "s3");
+
+    let mut req = Request::new(
+        Method::PUT,
+        "https://bucket.s3.amazonaws.com/key".parse().unwrap(),
+    );
+    *req.body_mut() = Some("hello".into());
+    signer.sign(&mut req, time()).await.unwrap();
+    assert_eq!(
+        req.headers()["x-amz-content-sha256"],
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(req.headers()["x-amz-security-token"], "session");
+    assert!(authorization(&req)
+        .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"));
+
+    let signer = signer.with_unsigned_payload();
+    signer.sign(&mut req, time()).await.unwrap();
+    assert_eq!(req.headers()["x-amz-content-sha256"], "UNSIGNED-PAYLOAD");
+}
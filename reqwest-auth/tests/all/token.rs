@@ -0,0 +1,86 @@
+use std::time::{Duration, SystemTime};
+
+use reqwest::Client;
+use reqwest_auth::{
+    BasicAuthMiddleware, BearerAuthMiddleware, FileToken, KubernetesToken, StaticToken,
+    TokenProvider,
+};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_bearer_token_is_added() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("authorization", "Bearer s3cr3t"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(BearerAuthMiddleware::new(StaticToken::new("s3cr3t")))
+        .build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn assert_basic_auth_is_added() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        // base64("alice:s3cr3t")
+        .and(header("authorization", "Basic YWxpY2U6czNjcjN0"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(BasicAuthMiddleware::new(
+            "alice",
+            StaticToken::new("s3cr3t"),
+        ))
+        .build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn assert_file_token_is_reread_on_change() {
+    let path = std::env::temp_dir().join(format!("reqwest-auth-token-{}", std::process::id()));
+    std::fs::write(&path, "first\n").unwrap();
+    let provider = FileToken::new(&path);
+    assert_eq!(provider.token().await.unwrap().expose_secret(), "first");
+
+    std::fs::write(&path, "second\n").unwrap();
+    // Make sure the modification time changes even on filesystems with a coarse resolution.
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(provider.token().await.unwrap().expose_secret(), "second");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn assert_kubernetes_token_and_api_server_are_found() {
+    let path = std::env::temp_dir().join(format!("reqwest-auth-sa-{}", std::process::id()));
+    std::fs::write(&path, "eyJhbGciOiJSUzI1NiJ9.sa.token").unwrap();
+    let provider = KubernetesToken::with_path(&path);
+    assert_eq!(
+        provider.token().await.unwrap().expose_secret(),
+        "eyJhbGciOiJSUzI1NiJ9.sa.token"
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    std::env::set_var("KUBERNETES_SERVICE_HOST", "fd00::1");
+    std::env::set_var("KUBERNETES_SERVICE_PORT", "443");
+    assert_eq!(
+        KubernetesToken::api_server_url().unwrap().as_str(),
+        "https://[fd00::1]/"
+    );
+}
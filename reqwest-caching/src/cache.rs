@@ -0,0 +1,727 @@
+//! `CacheMiddleware` caches responses following HTTP caching rules.
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use http::{header, Extensions, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{
+    ClientWithMiddleware, Clock, DryRunResponse, Error, Middleware, Next, Result, SystemClock,
+};
+use thiserror::Error;
+
+use crate::control::CacheControl;
+use crate::response::CachedResponse;
+use crate::stats::CacheStats;
+use crate::store::{CacheEntry, CacheMetadata, CacheStore};
+
+/// How a response was produced by [`CacheMiddleware`], recorded in the
+/// [response extensions](reqwest::Response::extensions).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// The response was served from the cache without contacting the origin.
+    Hit,
+    /// The cached response was served after the origin confirmed it was still valid.
+    Revalidated,
+    /// A stale cached response was served, see
+    /// [`CacheMiddleware::with_stale_while_revalidate`] and
+    /// [`CacheMiddleware::with_stale_if_error`].
+    Stale,
+    /// The response came from the origin.
+    Miss,
+}
+
+/// The key under which [`CacheMiddleware`] stores a response.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// Create a cache key.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// The key as a string, as passed to the [`CacheStore`].
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for CacheKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl From<&str> for CacheKey {
+    fn from(key: &str) -> Self {
+        Self(key.to_owned())
+    }
+}
+
+impl std::fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+type KeyFn = dyn Fn(&Request) -> CacheKey + Send + Sync + 'static;
+
+/// Extension putting a single request in offline mode, see [`OfflineMode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Offline;
+
+/// A switch putting a [`CacheMiddleware`] in offline mode, where it answers only from the cache
+/// and fails with [`CacheMiss`] instead of contacting the network.
+///
+/// The switch is shared with the middleware, so it can be toggled at runtime after the client
+/// has been built. Offline mode can also be enabled for a single request with the [`Offline`]
+/// extension.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_caching::{CacheMiddleware, MemoryStore};
+///
+/// let cache = CacheMiddleware::new(MemoryStore::new(1000));
+/// let offline = cache.offline_mode();
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(cache).build();
+///
+/// // Airplane mode on.
+/// offline.set(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OfflineMode(Arc<AtomicBool>);
+
+impl OfflineMode {
+    /// Turns offline mode on or off.
+    pub fn set(&self, offline: bool) {
+        self.0.store(offline, Ordering::Relaxed)
+    }
+
+    /// Returns true if offline mode is on.
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Error returned by [`CacheMiddleware`] in offline mode when there is no cached response for a
+/// request.
+#[derive(Debug, Error)]
+#[error("No cached response for {method} {url} while offline")]
+pub struct CacheMiss {
+    /// The method of the request.
+    pub method: Method,
+    /// The URL of the request.
+    pub url: Url,
+}
+
+/// Status codes whose responses may be cached without explicit freshness information.
+const HEURISTICALLY_CACHEABLE: &[u16] = &[200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// `CacheMiddleware` is a private HTTP cache, storing responses to `GET` requests in a
+/// [`CacheStore`] and serving them for as long as they are fresh according to RFC 9111.
+///
+/// Stale responses carrying an `ETag` or `Last-Modified` header are revalidated with a
+/// conditional request, and served from the cache if the origin answers `304 Not Modified`.
+/// Successful unsafe requests (e.g. `POST` or `DELETE`) invalidate the cached response for their
+/// URL. Requests with `Cache-Control: no-store` bypass the cache entirely, and requests with
+/// `Cache-Control: no-cache` always go to the origin.
+///
+/// The RFC 5861 extensions can be enabled to serve stale responses, see
+/// [`with_stale_while_revalidate`] and [`with_stale_if_error`].
+///
+/// Responses are stored under their URL by default. The key can include selected request
+/// headers with [`with_key_headers`], e.g. to keep the responses of different tenants apart, and
+/// volatile query parameters can be left out of it with [`ignore_query_params`]; or the key can be
+/// computed by a custom function set with [`with_key`]. Whatever the key, the request headers
+/// named by a response's `Vary` header must match for it to be served; responses with
+/// `Vary: *` are never stored.
+///
+/// In [offline mode](OfflineMode), responses are served from the cache however stale they are,
+/// and requests without a cached response fail with [`CacheMiss`].
+///
+/// Whether a response came from the cache is recorded in a [`CacheStatus`] extension, and
+/// counted in the [`CacheStats`] returned by [`stats`](Self::stats).
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_caching::{CacheMiddleware, MemoryStore};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(CacheMiddleware::new(MemoryStore::new(1000)))
+///     .build();
+/// ```
+///
+/// [`with_stale_while_revalidate`]: Self::with_stale_while_revalidate
+/// [`with_stale_if_error`]: Self::with_stale_if_error
+/// [`with_key_headers`]: Self::with_key_headers
+/// [`ignore_query_params`]: Self::ignore_query_params
+/// [`with_key`]: Self::with_key
+pub struct CacheMiddleware {
+    store: Arc<dyn CacheStore>,
+    stats: CacheStats,
+    max_body_size: usize,
+    key: Option<Box<KeyFn>>,
+    key_headers: Vec<HeaderName>,
+    ignored_query_params: Vec<String>,
+    stale_while_revalidate: Option<(Duration, ClientWithMiddleware)>,
+    stale_if_error: Option<Duration>,
+    offline: OfflineMode,
+    clock: Arc<dyn Clock>,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    refreshing: Arc<Mutex<HashSet<String>>>,
+}
+
+impl CacheMiddleware {
+    /// Construct `CacheMiddleware` storing responses in `store`.
+    pub fn new<S: CacheStore>(store: S) -> Self {
+        Self::new_with_arc(Arc::new(store))
+    }
+
+    /// Construct `CacheMiddleware` storing responses in a shared `store`.
+    pub fn new_with_arc(store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            stats: store.stats().unwrap_or_default(),
+            store,
+            max_body_size: 10 * 1024 * 1024,
+            key: None,
+            key_headers: Vec::new(),
+            ignored_query_params: Vec::new(),
+            stale_while_revalidate: None,
+            stale_if_error: None,
+            offline: OfflineMode::default(),
+            clock: Arc::new(SystemClock),
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Use `clock` to date responses and check their freshness.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set the largest response body that is cached, 10 MiB by default.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// The counters of the cache, shared with its store.
+    pub fn stats(&self) -> CacheStats {
+        self.stats.clone()
+    }
+
+    /// Include the values of the request headers `names` in the cache key.
+    pub fn with_key_headers(mut self, names: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.key_headers.extend(names);
+        self
+    }
+
+    /// Leave the query parameters `names` out of the cache key.
+    pub fn ignore_query_params<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ignored_query_params
+            .extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Compute cache keys with `key`, replacing the default key made of the URL and the headers
+    /// set with [`with_key_headers`](Self::with_key_headers).
+    pub fn with_key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&Request) -> CacheKey + Send + Sync + 'static,
+    {
+        self.key = Some(Box::new(key));
+        self
+    }
+
+    fn cache_key(&self, req: &Request) -> CacheKey {
+        if let Some(key) = &self.key {
+            return key(req);
+        }
+        let mut url = req.url().clone();
+        if !self.ignored_query_params.is_empty() && url.query().is_some() {
+            let kept: Vec<(String, String)> = url
+                .query_pairs()
+                .filter(|(name, _)| {
+                    !self
+                        .ignored_query_params
+                        .iter()
+                        .any(|ignored| ignored == name)
+                })
+                .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                .collect();
+            if kept.is_empty() {
+                url.set_query(None);
+            } else {
+                url.query_pairs_mut().clear().extend_pairs(kept);
+            }
+        }
+        let mut key = url.to_string();
+        for name in &self.key_headers {
+            for value in req.headers().get_all(name) {
+                key.push('\n');
+                key.push_str(name.as_str());
+                key.push_str(": ");
+                key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+            }
+        }
+        CacheKey(key)
+    }
+
+    /// Serve stale responses carrying a `stale-while-revalidate` directive immediately, while
+    /// refreshing them in the background with `client`, for at most `max_staleness` past their
+    /// expiry.
+    ///
+    /// The refresh doesn't run the rest of the middleware chain of the request, which lives no
+    /// longer than the request itself: it is sent with `client` instead, which should not include
+    /// this middleware. On `wasm32` responses are revalidated before being served instead.
+    pub fn with_stale_while_revalidate(
+        mut self,
+        max_staleness: Duration,
+        client: impl Into<ClientWithMiddleware>,
+    ) -> Self {
+        self.stale_while_revalidate = Some((max_staleness, client.into()));
+        self
+    }
+
+    /// Serve stale responses when the origin fails or answers with a `500`, `502`, `503` or `504`
+    /// status, for at most `max_staleness` past their expiry or as limited by their
+    /// `stale-if-error` directive.
+    ///
+    /// Responses with a `must-revalidate` directive are never served stale.
+    pub fn with_stale_if_error(mut self, max_staleness: Duration) -> Self {
+        self.stale_if_error = Some(max_staleness);
+        self
+    }
+
+    /// Use `offline` to switch offline mode on and off, e.g. to share it between clients.
+    pub fn with_offline_mode(mut self, offline: OfflineMode) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// The switch turning offline mode on and off.
+    pub fn offline_mode(&self) -> OfflineMode {
+        self.offline.clone()
+    }
+
+    async fn lookup(&self, key: &str, request_headers: &HeaderMap) -> Option<CacheEntry> {
+        match self.store.get(key).await {
+            Ok(entry) => entry.filter(|entry| entry.matches_vary(request_headers)),
+            Err(e) => {
+                tracing::warn!("Failed to read from cache: {:#}", e);
+                None
+            }
+        }
+    }
+
+    async fn invalidate(&self, key: &str) {
+        if let Err(e) = self.store.delete(key).await {
+            tracing::warn!("Failed to invalidate cache: {:#}", e);
+        }
+    }
+
+    /// Starts refreshing `entry` in the background, unless it is already being refreshed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn refresh(&self, client: &ClientWithMiddleware, key: String, req: Request, entry: CacheEntry) {
+        {
+            let mut refreshing = self.refreshing.lock().expect("refreshing lock poisoned");
+            if !refreshing.insert(key.clone()) {
+                return;
+            }
+        }
+        let client = client.clone();
+        let store = self.store.clone();
+        let refreshing = self.refreshing.clone();
+        let max_body_size = self.max_body_size;
+        let clock = self.clock.clone();
+        tokio::spawn(async move {
+            let mut req = req;
+            let request_headers = req.headers().clone();
+            let revalidating = add_validators(&mut req, &entry);
+            let request_time = clock.system_time();
+            let result = match client.execute(req).await {
+                Ok(res) => {
+                    let cached = revalidating.then_some(entry);
+                    complete(
+                        &*store,
+                        max_body_size,
+                        &key,
+                        cached,
+                        res,
+                        (request_time, clock.system_time()),
+                        &request_headers,
+                    )
+                    .await
+                    .map(|_| ())
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::debug!("Failed to refresh cached response: {}", e);
+            }
+            let mut refreshing = refreshing.lock().expect("refreshing lock poisoned");
+            refreshing.remove(&key);
+        });
+    }
+}
+
+/// How long `entry` may be served past its expiry at `now`, given the configured maximum and
+/// `directive` from its `Cache-Control` header.
+fn within_staleness(
+    entry: &CacheEntry,
+    now: SystemTime,
+    max_staleness: Duration,
+    directive: Option<Duration>,
+) -> bool {
+    let staleness = entry.age(now).saturating_sub(entry.freshness_lifetime());
+    staleness <= directive.unwrap_or(max_staleness).min(max_staleness)
+}
+
+/// Adds conditional headers to `req` to revalidate `entry`, unless the caller is making its own
+/// conditional request. Returns true if headers were added.
+pub(crate) fn add_validators(req: &mut Request, entry: &CacheEntry) -> bool {
+    let headers = req.headers_mut();
+    if headers.contains_key(header::IF_NONE_MATCH)
+        || headers.contains_key(header::IF_MODIFIED_SINCE)
+    {
+        return false;
+    }
+    let cached_headers = &entry.response.headers;
+    if let Some(etag) = cached_headers.get(header::ETAG) {
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        true
+    } else if let Some(last_modified) = cached_headers.get(header::LAST_MODIFIED) {
+        headers.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+        true
+    } else {
+        false
+    }
+}
+
+pub(crate) async fn store_entry(store: &dyn CacheStore, key: &str, entry: CacheEntry) {
+    if let Err(e) = store.put(key, entry).await {
+        tracing::warn!("Failed to write to cache: {:#}", e);
+    }
+}
+
+/// Updates the cache with the response from the origin, and returns the response to serve.
+/// `revalidated` is the entry that was being revalidated, if any.
+async fn complete(
+    store: &dyn CacheStore,
+    max_body_size: usize,
+    key: &str,
+    revalidated: Option<CacheEntry>,
+    res: Response,
+    (request_time, response_time): (SystemTime, SystemTime),
+    request_headers: &HeaderMap,
+) -> Result<Response> {
+    let varied_headers = varied_headers(res.headers(), request_headers);
+    if let Some(mut entry) = revalidated {
+        if res.status() == StatusCode::NOT_MODIFIED {
+            entry.update_headers(res.headers());
+            entry.metadata = CacheMetadata {
+                request_time,
+                response_time,
+                request_headers: varied_headers.unwrap_or_default(),
+            };
+            store_entry(store, key, entry.clone()).await;
+            return Ok(entry.to_response(response_time, CacheStatus::Revalidated));
+        }
+    }
+
+    let mut res = match varied_headers {
+        Some(request_headers) if is_storable(&res) => {
+            match CachedResponse::read(res, max_body_size).await? {
+                Ok((response, response_extensions)) => {
+                    let mut res = response.to_response();
+                    res.extensions_mut().extend(response_extensions);
+                    let metadata = CacheMetadata {
+                        request_time,
+                        response_time,
+                        request_headers,
+                    };
+                    store_entry(store, key, CacheEntry { response, metadata }).await;
+                    res
+                }
+                Err(res) => res,
+            }
+        }
+        _ => res,
+    };
+    res.extensions_mut().insert(CacheStatus::Miss);
+    Ok(res)
+}
+
+/// The names listed in the `Vary` headers of a response.
+fn vary_names(response_headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    response_headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Selects the request headers named by the response's `Vary` header, or returns `None` for
+/// `Vary: *`.
+fn varied_headers(response_headers: &HeaderMap, request_headers: &HeaderMap) -> Option<HeaderMap> {
+    let mut varied = HeaderMap::new();
+    for name in vary_names(response_headers) {
+        if name == "*" {
+            return None;
+        }
+        if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+            for value in request_headers.get_all(&name) {
+                varied.append(name.clone(), value.clone());
+            }
+        }
+    }
+    Some(varied)
+}
+
+fn header_time(headers: &HeaderMap, name: header::HeaderName) -> Option<SystemTime> {
+    httpdate::parse_http_date(headers.get(name)?.to_str().ok()?).ok()
+}
+
+impl CacheEntry {
+    /// The age of the response at `now`, following RFC 9111 section 4.2.3.
+    pub(crate) fn age(&self, now: SystemTime) -> Duration {
+        let headers = &self.response.headers;
+        let CacheMetadata {
+            request_time,
+            response_time,
+            ..
+        } = self.metadata;
+        let since = |later: SystemTime, earlier: SystemTime| {
+            later.duration_since(earlier).unwrap_or_default()
+        };
+        let apparent_age = header_time(headers, header::DATE)
+            .map(|date| since(response_time, date))
+            .unwrap_or_default();
+        let age_value = headers
+            .get(header::AGE)
+            .and_then(|age| age.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let corrected_age = age_value.saturating_add(since(response_time, request_time));
+        apparent_age
+            .max(corrected_age)
+            .saturating_add(since(now, response_time))
+    }
+
+    /// How long the response is fresh for, following RFC 9111 section 4.2.1.
+    pub(crate) fn freshness_lifetime(&self) -> Duration {
+        let headers = &self.response.headers;
+        if let Some(max_age) = CacheControl::parse(headers).max_age {
+            return max_age;
+        }
+        let date = header_time(headers, header::DATE).unwrap_or(self.metadata.response_time);
+        if headers.contains_key(header::EXPIRES) {
+            // Invalid dates mean the response is already expired.
+            return header_time(headers, header::EXPIRES)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or_default();
+        }
+        match header_time(headers, header::LAST_MODIFIED) {
+            Some(last_modified)
+                if HEURISTICALLY_CACHEABLE.contains(&self.response.status.as_u16()) =>
+            {
+                date.duration_since(last_modified).unwrap_or_default() / 10
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Returns true if the response can be served without revalidation at `now`.
+    pub(crate) fn is_fresh(&self, now: SystemTime) -> bool {
+        self.age(now) < self.freshness_lifetime()
+    }
+
+    /// Returns true if the request headers named by the response's `Vary` header match
+    /// `request_headers`.
+    pub(crate) fn matches_vary(&self, request_headers: &HeaderMap) -> bool {
+        vary_names(&self.response.headers).all(|name| {
+            name != "*"
+                && HeaderName::from_bytes(name.as_bytes()).is_ok_and(|name| {
+                    self.metadata
+                        .request_headers
+                        .get_all(&name)
+                        .iter()
+                        .eq(request_headers.get_all(&name).iter())
+                })
+        })
+    }
+
+    /// Updates the stored response with the headers of a `304 Not Modified` response, see
+    /// RFC 9111 section 4.3.4.
+    pub(crate) fn update_headers(&mut self, headers: &HeaderMap) {
+        for (name, value) in headers {
+            if name != header::CONTENT_LENGTH {
+                self.response.headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+
+    /// Builds the response to serve from the cache at `now`.
+    fn to_response(&self, now: SystemTime, status: CacheStatus) -> Response {
+        let mut res = self.response.to_response();
+        res.headers_mut()
+            .insert(header::AGE, HeaderValue::from(self.age(now).as_secs()));
+        res.extensions_mut().insert(status);
+        res
+    }
+}
+
+/// Returns true if `res` may be stored.
+fn is_storable(res: &Response) -> bool {
+    let headers = res.headers();
+    let control = CacheControl::parse(headers);
+    !control.no_store
+        && res.extensions().get::<DryRunResponse>().is_none()
+        && (control.max_age.is_some()
+            || headers.contains_key(header::EXPIRES)
+            || HEURISTICALLY_CACHEABLE.contains(&res.status().as_u16()))
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for CacheMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let res = self.respond(req, extensions, next).await?;
+        match res.extensions().get::<CacheStatus>() {
+            Some(CacheStatus::Hit) | Some(CacheStatus::Stale) => self.stats.record_hit(),
+            Some(CacheStatus::Revalidated) => self.stats.record_revalidation(),
+            Some(CacheStatus::Miss) => self.stats.record_miss(),
+            None => {}
+        }
+        Ok(res)
+    }
+}
+
+impl CacheMiddleware {
+    async fn respond(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let key = self.cache_key(&req);
+        let key = key.as_str();
+        if self.offline.is_enabled() || extensions.get::<Offline>().is_some() {
+            let cached = match *req.method() {
+                Method::GET => self.lookup(key, req.headers()).await,
+                _ => None,
+            };
+            return match cached {
+                Some(entry) => {
+                    let now = self.clock.system_time();
+                    let status = if entry.is_fresh(now) {
+                        CacheStatus::Hit
+                    } else {
+                        CacheStatus::Stale
+                    };
+                    Ok(entry.to_response(now, status))
+                }
+                None => Err(Error::middleware(CacheMiss {
+                    method: req.method().clone(),
+                    url: req.url().clone(),
+                })),
+            };
+        }
+        if !matches!(
+            *req.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        ) {
+            let res = next.run(req, extensions).await?;
+            if res.status().is_success() || res.status().is_redirection() {
+                self.invalidate(key).await;
+            }
+            return Ok(res);
+        }
+        let request_control = CacheControl::parse(req.headers());
+        if req.method() != Method::GET || request_control.no_store {
+            return next.run(req, extensions).await;
+        }
+
+        let cached = self.lookup(key, req.headers()).await;
+        let request_headers = req.headers().clone();
+        let mut revalidating = false;
+        if let Some(entry) = &cached {
+            let now = self.clock.system_time();
+            let response_control = CacheControl::parse(&entry.response.headers);
+            let usable = !response_control.no_cache && !request_control.no_cache;
+            let fresh = usable
+                && entry.is_fresh(now)
+                && request_control
+                    .max_age
+                    .is_none_or(|max_age| entry.age(now) <= max_age);
+            if fresh {
+                tracing::debug!("Serving {} from cache", req.url());
+                return Ok(entry.to_response(now, CacheStatus::Hit));
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some((max_staleness, client)) = &self.stale_while_revalidate {
+                let directive = response_control.stale_while_revalidate;
+                if usable
+                    && directive.is_some()
+                    && within_staleness(entry, now, *max_staleness, directive)
+                {
+                    if let Some(refresh_req) = req.try_clone() {
+                        tracing::debug!("Serving stale {} while revalidating", req.url());
+                        self.refresh(client, key.to_owned(), refresh_req, entry.clone());
+                        return Ok(entry.to_response(now, CacheStatus::Stale));
+                    }
+                }
+            }
+
+            revalidating = add_validators(&mut req, entry);
+        }
+
+        let request_time = self.clock.system_time();
+        let result = next.run(req, extensions).await;
+
+        let failed = match &result {
+            Ok(res) => matches!(res.status().as_u16(), 500 | 502 | 503 | 504),
+            Err(_) => true,
+        };
+        if let (true, Some(max_staleness), Some(entry)) = (failed, self.stale_if_error, &cached) {
+            let now = self.clock.system_time();
+            let response_control = CacheControl::parse(&entry.response.headers);
+            let directive = request_control
+                .stale_if_error
+                .or(response_control.stale_if_error);
+            if !response_control.must_revalidate
+                && within_staleness(entry, now, max_staleness, directive)
+            {
+                tracing::debug!("Origin failed, serving stale response from cache");
+                return Ok(entry.to_response(now, CacheStatus::Stale));
+            }
+        }
+
+        let cached = cached.filter(|_| revalidating);
+        complete(
+            &*self.store,
+            self.max_body_size,
+            key,
+            cached,
+            result?,
+            (request_time, self.clock.system_time()),
+            &request_headers,
+        )
+        .await
+    }
+}
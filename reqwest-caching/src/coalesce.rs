@@ -0,0 +1,175 @@
+//! `CoalesceMiddleware` collapses identical concurrent requests into one.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::channel::oneshot;
+use futures::future::{FutureExt, Shared};
+use http::{header, Extensions, Method};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+use crate::response::CachedResponse;
+
+type KeyFn = dyn Fn(&Request) -> Option<String> + Send + Sync + 'static;
+
+/// The outcome shared by the leader of a flight with its followers: `None` when the response
+/// couldn't be shared, in which case followers send their own request.
+type Flight = Shared<oneshot::Receiver<Option<Arc<CachedResponse>>>>;
+
+/// `CoalesceMiddleware` collapses concurrent requests with the same key into a single upstream
+/// request (also known as "singleflight"), and shares the buffered response among all waiters.
+///
+/// By default `GET` and `HEAD` requests are coalesced by method and URL, except for those
+/// carrying `Authorization` or `Cookie` headers whose responses may differ per caller. Use
+/// [`with_key`](Self::with_key) to choose which requests are coalesced and how.
+///
+/// Responses are only shared if their body is at most `max_body_size` bytes (1 MiB by default).
+/// When the response is larger, or the request fails, the waiting requests are sent on their
+/// own. Only the response status, headers and body are shared: response extensions are only
+/// seen by the request that actually went upstream.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_caching::CoalesceMiddleware;
+///
+/// // Coalesce all GET requests by URL, including authenticated ones.
+/// let coalesce = CoalesceMiddleware::new().with_key(|req| {
+///     (req.method() == reqwest::Method::GET).then(|| req.url().to_string())
+/// });
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(coalesce).build();
+/// ```
+pub struct CoalesceMiddleware {
+    key: Box<KeyFn>,
+    max_body_size: usize,
+    in_flight: Arc<Mutex<HashMap<String, (u64, Flight)>>>,
+    next_id: AtomicU64,
+}
+
+impl CoalesceMiddleware {
+    /// Construct `CoalesceMiddleware` with the default key function.
+    pub fn new() -> Self {
+        Self {
+            key: Box::new(default_key),
+            max_body_size: 1024 * 1024,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Coalesce requests by the key returned by `key`; requests for which it returns `None` are
+    /// never coalesced.
+    pub fn with_key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Box::new(key);
+        self
+    }
+
+    /// Set the largest response body that is shared between requests.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+impl Default for CoalesceMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn default_key(req: &Request) -> Option<String> {
+    let coalesced = matches!(*req.method(), Method::GET | Method::HEAD)
+        && !req.headers().contains_key(header::AUTHORIZATION)
+        && !req.headers().contains_key(header::COOKIE);
+    coalesced.then(|| format!("{} {}", req.method(), req.url()))
+}
+
+/// Removes the flight from the in-flight requests once the leader is done, even if it was
+/// cancelled.
+struct FlightGuard {
+    in_flight: Arc<Mutex<HashMap<String, (u64, Flight)>>>,
+    key: String,
+    id: u64,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().expect("in-flight lock poisoned");
+        if in_flight
+            .get(&self.key)
+            .is_some_and(|(id, _)| *id == self.id)
+        {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for CoalesceMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let key = match (self.key)(&req) {
+            Some(key) => key,
+            None => return next.run(req, extensions).await,
+        };
+
+        let (sender, guard) = {
+            let mut in_flight = self.in_flight.lock().expect("in-flight lock poisoned");
+            match in_flight.get(&key) {
+                Some((_, flight)) => (None, Err(flight.clone())),
+                None => {
+                    let (sender, receiver) = oneshot::channel();
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    in_flight.insert(key.clone(), (id, receiver.shared()));
+                    let guard = FlightGuard {
+                        in_flight: self.in_flight.clone(),
+                        key,
+                        id,
+                    };
+                    (Some(sender), Ok(guard))
+                }
+            }
+        };
+
+        let _guard = match guard {
+            Ok(guard) => guard,
+            Err(flight) => {
+                if let Ok(Some(shared)) = flight.await {
+                    tracing::debug!("Sharing coalesced response for {}", req.url());
+                    return Ok(shared.to_response());
+                }
+                return next.run(req, extensions).await;
+            }
+        };
+        let sender = sender.expect("leader has a sender");
+
+        let res = match next.run(req, extensions).await {
+            Ok(res) => res,
+            Err(e) => {
+                let _ = sender.send(None);
+                return Err(e);
+            }
+        };
+        match CachedResponse::read(res, self.max_body_size).await? {
+            Ok((buffered, response_extensions)) => {
+                let buffered = Arc::new(buffered);
+                let _ = sender.send(Some(buffered.clone()));
+                let mut res = buffered.to_response();
+                res.extensions_mut().extend(response_extensions);
+                Ok(res)
+            }
+            Err(res) => {
+                let _ = sender.send(None);
+                Ok(res)
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Parsing of the `Cache-Control` header.
+use std::time::Duration;
+
+use http::{header, HeaderMap};
+
+/// The `Cache-Control` directives understood by [`CacheMiddleware`](crate::CacheMiddleware).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct CacheControl {
+    pub(crate) no_store: bool,
+    pub(crate) no_cache: bool,
+    pub(crate) must_revalidate: bool,
+    pub(crate) max_age: Option<Duration>,
+    pub(crate) stale_while_revalidate: Option<Duration>,
+    pub(crate) stale_if_error: Option<Duration>,
+}
+
+impl CacheControl {
+    pub(crate) fn parse(headers: &HeaderMap) -> Self {
+        let mut control = Self::default();
+        let directives = headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name, Some(value.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+            let seconds = || {
+                value
+                    .and_then(|value| value.parse().ok())
+                    .map(Duration::from_secs)
+            };
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => control.no_store = true,
+                "no-cache" => control.no_cache = true,
+                "must-revalidate" | "proxy-revalidate" => control.must_revalidate = true,
+                "max-age" => control.max_age = seconds(),
+                "stale-while-revalidate" => control.stale_while_revalidate = seconds(),
+                "stale-if-error" => control.stale_if_error = seconds(),
+                _ => {}
+            }
+        }
+        if !headers.contains_key(header::CACHE_CONTROL)
+            && headers
+                .get(header::PRAGMA)
+                .is_some_and(|pragma| pragma.as_bytes().eq_ignore_ascii_case(b"no-cache"))
+        {
+            control.no_cache = true;
+        }
+        control
+    }
+}
@@ -0,0 +1,298 @@
+//! `DiskStore` keeps cache entries in files.
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+
+use crate::evict::{EvictionPolicy, Index};
+use crate::response::CachedResponse;
+use crate::stats::CacheStats;
+use crate::store::{CacheEntry, CacheMetadata, CacheStore};
+
+const MAGIC: &[u8] = b"reqwest-caching/1\n";
+
+/// A [`CacheStore`] keeping each entry in a file of a directory, so that the cache survives
+/// restarts.
+///
+/// Entries are written atomically, so several processes can share a directory. The disk usage
+/// can be bounded with [`with_max_entries`] and [`with_max_bytes`], evicting files according to
+/// the store's [`EvictionPolicy`]. The files already in the directory when the store is created
+/// are accounted for, oldest first; limits are enforced by each process on its own.
+///
+/// [`with_max_entries`]: Self::with_max_entries
+/// [`with_max_bytes`]: Self::with_max_bytes
+#[derive(Debug)]
+pub struct DiskStore {
+    dir: PathBuf,
+    max_entries: usize,
+    max_bytes: u64,
+    max_entry_size: u64,
+    index: Mutex<Index>,
+    stats: CacheStats,
+}
+
+impl DiskStore {
+    /// Construct `DiskStore` storing entries in `dir`, which is created if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let store = Self {
+            dir,
+            max_entries: usize::MAX,
+            max_bytes: u64::MAX,
+            max_entry_size: u64::MAX,
+            index: Mutex::new(Index::new(EvictionPolicy::Lru)),
+            stats: CacheStats::default(),
+        };
+        store.scan()?;
+        Ok(store)
+    }
+
+    /// Keep at most `max_entries` files.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Keep at most `max_bytes` bytes of files.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Don't store entries larger than `max_entry_size` bytes on disk.
+    pub fn with_max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.max_entry_size = max_entry_size;
+        self
+    }
+
+    /// Set which files are evicted first when the store is full.
+    pub fn with_eviction_policy(self, policy: EvictionPolicy) -> std::io::Result<Self> {
+        *self.index.lock().expect("index lock poisoned") = Index::new(policy);
+        self.scan()?;
+        Ok(self)
+    }
+
+    /// Adds the entries in the directory to the index, oldest first.
+    fn scan(&self) -> std::io::Result<()> {
+        let mut files = Vec::new();
+        for file in std::fs::read_dir(&self.dir)? {
+            let file = file?;
+            let metadata = file.metadata()?;
+            let name = file.file_name().to_string_lossy().into_owned();
+            if metadata.is_file() && !name.ends_with(".tmp") {
+                files.push((metadata.modified()?, name, metadata.len()));
+            }
+        }
+        files.sort();
+        let mut index = self.index.lock().expect("index lock poisoned");
+        for (_, name, size) in files {
+            index.insert(&name, size);
+        }
+        self.stats.set_usage(index.len(), index.bytes());
+        Ok(())
+    }
+
+    fn file_name(key: &str) -> String {
+        // FNV-1a, which is stable across platforms and releases. The key is stored in the file to
+        // detect collisions.
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    async fn remove_file(&self, name: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.dir.join(name)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl CacheStore for DiskStore {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<CacheEntry>> {
+        let name = Self::file_name(key);
+        let data = match tokio::fs::read(self.dir.join(&name)).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if !data.starts_with(MAGIC) {
+            // Written by another version of this crate.
+            return Ok(None);
+        }
+        let (stored_key, entry) = decode(Bytes::from(data))?;
+        if stored_key != key {
+            return Ok(None);
+        }
+        self.index.lock().expect("index lock poisoned").touch(&name);
+        Ok(Some(entry))
+    }
+
+    async fn put(&self, key: &str, entry: CacheEntry) -> anyhow::Result<()> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let name = Self::file_name(key);
+        let data = encode(key, &entry);
+        if data.len() as u64 > self.max_entry_size {
+            return self.delete(key).await;
+        }
+
+        let path = self.dir.join(&name);
+        let tmp = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        tokio::fs::write(&tmp, &data).await?;
+        if let Err(e) = tokio::fs::rename(&tmp, &path).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+
+        let evicted = {
+            let mut index = self.index.lock().expect("index lock poisoned");
+            index.insert(&name, data.len() as u64);
+            let evicted = index.evict(self.max_entries, self.max_bytes, &name);
+            self.stats.set_usage(index.len(), index.bytes());
+            evicted
+        };
+        for name in evicted {
+            self.stats.record_eviction();
+            self.remove_file(&name).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let name = Self::file_name(key);
+        {
+            let mut index = self.index.lock().expect("index lock poisoned");
+            index.remove(&name);
+            self.stats.set_usage(index.len(), index.bytes());
+        }
+        self.remove_file(&name).await
+    }
+
+    fn stats(&self) -> Option<CacheStats> {
+        Some(self.stats.clone())
+    }
+}
+
+fn put_bytes(buf: &mut BytesMut, bytes: &[u8]) {
+    buf.put_u32(bytes.len() as u32);
+    buf.put_slice(bytes);
+}
+
+fn put_time(buf: &mut BytesMut, time: SystemTime) {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    buf.put_u64(since_epoch.as_secs());
+    buf.put_u32(since_epoch.subsec_nanos());
+}
+
+fn put_headers(buf: &mut BytesMut, headers: &HeaderMap) {
+    buf.put_u32(headers.len() as u32);
+    for (name, value) in headers {
+        put_bytes(buf, name.as_str().as_bytes());
+        put_bytes(buf, value.as_bytes());
+    }
+}
+
+fn encode(key: &str, entry: &CacheEntry) -> Bytes {
+    let response = &entry.response;
+    let mut buf = BytesMut::with_capacity(response.body.len() + 1024);
+    buf.put_slice(MAGIC);
+    put_bytes(&mut buf, key.as_bytes());
+    put_time(&mut buf, entry.metadata.request_time);
+    put_time(&mut buf, entry.metadata.response_time);
+    put_headers(&mut buf, &entry.metadata.request_headers);
+    put_bytes(&mut buf, response.url.as_str().as_bytes());
+    buf.put_u16(response.status.as_u16());
+    buf.put_u8(match response.version {
+        Version::HTTP_09 => 0,
+        Version::HTTP_10 => 1,
+        Version::HTTP_2 => 3,
+        Version::HTTP_3 => 4,
+        _ => 2,
+    });
+    put_headers(&mut buf, &response.headers);
+    put_bytes(&mut buf, &response.body);
+    buf.freeze()
+}
+
+fn get_bytes(buf: &mut Bytes) -> anyhow::Result<Bytes> {
+    if buf.remaining() < 4 {
+        bail!("truncated cache entry");
+    }
+    let len = buf.get_u32() as usize;
+    if buf.remaining() < len {
+        bail!("truncated cache entry");
+    }
+    Ok(buf.split_to(len))
+}
+
+fn get_time(buf: &mut Bytes) -> anyhow::Result<SystemTime> {
+    if buf.remaining() < 12 {
+        bail!("truncated cache entry");
+    }
+    let secs = buf.get_u64();
+    let nanos = buf.get_u32();
+    Ok(UNIX_EPOCH + Duration::new(secs, nanos))
+}
+
+fn get_headers(buf: &mut Bytes) -> anyhow::Result<HeaderMap> {
+    if buf.remaining() < 4 {
+        bail!("truncated cache entry");
+    }
+    let count = buf.get_u32();
+    let mut headers = HeaderMap::new();
+    for _ in 0..count {
+        let name = HeaderName::from_bytes(&get_bytes(buf)?)?;
+        let value = HeaderValue::from_maybe_shared(get_bytes(buf)?)?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+fn decode(mut buf: Bytes) -> anyhow::Result<(String, CacheEntry)> {
+    if !buf.starts_with(MAGIC) {
+        bail!("not a cache entry");
+    }
+    buf.advance(MAGIC.len());
+    let key = String::from_utf8(get_bytes(&mut buf)?.to_vec())?;
+    let metadata = CacheMetadata {
+        request_time: get_time(&mut buf)?,
+        response_time: get_time(&mut buf)?,
+        request_headers: get_headers(&mut buf)?,
+    };
+    let url = std::str::from_utf8(&get_bytes(&mut buf)?)?.parse()?;
+    if buf.remaining() < 3 {
+        bail!("truncated cache entry");
+    }
+    let status = StatusCode::from_u16(buf.get_u16())?;
+    let version = match buf.get_u8() {
+        0 => Version::HTTP_09,
+        1 => Version::HTTP_10,
+        2 => Version::HTTP_11,
+        3 => Version::HTTP_2,
+        4 => Version::HTTP_3,
+        other => return Err(anyhow!("unknown HTTP version {}", other)),
+    };
+    let headers = get_headers(&mut buf)?;
+    let body = get_bytes(&mut buf)?;
+    let response = CachedResponse {
+        url,
+        status,
+        version,
+        headers,
+        body,
+    };
+    Ok((key, CacheEntry { response, metadata }))
+}
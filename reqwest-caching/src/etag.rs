@@ -0,0 +1,119 @@
+//! `EtagMiddleware` makes conditional requests for previously seen responses.
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use http::{header, Extensions, StatusCode};
+use reqwest::{Method, Request, Response};
+use reqwest_middleware::{DryRunResponse, Middleware, Next, Result};
+
+use crate::cache::{add_validators, store_entry, CacheStatus};
+use crate::response::CachedResponse;
+use crate::store::{CacheEntry, CacheMetadata, CacheStore};
+
+/// `EtagMiddleware` remembers the `ETag` and `Last-Modified` validators of responses to `GET`
+/// requests, and automatically makes conditional requests with `If-None-Match` or
+/// `If-Modified-Since` for their URL.
+///
+/// When the origin answers `304 Not Modified`, the remembered response is returned instead, so
+/// callers always see the full response. Unlike [`CacheMiddleware`](crate::CacheMiddleware),
+/// every request goes to the origin, whatever the freshness of the response: this saves
+/// bandwidth without ever serving outdated content.
+///
+/// Responses are remembered in a [`CacheStore`] under keys prefixed with `etag:`, so a store can
+/// be shared with a `CacheMiddleware`. Requests which already carry conditional headers are left
+/// alone.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_caching::{EtagMiddleware, MemoryStore};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(EtagMiddleware::new(MemoryStore::new(1000)))
+///     .build();
+/// ```
+pub struct EtagMiddleware {
+    store: Arc<dyn CacheStore>,
+    max_body_size: usize,
+}
+
+impl EtagMiddleware {
+    /// Construct `EtagMiddleware` remembering responses in `store`.
+    pub fn new<S: CacheStore>(store: S) -> Self {
+        Self::new_with_arc(Arc::new(store))
+    }
+
+    /// Construct `EtagMiddleware` remembering responses in a shared `store`.
+    pub fn new_with_arc(store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            store,
+            max_body_size: 10 * 1024 * 1024,
+        }
+    }
+
+    /// Set the largest response body that is remembered, 10 MiB by default.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for EtagMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if req.method() != Method::GET {
+            return next.run(req, extensions).await;
+        }
+        let key = format!("etag:{}", req.url());
+        let remembered = match self.store.get(&key).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Failed to read remembered response: {:#}", e);
+                None
+            }
+        };
+        let remembered = remembered.filter(|entry| add_validators(&mut req, entry));
+
+        let request_time = SystemTime::now();
+        let res = next.run(req, extensions).await?;
+        let metadata = CacheMetadata {
+            request_time,
+            response_time: SystemTime::now(),
+            request_headers: Default::default(),
+        };
+
+        if res.extensions().get::<DryRunResponse>().is_some() {
+            return Ok(res);
+        }
+        if let Some(mut entry) = remembered {
+            if res.status() == StatusCode::NOT_MODIFIED {
+                entry.update_headers(res.headers());
+                entry.metadata = metadata;
+                store_entry(&*self.store, &key, entry.clone()).await;
+                let mut res = entry.response.to_response();
+                res.extensions_mut().insert(CacheStatus::Revalidated);
+                return Ok(res);
+            }
+        }
+
+        let has_validators = res.headers().contains_key(header::ETAG)
+            || res.headers().contains_key(header::LAST_MODIFIED);
+        if res.status() != StatusCode::OK || !has_validators {
+            return Ok(res);
+        }
+        Ok(match CachedResponse::read(res, self.max_body_size).await? {
+            Ok((response, response_extensions)) => {
+                let mut res = response.to_response();
+                res.extensions_mut().extend(response_extensions);
+                store_entry(&*self.store, &key, CacheEntry { response, metadata }).await;
+                res
+            }
+            Err(res) => res,
+        })
+    }
+}
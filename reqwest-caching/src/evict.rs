@@ -0,0 +1,123 @@
+//! Bookkeeping for the eviction policies of the built-in stores.
+use std::collections::{BTreeSet, HashMap};
+
+/// Which entry a built-in [`CacheStore`](crate::CacheStore) evicts when it is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used entry.
+    #[default]
+    Lru,
+    /// Evict the least frequently used entry, and the least recently used one among those.
+    Lfu,
+}
+
+#[derive(Debug)]
+struct Slot {
+    size: u64,
+    uses: u64,
+    last_used: u64,
+}
+
+/// Tracks the size and use of entries, ordering them by eviction priority.
+#[derive(Debug)]
+pub(crate) struct Index {
+    policy: EvictionPolicy,
+    slots: HashMap<String, Slot>,
+    order: BTreeSet<(u64, u64, String)>,
+    tick: u64,
+    bytes: u64,
+}
+
+impl Index {
+    pub(crate) fn new(policy: EvictionPolicy) -> Self {
+        Self {
+            policy,
+            slots: HashMap::new(),
+            order: BTreeSet::new(),
+            tick: 0,
+            bytes: 0,
+        }
+    }
+
+    fn rank(&self, key: &str, slot: &Slot) -> (u64, u64, String) {
+        match self.policy {
+            EvictionPolicy::Lru => (slot.last_used, 0, key.to_owned()),
+            EvictionPolicy::Lfu => (slot.uses, slot.last_used, key.to_owned()),
+        }
+    }
+
+    /// Records a use of `key`, returning false if it isn't in the index.
+    pub(crate) fn touch(&mut self, key: &str) -> bool {
+        let slot = match self.slots.remove(key) {
+            Some(slot) => slot,
+            None => return false,
+        };
+        self.order.remove(&self.rank(key, &slot));
+        self.tick += 1;
+        let slot = Slot {
+            uses: slot.uses + 1,
+            last_used: self.tick,
+            ..slot
+        };
+        self.order.insert(self.rank(key, &slot));
+        self.slots.insert(key.to_owned(), slot);
+        true
+    }
+
+    /// Adds or replaces `key`, taking `size` bytes.
+    pub(crate) fn insert(&mut self, key: &str, size: u64) {
+        self.remove(key);
+        self.tick += 1;
+        let slot = Slot {
+            size,
+            uses: 1,
+            last_used: self.tick,
+        };
+        self.bytes += size;
+        self.order.insert(self.rank(key, &slot));
+        self.slots.insert(key.to_owned(), slot);
+    }
+
+    /// Removes `key`, returning true if it was in the index.
+    pub(crate) fn remove(&mut self, key: &str) -> bool {
+        match self.slots.remove(key) {
+            Some(slot) => {
+                self.order.remove(&self.rank(key, &slot));
+                self.bytes -= slot.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes and returns entries until there are at most `max_entries` entries taking at most
+    /// `max_bytes` bytes. `keep`, the entry just inserted, is only evicted if it doesn't fit on
+    /// its own.
+    pub(crate) fn evict(&mut self, max_entries: usize, max_bytes: u64, keep: &str) -> Vec<String> {
+        let mut evicted = Vec::new();
+        while self.slots.len() > max_entries || self.bytes > max_bytes {
+            let victim = self
+                .order
+                .iter()
+                .find(|(_, _, key)| key != keep)
+                .or_else(|| self.order.first())
+                .map(|(_, _, key)| key.clone());
+            match victim {
+                Some(key) => {
+                    self.remove(&key);
+                    evicted.push(key);
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub(crate) fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
@@ -0,0 +1,48 @@
+//! Middleware cutting down on redundant requests, built on [`reqwest_middleware`].
+//!
+//! Use [`CacheMiddleware`] to cache responses following HTTP caching rules, in memory with
+//! [`MemoryStore`], on disk with [`DiskStore`] or in any other [`CacheStore`], and
+//! [`CoalesceMiddleware`] to collapse identical concurrent requests into a single upstream
+//! request. [`EtagMiddleware`] makes conditional requests for previously seen responses, and
+//! [`NegativeCacheMiddleware`] fails fast for requests which recently failed.
+//! [`QueryCacheMiddleware`] briefly shares the responses to queries sent with `POST`, for the
+//! requests marked as safe to repeat.
+//!
+//! ## Example
+//!
+//! ```
+//! use reqwest_middleware::ClientBuilder;
+//! use reqwest_caching::{CacheMiddleware, CoalesceMiddleware, MemoryStore};
+//!
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(CacheMiddleware::new(MemoryStore::new(1000)))
+//!     .with(CoalesceMiddleware::new())
+//!     .build();
+//! ```
+
+mod cache;
+mod coalesce;
+mod control;
+#[cfg(not(target_arch = "wasm32"))]
+mod disk;
+mod etag;
+mod evict;
+mod memory;
+mod negative;
+mod query;
+mod response;
+mod stats;
+mod store;
+
+pub use cache::{CacheKey, CacheMiddleware, CacheMiss, CacheStatus, Offline, OfflineMode};
+pub use coalesce::CoalesceMiddleware;
+#[cfg(not(target_arch = "wasm32"))]
+pub use disk::DiskStore;
+pub use etag::EtagMiddleware;
+pub use evict::EvictionPolicy;
+pub use memory::MemoryStore;
+pub use negative::{NegativeCacheMiddleware, RecentFailure};
+pub use query::QueryCacheMiddleware;
+pub use response::CachedResponse;
+pub use stats::CacheStats;
+pub use store::{CacheEntry, CacheMetadata, CacheStore};
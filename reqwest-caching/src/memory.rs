@@ -0,0 +1,118 @@
+//! `MemoryStore` keeps cache entries in memory.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::evict::{EvictionPolicy, Index};
+use crate::stats::CacheStats;
+use crate::store::{CacheEntry, CacheStore};
+
+/// A [`CacheStore`] keeping entries in memory.
+///
+/// The store holds up to a given number of entries, and optionally up to a given number of bytes
+/// (see [`with_max_bytes`](Self::with_max_bytes)), evicting entries according to its
+/// [`EvictionPolicy`] when full.
+#[derive(Debug)]
+pub struct MemoryStore {
+    max_entries: usize,
+    max_bytes: u64,
+    max_entry_size: u64,
+    inner: Mutex<Inner>,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct Inner {
+    entries: HashMap<String, CacheEntry>,
+    index: Index,
+}
+
+impl MemoryStore {
+    /// Construct `MemoryStore` holding at most `max_entries` entries, evicting the least recently
+    /// used entry when full.
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            max_bytes: u64::MAX,
+            max_entry_size: u64::MAX,
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                index: Index::new(EvictionPolicy::Lru),
+            }),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Hold at most `max_bytes` bytes of entries, as measured by [`CacheEntry::size`].
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Don't store entries larger than `max_entry_size` bytes.
+    pub fn with_max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.max_entry_size = max_entry_size;
+        self
+    }
+
+    /// Set which entries are evicted first when the store is full.
+    pub fn with_eviction_policy(mut self, policy: EvictionPolicy) -> Self {
+        let inner = self.inner.get_mut().expect("store lock poisoned");
+        inner.entries.clear();
+        inner.index = Index::new(policy);
+        self
+    }
+
+    /// The number of entries in the store.
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .expect("store lock poisoned")
+            .entries
+            .len()
+    }
+
+    /// Returns true if the store holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl CacheStore for MemoryStore {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<CacheEntry>> {
+        let mut inner = self.inner.lock().expect("store lock poisoned");
+        inner.index.touch(key);
+        Ok(inner.entries.get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, entry: CacheEntry) -> anyhow::Result<()> {
+        let size = entry.size();
+        let mut inner = self.inner.lock().expect("store lock poisoned");
+        if size > self.max_entry_size {
+            inner.entries.remove(key);
+            inner.index.remove(key);
+        } else {
+            inner.entries.insert(key.to_owned(), entry);
+            inner.index.insert(key, size);
+            for evicted in inner.index.evict(self.max_entries, self.max_bytes, key) {
+                inner.entries.remove(&evicted);
+                self.stats.record_eviction();
+            }
+        }
+        self.stats.set_usage(inner.index.len(), inner.index.bytes());
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().expect("store lock poisoned");
+        inner.entries.remove(key);
+        inner.index.remove(key);
+        self.stats.set_usage(inner.index.len(), inner.index.bytes());
+        Ok(())
+    }
+
+    fn stats(&self) -> Option<CacheStats> {
+        Some(self.stats.clone())
+    }
+}
@@ -0,0 +1,162 @@
+//! `NegativeCacheMiddleware` remembers recent failures.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::{Extensions, StatusCode};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Clock, DryRunResponse, Error, Middleware, Next, Result, SystemClock};
+use thiserror::Error;
+
+use crate::response::CachedResponse;
+
+/// Error returned by [`NegativeCacheMiddleware`] when the request recently failed to connect.
+#[derive(Debug, Error)]
+#[error("Request to {url} failed recently ({reason}), not retrying for {retry_in:?}")]
+pub struct RecentFailure {
+    /// The URL of the request.
+    pub url: Url,
+    /// The error the last attempt failed with.
+    pub reason: String,
+    /// How long until the URL is contacted again.
+    pub retry_in: Duration,
+}
+
+#[derive(Clone, Debug)]
+enum Failure {
+    Connect(String),
+    Status(Arc<CachedResponse>),
+}
+
+/// `NegativeCacheMiddleware` remembers hard failures for a short time and fails fast instead of
+/// contacting the origin again, protecting hot loops that hammer a known-dead endpoint.
+///
+/// The following failures are remembered per method and URL:
+/// * connection errors, including DNS resolution failures, which are then returned as
+///   [`RecentFailure`] errors;
+/// * responses with a `404 Not Found` or `410 Gone` status by default, see
+///   [`with_statuses`](Self::with_statuses), which are then replayed. Bodies over 64 KiB are
+///   dropped from replayed responses.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_caching::NegativeCacheMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(NegativeCacheMiddleware::new(Duration::from_secs(10)))
+///     .build();
+/// ```
+pub struct NegativeCacheMiddleware {
+    ttl: Duration,
+    statuses: Vec<StatusCode>,
+    clock: Arc<dyn Clock>,
+    failures: Mutex<HashMap<String, (Failure, Instant)>>,
+}
+
+/// Largest response body kept when remembering a failed response.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+impl NegativeCacheMiddleware {
+    /// Construct `NegativeCacheMiddleware` remembering failures for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            statuses: vec![StatusCode::NOT_FOUND, StatusCode::GONE],
+            clock: Arc::new(SystemClock),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set the response statuses which are remembered as failures.
+    pub fn with_statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Use `clock` to expire remembered failures.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Forgets all remembered failures.
+    pub fn clear(&self) {
+        self.failures
+            .lock()
+            .expect("failures lock poisoned")
+            .clear();
+    }
+
+    fn remember(&self, key: String, failure: Failure) {
+        let now = self.clock.now();
+        let mut failures = self.failures.lock().expect("failures lock poisoned");
+        failures.retain(|_, (_, expires)| *expires > now);
+        failures.insert(key, (failure, now + self.ttl));
+    }
+
+    fn recall(&self, key: &str) -> Option<(Failure, Duration)> {
+        let failures = self.failures.lock().expect("failures lock poisoned");
+        let (failure, expires) = failures.get(key)?;
+        let retry_in = expires.checked_duration_since(self.clock.now())?;
+        Some((failure.clone(), retry_in))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for NegativeCacheMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let key = format!("{} {}", req.method(), req.url());
+        match self.recall(&key) {
+            Some((Failure::Connect(reason), retry_in)) => {
+                return Err(Error::middleware(RecentFailure {
+                    url: req.url().clone(),
+                    reason,
+                    retry_in,
+                }))
+            }
+            Some((Failure::Status(res), _)) => return Ok(res.to_response()),
+            None => {}
+        }
+
+        let res = match next.run(req, extensions).await {
+            Ok(res) => res,
+            Err(Error::Reqwest(e)) if e.is_connect() => {
+                self.remember(key, Failure::Connect(e.to_string()));
+                return Err(Error::Reqwest(e));
+            }
+            Err(e) => return Err(e),
+        };
+        if !self.statuses.contains(&res.status())
+            || res.extensions().get::<DryRunResponse>().is_some()
+        {
+            return Ok(res);
+        }
+        match CachedResponse::read(res, MAX_BODY_SIZE).await? {
+            Ok((response, response_extensions)) => {
+                let mut res = response.to_response();
+                res.extensions_mut().extend(response_extensions);
+                self.remember(key, Failure::Status(Arc::new(response)));
+                Ok(res)
+            }
+            Err(res) => {
+                let mut response = CachedResponse {
+                    url: res.url().clone(),
+                    status: res.status(),
+                    version: res.version(),
+                    headers: res.headers().clone(),
+                    body: Default::default(),
+                };
+                response.headers.remove(http::header::CONTENT_LENGTH);
+                self.remember(key, Failure::Status(Arc::new(response)));
+                Ok(res)
+            }
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! `QueryCacheMiddleware` briefly caches the responses to queries sent with `POST`.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::channel::oneshot;
+use futures::future::{FutureExt, Shared};
+use http::{header, Extensions};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Clock, Middleware, Next, Result, SystemClock};
+
+use crate::cache::CacheStatus;
+use crate::response::CachedResponse;
+
+type SafeFn = dyn Fn(&Request) -> bool + Send + Sync + 'static;
+
+type Flight = Shared<oneshot::Receiver<Option<Arc<CachedResponse>>>>;
+
+enum Slot {
+    InFlight(u64, Flight),
+    Cached(Arc<CachedResponse>, Instant),
+}
+
+/// `QueryCacheMiddleware` shares the responses to identical requests sent within a short time
+/// window, for APIs using `POST` for queries which are in fact safe to repeat, such as GraphQL
+/// queries or search endpoints.
+///
+/// Since `POST` requests usually have side effects, nothing is cached until requests are marked
+/// safe with [`with_safe_requests`]: only requests for which one of the predicates returns true
+/// are shared. They are keyed by method, URL, body, and `Authorization` and `Cookie` headers, so
+/// that responses aren't shared across callers, and requests with a streaming body are never
+/// shared. Requests with the same key sent while one is in flight wait for its response rather
+/// than being sent too.
+///
+/// Only successful responses whose body is at most `max_body_size` bytes (1 MiB by default) are
+/// kept, for the time to live given to [`new`], and at most `max_entries` of them (1000 by
+/// default). Shared responses are marked with a [`CacheStatus::Hit`] extension, and the others
+/// with [`CacheStatus::Miss`].
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_caching::QueryCacheMiddleware;
+///
+/// let queries = QueryCacheMiddleware::new(Duration::from_secs(5)).with_safe_requests(|req| {
+///     req.url().path() == "/graphql"
+///         && !req
+///             .body()
+///             .and_then(|body| body.as_bytes())
+///             .is_some_and(|body| body.starts_with(b"{\"query\":\"mutation"))
+/// });
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(queries).build();
+/// ```
+///
+/// [`new`]: Self::new
+/// [`with_safe_requests`]: Self::with_safe_requests
+pub struct QueryCacheMiddleware {
+    ttl: Duration,
+    safe: Vec<Box<SafeFn>>,
+    max_body_size: usize,
+    max_entries: usize,
+    clock: Arc<dyn Clock>,
+    slots: Arc<Mutex<HashMap<String, Slot>>>,
+    next_id: AtomicU64,
+}
+
+impl std::fmt::Debug for QueryCacheMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryCacheMiddleware")
+            .field("ttl", &self.ttl)
+            .field("max_body_size", &self.max_body_size)
+            .field("max_entries", &self.max_entries)
+            .finish_non_exhaustive()
+    }
+}
+
+impl QueryCacheMiddleware {
+    /// Construct `QueryCacheMiddleware` keeping responses for `ttl`, without safe requests.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            safe: Vec::new(),
+            max_body_size: 1024 * 1024,
+            max_entries: 1000,
+            clock: Arc::new(SystemClock),
+            slots: Arc::default(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Share the responses to the requests for which `safe` returns true.
+    pub fn with_safe_requests<F>(mut self, safe: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.safe.push(Box::new(safe));
+        self
+    }
+
+    /// Set the largest response body that is kept.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Set the largest number of responses kept at once.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Use `clock` to expire responses.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Forgets all kept responses.
+    pub fn clear(&self) {
+        let mut slots = self.slots.lock().expect("query cache lock poisoned");
+        slots.retain(|_, slot| matches!(slot, Slot::InFlight(..)));
+    }
+
+    /// The key of `req`, or `None` if its response can't be shared.
+    fn key(&self, req: &Request) -> Option<String> {
+        if !self.safe.iter().any(|safe| safe(req)) {
+            return None;
+        }
+        let body = match req.body() {
+            Some(body) => body.as_bytes()?,
+            None => &[],
+        };
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        for name in [header::AUTHORIZATION, header::COOKIE] {
+            for value in req.headers().get_all(name) {
+                value.as_bytes().hash(&mut hasher);
+            }
+        }
+        Some(format!(
+            "{} {} {}:{:016x}",
+            req.method(),
+            req.url(),
+            body.len(),
+            hasher.finish()
+        ))
+    }
+
+    fn store(&self, key: &str, id: u64, response: Arc<CachedResponse>) {
+        let now = self.clock.now();
+        let mut slots = self.slots.lock().expect("query cache lock poisoned");
+        slots.retain(|_, slot| !matches!(slot, Slot::Cached(_, expires) if *expires <= now));
+        let cached = slots
+            .values()
+            .filter(|slot| matches!(slot, Slot::Cached(..)))
+            .count();
+        if let Some(slot) = slots.get_mut(key) {
+            if matches!(slot, Slot::InFlight(current, _) if *current == id)
+                && cached < self.max_entries
+            {
+                *slot = Slot::Cached(response, now + self.ttl);
+            }
+        }
+    }
+}
+
+/// Removes the flight of the leader once it's done, unless its response was kept, even if it
+/// was cancelled.
+struct FlightGuard {
+    slots: Arc<Mutex<HashMap<String, Slot>>>,
+    key: String,
+    id: u64,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        let mut slots = self.slots.lock().expect("query cache lock poisoned");
+        if matches!(slots.get(&self.key), Some(Slot::InFlight(id, _)) if *id == self.id) {
+            slots.remove(&self.key);
+        }
+    }
+}
+
+fn hit(response: &CachedResponse) -> Response {
+    let mut res = response.to_response();
+    res.extensions_mut().insert(CacheStatus::Hit);
+    res
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for QueryCacheMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let key = match self.key(&req) {
+            Some(key) => key,
+            None => return next.run(req, extensions).await,
+        };
+
+        let leader = {
+            let now = self.clock.now();
+            let mut slots = self.slots.lock().expect("query cache lock poisoned");
+            match slots.get(&key) {
+                Some(Slot::Cached(response, expires)) if *expires > now => {
+                    tracing::debug!("Sharing cached response for {}", req.url());
+                    return Ok(hit(response));
+                }
+                Some(Slot::InFlight(_, flight)) => Err(flight.clone()),
+                _ => {
+                    let (sender, receiver) = oneshot::channel();
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    slots.insert(key.clone(), Slot::InFlight(id, receiver.shared()));
+                    let guard = FlightGuard {
+                        slots: self.slots.clone(),
+                        key,
+                        id,
+                    };
+                    Ok((sender, guard))
+                }
+            }
+        };
+
+        let (sender, guard) = match leader {
+            Ok(leader) => leader,
+            Err(flight) => {
+                if let Ok(Some(response)) = flight.await {
+                    tracing::debug!("Sharing in-flight response for {}", req.url());
+                    return Ok(hit(&response));
+                }
+                return next.run(req, extensions).await;
+            }
+        };
+
+        let res = match next.run(req, extensions).await {
+            Ok(res) => res,
+            Err(e) => {
+                let _ = sender.send(None);
+                return Err(e);
+            }
+        };
+        let read = if res.status().is_success() {
+            CachedResponse::read(res, self.max_body_size).await?
+        } else {
+            Err(res)
+        };
+        let mut res = match read {
+            Ok((response, response_extensions)) => {
+                let response = Arc::new(response);
+                self.store(&guard.key, guard.id, response.clone());
+                let _ = sender.send(Some(response.clone()));
+                let mut res = response.to_response();
+                res.extensions_mut().extend(response_extensions);
+                res
+            }
+            Err(res) => {
+                let _ = sender.send(None);
+                res
+            }
+        };
+        res.extensions_mut().insert(CacheStatus::Miss);
+        Ok(res)
+    }
+}
@@ -0,0 +1,103 @@
+use bytes::{Bytes, BytesMut};
+use futures::{future, stream, StreamExt};
+use http::response::Parts;
+use http::{HeaderMap, StatusCode, Version};
+use http_body_util::BodyExt;
+use reqwest::{Body, ResponseBuilderExt, Url};
+use reqwest_middleware::Result;
+
+/// A response whose body has been read in full, so that it can be stored and handed out any
+/// number of times.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedResponse {
+    /// The final URL of the response.
+    pub url: Url,
+    /// The response status.
+    pub status: StatusCode,
+    /// The HTTP version of the response.
+    pub version: Version,
+    /// The response headers.
+    pub headers: HeaderMap,
+    /// The response body.
+    pub body: Bytes,
+}
+
+impl CachedResponse {
+    /// Reads the body of `res`, giving up once it exceeds `max_body_size` bytes. In that case
+    /// the response is returned as is, with the part of the body read so far put back.
+    pub(crate) async fn read(
+        res: reqwest::Response,
+        max_body_size: usize,
+    ) -> Result<std::result::Result<(Self, http::Extensions), reqwest::Response>> {
+        let url = res.url().clone();
+        if res
+            .content_length()
+            .is_some_and(|len| len > max_body_size as u64)
+        {
+            return Ok(Err(res));
+        }
+
+        let (parts, mut body) = http::Response::from(res).into_parts();
+        let mut buffered = BytesMut::new();
+        while let Some(frame) = body.frame().await {
+            if let Ok(data) = frame?.into_data() {
+                buffered.extend_from_slice(&data);
+            }
+            if buffered.len() > max_body_size {
+                let prefix = buffered.freeze();
+                let rest = http_body_util::BodyStream::new(body).filter_map(|frame| {
+                    future::ready(match frame {
+                        Ok(frame) => frame.into_data().ok().map(Ok),
+                        Err(e) => Some(Err(e)),
+                    })
+                });
+                let body = Body::wrap_stream(stream::once(async move { Ok(prefix) }).chain(rest));
+                return Ok(Err(rebuild(url, parts, body)));
+            }
+        }
+
+        let Parts {
+            status,
+            version,
+            headers,
+            extensions,
+            ..
+        } = parts;
+        Ok(Ok((
+            Self {
+                url,
+                status,
+                version,
+                headers,
+                body: buffered.freeze(),
+            },
+            extensions,
+        )))
+    }
+
+    /// Builds a new [`reqwest::Response`] from the cached response.
+    pub fn to_response(&self) -> reqwest::Response {
+        let mut res = http::Response::builder()
+            .url(self.url.clone())
+            .body(self.body.clone())
+            .expect("response is valid");
+        *res.status_mut() = self.status;
+        *res.version_mut() = self.version;
+        *res.headers_mut() = self.headers.clone();
+        reqwest::Response::from(res)
+    }
+}
+
+/// Reassembles a [`reqwest::Response`] taken apart with [`http::Response::from`], which drops the
+/// URL.
+fn rebuild(url: Url, parts: Parts, body: Body) -> reqwest::Response {
+    let mut res = http::Response::builder()
+        .url(url)
+        .body(body)
+        .expect("response is valid");
+    *res.status_mut() = parts.status;
+    *res.version_mut() = parts.version;
+    *res.headers_mut() = parts.headers;
+    res.extensions_mut().extend(parts.extensions);
+    reqwest::Response::from(res)
+}
@@ -0,0 +1,103 @@
+//! `CacheStats` counts how effective a cache is.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    revalidations: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    entries: AtomicU64,
+    bytes_stored: AtomicU64,
+}
+
+/// Counters describing the effectiveness of a cache, shared between a [`CacheMiddleware`] and
+/// its [`CacheStore`].
+///
+/// The counters are updated live; get them with [`CacheMiddleware::stats`], or from the built-in
+/// stores directly.
+///
+/// ```
+/// use reqwest_caching::{CacheMiddleware, MemoryStore};
+///
+/// let cache = CacheMiddleware::new(MemoryStore::new(1000));
+/// let stats = cache.stats();
+/// // ... build a client with the cache and send requests ...
+/// println!(
+///     "{} hits, {} misses, {} bytes stored",
+///     stats.hits(),
+///     stats.misses(),
+///     stats.bytes_stored()
+/// );
+/// ```
+///
+/// [`CacheMiddleware`]: crate::CacheMiddleware
+/// [`CacheMiddleware::stats`]: crate::CacheMiddleware::stats
+/// [`CacheStore`]: crate::CacheStore
+#[derive(Clone, Debug, Default)]
+pub struct CacheStats(Arc<Counters>);
+
+impl CacheStats {
+    /// Number of responses served from the cache without contacting the origin, including stale
+    /// responses.
+    pub fn hits(&self) -> u64 {
+        self.0.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of cached responses served after the origin confirmed they were still valid.
+    pub fn revalidations(&self) -> u64 {
+        self.0.revalidations.load(Ordering::Relaxed)
+    }
+
+    /// Number of responses which came from the origin.
+    pub fn misses(&self) -> u64 {
+        self.0.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of entries evicted to make room for others.
+    pub fn evictions(&self) -> u64 {
+        self.0.evictions.load(Ordering::Relaxed)
+    }
+
+    /// Number of entries currently stored.
+    pub fn entries(&self) -> u64 {
+        self.0.entries.load(Ordering::Relaxed)
+    }
+
+    /// Number of bytes currently stored.
+    pub fn bytes_stored(&self) -> u64 {
+        self.0.bytes_stored.load(Ordering::Relaxed)
+    }
+
+    /// The proportion of requests answered by the cache, counting revalidations as hits.
+    pub fn hit_ratio(&self) -> f64 {
+        let hits = self.hits() + self.revalidations();
+        let total = hits + self.misses();
+        if total == 0 {
+            return 0.0;
+        }
+        hits as f64 / total as f64
+    }
+
+    pub(crate) fn record_hit(&self) {
+        self.0.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_revalidation(&self) {
+        self.0.revalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_miss(&self) {
+        self.0.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_eviction(&self) {
+        self.0.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_usage(&self, entries: usize, bytes: u64) {
+        self.0.entries.store(entries as u64, Ordering::Relaxed);
+        self.0.bytes_stored.store(bytes, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,70 @@
+//! The storage backend of [`CacheMiddleware`](crate::CacheMiddleware).
+use std::time::SystemTime;
+
+use http::HeaderMap;
+
+use crate::response::CachedResponse;
+use crate::stats::CacheStats;
+
+/// Metadata stored alongside a cached response, used to compute its age and match it to
+/// requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheMetadata {
+    /// When the request which produced the response was sent.
+    pub request_time: SystemTime,
+    /// When the response was received.
+    pub response_time: SystemTime,
+    /// The headers of the request which produced the response that are named by the response's
+    /// `Vary` header.
+    pub request_headers: HeaderMap,
+}
+
+/// An entry of a [`CacheStore`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// The cached response.
+    pub response: CachedResponse,
+    /// Metadata about the response.
+    pub metadata: CacheMetadata,
+}
+
+impl CacheEntry {
+    /// An estimate of the memory taken by the entry, in bytes: the size of the response body and
+    /// of all headers.
+    pub fn size(&self) -> u64 {
+        let headers = |headers: &HeaderMap| -> usize {
+            headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum()
+        };
+        (self.response.body.len()
+            + headers(&self.response.headers)
+            + headers(&self.metadata.request_headers)) as u64
+    }
+}
+
+/// Storage for the entries of a [`CacheMiddleware`](crate::CacheMiddleware).
+///
+/// [`MemoryStore`](crate::MemoryStore) and [`DiskStore`](crate::DiskStore) are provided; other
+/// backends such as Redis or S3 can be plugged in by implementing this trait. Errors returned by
+/// a store are logged and otherwise treated as cache misses, so a failing store never fails a
+/// request.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait CacheStore: 'static + Send + Sync {
+    /// Returns the entry stored under `key`, if any.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<CacheEntry>>;
+
+    /// Stores `entry` under `key`, replacing any previous entry.
+    async fn put(&self, key: &str, entry: CacheEntry) -> anyhow::Result<()>;
+
+    /// Removes the entry stored under `key`, if any.
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+    /// The counters of the store, which the [`CacheMiddleware`](crate::CacheMiddleware) using it
+    /// also records hits and misses in. Stores which don't keep count return `None`.
+    fn stats(&self) -> Option<CacheStats> {
+        None
+    }
+}
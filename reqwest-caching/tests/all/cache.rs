@@ -0,0 +1,335 @@
+use std::time::Duration;
+
+use reqwest::{Client, StatusCode};
+use reqwest_caching::{
+    CacheMiddleware, CacheMiss, CacheStatus, CacheStore, DiskStore, MemoryStore, Offline,
+};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn get(client: &ClientWithMiddleware, url: String) -> (CacheStatus, String) {
+    let res = client.get(url).send().await.unwrap();
+    let status = *res.extensions().get::<CacheStatus>().unwrap();
+    (status, res.text().await.unwrap())
+}
+
+async fn assert_fresh_responses_are_served_from_cache<S: CacheStore>(store: S) {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("cache-control", "max-age=60")
+                .set_body_string("cached"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(CacheMiddleware::new(store))
+        .build();
+
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Miss, "cached".to_owned())
+    );
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Hit, "cached".to_owned())
+    );
+}
+
+#[tokio::test]
+async fn assert_memory_store_serves_fresh_responses() {
+    assert_fresh_responses_are_served_from_cache(MemoryStore::new(10)).await;
+}
+
+#[tokio::test]
+async fn assert_disk_store_serves_fresh_responses() {
+    let dir = std::env::temp_dir().join(format!("reqwest-caching-test-{}", std::process::id()));
+    assert_fresh_responses_are_served_from_cache(DiskStore::new(&dir).unwrap()).await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn assert_huge_age_is_stale() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("cache-control", "max-age=60")
+                .insert_header("age", "18446744073709551615")
+                .set_body_string("stale"),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(CacheMiddleware::new(MemoryStore::new(10)))
+        .build();
+
+    for _ in 0..2 {
+        let (status, body) = get(&client, server.uri()).await;
+        assert_ne!(status, CacheStatus::Hit);
+        assert_eq!(body, "stale");
+    }
+}
+
+#[tokio::test]
+async fn assert_stale_responses_are_revalidated() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("cache-control", "no-cache")
+                .insert_header("etag", "\"v1\"")
+                .set_body_string("cached"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(CacheMiddleware::new(MemoryStore::new(10)))
+        .build();
+
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Miss, "cached".to_owned())
+    );
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.extensions().get::<CacheStatus>(),
+        Some(&CacheStatus::Revalidated)
+    );
+    assert_eq!(res.text().await.unwrap(), "cached");
+}
+
+#[tokio::test]
+async fn assert_stale_response_served_on_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("cache-control", "max-age=0")
+                .set_body_string("cached"),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            CacheMiddleware::new(MemoryStore::new(10)).with_stale_if_error(Duration::from_secs(60)),
+        )
+        .build();
+
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Miss, "cached".to_owned())
+    );
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Stale, "cached".to_owned())
+    );
+}
+
+#[tokio::test]
+async fn assert_stale_response_served_while_revalidating() {
+    let server = MockServer::start().await;
+    let response = |body: &str| {
+        ResponseTemplate::new(200)
+            .insert_header("cache-control", "max-age=0, stale-while-revalidate=60")
+            .set_body_string(body)
+    };
+    Mock::given(method("GET"))
+        .respond_with(response("v1"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(response("v2"))
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            CacheMiddleware::new(MemoryStore::new(10))
+                .with_stale_while_revalidate(Duration::from_secs(60), Client::new()),
+        )
+        .build();
+
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Miss, "v1".to_owned())
+    );
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Stale, "v1".to_owned())
+    );
+
+    // The refreshed response is eventually served.
+    for _ in 0..50 {
+        if get(&client, server.uri()).await == (CacheStatus::Stale, "v2".to_owned()) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("cached response was never refreshed");
+}
+
+#[tokio::test]
+async fn assert_offline_mode_only_serves_from_cache() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("cache-control", "max-age=0")
+                .set_body_string("cached"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let cache = CacheMiddleware::new(MemoryStore::new(10));
+    let offline = cache.offline_mode();
+    let client = ClientBuilder::new(Client::new()).with(cache).build();
+
+    get(&client, server.uri()).await;
+    offline.set(true);
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Stale, "cached".to_owned())
+    );
+    let err = client
+        .get(format!("{}/uncached", server.uri()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<CacheMiss>()));
+
+    // Offline mode can also be requested for a single request.
+    offline.set(false);
+    let err = client
+        .get(format!("{}/uncached", server.uri()))
+        .with_extension(Offline)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<CacheMiss>()));
+}
+
+#[tokio::test]
+async fn assert_dry_run_responses_are_not_stored() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("cache-control", "max-age=0")
+                .set_body_string("cached"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let cache = CacheMiddleware::new(MemoryStore::new(10));
+    let offline = cache.offline_mode();
+    let client = ClientBuilder::new(Client::new()).with(cache).build();
+
+    get(&client, server.uri()).await;
+    let req = client.get(server.uri()).dry_run().await.unwrap();
+    assert!(req.is_some());
+    offline.set(true);
+    assert_eq!(
+        get(&client, server.uri()).await,
+        (CacheStatus::Stale, "cached".to_owned())
+    );
+}
+
+#[tokio::test]
+async fn assert_cache_key_includes_selected_headers_and_ignores_query_params() {
+    let server = MockServer::start().await;
+    for tenant in ["a", "b"] {
+        Mock::given(method("GET"))
+            .and(header("x-tenant", tenant))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("cache-control", "max-age=60")
+                    .set_body_string(tenant),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            CacheMiddleware::new(MemoryStore::new(10))
+                .with_key_headers([reqwest::header::HeaderName::from_static("x-tenant")])
+                .ignore_query_params(["nonce"]),
+        )
+        .build();
+
+    for (tenant, nonce) in [("a", 1), ("b", 2), ("a", 3), ("b", 4)] {
+        let res = client
+            .get(format!("{}/?nonce={}", server.uri(), nonce))
+            .header("x-tenant", tenant)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().await.unwrap(), tenant);
+    }
+}
+
+#[tokio::test]
+async fn assert_vary_headers_must_match() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("cache-control", "max-age=60")
+                .insert_header("vary", "accept-language"),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(CacheMiddleware::new(MemoryStore::new(10)))
+        .build();
+    let send = |language: &'static str| {
+        client
+            .get(server.uri())
+            .header("accept-language", language)
+            .send()
+    };
+
+    let res = send("en").await.unwrap();
+    assert_eq!(
+        res.extensions().get::<CacheStatus>(),
+        Some(&CacheStatus::Miss)
+    );
+    let res = send("en").await.unwrap();
+    assert_eq!(
+        res.extensions().get::<CacheStatus>(),
+        Some(&CacheStatus::Hit)
+    );
+    let res = send("fr").await.unwrap();
+    assert_eq!(
+        res.extensions().get::<CacheStatus>(),
+        Some(&CacheStatus::Miss)
+    );
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_caching::CoalesceMiddleware;
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_concurrent_requests_are_coalesced() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("hello")
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(CoalesceMiddleware::new())
+        .build();
+    let send = || async { client.get(server.uri()).send().await.unwrap().text().await };
+
+    let (first, second, third) = tokio::join!(send(), send(), send());
+    for body in [first, second, third] {
+        assert_eq!(body.unwrap(), "hello");
+    }
+}
+
+#[tokio::test]
+async fn assert_large_responses_are_not_shared() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("a body larger than the limit")
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(CoalesceMiddleware::new().with_max_body_size(4))
+        .build();
+    let send = || async { client.get(server.uri()).send().await.unwrap().text().await };
+
+    let (first, second) = tokio::join!(send(), send());
+    assert_eq!(first.unwrap(), "a body larger than the limit");
+    assert_eq!(second.unwrap(), "a body larger than the limit");
+}
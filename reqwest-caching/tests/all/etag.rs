@@ -0,0 +1,40 @@
+use reqwest::{Client, StatusCode};
+use reqwest_caching::{CacheStatus, EtagMiddleware, MemoryStore};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_not_modified_returns_remembered_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(3)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("etag", "\"v1\"")
+                .set_body_string("remembered"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(EtagMiddleware::new(MemoryStore::new(10)))
+        .build();
+
+    for _ in 0..3 {
+        let res = client.get(server.uri()).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await.unwrap(), "remembered");
+    }
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(
+        res.extensions().get::<CacheStatus>(),
+        Some(&CacheStatus::Revalidated)
+    );
+}
@@ -0,0 +1,6 @@
+mod cache;
+mod coalesce;
+mod etag;
+mod negative;
+mod query;
+mod store;
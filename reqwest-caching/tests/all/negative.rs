@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::{Client, StatusCode};
+use reqwest_caching::{NegativeCacheMiddleware, RecentFailure};
+use reqwest_middleware::{ClientBuilder, Error, MockClock};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_not_found_is_remembered() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("gone fishing"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(NegativeCacheMiddleware::new(Duration::from_secs(60)))
+        .build();
+
+    for _ in 0..2 {
+        let res = client.get(server.uri()).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.text().await.unwrap(), "gone fishing");
+    }
+}
+
+#[tokio::test]
+async fn assert_connection_errors_fail_fast() {
+    // Find a port nobody listens on.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let client = ClientBuilder::new(Client::new())
+        .with(NegativeCacheMiddleware::new(Duration::from_secs(60)))
+        .build();
+
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(matches!(err, Error::Reqwest(err) if err.is_connect()));
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<RecentFailure>()));
+}
+
+#[tokio::test]
+async fn assert_failures_expire() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let client = ClientBuilder::new(Client::new())
+        .with(NegativeCacheMiddleware::new(Duration::from_secs(60)).with_clock(clock.clone()))
+        .build();
+
+    client.get(server.uri()).send().await.unwrap();
+    client.get(server.uri()).send().await.unwrap();
+    clock.advance(Duration::from_secs(61));
+    client.get(server.uri()).send().await.unwrap();
+}
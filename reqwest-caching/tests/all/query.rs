@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_caching::{CacheStatus, QueryCacheMiddleware};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, MockClock};
+use wiremock::matchers::{body_string, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(queries: QueryCacheMiddleware) -> ClientWithMiddleware {
+    ClientBuilder::new(Client::new()).with(queries).build()
+}
+
+fn graphql(req: &reqwest::Request) -> bool {
+    req.url().path() == "/graphql"
+}
+
+#[tokio::test]
+async fn shares_identical_safe_queries() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string("{users}"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("users"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_string("{teams}"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("teams"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client =
+        client(QueryCacheMiddleware::new(Duration::from_secs(5)).with_safe_requests(graphql));
+    let url = format!("{}/graphql", server.uri());
+
+    let first = client.post(&url).body("{users}").send().await.unwrap();
+    assert_eq!(first.extensions().get(), Some(&CacheStatus::Miss));
+    let second = client.post(&url).body("{users}").send().await.unwrap();
+    assert_eq!(second.extensions().get(), Some(&CacheStatus::Hit));
+    assert_eq!(second.text().await.unwrap(), "users");
+    let other = client.post(&url).body("{teams}").send().await.unwrap();
+    assert_eq!(other.text().await.unwrap(), "teams");
+}
+
+#[tokio::test]
+async fn leaves_requests_not_marked_safe_alone() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let client =
+        client(QueryCacheMiddleware::new(Duration::from_secs(5)).with_safe_requests(graphql));
+    let url = format!("{}/orders", server.uri());
+
+    client.post(&url).body("{}").send().await.unwrap();
+    client.post(&url).body("{}").send().await.unwrap();
+}
+
+#[tokio::test]
+async fn does_not_share_across_credentials() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let client =
+        client(QueryCacheMiddleware::new(Duration::from_secs(5)).with_safe_requests(graphql));
+    let url = format!("{}/graphql", server.uri());
+
+    for token in ["alice", "bob"] {
+        client
+            .post(&url)
+            .bearer_auth(token)
+            .body("{me}")
+            .send()
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn expires_responses_and_skips_failures() {
+    let server = MockServer::start().await;
+    Mock::given(body_string("{ok}"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(body_string("{broken}"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let clock = Arc::new(MockClock::new());
+    let client = client(
+        QueryCacheMiddleware::new(Duration::from_secs(5))
+            .with_safe_requests(graphql)
+            .with_clock(clock.clone()),
+    );
+    let url = format!("{}/graphql", server.uri());
+
+    client.post(&url).body("{ok}").send().await.unwrap();
+    client.post(&url).body("{ok}").send().await.unwrap();
+    clock.advance(Duration::from_secs(6));
+    client.post(&url).body("{ok}").send().await.unwrap();
+    client.post(&url).body("{broken}").send().await.unwrap();
+    client.post(&url).body("{broken}").send().await.unwrap();
+}
+
+#[tokio::test]
+async fn coalesces_concurrent_queries() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("hello")
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client =
+        client(QueryCacheMiddleware::new(Duration::from_secs(5)).with_safe_requests(graphql));
+    let url = format!("{}/graphql", server.uri());
+    let send = || async {
+        client
+            .post(&url)
+            .body("{}")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+    };
+
+    let (first, second) = tokio::join!(send(), send());
+    assert_eq!(first.unwrap(), "hello");
+    assert_eq!(second.unwrap(), "hello");
+}
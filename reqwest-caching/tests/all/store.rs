@@ -0,0 +1,77 @@
+use std::time::SystemTime;
+
+use reqwest::{Client, StatusCode, Version};
+use reqwest_caching::{
+    CacheEntry, CacheMetadata, CacheMiddleware, CacheStore, CachedResponse, EvictionPolicy,
+    MemoryStore,
+};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn entry(body: &'static str) -> CacheEntry {
+    CacheEntry {
+        response: CachedResponse {
+            url: "https://example.com".parse().unwrap(),
+            status: StatusCode::OK,
+            version: Version::HTTP_11,
+            headers: Default::default(),
+            body: body.into(),
+        },
+        metadata: CacheMetadata {
+            request_time: SystemTime::now(),
+            response_time: SystemTime::now(),
+            request_headers: Default::default(),
+        },
+    }
+}
+
+#[tokio::test]
+async fn assert_lfu_evicts_least_frequently_used() {
+    let store = MemoryStore::new(2).with_eviction_policy(EvictionPolicy::Lfu);
+    store.put("a", entry("a")).await.unwrap();
+    store.put("b", entry("b")).await.unwrap();
+    store.get("a").await.unwrap();
+    store.get("a").await.unwrap();
+    store.get("b").await.unwrap();
+    store.put("c", entry("c")).await.unwrap();
+
+    assert!(store.get("a").await.unwrap().is_some());
+    assert!(store.get("b").await.unwrap().is_none());
+    assert_eq!(store.stats().unwrap().evictions(), 1);
+}
+
+#[tokio::test]
+async fn assert_entries_over_size_limits_are_not_stored() {
+    let store = MemoryStore::new(10)
+        .with_max_entry_size(4)
+        .with_max_bytes(6);
+    store.put("small", entry("abc")).await.unwrap();
+    store.put("large", entry("abcde")).await.unwrap();
+    assert!(store.get("large").await.unwrap().is_none());
+
+    // Fits on its own, but not alongside the first entry.
+    store.put("other", entry("abcd")).await.unwrap();
+    assert!(store.get("small").await.unwrap().is_none());
+    assert_eq!(store.stats().unwrap().bytes_stored(), 4);
+}
+
+#[tokio::test]
+async fn assert_middleware_counts_hits_and_misses() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).insert_header("cache-control", "max-age=60"))
+        .mount(&server)
+        .await;
+
+    let cache = CacheMiddleware::new(MemoryStore::new(10));
+    let stats = cache.stats();
+    let client = ClientBuilder::new(Client::new()).with(cache).build();
+    for _ in 0..3 {
+        client.get(server.uri()).send().await.unwrap();
+    }
+
+    assert_eq!(stats.misses(), 1);
+    assert_eq!(stats.hits(), 2);
+    assert_eq!(stats.entries(), 1);
+}
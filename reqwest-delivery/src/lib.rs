@@ -0,0 +1,34 @@
+//! Reliable delivery of requests, built on [`reqwest_middleware`].
+//!
+//! Use [`OfflineQueue`] to store requests on disk and deliver them in order once the network is
+//! available, e.g. in desktop or mobile apps and agents running on flaky networks. Requests which
+//! can't be delivered are kept as dead letters.
+//!
+//! With the `webhook` feature, [`WebhookSender`] sends signed webhooks, retrying them for hours
+//! and recording every attempt.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use reqwest_delivery::OfflineQueue;
+//! use reqwest_middleware::ClientBuilder;
+//!
+//! # async fn run() -> reqwest_middleware::Result<()> {
+//! let client = ClientBuilder::new(reqwest::Client::new()).build();
+//! let queue = OfflineQueue::open(client.clone(), "outbox").unwrap();
+//! queue.enqueue(client.put("https://example.com/notes/1").body("Hello")).await?;
+//! queue.deliver().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod queue;
+#[cfg(feature = "webhook")]
+mod webhook;
+
+pub use queue::{BodyNotBuffered, DeliveryReport, OfflineQueue, QueuedRequest, StoredRequest};
+#[cfg(feature = "webhook")]
+pub use webhook::{
+    DeliveryAttempt, MemoryWebhookStore, WebhookDelivery, WebhookSender, WebhookStatus,
+    WebhookStore, WEBHOOK_ID, WEBHOOK_SIGNATURE, WEBHOOK_TIMESTAMP,
+};
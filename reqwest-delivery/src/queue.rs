@@ -0,0 +1,441 @@
+//! `OfflineQueue` keeps requests on disk until they can be delivered.
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use http::{HeaderName, HeaderValue, Method};
+use reqwest::{Request, Url};
+use reqwest_middleware::{ClientWithMiddleware, Clock, Error, RequestBuilder, Result, SystemClock};
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::{
+    DefaultRetryableStrategy, RetryDecision, RetryPolicy, Retryable, RetryableStrategy,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+const DEAD_LETTER_DIR: &str = "dead-letter";
+
+/// Error returned by [`OfflineQueue::enqueue`] for requests whose body is a stream, which can't
+/// be stored.
+#[derive(Debug, Error)]
+#[error("Request body can't be stored, only buffered bodies can be enqueued")]
+pub struct BodyNotBuffered;
+
+/// A request in a serializable form.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredRequest {
+    /// The request method.
+    pub method: String,
+    /// The request URL.
+    pub url: String,
+    /// The request headers, in order, with values decoded as UTF-8 lossily.
+    pub headers: Vec<(String, String)>,
+    /// The request body, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Vec<u8>>,
+}
+
+impl StoredRequest {
+    /// Capture `request`, failing with [`BodyNotBuffered`] if its body is a stream.
+    pub fn from_request(request: &Request) -> std::result::Result<Self, BodyNotBuffered> {
+        let body = match request.body() {
+            Some(body) => Some(body.as_bytes().ok_or(BodyNotBuffered)?.to_vec()),
+            None => None,
+        };
+        Ok(Self {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: request
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                    (name.to_string(), value)
+                })
+                .collect(),
+            body,
+        })
+    }
+
+    /// Rebuild the request.
+    pub fn to_request(&self) -> Result<Request> {
+        let method = Method::from_bytes(self.method.as_bytes()).map_err(Error::middleware)?;
+        let url = Url::parse(&self.url).map_err(Error::middleware)?;
+        let mut request = Request::new(method, url);
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(Error::middleware)?;
+            let value = HeaderValue::from_str(value).map_err(Error::middleware)?;
+            request.headers_mut().append(name, value);
+        }
+        *request.body_mut() = self.body.clone().map(Into::into);
+        Ok(request)
+    }
+}
+
+/// A request held by an [`OfflineQueue`], pending or dead-lettered.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedRequest {
+    /// The id returned by [`OfflineQueue::enqueue`], increasing in enqueuing order.
+    pub id: u64,
+    /// The request to deliver.
+    pub request: StoredRequest,
+    /// When the request was enqueued.
+    pub enqueued_at: SystemTime,
+    /// The number of failed delivery attempts.
+    pub attempts: u32,
+    /// When delivery is next attempted, after a transient failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_attempt_at: Option<SystemTime>,
+    /// The error of the last failed attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// The outcome of [`OfflineQueue::deliver`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryReport {
+    /// The number of requests delivered.
+    pub delivered: usize,
+    /// The number of requests moved to the dead letters.
+    pub dead_lettered: usize,
+    /// The number of requests still pending.
+    pub remaining: usize,
+    /// When the first pending request is due to be retried, if it failed transiently.
+    pub next_attempt_at: Option<SystemTime>,
+}
+
+/// What happened to a delivery attempt.
+enum Outcome {
+    Delivered,
+    /// The server couldn't be reached; try again later without counting the attempt.
+    Offline(String),
+    Failed(Retryable, String),
+}
+
+/// A durable queue of requests, delivered in order once the network is available.
+///
+/// Requests are written to a directory with [`enqueue`], one JSON file each, so that they survive
+/// restarts. [`deliver`] then sends them in the order they were enqueued, stopping at the first
+/// one which can't be delivered yet to preserve the order:
+///
+/// * when the server can't be reached, the request is kept as is, for the next call;
+/// * on transient failures, e.g. `503 Service Unavailable`, the request is retried after the
+///   delay given by the [`RetryPolicy`], and moved to the dead letters when the policy gives up;
+/// * on fatal failures, e.g. `400 Bad Request`, the request is moved to the dead letters
+///   straight away.
+///
+/// Dead letters are kept in the `dead-letter` subdirectory; they can be listed with
+/// [`dead_letters`], then [`requeue`]d or [`discard`]ed. A directory must only be used by one
+/// queue at a time.
+///
+/// ```no_run
+/// use reqwest_delivery::OfflineQueue;
+/// use reqwest_middleware::ClientBuilder;
+///
+/// # async fn run() -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new()).build();
+/// let queue = OfflineQueue::open(client.clone(), "outbox").unwrap();
+/// queue
+///     .enqueue(client.post("https://example.com/events").body("{\"kind\":\"click\"}"))
+///     .await?;
+/// // Later, e.g. when the network is back.
+/// let report = queue.deliver().await?;
+/// println!("{} delivered, {} pending", report.delivered, report.remaining);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`enqueue`]: Self::enqueue
+/// [`deliver`]: Self::deliver
+/// [`dead_letters`]: Self::dead_letters
+/// [`requeue`]: Self::requeue
+/// [`discard`]: Self::discard
+pub struct OfflineQueue {
+    client: ClientWithMiddleware,
+    dir: PathBuf,
+    next_id: AtomicU64,
+    retry_policy: Box<dyn RetryPolicy + Send + Sync>,
+    retryable_strategy: Box<dyn RetryableStrategy + Send + Sync>,
+    clock: Arc<dyn Clock>,
+    delivering: Mutex<()>,
+}
+
+impl std::fmt::Debug for OfflineQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OfflineQueue")
+            .field("dir", &self.dir)
+            .field("next_id", &self.next_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OfflineQueue {
+    /// Open the queue stored in `dir`, which is created if needed, sending requests with
+    /// `client`.
+    ///
+    /// By default, transient failures are retried with exponential backoff up to 10 times.
+    pub fn open(client: ClientWithMiddleware, dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(dir.join(DEAD_LETTER_DIR))?;
+        let last_id = ids(&dir)?
+            .into_iter()
+            .chain(ids(&dir.join(DEAD_LETTER_DIR))?)
+            .max();
+        Ok(Self {
+            client,
+            dir,
+            next_id: AtomicU64::new(last_id.map_or(0, |id| id + 1)),
+            retry_policy: Box::new(ExponentialBackoff::builder().build_with_max_retries(10)),
+            retryable_strategy: Box::new(DefaultRetryableStrategy),
+            clock: Arc::new(SystemClock),
+            delivering: Mutex::new(()),
+        })
+    }
+
+    /// Decide when to retry requests that failed transiently with `retry_policy`.
+    pub fn with_retry_policy<P>(mut self, retry_policy: P) -> Self
+    where
+        P: RetryPolicy + Send + Sync + 'static,
+    {
+        self.retry_policy = Box::new(retry_policy);
+        self
+    }
+
+    /// Decide which failures are transient with `retryable_strategy`.
+    pub fn with_retryable_strategy<S>(mut self, retryable_strategy: S) -> Self
+    where
+        S: RetryableStrategy + Send + Sync + 'static,
+    {
+        self.retryable_strategy = Box::new(retryable_strategy);
+        self
+    }
+
+    /// Use `clock` to tell when requests are due.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The directory the queue is stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Store `request` for delivery, returning its id.
+    ///
+    /// Fails with [`BodyNotBuffered`] if the request body is a stream.
+    pub async fn enqueue(&self, request: RequestBuilder) -> Result<u64> {
+        let request = request.build()?;
+        let request = StoredRequest::from_request(&request).map_err(Error::middleware)?;
+        let entry = QueuedRequest {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            request,
+            enqueued_at: self.clock.system_time(),
+            attempts: 0,
+            next_attempt_at: None,
+            last_error: None,
+        };
+        write(&self.dir, &entry)?;
+        tracing::debug!("Enqueued request {}", entry.id);
+        Ok(entry.id)
+    }
+
+    /// The requests waiting to be delivered, oldest first.
+    pub fn pending(&self) -> Result<Vec<QueuedRequest>> {
+        read_all(&self.dir)
+    }
+
+    /// The requests which couldn't be delivered, oldest first.
+    pub fn dead_letters(&self) -> Result<Vec<QueuedRequest>> {
+        read_all(&self.dir.join(DEAD_LETTER_DIR))
+    }
+
+    /// Move the dead letter `id` back to the queue, resetting its attempts. Returns false if
+    /// there is no such dead letter.
+    ///
+    /// The request keeps its place in the queue, i.e. it's delivered before requests enqueued
+    /// after it.
+    pub async fn requeue(&self, id: u64) -> Result<bool> {
+        let _delivering = self.delivering.lock().await;
+        let dead_letters = self.dir.join(DEAD_LETTER_DIR);
+        let mut entry = match read(&dead_letters, id)? {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        entry.attempts = 0;
+        entry.next_attempt_at = None;
+        entry.last_error = None;
+        write(&self.dir, &entry)?;
+        remove(&dead_letters, id)?;
+        Ok(true)
+    }
+
+    /// Delete the dead letter `id`. Returns false if there is no such dead letter.
+    pub fn discard(&self, id: u64) -> Result<bool> {
+        remove(&self.dir.join(DEAD_LETTER_DIR), id)
+    }
+
+    /// Send the pending requests in order, until the queue is empty or a request can't be
+    /// delivered yet.
+    ///
+    /// Concurrent calls are serialized, so a request is never sent twice at the same time.
+    pub async fn deliver(&self) -> Result<DeliveryReport> {
+        let _delivering = self.delivering.lock().await;
+        let ids = ids(&self.dir).map_err(Error::middleware)?;
+        let mut report = DeliveryReport::default();
+
+        for (i, &id) in ids.iter().enumerate() {
+            let mut entry = match read(&self.dir, id)? {
+                Some(entry) => entry,
+                None => continue,
+            };
+            if let Some(next_attempt_at) = entry.next_attempt_at {
+                if next_attempt_at > self.clock.system_time() {
+                    report.remaining = ids.len() - i;
+                    report.next_attempt_at = Some(next_attempt_at);
+                    break;
+                }
+            }
+
+            let (retryable, error) = match self.attempt(&entry).await {
+                Outcome::Delivered => {
+                    tracing::debug!("Delivered request {}", id);
+                    remove(&self.dir, id)?;
+                    report.delivered += 1;
+                    continue;
+                }
+                Outcome::Offline(error) => {
+                    tracing::debug!("Request {} not delivered, offline: {}", id, error);
+                    report.remaining = ids.len() - i;
+                    break;
+                }
+                Outcome::Failed(retryable, error) => (retryable, error),
+            };
+
+            let decision = match retryable {
+                Retryable::Transient => self
+                    .retry_policy
+                    .should_retry(entry.enqueued_at, entry.attempts),
+                Retryable::Fatal => RetryDecision::DoNotRetry,
+            };
+            entry.attempts += 1;
+            entry.last_error = Some(error);
+            match decision {
+                RetryDecision::Retry { execute_after } => {
+                    tracing::debug!("Request {} failed, retrying at {:?}", id, execute_after);
+                    entry.next_attempt_at = Some(execute_after);
+                    write(&self.dir, &entry)?;
+                    report.remaining = ids.len() - i;
+                    report.next_attempt_at = Some(execute_after);
+                    break;
+                }
+                RetryDecision::DoNotRetry => {
+                    tracing::warn!(
+                        "Request {} failed after {} attempts, moving it to the dead letters: {}",
+                        id,
+                        entry.attempts,
+                        entry.last_error.as_deref().unwrap_or_default()
+                    );
+                    entry.next_attempt_at = None;
+                    write(&self.dir.join(DEAD_LETTER_DIR), &entry)?;
+                    remove(&self.dir, id)?;
+                    report.dead_lettered += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    async fn attempt(&self, entry: &QueuedRequest) -> Outcome {
+        let request = match entry.request.to_request() {
+            Ok(request) => request,
+            Err(err) => return Outcome::Failed(Retryable::Fatal, err.to_string()),
+        };
+        let result = self.client.execute(request).await;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(Error::Reqwest(err)) = &result {
+            if err.is_connect() {
+                return Outcome::Offline(err.to_string());
+            }
+        }
+        let retryable = self.retryable_strategy.handle(&result);
+        match (retryable, result) {
+            (None, Ok(_)) => Outcome::Delivered,
+            (retryable, Ok(res)) => Outcome::Failed(
+                retryable.unwrap_or(Retryable::Fatal),
+                format!("HTTP status {}", res.status()),
+            ),
+            (retryable, Err(err)) => {
+                Outcome::Failed(retryable.unwrap_or(Retryable::Fatal), err.to_string())
+            }
+        }
+    }
+}
+
+fn path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:020}.json", id))
+}
+
+/// The ids of the requests in `dir`, in order.
+fn ids(dir: &Path) -> std::io::Result<Vec<u64>> {
+    let mut ids = Vec::new();
+    for file in fs::read_dir(dir)? {
+        let name = file?.file_name();
+        let id = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|id| id.parse::<u64>().ok());
+        ids.extend(id);
+    }
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+fn read(dir: &Path, id: u64) -> Result<Option<QueuedRequest>> {
+    match fs::read(path(dir, id)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|err| Error::Middleware(anyhow!("Invalid queued request {}: {}", id, err))),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::middleware(err)),
+    }
+}
+
+fn read_all(dir: &Path) -> Result<Vec<QueuedRequest>> {
+    let mut entries = Vec::new();
+    for id in ids(dir).map_err(Error::middleware)? {
+        entries.extend(read(dir, id)?);
+    }
+    Ok(entries)
+}
+
+/// Write `entry` atomically, so that a crash never leaves a truncated file behind.
+fn write(dir: &Path, entry: &QueuedRequest) -> Result<()> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let path = path(dir, entry.id);
+    let tmp = path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let bytes = serde_json::to_vec(entry).map_err(Error::middleware)?;
+    fs::write(&tmp, bytes)
+        .and_then(|()| fs::rename(&tmp, &path))
+        .map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            Error::middleware(err)
+        })
+}
+
+fn remove(dir: &Path, id: u64) -> Result<bool> {
+    match fs::remove_file(path(dir, id)) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(Error::middleware(err)),
+    }
+}
@@ -0,0 +1,398 @@
+//! `WebhookSender` delivers signed webhooks, retrying them for hours.
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::header::CONTENT_TYPE;
+use http::{HeaderName, HeaderValue};
+use reqwest::Url;
+use reqwest_middleware::{ClientWithMiddleware, Clock, Error, Result, SystemClock};
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::{
+    DefaultRetryableStrategy, Jitter, RetryDecision, RetryPolicy, Retryable, RetryableStrategy,
+};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+/// The header carrying the id of a webhook, the same for all its delivery attempts.
+pub const WEBHOOK_ID: HeaderName = HeaderName::from_static("webhook-id");
+/// The header carrying the Unix timestamp a webhook was signed at.
+pub const WEBHOOK_TIMESTAMP: HeaderName = HeaderName::from_static("webhook-timestamp");
+/// The default header carrying the signature of a webhook.
+pub const WEBHOOK_SIGNATURE: HeaderName = HeaderName::from_static("webhook-signature");
+
+/// Where a [`WebhookDelivery`] stands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookStatus {
+    /// The webhook is waiting for its next attempt.
+    Pending,
+    /// The receiver accepted the webhook.
+    Delivered,
+    /// The webhook was given up on.
+    Failed,
+}
+
+/// One attempt at delivering a webhook.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    /// When the attempt was made.
+    pub sent_at: SystemTime,
+    /// How long the receiver took to respond.
+    pub duration: Duration,
+    /// The status of the response, if one was received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Why the request failed, if no response was received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A webhook and the record of its delivery attempts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    /// A unique id, sent in the `webhook-id` header so that receivers can ignore duplicates.
+    pub id: String,
+    /// Where the webhook is sent.
+    pub url: String,
+    /// The JSON payload.
+    pub payload: Vec<u8>,
+    /// When the webhook was created.
+    pub created_at: SystemTime,
+    /// Where the delivery stands.
+    pub status: WebhookStatus,
+    /// When the next attempt is due, while the webhook is pending.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_attempt_at: Option<SystemTime>,
+    /// The attempts made so far, oldest first.
+    #[serde(default)]
+    pub attempts: Vec<DeliveryAttempt>,
+}
+
+/// Persistence hooks for [`WebhookSender`], so that pending webhooks survive restarts.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait WebhookStore: Send + Sync + 'static {
+    /// Store `delivery`, replacing any previous version with the same id. This is called when
+    /// the webhook is created and after each attempt.
+    async fn save(&self, delivery: &WebhookDelivery) -> Result<()>;
+
+    /// The pending webhooks, to be resumed with [`WebhookSender::resume`].
+    async fn pending(&self) -> Result<Vec<WebhookDelivery>>;
+}
+
+/// A [`WebhookStore`] keeping webhooks in memory, mostly useful for tests.
+#[derive(Debug, Default)]
+pub struct MemoryWebhookStore {
+    deliveries: Mutex<BTreeMap<String, WebhookDelivery>>,
+}
+
+impl MemoryWebhookStore {
+    /// Construct an empty `MemoryWebhookStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The webhook with id `id`.
+    pub fn get(&self, id: &str) -> Option<WebhookDelivery> {
+        self.deliveries
+            .lock()
+            .expect("webhook store lock poisoned")
+            .get(id)
+            .cloned()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl WebhookStore for MemoryWebhookStore {
+    async fn save(&self, delivery: &WebhookDelivery) -> Result<()> {
+        self.deliveries
+            .lock()
+            .expect("webhook store lock poisoned")
+            .insert(delivery.id.clone(), delivery.clone());
+        Ok(())
+    }
+
+    async fn pending(&self) -> Result<Vec<WebhookDelivery>> {
+        Ok(self
+            .deliveries
+            .lock()
+            .expect("webhook store lock poisoned")
+            .values()
+            .filter(|delivery| delivery.status == WebhookStatus::Pending)
+            .cloned()
+            .collect())
+    }
+}
+
+/// `WebhookSender` sends webhooks signed with a shared secret, retrying failed deliveries with an
+/// exponential backoff.
+///
+/// Each request carries the `webhook-id` and `webhook-timestamp` headers, and a signature header
+/// of the form `t=<timestamp>,v1=<signature>`, where the signature is the hex-encoded
+/// HMAC-SHA256 of `<timestamp>.<payload>`. Receivers should check it and reject old timestamps
+/// to prevent replays.
+///
+/// Webhooks are retried on transient failures, i.e. server errors, `429 Too Many Requests` and
+/// network errors, with delays growing from 30 seconds to an hour, with jitter, for up to a day.
+/// Other failures are final. Every attempt is recorded in the [`WebhookDelivery`], which is
+/// saved to the [`WebhookStore`] if there is one; [`resume`] carries on with the pending
+/// webhooks of a store, e.g. after a restart.
+///
+/// ```no_run
+/// use reqwest_delivery::WebhookSender;
+/// use reqwest_middleware::ClientBuilder;
+///
+/// # async fn run() -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new()).build();
+/// let sender = WebhookSender::new(client, "whsec_shared_secret");
+/// let delivery = sender
+///     .send("https://example.com/hooks", r#"{"event":"order.paid"}"#)
+///     .await?;
+/// println!("{:?} after {} attempts", delivery.status, delivery.attempts.len());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`resume`]: Self::resume
+pub struct WebhookSender {
+    client: ClientWithMiddleware,
+    key: hmac::Key,
+    signature_header: HeaderName,
+    retry_policy: Box<dyn RetryPolicy + Send + Sync>,
+    retryable_strategy: Box<dyn RetryableStrategy + Send + Sync>,
+    store: Option<Arc<dyn WebhookStore>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for WebhookSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookSender")
+            .field("signature_header", &self.signature_header)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebhookSender {
+    /// Construct `WebhookSender` sending webhooks with `client`, signed with `secret`.
+    pub fn new(client: ClientWithMiddleware, secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            client,
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref()),
+            signature_header: WEBHOOK_SIGNATURE,
+            retry_policy: Box::new(
+                ExponentialBackoff::builder()
+                    .retry_bounds(Duration::from_secs(30), Duration::from_secs(60 * 60))
+                    .jitter(Jitter::Bounded)
+                    .build_with_total_retry_duration(Duration::from_secs(24 * 60 * 60)),
+            ),
+            retryable_strategy: Box::new(DefaultRetryableStrategy),
+            store: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Send the signature in `header` rather than `webhook-signature`.
+    pub fn with_signature_header(mut self, header: HeaderName) -> Self {
+        self.signature_header = header;
+        self
+    }
+
+    /// Decide when to retry failed deliveries with `retry_policy`.
+    pub fn with_retry_policy<P>(mut self, retry_policy: P) -> Self
+    where
+        P: RetryPolicy + Send + Sync + 'static,
+    {
+        self.retry_policy = Box::new(retry_policy);
+        self
+    }
+
+    /// Decide which failures are worth retrying with `retryable_strategy`.
+    pub fn with_retryable_strategy<S>(mut self, retryable_strategy: S) -> Self
+    where
+        S: RetryableStrategy + Send + Sync + 'static,
+    {
+        self.retryable_strategy = Box::new(retryable_strategy);
+        self
+    }
+
+    /// Save webhooks to `store` whenever they change.
+    pub fn with_store(mut self, store: Arc<dyn WebhookStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Use `clock` to timestamp webhooks and wait between attempts.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The value of the signature header for `payload` sent at `timestamp`, in seconds since the
+    /// Unix epoch.
+    pub fn signature(&self, timestamp: u64, payload: &[u8]) -> String {
+        let mut signed = format!("{}.", timestamp).into_bytes();
+        signed.extend_from_slice(payload);
+        let tag = hmac::sign(&self.key, &signed);
+        format!("t={},v1={}", timestamp, hex(tag.as_ref()))
+    }
+
+    /// Create a pending webhook, saving it to the store, without sending it.
+    pub async fn create(
+        &self,
+        url: impl AsRef<str>,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<WebhookDelivery> {
+        let url = Url::parse(url.as_ref()).map_err(Error::middleware)?;
+        let now = self.clock.system_time();
+        let delivery = WebhookDelivery {
+            id: new_id(),
+            url: url.to_string(),
+            payload: payload.into(),
+            created_at: now,
+            status: WebhookStatus::Pending,
+            next_attempt_at: Some(now),
+            attempts: Vec::new(),
+        };
+        self.save(&delivery).await?;
+        Ok(delivery)
+    }
+
+    /// Send a webhook, retrying until it's delivered or given up on.
+    ///
+    /// This only fails if the webhook can't be saved to the store; delivery failures are
+    /// reported in the returned [`WebhookDelivery`].
+    pub async fn send(
+        &self,
+        url: impl AsRef<str>,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<WebhookDelivery> {
+        let mut delivery = self.create(url, payload).await?;
+        self.retry(&mut delivery).await?;
+        Ok(delivery)
+    }
+
+    /// Keep attempting to deliver a pending webhook, waiting between attempts, until it's
+    /// delivered or given up on.
+    pub async fn retry(&self, delivery: &mut WebhookDelivery) -> Result<()> {
+        while let Some(next_attempt_at) = delivery.next_attempt_at {
+            if let Ok(wait) = next_attempt_at.duration_since(self.clock.system_time()) {
+                self.clock.sleep(wait).await;
+            }
+            self.attempt(delivery).await?;
+        }
+        Ok(())
+    }
+
+    /// Make one attempt at delivering a pending webhook, recording it and scheduling the next
+    /// one if it failed.
+    pub async fn attempt(&self, delivery: &mut WebhookDelivery) -> Result<()> {
+        if delivery.status != WebhookStatus::Pending {
+            return Ok(());
+        }
+        let sent_at = self.clock.system_time();
+        let timestamp = sent_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let started = self.clock.now();
+        let result = self
+            .client
+            .post(&delivery.url)
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .header(WEBHOOK_ID, &delivery.id)
+            .header(WEBHOOK_TIMESTAMP, timestamp)
+            .header(
+                &self.signature_header,
+                self.signature(timestamp, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+        let retryable = self.retryable_strategy.handle(&result);
+        let (status, error) = match &result {
+            Ok(res) => (Some(res.status().as_u16()), None),
+            Err(err) => (
+                err.status().map(|status| status.as_u16()),
+                Some(err.to_string()),
+            ),
+        };
+        delivery.attempts.push(DeliveryAttempt {
+            sent_at,
+            duration: self.clock.now().saturating_duration_since(started),
+            status,
+            error,
+        });
+
+        let decision = match (retryable, &result) {
+            (None, Ok(_)) => {
+                tracing::debug!("Delivered webhook {}", delivery.id);
+                delivery.status = WebhookStatus::Delivered;
+                delivery.next_attempt_at = None;
+                return self.save(delivery).await;
+            }
+            (Some(Retryable::Transient), _) => self
+                .retry_policy
+                .should_retry(delivery.created_at, delivery.attempts.len() as u32 - 1),
+            _ => RetryDecision::DoNotRetry,
+        };
+        match decision {
+            RetryDecision::Retry { execute_after } => {
+                tracing::debug!(
+                    "Webhook {} failed, retrying at {:?}",
+                    delivery.id,
+                    execute_after
+                );
+                delivery.next_attempt_at = Some(execute_after);
+            }
+            RetryDecision::DoNotRetry => {
+                tracing::warn!(
+                    "Webhook {} failed after {} attempts, giving up",
+                    delivery.id,
+                    delivery.attempts.len()
+                );
+                delivery.status = WebhookStatus::Failed;
+                delivery.next_attempt_at = None;
+            }
+        }
+        self.save(delivery).await
+    }
+
+    /// Make one attempt at delivering each pending webhook of the store which is due, returning
+    /// them.
+    pub async fn resume(&self) -> Result<Vec<WebhookDelivery>> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(Vec::new()),
+        };
+        let now = self.clock.system_time();
+        let mut due = store.pending().await?;
+        due.retain(|delivery| delivery.next_attempt_at.is_none_or(|at| at <= now));
+        due.sort_by_key(|delivery| delivery.created_at);
+        for delivery in &mut due {
+            self.attempt(delivery).await?;
+        }
+        Ok(due)
+    }
+
+    async fn save(&self, delivery: &WebhookDelivery) -> Result<()> {
+        match &self.store {
+            Some(store) => store.save(delivery).await,
+            None => Ok(()),
+        }
+    }
+}
+
+fn new_id() -> String {
+    let mut bytes = [0; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("system random generator failed");
+    format!("msg_{}", hex(&bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,3 @@
+mod queue;
+#[cfg(feature = "webhook")]
+mod webhook;
@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures::stream;
+use reqwest_delivery::OfflineQueue;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::policies::ExponentialBackoff;
+use wiremock::matchers::{body_string, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("reqwest-delivery-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn client() -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new()).build()
+}
+
+#[tokio::test]
+async fn delivers_in_order_and_survives_reopening() {
+    let server = MockServer::start().await;
+    for (i, body) in ["first", "second"].iter().enumerate() {
+        Mock::given(method("POST"))
+            .and(path(format!("/events/{}", i)))
+            .and(header("x-kind", "test"))
+            .and(body_string(*body))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    let dir = dir("order");
+
+    let queue = OfflineQueue::open(client(), &dir).unwrap();
+    for (i, body) in ["first", "second"].iter().enumerate() {
+        let id = queue
+            .enqueue(
+                client()
+                    .post(format!("{}/events/{}", server.uri(), i))
+                    .header("x-kind", "test")
+                    .body(*body),
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, i as u64);
+    }
+    drop(queue);
+
+    let queue = OfflineQueue::open(client(), &dir).unwrap();
+    assert_eq!(queue.pending().unwrap().len(), 2);
+    let report = queue.deliver().await.unwrap();
+    assert_eq!(report.delivered, 2);
+    assert_eq!(report.remaining, 0);
+    assert!(queue.pending().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn keeps_requests_while_offline() {
+    // Nothing listens on the address of a dropped listener, so connections are refused.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let uri = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let queue = OfflineQueue::open(client(), dir("offline")).unwrap();
+    queue.enqueue(client().post(&uri).body("a")).await.unwrap();
+    queue.enqueue(client().post(&uri).body("b")).await.unwrap();
+
+    let report = queue.deliver().await.unwrap();
+    assert_eq!(report.delivered, 0);
+    assert_eq!(report.remaining, 2);
+    assert_eq!(report.next_attempt_at, None);
+    let pending = queue.pending().unwrap();
+    assert_eq!(pending[0].attempts, 0);
+}
+
+#[tokio::test]
+async fn retries_transient_failures_then_dead_letters() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    let queue = OfflineQueue::open(client(), dir("transient"))
+        .unwrap()
+        .with_retry_policy(
+            ExponentialBackoff::builder()
+                .retry_bounds(Duration::from_millis(1), Duration::from_millis(1))
+                .build_with_max_retries(1),
+        );
+    queue
+        .enqueue(client().post(server.uri()).body("a"))
+        .await
+        .unwrap();
+    queue
+        .enqueue(client().post(server.uri()).body("b"))
+        .await
+        .unwrap();
+
+    let report = queue.deliver().await.unwrap();
+    assert_eq!(report.remaining, 2);
+    assert!(report.next_attempt_at.is_some());
+    assert_eq!(queue.pending().unwrap()[0].attempts, 1);
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let report = queue.deliver().await.unwrap();
+    assert_eq!(report.dead_lettered, 1);
+    assert_eq!(report.remaining, 1);
+    let dead_letters = queue.dead_letters().unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].id, 0);
+    assert_eq!(dead_letters[0].attempts, 2);
+    assert_eq!(
+        dead_letters[0].last_error.as_deref(),
+        Some("HTTP status 503 Service Unavailable")
+    );
+}
+
+#[tokio::test]
+async fn dead_letters_fatal_failures_and_requeues_them() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string("bad"))
+        .respond_with(ResponseTemplate::new(400))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let queue = OfflineQueue::open(client(), dir("fatal")).unwrap();
+    let bad = queue
+        .enqueue(client().post(server.uri()).body("bad"))
+        .await
+        .unwrap();
+    queue
+        .enqueue(client().post(server.uri()).body("good"))
+        .await
+        .unwrap();
+
+    let report = queue.deliver().await.unwrap();
+    assert_eq!(report.dead_lettered, 1);
+    assert_eq!(report.delivered, 1);
+
+    assert!(queue.requeue(bad).await.unwrap());
+    assert!(queue.dead_letters().unwrap().is_empty());
+    let report = queue.deliver().await.unwrap();
+    assert_eq!(report.delivered, 1);
+    assert!(!queue.discard(bad).unwrap());
+}
+
+#[tokio::test]
+async fn refuses_streaming_bodies() {
+    let queue = OfflineQueue::open(client(), dir("stream")).unwrap();
+    let body = reqwest::Body::wrap_stream(stream::iter(vec![Ok::<_, std::io::Error>("a")]));
+    let err = queue
+        .enqueue(client().post("http://localhost").body(body))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("buffered"));
+}
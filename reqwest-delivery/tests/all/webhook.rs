@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest_delivery::{MemoryWebhookStore, WebhookSender, WebhookStatus, WebhookStore};
+use reqwest_middleware::ClientBuilder;
+use reqwest_retry::policies::ExponentialBackoff;
+use wiremock::matchers::{header, header_exists, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn sender() -> WebhookSender {
+    let client = ClientBuilder::new(reqwest::Client::new()).build();
+    WebhookSender::new(client, "secret").with_retry_policy(
+        ExponentialBackoff::builder()
+            .retry_bounds(Duration::from_millis(1), Duration::from_millis(1))
+            .build_with_max_retries(2),
+    )
+}
+
+#[tokio::test]
+async fn signs_webhooks() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header("content-type", "application/json"))
+        .and(header_exists("webhook-id"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let sender = sender();
+
+    let delivery = sender.send(server.uri(), r#"{"a":1}"#).await.unwrap();
+    assert_eq!(delivery.status, WebhookStatus::Delivered);
+
+    let request = &server.received_requests().await.unwrap()[0];
+    assert_eq!(request.headers["webhook-id"], delivery.id.as_str());
+    let timestamp: u64 = request.headers["webhook-timestamp"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(
+        request.headers["webhook-signature"],
+        sender.signature(timestamp, br#"{"a":1}"#).as_str()
+    );
+    assert!(sender
+        .signature(timestamp, br#"{"a":1}"#)
+        .starts_with(&format!("t={},v1=", timestamp)));
+}
+
+#[tokio::test]
+async fn retries_transient_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let delivery = sender().send(server.uri(), "{}").await.unwrap();
+    assert_eq!(delivery.status, WebhookStatus::Delivered);
+    let statuses: Vec<_> = delivery.attempts.iter().map(|a| a.status).collect();
+    assert_eq!(statuses, [Some(503), Some(200)]);
+}
+
+#[tokio::test]
+async fn gives_up() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+    let delivery = sender().send(server.uri(), "{}").await.unwrap();
+    assert_eq!(delivery.status, WebhookStatus::Failed);
+    assert_eq!(delivery.attempts.len(), 3);
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(410))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let delivery = sender().send(server.uri(), "{}").await.unwrap();
+    assert_eq!(delivery.status, WebhookStatus::Failed);
+    assert_eq!(delivery.next_attempt_at, None);
+}
+
+#[tokio::test]
+async fn resumes_pending_webhooks_from_the_store() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let store = Arc::new(MemoryWebhookStore::new());
+    let delivery = sender()
+        .with_store(store.clone())
+        .create(server.uri(), "{}")
+        .await
+        .unwrap();
+    assert_eq!(
+        store.pending().await.unwrap(),
+        std::slice::from_ref(&delivery)
+    );
+
+    let resumed = sender().with_store(store.clone()).resume().await.unwrap();
+    assert_eq!(resumed.len(), 1);
+    let saved = store.get(&delivery.id).unwrap();
+    assert_eq!(saved.status, WebhookStatus::Delivered);
+    assert_eq!(saved.attempts.len(), 1);
+    assert!(store.pending().await.unwrap().is_empty());
+}
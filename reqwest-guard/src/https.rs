@@ -0,0 +1,237 @@
+//! `RequireHttpsMiddleware` keeps requests off plain HTTP.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::header::STRICT_TRANSPORT_SECURITY;
+use http::{Extensions, HeaderValue};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Clock, DryRunResponse, Error, Middleware, Next, Result, SystemClock};
+use thiserror::Error;
+
+/// What [`RequireHttpsMiddleware`] does with plain `http` requests to hosts which aren't known
+/// to support HSTS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlainHttp {
+    /// Send them as they are.
+    Allow,
+    /// Send them over `https` instead.
+    Upgrade,
+    /// Fail them with [`InsecureRequest`].
+    Reject,
+}
+
+/// Error returned by [`RequireHttpsMiddleware`] for rejected plain `http` requests.
+#[derive(Debug, Error)]
+#[error("Plain HTTP request to {url} rejected")]
+pub struct InsecureRequest {
+    /// The URL of the rejected request.
+    pub url: Url,
+}
+
+/// The longest HSTS policy remembered, as browsers do: larger `max-age` values are clamped.
+const MAX_HSTS_AGE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// A parsed `Strict-Transport-Security` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StrictTransportSecurity {
+    /// How long the host must only be reached over `https`, at most a year. Zero means the host
+    /// must be forgotten.
+    pub max_age: Duration,
+    /// Whether the policy covers the subdomains of the host too.
+    pub include_subdomains: bool,
+}
+
+impl StrictTransportSecurity {
+    /// Parse a `Strict-Transport-Security` header, returning `None` if it's invalid, i.e. has no
+    /// `max-age` directive.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use http::HeaderValue;
+    /// use reqwest_guard::StrictTransportSecurity;
+    ///
+    /// let sts = StrictTransportSecurity::parse(&HeaderValue::from_static(
+    ///     "max-age=31536000; includeSubDomains",
+    /// ))
+    /// .unwrap();
+    /// assert_eq!(sts.max_age, Duration::from_secs(31536000));
+    /// assert!(sts.include_subdomains);
+    /// ```
+    pub fn parse(value: &HeaderValue) -> Option<Self> {
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        for directive in value.to_str().ok()?.split(';') {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            if name.eq_ignore_ascii_case("max-age") {
+                let secs = value?.parse().ok()?;
+                max_age = Some(Duration::from_secs(secs).min(MAX_HSTS_AGE));
+            } else if name.eq_ignore_ascii_case("includeSubDomains") {
+                include_subdomains = true;
+            }
+        }
+        Some(Self {
+            max_age: max_age?,
+            include_subdomains,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HstsEntry {
+    expires: Instant,
+    include_subdomains: bool,
+}
+
+/// `RequireHttpsMiddleware` upgrades or rejects plain `http` requests, and remembers the hosts
+/// advertising HSTS with a `Strict-Transport-Security` header, so that requests to them are
+/// always upgraded while their policy lasts.
+///
+/// As required by RFC 6797, `Strict-Transport-Security` headers are only honoured on `https`
+/// responses, and hosts which are IP addresses are ignored. Hosts can also be preloaded with
+/// [`with_hsts_host`]. Upgraded requests keep their port, unless it was the default `http` port,
+/// 80, which is replaced by the default `https` one.
+///
+/// `RequireHttpsMiddleware` is a handle: clones share the same HSTS hosts.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_guard::{PlainHttp, RequireHttpsMiddleware};
+/// use reqwest_middleware::ClientBuilder;
+///
+/// let https = RequireHttpsMiddleware::new(PlainHttp::Reject)
+///     .with_hsts_host("example.com", Duration::from_secs(365 * 24 * 60 * 60), true);
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(https).build();
+/// ```
+///
+/// [`with_hsts_host`]: Self::with_hsts_host
+#[derive(Clone)]
+pub struct RequireHttpsMiddleware {
+    plain_http: PlainHttp,
+    hosts: Arc<Mutex<HashMap<String, HstsEntry>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for RequireHttpsMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequireHttpsMiddleware")
+            .field("plain_http", &self.plain_http)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequireHttpsMiddleware {
+    /// Construct `RequireHttpsMiddleware` handling plain `http` requests to hosts without HSTS
+    /// according to `plain_http`.
+    pub fn new(plain_http: PlainHttp) -> Self {
+        Self {
+            plain_http,
+            hosts: Arc::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Upgrade requests to `host`, and to its subdomains if `include_subdomains` is true, for
+    /// `max_age`, at most a year.
+    pub fn with_hsts_host(
+        self,
+        host: impl Into<String>,
+        max_age: Duration,
+        include_subdomains: bool,
+    ) -> Self {
+        let mut host = host.into();
+        host.make_ascii_lowercase();
+        self.record(
+            host,
+            StrictTransportSecurity {
+                max_age,
+                include_subdomains,
+            },
+        );
+        self
+    }
+
+    /// Use `clock` to tell when HSTS policies expire.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns true if requests to `host` are upgraded because of HSTS.
+    pub fn is_hsts_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        let now = self.clock.now();
+        let hosts = self.hosts.lock().expect("hsts lock poisoned");
+        let known = |host: &str, subdomain: bool| {
+            hosts.get(host).is_some_and(|entry| {
+                entry.expires > now && (!subdomain || entry.include_subdomains)
+            })
+        };
+        if known(&host, false) {
+            return true;
+        }
+        host.match_indices('.')
+            .any(|(i, _)| known(&host[i + 1..], true))
+    }
+
+    fn record(&self, host: String, sts: StrictTransportSecurity) {
+        let mut hosts = self.hosts.lock().expect("hsts lock poisoned");
+        if sts.max_age.is_zero() {
+            hosts.remove(&host);
+        } else {
+            let entry = HstsEntry {
+                expires: self.clock.now() + sts.max_age.min(MAX_HSTS_AGE),
+                include_subdomains: sts.include_subdomains,
+            };
+            hosts.insert(host, entry);
+        }
+    }
+}
+
+/// Switch `url` to `https`, replacing the default `http` port.
+fn upgrade(url: &mut Url) {
+    if url.port() == Some(80) {
+        let _ = url.set_port(None);
+    }
+    let _ = url.set_scheme("https");
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RequireHttpsMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if req.url().scheme() == "http" {
+            let hsts = matches!(req.url().host(), Some(url::Host::Domain(host)) if self.is_hsts_host(host));
+            if hsts || self.plain_http == PlainHttp::Upgrade {
+                tracing::debug!("Upgrading request to {} to https", req.url());
+                upgrade(req.url_mut());
+            } else if self.plain_http == PlainHttp::Reject {
+                return Err(Error::middleware(InsecureRequest {
+                    url: req.url().clone(),
+                }));
+            }
+        }
+
+        let res = next.run(req, extensions).await?;
+        if res.url().scheme() == "https" && res.extensions().get::<DryRunResponse>().is_none() {
+            if let Some(url::Host::Domain(host)) = res.url().host() {
+                let sts = res
+                    .headers()
+                    .get(STRICT_TRANSPORT_SECURITY)
+                    .and_then(StrictTransportSecurity::parse);
+                if let Some(sts) = sts {
+                    self.record(host.to_ascii_lowercase(), sts);
+                }
+            }
+        }
+        Ok(res)
+    }
+}
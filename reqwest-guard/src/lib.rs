@@ -0,0 +1,39 @@
+//! Middleware restricting where a client may send requests, built on [`reqwest_middleware`].
+//!
+//! Use [`RequestGuardMiddleware`] to protect services fetching URLs supplied by users against
+//! server-side request forgery (SSRF), together with its [`GuardedResolver`], and
+//! [`HostPolicyMiddleware`] to allow, deny or require TLS for requests according to their host.
+//! [`RequireHttpsMiddleware`] upgrades or rejects plain `http` requests, and honours HSTS.
+//! [`RedirectMiddleware`] follows redirects through the middleware stack, without leaking
+//! credentials to other origins.
+//!
+//! ## Feature flags
+//!
+//! * `regex`: match hosts with regular expressions in [`HostPattern::regex`].
+//!
+//! ## Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use reqwest_guard::RequestGuardMiddleware;
+//! use reqwest_middleware::ClientBuilder;
+//!
+//! let guard = RequestGuardMiddleware::new();
+//! let reqwest_client = reqwest::Client::builder()
+//!     .dns_resolver(Arc::new(guard.resolver()))
+//!     .build()
+//!     .unwrap();
+//! let client = ClientBuilder::new(reqwest_client).with(guard).build();
+//! ```
+
+mod https;
+mod policy;
+mod redirect;
+mod ssrf;
+
+pub use https::{InsecureRequest, PlainHttp, RequireHttpsMiddleware, StrictTransportSecurity};
+pub use policy::{HostPattern, HostPolicyError, HostPolicyMiddleware, Verdict};
+pub use redirect::{RedirectChain, RedirectHop, RedirectMiddleware, TooManyRedirects};
+pub use ssrf::{
+    is_public_ip, BlockReason, GuardedResolver, RequestBlocked, RequestGuardMiddleware,
+};
@@ -0,0 +1,219 @@
+//! `HostPolicyMiddleware` enforces an egress policy based on hosts.
+use std::fmt;
+
+use http::Extensions;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use thiserror::Error;
+
+/// What [`HostPolicyMiddleware`] does with requests to a host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Let requests through.
+    Allow,
+    /// Fail requests with [`HostPolicyError::Denied`].
+    Deny,
+    /// Only let `https` and `wss` requests through, failing the others with
+    /// [`HostPolicyError::TlsRequired`].
+    RequireTls,
+}
+
+/// Error returned by [`HostPolicyMiddleware`] for requests violating the policy.
+#[derive(Debug, Error)]
+pub enum HostPolicyError {
+    /// Requests to the host are denied.
+    #[error("Host policy denies requests to `{host}`")]
+    Denied {
+        /// The host of the request.
+        host: String,
+        /// The pattern of the rule which denied it, or `None` for the default verdict.
+        pattern: Option<String>,
+    },
+    /// Requests to the host must use TLS.
+    #[error("Host policy requires TLS for requests to `{host}`")]
+    TlsRequired {
+        /// The host of the request.
+        host: String,
+        /// The pattern of the rule which required TLS, or `None` for the default verdict.
+        pattern: Option<String>,
+    },
+}
+
+#[derive(Clone)]
+enum Matcher {
+    Glob,
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+/// A pattern matched against hosts, case-insensitively.
+#[derive(Clone)]
+pub struct HostPattern {
+    source: String,
+    matcher: Matcher,
+}
+
+impl HostPattern {
+    /// A glob pattern, where `*` matches any sequence of characters and `?` any one character.
+    ///
+    /// For instance `*.example.com` matches all the subdomains of `example.com`, but not
+    /// `example.com` itself, and `*` matches all hosts.
+    pub fn glob(pattern: impl AsRef<str>) -> Self {
+        Self {
+            source: pattern.as_ref().to_ascii_lowercase(),
+            matcher: Matcher::Glob,
+        }
+    }
+
+    /// A regular expression, which must match the whole host.
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> std::result::Result<Self, regex::Error> {
+        let regex = regex::RegexBuilder::new(&format!("^(?:{})$", pattern))
+            .case_insensitive(true)
+            .build()?;
+        Ok(Self {
+            source: pattern.to_owned(),
+            matcher: Matcher::Regex(regex),
+        })
+    }
+
+    /// Returns true if `host` matches the pattern.
+    pub fn matches(&self, host: &str) -> bool {
+        match &self.matcher {
+            Matcher::Glob => {
+                glob_matches(self.source.as_bytes(), host.to_ascii_lowercase().as_bytes())
+            }
+            #[cfg(feature = "regex")]
+            Matcher::Regex(regex) => regex.is_match(host),
+        }
+    }
+}
+
+impl fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl fmt::Debug for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.matcher {
+            Matcher::Glob => write!(f, "HostPattern::glob({:?})", self.source),
+            #[cfg(feature = "regex")]
+            Matcher::Regex(_) => write!(f, "HostPattern::regex({:?})", self.source),
+        }
+    }
+}
+
+/// Matches `text` against a glob `pattern` of `*` and `?` wildcards, backtracking to the last
+/// `*` on mismatches.
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// `HostPolicyMiddleware` allows, denies or requires TLS for requests according to their host,
+/// so that an egress policy can be enforced by every service using the client.
+///
+/// Rules are checked in the order they were added, and the verdict of the first one whose
+/// pattern matches the host applies; requests to hosts matching no rule get the default
+/// verdict. Requests violating the policy fail with [`HostPolicyError`].
+///
+/// ```
+/// use reqwest_guard::{HostPattern, HostPolicyMiddleware, Verdict};
+/// use reqwest_middleware::ClientBuilder;
+///
+/// let policy = HostPolicyMiddleware::new(Verdict::Deny)
+///     .with_rule(HostPattern::glob("*.internal.example.com"), Verdict::Allow)
+///     .with_rule(HostPattern::glob("*.example.com"), Verdict::RequireTls)
+///     .with_rule(HostPattern::glob("api.github.com"), Verdict::RequireTls);
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(policy).build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct HostPolicyMiddleware {
+    rules: Vec<(HostPattern, Verdict)>,
+    default: Verdict,
+}
+
+impl HostPolicyMiddleware {
+    /// Construct `HostPolicyMiddleware` applying `default` to requests matching no rule.
+    pub fn new(default: Verdict) -> Self {
+        Self {
+            rules: Vec::new(),
+            default,
+        }
+    }
+
+    /// Apply `verdict` to requests whose host matches `pattern`, unless an earlier rule matches.
+    pub fn with_rule(mut self, pattern: HostPattern, verdict: Verdict) -> Self {
+        self.rules.push((pattern, verdict));
+        self
+    }
+
+    /// The verdict for requests to `host`, and the pattern of the rule it comes from.
+    pub fn verdict(&self, host: &str) -> (Verdict, Option<&HostPattern>) {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern.matches(host))
+            .map_or((self.default, None), |(pattern, verdict)| {
+                (*verdict, Some(pattern))
+            })
+    }
+
+    /// Check whether a request to `url` is allowed.
+    pub fn check(&self, url: &Url) -> std::result::Result<(), HostPolicyError> {
+        let host = url.host_str().unwrap_or_default();
+        let (verdict, pattern) = self.verdict(host);
+        let pattern = pattern.map(HostPattern::to_string);
+        match verdict {
+            Verdict::Allow => Ok(()),
+            Verdict::Deny => Err(HostPolicyError::Denied {
+                host: host.to_owned(),
+                pattern,
+            }),
+            Verdict::RequireTls if matches!(url.scheme(), "https" | "wss") => Ok(()),
+            Verdict::RequireTls => Err(HostPolicyError::TlsRequired {
+                host: host.to_owned(),
+                pattern,
+            }),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for HostPolicyMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if let Err(err) = self.check(req.url()) {
+            tracing::warn!("{}", err);
+            return Err(Error::middleware(err));
+        }
+        next.run(req, extensions).await
+    }
+}
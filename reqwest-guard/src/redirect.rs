@@ -0,0 +1,204 @@
+//! `RedirectMiddleware` follows redirects through the middleware stack.
+use http::header::{
+    AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION,
+    PROXY_AUTHORIZATION, TRANSFER_ENCODING,
+};
+use http::{Extensions, HeaderName, Method, StatusCode};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use thiserror::Error;
+
+/// Error returned by [`RedirectMiddleware`] when a request is redirected more times than
+/// allowed.
+#[derive(Debug, Error)]
+#[error("Too many redirects, the limit is {limit}")]
+pub struct TooManyRedirects {
+    /// The maximum number of redirects.
+    pub limit: usize,
+    /// The redirects followed before giving up.
+    pub chain: Vec<RedirectHop>,
+}
+
+/// A redirect followed by [`RedirectMiddleware`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedirectHop {
+    /// The URL which redirected.
+    pub url: Url,
+    /// The redirection status.
+    pub status: StatusCode,
+}
+
+/// The redirects followed to get a response, oldest first, added to the response extensions by
+/// [`RedirectMiddleware`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RedirectChain(pub Vec<RedirectHop>);
+
+/// `RedirectMiddleware` follows redirects itself, so that the middleware after it runs for each
+/// hop, e.g. to check the target of each redirect with
+/// [`RequestGuardMiddleware`](crate::RequestGuardMiddleware) or to store the cookies of
+/// intermediate responses.
+///
+/// reqwest's own redirects must be disabled with [`reqwest::redirect::Policy::none`], and the
+/// middleware should come first in the stack. The `Authorization`, `Proxy-Authorization` and
+/// `Cookie` headers, and any added with [`with_sensitive_header`], are removed when a redirect
+/// leads to another origin. `303 See Other` responses, and `301 Moved Permanently` and
+/// `302 Found` responses to `POST` requests, are followed with a `GET` request without body;
+/// other redirects are followed with the same request, unless its body is a stream, in which
+/// case the redirect response is returned.
+///
+/// At most 10 redirects are followed by default, after which requests fail with
+/// [`TooManyRedirects`]. The redirects followed are recorded in a [`RedirectChain`] in the
+/// response extensions.
+///
+/// ```
+/// use reqwest_guard::{RedirectMiddleware, RequestGuardMiddleware};
+/// use reqwest_middleware::ClientBuilder;
+///
+/// let reqwest_client = reqwest::Client::builder()
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()
+///     .unwrap();
+/// let client = ClientBuilder::new(reqwest_client)
+///     .with(RedirectMiddleware::new().with_max_redirects(5))
+///     .with(RequestGuardMiddleware::new())
+///     .build();
+/// ```
+///
+/// [`with_sensitive_header`]: Self::with_sensitive_header
+#[derive(Clone, Debug)]
+pub struct RedirectMiddleware {
+    max_redirects: usize,
+    sensitive_headers: Vec<HeaderName>,
+}
+
+impl Default for RedirectMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedirectMiddleware {
+    /// Construct `RedirectMiddleware` following up to 10 redirects.
+    pub fn new() -> Self {
+        Self {
+            max_redirects: 10,
+            sensitive_headers: vec![AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE],
+        }
+    }
+
+    /// Follow up to `max_redirects` redirects.
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Also remove `header` from requests redirected to another origin.
+    pub fn with_sensitive_header(mut self, header: HeaderName) -> Self {
+        self.sensitive_headers.push(header);
+        self
+    }
+
+    /// The request to send to follow the redirect of `res`, given a copy of the request `sent`
+    /// without body and a full one if it could be cloned, or `None` if it can't be followed.
+    fn follow(&self, sent: Request, replay: Option<Request>, res: &Response) -> Option<Request> {
+        let location = res.headers().get(LOCATION)?.to_str().ok()?;
+        let location = sent.url().join(location).ok()?;
+        if !matches!(location.scheme(), "http" | "https") {
+            return None;
+        }
+
+        let status = res.status();
+        let to_get = (status == StatusCode::SEE_OTHER && sent.method() != Method::HEAD)
+            || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+                && sent.method() == Method::POST);
+        let url = sent.url().clone();
+        let mut next = if to_get {
+            let mut next = sent;
+            *next.method_mut() = Method::GET;
+            for header in [
+                CONTENT_TYPE,
+                CONTENT_LENGTH,
+                CONTENT_ENCODING,
+                TRANSFER_ENCODING,
+            ] {
+                next.headers_mut().remove(header);
+            }
+            next
+        } else {
+            replay?
+        };
+
+        if url.scheme() != location.scheme()
+            || url.host() != location.host()
+            || url.port_or_known_default() != location.port_or_known_default()
+        {
+            for header in &self.sensitive_headers {
+                next.headers_mut().remove(header);
+            }
+        }
+        *next.url_mut() = location;
+        Some(next)
+    }
+}
+
+/// A copy of `req` without its body.
+fn without_body(req: &Request) -> Request {
+    let mut copy = Request::new(req.method().clone(), req.url().clone());
+    *copy.headers_mut() = req.headers().clone();
+    *copy.timeout_mut() = req.timeout().copied();
+    *copy.version_mut() = req.version();
+    copy
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RedirectMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let mut chain = Vec::new();
+        loop {
+            let sent = without_body(&req);
+            let replay = req.try_clone();
+            let mut res = next.clone().run(req, extensions).await?;
+            let url = sent.url().clone();
+            let followed = if is_redirect(res.status()) {
+                self.follow(sent, replay, &res)
+            } else {
+                None
+            };
+            req = match followed {
+                Some(req) => req,
+                None => {
+                    res.extensions_mut().insert(RedirectChain(chain));
+                    return Ok(res);
+                }
+            };
+            chain.push(RedirectHop {
+                url,
+                status: res.status(),
+            });
+            if chain.len() > self.max_redirects {
+                return Err(Error::middleware(TooManyRedirects {
+                    limit: self.max_redirects,
+                    chain,
+                }));
+            }
+            tracing::debug!("Following redirect to {}", req.url());
+        }
+    }
+}
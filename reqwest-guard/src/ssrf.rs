@@ -0,0 +1,338 @@
+//! `RequestGuardMiddleware` protects against server-side request forgery.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use http::Extensions;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use thiserror::Error;
+
+type IpFilterFn = dyn Fn(IpAddr) -> bool + Send + Sync + 'static;
+
+/// Why [`RequestGuardMiddleware`] or [`GuardedResolver`] blocked a request.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum BlockReason {
+    /// The scheme of the URL isn't allowed.
+    #[error("scheme `{0}` is not allowed")]
+    Scheme(String),
+    /// The port of the URL isn't allowed.
+    #[error("port {0} is not allowed")]
+    Port(u16),
+    /// The host is, or resolves to, an address which isn't allowed.
+    #[error("address {0} is not allowed")]
+    Address(IpAddr),
+    /// The URL has no host.
+    #[error("the URL has no host")]
+    NoHost,
+    /// The host couldn't be resolved, so its addresses couldn't be checked.
+    #[error("the host could not be resolved: {0}")]
+    Resolution(String),
+}
+
+/// Error returned by [`RequestGuardMiddleware`] for blocked requests.
+#[derive(Debug, Error)]
+#[error("Request to {url} blocked: {reason}")]
+pub struct RequestBlocked {
+    /// The URL of the blocked request.
+    pub url: Url,
+    /// Why the request was blocked.
+    pub reason: BlockReason,
+}
+
+/// Returns true if `ip` is a publicly routable address.
+///
+/// Loopback, private, link-local (including the `169.254.169.254` cloud metadata endpoint),
+/// shared, unique local, multicast, documentation and reserved addresses aren't. IPv6 addresses
+/// embedding an IPv4 address, e.g. `::ffff:127.0.0.1`, are judged by the IPv4 address.
+///
+/// ```
+/// use reqwest_guard::is_public_ip;
+///
+/// assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+/// assert!(!is_public_ip("169.254.169.254".parse().unwrap()));
+/// assert!(!is_public_ip("::ffff:10.0.0.1".parse().unwrap()));
+/// ```
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(a == 0
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // 100.64.0.0/10, shared address space, e.g. carrier-grade NAT.
+        || (a == 100 && b & 0xc0 == 64)
+        // 192.0.0.0/24, IETF protocol assignments.
+        || (a == 192 && b == 0 && c == 0)
+        // 198.18.0.0/15, benchmarking.
+        || (a == 198 && b & 0xfe == 18)
+        // 240.0.0.0/4, reserved.
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(ip) = embedded_ipv4(ip) {
+        return is_public_ipv4(ip);
+    }
+    let segments = ip.segments();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // fc00::/7, unique local.
+        || segments[0] & 0xfe00 == 0xfc00
+        // fe80::/10, link-local, and fec0::/10, the deprecated site-local.
+        || segments[0] & 0xffc0 == 0xfe80
+        || segments[0] & 0xffc0 == 0xfec0
+        // 2001:db8::/32, documentation.
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+}
+
+/// The IPv4 address embedded in an IPv4-mapped, IPv4-compatible, NAT64 or 6to4 address.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let octets = ip.octets();
+    let tail = Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15]);
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0xffff, _, _] => Some(tail),
+        [0, 0, 0, 0, 0, 0, _, _] if !ip.is_unspecified() && !ip.is_loopback() => Some(tail),
+        [0x64, 0xff9b, 0, 0, 0, 0, _, _] => Some(tail),
+        [0x2002, _, _, _, _, _, _, _] => {
+            Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5]))
+        }
+        _ => None,
+    }
+}
+
+/// `RequestGuardMiddleware` blocks requests which could reach internal services, for clients
+/// fetching URLs supplied by users.
+///
+/// By default, only `http` and `https` URLs are allowed, and hosts must only resolve to public
+/// addresses, see [`is_public_ip`]; requests whose host can't be resolved are blocked too. The
+/// allowed schemes, ports and addresses can be configured. Blocked requests fail with
+/// [`RequestBlocked`].
+///
+/// Checking the addresses in the middleware isn't enough on its own: the DNS records can change
+/// between the check and the connection, e.g. in a DNS rebinding attack. Install the
+/// [`GuardedResolver`] returned by [`resolver`] in the `reqwest::Client` as well, so that the
+/// addresses connected to are checked with the same rules.
+///
+/// Redirects followed by `reqwest` don't go through the middleware, and the resolver isn't
+/// called for hosts which are IP addresses, so a redirect to e.g. `http://127.0.0.1/` wouldn't
+/// be blocked. Disable them with [`reqwest::redirect::Policy::none`] and put a
+/// [`RedirectMiddleware`](crate::RedirectMiddleware) before the guard, so that each redirect is
+/// checked, or check the redirect targets in a custom [`reqwest::redirect::Policy`]:
+///
+/// ```
+/// use std::sync::Arc;
+/// use reqwest_guard::{RedirectMiddleware, RequestGuardMiddleware};
+/// use reqwest_middleware::ClientBuilder;
+///
+/// let guard = RequestGuardMiddleware::new()
+///     .with_allowed_schemes(["https"])
+///     .with_allowed_ports([443]);
+/// let reqwest_client = reqwest::Client::builder()
+///     .dns_resolver(Arc::new(guard.resolver()))
+///     .redirect(reqwest::redirect::Policy::none())
+///     .build()
+///     .unwrap();
+/// let client = ClientBuilder::new(reqwest_client)
+///     .with(RedirectMiddleware::new())
+///     .with(guard)
+///     .build();
+/// ```
+///
+/// [`resolver`]: Self::resolver
+#[derive(Clone)]
+pub struct RequestGuardMiddleware {
+    schemes: Vec<String>,
+    ports: Option<Vec<u16>>,
+    ip_filter: Arc<IpFilterFn>,
+}
+
+impl std::fmt::Debug for RequestGuardMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestGuardMiddleware")
+            .field("schemes", &self.schemes)
+            .field("ports", &self.ports)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for RequestGuardMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestGuardMiddleware {
+    /// Construct `RequestGuardMiddleware` allowing `http` and `https` requests to public
+    /// addresses on any port.
+    pub fn new() -> Self {
+        Self {
+            schemes: vec!["http".to_owned(), "https".to_owned()],
+            ports: None,
+            ip_filter: Arc::new(is_public_ip),
+        }
+    }
+
+    /// Only allow URLs with one of `schemes`.
+    pub fn with_allowed_schemes<I>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.schemes = schemes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only allow URLs with one of `ports`, explicit or implied by the scheme.
+    pub fn with_allowed_ports(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        self.ports = Some(ports.into_iter().collect());
+        self
+    }
+
+    /// Only allow the addresses for which `ip_filter` returns true, instead of the public ones.
+    ///
+    /// ```
+    /// use std::net::IpAddr;
+    /// use reqwest_guard::{is_public_ip, RequestGuardMiddleware};
+    ///
+    /// // Also allow a trusted internal proxy.
+    /// let proxy: IpAddr = "10.0.0.8".parse().unwrap();
+    /// let guard = RequestGuardMiddleware::new().with_ip_filter(move |ip| ip == proxy || is_public_ip(ip));
+    /// ```
+    pub fn with_ip_filter<F>(mut self, ip_filter: F) -> Self
+    where
+        F: Fn(IpAddr) -> bool + Send + Sync + 'static,
+    {
+        self.ip_filter = Arc::new(ip_filter);
+        self
+    }
+
+    /// A [`GuardedResolver`] checking addresses with the same filter as the middleware.
+    pub fn resolver(&self) -> GuardedResolver {
+        GuardedResolver {
+            ip_filter: self.ip_filter.clone(),
+        }
+    }
+
+    /// Check whether a request to `url` is allowed, resolving its host if needed.
+    pub async fn check(&self, url: &Url) -> std::result::Result<(), RequestBlocked> {
+        self.check_reason(url)
+            .await
+            .map_err(|reason| RequestBlocked {
+                url: url.clone(),
+                reason,
+            })
+    }
+
+    async fn check_reason(&self, url: &Url) -> std::result::Result<(), BlockReason> {
+        if !self.schemes.iter().any(|scheme| scheme == url.scheme()) {
+            return Err(BlockReason::Scheme(url.scheme().to_owned()));
+        }
+        let port = url.port_or_known_default().unwrap_or_default();
+        if let Some(ports) = &self.ports {
+            if !ports.contains(&port) {
+                return Err(BlockReason::Port(port));
+            }
+        }
+        let addrs: Vec<IpAddr> = match url.host() {
+            None => return Err(BlockReason::NoHost),
+            Some(url::Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+            Some(url::Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+            Some(url::Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|err| BlockReason::Resolution(err.to_string()))?
+                .map(|addr| addr.ip())
+                .collect(),
+        };
+        check_addrs(&*self.ip_filter, addrs)
+    }
+}
+
+fn check_addrs(
+    ip_filter: &IpFilterFn,
+    addrs: impl IntoIterator<Item = IpAddr>,
+) -> std::result::Result<(), BlockReason> {
+    match addrs.into_iter().find(|ip| !ip_filter(*ip)) {
+        Some(ip) => Err(BlockReason::Address(ip)),
+        None => Ok(()),
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RequestGuardMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if let Err(err) = self.check(req.url()).await {
+            tracing::warn!("{}", err);
+            return Err(Error::middleware(err));
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// A DNS resolver for `reqwest::ClientBuilder::dns_resolver` failing with
+/// [`BlockReason::Address`] when a host resolves to an address which isn't allowed, so that
+/// clients only ever connect to addresses which were checked.
+///
+/// It is usually obtained from [`RequestGuardMiddleware::resolver`].
+#[derive(Clone)]
+pub struct GuardedResolver {
+    ip_filter: Arc<IpFilterFn>,
+}
+
+impl std::fmt::Debug for GuardedResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GuardedResolver").finish_non_exhaustive()
+    }
+}
+
+impl Default for GuardedResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GuardedResolver {
+    /// Construct `GuardedResolver` only allowing public addresses, see [`is_public_ip`].
+    pub fn new() -> Self {
+        Self {
+            ip_filter: Arc::new(is_public_ip),
+        }
+    }
+
+    /// Only allow the addresses for which `ip_filter` returns true.
+    pub fn with_ip_filter<F>(mut self, ip_filter: F) -> Self
+    where
+        F: Fn(IpAddr) -> bool + Send + Sync + 'static,
+    {
+        self.ip_filter = Arc::new(ip_filter);
+        self
+    }
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let ip_filter = self.ip_filter.clone();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            check_addrs(&*ip_filter, addrs.iter().map(SocketAddr::ip))?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use http::HeaderValue;
+use reqwest_guard::{InsecureRequest, PlainHttp, RequireHttpsMiddleware, StrictTransportSecurity};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn client(https: RequireHttpsMiddleware) -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(https)
+        .build()
+}
+
+#[test]
+fn parses_sts_headers() {
+    let parse = |value| StrictTransportSecurity::parse(&HeaderValue::from_static(value));
+    assert_eq!(
+        parse("max-age=\"60\""),
+        Some(StrictTransportSecurity {
+            max_age: Duration::from_secs(60),
+            include_subdomains: false,
+        })
+    );
+    assert!(
+        parse("includesubdomains ; MAX-AGE=0")
+            .unwrap()
+            .include_subdomains
+    );
+    assert_eq!(parse("includeSubDomains"), None);
+    assert_eq!(parse("max-age=soon"), None);
+    assert_eq!(
+        parse("max-age=18446744073709551615").unwrap().max_age,
+        Duration::from_secs(365 * 24 * 60 * 60)
+    );
+}
+
+#[tokio::test]
+async fn rejects_plain_http() {
+    let err = client(RequireHttpsMiddleware::new(PlainHttp::Reject))
+        .get("http://example.com")
+        .send()
+        .await
+        .unwrap_err();
+    match err {
+        Error::Middleware(err) => assert!(err.is::<InsecureRequest>()),
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[tokio::test]
+async fn upgrades_plain_http() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let err = client(RequireHttpsMiddleware::new(PlainHttp::Upgrade))
+        .get(server.uri())
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.url().unwrap().scheme(), "https");
+}
+
+#[tokio::test]
+async fn upgrades_hsts_hosts() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let https = RequireHttpsMiddleware::new(PlainHttp::Allow)
+        .with_hsts_host("LOCALHOST", HOUR, false)
+        .with_hsts_host("example.com", HOUR, true);
+    assert!(https.is_hsts_host("localhost"));
+    assert!(!https.is_hsts_host("sub.localhost"));
+    assert!(https.is_hsts_host("a.b.example.com"));
+    assert!(!https.is_hsts_host("notexample.com"));
+    let client = client(https.clone());
+
+    client.get(server.uri()).send().await.unwrap();
+    let url = server.uri().replace("127.0.0.1", "localhost");
+    let err = client.get(url).send().await.unwrap_err();
+    assert_eq!(err.url().unwrap().scheme(), "https");
+
+    let https = https.with_hsts_host("localhost", Duration::ZERO, false);
+    assert!(!https.is_hsts_host("localhost"));
+
+    let https = https.with_hsts_host("forever.example", Duration::MAX, false);
+    assert!(https.is_hsts_host("forever.example"));
+}
@@ -0,0 +1,4 @@
+mod https;
+mod policy;
+mod redirect;
+mod ssrf;
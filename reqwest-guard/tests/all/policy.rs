@@ -0,0 +1,80 @@
+use reqwest_guard::{HostPattern, HostPolicyError, HostPolicyMiddleware, Verdict};
+use reqwest_middleware::{ClientBuilder, Error};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn policy() -> HostPolicyMiddleware {
+    HostPolicyMiddleware::new(Verdict::Deny)
+        .with_rule(HostPattern::glob("*.internal.example.com"), Verdict::Allow)
+        .with_rule(HostPattern::glob("*.example.com"), Verdict::RequireTls)
+        .with_rule(HostPattern::glob("cdn-??.example.org"), Verdict::Allow)
+}
+
+#[test]
+fn matches_globs() {
+    let pattern = HostPattern::glob("*.Example.com");
+    assert!(pattern.matches("api.example.com"));
+    assert!(pattern.matches("a.b.EXAMPLE.com"));
+    assert!(!pattern.matches("example.com"));
+    assert!(!pattern.matches("api.example.com.evil.net"));
+    assert!(HostPattern::glob("*").matches("anything"));
+    assert!(HostPattern::glob("a*b*c").matches("aXbYbZc"));
+    assert!(!HostPattern::glob("a*b*c").matches("aXbYbZ"));
+}
+
+#[test]
+fn applies_the_first_matching_rule() {
+    let policy = policy();
+    assert_eq!(policy.verdict("db.internal.example.com").0, Verdict::Allow);
+    assert_eq!(policy.verdict("api.example.com").0, Verdict::RequireTls);
+    assert_eq!(policy.verdict("cdn-01.example.org").0, Verdict::Allow);
+    assert_eq!(policy.verdict("cdn-001.example.org").0, Verdict::Deny);
+    assert!(policy.verdict("example.net").1.is_none());
+
+    assert!(policy
+        .check(&"https://api.example.com".parse().unwrap())
+        .is_ok());
+    match policy.check(&"http://api.example.com".parse().unwrap()) {
+        Err(HostPolicyError::TlsRequired { host, pattern }) => {
+            assert_eq!(host, "api.example.com");
+            assert_eq!(pattern.as_deref(), Some("*.example.com"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(matches!(
+        policy.check(&"https://example.net".parse().unwrap()),
+        Err(HostPolicyError::Denied { pattern: None, .. })
+    ));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn matches_regexes() {
+    let pattern = HostPattern::regex(r"api-\d+\.example\.com").unwrap();
+    assert!(pattern.matches("API-12.example.com"));
+    assert!(!pattern.matches("api-12.example.com.evil.net"));
+    assert_eq!(pattern.to_string(), r"api-\d+\.example\.com");
+}
+
+#[tokio::test]
+async fn fails_denied_requests() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(
+            HostPolicyMiddleware::new(Verdict::Deny)
+                .with_rule(HostPattern::glob("127.0.0.1"), Verdict::Allow),
+        )
+        .build();
+
+    client.get(server.uri()).send().await.unwrap();
+    let url = server.uri().replace("127.0.0.1", "localhost");
+    match client.get(url).send().await.unwrap_err() {
+        Error::Middleware(err) => assert!(err.is::<HostPolicyError>()),
+        err => panic!("unexpected error: {}", err),
+    }
+}
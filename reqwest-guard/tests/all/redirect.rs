@@ -0,0 +1,127 @@
+use reqwest_guard::{RedirectChain, RedirectMiddleware, TooManyRedirects};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use wiremock::matchers::{body_string, header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(redirect: RedirectMiddleware) -> ClientWithMiddleware {
+    let reqwest_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    ClientBuilder::new(reqwest_client).with(redirect).build()
+}
+
+fn redirect(status: u16, location: impl AsRef<str>) -> ResponseTemplate {
+    ResponseTemplate::new(status).insert_header("location", location.as_ref())
+}
+
+#[tokio::test]
+async fn strips_credentials_across_origins() {
+    let origin = MockServer::start().await;
+    let other = MockServer::start().await;
+    Mock::given(path("/start"))
+        .respond_with(redirect(302, "/same"))
+        .expect(1)
+        .mount(&origin)
+        .await;
+    Mock::given(path("/same"))
+        .and(header("authorization", "Bearer secret"))
+        .respond_with(redirect(301, format!("{}/other", other.uri())))
+        .expect(1)
+        .mount(&origin)
+        .await;
+    Mock::given(path("/other"))
+        .and(header_exists("authorization"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&other)
+        .await;
+    Mock::given(path("/other"))
+        .and(header("x-trace", "1"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&other)
+        .await;
+
+    let res = client(RedirectMiddleware::new())
+        .get(format!("{}/start", origin.uri()))
+        .header("authorization", "Bearer secret")
+        .header("x-trace", "1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let chain = &res.extensions().get::<RedirectChain>().unwrap().0;
+    let hops: Vec<_> = chain
+        .iter()
+        .map(|hop| (hop.url.path(), hop.status.as_u16()))
+        .collect();
+    assert_eq!(hops, [("/start", 302), ("/same", 301)]);
+}
+
+#[tokio::test]
+async fn rewrites_methods() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/form"))
+        .respond_with(redirect(303, "/result"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/result"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/old"))
+        .respond_with(redirect(308, "/new"))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/new"))
+        .and(body_string("data"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = client(RedirectMiddleware::new());
+
+    let res = client
+        .post(format!("{}/form", server.uri()))
+        .body("a=1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client
+        .put(format!("{}/old", server.uri()))
+        .body("data")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 204);
+}
+
+#[tokio::test]
+async fn caps_hops() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(redirect(302, "/loop"))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let err = client(RedirectMiddleware::new().with_max_redirects(2))
+        .get(server.uri())
+        .send()
+        .await
+        .unwrap_err();
+    match err {
+        Error::Middleware(err) => {
+            let err = err.downcast::<TooManyRedirects>().unwrap();
+            assert_eq!(err.limit, 2);
+            assert_eq!(err.chain.len(), 3);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
@@ -0,0 +1,112 @@
+use reqwest_guard::{
+    is_public_ip, BlockReason, GuardedResolver, RequestBlocked, RequestGuardMiddleware,
+};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(guard: RequestGuardMiddleware) -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(guard)
+        .build()
+}
+
+fn reason(err: Error) -> BlockReason {
+    match err {
+        Error::Middleware(err) => err.downcast::<RequestBlocked>().unwrap().reason,
+        Error::Reqwest(err) => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn classifies_addresses() {
+    for ip in ["93.184.216.34", "2606:2800:220:1::1", "2002:5db8:d822::1"] {
+        assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+    for ip in [
+        "0.0.0.0",
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.100.100.200",
+        "255.255.255.255",
+        "::",
+        "::1",
+        "fe80::1",
+        "fd00:ec2::254",
+        "::ffff:127.0.0.1",
+        "64:ff9b::a00:1",
+        "2002:a00:1::",
+    ] {
+        assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+}
+
+#[tokio::test]
+async fn blocks_private_addresses() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let client = client(RequestGuardMiddleware::new());
+
+    let err = client.get(server.uri()).send().await.unwrap_err();
+    assert_eq!(
+        reason(err),
+        BlockReason::Address("127.0.0.1".parse().unwrap())
+    );
+    let url = server.uri().replace("127.0.0.1", "localhost");
+    let err = client.get(url).send().await.unwrap_err();
+    assert!(matches!(reason(err), BlockReason::Address(ip) if ip.is_loopback()));
+}
+
+#[tokio::test]
+async fn allows_filtered_addresses() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = client(RequestGuardMiddleware::new().with_ip_filter(|ip| ip.is_loopback()));
+    client.get(server.uri()).send().await.unwrap();
+}
+
+#[tokio::test]
+async fn checks_schemes_and_ports() {
+    let guard = RequestGuardMiddleware::new()
+        .with_allowed_schemes(["https"])
+        .with_allowed_ports([443]);
+
+    let err = guard
+        .check(&"http://example.com".parse().unwrap())
+        .await
+        .unwrap_err();
+    assert_eq!(err.reason, BlockReason::Scheme("http".to_owned()));
+    let err = guard
+        .check(&"https://example.com:8443".parse().unwrap())
+        .await
+        .unwrap_err();
+    assert_eq!(err.reason, BlockReason::Port(8443));
+    let err = guard
+        .check(&"https://[::1]/".parse().unwrap())
+        .await
+        .unwrap_err();
+    assert_eq!(err.reason, BlockReason::Address("::1".parse().unwrap()));
+}
+
+#[tokio::test]
+async fn resolver_blocks_private_addresses() {
+    let server = MockServer::start().await;
+    let reqwest_client = reqwest::Client::builder()
+        .dns_resolver(std::sync::Arc::new(GuardedResolver::new()))
+        .build()
+        .unwrap();
+    let url = server.uri().replace("127.0.0.1", "localhost");
+    let err = reqwest_client.get(url).send().await.unwrap_err();
+    assert!(err.is_connect());
+}
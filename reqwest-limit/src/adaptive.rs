@@ -0,0 +1,257 @@
+//! `AdaptiveRateLimitMiddleware` paces requests according to the rate limits advertised by the
+//! server.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use http::{Extensions, HeaderMap, StatusCode};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Clock, Middleware, Next, Result, SystemClock};
+
+use crate::host_key;
+
+/// The quota a server advertised for a host, as last seen by [`AdaptiveRateLimitMiddleware`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaState {
+    /// The total number of requests allowed in the current window, if advertised.
+    pub limit: Option<u64>,
+    /// The number of requests left in the current window, counting requests sent since the last
+    /// response.
+    pub remaining: u64,
+    /// When the current window ends.
+    pub reset: Instant,
+    /// When set, requests are held back until then because the server answered
+    /// `429 Too Many Requests`.
+    pub paused_until: Option<Instant>,
+}
+
+impl QuotaState {
+    /// How long the next request has to wait at `now` to stay under quota.
+    fn delay(&self, now: Instant, last_sent: Option<Instant>) -> Duration {
+        if let Some(paused_until) = self.paused_until {
+            if paused_until > now {
+                return paused_until - now;
+            }
+        }
+        if self.reset <= now {
+            return Duration::ZERO;
+        }
+        if self.remaining == 0 {
+            return self.reset - now;
+        }
+        // Spread the remaining requests evenly over what's left of the window.
+        let spacing = (self.reset - now) / (self.remaining.min(u32::MAX as u64) as u32);
+        last_sent
+            .map(|last_sent| (last_sent + spacing).saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+struct HostState {
+    quota: QuotaState,
+    last_sent: Option<Instant>,
+}
+
+/// A handle on the quotas tracked by an [`AdaptiveRateLimitMiddleware`], which remains usable
+/// after the middleware has been added to a client.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimitQuotas {
+    hosts: Arc<Mutex<HashMap<String, HostState>>>,
+}
+
+impl RateLimitQuotas {
+    /// The quota last advertised for the host of `url`, if any.
+    pub fn get(&self, url: &Url) -> Option<QuotaState> {
+        let hosts = self.hosts.lock().expect("quotas lock poisoned");
+        hosts.get(&host_key(url)).map(|host| host.quota)
+    }
+
+    /// Forgets the quota tracked for the host of `url`.
+    pub fn clear(&self, url: &Url) {
+        let mut hosts = self.hosts.lock().expect("quotas lock poisoned");
+        hosts.remove(&host_key(url));
+    }
+
+    /// Takes a slot for a request to `host`, returning how long to wait before sending it.
+    fn reserve(&self, host: &str, now: Instant) -> Duration {
+        let mut hosts = self.hosts.lock().expect("quotas lock poisoned");
+        let state = match hosts.get_mut(host) {
+            Some(state) => state,
+            None => return Duration::ZERO,
+        };
+        let delay = state.quota.delay(now, state.last_sent);
+        let sent_at = now + delay;
+        if state.quota.reset <= sent_at {
+            // The window we knew about is over: we know nothing about the next one until the
+            // server tells us.
+            state.quota.remaining = u64::MAX;
+            state.quota.reset = sent_at;
+        }
+        state.quota.remaining = state.quota.remaining.saturating_sub(1);
+        state.quota.paused_until = None;
+        state.last_sent = Some(sent_at);
+        delay
+    }
+
+    fn update(&self, host: String, quota: QuotaState) {
+        let mut hosts = self.hosts.lock().expect("quotas lock poisoned");
+        let last_sent = hosts.get(&host).and_then(|state| state.last_sent);
+        hosts.insert(host, HostState { quota, last_sent });
+    }
+}
+
+/// `AdaptiveRateLimitMiddleware` reads the rate limit headers returned by servers and paces
+/// subsequent requests to the same host so that they stay under quota.
+///
+/// The following headers are understood:
+/// * `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`, where the reset is
+///   either a number of seconds or a Unix timestamp;
+/// * the IETF `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` fields, as well as
+///   the combined `RateLimit: limit=100, remaining=50, reset=30` form.
+///
+/// Remaining requests are spread evenly until the quota resets, and once the quota is exhausted
+/// requests wait for the reset. A `429 Too Many Requests` response pauses the host until its
+/// `Retry-After`, or the advertised reset when there is none.
+///
+/// The quotas are available through [`quotas`](Self::quotas):
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::AdaptiveRateLimitMiddleware;
+///
+/// let limiter = AdaptiveRateLimitMiddleware::new();
+/// let quotas = limiter.quotas();
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(limiter).build();
+///
+/// let url = "https://api.github.com".parse().unwrap();
+/// if let Some(quota) = quotas.get(&url) {
+///     println!("{} requests left", quota.remaining);
+/// }
+/// ```
+pub struct AdaptiveRateLimitMiddleware {
+    quotas: RateLimitQuotas,
+    clock: Arc<dyn Clock>,
+}
+
+impl AdaptiveRateLimitMiddleware {
+    /// Construct `AdaptiveRateLimitMiddleware`.
+    pub fn new() -> Self {
+        Self {
+            quotas: RateLimitQuotas::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use `clock` to measure time and wait.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// A handle on the quotas advertised by servers.
+    pub fn quotas(&self) -> RateLimitQuotas {
+        self.quotas.clone()
+    }
+}
+
+impl Default for AdaptiveRateLimitMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for AdaptiveRateLimitMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let host = host_key(req.url());
+        let delay = self.quotas.reserve(&host, self.clock.now());
+        if !delay.is_zero() {
+            tracing::debug!("Pacing request to {} by {:?}", host, delay);
+            self.clock.sleep(delay).await;
+        }
+
+        let res = next.run(req, extensions).await?;
+        let now = self.clock.now();
+        let mut quota = parse_quota(res.headers(), now);
+        if res.status() == StatusCode::TOO_MANY_REQUESTS {
+            let paused_until = parse_retry_after(res.headers(), now)
+                .or_else(|| quota.map(|quota| quota.reset))
+                .unwrap_or(now);
+            tracing::debug!("Rate limited by {}, pausing", host);
+            quota = Some(QuotaState {
+                paused_until: Some(paused_until),
+                ..quota.unwrap_or(QuotaState {
+                    limit: None,
+                    remaining: 0,
+                    reset: paused_until,
+                    paused_until: None,
+                })
+            });
+        }
+        if let Some(quota) = quota {
+            self.quotas.update(host, quota);
+        }
+        Ok(res)
+    }
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Turns a reset value into an instant; values past a billion seconds are Unix timestamps.
+/// Returns `None` if the instant is too far in the future to be represented.
+fn reset_instant(reset: u64, now: Instant) -> Option<Instant> {
+    if reset < 1_000_000_000 {
+        return now.checked_add(Duration::from_secs(reset));
+    }
+    let reset = UNIX_EPOCH.checked_add(Duration::from_secs(reset))?;
+    now.checked_add(reset.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+fn parse_quota(headers: &HeaderMap, now: Instant) -> Option<QuotaState> {
+    let (limit, remaining, reset) = if let Some(value) = headers.get("ratelimit") {
+        let mut fields = HashMap::new();
+        for field in value.to_str().ok()?.split(',') {
+            if let Some((key, value)) = field.split_once('=') {
+                fields.insert(key.trim().to_ascii_lowercase(), value.trim().parse().ok());
+            }
+        }
+        let mut field = |name: &str| fields.remove(name).flatten();
+        (field("limit"), field("remaining"), field("reset"))
+    } else if headers.contains_key("ratelimit-remaining") {
+        (
+            header_u64(headers, "ratelimit-limit"),
+            header_u64(headers, "ratelimit-remaining"),
+            header_u64(headers, "ratelimit-reset"),
+        )
+    } else {
+        (
+            header_u64(headers, "x-ratelimit-limit"),
+            header_u64(headers, "x-ratelimit-remaining"),
+            header_u64(headers, "x-ratelimit-reset"),
+        )
+    };
+    Some(QuotaState {
+        limit,
+        remaining: remaining?,
+        reset: reset_instant(reset?, now)?,
+        paused_until: None,
+    })
+}
+
+fn parse_retry_after(headers: &HeaderMap, now: Instant) -> Option<Instant> {
+    let value = headers.get(http::header::RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse() {
+        return now.checked_add(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    now.checked_add(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
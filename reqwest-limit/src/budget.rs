@@ -0,0 +1,367 @@
+//! `BudgetMiddleware` tracks the usage of daily and monthly request budgets.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::{Extensions, HeaderName};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Clock, Error, Middleware, Next, Result, SystemClock};
+use thiserror::Error;
+
+use crate::host_key;
+
+type KeyFn = dyn Fn(&Request, &Extensions) -> Option<String> + Send + Sync + 'static;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The calendar period, in UTC, over which a budget of [`BudgetMiddleware`] applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BudgetPeriod {
+    /// The budget is renewed every day at midnight.
+    Daily,
+    /// The budget is renewed on the first day of every month.
+    Monthly,
+}
+
+impl BudgetPeriod {
+    /// The index of the period `time` falls in, counted from the Unix epoch.
+    fn index(self, time: SystemTime) -> i64 {
+        let days = (time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / SECONDS_PER_DAY) as i64;
+        match self {
+            BudgetPeriod::Daily => days,
+            BudgetPeriod::Monthly => {
+                let (year, month, _) = civil_from_days(days);
+                year * 12 + i64::from(month) - 1
+            }
+        }
+    }
+
+    /// When the period with the given index ends.
+    fn end(self, index: i64) -> SystemTime {
+        let days = match self {
+            BudgetPeriod::Daily => index + 1,
+            BudgetPeriod::Monthly => {
+                let next = index + 1;
+                days_from_civil(next.div_euclid(12), next.rem_euclid(12) as u32 + 1, 1)
+            }
+        };
+        UNIX_EPOCH + Duration::from_secs(days.max(0) as u64 * SECONDS_PER_DAY)
+    }
+}
+
+/// The date of the day `days` after the Unix epoch, as (year, month, day).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's `civil_from_days` algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The number of days between the Unix epoch and the given date, the inverse of
+/// [`civil_from_days`].
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// What [`BudgetMiddleware`] does with requests once a budget is exhausted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverBudget {
+    /// Send them, logging a warning when the budget runs out.
+    Warn,
+    /// Fail them with [`BudgetExceeded`].
+    Fail,
+}
+
+/// Error returned by [`BudgetMiddleware`] for requests over an exhausted budget when configured
+/// with [`OverBudget::Fail`].
+#[derive(Debug, Error)]
+#[error("{period:?} budget of {limit} for `{key}` exhausted")]
+pub struct BudgetExceeded {
+    /// The key of the request.
+    pub key: String,
+    /// The period of the exhausted budget.
+    pub period: BudgetPeriod,
+    /// The budget.
+    pub limit: u64,
+    /// The usage so far in the period.
+    pub used: u64,
+    /// When the budget is renewed.
+    pub resets_at: SystemTime,
+}
+
+/// The usage of a budget of [`BudgetMiddleware`] by a key, in the current period.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BudgetUsage {
+    /// The key.
+    pub key: String,
+    /// The period of the budget.
+    pub period: BudgetPeriod,
+    /// The budget.
+    pub limit: u64,
+    /// The usage so far in the period.
+    pub used: u64,
+    /// When the budget is renewed.
+    pub resets_at: SystemTime,
+}
+
+impl BudgetUsage {
+    /// What is left of the budget.
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Counter {
+    index: i64,
+    used: u64,
+}
+
+/// `BudgetMiddleware` counts requests against daily and/or monthly budgets, per key, for APIs
+/// billed by usage or with hard quotas, so that a client can stop before running up costs or
+/// getting locked out.
+///
+/// Requests are keyed by `host:port` by default, or by [`with_key`], e.g. by API key or tenant.
+/// Each request costs 1, or, when a cost header is set with [`with_cost_header`], the cost
+/// reported by the response in that header, such as `X-RateLimit-Cost`. Requests are charged
+/// when sent, and their cost adjusted once their response is received, so failed requests cost
+/// 1.
+///
+/// Periods are calendar days or months in UTC. Once a budget is exhausted, requests are either
+/// sent with a warning or fail with [`BudgetExceeded`] until it's renewed, see [`OverBudget`].
+/// The current usage is returned by [`usage`].
+///
+/// `BudgetMiddleware` is a handle: clones share the same usage.
+///
+/// ```
+/// use http::HeaderName;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::{BudgetMiddleware, BudgetPeriod, OverBudget};
+///
+/// let budget = BudgetMiddleware::new(OverBudget::Fail)
+///     .with_budget(BudgetPeriod::Daily, 10_000)
+///     .with_budget(BudgetPeriod::Monthly, 250_000)
+///     .with_cost_header(HeaderName::from_static("x-ratelimit-cost"));
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(budget.clone())
+///     .build();
+///
+/// for usage in budget.usage() {
+///     println!("{}: {}/{} {:?}", usage.key, usage.used, usage.limit, usage.period);
+/// }
+/// ```
+///
+/// [`usage`]: Self::usage
+/// [`with_cost_header`]: Self::with_cost_header
+/// [`with_key`]: Self::with_key
+#[derive(Clone)]
+pub struct BudgetMiddleware {
+    over_budget: OverBudget,
+    budgets: Vec<(BudgetPeriod, u64)>,
+    key: Arc<KeyFn>,
+    cost_header: Option<HeaderName>,
+    clock: Arc<dyn Clock>,
+    counters: Arc<Mutex<HashMap<(String, BudgetPeriod), Counter>>>,
+}
+
+impl std::fmt::Debug for BudgetMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BudgetMiddleware")
+            .field("over_budget", &self.over_budget)
+            .field("budgets", &self.budgets)
+            .field("cost_header", &self.cost_header)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BudgetMiddleware {
+    /// Construct `BudgetMiddleware` without any budget; add some with
+    /// [`with_budget`](Self::with_budget).
+    pub fn new(over_budget: OverBudget) -> Self {
+        Self {
+            over_budget,
+            budgets: Vec::new(),
+            key: Arc::new(|req, _| Some(host_key(req.url()))),
+            cost_header: None,
+            clock: Arc::new(SystemClock),
+            counters: Arc::default(),
+        }
+    }
+
+    /// Allow each key a usage of `limit` per `period`.
+    pub fn with_budget(mut self, period: BudgetPeriod, limit: u64) -> Self {
+        self.budgets.retain(|(budget, _)| *budget != period);
+        self.budgets.push((period, limit));
+        self
+    }
+
+    /// Count requests against the key returned by `key`. Requests for which it returns `None`
+    /// aren't counted.
+    pub fn with_key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&Request, &Extensions) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Arc::new(key);
+        self
+    }
+
+    /// Charge requests the cost in the `header` of their response, when there is one.
+    pub fn with_cost_header(mut self, header: HeaderName) -> Self {
+        self.cost_header = Some(header);
+        self
+    }
+
+    /// Use `clock` to tell the current period.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The usage of the budgets of all keys with requests in the current periods.
+    pub fn usage(&self) -> Vec<BudgetUsage> {
+        let now = self.clock.system_time();
+        let counters = self.counters.lock().expect("budget lock poisoned");
+        let mut usage: Vec<_> = counters
+            .iter()
+            .filter_map(|((key, period), counter)| {
+                let index = period.index(now);
+                if counter.index != index {
+                    return None;
+                }
+                self.snapshot(key, *period, counter.used, index)
+            })
+            .collect();
+        usage.sort_by(|a, b| (&a.key, a.period as u8).cmp(&(&b.key, b.period as u8)));
+        usage
+    }
+
+    /// The usage of the budgets of `key` in the current periods.
+    pub fn usage_of(&self, key: &str) -> Vec<BudgetUsage> {
+        let now = self.clock.system_time();
+        let counters = self.counters.lock().expect("budget lock poisoned");
+        self.budgets
+            .iter()
+            .filter_map(|(period, _)| {
+                let index = period.index(now);
+                let used = counters
+                    .get(&(key.to_owned(), *period))
+                    .filter(|counter| counter.index == index)
+                    .map_or(0, |counter| counter.used);
+                self.snapshot(key, *period, used, index)
+            })
+            .collect()
+    }
+
+    fn snapshot(
+        &self,
+        key: &str,
+        period: BudgetPeriod,
+        used: u64,
+        index: i64,
+    ) -> Option<BudgetUsage> {
+        let (_, limit) = self.budgets.iter().find(|(budget, _)| *budget == period)?;
+        Some(BudgetUsage {
+            key: key.to_owned(),
+            period,
+            limit: *limit,
+            used,
+            resets_at: period.end(index),
+        })
+    }
+
+    /// Charge `key` with `cost` in every budget. If `enforce` is true and budgets are enforced,
+    /// nothing is charged when a budget is already exhausted, which is returned instead.
+    fn charge(
+        &self,
+        key: &str,
+        cost: i64,
+        enforce: bool,
+    ) -> std::result::Result<(), BudgetExceeded> {
+        let now = self.clock.system_time();
+        let mut counters = self.counters.lock().expect("budget lock poisoned");
+        if enforce && self.over_budget == OverBudget::Fail {
+            for (period, limit) in &self.budgets {
+                let index = period.index(now);
+                let used = counters
+                    .get(&(key.to_owned(), *period))
+                    .filter(|counter| counter.index == index)
+                    .map_or(0, |counter| counter.used);
+                if used >= *limit {
+                    return Err(BudgetExceeded {
+                        key: key.to_owned(),
+                        period: *period,
+                        limit: *limit,
+                        used,
+                        resets_at: period.end(index),
+                    });
+                }
+            }
+        }
+        for (period, limit) in &self.budgets {
+            let index = period.index(now);
+            let counter = counters
+                .entry((key.to_owned(), *period))
+                .or_insert(Counter { index, used: 0 });
+            if counter.index != index {
+                *counter = Counter { index, used: 0 };
+            }
+            let before = counter.used;
+            counter.used = if cost < 0 {
+                before.saturating_sub(cost.unsigned_abs())
+            } else {
+                before.saturating_add(cost as u64)
+            };
+            if before < *limit && counter.used >= *limit {
+                tracing::warn!("{:?} budget of {} for `{}` exhausted", period, limit, key);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for BudgetMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let key = match (self.key)(&req, extensions) {
+            Some(key) if !self.budgets.is_empty() => key,
+            _ => return next.run(req, extensions).await,
+        };
+        self.charge(&key, 1, true).map_err(Error::middleware)?;
+
+        let res = next.run(req, extensions).await?;
+        let cost = self
+            .cost_header
+            .as_ref()
+            .and_then(|header| res.headers().get(header))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u32>().ok());
+        if let Some(cost) = cost {
+            // The request was charged 1 when sent.
+            let _ = self.charge(&key, i64::from(cost) - 1, false);
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,147 @@
+//! `BulkheadMiddleware` isolates the concurrency of different classes of requests.
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use thiserror::Error;
+use tokio::sync::Semaphore;
+
+/// Extension assigning a request to a partition of a [`BulkheadMiddleware`].
+///
+/// ```no_run
+/// # async fn example(client: reqwest_middleware::ClientWithMiddleware) {
+/// use reqwest_limit::RequestClass;
+///
+/// let res = client
+///     .post("https://payments.example.com/charge")
+///     .with_extension(RequestClass::new("payments"))
+///     .send()
+///     .await;
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestClass(pub Cow<'static, str>);
+
+impl RequestClass {
+    /// Create a request class with the given name.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self(name.into())
+    }
+}
+
+/// Error returned by [`BulkheadMiddleware`] when the partition of a request has no free slot.
+#[derive(Debug, Error)]
+#[error("Bulkhead partition {class:?} is full")]
+pub struct BulkheadFull {
+    /// The name of the full partition.
+    pub class: String,
+}
+
+/// `BulkheadMiddleware` partitions concurrency between classes of requests, so that one class
+/// can't starve the others: reserving 10 slots for payment calls means that bulk background syncs
+/// can never take them.
+///
+/// The class of a request is taken from its [`RequestClass`] extension, or else from the first
+/// matching URL prefix registered with [`with_url_prefix`](Self::with_url_prefix). Requests
+/// without a class, or whose class has no partition, go to the default partition if one is set
+/// and are otherwise not limited.
+///
+/// A request whose partition is full fails immediately with [`BulkheadFull`] rather than
+/// queueing.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::BulkheadMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(
+///         BulkheadMiddleware::new()
+///             .with_partition("payments", 10)
+///             .with_url_prefix("https://payments.example.com/", "payments")
+///             .with_default_partition(50),
+///     )
+///     .build();
+/// ```
+#[derive(Default)]
+pub struct BulkheadMiddleware {
+    partitions: HashMap<String, Arc<Semaphore>>,
+    url_prefixes: Vec<(String, String)>,
+    default: Option<Arc<Semaphore>>,
+}
+
+impl BulkheadMiddleware {
+    /// Construct `BulkheadMiddleware` without any partition.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `limit` requests of class `class` in flight.
+    pub fn with_partition(mut self, class: impl Into<String>, limit: usize) -> Self {
+        self.partitions
+            .insert(class.into(), Arc::new(Semaphore::new(limit)));
+        self
+    }
+
+    /// Assign requests whose URL starts with `prefix` to class `class`.
+    pub fn with_url_prefix(mut self, prefix: impl Into<String>, class: impl Into<String>) -> Self {
+        self.url_prefixes.push((prefix.into(), class.into()));
+        self
+    }
+
+    /// Allow at most `limit` requests without a partition of their own in flight.
+    pub fn with_default_partition(mut self, limit: usize) -> Self {
+        self.default = Some(Arc::new(Semaphore::new(limit)));
+        self
+    }
+
+    /// Number of free slots in the partition of class `class`.
+    pub fn available(&self, class: &str) -> Option<usize> {
+        self.partitions
+            .get(class)
+            .map(|partition| partition.available_permits())
+    }
+
+    fn classify<'a>(&'a self, req: &Request, extensions: &'a Extensions) -> Option<&'a str> {
+        if let Some(class) = extensions.get::<RequestClass>() {
+            return Some(&class.0);
+        }
+        self.url_prefixes
+            .iter()
+            .find(|(prefix, _)| req.url().as_str().starts_with(prefix.as_str()))
+            .map(|(_, class)| class.as_str())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for BulkheadMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let class = self.classify(&req, extensions);
+        let partition = match class.and_then(|class| self.partitions.get(class)) {
+            Some(partition) => Some((class.unwrap_or_default(), partition)),
+            None => self
+                .default
+                .as_ref()
+                .map(|partition| ("default", partition)),
+        };
+        let _permit = match partition {
+            Some((class, partition)) => {
+                Some(partition.clone().try_acquire_owned().map_err(|_| {
+                    Error::middleware(BulkheadFull {
+                        class: class.to_owned(),
+                    })
+                })?)
+            }
+            None => None,
+        };
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,118 @@
+//! `ConcurrencyLimitMiddleware` caps the number of requests in flight.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use tokio::sync::Semaphore;
+
+use crate::host_key;
+
+/// Extension recording how long a request waited for a slot in
+/// [`ConcurrencyLimitMiddleware`] before being sent.
+///
+/// It is inserted both in the request extensions and in the
+/// [response extensions](reqwest::Response::extensions), so callers can observe backpressure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueWait(pub Duration);
+
+/// `ConcurrencyLimitMiddleware` caps the number of requests in flight, making further requests
+/// wait for a slot to free up.
+///
+/// Besides the global limit, a per-host limit can be set so that a single slow upstream can't
+/// consume the entire budget. A slot is held until the response headers are received (or the
+/// request fails); reading the response body doesn't count towards the limit.
+///
+/// The per-host slots are only kept while a host has requests in flight or queued: the others are
+/// forgotten whenever a new host is contacted.
+///
+/// The time spent waiting is recorded in a [`QueueWait`] extension.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::ConcurrencyLimitMiddleware;
+///
+/// // At most 100 requests in flight, and at most 10 to any given host.
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(ConcurrencyLimitMiddleware::new(100).with_per_host_limit(10))
+///     .build();
+/// ```
+pub struct ConcurrencyLimitMiddleware {
+    global: Arc<Semaphore>,
+    per_host_limit: Option<usize>,
+    per_host: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl ConcurrencyLimitMiddleware {
+    /// Construct `ConcurrencyLimitMiddleware` allowing `limit` requests in flight.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(limit)),
+            per_host_limit: None,
+            per_host: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Also allow at most `limit` requests in flight to any single `host:port`.
+    pub fn with_per_host_limit(mut self, limit: usize) -> Self {
+        self.per_host_limit = Some(limit);
+        self
+    }
+
+    /// Number of requests that can currently be sent without waiting, ignoring per-host limits.
+    pub fn available(&self) -> usize {
+        self.global.available_permits()
+    }
+
+    /// Number of hosts whose per-host slots are currently tracked.
+    pub fn tracked_hosts(&self) -> usize {
+        self.per_host.lock().expect("per-host lock poisoned").len()
+    }
+
+    fn host_semaphore(&self, host: String, limit: usize) -> Arc<Semaphore> {
+        let mut per_host = self.per_host.lock().expect("per-host lock poisoned");
+        if !per_host.contains_key(&host) {
+            // Forget the hosts nobody holds or waits for a slot of: they only get cloned under
+            // the lock, so they can't be picked up again meanwhile.
+            per_host.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        }
+        per_host
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+            .clone()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ConcurrencyLimitMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let start = Instant::now();
+        // Wait for the host first so that we don't hold a global slot while queueing for a busy
+        // host.
+        let _host_permit = match self.per_host_limit {
+            Some(limit) => Some(
+                self.host_semaphore(host_key(req.url()), limit)
+                    .acquire_owned()
+                    .await
+                    .map_err(Error::middleware)?,
+            ),
+            None => None,
+        };
+        let _permit = self.global.acquire().await.map_err(Error::middleware)?;
+
+        let wait = QueueWait(start.elapsed());
+        extensions.insert(wait);
+        next.run(req, extensions).await.map(|mut res| {
+            res.extensions_mut().insert(wait);
+            res
+        })
+    }
+}
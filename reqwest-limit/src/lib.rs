@@ -0,0 +1,48 @@
+//! Middleware limiting the load a client puts on upstreams, built on [`reqwest_middleware`].
+//!
+//! Use [`ConcurrencyLimitMiddleware`] to cap the number of requests in flight,
+//! [`BulkheadMiddleware`] to keep classes of requests from starving each other,
+//! [`PrioritySchedulerMiddleware`] to send the most important requests first, and
+//! [`RateLimitMiddleware`] to limit the rate at which they are sent. [`AdaptiveRateLimitMiddleware`]
+//! follows the rate limits advertised by servers instead, and [`PolitenessMiddleware`] spaces out
+//! requests to the same site as expected from crawlers. [`RobotsTxtMiddleware`] makes crawlers
+//! honour `robots.txt`. [`BudgetMiddleware`] counts requests against daily and monthly budgets.
+//!
+//! ## Example
+//!
+//! ```
+//! use reqwest_middleware::ClientBuilder;
+//! use reqwest_limit::{ConcurrencyLimitMiddleware, OverLimit, Quota, RateLimitMiddleware};
+//!
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(ConcurrencyLimitMiddleware::new(100).with_per_host_limit(10))
+//!     .with(RateLimitMiddleware::new(OverLimit::Wait).with_per_host_quota(Quota::per_second(10)))
+//!     .build();
+//! ```
+
+mod adaptive;
+mod budget;
+mod bulkhead;
+mod concurrency;
+mod politeness;
+mod priority;
+mod rate;
+mod robots;
+
+pub use adaptive::{AdaptiveRateLimitMiddleware, QuotaState, RateLimitQuotas};
+pub use budget::{BudgetExceeded, BudgetMiddleware, BudgetPeriod, BudgetUsage, OverBudget};
+pub use bulkhead::{BulkheadFull, BulkheadMiddleware, RequestClass};
+pub use concurrency::{ConcurrencyLimitMiddleware, QueueWait};
+pub use politeness::{registrable_domain, CrawlDelays, PolitenessMiddleware};
+pub use priority::{Priority, PrioritySchedulerMiddleware};
+pub use rate::{OverLimit, Quota, RateLimitMiddleware, RateLimited};
+pub use robots::{DisallowedByRobots, RobotsRules, RobotsTxtMiddleware};
+
+/// The `host:port` key used for per-host limits.
+pub(crate) fn host_key(url: &reqwest::Url) -> String {
+    format!(
+        "{}:{}",
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
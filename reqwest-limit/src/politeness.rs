@@ -0,0 +1,142 @@
+//! `PolitenessMiddleware` spaces out requests to the same site.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::Extensions;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Clock, Middleware, Next, Result, SystemClock};
+
+/// Returns the registrable domain of `url` (e.g. `example.co.uk` for `www.example.co.uk`), or
+/// its host when it has none, such as for IP addresses.
+pub fn registrable_domain(url: &Url) -> Option<String> {
+    let domain = match url.domain() {
+        Some(domain) => psl::domain_str(domain).unwrap_or(domain),
+        None => url.host_str()?,
+    };
+    Some(domain.to_ascii_lowercase())
+}
+
+/// A handle on the `Crawl-delay` hints used by a [`PolitenessMiddleware`], which remains usable
+/// after the middleware has been added to a client.
+#[derive(Clone, Debug, Default)]
+pub struct CrawlDelays {
+    delays: Arc<Mutex<HashMap<String, Duration>>>,
+}
+
+impl CrawlDelays {
+    /// Sets the `Crawl-delay` hint for the registrable domain of `url`.
+    pub fn set(&self, url: &Url, delay: Duration) {
+        if let Some(domain) = registrable_domain(url) {
+            let mut delays = self.delays.lock().expect("crawl delays lock poisoned");
+            delays.insert(domain, delay);
+        }
+    }
+
+    /// The `Crawl-delay` hint for `domain`, if any.
+    pub fn get(&self, domain: &str) -> Option<Duration> {
+        let delays = self.delays.lock().expect("crawl delays lock poisoned");
+        delays.get(domain).copied()
+    }
+}
+
+/// `PolitenessMiddleware` enforces a minimum delay between successive requests to the same
+/// registrable domain, as expected from crawlers and scrapers.
+///
+/// Requests to `www.example.com` and `api.example.com` share the same schedule. The delay can be
+/// overridden for individual domains, and a `Crawl-delay` hint for a domain (see [`CrawlDelays`])
+/// is respected when it is longer than the configured delay.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::PolitenessMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(
+///         PolitenessMiddleware::new(Duration::from_secs(1))
+///             .with_domain_delay("example.com", Duration::from_secs(5)),
+///     )
+///     .build();
+/// ```
+pub struct PolitenessMiddleware {
+    delay: Duration,
+    overrides: HashMap<String, Duration>,
+    crawl_delays: CrawlDelays,
+    next_allowed: Mutex<HashMap<String, Instant>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PolitenessMiddleware {
+    /// Construct `PolitenessMiddleware` waiting at least `delay` between requests to a domain.
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            overrides: HashMap::new(),
+            crawl_delays: CrawlDelays::default(),
+            next_allowed: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Wait `delay` between requests to `domain` instead of the default delay.
+    pub fn with_domain_delay(mut self, domain: impl Into<String>, delay: Duration) -> Self {
+        self.overrides
+            .insert(domain.into().to_ascii_lowercase(), delay);
+        self
+    }
+
+    /// Read `Crawl-delay` hints from `crawl_delays`, e.g. to share them between clients.
+    pub fn with_crawl_delays(mut self, crawl_delays: CrawlDelays) -> Self {
+        self.crawl_delays = crawl_delays;
+        self
+    }
+
+    /// Use `clock` to measure time and wait.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// A handle on the `Crawl-delay` hints respected by this middleware.
+    pub fn crawl_delays(&self) -> CrawlDelays {
+        self.crawl_delays.clone()
+    }
+
+    fn delay_for(&self, domain: &str) -> Duration {
+        let delay = self.overrides.get(domain).copied().unwrap_or(self.delay);
+        self.crawl_delays
+            .get(domain)
+            .map_or(delay, |crawl_delay| crawl_delay.max(delay))
+    }
+
+    /// Takes the next slot for `domain`, returning how long to wait for it.
+    fn reserve(&self, domain: String) -> Duration {
+        let delay = self.delay_for(&domain);
+        let now = self.clock.now();
+        let mut next_allowed = self.next_allowed.lock().expect("schedule lock poisoned");
+        let slot = next_allowed.get(&domain).map_or(now, |at| (*at).max(now));
+        next_allowed.insert(domain, slot + delay);
+        slot - now
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for PolitenessMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if let Some(domain) = registrable_domain(req.url()) {
+            let wait = self.reserve(domain);
+            if !wait.is_zero() {
+                tracing::debug!("Waiting {:?} before requesting {}", wait, req.url());
+                self.clock.sleep(wait).await;
+            }
+        }
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,187 @@
+//! `PrioritySchedulerMiddleware` releases queued requests by priority.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+use tokio::sync::oneshot;
+
+use crate::QueueWait;
+
+/// The priority of a request, as a request extension, for [`PrioritySchedulerMiddleware`].
+/// Requests without one have the [`Normal`](Priority::Normal) priority.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Background requests, e.g. bulk synchronisation.
+    Low,
+    /// The priority of requests without a `Priority` extension.
+    #[default]
+    Normal,
+    /// Interactive requests, e.g. those a user is waiting for.
+    High,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    /// The waiters of each priority, in the order of [`Priority`], first come first served.
+    queues: [VecDeque<oneshot::Sender<()>>; 3],
+}
+
+#[derive(Debug)]
+struct Scheduler {
+    limit: usize,
+    state: Mutex<State>,
+}
+
+impl Scheduler {
+    async fn acquire(self: &Arc<Self>, priority: Priority) -> Slot {
+        let rx = {
+            let mut state = self.state.lock().expect("scheduler lock poisoned");
+            if state.in_flight < self.limit {
+                state.in_flight += 1;
+                return Slot(self.clone());
+            }
+            let (tx, rx) = oneshot::channel();
+            state.queues[priority.index()].push_back(tx);
+            rx
+        };
+        let mut waiter = Waiter {
+            rx: Some(rx),
+            scheduler: self.clone(),
+        };
+        waiter.wait().await
+    }
+
+    /// Hands the slot of a completed request to the next waiter, or frees it.
+    fn release(&self) {
+        let mut state = self.state.lock().expect("scheduler lock poisoned");
+        for priority in Priority::ALL {
+            while let Some(waiter) = state.queues[priority.index()].pop_front() {
+                // Waiters which gave up have dropped their receiver.
+                if waiter.send(()).is_ok() {
+                    return;
+                }
+            }
+        }
+        state.in_flight -= 1;
+    }
+}
+
+/// A slot to send a request, released when dropped.
+struct Slot(Arc<Scheduler>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+/// A request waiting for a slot, which gives it back if it's cancelled after being handed one.
+struct Waiter {
+    rx: Option<oneshot::Receiver<()>>,
+    scheduler: Arc<Scheduler>,
+}
+
+impl Waiter {
+    async fn wait(&mut self) -> Slot {
+        if let Some(rx) = &mut self.rx {
+            // Senders are only dropped after sending, or once the receiver is dropped.
+            let _ = rx.await;
+        }
+        self.rx = None;
+        Slot(self.scheduler.clone())
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                self.scheduler.release();
+            }
+        }
+    }
+}
+
+/// `PrioritySchedulerMiddleware` caps the number of requests in flight, like
+/// [`ConcurrencyLimitMiddleware`], and releases the requests waiting for a slot highest
+/// [`Priority`] first, so that interactive requests aren't stuck behind a queue of background
+/// ones sharing the same client.
+///
+/// Requests of the same priority are released in the order they arrived. A slot is held until
+/// the response headers are received, or the request fails. The time spent waiting is recorded
+/// in a [`QueueWait`] extension.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::{Priority, PrioritySchedulerMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(PrioritySchedulerMiddleware::new(10))
+///     .build();
+/// let request = client
+///     .get("https://example.com/search")
+///     .with_extension(Priority::High);
+/// ```
+///
+/// [`ConcurrencyLimitMiddleware`]: crate::ConcurrencyLimitMiddleware
+#[derive(Clone, Debug)]
+pub struct PrioritySchedulerMiddleware {
+    scheduler: Arc<Scheduler>,
+}
+
+impl PrioritySchedulerMiddleware {
+    /// Construct `PrioritySchedulerMiddleware` allowing `limit` requests in flight.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            scheduler: Arc::new(Scheduler {
+                limit,
+                state: Mutex::new(State::default()),
+            }),
+        }
+    }
+
+    /// Number of requests waiting for a slot.
+    pub fn queued(&self) -> usize {
+        let state = self
+            .scheduler
+            .state
+            .lock()
+            .expect("scheduler lock poisoned");
+        state.queues.iter().map(VecDeque::len).sum()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for PrioritySchedulerMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let start = Instant::now();
+        let priority = extensions.get::<Priority>().copied().unwrap_or_default();
+        let _slot = self.scheduler.acquire(priority).await;
+
+        let wait = QueueWait(start.elapsed());
+        extensions.insert(wait);
+        next.run(req, extensions).await.map(|mut res| {
+            res.extensions_mut().insert(wait);
+            res
+        })
+    }
+}
@@ -0,0 +1,313 @@
+//! `RateLimitMiddleware` limits the rate at which requests are sent.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Clock, Error, Middleware, Next, Result, SystemClock};
+use thiserror::Error;
+
+use crate::host_key;
+
+/// A rate of requests, with an allowance for bursts.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_limit::Quota;
+///
+/// // 10 requests per second on average, but never more than 2 at once.
+/// let quota = Quota::per_second(10).with_burst(2);
+/// // 1000 requests per hour.
+/// let quota = Quota::new(1000, Duration::from_secs(60 * 60));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quota {
+    interval: Duration,
+    burst: u32,
+}
+
+impl Quota {
+    /// Allow `requests` per `period`, all of which may be sent in a single burst.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1);
+        Self {
+            interval: period / requests,
+            burst: requests,
+        }
+    }
+
+    /// Allow `requests` per second.
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// Allow `requests` per minute.
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// Set the maximum number of requests that may be sent at once.
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// The time between two requests at the sustained rate.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The maximum number of requests that may be sent at once.
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// How far ahead of schedule requests may run.
+    fn tolerance(&self) -> Duration {
+        self.interval * (self.burst - 1)
+    }
+}
+
+/// What [`RateLimitMiddleware`] does with requests over the limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverLimit {
+    /// Wait until the request can be sent.
+    Wait,
+    /// Fail immediately with [`RateLimited`].
+    Fail,
+}
+
+/// Error returned by [`RateLimitMiddleware`] for requests over the limit when configured with
+/// [`OverLimit::Fail`].
+#[derive(Debug, Error)]
+#[error("Rate limit exceeded, retry after {retry_after:?}")]
+pub struct RateLimited {
+    /// How long until the request could be sent.
+    pub retry_after: Duration,
+}
+
+/// A generic cell rate algorithm (GCRA) limiter, keeping the theoretical arrival time of the next
+/// request for each key.
+#[derive(Debug)]
+pub(crate) struct Gcra {
+    quota: Quota,
+    buckets: Mutex<Buckets>,
+}
+
+/// The theoretical arrival time of each key, with the order in which it was last reserved.
+#[derive(Debug, Default)]
+struct Buckets {
+    tats: HashMap<String, (Instant, u64)>,
+    reservations: u64,
+}
+
+impl Gcra {
+    pub(crate) fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Returns how long a request for `key` must wait to conform to the quota, without
+    /// reserving its slot.
+    pub(crate) fn check(&self, key: &str, now: Instant) -> Duration {
+        let buckets = self.buckets.lock().expect("limiter lock poisoned");
+        let tat = buckets
+            .tats
+            .get(key)
+            .map_or(now, |(tat, _)| (*tat).max(now));
+        tat.saturating_duration_since(now + self.quota.tolerance())
+    }
+
+    /// Reserves the slot of a request for `key`, and returns how long it must wait to conform
+    /// to the quota.
+    ///
+    /// At most `max_keys` keys are tracked: keys whose bucket is full again are forgotten first,
+    /// then those closest to being full, the least recently reserved first among equals.
+    pub(crate) fn acquire(&self, key: &str, now: Instant, max_keys: usize) -> Duration {
+        let mut buckets = self.buckets.lock().expect("limiter lock poisoned");
+        let tat = buckets
+            .tats
+            .get(key)
+            .map_or(now, |(tat, _)| (*tat).max(now));
+        let wait = tat.saturating_duration_since(now + self.quota.tolerance());
+        let tats = &mut buckets.tats;
+        if !tats.contains_key(key) && tats.len() >= max_keys {
+            tats.retain(|_, (tat, _)| *tat > now);
+            while tats.len() >= max_keys.max(1) {
+                let oldest = tats
+                    .iter()
+                    .min_by_key(|(_, bucket)| **bucket)
+                    .map(|(key, _)| key.clone())
+                    .expect("limiter has keys");
+                tats.remove(&oldest);
+            }
+        }
+        buckets.reservations += 1;
+        let reservation = buckets.reservations;
+        buckets
+            .tats
+            .insert(key.to_owned(), (tat + self.quota.interval, reservation));
+        wait
+    }
+}
+
+type KeyFn = dyn Fn(&Request, &Extensions) -> Option<String> + Send + Sync + 'static;
+
+/// `RateLimitMiddleware` limits the rate of requests using the generic cell rate algorithm,
+/// globally, per host and/or per key derived from the request, e.g. from a tenant id or API key
+/// in its extensions.
+///
+/// Requests over the limit either wait for their turn or fail with [`RateLimited`], see
+/// [`OverLimit`]. Time is measured with a [`Clock`], which can be replaced for tests.
+///
+/// The state of each host or key is created on its first request, and at most `max_keys` of
+/// them (10000 by default) are kept per quota: keys which haven't been used for long enough to
+/// be back to a full burst are dropped first, then the least recently limited ones, which may
+/// let their next requests through a bit early.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::{OverLimit, Quota, RateLimitMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(
+///         RateLimitMiddleware::new(OverLimit::Wait)
+///             .with_global_quota(Quota::per_second(100))
+///             .with_per_host_quota(Quota::per_second(10).with_burst(5)),
+///     )
+///     .build();
+/// ```
+///
+/// Per tenant quotas, for tenants recorded in an extension:
+///
+/// ```
+/// use reqwest_limit::{OverLimit, Quota, RateLimitMiddleware};
+///
+/// #[derive(Clone)]
+/// struct TenantId(String);
+///
+/// let limit = RateLimitMiddleware::new(OverLimit::Fail)
+///     .with_per_key_quota(Quota::per_minute(600), |_, extensions| {
+///         extensions.get::<TenantId>().map(|tenant| tenant.0.clone())
+///     });
+/// ```
+pub struct RateLimitMiddleware {
+    over_limit: OverLimit,
+    global: Option<Gcra>,
+    per_host: Option<Gcra>,
+    per_key: Vec<(Gcra, Box<KeyFn>)>,
+    max_keys: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimitMiddleware {
+    /// Construct `RateLimitMiddleware` without any quota; add some with [`with_global_quota`]
+    /// and [`with_per_host_quota`].
+    ///
+    /// [`with_global_quota`]: Self::with_global_quota
+    /// [`with_per_host_quota`]: Self::with_per_host_quota
+    pub fn new(over_limit: OverLimit) -> Self {
+        Self {
+            over_limit,
+            global: None,
+            per_host: None,
+            per_key: Vec::new(),
+            max_keys: 10_000,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Limit all requests to `quota`.
+    pub fn with_global_quota(mut self, quota: Quota) -> Self {
+        self.global = Some(Gcra::new(quota));
+        self
+    }
+
+    /// Limit the requests to each `host:port` to `quota`.
+    pub fn with_per_host_quota(mut self, quota: Quota) -> Self {
+        self.per_host = Some(Gcra::new(quota));
+        self
+    }
+
+    /// Limit the requests for each key returned by `key` to `quota`. Requests for which `key`
+    /// returns `None` aren't limited by this quota.
+    pub fn with_per_key_quota<F>(mut self, quota: Quota, key: F) -> Self
+    where
+        F: Fn(&Request, &Extensions) -> Option<String> + Send + Sync + 'static,
+    {
+        self.per_key.push((Gcra::new(quota), Box::new(key)));
+        self
+    }
+
+    /// Keep the state of at most `max_keys` hosts or keys per quota.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    /// Use `clock` to measure time and wait.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns how long the request must wait, reserving its slot if it will be sent.
+    fn acquire(
+        &self,
+        req: &Request,
+        extensions: &Extensions,
+    ) -> std::result::Result<Duration, RateLimited> {
+        let now = self.clock.now();
+        let reserve = self.over_limit == OverLimit::Wait;
+        let mut limiters = Vec::with_capacity(2 + self.per_key.len());
+        limiters.extend(self.global.as_ref().map(|limiter| (limiter, String::new())));
+        limiters.extend(
+            self.per_host
+                .as_ref()
+                .map(|limiter| (limiter, host_key(req.url()))),
+        );
+        limiters.extend(
+            self.per_key
+                .iter()
+                .filter_map(|(limiter, key)| Some((limiter, key(req, extensions)?))),
+        );
+
+        if !reserve {
+            // Only take slots if all limiters allow the request through right now.
+            let retry_after = limiters
+                .iter()
+                .map(|(limiter, key)| limiter.check(key, now))
+                .max()
+                .unwrap_or_default();
+            if !retry_after.is_zero() {
+                return Err(RateLimited { retry_after });
+            }
+        }
+        Ok(limiters
+            .iter()
+            .map(|(limiter, key)| limiter.acquire(key, now, self.max_keys))
+            .max()
+            .unwrap_or_default())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RateLimitMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let wait = self.acquire(&req, extensions).map_err(Error::middleware)?;
+        if !wait.is_zero() {
+            tracing::debug!("Rate limited, waiting {:?}", wait);
+            self.clock.sleep(wait).await;
+        }
+        next.run(req, extensions).await
+    }
+}
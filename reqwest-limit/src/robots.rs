@@ -0,0 +1,311 @@
+//! `RobotsTxtMiddleware` refuses requests disallowed by the site's `robots.txt`.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::Extensions;
+use reqwest::{Client, Request, Response, Url};
+use reqwest_middleware::{Clock, Error, Middleware, Next, Result, SystemClock};
+use thiserror::Error;
+
+use crate::politeness::CrawlDelays;
+
+/// The longest `Crawl-delay` honoured, larger ones are clamped.
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Error returned by [`RobotsTxtMiddleware`] for requests disallowed by `robots.txt`.
+#[derive(Debug, Error)]
+#[error("Request to {url} is disallowed by robots.txt")]
+pub struct DisallowedByRobots {
+    /// The URL that was requested.
+    pub url: Url,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+impl Rule {
+    /// Matches `path` against the pattern, where `*` matches any sequence of characters and a
+    /// trailing `$` anchors the pattern at the end of the path.
+    fn matches(&self, path: &str) -> bool {
+        let (pattern, anchored) = match self.pattern.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (self.pattern.as_str(), false),
+        };
+        let mut parts = pattern.split('*');
+        let first = parts.next().unwrap_or_default();
+        let mut rest = match path.strip_prefix(first) {
+            Some(rest) => rest,
+            None => return false,
+        };
+        let parts: Vec<_> = parts.collect();
+        for (i, part) in parts.iter().enumerate() {
+            let last = i + 1 == parts.len();
+            if last && anchored {
+                return rest.ends_with(part);
+            }
+            match rest.find(part) {
+                Some(at) => rest = &rest[at + part.len()..],
+                None => return false,
+            }
+        }
+        !anchored || rest.is_empty()
+    }
+}
+
+/// The rules of a `robots.txt` file that apply to a given user-agent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// Parses the rules of `robots_txt` for `user_agent`, falling back to the `*` group when no
+    /// group names it.
+    pub fn parse(robots_txt: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let mut specific: Option<RobotsRules> = None;
+        let mut wildcard: Option<RobotsRules> = None;
+        // Whether the current group applies to us, as the specific or the wildcard group.
+        let (mut is_specific, mut is_wildcard) = (false, false);
+        let mut in_agents = false;
+
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            if key == "user-agent" {
+                if !in_agents {
+                    is_specific = false;
+                    is_wildcard = false;
+                    in_agents = true;
+                }
+                let agent = value.to_ascii_lowercase();
+                if agent == "*" {
+                    is_wildcard = true;
+                } else if !agent.is_empty() && user_agent.contains(&agent) {
+                    is_specific = true;
+                }
+                continue;
+            }
+            in_agents = false;
+            let group = if is_specific {
+                specific.get_or_insert_with(Default::default)
+            } else if is_wildcard {
+                wildcard.get_or_insert_with(Default::default)
+            } else {
+                continue;
+            };
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => group.rules.push(Rule {
+                    allow: key == "allow",
+                    pattern: value.to_owned(),
+                }),
+                "crawl-delay" => {
+                    group.crawl_delay =
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|secs| *secs >= 0.0)
+                            .map(|secs| {
+                                Duration::try_from_secs_f64(secs)
+                                    .map_or(MAX_CRAWL_DELAY, |delay| delay.min(MAX_CRAWL_DELAY))
+                            })
+                }
+                _ => {}
+            }
+        }
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    /// Rules disallowing everything, used when `robots.txt` couldn't be fetched.
+    fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_owned(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// Returns true if `url` may be requested. The most specific matching rule wins, with `Allow`
+    /// winning ties.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_owned(),
+        };
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(&path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    /// The `Crawl-delay` for the user-agent, if any, at most an hour.
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+struct CachedRules {
+    rules: Arc<RobotsRules>,
+    expires: Instant,
+}
+
+/// `RobotsTxtMiddleware` fetches and caches the `robots.txt` of each origin, and refuses requests
+/// to paths it disallows for the configured user-agent with [`DisallowedByRobots`].
+///
+/// `robots.txt` is fetched with a plain [`reqwest::Client`] so that the fetch doesn't go through
+/// the middleware chain, and cached for a day by default. As recommended by RFC 9309, a missing
+/// `robots.txt` (any `4xx` response) allows everything while a server error or a network failure
+/// disallows everything, unless [`allow_on_fetch_failure`](Self::allow_on_fetch_failure) is
+/// set.
+///
+/// `Crawl-delay` directives can be forwarded to a [`PolitenessMiddleware`] with
+/// [`with_crawl_delays`](Self::with_crawl_delays).
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_limit::{PolitenessMiddleware, RobotsTxtMiddleware};
+///
+/// let politeness = PolitenessMiddleware::new(Duration::from_secs(1));
+/// let robots = RobotsTxtMiddleware::new(reqwest::Client::new(), "my-crawler")
+///     .with_crawl_delays(politeness.crawl_delays());
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(robots)
+///     .with(politeness)
+///     .build();
+/// ```
+///
+/// [`PolitenessMiddleware`]: crate::PolitenessMiddleware
+pub struct RobotsTxtMiddleware {
+    client: Client,
+    user_agent: String,
+    ttl: Duration,
+    allow_on_fetch_failure: bool,
+    crawl_delays: Option<CrawlDelays>,
+    cache: Mutex<HashMap<String, CachedRules>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl RobotsTxtMiddleware {
+    /// Construct `RobotsTxtMiddleware` fetching `robots.txt` with `client` and applying the rules
+    /// for the `user_agent` product token.
+    pub fn new(client: Client, user_agent: impl Into<String>) -> Self {
+        Self {
+            client,
+            user_agent: user_agent.into(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            allow_on_fetch_failure: false,
+            crawl_delays: None,
+            cache: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set how long a fetched `robots.txt` is cached for.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Allow all requests to an origin whose `robots.txt` couldn't be fetched because of a
+    /// server or network error.
+    pub fn allow_on_fetch_failure(mut self, allow: bool) -> Self {
+        self.allow_on_fetch_failure = allow;
+        self
+    }
+
+    /// Record `Crawl-delay` directives in `crawl_delays`.
+    pub fn with_crawl_delays(mut self, crawl_delays: CrawlDelays) -> Self {
+        self.crawl_delays = Some(crawl_delays);
+        self
+    }
+
+    /// Use `clock` to expire cached `robots.txt` files.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    async fn rules(&self, url: &Url) -> Arc<RobotsRules> {
+        let origin = url.origin().ascii_serialization();
+        let now = self.clock.now();
+        {
+            let cache = self.cache.lock().expect("robots cache lock poisoned");
+            if let Some(cached) = cache.get(&origin).filter(|cached| cached.expires > now) {
+                return cached.rules.clone();
+            }
+        }
+
+        let rules = Arc::new(self.fetch(url).await);
+        if let (Some(crawl_delays), Some(delay)) = (&self.crawl_delays, rules.crawl_delay()) {
+            crawl_delays.set(url, delay);
+        }
+        let mut cache = self.cache.lock().expect("robots cache lock poisoned");
+        cache.insert(
+            origin,
+            CachedRules {
+                rules: rules.clone(),
+                expires: now + self.ttl,
+            },
+        );
+        rules
+    }
+
+    async fn fetch(&self, url: &Url) -> RobotsRules {
+        let mut robots_url = url.clone();
+        robots_url.set_path("/robots.txt");
+        robots_url.set_query(None);
+        robots_url.set_fragment(None);
+
+        let result = match self.client.get(robots_url).send().await {
+            Ok(res) if res.status().is_success() => res.text().await.map(Some),
+            Ok(res) if res.status().is_client_error() => Ok(None),
+            Ok(res) => res.error_for_status().map(|_| None),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(Some(body)) => RobotsRules::parse(&body, &self.user_agent),
+            Ok(None) => RobotsRules::default(),
+            Err(e) => {
+                tracing::debug!("Failed to fetch robots.txt for {}: {}", url, e);
+                if self.allow_on_fetch_failure {
+                    RobotsRules::default()
+                } else {
+                    RobotsRules::disallow_all()
+                }
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RobotsTxtMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !self.rules(req.url()).await.is_allowed(req.url()) {
+            return Err(Error::middleware(DisallowedByRobots {
+                url: req.url().clone(),
+            }));
+        }
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::{Client, StatusCode};
+use reqwest_limit::AdaptiveRateLimitMiddleware;
+use reqwest_middleware::{ClientBuilder, Clock, MockClock};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_requests_wait_for_quota_reset() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-ratelimit-limit", "10")
+                .insert_header("x-ratelimit-remaining", "0")
+                .insert_header("x-ratelimit-reset", "5"),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let start = clock.now();
+    let limiter = AdaptiveRateLimitMiddleware::new().with_clock(clock.clone());
+    let quotas = limiter.quotas();
+    let client = ClientBuilder::new(Client::new()).with(limiter).build();
+
+    client.get(server.uri()).send().await.unwrap();
+    let quota = quotas.get(&server.uri().parse().unwrap()).unwrap();
+    assert_eq!(quota.limit, Some(10));
+    assert_eq!(quota.remaining, 0);
+
+    client.get(server.uri()).send().await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn assert_too_many_requests_pauses_host() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "30"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let start = clock.now();
+    let client = ClientBuilder::new(Client::new())
+        .with(AdaptiveRateLimitMiddleware::new().with_clock(clock.clone()))
+        .build();
+
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(clock.now() - start, Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn assert_huge_headers_are_ignored() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "18446744073709551615")
+                .insert_header("x-ratelimit-remaining", "0")
+                .insert_header("x-ratelimit-reset", "18446744073709551615"),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let start = clock.now();
+    let client = ClientBuilder::new(Client::new())
+        .with(AdaptiveRateLimitMiddleware::new().with_clock(clock.clone()))
+        .build();
+
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(clock.now(), start);
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use http::HeaderName;
+use reqwest::Client;
+use reqwest_limit::{BudgetExceeded, BudgetMiddleware, BudgetPeriod, OverBudget};
+use reqwest_middleware::{ClientBuilder, Clock, Error, MockClock};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn fails_requests_over_budget() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let budget = BudgetMiddleware::new(OverBudget::Fail)
+        .with_budget(BudgetPeriod::Daily, 2)
+        .with_clock(clock.clone());
+    let client = ClientBuilder::new(Client::new())
+        .with(budget.clone())
+        .build();
+
+    client.get(server.uri()).send().await.unwrap();
+    client.get(server.uri()).send().await.unwrap();
+    let err = client.get(server.uri()).send().await.unwrap_err();
+    let exceeded = match err {
+        Error::Middleware(err) => err.downcast::<BudgetExceeded>().unwrap(),
+        err => panic!("unexpected error: {}", err),
+    };
+    assert_eq!(exceeded.period, BudgetPeriod::Daily);
+    assert_eq!(exceeded.used, 2);
+    assert!(exceeded.resets_at > clock.system_time());
+    assert!(exceeded.resets_at <= clock.system_time() + Duration::from_secs(24 * 60 * 60));
+
+    let usage = budget.usage();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].remaining(), 0);
+}
+
+#[tokio::test]
+async fn renews_budgets_with_the_period() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            BudgetMiddleware::new(OverBudget::Fail)
+                .with_budget(BudgetPeriod::Daily, 1)
+                .with_clock(clock.clone()),
+        )
+        .build();
+
+    client.get(server.uri()).send().await.unwrap();
+    assert!(client.get(server.uri()).send().await.is_err());
+    clock.advance(Duration::from_secs(24 * 60 * 60));
+    client.get(server.uri()).send().await.unwrap();
+}
+
+#[tokio::test]
+async fn charges_cost_from_header_and_warns() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).insert_header("x-ratelimit-cost", "7"))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let budget = BudgetMiddleware::new(OverBudget::Warn)
+        .with_budget(BudgetPeriod::Monthly, 10)
+        .with_cost_header(HeaderName::from_static("x-ratelimit-cost"))
+        .with_key(|_, _| Some("acme".to_owned()));
+    let client = ClientBuilder::new(Client::new())
+        .with(budget.clone())
+        .build();
+
+    for _ in 0..3 {
+        client.get(server.uri()).send().await.unwrap();
+    }
+    let usage = budget.usage_of("acme");
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].used, 21);
+    assert_eq!(usage[0].limit, 10);
+}
+
+#[test]
+fn monthly_budgets_reset_on_the_first_of_the_month() {
+    let clock = Arc::new(MockClock::new());
+    let budget = BudgetMiddleware::new(OverBudget::Fail)
+        .with_budget(BudgetPeriod::Monthly, 10)
+        .with_clock(clock.clone());
+
+    let usage = budget.usage_of("api.example.com:443");
+    let resets_at = usage[0].resets_at;
+    let days = resets_at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        / (24 * 60 * 60);
+    // The next month starts at midnight, within 31 days.
+    assert_eq!(
+        resets_at,
+        SystemTime::UNIX_EPOCH + Duration::from_secs(days * 24 * 60 * 60)
+    );
+    assert!(resets_at > clock.system_time());
+    assert!(resets_at <= clock.system_time() + Duration::from_secs(31 * 24 * 60 * 60));
+    assert_eq!(usage[0].used, 0);
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_limit::{BulkheadFull, BulkheadMiddleware, RequestClass};
+use reqwest_middleware::{ClientBuilder, Error};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_full_partition_rejects_without_affecting_others() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            BulkheadMiddleware::new()
+                .with_partition("payments", 1)
+                .with_partition("background", 1),
+        )
+        .build();
+    let send = |class: &'static str| {
+        client
+            .get(server.uri())
+            .with_extension(RequestClass::new(class))
+            .send()
+    };
+
+    let (first, second, payment) = tokio::join!(send("background"), send("background"), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        send("payments").await
+    });
+    let rejected = match (first, second) {
+        (Ok(_), Err(err)) | (Err(err), Ok(_)) => err,
+        _ => panic!("expected exactly one background request to be rejected"),
+    };
+    match rejected {
+        Error::Middleware(err) => {
+            assert_eq!(err.downcast::<BulkheadFull>().unwrap().class, "background")
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+    payment.unwrap();
+}
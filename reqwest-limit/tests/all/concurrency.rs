@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_limit::{ConcurrencyLimitMiddleware, QueueWait};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_requests_queue_over_limit() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(ConcurrencyLimitMiddleware::new(1))
+        .build();
+
+    let (first, second) = tokio::join!(
+        client.get(server.uri()).send(),
+        client.get(server.uri()).send()
+    );
+    let waits: Vec<_> = [first.unwrap(), second.unwrap()]
+        .iter()
+        .map(|res| res.extensions().get::<QueueWait>().unwrap().0)
+        .collect();
+
+    // One of the requests had to wait for the other to complete.
+    assert!(waits.iter().any(|wait| *wait >= Duration::from_millis(150)));
+    assert!(waits.iter().any(|wait| *wait < Duration::from_millis(150)));
+}
+
+#[tokio::test]
+async fn assert_idle_hosts_are_forgotten() {
+    let (first, second) = (MockServer::start().await, MockServer::start().await);
+    for server in [&first, &second] {
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+            .mount(server)
+            .await;
+    }
+
+    let limit = Arc::new(ConcurrencyLimitMiddleware::new(10).with_per_host_limit(1));
+    let client = ClientBuilder::new(Client::new())
+        .with_arc(limit.clone())
+        .build();
+
+    // The second host is contacted while the first one is busy, so both are kept.
+    let (a, b) = tokio::join!(
+        client.get(first.uri()).send(),
+        client.get(second.uri()).send()
+    );
+    a.unwrap();
+    b.unwrap();
+    assert_eq!(limit.tracked_hosts(), 2);
+
+    // Only the host contacted last is kept once the others are idle.
+    for _ in 0..3 {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        client.get(server.uri()).send().await.unwrap();
+        assert_eq!(limit.tracked_hosts(), 1);
+    }
+}
@@ -0,0 +1,8 @@
+mod adaptive;
+mod budget;
+mod bulkhead;
+mod concurrency;
+mod politeness;
+mod priority;
+mod rate;
+mod robots;
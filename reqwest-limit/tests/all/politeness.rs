@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_limit::{registrable_domain, PolitenessMiddleware};
+use reqwest_middleware::{ClientBuilder, Clock, MockClock};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_requests_to_same_domain_are_spaced_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(4)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let start = clock.now();
+    let politeness = PolitenessMiddleware::new(Duration::from_secs(1)).with_clock(clock.clone());
+    let crawl_delays = politeness.crawl_delays();
+    let client = ClientBuilder::new(Client::new()).with(politeness).build();
+
+    client.get(server.uri()).send().await.unwrap();
+    client.get(server.uri()).send().await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(1));
+
+    // A longer Crawl-delay takes precedence from the next request onwards.
+    crawl_delays.set(&server.uri().parse().unwrap(), Duration::from_secs(10));
+    client.get(server.uri()).send().await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(2));
+    client.get(server.uri()).send().await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(12));
+}
+
+#[test]
+fn assert_registrable_domain_groups_subdomains() {
+    let url = "https://www.example.co.uk/path".parse().unwrap();
+    assert_eq!(registrable_domain(&url).unwrap(), "example.co.uk");
+    let url = "http://127.0.0.1:8080".parse().unwrap();
+    assert_eq!(registrable_domain(&url).unwrap(), "127.0.0.1");
+}
@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest_limit::{Priority, PrioritySchedulerMiddleware};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_high_priority_requests_go_first() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(300)))
+        .mount(&server)
+        .await;
+
+    let scheduler = PrioritySchedulerMiddleware::new(1);
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(scheduler.clone())
+        .build();
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    let mut tasks = Vec::new();
+    for priority in [
+        Priority::Normal,
+        Priority::Low,
+        Priority::Normal,
+        Priority::High,
+    ] {
+        let client = client.clone();
+        let url = server.uri();
+        let order = order.clone();
+        tasks.push(tokio::spawn(async move {
+            client
+                .get(url)
+                .with_extension(priority)
+                .send()
+                .await
+                .unwrap();
+            order.lock().unwrap().push(priority);
+        }));
+        // Queue the requests in order.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(scheduler.queued(), 3);
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(
+        *order.lock().unwrap(),
+        vec![
+            Priority::Normal,
+            Priority::High,
+            Priority::Normal,
+            Priority::Low
+        ]
+    );
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_limit::{OverLimit, Quota, RateLimitMiddleware, RateLimited};
+use reqwest_middleware::{ClientBuilder, Clock, MockClock};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_requests_wait_for_their_turn() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let start = clock.now();
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            RateLimitMiddleware::new(OverLimit::Wait)
+                .with_per_host_quota(Quota::per_second(1))
+                .with_clock(clock.clone()),
+        )
+        .build();
+
+    for _ in 0..3 {
+        client.get(server.uri()).send().await.unwrap();
+    }
+
+    // The first request goes straight through, the others wait a second each.
+    assert_eq!(clock.now() - start, Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn assert_requests_fail_fast_over_limit() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            RateLimitMiddleware::new(OverLimit::Fail)
+                .with_global_quota(Quota::per_second(10).with_burst(1))
+                .with_clock(clock.clone()),
+        )
+        .build();
+
+    client.get(server.uri()).send().await.unwrap();
+    let err = client.get(server.uri()).send().await.unwrap_err();
+    let limited = match err {
+        reqwest_middleware::Error::Middleware(err) => err.downcast::<RateLimited>().unwrap(),
+        err => panic!("unexpected error: {}", err),
+    };
+    assert_eq!(limited.retry_after, Duration::from_millis(100));
+
+    clock.advance(limited.retry_after);
+    client.get(server.uri()).send().await.unwrap();
+}
+
+#[tokio::test]
+async fn assert_rejected_requests_dont_take_slots() {
+    let (first, second) = (MockServer::start().await, MockServer::start().await);
+    for server in [&first, &second] {
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            RateLimitMiddleware::new(OverLimit::Fail)
+                .with_global_quota(Quota::per_second(10).with_burst(2))
+                .with_per_host_quota(Quota::per_second(1))
+                .with_clock(Arc::new(MockClock::new())),
+        )
+        .build();
+
+    client.get(first.uri()).send().await.unwrap();
+    // Rejected by the per host quota, without taking the second global slot.
+    assert!(client.get(first.uri()).send().await.is_err());
+    client.get(second.uri()).send().await.unwrap();
+    assert!(client.get(second.uri()).send().await.is_err());
+}
+
+#[derive(Clone)]
+struct TenantId(&'static str);
+
+fn per_tenant(max_keys: usize, clock: Arc<MockClock>) -> reqwest_middleware::ClientWithMiddleware {
+    ClientBuilder::new(Client::new())
+        .with(
+            RateLimitMiddleware::new(OverLimit::Fail)
+                .with_per_key_quota(Quota::per_second(1), |_, extensions| {
+                    extensions
+                        .get::<TenantId>()
+                        .map(|tenant| tenant.0.to_owned())
+                })
+                .with_max_keys(max_keys)
+                .with_clock(clock),
+        )
+        .build()
+}
+
+#[tokio::test]
+async fn assert_keyed_quotas_are_per_tenant() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(4)
+        .mount(&server)
+        .await;
+
+    let client = per_tenant(100, Arc::new(MockClock::new()));
+    let send = |tenant| {
+        client
+            .get(server.uri())
+            .with_extension(TenantId(tenant))
+            .send()
+    };
+
+    send("acme").await.unwrap();
+    send("globex").await.unwrap();
+    assert!(send("acme").await.is_err());
+    assert!(send("globex").await.is_err());
+    // Requests without tenant aren't limited by the keyed quota.
+    client.get(server.uri()).send().await.unwrap();
+    client.get(server.uri()).send().await.unwrap();
+}
+
+#[tokio::test]
+async fn assert_keyed_buckets_are_bounded() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let client = per_tenant(2, clock.clone());
+    let send = |tenant| {
+        client
+            .get(server.uri())
+            .with_extension(TenantId(tenant))
+            .send()
+    };
+
+    send("a").await.unwrap();
+    clock.advance(Duration::from_millis(100));
+    send("b").await.unwrap();
+    clock.advance(Duration::from_millis(100));
+    send("c").await.unwrap();
+    // `a` was forgotten to make room for `c`, then `b` for `a`, while `c` is still limited.
+    send("a").await.unwrap();
+    assert!(send("c").await.is_err());
+}
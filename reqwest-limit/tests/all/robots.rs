@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_limit::{DisallowedByRobots, RobotsRules, RobotsTxtMiddleware};
+use reqwest_middleware::{ClientBuilder, Error};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ROBOTS_TXT: &str = "\
+User-agent: *
+Disallow: /
+
+User-agent: my-crawler
+Disallow: /private
+Allow: /private/public$
+Crawl-delay: 2
+";
+
+#[test]
+fn assert_rules_for_user_agent() {
+    let rules = RobotsRules::parse(ROBOTS_TXT, "My-Crawler/1.0");
+    let allowed =
+        |url: &str| rules.is_allowed(&format!("https://example.com{}", url).parse().unwrap());
+    assert!(allowed("/"));
+    assert!(!allowed("/private/page"));
+    assert!(allowed("/private/public"));
+    assert!(!allowed("/private/public/page"));
+    assert_eq!(rules.crawl_delay().unwrap().as_secs(), 2);
+
+    let rules = RobotsRules::parse(ROBOTS_TXT, "other-crawler");
+    assert!(!rules.is_allowed(&"https://example.com/".parse().unwrap()));
+}
+
+#[test]
+fn assert_crawl_delay_is_bounded() {
+    let crawl_delay = |value: &str| {
+        RobotsRules::parse(
+            &format!("User-agent: *\nCrawl-delay: {}\n", value),
+            "crawler",
+        )
+        .crawl_delay()
+    };
+    assert_eq!(crawl_delay("0.5"), Some(Duration::from_millis(500)));
+    assert_eq!(crawl_delay("1e30"), Some(Duration::from_secs(60 * 60)));
+    assert_eq!(crawl_delay("-1"), None);
+    assert_eq!(crawl_delay("NaN"), None);
+}
+
+#[tokio::test]
+async fn assert_disallowed_requests_are_refused() {
+    let server = MockServer::start().await;
+    Mock::given(path("/robots.txt"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ROBOTS_TXT))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(RobotsTxtMiddleware::new(Client::new(), "my-crawler"))
+        .build();
+
+    client
+        .get(format!("{}/index.html", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    let err = client
+        .get(format!("{}/private/page", server.uri()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<DisallowedByRobots>()));
+}
+
+#[tokio::test]
+async fn assert_server_error_disallows_unless_configured() {
+    let server = MockServer::start().await;
+    Mock::given(path("/robots.txt"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    Mock::given(path("/"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let strict = ClientBuilder::new(Client::new())
+        .with(RobotsTxtMiddleware::new(Client::new(), "my-crawler"))
+        .build();
+    assert!(strict.get(server.uri()).send().await.is_err());
+
+    let lenient = ClientBuilder::new(Client::new())
+        .with(RobotsTxtMiddleware::new(Client::new(), "my-crawler").allow_on_fetch_failure(true))
+        .build();
+    assert!(lenient.get(server.uri()).send().await.is_ok());
+}
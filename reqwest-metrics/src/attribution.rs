@@ -0,0 +1,194 @@
+//! `AttributionMiddleware` reports the usage of each request with its attribution tags.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http::{Extensions, StatusCode};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+use crate::sink::Outcome;
+
+/// A request extension with the tags attributing the request, e.g. to a team, a feature or a
+/// job, for [`AttributionMiddleware`].
+///
+/// ```no_run
+/// # async fn example(client: reqwest_middleware::ClientWithMiddleware) {
+/// use reqwest_metrics::Attribution;
+///
+/// let res = client
+///     .get("https://api.example.com/reports")
+///     .with_extension(Attribution::new().with_tag("team", "billing").with_tag("job", "42"))
+///     .send()
+///     .await;
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Attribution(pub BTreeMap<String, String>);
+
+impl Attribution {
+    /// Create an empty `Attribution`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the `name` tag, replacing any previous value.
+    pub fn with_tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// The usage of a request reported by [`AttributionMiddleware`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsageRecord {
+    /// The attribution tags of the request, including the default ones.
+    pub tags: BTreeMap<String, String>,
+    /// The request method, e.g. `GET`.
+    pub method: String,
+    /// The host the request is sent to.
+    pub host: String,
+    /// How the request ended.
+    pub outcome: Outcome,
+    /// The status of the response, if any.
+    pub status: Option<StatusCode>,
+    /// The time until the response headers were received.
+    pub duration: Duration,
+    /// The size of the request body, when known upfront.
+    pub request_size: Option<u64>,
+    /// The size of the response body, from its `Content-Length`.
+    pub response_size: Option<u64>,
+}
+
+/// A destination for the [`UsageRecord`]s of [`AttributionMiddleware`], such as a usage
+/// database or an analytics pipeline.
+///
+/// It's implemented by closures taking a `&UsageRecord`. Records are reported as requests end,
+/// so sinks shouldn't block: those writing to slow destinations should buffer records and write
+/// them in the background.
+pub trait UsageSink: 'static + Send + Sync {
+    /// Called once for each request.
+    fn record(&self, record: &UsageRecord);
+}
+
+impl<F> UsageSink for F
+where
+    F: Fn(&UsageRecord) + Send + Sync + 'static,
+{
+    fn record(&self, record: &UsageRecord) {
+        self(record)
+    }
+}
+
+/// `AttributionMiddleware` reports a [`UsageRecord`] for each request to a [`UsageSink`], with
+/// the tags of its [`Attribution`] extension, for chargeback and usage analytics without
+/// tracing infrastructure.
+///
+/// Default tags, such as the name of the service, are added to all the records with
+/// [`with_default_tag`](Self::with_default_tag); the tags of the request take precedence. As
+/// with [`MetricsMiddleware`](crate::MetricsMiddleware), the duration is the time until the
+/// response headers are received and the size of the response is taken from its
+/// `Content-Length`, and added after a retry middleware, each attempt is reported.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_metrics::{AttributionMiddleware, UsageRecord};
+///
+/// let attribution = AttributionMiddleware::new(|record: &UsageRecord| {
+///     println!("{:?} {} {:?}", record.tags, record.host, record.duration);
+/// })
+/// .with_default_tag("service", "invoicing");
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(attribution)
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct AttributionMiddleware {
+    sink: Arc<dyn UsageSink>,
+    default_tags: BTreeMap<String, String>,
+}
+
+impl AttributionMiddleware {
+    /// Construct `AttributionMiddleware` reporting to `sink`.
+    pub fn new<S: UsageSink>(sink: S) -> Self {
+        Self::with_sink_arc(Arc::new(sink))
+    }
+
+    /// Construct `AttributionMiddleware` reporting to a shared `sink`.
+    pub fn with_sink_arc(sink: Arc<dyn UsageSink>) -> Self {
+        Self {
+            sink,
+            default_tags: BTreeMap::new(),
+        }
+    }
+
+    /// Add the `name` tag to the records of all requests without their own.
+    pub fn with_default_tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.default_tags.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// Reports the usage of a request when dropped, as cancelled unless completed first.
+struct Pending<'a> {
+    sink: &'a dyn UsageSink,
+    record: UsageRecord,
+    started_at: Instant,
+}
+
+impl Pending<'_> {
+    fn complete(mut self, result: &Result<Response>) {
+        match result {
+            Ok(res) => {
+                self.record.outcome = Outcome::Status((res.status().as_u16() / 100) as u8);
+                self.record.status = Some(res.status());
+                self.record.response_size = res.content_length();
+            }
+            Err(_) => self.record.outcome = Outcome::Error,
+        }
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.record.duration = self.started_at.elapsed();
+        self.sink.record(&self.record);
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for AttributionMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let mut tags = self.default_tags.clone();
+        if let Some(Attribution(request_tags)) = extensions.get::<Attribution>() {
+            tags.extend(request_tags.clone());
+        }
+        let pending = Pending {
+            sink: self.sink.as_ref(),
+            record: UsageRecord {
+                tags,
+                method: req.method().to_string(),
+                host: req.url().host_str().unwrap_or_default().to_owned(),
+                outcome: Outcome::Cancelled,
+                status: None,
+                duration: Duration::default(),
+                request_size: req
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(|bytes| bytes.len() as u64),
+                response_size: None,
+            },
+            started_at: Instant::now(),
+        };
+
+        let result = next.run(req, extensions).await;
+        pending.complete(&result);
+        result
+    }
+}
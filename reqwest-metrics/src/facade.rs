@@ -0,0 +1,57 @@
+//! `MetricsFacade` records client metrics with the `metrics` crate.
+use metrics::{counter, gauge, histogram, Label};
+
+use crate::sink::{MetricsSink, Outcome, RequestLabels, RequestMetrics};
+
+/// The number of requests sent, labelled with their method, host and status class.
+pub const REQUESTS_TOTAL: &str = "http_client_requests_total";
+/// The number of requests which failed without a response, labelled with their method and host.
+pub const ERRORS_TOTAL: &str = "http_client_errors_total";
+/// The number of requests waiting for their response, labelled with their method and host.
+pub const REQUESTS_IN_FLIGHT: &str = "http_client_requests_in_flight";
+/// The time until the response headers were received, in seconds.
+pub const REQUEST_DURATION_SECONDS: &str = "http_client_request_duration_seconds";
+/// The size of request bodies, in bytes.
+pub const REQUEST_SIZE_BYTES: &str = "http_client_request_size_bytes";
+/// The size of response bodies, in bytes.
+pub const RESPONSE_SIZE_BYTES: &str = "http_client_response_size_bytes";
+
+/// A [`MetricsSink`] recording metrics with the [`metrics`](https://docs.rs/metrics) facade, so
+/// that any of its exporters can be used.
+///
+/// The metrics are named after the constants of this crate, e.g. [`REQUESTS_TOTAL`], and
+/// labelled with `method`, `host` and, for those about completed requests, `status_class`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsFacade;
+
+impl MetricsSink for MetricsFacade {
+    fn request_started(&self, labels: &RequestLabels) {
+        gauge!(REQUESTS_IN_FLIGHT, request_labels(labels)).increment(1.0);
+    }
+
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        let labels = request_labels(&metrics.labels);
+        gauge!(REQUESTS_IN_FLIGHT, labels.clone()).decrement(1.0);
+        if metrics.outcome == Outcome::Error {
+            counter!(ERRORS_TOTAL, labels.clone()).increment(1);
+        }
+
+        let mut labels = labels;
+        labels.push(Label::new("status_class", metrics.outcome.status_class()));
+        counter!(REQUESTS_TOTAL, labels.clone()).increment(1);
+        histogram!(REQUEST_DURATION_SECONDS, labels.clone()).record(metrics.duration.as_secs_f64());
+        if let Some(size) = metrics.request_size {
+            histogram!(REQUEST_SIZE_BYTES, labels.clone()).record(size as f64);
+        }
+        if let Some(size) = metrics.response_size {
+            histogram!(RESPONSE_SIZE_BYTES, labels).record(size as f64);
+        }
+    }
+}
+
+fn request_labels(labels: &RequestLabels) -> Vec<Label> {
+    vec![
+        Label::new("method", labels.method.clone()),
+        Label::new("host", labels.host.clone()),
+    ]
+}
@@ -0,0 +1,46 @@
+//! Metrics middleware built on [`reqwest_middleware`].
+//!
+//! [`MetricsMiddleware`] counts the requests of a client, the failed ones and those in flight,
+//! and measures their latency and the size of their bodies, by method, host and status class.
+//! The metrics are reported to a [`MetricsSink`], by default the [`MetricsFacade`] recording
+//! them with the [`metrics`](https://docs.rs/metrics) crate, so that any of its exporters can be
+//! used. [`StatsdSink`] sends them to a StatsD or DogStatsD agent instead.
+//!
+//! [`AttributionMiddleware`] reports a [`UsageRecord`] for each request, with the [`Attribution`]
+//! tags of the request, to a [`UsageSink`], for chargeback and usage analytics.
+//!
+//! ## Feature flags
+//!
+//! * `prometheus`: [`PrometheusSink`] to register the metrics in a Prometheus registry.
+//!
+//! ## Example
+//!
+//! ```
+//! use reqwest_middleware::ClientBuilder;
+//! use reqwest_metrics::MetricsMiddleware;
+//!
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(MetricsMiddleware::new())
+//!     .build();
+//! ```
+
+mod attribution;
+mod facade;
+mod middleware;
+#[cfg(feature = "prometheus")]
+mod prometheus_sink;
+mod sink;
+#[cfg(not(target_arch = "wasm32"))]
+mod statsd;
+
+pub use attribution::{Attribution, AttributionMiddleware, UsageRecord, UsageSink};
+pub use facade::{
+    MetricsFacade, ERRORS_TOTAL, REQUESTS_IN_FLIGHT, REQUESTS_TOTAL, REQUEST_DURATION_SECONDS,
+    REQUEST_SIZE_BYTES, RESPONSE_SIZE_BYTES,
+};
+pub use middleware::MetricsMiddleware;
+#[cfg(feature = "prometheus")]
+pub use prometheus_sink::{HostLabel, PrometheusSink, PrometheusSinkBuilder};
+pub use sink::{MetricsSink, Outcome, RequestLabels, RequestMetrics};
+#[cfg(not(target_arch = "wasm32"))]
+pub use statsd::{StatsdFormat, StatsdSink};
@@ -0,0 +1,119 @@
+//! `MetricsMiddleware` measures the requests of a client.
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+use crate::facade::MetricsFacade;
+use crate::sink::{MetricsSink, Outcome, RequestLabels, RequestMetrics};
+
+/// `MetricsMiddleware` measures the requests of a client and reports them to a [`MetricsSink`],
+/// the [`metrics`](https://docs.rs/metrics) crate by default.
+///
+/// It counts requests, failed requests and requests in flight, and measures their duration and
+/// the size of their bodies, by method, host and status class. The duration is the time until
+/// the response headers are received, excluding the reading of the body, whose size is taken
+/// from its `Content-Length`. Requests cancelled before their response are reported as such.
+///
+/// Added after a retry middleware, each attempt is measured.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_metrics::MetricsMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(MetricsMiddleware::new())
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct MetricsMiddleware {
+    sink: Arc<dyn MetricsSink>,
+}
+
+impl MetricsMiddleware {
+    /// Construct `MetricsMiddleware` recording metrics with the `metrics` crate.
+    pub fn new() -> Self {
+        Self::with_sink(MetricsFacade)
+    }
+
+    /// Construct `MetricsMiddleware` reporting metrics to `sink`.
+    pub fn with_sink<S: MetricsSink>(sink: S) -> Self {
+        Self::with_sink_arc(Arc::new(sink))
+    }
+
+    /// Construct `MetricsMiddleware` reporting metrics to a shared `sink`.
+    pub fn with_sink_arc(sink: Arc<dyn MetricsSink>) -> Self {
+        Self { sink }
+    }
+}
+
+impl Default for MetricsMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reports a request as finished when dropped, as cancelled unless completed first.
+struct InFlight<'a> {
+    sink: &'a dyn MetricsSink,
+    metrics: RequestMetrics,
+    started_at: Instant,
+}
+
+impl InFlight<'_> {
+    fn complete(mut self, outcome: Outcome, response_size: Option<u64>) {
+        self.metrics.outcome = outcome;
+        self.metrics.response_size = response_size;
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.metrics.duration = self.started_at.elapsed();
+        self.sink.request_finished(&self.metrics);
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for MetricsMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let labels = RequestLabels {
+            method: req.method().to_string(),
+            host: req.url().host_str().unwrap_or_default().to_owned(),
+        };
+        let request_size = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| bytes.len() as u64);
+        self.sink.request_started(&labels);
+        let in_flight = InFlight {
+            sink: self.sink.as_ref(),
+            metrics: RequestMetrics {
+                labels,
+                outcome: Outcome::Cancelled,
+                duration: Duration::default(),
+                request_size,
+                response_size: None,
+            },
+            started_at: Instant::now(),
+        };
+
+        let result = next.run(req, extensions).await;
+        match &result {
+            Ok(res) => in_flight.complete(
+                Outcome::Status((res.status().as_u16() / 100) as u8),
+                res.content_length(),
+            ),
+            Err(_) => in_flight.complete(Outcome::Error, None),
+        }
+        result
+    }
+}
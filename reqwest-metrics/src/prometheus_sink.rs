@@ -0,0 +1,257 @@
+//! `PrometheusSink` registers client metrics in a Prometheus registry.
+use std::collections::HashSet;
+
+use prometheus::core::Collector;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry};
+
+use crate::facade::{
+    ERRORS_TOTAL, REQUESTS_IN_FLIGHT, REQUESTS_TOTAL, REQUEST_DURATION_SECONDS, REQUEST_SIZE_BYTES,
+    RESPONSE_SIZE_BYTES,
+};
+use crate::sink::{MetricsSink, Outcome, RequestLabels, RequestMetrics};
+
+/// The label value of the hosts and methods left out by the cardinality controls.
+const OTHER: &str = "_OTHER";
+
+const METHODS: &[&str] = &[
+    "CONNECT", "DELETE", "GET", "HEAD", "OPTIONS", "PATCH", "POST", "PUT", "TRACE",
+];
+
+/// How the `host` label of a [`PrometheusSink`] is set, to bound the number of time series.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HostLabel {
+    /// The host of each request.
+    All,
+    /// The hosts of the set, and `_OTHER` for any other host.
+    Only(HashSet<String>),
+    /// No `host` label.
+    None,
+}
+
+/// Builds a [`PrometheusSink`], see [`PrometheusSink::builder`].
+#[derive(Clone, Debug)]
+pub struct PrometheusSinkBuilder {
+    namespace: Option<String>,
+    duration_buckets: Vec<f64>,
+    size_buckets: Vec<f64>,
+    host_label: HostLabel,
+}
+
+impl PrometheusSinkBuilder {
+    /// Prefix the metric names with `namespace`, e.g. `myapp_http_client_requests_total`.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Set the buckets of the duration histogram, in seconds.
+    pub fn with_duration_buckets(mut self, buckets: Vec<f64>) -> Self {
+        self.duration_buckets = buckets;
+        self
+    }
+
+    /// Set the buckets of the body size histograms, in bytes.
+    pub fn with_size_buckets(mut self, buckets: Vec<f64>) -> Self {
+        self.size_buckets = buckets;
+        self
+    }
+
+    /// Set how the `host` label is set, the host of each request by default.
+    pub fn with_host_label(mut self, host_label: HostLabel) -> Self {
+        self.host_label = host_label;
+        self
+    }
+
+    /// Registers the metrics in `registry`, failing if they already are, in which case none of
+    /// them is registered.
+    pub fn register(self, registry: &Registry) -> prometheus::Result<PrometheusSink> {
+        let Self {
+            namespace,
+            duration_buckets,
+            size_buckets,
+            host_label,
+        } = self;
+        let with_host = host_label != HostLabel::None;
+        let request_labels: &[&str] = if with_host {
+            &["method", "host"]
+        } else {
+            &["method"]
+        };
+        let completed_labels: &[&str] = if with_host {
+            &["method", "host", "status_class"]
+        } else {
+            &["method", "status_class"]
+        };
+        let opts = |name: &str, help: &str| {
+            let opts = Opts::new(name, help);
+            match &namespace {
+                Some(namespace) => opts.namespace(namespace.clone()),
+                None => opts,
+            }
+        };
+        let histogram = |name: &str, help: &str, buckets: &[f64]| {
+            HistogramVec::new(
+                HistogramOpts::from(opts(name, help)).buckets(buckets.to_vec()),
+                completed_labels,
+            )
+        };
+
+        let sink = PrometheusSink {
+            requests: IntCounterVec::new(
+                opts(REQUESTS_TOTAL, "Number of HTTP requests sent."),
+                completed_labels,
+            )?,
+            errors: IntCounterVec::new(
+                opts(
+                    ERRORS_TOTAL,
+                    "Number of HTTP requests failed without a response.",
+                ),
+                request_labels,
+            )?,
+            in_flight: IntGaugeVec::new(
+                opts(
+                    REQUESTS_IN_FLIGHT,
+                    "Number of HTTP requests waiting for a response.",
+                ),
+                request_labels,
+            )?,
+            duration: histogram(
+                REQUEST_DURATION_SECONDS,
+                "Time until the HTTP response headers were received, in seconds.",
+                &duration_buckets,
+            )?,
+            request_size: histogram(
+                REQUEST_SIZE_BYTES,
+                "Size of the HTTP request bodies, in bytes.",
+                &size_buckets,
+            )?,
+            response_size: histogram(
+                RESPONSE_SIZE_BYTES,
+                "Size of the HTTP response bodies, in bytes.",
+                &size_buckets,
+            )?,
+            host_label,
+        };
+        for (registered, collector) in sink.collectors().into_iter().enumerate() {
+            if let Err(err) = registry.register(collector) {
+                // Don't leave the metrics registered so far behind.
+                for collector in sink.collectors().into_iter().take(registered) {
+                    let _ = registry.unregister(collector);
+                }
+                return Err(err);
+            }
+        }
+        Ok(sink)
+    }
+}
+
+/// A [`MetricsSink`] registering the client metrics in a Prometheus [`Registry`], to expose them
+/// on the `/metrics` endpoint of a service.
+///
+/// The metrics are named after the constants of this crate, e.g. [`REQUESTS_TOTAL`], and
+/// labelled with `method`, `host` and, for those about completed requests, `status_class`.
+/// Methods other than the standard ones are labelled `_OTHER`, and the [`HostLabel`] bounds
+/// the number of hosts, should requests be sent to many of them.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_metrics::{MetricsMiddleware, PrometheusSink};
+///
+/// # fn main() -> prometheus::Result<()> {
+/// let registry = prometheus::Registry::new();
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(MetricsMiddleware::with_sink(PrometheusSink::new(&registry)?))
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`REQUESTS_TOTAL`]: crate::REQUESTS_TOTAL
+#[derive(Clone)]
+pub struct PrometheusSink {
+    requests: IntCounterVec,
+    errors: IntCounterVec,
+    in_flight: IntGaugeVec,
+    duration: HistogramVec,
+    request_size: HistogramVec,
+    response_size: HistogramVec,
+    host_label: HostLabel,
+}
+
+impl PrometheusSink {
+    fn collectors(&self) -> Vec<Box<dyn Collector>> {
+        vec![
+            Box::new(self.requests.clone()),
+            Box::new(self.errors.clone()),
+            Box::new(self.in_flight.clone()),
+            Box::new(self.duration.clone()),
+            Box::new(self.request_size.clone()),
+            Box::new(self.response_size.clone()),
+        ]
+    }
+
+    /// Registers the metrics in `registry` with the default options.
+    pub fn new(registry: &Registry) -> prometheus::Result<Self> {
+        Self::builder().register(registry)
+    }
+
+    /// Returns a builder to set the options of the metrics before registering them: their
+    /// namespace, the histogram buckets and the `host` label.
+    ///
+    /// Durations have the default Prometheus buckets, from 5ms to 10s, and sizes powers of 4
+    /// from 64B to 16MiB.
+    pub fn builder() -> PrometheusSinkBuilder {
+        PrometheusSinkBuilder {
+            namespace: None,
+            duration_buckets: prometheus::DEFAULT_BUCKETS.to_vec(),
+            size_buckets: (3..=12).map(|exp| 4f64.powi(exp)).collect(),
+            host_label: HostLabel::All,
+        }
+    }
+
+    /// The values of the `method` and `host` labels of a request.
+    fn label_values<'a>(&self, labels: &'a RequestLabels) -> Vec<&'a str> {
+        let method = match METHODS.contains(&labels.method.as_str()) {
+            true => labels.method.as_str(),
+            false => OTHER,
+        };
+        match &self.host_label {
+            HostLabel::All => vec![method, &labels.host],
+            HostLabel::Only(hosts) if hosts.contains(&labels.host) => vec![method, &labels.host],
+            HostLabel::Only(_) => vec![method, OTHER],
+            HostLabel::None => vec![method],
+        }
+    }
+}
+
+impl MetricsSink for PrometheusSink {
+    fn request_started(&self, labels: &RequestLabels) {
+        self.in_flight
+            .with_label_values(&self.label_values(labels))
+            .inc();
+    }
+
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        let mut values = self.label_values(&metrics.labels);
+        self.in_flight.with_label_values(&values).dec();
+        if metrics.outcome == Outcome::Error {
+            self.errors.with_label_values(&values).inc();
+        }
+
+        values.push(metrics.outcome.status_class());
+        self.requests.with_label_values(&values).inc();
+        self.duration
+            .with_label_values(&values)
+            .observe(metrics.duration.as_secs_f64());
+        if let Some(size) = metrics.request_size {
+            self.request_size
+                .with_label_values(&values)
+                .observe(size as f64);
+        }
+        if let Some(size) = metrics.response_size {
+            self.response_size
+                .with_label_values(&values)
+                .observe(size as f64);
+        }
+    }
+}
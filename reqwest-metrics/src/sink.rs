@@ -0,0 +1,65 @@
+//! `MetricsSink` receives the measurements of [`MetricsMiddleware`](crate::MetricsMiddleware).
+use std::time::Duration;
+
+/// The labels of the metrics of a request.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestLabels {
+    /// The request method, e.g. `GET`.
+    pub method: String,
+    /// The host the request is sent to.
+    pub host: String,
+}
+
+/// How a request ended, see [`RequestMetrics::outcome`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// A response was received, with a status code of the given class, from 1 to 5.
+    Status(u8),
+    /// The request failed without a response.
+    Error,
+    /// The request was cancelled before its response, by dropping its future.
+    Cancelled,
+}
+
+impl Outcome {
+    /// The `status_class` label of the outcome: `2xx`, `4xx`, ... or `error` and `cancelled`.
+    pub fn status_class(&self) -> &'static str {
+        match self {
+            Self::Status(1) => "1xx",
+            Self::Status(2) => "2xx",
+            Self::Status(3) => "3xx",
+            Self::Status(4) => "4xx",
+            Self::Status(_) => "5xx",
+            Self::Error => "error",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// The measurements of a completed request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestMetrics {
+    /// The labels of the request.
+    pub labels: RequestLabels,
+    /// How the request ended.
+    pub outcome: Outcome,
+    /// The time until the response headers were received.
+    pub duration: Duration,
+    /// The size of the request body, when known upfront.
+    pub request_size: Option<u64>,
+    /// The size of the response body, from its `Content-Length`.
+    pub response_size: Option<u64>,
+}
+
+/// A destination for the metrics of [`MetricsMiddleware`](crate::MetricsMiddleware), such as a
+/// metrics library or a monitoring agent.
+///
+/// [`MetricsFacade`](crate::MetricsFacade) records them with the
+/// [`metrics`](https://docs.rs/metrics) crate, for use with any of its exporters.
+pub trait MetricsSink: 'static + Send + Sync {
+    /// Called when a request is sent, to count the requests in flight.
+    fn request_started(&self, labels: &RequestLabels);
+
+    /// Called once a request started with [`request_started`](Self::request_started) ended.
+    fn request_finished(&self, metrics: &RequestMetrics);
+}
@@ -0,0 +1,183 @@
+//! `StatsdSink` sends client metrics to a StatsD or DogStatsD agent over UDP.
+use std::fmt::Write;
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use crate::facade::{
+    ERRORS_TOTAL, REQUESTS_IN_FLIGHT, REQUESTS_TOTAL, REQUEST_DURATION_SECONDS, REQUEST_SIZE_BYTES,
+    RESPONSE_SIZE_BYTES,
+};
+use crate::sink::{MetricsSink, Outcome, RequestLabels, RequestMetrics};
+
+/// The line format of a [`StatsdSink`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsdFormat {
+    /// DogStatsD, with the labels of the metrics as tags, e.g. `name:1|c|#method:GET`.
+    DogStatsd,
+    /// Plain StatsD, which has no tags: the metrics are only aggregated by name.
+    Plain,
+}
+
+/// A [`MetricsSink`] sending the client metrics to a StatsD agent, such as the Datadog agent, in
+/// UDP datagrams.
+///
+/// The metrics are named after the constants of this crate, e.g. [`REQUESTS_TOTAL`], and tagged
+/// with `method`, `host` and, for those about completed requests, `status_class`, on top of the
+/// tags set with [`with_tag`](Self::with_tag). Durations and sizes are sent as histograms.
+///
+/// Datagrams are sent without blocking, and dropped should they fail to be sent, as is usual with
+/// StatsD.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_metrics::{MetricsMiddleware, StatsdSink};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let sink = StatsdSink::new("127.0.0.1:8125")?
+///     .with_prefix("checkout")
+///     .with_tag("env", "production");
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(MetricsMiddleware::with_sink(sink))
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`REQUESTS_TOTAL`]: crate::REQUESTS_TOTAL
+#[derive(Debug)]
+pub struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    tags: String,
+    format: StatsdFormat,
+}
+
+impl StatsdSink {
+    /// Construct `StatsdSink` sending DogStatsD datagrams to the agent at `addr`, e.g.
+    /// `127.0.0.1:8125`.
+    pub fn new(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no StatsD address"))?;
+        let local = if addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            prefix: String::new(),
+            tags: String::new(),
+            format: StatsdFormat::DogStatsd,
+        })
+    }
+
+    /// Prefix the metric names with `prefix.`, e.g. `checkout.http_client_requests_total`.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        if !self.prefix.is_empty() && !self.prefix.ends_with('.') {
+            self.prefix.push('.');
+        }
+        self
+    }
+
+    /// Add the `key:value` tag to all the metrics.
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        push_tag(&mut self.tags, key, value);
+        self
+    }
+
+    /// Set the line format, DogStatsD by default.
+    pub fn with_format(mut self, format: StatsdFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    fn push_line(&self, datagram: &mut String, name: &str, value: &str, kind: &str, tags: &str) {
+        if !datagram.is_empty() {
+            datagram.push('\n');
+        }
+        let _ = write!(datagram, "{}{}:{}|{}", self.prefix, name, value, kind);
+        if self.format == StatsdFormat::DogStatsd && !tags.is_empty() {
+            let _ = write!(datagram, "|#{}", tags);
+        }
+    }
+
+    fn request_tags(&self, labels: &RequestLabels) -> String {
+        let mut tags = self.tags.clone();
+        push_tag(&mut tags, "method", &labels.method);
+        push_tag(&mut tags, "host", &labels.host);
+        tags
+    }
+
+    fn send(&self, datagram: &str) {
+        // Metrics are best effort, they are dropped when the agent can't keep up or is down.
+        let _ = self.socket.send(datagram.as_bytes());
+    }
+}
+
+/// Appends the `key:value` tag to `tags`, replacing the characters reserved by DogStatsD.
+fn push_tag(tags: &mut String, key: &str, value: &str) {
+    if !tags.is_empty() {
+        tags.push(',');
+    }
+    let sanitize = |c| match c {
+        ',' | '|' | '#' | '\n' => '_',
+        c => c,
+    };
+    tags.extend(key.chars().map(sanitize));
+    tags.push(':');
+    tags.extend(value.chars().map(sanitize));
+}
+
+impl MetricsSink for StatsdSink {
+    fn request_started(&self, labels: &RequestLabels) {
+        let mut datagram = String::new();
+        let tags = self.request_tags(labels);
+        self.push_line(&mut datagram, REQUESTS_IN_FLIGHT, "+1", "g", &tags);
+        self.send(&datagram);
+    }
+
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        let mut datagram = String::new();
+        let mut tags = self.request_tags(&metrics.labels);
+        self.push_line(&mut datagram, REQUESTS_IN_FLIGHT, "-1", "g", &tags);
+        if metrics.outcome == Outcome::Error {
+            self.push_line(&mut datagram, ERRORS_TOTAL, "1", "c", &tags);
+        }
+
+        push_tag(&mut tags, "status_class", metrics.outcome.status_class());
+        self.push_line(&mut datagram, REQUESTS_TOTAL, "1", "c", &tags);
+        let duration = metrics.duration.as_secs_f64().to_string();
+        self.push_line(
+            &mut datagram,
+            REQUEST_DURATION_SECONDS,
+            &duration,
+            "h",
+            &tags,
+        );
+        if let Some(size) = metrics.request_size {
+            self.push_line(
+                &mut datagram,
+                REQUEST_SIZE_BYTES,
+                &size.to_string(),
+                "h",
+                &tags,
+            );
+        }
+        if let Some(size) = metrics.response_size {
+            self.push_line(
+                &mut datagram,
+                RESPONSE_SIZE_BYTES,
+                &size.to_string(),
+                "h",
+                &tags,
+            );
+        }
+        self.send(&datagram);
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::{Arc, Mutex};
+
+use reqwest::Client;
+use reqwest_metrics::{Attribution, AttributionMiddleware, Outcome, UsageRecord};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_usage_is_reported_with_tags() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+        .mount(&server)
+        .await;
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let sink = records.clone();
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            AttributionMiddleware::new(move |record: &UsageRecord| {
+                sink.lock().unwrap().push(record.clone())
+            })
+            .with_default_tag("service", "invoicing")
+            .with_default_tag("team", "platform"),
+        )
+        .build();
+    client
+        .post(server.uri())
+        .body("payload")
+        .with_extension(
+            Attribution::new()
+                .with_tag("team", "billing")
+                .with_tag("job", "42"),
+        )
+        .send()
+        .await
+        .unwrap();
+    client.post(server.uri()).send().await.unwrap();
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    let tags: Vec<_> = records[0]
+        .tags
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    assert_eq!(tags, ["job=42", "service=invoicing", "team=billing"]);
+    assert_eq!(records[0].method, "POST");
+    assert_eq!(records[0].host, "127.0.0.1");
+    assert_eq!(records[0].outcome, Outcome::Status(2));
+    assert_eq!(records[0].status, Some(reqwest::StatusCode::CREATED));
+    assert_eq!(records[0].request_size, Some(7));
+    assert_eq!(records[0].response_size, Some(7));
+    assert_eq!(records[1].tags.len(), 2);
+    assert_eq!(records[1].tags["team"], "platform");
+}
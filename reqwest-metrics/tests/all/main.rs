@@ -0,0 +1,5 @@
+mod attribution;
+mod middleware;
+#[cfg(feature = "prometheus")]
+mod prometheus_sink;
+mod statsd;
@@ -0,0 +1,84 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest_metrics::{MetricsMiddleware, MetricsSink, Outcome, RequestLabels, RequestMetrics};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Default)]
+struct RecordingSink {
+    started: Mutex<Vec<RequestLabels>>,
+    finished: Mutex<Vec<RequestMetrics>>,
+}
+
+impl MetricsSink for RecordingSink {
+    fn request_started(&self, labels: &RequestLabels) {
+        self.started.lock().unwrap().push(labels.clone());
+    }
+
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        self.finished.lock().unwrap().push(metrics.clone());
+    }
+}
+
+#[tokio::test]
+async fn assert_completed_request_is_measured() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+        .mount(&server)
+        .await;
+
+    let sink = Arc::new(RecordingSink::default());
+    let client = ClientBuilder::new(Client::new())
+        .with(MetricsMiddleware::with_sink_arc(sink.clone()))
+        .build();
+    client
+        .post(server.uri())
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+
+    let labels = RequestLabels {
+        method: "POST".to_owned(),
+        host: "127.0.0.1".to_owned(),
+    };
+    assert_eq!(*sink.started.lock().unwrap(), std::slice::from_ref(&labels));
+    let finished = sink.finished.lock().unwrap();
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].labels, labels);
+    assert_eq!(finished[0].outcome, Outcome::Status(2));
+    assert_eq!(finished[0].outcome.status_class(), "2xx");
+    assert_eq!(finished[0].request_size, Some(7));
+    assert_eq!(finished[0].response_size, Some(7));
+}
+
+#[tokio::test]
+async fn assert_failed_and_cancelled_requests_are_measured() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+        .mount(&server)
+        .await;
+
+    let sink = Arc::new(RecordingSink::default());
+    let client = ClientBuilder::new(Client::new())
+        .with(MetricsMiddleware::with_sink_arc(sink.clone()))
+        .build();
+    client.get("http://127.0.0.1:1").send().await.unwrap_err();
+    let cancelled =
+        tokio::time::timeout(Duration::from_millis(100), client.get(server.uri()).send()).await;
+    assert!(cancelled.is_err());
+
+    let outcomes: Vec<_> = sink
+        .finished
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|metrics| metrics.outcome)
+        .collect();
+    assert_eq!(outcomes, [Outcome::Error, Outcome::Cancelled]);
+}
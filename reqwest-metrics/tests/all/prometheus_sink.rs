@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+
+use reqwest::Client;
+use reqwest_metrics::{HostLabel, MetricsMiddleware, PrometheusSink};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_metrics_are_registered() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let registry = prometheus::Registry::new();
+    let sink = PrometheusSink::builder()
+        .with_namespace("test")
+        .with_host_label(HostLabel::Only(HashSet::new()))
+        .register(&registry)
+        .unwrap();
+    let client = ClientBuilder::new(Client::new())
+        .with(MetricsMiddleware::with_sink(sink))
+        .build();
+    client.get(server.uri()).send().await.unwrap();
+
+    let families = registry.gather();
+    let requests = families
+        .iter()
+        .find(|family| family.get_name() == "test_http_client_requests_total")
+        .unwrap();
+    let metric = &requests.get_metric()[0];
+    let labels: Vec<_> = metric
+        .get_label()
+        .iter()
+        .map(|label| (label.get_name(), label.get_value()))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            ("host", "_OTHER"),
+            ("method", "GET"),
+            ("status_class", "4xx")
+        ]
+    );
+    assert_eq!(metric.get_counter().get_value(), 1.0);
+
+    // Registering the metrics twice fails.
+    assert!(PrometheusSink::builder()
+        .with_namespace("test")
+        .register(&registry)
+        .is_err());
+
+    // A failed registration leaves no metrics behind.
+    let registry = prometheus::Registry::new();
+    let errors = prometheus::IntCounter::new("http_client_errors_total", "Taken.").unwrap();
+    registry.register(Box::new(errors)).unwrap();
+    assert!(PrometheusSink::new(&registry).is_err());
+    assert_eq!(registry.gather().len(), 1);
+}
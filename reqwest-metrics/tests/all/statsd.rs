@@ -0,0 +1,72 @@
+use std::net::UdpSocket;
+
+use reqwest::Client;
+use reqwest_metrics::{MetricsMiddleware, StatsdFormat, StatsdSink};
+use reqwest_middleware::ClientBuilder;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn recv(agent: &UdpSocket) -> String {
+    let mut buf = [0; 4096];
+    let len = agent.recv(&mut buf).unwrap();
+    String::from_utf8(buf[..len].to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn assert_metrics_are_sent_with_tags() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .mount(&server)
+        .await;
+
+    let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sink = StatsdSink::new(agent.local_addr().unwrap())
+        .unwrap()
+        .with_prefix("app")
+        .with_tag("env", "test");
+    let client = ClientBuilder::new(Client::new())
+        .with(MetricsMiddleware::with_sink(sink))
+        .build();
+    client.get(server.uri()).send().await.unwrap();
+
+    assert_eq!(
+        recv(&agent),
+        "app.http_client_requests_in_flight:+1|g|#env:test,method:GET,host:127.0.0.1"
+    );
+    let finished = recv(&agent);
+    let lines: Vec<_> = finished.lines().collect();
+    assert_eq!(
+        lines[..2],
+        [
+            "app.http_client_requests_in_flight:-1|g|#env:test,method:GET,host:127.0.0.1",
+            "app.http_client_requests_total:1|c|#env:test,method:GET,host:127.0.0.1,status_class:2xx",
+        ]
+    );
+    assert!(lines[2].starts_with("app.http_client_request_duration_seconds:"));
+    assert_eq!(
+        lines[3],
+        "app.http_client_response_size_bytes:2|h|#env:test,method:GET,host:127.0.0.1,status_class:2xx"
+    );
+}
+
+#[tokio::test]
+async fn assert_plain_statsd_has_no_tags() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sink = StatsdSink::new(agent.local_addr().unwrap())
+        .unwrap()
+        .with_format(StatsdFormat::Plain);
+    let client = ClientBuilder::new(Client::new())
+        .with(MetricsMiddleware::with_sink(sink))
+        .build();
+    client.get(server.uri()).send().await.unwrap();
+
+    assert_eq!(recv(&agent), "http_client_requests_in_flight:+1|g");
+    assert!(!recv(&agent).contains('#'));
+}
@@ -0,0 +1,129 @@
+use bytes::Bytes;
+use futures::{Stream, TryStreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::{Body, Response, ResponseBuilderExt};
+use std::pin::Pin;
+
+/// A type-erased error, as carried by [`BodyStream`]s.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The body of a message as a stream of chunks, see [`map_response_body`].
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + Send>>;
+
+/// Replaces the body of `res` with the stream returned by `f` from its current body, so that
+/// middleware can transform response bodies as they are read, e.g. to decompress them, verify a
+/// checksum, or count bytes.
+///
+/// `f` is also given the headers of the response, to update those describing the body, such as
+/// `Content-Length` or `Content-Encoding`. The status, version, URL and extensions of the
+/// response are kept. Nothing is read until the body of the returned response is.
+///
+/// # Optional
+///
+/// This requires the optional `stream` feature enabled, and isn't available on `wasm32`, where
+/// reqwest can't stream bodies.
+///
+/// ```
+/// use futures::StreamExt;
+/// use http::Extensions;
+/// use reqwest::{Request, Response};
+/// use reqwest_middleware::{map_response_body, Middleware, Next, Result};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+///
+/// /// Counts the bytes of the bodies of responses.
+/// struct ByteCounter(Arc<AtomicU64>);
+///
+/// #[async_trait::async_trait]
+/// impl Middleware for ByteCounter {
+///     async fn handle(
+///         &self,
+///         req: Request,
+///         extensions: &mut Extensions,
+///         next: Next<'_>,
+///     ) -> Result<Response> {
+///         let res = next.run(req, extensions).await?;
+///         let count = self.0.clone();
+///         Ok(map_response_body(res, move |_headers, body| {
+///             body.inspect(move |chunk| {
+///                 if let Ok(chunk) = chunk {
+///                     count.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+///                 }
+///             })
+///         }))
+///     }
+/// }
+/// ```
+pub fn map_response_body<F, S>(mut res: Response, f: F) -> Response
+where
+    F: FnOnce(&mut HeaderMap, BodyStream) -> S,
+    S: Stream<Item = Result<Bytes, BoxError>> + Send + 'static,
+{
+    let url = res.url().clone();
+    let status = res.status();
+    let version = res.version();
+    let mut headers = std::mem::take(res.headers_mut());
+    let extensions = std::mem::take(res.extensions_mut());
+    let body: BodyStream = Box::pin(res.bytes_stream().map_err(BoxError::from));
+    let body = f(&mut headers, body);
+
+    let mut res = http::Response::builder()
+        .url(url)
+        .body(Body::wrap_stream(body))
+        .expect("response is valid");
+    *res.status_mut() = status;
+    *res.version_mut() = version;
+    *res.headers_mut() = headers;
+    res.extensions_mut().extend(extensions);
+    Response::from(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::StreamExt;
+    use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+    use reqwest::{StatusCode, Version};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Marker(&'static str);
+
+    #[tokio::test]
+    async fn body_is_transformed_and_the_rest_is_kept() {
+        let res: Response = http::Response::builder()
+            .status(StatusCode::CREATED)
+            .version(Version::HTTP_2)
+            .url("https://example.com/items".parse().unwrap())
+            .header(CONTENT_TYPE, "text/plain")
+            .header(CONTENT_LENGTH, "5")
+            .extension(Marker("kept"))
+            .body("hello")
+            .unwrap()
+            .into();
+
+        let res = map_response_body(res, |headers, body| {
+            headers.remove(CONTENT_LENGTH);
+            body.map_ok(|chunk| Bytes::from(chunk.to_ascii_uppercase()))
+        });
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.version(), Version::HTTP_2);
+        assert_eq!(res.url().as_str(), "https://example.com/items");
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/plain");
+        assert!(!res.headers().contains_key(CONTENT_LENGTH));
+        assert_eq!(res.extensions().get(), Some(&Marker("kept")));
+        assert_eq!(res.text().await.unwrap(), "HELLO");
+    }
+
+    #[tokio::test]
+    async fn body_is_read_lazily() {
+        let res: Response = http::Response::builder().body("hello").unwrap().into();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let res = map_response_body(res, move |_, body| {
+            body.inspect(move |_| tx.send(()).unwrap())
+        });
+        assert!(rx.try_recv().is_err());
+        res.bytes().await.unwrap();
+        assert!(rx.try_recv().is_ok());
+    }
+}
@@ -5,13 +5,26 @@ use serde::Serialize;
 use std::convert::TryFrom;
 use std::fmt::{self, Display};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "json")]
+use reqwest::header::ACCEPT;
+#[cfg(feature = "xml")]
+use reqwest::header::CONTENT_TYPE;
 #[cfg(feature = "multipart")]
 use reqwest::multipart;
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
 
+#[cfg(any(feature = "json", feature = "xml"))]
+use crate::error::Error;
 use crate::error::Result;
+#[cfg(feature = "json")]
+use crate::graphql::{GraphQLBody, GraphQLResponse, GRAPHQL_ACCEPT};
 use crate::middleware::{Middleware, Next};
-use crate::RequestInitialiser;
+#[cfg(feature = "json")]
+use crate::StatusError;
+use crate::{Clock, DryRun, RequestInitialiser, SystemClock};
 
 /// A `ClientBuilder` is used to build a [`ClientWithMiddleware`].
 ///
@@ -516,6 +529,70 @@ impl RequestBuilder {
         }
     }
 
+    /// Send an XML body, setting the `Content-Type` header to `application/xml`.
+    ///
+    /// The response can be deserialized with [`XmlResponseExt::xml`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `xml` feature enabled.
+    ///
+    /// # Errors
+    ///
+    /// Unlike [`json`](Self::json), serialization errors are returned right away, as reqwest
+    /// can't carry them to [`send`](Self::send). Serialization fails if `T`'s implementation of
+    /// `Serialize` decides to fail, or if `T` can't be represented as an XML document, e.g. if it
+    /// is a sequence or a primitive.
+    ///
+    /// [`XmlResponseExt::xml`]: crate::XmlResponseExt::xml
+    #[cfg(feature = "xml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xml")))]
+    pub fn xml<T: Serialize + ?Sized>(self, xml: &T) -> Result<Self> {
+        let body = quick_xml::se::to_string(xml).map_err(Error::middleware)?;
+        Ok(self.header(CONTENT_TYPE, "application/xml").body(body))
+    }
+
+    /// Send a GraphQL `query` with its `variables`, as a JSON body.
+    ///
+    /// The request should be a `POST` request. Its response can be handled with
+    /// [`send_graphql`](Self::send_graphql).
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn graphql<V: Serialize + ?Sized>(self, query: &str, variables: &V) -> Self {
+        self.header(ACCEPT, GRAPHQL_ACCEPT).json(&GraphQLBody {
+            query: Some(query),
+            variables,
+            persisted_query_hash: None,
+        })
+    }
+
+    /// Send a persisted GraphQL query with its `variables`, identified by the SHA-256 hash of its
+    /// document, in hexadecimal, following the automatic persisted queries protocol.
+    ///
+    /// Servers answer with a `PersistedQueryNotFound` error until the query was sent once with
+    /// [`graphql`](Self::graphql) and the same hash.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn graphql_persisted<V: Serialize + ?Sized>(
+        self,
+        sha256_hash: &str,
+        variables: &V,
+    ) -> Self {
+        self.header(ACCEPT, GRAPHQL_ACCEPT).json(&GraphQLBody {
+            query: None,
+            variables,
+            persisted_query_hash: Some(sha256_hash),
+        })
+    }
+
     /// Disable CORS on fetching the request.
     ///
     /// # WASM
@@ -597,6 +674,101 @@ impl RequestBuilder {
         client.execute_with_extensions(req?, &mut extensions).await
     }
 
+    /// Waits for `delay`, then sends the request, see [`send`](Self::send).
+    ///
+    /// Use a [`RequestScheduler`](crate::RequestScheduler) for requests which must be sent even
+    /// if the future is dropped, or cancelled from elsewhere.
+    pub async fn send_after(self, delay: Duration) -> Result<Response> {
+        SystemClock.sleep(delay).await;
+        self.send().await
+    }
+
+    /// Waits until `at`, then sends the request, see [`send`](Self::send). The request is sent
+    /// right away if `at` has passed.
+    pub async fn send_at(self, at: Instant) -> Result<Response> {
+        self.send_after(at.saturating_duration_since(Instant::now()))
+            .await
+    }
+
+    /// Sends the request and deserializes the JSON body of its response to `T`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    ///
+    /// # Errors
+    ///
+    /// On top of the errors of [`send`], this method fails with a [`StatusError`] if the status of
+    /// the response isn't a success, keeping its content type and the start of its body, and if
+    /// the body isn't valid JSON for `T`.
+    ///
+    /// [`send`]: Self::send
+    /// [`StatusError`]: crate::StatusError
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub async fn send_json<T: DeserializeOwned>(self) -> Result<T> {
+        let res = self.send().await?;
+        if !res.status().is_success() {
+            let err = StatusError::from_response(res, StatusError::DEFAULT_MAX_BODY_SIZE).await;
+            return Err(Error::middleware(err));
+        }
+        Ok(res.json().await?)
+    }
+
+    /// Sends a GraphQL request, see [`graphql`](Self::graphql), and deserializes the `data` of
+    /// its response to `T`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    ///
+    /// # Errors
+    ///
+    /// On top of the errors of [`send`], this method fails with [`GraphQLErrors`] if the
+    /// response has errors, with a [`StatusError`] if it isn't a GraphQL response and its status
+    /// isn't a success, and if its data isn't valid for `T`.
+    ///
+    /// [`send`]: Self::send
+    /// [`GraphQLErrors`]: crate::GraphQLErrors
+    /// [`StatusError`]: crate::StatusError
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub async fn send_graphql<T: DeserializeOwned>(self) -> Result<T> {
+        let res = self.send().await?;
+        GraphQLResponse::from_response(res).await?.into_data()
+    }
+
+    /// Runs the request through the initialisers and middleware of the client without sending
+    /// it, and returns it as it would have been sent.
+    ///
+    /// The transport answers the request with an empty `200 OK` response instead of sending it,
+    /// see [`DryRun`]. `None` is returned if a middleware answered the request itself, without
+    /// passing it on.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the request can't be built or a middleware fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> reqwest_middleware::Result<()> {
+    /// let req = reqwest_middleware::ClientWithMiddleware::from(reqwest::Client::new())
+    ///     .get("https://hyper.rs")
+    ///     .bearer_auth("token")
+    ///     .dry_run()
+    ///     .await?
+    ///     .expect("request reached the transport");
+    /// assert!(req.headers().contains_key("authorization"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn dry_run(self) -> Result<Option<Request>> {
+        let dry_run = DryRun::new();
+        self.with_extension(dry_run.clone()).send().await?;
+        Ok(dry_run.take_request())
+    }
+
     /// Attempt to clone the RequestBuilder.
     ///
     /// `None` is returned if the RequestBuilder can not be cloned,
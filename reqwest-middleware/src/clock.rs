@@ -0,0 +1,155 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A source of time for middleware which measures time, waits or expires state, e.g. backoff,
+/// rate limiting, caching or circuit breaking.
+///
+/// [`SystemClock`] is used by default; tests can use [`MockClock`] to control time
+/// deterministically.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Returns the current wall-clock time, for comparisons with dates, e.g. those of HTTP
+    /// headers.
+    fn system_time(&self) -> SystemTime;
+
+    /// Waits until `duration` has elapsed.
+    async fn sleep(&self, duration: Duration);
+}
+
+/// The real clock, sleeping with [`tokio::time::sleep`] on non-`wasm32` archs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        #[cfg(not(target_arch = "wasm32"))]
+        tokio::time::sleep(duration).await;
+        #[cfg(target_arch = "wasm32")]
+        wasm_timer::Delay::new(duration)
+            .await
+            .expect("failed sleeping");
+    }
+}
+
+/// A clock which only moves when told to.
+///
+/// [`sleep`](Clock::sleep) returns immediately after advancing the clock by the requested
+/// duration, so code waiting on middleware runs instantly while still observing the passage of
+/// time.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::{Clock, MockClock};
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(clock.now() - start, Duration::from_secs(60));
+/// ```
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<(Instant, SystemTime)>,
+}
+
+impl MockClock {
+    /// Create a clock frozen at the current time.
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new((Instant::now(), SystemTime::now())),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().expect("clock lock poisoned");
+        now.0 += duration;
+        now.1 += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.lock().expect("clock lock poisoned").0
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.now.lock().expect("clock lock poisoned").1
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn advance_moves_both_times() {
+        let clock = MockClock::new();
+        let (now, system_time) = (clock.now(), clock.system_time());
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now() - now, Duration::from_secs(90));
+        assert_eq!(
+            clock.system_time().duration_since(system_time).unwrap(),
+            Duration::from_secs(90)
+        );
+    }
+
+    #[test]
+    fn time_is_frozen() {
+        let clock = MockClock::new();
+        let now = clock.now();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(clock.now(), now);
+    }
+
+    #[tokio::test]
+    async fn sleepers_wake_in_order() {
+        let clock = Arc::new(MockClock::new());
+        let start = clock.now();
+        let woken = Arc::new(Mutex::new(Vec::new()));
+        let sleep = |secs| {
+            let (clock, woken) = (clock.clone(), woken.clone());
+            async move {
+                clock.sleep(Duration::from_secs(secs)).await;
+                woken.lock().unwrap().push((secs, clock.now() - start));
+            }
+        };
+        sleep(1).await;
+        tokio::join!(sleep(2), sleep(3));
+        assert_eq!(
+            *woken.lock().unwrap(),
+            [
+                (1, Duration::from_secs(1)),
+                (2, Duration::from_secs(3)),
+                (3, Duration::from_secs(6)),
+            ]
+        );
+    }
+}
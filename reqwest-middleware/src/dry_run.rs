@@ -0,0 +1,55 @@
+use std::sync::{Arc, Mutex};
+
+use reqwest::{Request, Response, ResponseBuilderExt};
+
+/// Extension putting a request in dry-run mode: it goes through all the middleware, but the
+/// transport answers it with an empty `200 OK` response instead of sending it.
+///
+/// The request as it reached the transport is kept, see [`RequestBuilder::dry_run`], to validate
+/// the configuration of a client or preview its requests. Middleware can check for this
+/// extension to skip their side effects, and the synthetic response carries a [`DryRunResponse`]
+/// extension so that it isn't mistaken for a real one, e.g. stored in a cache.
+///
+/// Every request of a client can be put in dry-run mode with the [`Extension`] initialiser:
+///
+/// ```
+/// use reqwest_middleware::{ClientBuilder, DryRun, Extension};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with_init(Extension(DryRun::new()))
+///     .build();
+/// ```
+///
+/// [`RequestBuilder::dry_run`]: crate::RequestBuilder::dry_run
+/// [`Extension`]: crate::Extension
+#[derive(Clone, Debug, Default)]
+pub struct DryRun {
+    request: Arc<Mutex<Option<Request>>>,
+}
+
+/// Response extension marking the empty responses of requests in [`DryRun`] mode, which middleware
+/// storing responses or learning from them must ignore.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DryRunResponse;
+
+impl DryRun {
+    /// Create a new [`DryRun`] extension.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the last request which reached the transport.
+    pub fn take_request(&self) -> Option<Request> {
+        self.request.lock().expect("dry run lock poisoned").take()
+    }
+
+    pub(crate) fn respond(&self, req: Request) -> Response {
+        let res = http::Response::builder()
+            .url(req.url().clone())
+            .extension(DryRunResponse)
+            .body(Vec::new())
+            .expect("response is valid");
+        *self.request.lock().expect("dry run lock poisoned") = Some(req);
+        Response::from(res)
+    }
+}
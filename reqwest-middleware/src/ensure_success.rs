@@ -0,0 +1,157 @@
+use http::Extensions;
+use reqwest::{Request, Response};
+
+use crate::error::{Error, Result};
+use crate::middleware::{Middleware, Next};
+use crate::StatusError;
+
+/// Extension disabling [`EnsureSuccessMiddleware`] for a request, for callers handling its error
+/// statuses, such as `404 Not Found`, themselves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DisableEnsureSuccess;
+
+/// `EnsureSuccessMiddleware` turns responses with a client or server error status, `4xx` or
+/// `5xx`, into [`StatusError`] errors, within the middleware stack.
+///
+/// It is the middleware counterpart of [`reqwest::Response::error_for_status`]: the middleware
+/// added before it, such as retry or circuit breaker middleware, see the error statuses as failed
+/// requests. The start of the body of the response is kept in the error for diagnostics, up to
+/// [`with_max_body_size`](Self::with_max_body_size) bytes.
+///
+/// Requests with the [`DisableEnsureSuccess`] extension are left untouched.
+///
+/// ```
+/// use reqwest_middleware::{ClientBuilder, DisableEnsureSuccess, EnsureSuccessMiddleware};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(EnsureSuccessMiddleware::new())
+///     .build();
+/// let res = client
+///     .get("https://api.example.com/users/1")
+///     .with_extension(DisableEnsureSuccess)
+///     .send()
+///     .await?;
+/// if res.status() == reqwest::StatusCode::NOT_FOUND {
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct EnsureSuccessMiddleware {
+    max_body_size: usize,
+}
+
+impl EnsureSuccessMiddleware {
+    /// Construct `EnsureSuccessMiddleware` keeping up to
+    /// [`StatusError::DEFAULT_MAX_BODY_SIZE`] bytes of the bodies of error responses.
+    pub fn new() -> Self {
+        Self {
+            max_body_size: StatusError::DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Keep up to `max_body_size` bytes of the bodies of error responses.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+impl Default for EnsureSuccessMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for EnsureSuccessMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let disabled = extensions.get::<DisableEnsureSuccess>().is_some();
+        let res = next.run(req, extensions).await?;
+        let status = res.status();
+        if disabled || !(status.is_client_error() || status.is_server_error()) {
+            return Ok(res);
+        }
+        let err = StatusError::from_response(res, self.max_body_size).await;
+        Err(Error::middleware(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest::StatusCode;
+    use wiremock::matchers::path;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::{ClientBuilder, ClientWithMiddleware};
+
+    async fn server() -> MockServer {
+        let server = MockServer::start().await;
+        for status in [200u16, 302, 304, 404] {
+            Mock::given(path(format!("/{}", status)))
+                .respond_with(ResponseTemplate::new(status).set_body_string("details"))
+                .mount(&server)
+                .await;
+        }
+        server
+    }
+
+    fn client() -> ClientWithMiddleware {
+        ClientBuilder::new(reqwest::Client::new())
+            .with(EnsureSuccessMiddleware::new().with_max_body_size(3))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn non_error_statuses_are_returned() {
+        let server = server().await;
+        for status in [200, 302, 304] {
+            let res = client()
+                .get(format!("{}/{}", server.uri(), status))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status().as_u16(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn error_statuses_fail() {
+        let server = server().await;
+        let err = client()
+            .get(format!("{}/404", server.uri()))
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+        let err = match err {
+            Error::Middleware(err) => err,
+            err => panic!("unexpected error {:?}", err),
+        };
+        let err = err.downcast_ref::<StatusError>().unwrap();
+        assert_eq!(err.body(), b"det");
+        assert!(err.is_truncated());
+    }
+
+    #[tokio::test]
+    async fn disabled_requests_are_untouched() {
+        let server = server().await;
+        let res = client()
+            .get(format!("{}/404", server.uri()))
+            .with_extension(DisableEnsureSuccess)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
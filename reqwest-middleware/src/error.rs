@@ -1,6 +1,8 @@
 use reqwest::{StatusCode, Url};
 use thiserror::Error;
 
+use crate::StatusError;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Error, Debug)]
@@ -82,10 +84,10 @@ impl Error {
         }
     }
 
-    /// Returns true if the error is from `Response::error_for_status`.
+    /// Returns true if the error is from `Response::error_for_status` or a [`StatusError`].
     pub fn is_status(&self) -> bool {
         match self {
-            Error::Middleware(_) => false,
+            Error::Middleware(e) => e.is::<StatusError>(),
             Error::Reqwest(e) => e.is_status(),
         }
     }
@@ -134,7 +136,7 @@ impl Error {
     /// Returns the status code, if the error was generated from a response.
     pub fn status(&self) -> Option<StatusCode> {
         match self {
-            Error::Middleware(_) => None,
+            Error::Middleware(e) => e.downcast_ref::<StatusError>().map(StatusError::status),
             Error::Reqwest(e) => e.status(),
         }
     }
@@ -0,0 +1,303 @@
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::StatusError;
+
+/// The media type of GraphQL responses, defined by the GraphQL over HTTP specification.
+pub const GRAPHQL_RESPONSE_JSON: &str = "application/graphql-response+json";
+
+/// The `Accept` header of GraphQL requests, preferring [`GRAPHQL_RESPONSE_JSON`] over the legacy
+/// `application/json`.
+pub(crate) const GRAPHQL_ACCEPT: &str = "application/graphql-response+json, application/json;q=0.9";
+
+/// The body of a GraphQL request, sent as JSON.
+pub(crate) struct GraphQLBody<'a, V: ?Sized> {
+    pub(crate) query: Option<&'a str>,
+    pub(crate) variables: &'a V,
+    pub(crate) persisted_query_hash: Option<&'a str>,
+}
+
+impl<V: Serialize + ?Sized> Serialize for GraphQLBody<'_, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(query) = self.query {
+            map.serialize_entry("query", query)?;
+        }
+        map.serialize_entry("variables", self.variables)?;
+        if let Some(hash) = self.persisted_query_hash {
+            let extensions = json!({ "persistedQuery": { "version": 1, "sha256Hash": hash } });
+            map.serialize_entry("extensions", &extensions)?;
+        }
+        map.end()
+    }
+}
+
+/// A location in the GraphQL document of a [`GraphQLError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphQLLocation {
+    /// The line of the location, starting at 1.
+    pub line: u64,
+    /// The column of the location, starting at 1.
+    pub column: u64,
+}
+
+/// An error of a GraphQL response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphQLError {
+    /// The description of the error.
+    pub message: String,
+    /// The locations in the GraphQL document the error relates to.
+    pub locations: Vec<GraphQLLocation>,
+    /// The path of the response field which failed, made of field names and list indices.
+    pub path: Vec<Value>,
+    /// The extensions of the error, such as an error code.
+    pub extensions: Map<String, Value>,
+}
+
+impl GraphQLError {
+    fn from_value(value: Value) -> Self {
+        let mut members = match value {
+            Value::Object(members) => members,
+            value => {
+                let mut members = Map::new();
+                members.insert("message".to_owned(), value);
+                members
+            }
+        };
+        let message = match members.remove("message") {
+            Some(Value::String(message)) => message,
+            Some(message) => message.to_string(),
+            None => String::new(),
+        };
+        let locations = match members.remove("locations") {
+            Some(Value::Array(locations)) => locations
+                .iter()
+                .filter_map(|location| {
+                    Some(GraphQLLocation {
+                        line: location.get("line")?.as_u64()?,
+                        column: location.get("column")?.as_u64()?,
+                    })
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let path = match members.remove("path") {
+            Some(Value::Array(path)) => path,
+            _ => Vec::new(),
+        };
+        let extensions = match members.remove("extensions") {
+            Some(Value::Object(extensions)) => extensions,
+            _ => Map::new(),
+        };
+        Self {
+            message,
+            locations,
+            path,
+            extensions,
+        }
+    }
+}
+
+impl fmt::Display for GraphQLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if !self.path.is_empty() {
+            let path = self
+                .path
+                .iter()
+                .map(|segment| match segment {
+                    Value::String(field) => field.clone(),
+                    segment => segment.to_string(),
+                })
+                .collect::<Vec<_>>();
+            write!(f, " (at {})", path.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// The error of a GraphQL response with top-level errors, see
+/// [`RequestBuilder::send_graphql`](crate::RequestBuilder::send_graphql).
+///
+/// The data of the response is kept, as GraphQL servers can return partial results along with
+/// the errors of the fields which failed.
+#[derive(Debug, thiserror::Error)]
+#[error("GraphQL request failed: {}", display_errors(.errors))]
+pub struct GraphQLErrors {
+    /// The errors of the response.
+    pub errors: Vec<GraphQLError>,
+    /// The data of the response, if any.
+    pub data: Option<Value>,
+}
+
+fn display_errors(errors: &[GraphQLError]) -> String {
+    errors
+        .iter()
+        .map(GraphQLError::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// A GraphQL response, as defined by the GraphQL over HTTP specification.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphQLResponse {
+    /// The data of the response, `None` if the request failed before execution.
+    pub data: Option<Value>,
+    /// The errors raised while handling the request.
+    pub errors: Vec<GraphQLError>,
+    /// The extensions of the response.
+    pub extensions: Map<String, Value>,
+}
+
+impl GraphQLResponse {
+    /// Parses a GraphQL response from a JSON `body`.
+    pub fn from_slice(body: &[u8]) -> serde_json::Result<Self> {
+        let mut members: Map<String, Value> = serde_json::from_slice(body)?;
+        let data = members.remove("data").filter(|data| !data.is_null());
+        let errors = match members.remove("errors") {
+            Some(Value::Array(errors)) => {
+                errors.into_iter().map(GraphQLError::from_value).collect()
+            }
+            _ => Vec::new(),
+        };
+        let extensions = match members.remove("extensions") {
+            Some(Value::Object(extensions)) => extensions,
+            _ => Map::new(),
+        };
+        Ok(Self {
+            data,
+            errors,
+            extensions,
+        })
+    }
+
+    /// Reads and parses the GraphQL response of `res`.
+    ///
+    /// # Errors
+    ///
+    /// This method fails with a [`StatusError`] if the status of `res` isn't a success and its
+    /// content type isn't [`GRAPHQL_RESPONSE_JSON`], as its body is then not a GraphQL response,
+    /// and if its body can't be read or parsed.
+    pub async fn from_response(res: Response) -> Result<Self> {
+        let is_graphql = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|essence| essence.trim().eq_ignore_ascii_case(GRAPHQL_RESPONSE_JSON));
+        if !res.status().is_success() && !is_graphql {
+            let err = StatusError::from_response(res, StatusError::DEFAULT_MAX_BODY_SIZE).await;
+            return Err(Error::middleware(err));
+        }
+        let body = res.bytes().await?;
+        Self::from_slice(&body).map_err(Error::middleware)
+    }
+
+    /// Deserializes the data of the response to `T`.
+    ///
+    /// # Errors
+    ///
+    /// This method fails with [`GraphQLErrors`] if the response has errors, even when it has
+    /// partial data, and if the data can't be deserialized to `T`.
+    pub fn into_data<T: DeserializeOwned>(self) -> Result<T> {
+        if !self.errors.is_empty() {
+            return Err(Error::middleware(GraphQLErrors {
+                errors: self.errors,
+                data: self.data,
+            }));
+        }
+        serde_json::from_value(self.data.unwrap_or(Value::Null)).map_err(Error::middleware)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use reqwest::StatusCode;
+
+    const PARTIAL: &str = r#"{
+        "data": {"hero": {"name": "R2-D2", "friends": [{"name": null}]}},
+        "errors": [{
+            "message": "Name for character with ID 1002 could not be fetched.",
+            "locations": [{"line": 6, "column": 7}],
+            "path": ["hero", "friends", 0, "name"],
+            "extensions": {"code": "NOT_FOUND"}
+        }]
+    }"#;
+
+    fn response(status: StatusCode, content_type: &str, body: &'static str) -> Response {
+        http::Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn errors_are_parsed() {
+        let res = GraphQLResponse::from_slice(PARTIAL.as_bytes()).unwrap();
+        assert_eq!(res.errors.len(), 1);
+        let error = &res.errors[0];
+        assert_eq!(error.locations, [GraphQLLocation { line: 6, column: 7 }]);
+        assert_eq!(
+            error.path,
+            [json!("hero"), json!("friends"), json!(0), json!("name")]
+        );
+        assert_eq!(error.extensions["code"], "NOT_FOUND");
+        assert_eq!(
+            error.to_string(),
+            "Name for character with ID 1002 could not be fetched. (at hero.friends.0.name)"
+        );
+    }
+
+    #[test]
+    fn partial_data_is_kept_with_errors() {
+        let res = GraphQLResponse::from_slice(PARTIAL.as_bytes()).unwrap();
+        assert_eq!(res.data.as_ref().unwrap()["hero"]["name"], "R2-D2");
+        let err = match res.into_data::<Value>().unwrap_err() {
+            Error::Middleware(err) => err,
+            err => panic!("unexpected error {:?}", err),
+        };
+        let err = err.downcast_ref::<GraphQLErrors>().unwrap();
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.data.as_ref().unwrap()["hero"]["name"], "R2-D2");
+    }
+
+    #[test]
+    fn data_is_deserialized() {
+        let res = GraphQLResponse::from_slice(br#"{"data": {"hero": {"name": "R2-D2"}}}"#).unwrap();
+        assert!(res.errors.is_empty());
+        let data: HashMap<String, HashMap<String, String>> = res.into_data().unwrap();
+        assert_eq!(data["hero"]["name"], "R2-D2");
+    }
+
+    #[tokio::test]
+    async fn successful_responses_can_carry_errors() {
+        let res = response(StatusCode::OK, "application/json", PARTIAL);
+        let res = GraphQLResponse::from_response(res).await.unwrap();
+        assert_eq!(res.errors.len(), 1);
+        assert!(res.data.is_some());
+    }
+
+    #[tokio::test]
+    async fn error_statuses_are_graphql_responses_only_with_their_media_type() {
+        let body = r#"{"errors": [{"message": "Syntax error"}]}"#;
+        let res = response(StatusCode::BAD_REQUEST, GRAPHQL_RESPONSE_JSON, body);
+        let res = GraphQLResponse::from_response(res).await.unwrap();
+        assert_eq!(res.data, None);
+        assert_eq!(res.errors[0].message, "Syntax error");
+
+        let res = response(StatusCode::BAD_REQUEST, "application/json", body);
+        let err = GraphQLResponse::from_response(res).await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+    }
+}
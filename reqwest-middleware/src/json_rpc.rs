@@ -0,0 +1,395 @@
+use reqwest::{IntoUrl, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{Error, Result};
+use crate::{ClientWithMiddleware, StatusError};
+
+/// The error object of a failed JSON-RPC call.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[error("JSON-RPC error {code}: {message}")]
+pub struct JsonRpcError {
+    /// The type of the error, see the `*` constants for the ones defined by JSON-RPC 2.0.
+    pub code: i64,
+    /// A short description of the error.
+    pub message: String,
+    /// Additional information about the error, defined by the server.
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    /// Invalid JSON was received by the server.
+    pub const PARSE_ERROR: i64 = -32700;
+    /// The JSON sent is not a valid request object.
+    pub const INVALID_REQUEST: i64 = -32600;
+    /// The method does not exist or is not available.
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    /// Invalid method parameters.
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// Internal JSON-RPC error.
+    pub const INTERNAL_ERROR: i64 = -32603;
+
+    fn from_value(value: Value) -> Self {
+        let mut members = match value {
+            Value::Object(members) => members,
+            _ => Map::new(),
+        };
+        Self {
+            code: members
+                .get("code")
+                .and_then(Value::as_i64)
+                .unwrap_or(Self::INTERNAL_ERROR),
+            message: match members.remove("message") {
+                Some(Value::String(message)) => message,
+                _ => String::new(),
+            },
+            data: members.remove("data"),
+        }
+    }
+}
+
+/// The error of a JSON-RPC response which doesn't follow the protocol, such as a response with
+/// neither a result nor an error, or a batch response missing the response of a call.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid JSON-RPC response: {0}")]
+pub struct InvalidJsonRpcResponse(String);
+
+/// A client for JSON-RPC 2.0 over HTTP, sending calls to an endpoint through the middleware of a
+/// [`ClientWithMiddleware`].
+///
+/// Each call is sent as a `POST` request with a JSON body, and is given its own identifier.
+/// Failed calls are surfaced as [`JsonRpcError`]s, wrapped in [`Error::Middleware`].
+///
+/// # Optional
+///
+/// This requires the optional `json` feature enabled.
+///
+/// ```no_run
+/// use reqwest_middleware::{ClientWithMiddleware, JsonRpcClient};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientWithMiddleware::from(reqwest::Client::new());
+/// let rpc = JsonRpcClient::new(client, "https://rpc.example.com")?;
+/// let block: u64 = rpc.call("eth_blockNumber", ()).await?;
+///
+/// let mut batch = rpc.batch();
+/// batch.call("add", [1, 2]);
+/// batch.call("add", [3, 4]);
+/// for result in batch.send().await? {
+///     println!("{:?}", result);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Error::Middleware`]: crate::Error::Middleware
+#[derive(Debug)]
+pub struct JsonRpcClient {
+    client: ClientWithMiddleware,
+    url: Url,
+    next_id: AtomicU64,
+}
+
+impl JsonRpcClient {
+    /// Construct a `JsonRpcClient` sending calls to `url` with `client`.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if `url` can't be parsed.
+    pub fn new<U: IntoUrl>(client: ClientWithMiddleware, url: U) -> Result<Self> {
+        Ok(Self {
+            client,
+            url: url.into_url()?,
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Calls `method` with `params`, which should serialize to an array or an object, or `()`
+    /// for no parameters, and deserializes its result to `R`.
+    ///
+    /// # Errors
+    ///
+    /// On top of the errors of sending the request, this method fails with a [`JsonRpcError`]
+    /// if the call failed, with a [`StatusError`] if the HTTP request failed, and if the result
+    /// isn't valid for `R`.
+    pub async fn call<P, R>(&self, method: &str, params: P) -> Result<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let id = self.next_id();
+        let body = self.post(&request(method, params, Some(id))?).await?;
+        let result = response_result(body)?.map_err(Error::middleware)?;
+        serde_json::from_value(result).map_err(Error::middleware)
+    }
+
+    /// Sends a notification, a call of `method` with `params` whose result isn't returned.
+    pub async fn notify<P: Serialize>(&self, method: &str, params: P) -> Result<()> {
+        self.post(&request(method, params, None)?).await?;
+        Ok(())
+    }
+
+    /// Starts a batch of calls, sent together in one request.
+    pub fn batch(&self) -> JsonRpcBatch<'_> {
+        JsonRpcBatch {
+            client: self,
+            calls: Vec::new(),
+            ids: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Sends `body` and returns the JSON body of the response, `Null` if it is empty, as it is
+    /// for notifications.
+    async fn post(&self, body: &Value) -> Result<Value> {
+        let res = self.client.post(self.url.clone()).json(body).send().await?;
+        if !res.status().is_success() {
+            let err = StatusError::from_response(res, StatusError::DEFAULT_MAX_BODY_SIZE).await;
+            return Err(Error::middleware(err));
+        }
+        let body = res.bytes().await?;
+        if body.is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_slice(&body).map_err(Error::middleware)
+    }
+}
+
+/// A batch of JSON-RPC calls, see [`JsonRpcClient::batch`].
+#[derive(Debug)]
+pub struct JsonRpcBatch<'a> {
+    client: &'a JsonRpcClient,
+    calls: Vec<Value>,
+    ids: Vec<u64>,
+    error: Option<Error>,
+}
+
+impl JsonRpcBatch<'_> {
+    /// Adds a call of `method` with `params` to the batch. Its result is returned by
+    /// [`send`](Self::send) at the position it was added.
+    pub fn call<P: Serialize>(&mut self, method: &str, params: P) -> &mut Self {
+        let id = self.client.next_id();
+        match request(method, params, Some(id)) {
+            Ok(call) => {
+                self.calls.push(call);
+                self.ids.push(id);
+            }
+            Err(err) => self.error = self.error.take().or(Some(err)),
+        }
+        self
+    }
+
+    /// Adds a notification of `method` with `params` to the batch.
+    pub fn notify<P: Serialize>(&mut self, method: &str, params: P) -> &mut Self {
+        match request(method, params, None) {
+            Ok(call) => self.calls.push(call),
+            Err(err) => self.error = self.error.take().or(Some(err)),
+        }
+        self
+    }
+
+    /// Sends the batch, returning the results of its calls in the order they were added.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the parameters of a call couldn't be serialized, if the request
+    /// failed, and if the response doesn't have the result of every call.
+    pub async fn send(self) -> Result<Vec<std::result::Result<Value, JsonRpcError>>> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        if self.calls.is_empty() {
+            return Ok(Vec::new());
+        }
+        let responses = match self.client.post(&Value::Array(self.calls)).await? {
+            Value::Array(responses) => responses,
+            Value::Null if self.ids.is_empty() => return Ok(Vec::new()),
+            // A single error object is returned when the whole batch is rejected.
+            response => {
+                return Err(match response_result(response)? {
+                    Err(err) => Error::middleware(err),
+                    Ok(_) => Error::middleware(InvalidJsonRpcResponse(
+                        "expected an array of responses".to_owned(),
+                    )),
+                })
+            }
+        };
+        let mut results = responses
+            .into_iter()
+            .map(|response| {
+                let id = response.get("id").and_then(Value::as_u64);
+                Ok((id, response_result(response)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.ids
+            .iter()
+            .map(|&id| {
+                let position = results
+                    .iter()
+                    .position(|(response_id, _)| *response_id == Some(id))
+                    .ok_or_else(|| {
+                        Error::middleware(InvalidJsonRpcResponse(format!(
+                            "missing response for call {}",
+                            id
+                        )))
+                    })?;
+                Ok(results.swap_remove(position).1)
+            })
+            .collect()
+    }
+}
+
+fn request<P: Serialize>(method: &str, params: P, id: Option<u64>) -> Result<Value> {
+    let params = serde_json::to_value(params).map_err(Error::middleware)?;
+    let mut request = json!({ "jsonrpc": "2.0", "method": method });
+    let members = request.as_object_mut().expect("request is an object");
+    if !params.is_null() {
+        members.insert("params".to_owned(), params);
+    }
+    if let Some(id) = id {
+        members.insert("id".to_owned(), id.into());
+    }
+    Ok(request)
+}
+
+/// The result of the call of a JSON-RPC response object, failing if it isn't one.
+fn response_result(response: Value) -> Result<std::result::Result<Value, JsonRpcError>> {
+    let mut members = match response {
+        Value::Object(members) => members,
+        response => {
+            return Err(Error::middleware(InvalidJsonRpcResponse(format!(
+                "expected an object, got {}",
+                response
+            ))))
+        }
+    };
+    if let Some(error) = members.remove("error") {
+        return Ok(Err(JsonRpcError::from_value(error)));
+    }
+    match members.remove("result") {
+        Some(result) => Ok(Ok(result)),
+        None => Err(Error::middleware(InvalidJsonRpcResponse(
+            "neither a result nor an error".to_owned(),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Answers `add` calls with the sum of their parameters and other calls with an error,
+    /// responding to batches in reverse order.
+    struct Calculator;
+
+    impl Calculator {
+        fn answer(call: &Value) -> Option<Value> {
+            let id = call.get("id")?;
+            let params = call["params"].as_array().cloned().unwrap_or_default();
+            Some(match call["method"].as_str() {
+                Some("add") => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": params.iter().filter_map(Value::as_i64).sum::<i64>(),
+                }),
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": -32601, "message": "Method not found", "data": call["method"]},
+                }),
+            })
+        }
+    }
+
+    impl Respond for Calculator {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            match serde_json::from_slice(&request.body).unwrap() {
+                Value::Array(calls) => {
+                    let responses: Vec<_> = calls.iter().rev().filter_map(Self::answer).collect();
+                    ResponseTemplate::new(200).set_body_json(responses)
+                }
+                call => match Self::answer(&call) {
+                    Some(response) => ResponseTemplate::new(200).set_body_json(response),
+                    None => ResponseTemplate::new(204),
+                },
+            }
+        }
+    }
+
+    async fn client() -> (MockServer, JsonRpcClient) {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(Calculator)
+            .mount(&server)
+            .await;
+        let rpc = JsonRpcClient::new(reqwest::Client::new().into(), server.uri()).unwrap();
+        (server, rpc)
+    }
+
+    #[tokio::test]
+    async fn calls_return_their_result() {
+        let (_server, rpc) = client().await;
+        let sum: i64 = rpc.call("add", [1, 2]).await.unwrap();
+        assert_eq!(sum, 3);
+        rpc.notify("add", [1, 2]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn error_objects_are_returned() {
+        let (_server, rpc) = client().await;
+        let err = match rpc.call::<_, i64>("sub", [1, 2]).await.unwrap_err() {
+            Error::Middleware(err) => err,
+            err => panic!("unexpected error {:?}", err),
+        };
+        assert_eq!(
+            err.downcast_ref::<JsonRpcError>(),
+            Some(&JsonRpcError {
+                code: JsonRpcError::METHOD_NOT_FOUND,
+                message: "Method not found".to_owned(),
+                data: Some(json!("sub")),
+            })
+        );
+        assert_eq!(err.to_string(), "JSON-RPC error -32601: Method not found");
+    }
+
+    #[tokio::test]
+    async fn batch_responses_are_matched_by_id() {
+        let (_server, rpc) = client().await;
+        let mut batch = rpc.batch();
+        batch
+            .call("add", [1, 2])
+            .notify("add", [0])
+            .call("sub", [3, 4])
+            .call("add", [5, 6]);
+        let results = batch.send().await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], Ok(json!(3)));
+        assert_eq!(
+            results[1].as_ref().unwrap_err().code,
+            JsonRpcError::METHOD_NOT_FOUND
+        );
+        assert_eq!(results[2], Ok(json!(11)));
+    }
+
+    #[test]
+    fn responses_must_have_a_result_or_an_error() {
+        assert!(response_result(json!({"jsonrpc": "2.0", "id": 1})).is_err());
+        assert!(response_result(json!([])).is_err());
+        assert_eq!(
+            response_result(json!({"jsonrpc": "2.0", "id": 1, "error": {}})).unwrap(),
+            Err(JsonRpcError {
+                code: JsonRpcError::INTERNAL_ERROR,
+                message: String::new(),
+                data: None,
+            })
+        );
+    }
+}
@@ -49,12 +49,69 @@
 #[cfg(doctest)]
 pub struct ReadmeDoctests;
 
+#[cfg(all(feature = "stream", not(target_arch = "wasm32")))]
+mod body;
 mod client;
+mod clock;
+mod dry_run;
+mod ensure_success;
 mod error;
+#[cfg(feature = "json")]
+mod graphql;
+#[cfg(feature = "json")]
+mod json_rpc;
+#[cfg(feature = "stream")]
+mod long_poll;
 mod middleware;
+#[cfg(all(feature = "json", feature = "stream"))]
+mod ndjson;
+#[cfg(all(feature = "json", feature = "stream"))]
+mod paginate;
+#[cfg(feature = "json")]
+mod problem;
+mod redaction;
 mod req_init;
+mod resend;
+#[cfg(not(target_arch = "wasm32"))]
+mod schedule;
+#[cfg(feature = "stream")]
+mod sse;
+mod status;
+#[cfg(feature = "xml")]
+mod xml;
 
+#[cfg(all(feature = "stream", not(target_arch = "wasm32")))]
+pub use body::{map_response_body, BodyStream, BoxError};
 pub use client::{ClientBuilder, ClientWithMiddleware, RequestBuilder};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use dry_run::{DryRun, DryRunResponse};
+pub use ensure_success::{DisableEnsureSuccess, EnsureSuccessMiddleware};
 pub use error::{Error, Result};
+#[cfg(feature = "json")]
+pub use graphql::{
+    GraphQLError, GraphQLErrors, GraphQLLocation, GraphQLResponse, GRAPHQL_RESPONSE_JSON,
+};
+#[cfg(feature = "json")]
+pub use json_rpc::{InvalidJsonRpcResponse, JsonRpcBatch, JsonRpcClient, JsonRpcError};
+#[cfg(feature = "stream")]
+pub use long_poll::LongPoll;
 pub use middleware::{Middleware, Next};
+#[cfg(all(feature = "json", feature = "stream"))]
+pub use ndjson::{LineTooLong, NdjsonStream};
+#[cfg(all(feature = "json", feature = "stream"))]
+pub use paginate::{
+    BodyCursor, InvalidPage, LinkHeader, OffsetLimit, Page, PageExtractor, PageRequestNotCloneable,
+    Paginator,
+};
+#[cfg(feature = "json")]
+pub use problem::{Problem, PROBLEM_JSON};
+pub use redaction::{RedactedHeaders, RedactionPolicy, REDACTED};
 pub use req_init::{Extension, RequestInitialiser};
+pub use resend::ResendCount;
+#[cfg(not(target_arch = "wasm32"))]
+pub use schedule::{RequestCancelled, RequestScheduler, ScheduledRequest};
+#[cfg(feature = "stream")]
+pub use sse::{Event, EventSource, EventSourceNotCloneable, EventTooLarge, InvalidEventStream};
+pub use status::StatusError;
+#[cfg(feature = "xml")]
+pub use xml::XmlResponseExt;
@@ -0,0 +1,333 @@
+use futures::Stream;
+use reqwest::Response;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::{Clock, RequestBuilder, SystemClock};
+
+#[cfg(not(target_arch = "wasm32"))]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+type Start<T> = Box<dyn FnOnce(Settings) -> BoxStream<'static, Result<T>> + Send>;
+#[cfg(target_arch = "wasm32")]
+type Start<T> = Box<dyn FnOnce(Settings) -> BoxStream<'static, Result<T>>>;
+
+#[derive(Clone)]
+struct Settings {
+    timeout: Option<Duration>,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+/// A stream of the results of a long-polling API, such as Telegram's `getUpdates` or Consul's
+/// blocking queries, issuing a new request as soon as the previous one completes.
+///
+/// Each request is built by a closure from the cursor of the previous response, e.g. an offset
+/// or an index, and each response is handled by another closure returning the item to yield and
+/// the next cursor, `None` to keep the current one. Requests go through the middleware of their
+/// client.
+///
+/// Requests timing out, see [`with_timeout`](Self::with_timeout), are issued again right away,
+/// as it is how long polls end when nothing happens. Other errors are yielded, and the next
+/// request waits for an exponential backoff, see [`with_backoff`](Self::with_backoff). Dropping
+/// the stream cancels the request in flight.
+///
+/// # Optional
+///
+/// This requires the optional `stream` feature enabled.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use reqwest_middleware::{ClientWithMiddleware, LongPoll};
+/// use std::time::Duration;
+///
+/// # async fn example() {
+/// let client = ClientWithMiddleware::from(reqwest::Client::new());
+/// let mut updates = LongPoll::new(
+///     move |index: Option<&String>| {
+///         client
+///             .get("http://localhost:8500/v1/kv/config")
+///             .query(&[("wait", "5m"), ("index", index.map_or("0", |i| i.as_str()))])
+///     },
+///     |res| async move {
+///         let index = res
+///             .headers()
+///             .get("x-consul-index")
+///             .and_then(|index| index.to_str().ok())
+///             .map(str::to_owned);
+///         Ok((res.text().await?, index))
+///     },
+/// )
+/// .with_timeout(Duration::from_secs(330));
+/// while let Some(update) = updates.next().await {
+///     println!("{:?}", update);
+/// }
+/// # }
+/// ```
+pub struct LongPoll<T> {
+    settings: Settings,
+    start: Option<Start<T>>,
+    stream: Option<BoxStream<'static, Result<T>>>,
+}
+
+impl<T: Send + 'static> LongPoll<T> {
+    /// Construct a `LongPoll` sending the requests built by `request` with the current cursor,
+    /// and handling their responses with `handle`.
+    pub fn new<C, Req, H, Fut>(request: Req, handle: H) -> Self
+    where
+        C: Send + 'static,
+        Req: FnMut(Option<&C>) -> RequestBuilder + Send + 'static,
+        H: FnMut(Response) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(T, Option<C>)>> + Send + 'static,
+    {
+        let start = move |settings: Settings| -> BoxStream<'static, Result<T>> {
+            let poller = Poller {
+                request,
+                handle,
+                cursor: None,
+                backoff: None,
+                settings,
+            };
+            Box::pin(futures::stream::unfold(poller, |mut poller| async move {
+                let item = poller.next().await;
+                Some((item, poller))
+            }))
+        };
+        Self {
+            settings: Settings {
+                timeout: None,
+                min_backoff: Duration::from_secs(1),
+                max_backoff: Duration::from_secs(60),
+                clock: Arc::new(SystemClock),
+            },
+            start: Some(Box::new(start)),
+            stream: None,
+        }
+    }
+}
+
+impl<T> LongPoll<T> {
+    /// Time requests out after `timeout`, to issue them again. It should be longer than the time
+    /// the server holds requests for.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = Some(timeout);
+        self
+    }
+
+    /// Wait from `min` to `max`, doubling on each consecutive error, before issuing a request
+    /// after an error. Defaults to 1 second to 1 minute.
+    pub fn with_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.settings.min_backoff = min;
+        self.settings.max_backoff = max;
+        self
+    }
+
+    /// Wait for backoffs with `clock`, [`SystemClock`] by default.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.settings.clock = clock;
+        self
+    }
+}
+
+impl<T> fmt::Debug for LongPoll<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LongPoll")
+            .field("timeout", &self.settings.timeout)
+            .field("min_backoff", &self.settings.min_backoff)
+            .field("max_backoff", &self.settings.max_backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Stream for LongPoll<T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(start) = this.start.take() {
+            this.stream = Some(start(this.settings.clone()));
+        }
+        match &mut this.stream {
+            Some(stream) => stream.as_mut().poll_next(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+struct Poller<C, Req, H> {
+    request: Req,
+    handle: H,
+    cursor: Option<C>,
+    backoff: Option<Duration>,
+    settings: Settings,
+}
+
+impl<C, Req, H, Fut, T> Poller<C, Req, H>
+where
+    Req: FnMut(Option<&C>) -> RequestBuilder,
+    H: FnMut(Response) -> Fut,
+    Fut: Future<Output = Result<(T, Option<C>)>>,
+{
+    async fn next(&mut self) -> Result<T> {
+        loop {
+            if let Some(backoff) = self.backoff {
+                self.settings.clock.sleep(backoff).await;
+            }
+            let mut builder = (self.request)(self.cursor.as_ref());
+            if let Some(timeout) = self.settings.timeout {
+                builder = builder.timeout(timeout);
+            }
+            let result = match builder.send().await {
+                Ok(res) => (self.handle)(res).await,
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok((item, cursor)) => {
+                    self.backoff = None;
+                    if cursor.is_some() {
+                        self.cursor = cursor;
+                    }
+                    return Ok(item);
+                }
+                Err(err) if err.is_timeout() => self.backoff = None,
+                Err(err) => {
+                    self.backoff = Some(match self.backoff {
+                        Some(backoff) => (backoff * 2).min(self.settings.max_backoff),
+                        None => self.settings.min_backoff,
+                    });
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::StreamExt;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    use crate::{ClientWithMiddleware, MockClock};
+
+    /// Answers with the index following the one of the request.
+    fn next_index(req: &Request) -> ResponseTemplate {
+        let index = req
+            .url
+            .query_pairs()
+            .find(|(name, _)| name == "index")
+            .and_then(|(_, index)| index.parse::<u64>().ok())
+            .unwrap_or(0);
+        ResponseTemplate::new(200).set_body_string((index + 1).to_string())
+    }
+
+    fn long_poll(server: &MockServer) -> LongPoll<u64> {
+        let client = ClientWithMiddleware::from(reqwest::Client::new());
+        let uri = server.uri();
+        LongPoll::new(
+            move |index: Option<&u64>| {
+                let builder = client.get(&uri);
+                match index {
+                    Some(index) => builder.query(&[("index", index)]),
+                    None => builder,
+                }
+            },
+            |res| async move {
+                let index: u64 = res.error_for_status()?.text().await?.parse().unwrap();
+                Ok((index, Some(index)))
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn requests_are_issued_with_the_cursor() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(next_index)
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let items: Vec<_> = long_poll(&server).take(3).collect().await;
+        let items: Vec<_> = items.into_iter().map(Result::unwrap).collect();
+        assert_eq!(items, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn errors_back_off() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(next_index)
+            .mount(&server)
+            .await;
+
+        let clock = Arc::new(MockClock::new());
+        let start = clock.now();
+        let mut poll = long_poll(&server)
+            .with_backoff(Duration::from_secs(1), Duration::from_secs(3))
+            .with_clock(clock.clone());
+        for _ in 0..3 {
+            assert!(poll.next().await.unwrap().is_err());
+        }
+        assert_eq!(clock.now() - start, Duration::from_secs(1 + 2));
+        assert_eq!(poll.next().await.unwrap().unwrap(), 1);
+        assert_eq!(clock.now() - start, Duration::from_secs(1 + 2 + 3));
+        // Successes reset the backoff.
+        assert_eq!(poll.next().await.unwrap().unwrap(), 2);
+        assert_eq!(clock.now() - start, Duration::from_secs(1 + 2 + 3));
+    }
+
+    #[tokio::test]
+    async fn timed_out_requests_are_issued_again() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(next_index)
+            .mount(&server)
+            .await;
+
+        let clock = Arc::new(MockClock::new());
+        let start = clock.now();
+        let mut poll = long_poll(&server)
+            .with_timeout(Duration::from_millis(100))
+            .with_clock(clock.clone());
+        assert_eq!(poll.next().await.unwrap().unwrap(), 1);
+        assert_eq!(clock.now(), start);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn dropping_the_stream_cancels_polling() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(next_index)
+            .mount(&server)
+            .await;
+
+        let mut poll = long_poll(&server);
+        assert_eq!(poll.next().await.unwrap().unwrap(), 1);
+        drop(poll);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+}
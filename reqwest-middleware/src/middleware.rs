@@ -2,6 +2,7 @@ use http::Extensions;
 use reqwest::{Client, Request, Response};
 
 use crate::error::{Error, Result};
+use crate::DryRun;
 
 use std::sync::Arc;
 
@@ -40,6 +41,9 @@ pub trait Middleware: 'static + Send + Sync {
     ///
     /// If you need to forward data down the middleware stack, you can use the `extensions`
     /// argument.
+    ///
+    /// To transform the body of the response as it is read, rather than buffering it, see
+    /// `map_response_body`, with the `stream` feature.
     async fn handle(
         &self,
         req: Request,
@@ -99,6 +103,9 @@ impl<'a> Next<'a> {
         if let Some((current, rest)) = self.middlewares.split_first() {
             self.middlewares = rest;
             current.handle(req, extensions, self)
+        } else if let Some(dry_run) = extensions.get::<DryRun>() {
+            let res = dry_run.respond(req);
+            Box::pin(async move { Ok(res) })
         } else {
             Box::pin(async move { self.client.execute(req).await.map_err(Error::from) })
         }
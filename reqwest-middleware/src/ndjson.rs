@@ -0,0 +1,203 @@
+use bytes::Bytes;
+use futures::Stream;
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::error::{Error, Result};
+
+#[cfg(not(target_arch = "wasm32"))]
+type BodyStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+#[cfg(target_arch = "wasm32")]
+type BodyStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>>>>;
+
+/// The error of an [`NdjsonStream`] reading a line longer than its maximum line size.
+#[derive(Debug, thiserror::Error)]
+#[error("NDJSON line exceeds the maximum size of {0} bytes")]
+pub struct LineTooLong(pub usize);
+
+/// A stream of the values of a newline-delimited JSON (NDJSON, or JSON Lines) response body,
+/// as returned by Docker, Kubernetes watches or log-tailing APIs.
+///
+/// Each non-empty line of the body is deserialized to `T` as it arrives. The body is only read
+/// as the stream is polled, so a slow consumer slows down the download rather than buffering the
+/// body. Lines longer than [`with_max_line_size`](Self::with_max_line_size) fail the stream with
+/// [`LineTooLong`], to bound the memory used by servers which never send a newline.
+///
+/// The stream ends after the first error.
+///
+/// # Optional
+///
+/// This requires the optional `json` and `stream` features enabled.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use reqwest_middleware::{ClientWithMiddleware, NdjsonStream};
+/// use std::collections::HashMap;
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let res = ClientWithMiddleware::from(reqwest::Client::new())
+///     .get("http://localhost:2375/events")
+///     .send()
+///     .await?;
+/// let mut events = NdjsonStream::<HashMap<String, serde_json::Value>>::new(res);
+/// while let Some(event) = events.next().await {
+///     println!("{:?}", event?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct NdjsonStream<T> {
+    body: BodyStream,
+    buffer: Vec<u8>,
+    max_line_size: usize,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> NdjsonStream<T> {
+    /// The maximum line size by default, 1 MiB.
+    pub const DEFAULT_MAX_LINE_SIZE: usize = 1024 * 1024;
+
+    /// Construct an `NdjsonStream` reading the body of `res`.
+    pub fn new(res: Response) -> Self {
+        Self {
+            body: Box::pin(res.bytes_stream()),
+            buffer: Vec::new(),
+            max_line_size: Self::DEFAULT_MAX_LINE_SIZE,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Fail the stream on lines longer than `max_line_size` bytes.
+    pub fn with_max_line_size(mut self, max_line_size: usize) -> Self {
+        self.max_line_size = max_line_size;
+        self
+    }
+}
+
+impl<T: DeserializeOwned> NdjsonStream<T> {
+    /// Takes the next complete non-empty line out of the buffer, or the rest of the buffer at the
+    /// end of the body.
+    fn next_line(&mut self, eof: bool) -> Option<Vec<u8>> {
+        loop {
+            let line = match self.buffer.iter().position(|&b| b == b'\n') {
+                Some(end) => {
+                    let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+                    line.pop();
+                    line
+                }
+                None if eof && !self.buffer.is_empty() => std::mem::take(&mut self.buffer),
+                None => return None,
+            };
+            if line.iter().any(|b| !b.is_ascii_whitespace()) {
+                return Some(line);
+            }
+        }
+    }
+
+    fn parse(&mut self, line: &[u8]) -> Result<T> {
+        serde_json::from_slice(line).map_err(|err| {
+            self.done = true;
+            Error::middleware(err)
+        })
+    }
+}
+
+impl<T: DeserializeOwned> Stream for NdjsonStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if let Some(line) = this.next_line(false) {
+                return Poll::Ready(Some(this.parse(&line)));
+            }
+            if this.buffer.len() > this.max_line_size {
+                this.done = true;
+                return Poll::Ready(Some(Err(Error::middleware(LineTooLong(
+                    this.max_line_size,
+                )))));
+            }
+            match this.body.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.buffer.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(err))) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Ready(None) => {
+                    let line = this.next_line(true);
+                    this.done = true;
+                    return Poll::Ready(line.map(|line| this.parse(&line)));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+
+    fn response(chunks: &[&'static str]) -> Response {
+        let chunks: Vec<std::io::Result<Bytes>> = chunks
+            .iter()
+            .map(|&chunk| Ok(Bytes::from_static(chunk.as_bytes())))
+            .collect();
+        http::Response::builder()
+            .body(reqwest::Body::wrap_stream(futures::stream::iter(chunks)))
+            .unwrap()
+            .into()
+    }
+
+    async fn values(stream: NdjsonStream<Value>) -> Vec<Result<Value>> {
+        stream.collect().await
+    }
+
+    #[tokio::test]
+    async fn lines_split_across_chunks_are_joined() {
+        let res = response(&["{\"a\":", "1}\n\n{\"b\"", ":2}\r\n"]);
+        let values = values(NdjsonStream::new(res)).await;
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].as_ref().unwrap(), &json!({"a": 1}));
+        assert_eq!(values[1].as_ref().unwrap(), &json!({"b": 2}));
+    }
+
+    #[tokio::test]
+    async fn trailing_line_without_newline_is_read() {
+        let res = response(&["1\n2\n", "3"]);
+        let values = values(NdjsonStream::new(res)).await;
+        let values: Vec<_> = values.into_iter().map(Result::unwrap).collect();
+        assert_eq!(values, [json!(1), json!(2), json!(3)]);
+    }
+
+    #[tokio::test]
+    async fn long_lines_fail_the_stream() {
+        let res = response(&["1\n", "\"0123", "456789\"", "\n2\n"]);
+        let values = values(NdjsonStream::new(res).with_max_line_size(8)).await;
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].as_ref().unwrap(), &json!(1));
+        match &values[1] {
+            Err(Error::Middleware(err)) => assert!(err.is::<LineTooLong>()),
+            value => panic!("unexpected value {:?}", value),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_lines_end_the_stream() {
+        let res = response(&["1\nnot json\n2\n"]);
+        let values = values(NdjsonStream::new(res)).await;
+        assert_eq!(values.len(), 2);
+        assert!(values[1].is_err());
+    }
+}
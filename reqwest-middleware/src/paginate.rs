@@ -0,0 +1,531 @@
+use futures::{Stream, TryStreamExt};
+use reqwest::header::LINK;
+use reqwest::{Request, Response, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::error::{Error, Result};
+use crate::{RequestBuilder, StatusError};
+
+#[cfg(not(target_arch = "wasm32"))]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+
+/// The error of a [`Paginator`] whose request can't be sent again for the next page, because its
+/// body is a stream.
+#[derive(Debug, thiserror::Error)]
+#[error("Paginated request can't be cloned for the next page")]
+pub struct PageRequestNotCloneable;
+
+/// The error of a [`PageExtractor`] given a response which isn't a page it understands.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid page: {0}")]
+pub struct InvalidPage(pub String);
+
+/// A page of results, as extracted by a [`PageExtractor`].
+#[derive(Debug)]
+pub struct Page<T> {
+    /// The items of the page.
+    pub items: Vec<T>,
+    /// The request of the next page, `None` on the last page.
+    pub next: Option<RequestBuilder>,
+}
+
+/// Extracts the items of the pages of a paginated API, and the requests of the next pages, for
+/// a [`Paginator`].
+///
+/// [`LinkHeader`], [`BodyCursor`] and [`OffsetLimit`] handle the common pagination schemes.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait PageExtractor<T>: Send + Sync + 'static {
+    /// Extracts the page of `res`, the successful response to `request`, which can be modified
+    /// into the request of the next page.
+    async fn extract(&self, request: RequestBuilder, res: Response) -> Result<Page<T>>;
+}
+
+/// A stream of the items of a paginated API, fetching pages one at a time, as the items of the
+/// previous one are consumed.
+///
+/// The first page is requested with the request given, then the requests of the following pages
+/// are built by a [`PageExtractor`] from the request and response of the previous page. Requests
+/// go through the middleware of their client. Responses with a status which isn't a success fail
+/// with a [`StatusError`].
+///
+/// The stream ends after the last page, the page limit set with
+/// [`with_max_pages`](Self::with_max_pages), or the first error. Use [`pages`](Self::pages) for a
+/// stream of whole pages instead.
+///
+/// # Optional
+///
+/// This requires the optional `json` and `stream` features enabled.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use reqwest_middleware::{ClientWithMiddleware, LinkHeader, Paginator};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientWithMiddleware::from(reqwest::Client::new());
+/// let mut repos = Paginator::<serde_json::Value>::new(
+///     client.get("https://api.github.com/orgs/rust-lang/repos?per_page=100"),
+///     LinkHeader::new(),
+/// )
+/// .with_max_pages(10);
+/// while let Some(repo) = repos.next().await {
+///     println!("{}", repo?["full_name"]);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Paginator<T> {
+    request: Option<RequestBuilder>,
+    extractor: Arc<dyn PageExtractor<T>>,
+    max_pages: Option<usize>,
+    items: Option<BoxStream<'static, Result<T>>>,
+}
+
+impl<T: Send + 'static> Paginator<T> {
+    /// Construct a `Paginator` requesting the first page with `request`, and the following pages
+    /// as built by `extractor`.
+    pub fn new<E: PageExtractor<T>>(request: RequestBuilder, extractor: E) -> Self {
+        Self {
+            request: Some(request),
+            extractor: Arc::new(extractor),
+            max_pages: None,
+            items: None,
+        }
+    }
+
+    /// Stop after `max_pages` pages.
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Returns a stream of the items of each page, rather than of the items themselves.
+    pub fn pages(self) -> impl Stream<Item = Result<Vec<T>>> {
+        pages(self.request, self.extractor, self.max_pages)
+    }
+}
+
+impl<T> fmt::Debug for Paginator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Paginator")
+            .field("max_pages", &self.max_pages)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Send + 'static> Stream for Paginator<T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.items.is_none() {
+            let pages = pages(this.request.take(), this.extractor.clone(), this.max_pages);
+            let items = pages
+                .map_ok(|items| futures::stream::iter(items.into_iter().map(Ok)))
+                .try_flatten();
+            this.items = Some(Box::pin(items));
+        }
+        match &mut this.items {
+            Some(items) => items.as_mut().poll_next(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+fn pages<T: Send + 'static>(
+    request: Option<RequestBuilder>,
+    extractor: Arc<dyn PageExtractor<T>>,
+    max_pages: Option<usize>,
+) -> BoxStream<'static, Result<Vec<T>>> {
+    Box::pin(futures::stream::try_unfold(
+        (request, 0),
+        move |(request, fetched)| {
+            let extractor = extractor.clone();
+            async move {
+                let request = match request {
+                    Some(request) if max_pages.is_none_or(|max| fetched < max) => request,
+                    _ => return Ok(None),
+                };
+                let template = request
+                    .try_clone()
+                    .ok_or_else(|| Error::middleware(PageRequestNotCloneable))?;
+                let res = request.send().await?;
+                if !res.status().is_success() {
+                    let err =
+                        StatusError::from_response(res, StatusError::DEFAULT_MAX_BODY_SIZE).await;
+                    return Err(Error::middleware(err));
+                }
+                let page = extractor.extract(template, res).await?;
+                Ok(Some((page.items, (page.next, fetched + 1))))
+            }
+        },
+    ))
+}
+
+/// Extracts the items of pages with JSON bodies, following the `next` links of their `Link`
+/// headers, as specified by [RFC 8288](https://www.rfc-editor.org/rfc/rfc8288), e.g. GitHub's.
+///
+/// The items are the JSON array of the body, or at the pointer set with
+/// [`with_items_pointer`](Self::with_items_pointer).
+#[derive(Clone, Debug, Default)]
+pub struct LinkHeader {
+    items_pointer: String,
+}
+
+impl LinkHeader {
+    /// Construct a `LinkHeader` extractor of pages whose bodies are arrays of items.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the items from the array at the JSON pointer `pointer`, e.g. `/items`.
+    pub fn with_items_pointer(mut self, pointer: impl Into<String>) -> Self {
+        self.items_pointer = pointer.into();
+        self
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<T: DeserializeOwned + Send + 'static> PageExtractor<T> for LinkHeader {
+    async fn extract(&self, request: RequestBuilder, res: Response) -> Result<Page<T>> {
+        let next = next_link(&res);
+        let body: Value = res.json().await?;
+        let items = items_at(&body, &self.items_pointer)?;
+        let next = match next {
+            Some(url) => Some(map_request(request, |req| *req.url_mut() = url)?),
+            None => None,
+        };
+        Ok(Page { items, next })
+    }
+}
+
+/// Extracts the items of pages with JSON bodies holding the cursor of the next page, passed back
+/// in a query parameter, e.g. Slack's `response_metadata.next_cursor`.
+///
+/// Pagination ends when the cursor is missing, `null` or empty. The items are the JSON array of
+/// the body, or at the pointer set with [`with_items_pointer`](Self::with_items_pointer).
+#[derive(Clone, Debug)]
+pub struct BodyCursor {
+    cursor_pointer: String,
+    param: String,
+    items_pointer: String,
+}
+
+impl BodyCursor {
+    /// Construct a `BodyCursor` extractor taking the cursor at the JSON pointer `cursor_pointer`,
+    /// e.g. `/response_metadata/next_cursor`, and passing it in the query parameter `param`.
+    pub fn new(cursor_pointer: impl Into<String>, param: impl Into<String>) -> Self {
+        Self {
+            cursor_pointer: cursor_pointer.into(),
+            param: param.into(),
+            items_pointer: String::new(),
+        }
+    }
+
+    /// Take the items from the array at the JSON pointer `pointer`, e.g. `/members`.
+    pub fn with_items_pointer(mut self, pointer: impl Into<String>) -> Self {
+        self.items_pointer = pointer.into();
+        self
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<T: DeserializeOwned + Send + 'static> PageExtractor<T> for BodyCursor {
+    async fn extract(&self, request: RequestBuilder, res: Response) -> Result<Page<T>> {
+        let body: Value = res.json().await?;
+        let items = items_at(&body, &self.items_pointer)?;
+        let cursor = match body.pointer(&self.cursor_pointer) {
+            Some(Value::String(cursor)) if !cursor.is_empty() => Some(cursor.clone()),
+            Some(Value::Number(cursor)) => Some(cursor.to_string()),
+            _ => None,
+        };
+        let next = match cursor {
+            Some(cursor) => Some(map_request(request, |req| {
+                set_query_param(req.url_mut(), &self.param, &cursor)
+            })?),
+            None => None,
+        };
+        Ok(Page { items, next })
+    }
+}
+
+/// Extracts the items of pages with JSON bodies, requesting the next page with an offset query
+/// parameter moved past the items received.
+///
+/// Pagination ends on a page with fewer items than the limit, which the first request should
+/// set, e.g. `?limit=100`. The items are the JSON array of the body, or at the pointer set with
+/// [`with_items_pointer`](Self::with_items_pointer).
+#[derive(Clone, Debug)]
+pub struct OffsetLimit {
+    offset_param: String,
+    limit_param: String,
+    limit: usize,
+    items_pointer: String,
+}
+
+impl OffsetLimit {
+    /// Construct an `OffsetLimit` extractor of pages of `limit` items, passing the offset and the
+    /// limit in the query parameters `offset_param` and `limit_param`.
+    pub fn new(
+        offset_param: impl Into<String>,
+        limit_param: impl Into<String>,
+        limit: usize,
+    ) -> Self {
+        Self {
+            offset_param: offset_param.into(),
+            limit_param: limit_param.into(),
+            limit,
+            items_pointer: String::new(),
+        }
+    }
+
+    /// Take the items from the array at the JSON pointer `pointer`, e.g. `/results`.
+    pub fn with_items_pointer(mut self, pointer: impl Into<String>) -> Self {
+        self.items_pointer = pointer.into();
+        self
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<T: DeserializeOwned + Send + 'static> PageExtractor<T> for OffsetLimit {
+    async fn extract(&self, request: RequestBuilder, res: Response) -> Result<Page<T>> {
+        let offset = res
+            .url()
+            .query_pairs()
+            .find(|(name, _)| *name == *self.offset_param)
+            .and_then(|(_, offset)| offset.parse::<usize>().ok())
+            .unwrap_or(0);
+        let body: Value = res.json().await?;
+        let items: Vec<T> = items_at(&body, &self.items_pointer)?;
+        let next = if items.is_empty() || items.len() < self.limit {
+            None
+        } else {
+            let offset = (offset + items.len()).to_string();
+            let limit = self.limit.to_string();
+            Some(map_request(request, |req| {
+                set_query_param(req.url_mut(), &self.offset_param, &offset);
+                set_query_param(req.url_mut(), &self.limit_param, &limit);
+            })?)
+        };
+        Ok(Page { items, next })
+    }
+}
+
+/// Deserializes the array at the JSON pointer `pointer` of `body`.
+fn items_at<T: DeserializeOwned>(body: &Value, pointer: &str) -> Result<Vec<T>> {
+    let items = body
+        .pointer(pointer)
+        .ok_or_else(|| Error::middleware(InvalidPage(format!("no items at {:?}", pointer))))?;
+    Vec::<T>::deserialize(items).map_err(Error::middleware)
+}
+
+/// Applies `f` to the request of `builder`, keeping its client and extensions.
+fn map_request(
+    mut builder: RequestBuilder,
+    f: impl FnOnce(&mut Request),
+) -> Result<RequestBuilder> {
+    let extensions = std::mem::take(builder.extensions());
+    let (client, req) = builder.build_split();
+    let mut req = req?;
+    f(&mut req);
+    let mut builder = RequestBuilder::from_parts(client, req);
+    *builder.extensions() = extensions;
+    Ok(builder)
+}
+
+/// Sets the query parameter `name` of `url` to `value`, replacing its previous values.
+fn set_query_param(url: &mut Url, name: &str, value: &str) {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != name)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(name, value);
+}
+
+/// The target of the `next` link of the `Link` headers of `res`, resolved against its URL.
+fn next_link(res: &Response) -> Option<Url> {
+    for value in res.headers().get_all(LINK) {
+        let mut rest = match value.to_str() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        while let Some(start) = rest.find('<') {
+            let end = match rest[start..].find('>') {
+                Some(end) => start + end,
+                None => break,
+            };
+            let target = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+            let params_end = rest.find('<').unwrap_or(rest.len());
+            let params = &rest[..params_end];
+            rest = &rest[params_end..];
+            let is_next = params
+                .split(';')
+                .filter_map(|param| param.split_once('='))
+                .any(|(name, value)| {
+                    name.trim().eq_ignore_ascii_case("rel")
+                        && value
+                            .trim()
+                            .trim_end_matches(',')
+                            .trim_end()
+                            .trim_matches('"')
+                            .split_whitespace()
+                            .any(|rel| rel.eq_ignore_ascii_case("next"))
+                });
+            if is_next {
+                return res.url().join(target).ok();
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::StreamExt;
+    use reqwest::ResponseBuilderExt;
+    use serde_json::json;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::ClientWithMiddleware;
+
+    fn response(links: &[&'static str]) -> Response {
+        let mut builder =
+            http::Response::builder().url("https://api.example.com/items?page=1".parse().unwrap());
+        for link in links {
+            builder = builder.header(LINK, *link);
+        }
+        builder.body("").unwrap().into()
+    }
+
+    #[test]
+    fn next_links_are_parsed() {
+        let next = |links| next_link(&response(links)).map(String::from);
+        assert_eq!(
+            next(&[
+                r#"<https://api.example.com/items?page=2>; rel="next", </items?page=5>; rel="last""#
+            ]),
+            Some("https://api.example.com/items?page=2".to_owned())
+        );
+        assert_eq!(
+            next(&[
+                r#"</items?page=1>; rel="prev""#,
+                "</items?page=3>; rel=\"last next\""
+            ]),
+            Some("https://api.example.com/items?page=3".to_owned())
+        );
+        assert_eq!(
+            next(&["<?page=2>;rel=NEXT"]),
+            Some("https://api.example.com/items?page=2".to_owned())
+        );
+        assert_eq!(
+            next(&[r#"<https://api.example.com/items?page=5>; rel="last""#]),
+            None
+        );
+        assert_eq!(next(&[]), None);
+    }
+
+    async fn server() -> MockServer {
+        let server = MockServer::start().await;
+        for page in 1..=3 {
+            let mut template =
+                ResponseTemplate::new(200).set_body_json(json!([page * 10, page * 10 + 1]));
+            if page < 3 {
+                template = template.insert_header(
+                    "link",
+                    format!("<{}/items?page={}>; rel=\"next\"", server.uri(), page + 1).as_str(),
+                );
+            }
+            Mock::given(query_param("page", page.to_string()))
+                .respond_with(template)
+                .mount(&server)
+                .await;
+        }
+        server
+    }
+
+    fn paginator(server: &MockServer) -> Paginator<u64> {
+        let client = ClientWithMiddleware::from(reqwest::Client::new());
+        Paginator::new(
+            client.get(format!("{}/items?page=1", server.uri())),
+            LinkHeader::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn pagination_ends_on_the_last_page() {
+        let server = server().await;
+        let items: Vec<_> = paginator(&server).collect().await;
+        let items: Vec<_> = items.into_iter().map(Result::unwrap).collect();
+        assert_eq!(items, [10, 11, 20, 21, 30, 31]);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn pagination_stops_at_max_pages() {
+        let server = server().await;
+        let pages: Vec<_> = paginator(&server).with_max_pages(2).pages().collect().await;
+        let pages: Vec<_> = pages.into_iter().map(Result::unwrap).collect();
+        assert_eq!(pages, [vec![10, 11], vec![20, 21]]);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn offset_pagination_ends_on_a_short_page() {
+        let server = MockServer::start().await;
+        for (offset, items) in [
+            ("0", json!({"results": [1, 2]})),
+            ("2", json!({"results": [3]})),
+        ] {
+            Mock::given(query_param("offset", offset))
+                .respond_with(ResponseTemplate::new(200).set_body_json(items))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = ClientWithMiddleware::from(reqwest::Client::new());
+        let items: Vec<_> = Paginator::<u64>::new(
+            client.get(format!("{}/items?offset=0&limit=2", server.uri())),
+            OffsetLimit::new("offset", "limit", 2).with_items_pointer("/results"),
+        )
+        .collect()
+        .await;
+        let items: Vec<_> = items.into_iter().map(Result::unwrap).collect();
+        assert_eq!(items, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn error_statuses_end_the_stream() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let items: Vec<_> = paginator(&server).collect().await;
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].as_ref().unwrap_err().status(),
+            Some(reqwest::StatusCode::SERVICE_UNAVAILABLE)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use reqwest::StatusCode;
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+
+/// The media type of problem details.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Problem details, the machine-readable format of HTTP API errors defined by
+/// [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457), which obsoletes RFC 7807.
+///
+/// The [`StatusError`] of a response whose content type is `application/problem+json` carries
+/// its parsed body, see [`StatusError::problem`].
+///
+/// # Optional
+///
+/// This requires the optional `json` feature enabled.
+///
+/// [`StatusError`]: crate::StatusError
+/// [`StatusError::problem`]: crate::StatusError::problem
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    /// A URI reference identifying the problem type, `about:blank` when absent.
+    pub problem_type: String,
+    /// A short, human-readable summary of the problem type.
+    pub title: Option<String>,
+    /// The status code generated by the origin server for this occurrence of the problem.
+    pub status: Option<StatusCode>,
+    /// A human-readable explanation specific to this occurrence of the problem.
+    pub detail: Option<String>,
+    /// A URI reference identifying this occurrence of the problem.
+    pub instance: Option<String>,
+    /// The extension members of the problem type.
+    pub extensions: Map<String, Value>,
+}
+
+impl Problem {
+    /// Parses problem details from a JSON `body`, returning `None` if it isn't a JSON object.
+    ///
+    /// Members with the wrong type are ignored, as the RFC requires.
+    pub fn from_slice(body: &[u8]) -> Option<Self> {
+        match serde_json::from_slice(body).ok()? {
+            Value::Object(members) => Some(Self::from_members(members)),
+            _ => None,
+        }
+    }
+
+    /// Whether `content_type` is the media type of problem details.
+    pub fn is_problem_content_type(content_type: &str) -> bool {
+        content_type
+            .split(';')
+            .next()
+            .is_some_and(|essence| essence.trim().eq_ignore_ascii_case(PROBLEM_JSON))
+    }
+
+    fn from_members(mut members: Map<String, Value>) -> Self {
+        let mut string = |name: &str| match members.remove(name) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        };
+        let problem_type = string("type").unwrap_or_else(|| "about:blank".to_owned());
+        let title = string("title");
+        let detail = string("detail");
+        let instance = string("instance");
+        let status = match members.remove("status") {
+            Some(Value::Number(status)) => status
+                .as_u64()
+                .and_then(|status| u16::try_from(status).ok())
+                .and_then(|status| StatusCode::from_u16(status).ok()),
+            _ => None,
+        };
+        Self {
+            problem_type,
+            title,
+            status,
+            detail,
+            instance,
+            extensions: members,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems_are_parsed() {
+        let problem = Problem::from_slice(
+            br#"{
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "You do not have enough credit.",
+                "status": 403,
+                "detail": "Your current balance is 30, but that costs 50.",
+                "instance": "/account/12345/msgs/abc",
+                "balance": 30,
+                "accounts": ["/account/12345", "/account/67890"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            problem.problem_type,
+            "https://example.com/probs/out-of-credit"
+        );
+        assert_eq!(
+            problem.title.as_deref(),
+            Some("You do not have enough credit.")
+        );
+        assert_eq!(problem.status, Some(StatusCode::FORBIDDEN));
+        assert_eq!(
+            problem.detail.as_deref(),
+            Some("Your current balance is 30, but that costs 50.")
+        );
+        assert_eq!(problem.instance.as_deref(), Some("/account/12345/msgs/abc"));
+        assert_eq!(problem.extensions.len(), 2);
+        assert_eq!(problem.extensions["balance"], 30);
+    }
+
+    #[test]
+    fn invalid_members_are_ignored() {
+        let problem = Problem::from_slice(br#"{"title": 1, "status": "403"}"#).unwrap();
+        assert_eq!(problem.problem_type, "about:blank");
+        assert_eq!(problem.title, None);
+        assert_eq!(problem.status, None);
+        assert!(problem.extensions.is_empty());
+
+        assert_eq!(Problem::from_slice(b"[]"), None);
+        assert_eq!(Problem::from_slice(b"not json"), None);
+    }
+
+    #[test]
+    fn problem_content_types_are_recognised() {
+        assert!(Problem::is_problem_content_type("application/problem+json"));
+        assert!(Problem::is_problem_content_type(
+            "Application/Problem+JSON; charset=utf-8"
+        ));
+        assert!(!Problem::is_problem_content_type("application/json"));
+        assert!(!Problem::is_problem_content_type("text/html"));
+    }
+}
@@ -0,0 +1,24 @@
+use http::Extensions;
+
+/// The number of times a request was sent before the current attempt, kept in its
+/// [`Extensions`].
+///
+/// Middleware sending requests again, such as retry middleware, increment it before each new
+/// attempt, so that the middleware after them can tell attempts apart, for instance to report
+/// the `http.request.resend_count` attribute of OpenTelemetry. It is absent for the first
+/// attempt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResendCount(pub u32);
+
+impl ResendCount {
+    /// Returns the resend count of the request with `extensions`, `0` for its first attempt.
+    pub fn get(extensions: &Extensions) -> u32 {
+        extensions.get::<Self>().map_or(0, |count| count.0)
+    }
+
+    /// Increments the resend count of the request with `extensions`, before sending it again.
+    pub fn increment(extensions: &mut Extensions) {
+        let count = Self::get(extensions) + 1;
+        extensions.insert(Self(count));
+    }
+}
@@ -0,0 +1,248 @@
+use reqwest::Response;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::error::{Error, Result};
+use crate::{Clock, RequestBuilder, SystemClock};
+
+/// The error of a [`ScheduledRequest`] cancelled before its response was received.
+#[derive(Debug, thiserror::Error)]
+#[error("Scheduled request was cancelled")]
+pub struct RequestCancelled;
+
+type Pending = Arc<Mutex<HashMap<u64, AbortHandle>>>;
+
+/// Sends requests at a later time, e.g. to retry a webhook in 5 minutes, from tasks it owns, so
+/// that they are sent even if nothing awaits them.
+///
+/// Scheduling a request returns a [`ScheduledRequest`], a future of its response which can
+/// cancel it. Dropping it doesn't cancel the request. The scheduler, and its clones, can cancel
+/// all the requests scheduled and not completed yet.
+///
+/// This requires a tokio runtime, and isn't available on `wasm32`.
+///
+/// ```no_run
+/// use reqwest_middleware::{ClientWithMiddleware, RequestScheduler};
+/// use std::time::Duration;
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientWithMiddleware::from(reqwest::Client::new());
+/// let scheduler = RequestScheduler::new();
+/// let retry = scheduler.send_after(
+///     client.post("https://hooks.example.com/deliveries").body("{}"),
+///     Duration::from_secs(300),
+/// );
+/// // Cancel the retry if the delivery succeeded meanwhile.
+/// retry.cancel();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RequestScheduler {
+    clock: Arc<dyn Clock>,
+    next_id: Arc<AtomicU64>,
+    pending: Pending,
+}
+
+impl RequestScheduler {
+    /// Construct a `RequestScheduler` waiting with the [`SystemClock`].
+    pub fn new() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+            next_id: Arc::new(AtomicU64::new(0)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wait for scheduled times with `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sends `request` after `delay`.
+    pub fn send_after(&self, request: RequestBuilder, delay: Duration) -> ScheduledRequest {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let clock = self.clock.clone();
+        let guard = PendingGuard {
+            id,
+            pending: self.pending.clone(),
+        };
+        // Hold the lock until the task is registered, so that it can't unregister first.
+        let mut pending = self.pending.lock().expect("scheduler lock poisoned");
+        let handle = tokio::spawn(async move {
+            let _guard = guard;
+            clock.sleep(delay).await;
+            request.send().await
+        });
+        pending.insert(id, handle.abort_handle());
+        ScheduledRequest { id, handle }
+    }
+
+    /// Sends `request` at `at`, or right away if it has passed.
+    pub fn send_at(&self, request: RequestBuilder, at: Instant) -> ScheduledRequest {
+        let delay = at.saturating_duration_since(self.clock.now());
+        self.send_after(request, delay)
+    }
+
+    /// Number of requests scheduled and not completed yet.
+    pub fn pending(&self) -> usize {
+        self.pending.lock().expect("scheduler lock poisoned").len()
+    }
+
+    /// Cancels all the requests scheduled and not completed yet.
+    pub fn cancel_all(&self) {
+        for (_, handle) in self
+            .pending
+            .lock()
+            .expect("scheduler lock poisoned")
+            .drain()
+        {
+            handle.abort();
+        }
+    }
+}
+
+impl Default for RequestScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RequestScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestScheduler")
+            .field("pending", &self.pending())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Unregisters a scheduled request once its task completes or is cancelled.
+struct PendingGuard {
+    id: u64,
+    pending: Pending,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.pending
+            .lock()
+            .expect("scheduler lock poisoned")
+            .remove(&self.id);
+    }
+}
+
+/// A request scheduled by a [`RequestScheduler`], and the future of its response.
+///
+/// The future fails with [`RequestCancelled`] if the request was cancelled first.
+#[derive(Debug)]
+pub struct ScheduledRequest {
+    id: u64,
+    handle: JoinHandle<Result<Response>>,
+}
+
+impl ScheduledRequest {
+    /// The identifier of the request, unique for its scheduler.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Cancels the request, before it is sent or while it is in flight.
+    pub fn cancel(&self) {
+        self.handle.abort();
+    }
+
+    /// Whether the request completed, or was cancelled.
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+impl Future for ScheduledRequest {
+    type Output = Result<Response>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(err)) if err.is_cancelled() => {
+                Poll::Ready(Err(Error::middleware(RequestCancelled)))
+            }
+            Poll::Ready(Err(err)) => std::panic::resume_unwind(err.into_panic()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::{ClientWithMiddleware, MockClock};
+
+    async fn server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn request(server: &MockServer) -> RequestBuilder {
+        ClientWithMiddleware::from(reqwest::Client::new()).post(server.uri())
+    }
+
+    fn assert_cancelled(result: Result<Response>) {
+        match result {
+            Err(Error::Middleware(err)) => assert!(err.is::<RequestCancelled>()),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_are_sent_after_their_delay() {
+        let server = server().await;
+        let clock = Arc::new(MockClock::new());
+        let start = clock.now();
+        let scheduler = RequestScheduler::new().with_clock(clock.clone());
+
+        let scheduled = scheduler.send_after(request(&server), Duration::from_secs(300));
+        assert_eq!(scheduler.pending(), 1);
+        assert_eq!(scheduled.await.unwrap().status(), 202);
+        assert_eq!(clock.now() - start, Duration::from_secs(300));
+        assert_eq!(scheduler.pending(), 0);
+
+        let scheduled = scheduler.send_at(request(&server), start);
+        scheduled.await.unwrap();
+        assert_eq!(clock.now() - start, Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn requests_can_be_cancelled() {
+        let server = server().await;
+        let scheduler = RequestScheduler::new();
+
+        let scheduled = scheduler.send_after(request(&server), Duration::from_secs(300));
+        scheduled.cancel();
+        assert_cancelled(scheduled.await);
+
+        let first = scheduler.send_after(request(&server), Duration::from_secs(300));
+        let second = scheduler.send_after(request(&server), Duration::from_secs(600));
+        assert_ne!(first.id(), second.id());
+        assert_eq!(scheduler.pending(), 2);
+        scheduler.clone().cancel_all();
+        assert_eq!(scheduler.pending(), 0);
+        assert_cancelled(first.await);
+        assert_cancelled(second.await);
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,484 @@
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::header::{HeaderName, HeaderValue, ACCEPT, CACHE_CONTROL};
+use reqwest::StatusCode;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::{Clock, RequestBuilder, StatusError, SystemClock};
+
+#[cfg(not(target_arch = "wasm32"))]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+
+/// The header sent on reconnection with the identifier of the last event received.
+const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+/// An event received by an [`EventSource`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    /// The type of the event, `message` unless the server named it.
+    pub event: String,
+    /// The data of the event, its `data` lines joined with newlines.
+    pub data: String,
+    /// The identifier of the last event sent by the server, if any.
+    pub id: Option<String>,
+}
+
+impl Event {
+    /// Deserializes the data of the event as JSON.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(&self.data)
+    }
+}
+
+/// The error of an [`EventSource`] whose request can't be sent again, because its body is a
+/// stream.
+#[derive(Debug, thiserror::Error)]
+#[error("Event source request can't be cloned to reconnect")]
+pub struct EventSourceNotCloneable;
+
+/// The error of an [`EventSource`] whose response isn't an event stream.
+#[derive(Debug, thiserror::Error)]
+#[error("Event source response has content type {0:?}, expected text/event-stream")]
+pub struct InvalidEventStream(pub Option<String>);
+
+/// The error of an [`EventSource`] receiving an event, or a line, longer than its maximum event
+/// size.
+#[derive(Debug, thiserror::Error)]
+#[error("Server-sent event exceeds the maximum size of {0} bytes")]
+pub struct EventTooLarge(pub usize);
+
+/// A client for server-sent events, parsing `text/event-stream` responses into a stream of
+/// [`Event`]s, in the manner of the `EventSource` web API.
+///
+/// When the connection is lost, or the server ends the response, the request is sent again after
+/// the reconnection delay, which servers can change with `retry` fields, with the `Last-Event-ID`
+/// header set to the identifier of the last event received. The request goes through the
+/// middleware of the client on each connection, so that authentication or tracing applies to
+/// reconnections too.
+///
+/// Failed connections are returned as errors by the stream, which keeps reconnecting unless the
+/// response has a status which isn't a success, surfaced as a [`StatusError`], or isn't an
+/// event stream. A `204 No Content` response ends the stream, as it is how servers ask clients
+/// to stop reconnecting.
+///
+/// Events longer than [`with_max_event_size`](Self::with_max_event_size) end the stream with
+/// [`EventTooLarge`], to bound the memory used by servers which never end their events.
+///
+/// # Optional
+///
+/// This requires the optional `stream` feature enabled.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use reqwest_middleware::{ClientWithMiddleware, EventSource};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientWithMiddleware::from(reqwest::Client::new());
+/// let mut events = EventSource::new(client.get("https://api.example.com/events"));
+/// while let Some(event) = events.next().await {
+///     let event = event?;
+///     println!("{}: {}", event.event, event.data);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct EventSource {
+    state: Option<Box<State>>,
+    stream: Option<BoxStream<'static, Result<Event>>>,
+}
+
+impl EventSource {
+    /// The reconnection delay until the server sets one.
+    pub const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+    /// The maximum event size by default, 1 MiB.
+    pub const DEFAULT_MAX_EVENT_SIZE: usize = 1024 * 1024;
+
+    /// Construct an `EventSource` sending the request of `builder`, which must be cloneable to
+    /// reconnect, i.e. not have a streaming body.
+    pub fn new(builder: RequestBuilder) -> Self {
+        Self {
+            state: Some(Box::new(State {
+                builder,
+                last_event_id: None,
+                retry: Self::DEFAULT_RETRY,
+                max_event_size: Self::DEFAULT_MAX_EVENT_SIZE,
+                clock: Arc::new(SystemClock),
+                body: None,
+                parser: Parser::default(),
+                reconnect: false,
+                done: false,
+            })),
+            stream: None,
+        }
+    }
+
+    /// Resume the stream after the event with identifier `last_event_id`.
+    pub fn with_last_event_id(mut self, last_event_id: impl Into<String>) -> Self {
+        if let Some(state) = &mut self.state {
+            state.last_event_id = Some(last_event_id.into());
+        }
+        self
+    }
+
+    /// Wait `retry` before reconnecting, until the server sets another delay.
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        if let Some(state) = &mut self.state {
+            state.retry = retry;
+        }
+        self
+    }
+
+    /// End the stream on events, or lines, longer than `max_event_size` bytes.
+    pub fn with_max_event_size(mut self, max_event_size: usize) -> Self {
+        if let Some(state) = &mut self.state {
+            state.max_event_size = max_event_size;
+        }
+        self
+    }
+
+    /// Wait for reconnections with `clock`, [`SystemClock`] by default.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        if let Some(state) = &mut self.state {
+            state.clock = clock;
+        }
+        self
+    }
+}
+
+impl fmt::Debug for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSource").finish_non_exhaustive()
+    }
+}
+
+impl Stream for EventSource {
+    type Item = Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(state) = this.state.take() {
+            let stream = futures::stream::unfold(state, |mut state| async move {
+                let event = state.next_event().await?;
+                Some((event, state))
+            });
+            this.stream = Some(Box::pin(stream));
+        }
+        match &mut this.stream {
+            Some(stream) => stream.as_mut().poll_next(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+struct State {
+    builder: RequestBuilder,
+    last_event_id: Option<String>,
+    retry: Duration,
+    max_event_size: usize,
+    clock: Arc<dyn Clock>,
+    body: Option<BoxStream<'static, reqwest::Result<Bytes>>>,
+    parser: Parser,
+    reconnect: bool,
+    done: bool,
+}
+
+impl State {
+    async fn next_event(&mut self) -> Option<Result<Event>> {
+        loop {
+            if self.done {
+                return None;
+            }
+            if let Some(event) = self.parser.next_event() {
+                if let Some(retry) = self.parser.retry.take() {
+                    self.retry = retry;
+                }
+                self.last_event_id = self.parser.last_event_id.clone();
+                match event {
+                    Some(event) => return Some(Ok(event)),
+                    None => continue,
+                }
+            }
+            if self.parser.buffered_size() > self.max_event_size {
+                self.done = true;
+                return Some(Err(Error::middleware(EventTooLarge(self.max_event_size))));
+            }
+            let body = match &mut self.body {
+                Some(body) => body,
+                None => match self.connect().await {
+                    Ok(()) => continue,
+                    Err(err) => return Some(Err(err)),
+                },
+            };
+            match body.next().await {
+                Some(Ok(chunk)) => self.parser.feed(&chunk),
+                Some(Err(err)) => {
+                    self.disconnect();
+                    return Some(Err(err.into()));
+                }
+                None => self.disconnect(),
+            }
+        }
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        if self.reconnect {
+            self.clock.sleep(self.retry).await;
+        }
+        self.reconnect = true;
+
+        let mut builder = match self.builder.try_clone() {
+            Some(builder) => builder,
+            None => {
+                self.done = true;
+                return Err(Error::middleware(EventSourceNotCloneable));
+            }
+        };
+        builder = builder
+            .header(ACCEPT, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache");
+        if let Some(id) = &self.last_event_id {
+            if let Ok(id) = HeaderValue::from_str(id) {
+                builder = builder.header(LAST_EVENT_ID, id);
+            }
+        }
+
+        let res = builder
+            .send()
+            .await
+            .inspect_err(|err| self.done = err.is_builder())?;
+        if res.status() == StatusCode::NO_CONTENT {
+            self.done = true;
+            return Ok(());
+        }
+        if !res.status().is_success() {
+            self.done = true;
+            let err = StatusError::from_response(res, StatusError::DEFAULT_MAX_BODY_SIZE).await;
+            return Err(Error::middleware(err));
+        }
+        let content_type = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let is_event_stream = content_type
+            .as_deref()
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|essence| essence.trim().eq_ignore_ascii_case("text/event-stream"));
+        if !is_event_stream {
+            self.done = true;
+            return Err(Error::middleware(InvalidEventStream(content_type)));
+        }
+        self.parser = Parser::with_last_event_id(self.last_event_id.clone());
+        self.body = Some(Box::pin(res.bytes_stream()));
+        Ok(())
+    }
+
+    fn disconnect(&mut self) {
+        self.body = None;
+    }
+}
+
+/// Parser of the `text/event-stream` format, as specified by the HTML standard.
+#[derive(Debug, Default)]
+struct Parser {
+    buffer: Vec<u8>,
+    started: bool,
+    event: Option<String>,
+    data: String,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Parser {
+    fn with_last_event_id(last_event_id: Option<String>) -> Self {
+        Self {
+            last_event_id,
+            ..Self::default()
+        }
+    }
+
+    /// The size of the incomplete line and event buffered.
+    fn buffered_size(&self) -> usize {
+        self.buffer.len() + self.data.len()
+    }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+        if !self.started && self.buffer.len() >= 3 {
+            self.started = true;
+            if self.buffer.starts_with(b"\xEF\xBB\xBF") {
+                self.buffer.drain(..3);
+            }
+        }
+    }
+
+    /// Processes the next line, returning `Some(Some(event))` when it dispatches an event,
+    /// `Some(None)` when it doesn't, and `None` when no complete line is buffered.
+    fn next_event(&mut self) -> Option<Option<Event>> {
+        let end = self.buffer.iter().position(|&b| b == b'\n' || b == b'\r')?;
+        // A carriage return at the end of the buffer may be followed by a line feed.
+        if self.buffer[end] == b'\r' && end + 1 == self.buffer.len() {
+            return None;
+        }
+        let skip = if self.buffer[end] == b'\r' && self.buffer[end + 1] == b'\n' {
+            2
+        } else {
+            1
+        };
+        let line: Vec<u8> = self.buffer.drain(..end + skip).take(end).collect();
+        let line = String::from_utf8_lossy(&line);
+        Some(self.process_line(&line))
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<Event> {
+        if line.is_empty() {
+            let event = self.event.take();
+            if self.data.is_empty() {
+                return None;
+            }
+            let mut data = std::mem::take(&mut self.data);
+            if data.ends_with('\n') {
+                data.pop();
+            }
+            return Some(Event {
+                event: event.unwrap_or_else(|| "message".to_owned()),
+                data,
+                id: self.last_event_id.clone(),
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_owned()),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_owned()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::ClientWithMiddleware;
+
+    fn parse(parser: &mut Parser, chunk: &str) -> Vec<Event> {
+        parser.feed(chunk.as_bytes());
+        std::iter::from_fn(|| parser.next_event())
+            .flatten()
+            .collect()
+    }
+
+    fn message(data: &str, id: Option<&str>) -> Event {
+        Event {
+            event: "message".to_owned(),
+            data: data.to_owned(),
+            id: id.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn data_lines_are_joined() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parse(&mut parser, "data: first\ndata:second\ndata\n\n"),
+            [message("first\nsecond\n", None)]
+        );
+    }
+
+    #[test]
+    fn fields_are_parsed() {
+        let mut parser = Parser::default();
+        let events = parse(
+            &mut parser,
+            "\u{FEFF}: comment\r\nevent: update\r\nid: 1\r\nretry: 500\r\ndata: {}\r\n\r\n",
+        );
+        assert_eq!(
+            events,
+            [Event {
+                event: "update".to_owned(),
+                data: "{}".to_owned(),
+                id: Some("1".to_owned()),
+            }]
+        );
+        assert_eq!(parser.retry, Some(Duration::from_millis(500)));
+
+        // The identifier is kept for the next events, and invalid retries are ignored.
+        parser.retry = None;
+        assert_eq!(
+            parse(&mut parser, "retry: soon\ndata: next\n\n"),
+            [message("next", Some("1"))]
+        );
+        assert_eq!(parser.retry, None);
+    }
+
+    #[test]
+    fn lines_are_split_across_chunks() {
+        let mut parser = Parser::default();
+        assert!(parse(&mut parser, "data: a\r").is_empty());
+        assert!(parse(&mut parser, "\ndata: b").is_empty());
+        assert_eq!(parse(&mut parser, "c\r\r\n"), [message("a\nbc", None)]);
+    }
+
+    #[test]
+    fn comments_and_empty_events_are_not_dispatched() {
+        let mut parser = Parser::default();
+        assert!(parse(&mut parser, ": keep-alive\n\nevent: ping\n\n").is_empty());
+        assert_eq!(parse(&mut parser, "data: x\n\n"), [message("x", None)]);
+    }
+
+    #[tokio::test]
+    async fn long_events_end_the_stream() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw("data: short\n\ndata: 0123456789", "text/event-stream"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = ClientWithMiddleware::from(reqwest::Client::new());
+        let events: Vec<_> = EventSource::new(client.get(server.uri()))
+            .with_max_event_size(8)
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_ref().unwrap(), &message("short", None));
+        match &events[1] {
+            Err(Error::Middleware(err)) => assert!(err.is::<EventTooLarge>()),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Response, StatusCode, Url};
+
+#[cfg(feature = "json")]
+use crate::Problem;
+
+/// The error of a response whose status isn't a success, with the start of its body kept for
+/// diagnostics.
+///
+/// Unlike [`reqwest::Response::error_for_status`], which drops the response, the body that
+/// servers send to explain their errors isn't lost. It is surfaced as an [`Error::Middleware`],
+/// and [`Error::status`] and [`Error::is_status`] look through it, see
+/// [`RequestBuilder::send_json`] and [`EnsureSuccessMiddleware`].
+///
+/// With the `json` feature, the body of an `application/problem+json` response is parsed as a
+/// [`Problem`], see [`problem`](Self::problem).
+///
+/// [`Error::Middleware`]: crate::Error::Middleware
+/// [`Error::status`]: crate::Error::status
+/// [`Error::is_status`]: crate::Error::is_status
+/// [`RequestBuilder::send_json`]: crate::RequestBuilder::send_json
+/// [`EnsureSuccessMiddleware`]: crate::EnsureSuccessMiddleware
+/// [`Problem`]: crate::Problem
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP status {status} for url ({url})")]
+pub struct StatusError {
+    status: StatusCode,
+    url: Url,
+    content_type: Option<String>,
+    body: Vec<u8>,
+    truncated: bool,
+    #[cfg(feature = "json")]
+    problem: Option<Problem>,
+}
+
+impl StatusError {
+    /// The number of bytes of the body kept by default.
+    pub const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024;
+
+    /// Consumes `res`, keeping up to `max_body_size` bytes of its body. The rest of the body
+    /// isn't read.
+    ///
+    /// A body which can't be read is cut where the error occurred, as the status is the error to
+    /// report.
+    pub async fn from_response(mut res: Response, max_body_size: usize) -> Self {
+        let status = res.status();
+        let url = res.url().clone();
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let mut body = Vec::new();
+        while body.len() <= max_body_size {
+            match res.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        let truncated = body.len() > max_body_size;
+        body.truncate(max_body_size);
+        #[cfg(feature = "json")]
+        let problem = content_type
+            .as_deref()
+            .filter(|content_type| !truncated && Problem::is_problem_content_type(content_type))
+            .and_then(|_| Problem::from_slice(&body));
+        Self {
+            status,
+            url,
+            content_type,
+            body,
+            truncated,
+            #[cfg(feature = "json")]
+            problem,
+        }
+    }
+
+    /// The status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The URL of the response.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The `Content-Type` of the response, if any.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// The start of the body of the response.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// The start of the body of the response as text, with invalid UTF-8 replaced.
+    pub fn text(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    /// Whether the body was longer than what was kept.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The problem details of the response, if its content type is `application/problem+json`
+    /// and its body wasn't truncated.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn problem(&self) -> Option<&Problem> {
+        self.problem.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest::ResponseBuilderExt;
+
+    fn response(content_type: &str, body: &'static str) -> Response {
+        http::Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .url("https://example.com/users".parse().unwrap())
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap()
+            .into()
+    }
+
+    #[tokio::test]
+    async fn body_is_truncated() {
+        let err = StatusError::from_response(response("text/plain", "0123456789"), 4).await;
+        assert_eq!(err.body(), b"0123");
+        assert_eq!(err.text(), "0123");
+        assert!(err.is_truncated());
+
+        let err = StatusError::from_response(response("text/plain", "0123"), 4).await;
+        assert_eq!(err.body(), b"0123");
+        assert!(!err.is_truncated());
+    }
+
+    #[tokio::test]
+    async fn error_is_displayed() {
+        let err = StatusError::from_response(response("text/plain", "oops"), 64).await;
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.content_type(), Some("text/plain"));
+        assert_eq!(
+            err.to_string(),
+            "HTTP status 400 Bad Request for url (https://example.com/users)"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn problem_is_parsed() {
+        let body = r#"{"title":"Invalid name","status":400}"#;
+        let err = StatusError::from_response(response(crate::PROBLEM_JSON, body), 64).await;
+        let problem = err.problem().unwrap();
+        assert_eq!(problem.title.as_deref(), Some("Invalid name"));
+        assert_eq!(problem.status, Some(StatusCode::BAD_REQUEST));
+
+        let err = StatusError::from_response(response(crate::PROBLEM_JSON, body), 8).await;
+        assert!(err.problem().is_none());
+
+        let err = StatusError::from_response(response("application/json", body), 64).await;
+        assert!(err.problem().is_none());
+    }
+}
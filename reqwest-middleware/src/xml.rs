@@ -0,0 +1,99 @@
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+
+use crate::error::{Error, Result};
+
+/// Extension trait deserializing the XML bodies of responses.
+///
+/// # Optional
+///
+/// This requires the optional `xml` feature enabled.
+///
+/// ```no_run
+/// use reqwest_middleware::{ClientWithMiddleware, XmlResponseExt};
+/// use std::collections::HashMap;
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let fields: HashMap<String, String> = ClientWithMiddleware::from(reqwest::Client::new())
+///     .get("https://legacy.example.com/service")
+///     .send()
+///     .await?
+///     .xml()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait XmlResponseExt {
+    /// Deserializes the XML body of the response to `T`.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the body can't be read, or isn't valid XML for `T`.
+    async fn xml<T: DeserializeOwned>(self) -> Result<T>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl XmlResponseExt for Response {
+    async fn xml<T: DeserializeOwned>(self) -> Result<T> {
+        let body = self.text().await?;
+        quick_xml::de::from_str(&body).map_err(Error::middleware)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::{Deserialize, Serialize};
+    use wiremock::matchers::header;
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    use crate::ClientWithMiddleware;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename = "note")]
+    struct Note {
+        to: String,
+        body: String,
+    }
+
+    #[tokio::test]
+    async fn bodies_round_trip() {
+        let server = MockServer::start().await;
+        Mock::given(header("content-type", "application/xml"))
+            .respond_with(|req: &Request| {
+                ResponseTemplate::new(200).set_body_raw(req.body.clone(), "application/xml")
+            })
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let note = Note {
+            to: "Tove".to_owned(),
+            body: "Don't forget me this weekend!".to_owned(),
+        };
+        let echoed: Note = ClientWithMiddleware::from(reqwest::Client::new())
+            .post(server.uri())
+            .xml(&note)
+            .unwrap()
+            .send()
+            .await
+            .unwrap()
+            .xml()
+            .await
+            .unwrap();
+        assert_eq!(echoed, note);
+    }
+
+    #[tokio::test]
+    async fn invalid_bodies_fail() {
+        let res: Response = http::Response::builder()
+            .body("<note><to>Tove</to>")
+            .unwrap()
+            .into();
+        assert!(res.xml::<Note>().await.is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! `BufferBodyMiddleware` makes streaming request bodies replayable.
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::{Bytes, BytesMut};
+use futures::{future, stream, StreamExt};
+use http::Extensions;
+use http_body_util::BodyExt;
+use reqwest::{Body, Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use tokio::io::AsyncWriteExt;
+
+/// `BufferBodyMiddleware` reads streaming request bodies so that the middleware further down the
+/// chain (e.g. [`RetryTransientMiddleware`]) can replay or inspect them.
+///
+/// Bodies up to `max_in_memory` bytes are buffered in memory, which makes the request clonable
+/// just as if it had been built from static bytes.
+///
+/// Larger bodies are either passed through unchanged (the default), or, when
+/// [`spill_to_disk`] is enabled, written to a temporary file. In the latter case the request
+/// still isn't clonable, but a [`ReplayableBody`] is inserted in the extensions which
+/// [`RetryTransientMiddleware`] uses to rebuild the request for each attempt.
+///
+/// Requests that have no body, or whose body is already buffered, are not touched.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_retry::{policies::ExponentialBackoff, BufferBodyMiddleware, RetryTransientMiddleware};
+///
+/// let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     // Must run before the retry middleware so that it sees buffered bodies.
+///     .with(BufferBodyMiddleware::new(1024 * 1024).spill_to_disk())
+///     .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+///     .build();
+/// ```
+///
+/// [`RetryTransientMiddleware`]: crate::RetryTransientMiddleware
+/// [`spill_to_disk`]: Self::spill_to_disk
+pub struct BufferBodyMiddleware {
+    max_in_memory: usize,
+    spill_dir: Option<PathBuf>,
+}
+
+impl BufferBodyMiddleware {
+    /// Construct `BufferBodyMiddleware` which buffers bodies of up to `max_in_memory` bytes.
+    pub fn new(max_in_memory: usize) -> Self {
+        Self {
+            max_in_memory,
+            spill_dir: None,
+        }
+    }
+
+    /// Write bodies larger than the in-memory limit to a file in the system temporary directory.
+    pub fn spill_to_disk(self) -> Self {
+        self.spill_to_dir(std::env::temp_dir())
+    }
+
+    /// Write bodies larger than the in-memory limit to a file in `dir`.
+    pub fn spill_to_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.spill_dir = Some(dir.into());
+        self
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for BufferBodyMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let is_streaming = req.body().is_some_and(|body| body.as_bytes().is_none());
+        if is_streaming {
+            let body = req.body_mut().take().expect("body is present");
+            let body = self.buffer(body, extensions).await?;
+            *req.body_mut() = Some(body);
+        }
+        next.run(req, extensions).await
+    }
+}
+
+impl BufferBodyMiddleware {
+    async fn buffer(&self, mut body: Body, extensions: &mut Extensions) -> Result<Body> {
+        let mut buffered = BytesMut::new();
+        while let Some(frame) = body.frame().await {
+            if let Ok(data) = frame?.into_data() {
+                buffered.extend_from_slice(&data);
+            }
+            if buffered.len() > self.max_in_memory {
+                return match &self.spill_dir {
+                    Some(dir) => {
+                        let replayable =
+                            ReplayableBody::spill(dir, buffered.freeze(), body).await?;
+                        let body = replayable.body()?;
+                        extensions.insert(replayable);
+                        Ok(body)
+                    }
+                    // Stitch what we've read so far back in front of the rest of the stream.
+                    None => Ok(Body::wrap_stream(
+                        stream::once(async move { Ok(buffered.freeze()) }).chain(body_stream(body)),
+                    )),
+                };
+            }
+        }
+        Ok(Body::from(buffered.freeze()))
+    }
+}
+
+/// A request body spilled to disk by [`BufferBodyMiddleware`], which can be read any number of
+/// times.
+///
+/// The underlying file is removed once the last copy of this extension is dropped.
+#[derive(Clone, Debug)]
+pub struct ReplayableBody {
+    file: Arc<SpillFile>,
+}
+
+impl ReplayableBody {
+    async fn spill(dir: &Path, prefix: Bytes, rest: Body) -> Result<Self> {
+        let file = SpillFile::new(dir);
+        let mut writer = tokio::fs::File::create(&file.0)
+            .await
+            .map_err(Error::middleware)?;
+        writer.write_all(&prefix).await.map_err(Error::middleware)?;
+        let mut rest = body_stream(rest);
+        while let Some(chunk) = rest.next().await {
+            writer.write_all(&chunk?).await.map_err(Error::middleware)?;
+        }
+        writer.flush().await.map_err(Error::middleware)?;
+        Ok(Self {
+            file: Arc::new(file),
+        })
+    }
+
+    /// Returns a new streaming body reading the spilled content from the start.
+    pub fn body(&self) -> Result<Body> {
+        let file = std::fs::File::open(&self.file.0).map_err(Error::middleware)?;
+        let reader = tokio_util::io::ReaderStream::new(tokio::fs::File::from_std(file));
+        Ok(Body::wrap_stream(reader))
+    }
+
+    /// Builds a copy of `req` whose body is read from the spilled content.
+    pub fn replay(&self, req: &Request) -> Result<Request> {
+        let mut replayed = Request::new(req.method().clone(), req.url().clone());
+        *replayed.headers_mut() = req.headers().clone();
+        *replayed.timeout_mut() = req.timeout().copied();
+        *replayed.version_mut() = req.version();
+        *replayed.body_mut() = Some(self.body()?);
+        Ok(replayed)
+    }
+}
+
+/// A uniquely named temporary file that is deleted when dropped.
+#[derive(Debug)]
+struct SpillFile(PathBuf);
+
+impl SpillFile {
+    fn new(dir: &Path) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let name = format!(
+            "reqwest-retry-body-{}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos
+        );
+        Self(dir.join(name))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn body_stream(body: Body) -> impl futures::Stream<Item = Result<Bytes>> + Send {
+    http_body_util::BodyStream::new(body).filter_map(|frame| {
+        future::ready(match frame {
+            Ok(frame) => frame.into_data().ok().map(Ok),
+            Err(e) => Some(Err(Error::Reqwest(e))),
+        })
+    })
+}
@@ -0,0 +1,419 @@
+//! `CircuitBreakerMiddleware` stops sending requests to upstreams that keep failing.
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::Extensions;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Clock, Error, Middleware, Next, Result, SystemClock};
+use thiserror::Error;
+
+use crate::retryable::Retryable;
+use crate::retryable_strategy::{DefaultRetryableStrategy, RetryableStrategy};
+
+/// The state of a circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally and failures are counted.
+    Closed,
+    /// Requests are rejected immediately with [`CircuitOpen`].
+    Open,
+    /// A limited number of probe requests are let through to check whether the upstream has
+    /// recovered.
+    HalfOpen,
+}
+
+/// Whether [`CircuitBreakerMiddleware`] keeps a circuit per host or one for the whole client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitScope {
+    /// One circuit per `host:port`.
+    PerHost,
+    /// A single circuit shared by all requests.
+    Client,
+}
+
+/// A state transition, passed to the listener registered with
+/// [`CircuitBreakerMiddleware::on_state_change`].
+#[derive(Clone, Debug)]
+pub struct CircuitStateChange {
+    /// The `host:port` of the circuit, or `None` for [`CircuitScope::Client`].
+    pub host: Option<String>,
+    pub from: CircuitState,
+    pub to: CircuitState,
+}
+
+/// Error returned by [`CircuitBreakerMiddleware`] without sending the request while the circuit
+/// is open.
+#[derive(Debug, Error)]
+#[error("Circuit breaker is open{}", .host.as_ref().map(|h| format!(" for {}", h)).unwrap_or_default())]
+pub struct CircuitOpen {
+    /// The `host:port` of the circuit, or `None` for [`CircuitScope::Client`].
+    pub host: Option<String>,
+}
+
+type StateListener = Arc<dyn Fn(&CircuitStateChange) + Send + Sync>;
+
+/// `CircuitBreakerMiddleware` tracks failures of requests and, once a threshold is reached,
+/// "opens" the circuit: further requests fail immediately with [`CircuitOpen`] rather than
+/// piling more load on a struggling upstream.
+///
+/// After the open duration has elapsed the circuit becomes half-open and lets a few probe
+/// requests through. If they all succeed the circuit closes again, otherwise it reopens.
+///
+/// The circuit trips when either threshold is reached:
+/// * a number of consecutive failures, see [`with_consecutive_failures`];
+/// * a failure rate over a sliding window, see [`with_failure_rate`].
+///
+/// Outcomes are classified with a [`RetryableStrategy`]: anything deemed
+/// [`Retryable::Transient`] (server errors, timeouts, connection errors with the default
+/// strategy) counts as a failure.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_retry::{CircuitBreakerMiddleware, CircuitScope};
+///
+/// let breaker = CircuitBreakerMiddleware::new()
+///     .with_scope(CircuitScope::PerHost)
+///     .with_consecutive_failures(5)
+///     .with_open_duration(Duration::from_secs(30))
+///     .on_state_change(|change| {
+///         println!("circuit for {:?} is now {:?}", change.host, change.to);
+///     });
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(breaker).build();
+/// ```
+///
+/// [`with_consecutive_failures`]: Self::with_consecutive_failures
+/// [`with_failure_rate`]: Self::with_failure_rate
+pub struct CircuitBreakerMiddleware<
+    R: RetryableStrategy + Send + Sync + 'static = DefaultRetryableStrategy,
+> {
+    retryable_strategy: R,
+    scope: CircuitScope,
+    consecutive_failures: Option<u32>,
+    failure_rate: Option<FailureRate>,
+    open_duration: Duration,
+    half_open_probes: u32,
+    listener: Option<StateListener>,
+    clock: Arc<dyn Clock>,
+    circuits: Mutex<HashMap<Option<String>, Circuit>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FailureRate {
+    rate: f64,
+    min_requests: usize,
+    window: Duration,
+}
+
+impl CircuitBreakerMiddleware<DefaultRetryableStrategy> {
+    /// Construct `CircuitBreakerMiddleware` with a per-host scope, tripping after 5 consecutive
+    /// failures and staying open for 30 seconds.
+    pub fn new() -> Self {
+        Self::new_with_strategy(DefaultRetryableStrategy)
+    }
+}
+
+impl Default for CircuitBreakerMiddleware<DefaultRetryableStrategy> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: RetryableStrategy + Send + Sync + 'static> CircuitBreakerMiddleware<R> {
+    /// Construct `CircuitBreakerMiddleware` using a custom
+    /// [retryable_strategy](RetryableStrategy) to classify failures.
+    pub fn new_with_strategy(retryable_strategy: R) -> Self {
+        Self {
+            retryable_strategy,
+            scope: CircuitScope::PerHost,
+            consecutive_failures: Some(5),
+            failure_rate: None,
+            open_duration: Duration::from_secs(30),
+            half_open_probes: 1,
+            listener: None,
+            clock: Arc::new(SystemClock),
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set whether circuits are kept per host or for the whole client.
+    pub fn with_scope(mut self, scope: CircuitScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Trip the circuit after `failures` consecutive failures.
+    pub fn with_consecutive_failures(mut self, failures: u32) -> Self {
+        self.consecutive_failures = Some(failures);
+        self
+    }
+
+    /// Don't trip the circuit on consecutive failures, only on the failure rate.
+    pub fn without_consecutive_failures(mut self) -> Self {
+        self.consecutive_failures = None;
+        self
+    }
+
+    /// Trip the circuit when the ratio of failed requests over the last `window` reaches `rate`
+    /// (in `0.0..=1.0`), provided at least `min_requests` were made.
+    pub fn with_failure_rate(mut self, rate: f64, min_requests: usize, window: Duration) -> Self {
+        self.failure_rate = Some(FailureRate {
+            rate,
+            min_requests,
+            window,
+        });
+        self
+    }
+
+    /// Set how long the circuit stays open before letting probe requests through.
+    pub fn with_open_duration(mut self, open_duration: Duration) -> Self {
+        self.open_duration = open_duration;
+        self
+    }
+
+    /// Set how many successful probe requests are needed to close a half-open circuit.
+    pub fn with_half_open_probes(mut self, probes: u32) -> Self {
+        self.half_open_probes = probes.max(1);
+        self
+    }
+
+    /// Register a listener invoked on every state transition, e.g. to export breaker state to
+    /// metrics.
+    pub fn on_state_change<F>(mut self, listener: F) -> Self
+    where
+        F: Fn(&CircuitStateChange) + Send + Sync + 'static,
+    {
+        self.listener = Some(Arc::new(listener));
+        self
+    }
+
+    /// Use `clock` to measure how long circuits stay open and the failure rate window.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the current state of the circuit used for `url`.
+    pub fn state(&self, url: &Url) -> CircuitState {
+        let key = self.key(url);
+        let mut circuits = self.circuits.lock().expect("circuits lock poisoned");
+        circuits
+            .get_mut(&key)
+            .map(|circuit| {
+                circuit.refresh(self.clock.now());
+                circuit.state()
+            })
+            .unwrap_or(CircuitState::Closed)
+    }
+
+    fn key(&self, url: &Url) -> Option<String> {
+        match self.scope {
+            CircuitScope::Client => None,
+            CircuitScope::PerHost => Some(format!(
+                "{}:{}",
+                url.host_str().unwrap_or_default(),
+                url.port_or_known_default().unwrap_or_default()
+            )),
+        }
+    }
+
+    fn transition(&self, host: &Option<String>, from: CircuitState, to: CircuitState) {
+        if from == to {
+            return;
+        }
+        tracing::warn!(host = ?host, "Circuit breaker state changed from {:?} to {:?}", from, to);
+        if let Some(listener) = &self.listener {
+            listener(&CircuitStateChange {
+                host: host.clone(),
+                from,
+                to,
+            });
+        }
+    }
+
+    /// Checks whether a request may be sent.
+    fn acquire(&self, key: &Option<String>) -> Result<()> {
+        let mut circuits = self.circuits.lock().expect("circuits lock poisoned");
+        let circuit = circuits.entry(key.clone()).or_default();
+        let before = circuit.state();
+        let now = self.clock.now();
+        circuit.refresh(now);
+        let permitted = circuit.try_acquire(now, self.half_open_probes, self.open_duration);
+        let after = circuit.state();
+        drop(circuits);
+
+        self.transition(key, before, after);
+        if permitted {
+            Ok(())
+        } else {
+            Err(Error::middleware(CircuitOpen { host: key.clone() }))
+        }
+    }
+
+    fn record(&self, key: &Option<String>, failed: bool) {
+        let now = self.clock.now();
+        let mut circuits = self.circuits.lock().expect("circuits lock poisoned");
+        let circuit = circuits.entry(key.clone()).or_default();
+        let before = circuit.state();
+        match &mut circuit.inner {
+            CircuitInner::Closed {
+                consecutive_failures,
+                outcomes,
+            } => {
+                *consecutive_failures = if failed { *consecutive_failures + 1 } else { 0 };
+                let mut trip = self
+                    .consecutive_failures
+                    .is_some_and(|threshold| *consecutive_failures >= threshold);
+                if let Some(rate) = self.failure_rate {
+                    outcomes.push_back((now, failed));
+                    while outcomes
+                        .front()
+                        .is_some_and(|(at, _)| now.duration_since(*at) > rate.window)
+                    {
+                        outcomes.pop_front();
+                    }
+                    let failures = outcomes.iter().filter(|(_, failed)| *failed).count();
+                    trip |= outcomes.len() >= rate.min_requests
+                        && failures as f64 / outcomes.len() as f64 >= rate.rate;
+                }
+                if trip {
+                    circuit.open(now + self.open_duration);
+                }
+            }
+            CircuitInner::HalfOpen {
+                in_flight,
+                successes,
+                ..
+            } => {
+                *in_flight = in_flight.saturating_sub(1);
+                if failed {
+                    circuit.open(now + self.open_duration);
+                } else {
+                    *successes += 1;
+                    if *successes >= self.half_open_probes {
+                        circuit.inner = CircuitInner::closed();
+                    }
+                }
+            }
+            // A request let through before the circuit opened finished, nothing to do.
+            CircuitInner::Open { .. } => {}
+        }
+        let after = circuit.state();
+        drop(circuits);
+
+        self.transition(key, before, after);
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<R> Middleware for CircuitBreakerMiddleware<R>
+where
+    R: RetryableStrategy + Send + Sync + 'static,
+{
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let key = self.key(req.url());
+        self.acquire(&key)?;
+        let result = next.run(req, extensions).await;
+        let failed = matches!(
+            self.retryable_strategy.handle(&result),
+            Some(Retryable::Transient)
+        );
+        self.record(&key, failed);
+        result
+    }
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    inner: CircuitInner,
+}
+
+#[derive(Debug)]
+enum CircuitInner {
+    Closed {
+        consecutive_failures: u32,
+        outcomes: VecDeque<(Instant, bool)>,
+    },
+    Open {
+        until: Instant,
+    },
+    HalfOpen {
+        in_flight: u32,
+        successes: u32,
+        since: Instant,
+    },
+}
+
+impl CircuitInner {
+    fn closed() -> Self {
+        CircuitInner::Closed {
+            consecutive_failures: 0,
+            outcomes: VecDeque::new(),
+        }
+    }
+}
+
+impl Default for CircuitInner {
+    fn default() -> Self {
+        Self::closed()
+    }
+}
+
+impl Circuit {
+    fn state(&self) -> CircuitState {
+        match self.inner {
+            CircuitInner::Closed { .. } => CircuitState::Closed,
+            CircuitInner::Open { .. } => CircuitState::Open,
+            CircuitInner::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    fn open(&mut self, until: Instant) {
+        self.inner = CircuitInner::Open { until };
+    }
+
+    /// Moves an open circuit to half-open once its open duration has elapsed.
+    fn refresh(&mut self, now: Instant) {
+        if let CircuitInner::Open { until } = self.inner {
+            if now >= until {
+                self.inner = CircuitInner::HalfOpen {
+                    in_flight: 0,
+                    successes: 0,
+                    since: now,
+                };
+            }
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant, probes: u32, probe_timeout: Duration) -> bool {
+        match &mut self.inner {
+            CircuitInner::Closed { .. } => true,
+            CircuitInner::Open { .. } => false,
+            CircuitInner::HalfOpen {
+                in_flight,
+                successes,
+                since,
+            } => {
+                // Probes that never reported back (e.g. because they were cancelled) shouldn't
+                // keep the circuit half-open forever.
+                if now.duration_since(*since) > probe_timeout {
+                    *in_flight = 0;
+                    *since = now;
+                }
+                if *in_flight + *successes < probes {
+                    *in_flight += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
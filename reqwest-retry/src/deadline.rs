@@ -0,0 +1,88 @@
+//! `Deadline` bounds the total time spent on a request across all retry attempts.
+use std::time::{Duration, SystemTime};
+
+use thiserror::Error;
+
+/// An overall deadline for a request, covering every attempt made by
+/// [`RetryTransientMiddleware`](crate::RetryTransientMiddleware).
+///
+/// A per-request [`timeout`] applies to each attempt individually, so three retries of a request
+/// with a 30 second timeout can take well over 90 seconds. When a `Deadline` is present in the
+/// request extensions the retry middleware will:
+/// * clamp the timeout of each attempt so that it can't run past the deadline;
+/// * stop retrying, returning the last outcome, if the next attempt would start after the
+///   deadline;
+/// * fail with [`DeadlineExceeded`] if the deadline has already passed before an attempt starts.
+///
+/// Deadlines are absolute points in time, so they should be attached to each request rather than
+/// to the client:
+///
+/// ```no_run
+/// # use reqwest_middleware::Result;
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_retry::{policies::ExponentialBackoff, Deadline, RetryTransientMiddleware};
+///
+/// # async fn example() -> Result<()> {
+/// let retry_policy = ExponentialBackoff::builder().build_with_max_retries(5);
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+///     .build();
+///
+/// let resp = client
+///     .get("https://truelayer.com")
+///     .timeout(Duration::from_secs(30))
+///     // Give up after 45 seconds, however many attempts that allows.
+///     .with_extension(Deadline::after(Duration::from_secs(45)))
+///     .send()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`timeout`]: reqwest_middleware::RequestBuilder::timeout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(SystemTime);
+
+impl Deadline {
+    /// Create a deadline at the given point in time.
+    pub fn at(deadline: SystemTime) -> Self {
+        Self(deadline)
+    }
+
+    /// Create a deadline `duration` from now.
+    pub fn after(duration: Duration) -> Self {
+        Self(SystemTime::now() + duration)
+    }
+
+    /// The point in time at which the deadline expires.
+    pub fn time(&self) -> SystemTime {
+        self.0
+    }
+
+    /// Time left until the deadline expires, or `None` if it has already expired.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining_at(SystemTime::now())
+    }
+
+    /// Time left at `now` until the deadline expires, or `None` if it has already expired.
+    ///
+    /// The retry middleware calls this with the time of its [`Clock`](reqwest_middleware::Clock).
+    pub fn remaining_at(&self, now: SystemTime) -> Option<Duration> {
+        match self.0.duration_since(now) {
+            Ok(remaining) if !remaining.is_zero() => Some(remaining),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the deadline has already expired.
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_none()
+    }
+}
+
+/// Error returned by [`RetryTransientMiddleware`](crate::RetryTransientMiddleware) when the
+/// request's [`Deadline`] expired before an attempt could be started.
+#[derive(Debug, Error)]
+#[error("Request deadline exceeded")]
+pub struct DeadlineExceeded;
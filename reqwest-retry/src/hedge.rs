@@ -0,0 +1,159 @@
+//! `HedgeMiddleware` issues speculative duplicate requests to cut tail latency.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use http::{Extensions, Method};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Clock, Middleware, Next, Result, SystemClock};
+
+/// How long [`HedgeMiddleware`] waits for a response before issuing the hedged request.
+#[derive(Clone, Copy, Debug)]
+pub enum HedgeDelay {
+    /// Always wait for the same amount of time.
+    Fixed(Duration),
+    /// Wait for the given percentile (in `0.0..=1.0`) of recently observed latencies.
+    ///
+    /// `fallback` is used until at least `min_samples` latencies have been observed.
+    Percentile {
+        percentile: f64,
+        min_samples: usize,
+        fallback: Duration,
+    },
+}
+
+/// Number of latencies kept to compute [`HedgeDelay::Percentile`].
+const LATENCY_WINDOW: usize = 256;
+
+/// `HedgeMiddleware` sends a second, identical request when the first one hasn't completed
+/// within a given delay, and returns whichever response arrives first. The other request is
+/// cancelled by dropping it.
+///
+/// Hedging trades a little extra load for lower tail latency, so it is only applied to
+/// idempotent requests: by default `GET`, `HEAD` and `OPTIONS`, see [`with_methods`]. Requests
+/// with streaming bodies can't be duplicated and are never hedged.
+///
+/// Each attempt runs the rest of the middleware chain with its own copy of the extensions; the
+/// extensions of the winning attempt are merged back once it completes.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_retry::{HedgeDelay, HedgeMiddleware};
+///
+/// // Hedge requests that take longer than the p95 of recent requests.
+/// let hedge = HedgeMiddleware::new(HedgeDelay::Percentile {
+///     percentile: 0.95,
+///     min_samples: 20,
+///     fallback: Duration::from_millis(200),
+/// });
+/// let client = ClientBuilder::new(reqwest::Client::new()).with(hedge).build();
+/// ```
+///
+/// [`with_methods`]: Self::with_methods
+pub struct HedgeMiddleware {
+    delay: HedgeDelay,
+    methods: Vec<Method>,
+    latencies: Mutex<VecDeque<Duration>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl HedgeMiddleware {
+    /// Construct `HedgeMiddleware` with the given [`HedgeDelay`].
+    pub fn new(delay: HedgeDelay) -> Self {
+        Self {
+            delay,
+            methods: vec![Method::GET, Method::HEAD, Method::OPTIONS],
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set the request methods which are safe to hedge.
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Use `clock` to wait before hedging and to measure latencies.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn current_delay(&self) -> Duration {
+        match self.delay {
+            HedgeDelay::Fixed(delay) => delay,
+            HedgeDelay::Percentile {
+                percentile,
+                min_samples,
+                fallback,
+            } => {
+                let latencies = self.latencies.lock().expect("latencies lock poisoned");
+                if latencies.is_empty() || latencies.len() < min_samples {
+                    return fallback;
+                }
+                let mut sorted: Vec<_> = latencies.iter().copied().collect();
+                sorted.sort_unstable();
+                let rank = (percentile.clamp(0.0, 1.0) * (sorted.len() - 1) as f64).round();
+                sorted[rank as usize]
+            }
+        }
+    }
+
+    fn record_latency(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().expect("latencies lock poisoned");
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for HedgeMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let hedged_req = if self.methods.contains(req.method()) {
+            req.try_clone()
+        } else {
+            None
+        };
+        let hedged_req = match hedged_req {
+            Some(hedged_req) => hedged_req,
+            None => return next.run(req, extensions).await,
+        };
+
+        let delay = self.current_delay();
+        let start = self.clock.now();
+        let mut primary_ext = extensions.clone();
+        let mut hedged_ext = extensions.clone();
+        let (result, primary_won) = {
+            let primary = next.clone().run(req, &mut primary_ext);
+            let hedged = async {
+                self.clock.sleep(delay).await;
+
+                tracing::debug!("No response after {:?}, sending hedged request", delay);
+                next.clone().run(hedged_req, &mut hedged_ext).await
+            };
+            futures::pin_mut!(hedged);
+            // Dropping the losing future cancels the request.
+            match future::select(primary, hedged).await {
+                Either::Left((result, _)) => (result, true),
+                Either::Right((result, _)) => (result, false),
+            }
+        };
+
+        if result.is_ok() {
+            self.record_latency(self.clock.now() - start);
+        }
+        extensions.extend(if primary_won { primary_ext } else { hedged_ext });
+        result
+    }
+}
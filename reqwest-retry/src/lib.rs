@@ -25,12 +25,24 @@
 //! }
 //! ```
 
+#[cfg(not(target_arch = "wasm32"))]
+mod buffer;
+mod circuit_breaker;
+mod deadline;
+mod hedge;
 mod middleware;
 mod retryable;
 mod retryable_strategy;
 
 pub use retry_policies::{policies, Jitter, RetryDecision, RetryPolicy};
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use buffer::{BufferBodyMiddleware, ReplayableBody};
+pub use circuit_breaker::{
+    CircuitBreakerMiddleware, CircuitOpen, CircuitScope, CircuitState, CircuitStateChange,
+};
+pub use deadline::{Deadline, DeadlineExceeded};
+pub use hedge::{HedgeDelay, HedgeMiddleware};
 pub use middleware::RetryTransientMiddleware;
 pub use retryable::Retryable;
 pub use retryable_strategy::{
@@ -1,12 +1,16 @@
 //! `RetryTransientMiddleware` implements retrying requests on transient errors.
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::buffer::ReplayableBody;
+use crate::deadline::{Deadline, DeadlineExceeded};
 use crate::retryable_strategy::RetryableStrategy;
 use crate::{retryable::Retryable, retryable_strategy::DefaultRetryableStrategy};
 use anyhow::anyhow;
 use http::Extensions;
 use reqwest::{Request, Response};
-use reqwest_middleware::{Error, Middleware, Next, Result};
+use reqwest_middleware::{Clock, Error, Middleware, Next, ResendCount, Result, SystemClock};
 use retry_policies::RetryPolicy;
 
 #[doc(hidden)]
@@ -28,9 +32,11 @@ macro_rules! log_retry {
 /// and can be safely executed again.
 ///
 /// Currently, it allows setting a [RetryPolicy] algorithm for calculating the __wait_time__
-/// between each request retry. Sleeping on non-`wasm32` archs is performed using
-/// [`tokio::time::sleep`], therefore it will respect pauses/auto-advance if run under a
-/// runtime that supports them.
+/// between each request retry. Sleeping is performed with a [`Clock`], [`SystemClock`] by
+/// default, which uses [`tokio::time::sleep`] on non-`wasm32` archs and therefore respects
+/// pauses/auto-advance if run under a runtime that supports them. Tests can use a
+/// [`MockClock`](reqwest_middleware::MockClock) instead, see [`with_clock`](Self::with_clock), to
+/// skip the waits.
 ///
 ///```rust
 ///     use std::time::Duration;
@@ -51,6 +57,8 @@ macro_rules! log_retry {
 ///     let client = ClientBuilder::new(Client::new()).with(retry_transient_middleware).build();
 ///```
 ///
+/// To bound the total time spent across all attempts, attach a [`Deadline`] to the request.
+///
 /// # Note
 ///
 /// This middleware always errors when given requests with streaming bodies, before even executing
@@ -58,11 +66,13 @@ macro_rules! log_retry {
 /// 'Request object is not clonable. Are you passing a streaming body?'.
 ///
 /// Some workaround suggestions:
+/// * Attach a [`BufferBodyMiddleware`](crate::BufferBodyMiddleware) before this middleware, which
+///   buffers streaming bodies in memory or on disk so that they can be replayed.
 /// * If you can fit the data in memory, you can instead build static request bodies e.g. with
-/// `Body`'s `From<String>` or `From<Bytes>` implementations.
+///   `Body`'s `From<String>` or `From<Bytes>` implementations.
 /// * You can wrap this middleware in a custom one which skips retries for streaming requests.
 /// * You can write a custom retry middleware that builds new streaming requests from the data
-/// source directly, avoiding the issue of streaming requests not being clonable.
+///   source directly, avoiding the issue of streaming requests not being clonable.
 pub struct RetryTransientMiddleware<
     T: RetryPolicy + Send + Sync + 'static,
     R: RetryableStrategy + Send + Sync + 'static = DefaultRetryableStrategy,
@@ -70,6 +80,7 @@ pub struct RetryTransientMiddleware<
     retry_policy: T,
     retryable_strategy: R,
     retry_log_level: tracing::Level,
+    clock: Arc<dyn Clock>,
 }
 
 impl<T: RetryPolicy + Send + Sync> RetryTransientMiddleware<T, DefaultRetryableStrategy> {
@@ -97,8 +108,17 @@ where
             retry_policy,
             retryable_strategy,
             retry_log_level: tracing::Level::WARN,
+            clock: Arc::new(SystemClock),
         }
     }
+
+    /// Use `clock` to wait between attempts.
+    ///
+    /// The retry policy still decides when to retry based on the system time.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
 }
 
 #[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
@@ -137,17 +157,38 @@ where
         ext: &'a mut Extensions,
     ) -> Result<Response> {
         let mut n_past_retries = 0;
-        let start_time = SystemTime::now();
+        let started_at = self.clock.now();
+        let deadline = ext.get::<Deadline>().copied();
         loop {
             // Cloning the request object before-the-fact is not ideal..
             // However, if the body of the request is not static, e.g of type `Bytes`,
             // the Clone operation should be of constant complexity and not O(N)
             // since the byte abstraction is a shared pointer over a buffer.
-            let duplicate_request = req.try_clone().ok_or_else(|| {
-                Error::Middleware(anyhow!(
-                    "Request object is not clonable. Are you passing a streaming body?".to_string()
-                ))
-            })?;
+            let mut duplicate_request = match req.try_clone() {
+                Some(duplicate_request) => duplicate_request,
+                #[cfg(not(target_arch = "wasm32"))]
+                None if ext.get::<ReplayableBody>().is_some() => {
+                    // The body was spilled to disk by `BufferBodyMiddleware`, read it again.
+                    ext.get::<ReplayableBody>()
+                        .expect("replayable body is present")
+                        .replay(&req)?
+                }
+                None => {
+                    return Err(Error::Middleware(anyhow!(
+                        "Request object is not clonable. Are you passing a streaming body?"
+                            .to_string()
+                    )))
+                }
+            };
+
+            if let Some(deadline) = deadline {
+                let remaining = deadline
+                    .remaining_at(self.clock.system_time())
+                    .ok_or_else(|| Error::middleware(DeadlineExceeded))?;
+                // Don't let a single attempt outlive the overall deadline.
+                let timeout = duplicate_request.timeout_mut();
+                *timeout = Some(timeout.map_or(remaining, |t| t.min(remaining)));
+            }
 
             let result = next.clone().run(duplicate_request, ext).await;
 
@@ -157,11 +198,26 @@ where
                 Some(Retryable::Transient) => {
                     // If the response failed and the error type was transient
                     // we can safely try to retry the request.
+                    // Retry policies schedule attempts with the system time, so the time elapsed
+                    // on the clock is translated to it, and so is the wait back.
+                    let now = SystemTime::now();
+                    let start_time = now - (self.clock.now() - started_at);
                     let retry_decision = self.retry_policy.should_retry(start_time, n_past_retries);
                     if let retry_policies::RetryDecision::Retry { execute_after } = retry_decision {
                         let duration = execute_after
-                            .duration_since(SystemTime::now())
+                            .duration_since(now)
                             .unwrap_or_else(|_| Duration::default());
+                        let next_attempt_at = self.clock.system_time() + duration;
+                        if deadline.is_some_and(|d| next_attempt_at >= d.time()) {
+                            // The next attempt would start after the deadline, so there's no
+                            // point in waiting for it.
+                            log_retry!(
+                                self.retry_log_level,
+                                "Not retrying after attempt #{}: the request deadline would be exceeded",
+                                n_past_retries
+                            );
+                            break result;
+                        }
                         // Sleep the requested amount before we try again.
                         log_retry!(
                             self.retry_log_level,
@@ -169,14 +225,10 @@ where
                             n_past_retries,
                             duration
                         );
-                        #[cfg(not(target_arch = "wasm32"))]
-                        tokio::time::sleep(duration).await;
-                        #[cfg(target_arch = "wasm32")]
-                        wasm_timer::Delay::new(duration)
-                            .await
-                            .expect("failed sleeping");
+                        self.clock.sleep(duration).await;
 
                         n_past_retries += 1;
+                        ResendCount::increment(ext);
                         continue;
                     } else {
                         result
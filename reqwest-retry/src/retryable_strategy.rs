@@ -1,6 +1,6 @@
 use crate::retryable::Retryable;
 use http::StatusCode;
-use reqwest_middleware::Error;
+use reqwest_middleware::{Error, StatusError};
 
 /// A strategy to create a [`Retryable`] from a [`Result<reqwest::Response, reqwest_middleware::Error>`]
 ///
@@ -111,7 +111,10 @@ impl RetryableStrategy for DefaultRetryableStrategy {
 ///
 /// Note that success here means that the request finished without interruption, not that it was logically OK.
 pub fn default_on_request_success(success: &reqwest::Response) -> Option<Retryable> {
-    let status = success.status();
+    on_status(success.status())
+}
+
+fn on_status(status: StatusCode) -> Option<Retryable> {
     if status.is_server_error() {
         Some(Retryable::Transient)
     } else if status.is_client_error()
@@ -130,11 +133,16 @@ pub fn default_on_request_success(success: &reqwest::Response) -> Option<Retryab
 
 /// Default request failure retry strategy.
 ///
-/// Will only retry if the request failed due to a network error
+/// Will only retry if the request failed due to a network error, or if it failed with a
+/// [`StatusError`] whose status would be retried by [`default_on_request_success`], e.g. when
+/// the error was raised by an `EnsureSuccessMiddleware` added after the retry middleware.
 pub fn default_on_request_failure(error: &Error) -> Option<Retryable> {
     match error {
-        // If something fails in the middleware we're screwed.
-        Error::Middleware(_) => Some(Retryable::Fatal),
+        Error::Middleware(error) => match error.downcast_ref::<StatusError>() {
+            Some(error) => on_status(error.status()),
+            // If something fails in the middleware we're screwed.
+            None => Some(Retryable::Fatal),
+        },
         Error::Reqwest(error) => {
             #[cfg(not(target_arch = "wasm32"))]
             let is_connect = error.is_connect();
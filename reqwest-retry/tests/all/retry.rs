@@ -2,8 +2,14 @@ use futures::FutureExt;
 use paste::paste;
 use reqwest::Client;
 use reqwest::StatusCode;
-use reqwest_middleware::ClientBuilder;
-use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use reqwest_middleware::{
+    ClientBuilder, Clock, EnsureSuccessMiddleware, Error, Middleware, MockClock, Next, ResendCount,
+    StatusError,
+};
+use reqwest_retry::{
+    policies::ExponentialBackoff, BufferBodyMiddleware, CircuitBreakerMiddleware, CircuitOpen,
+    CircuitState, Deadline, HedgeDelay, HedgeMiddleware, RetryTransientMiddleware,
+};
 use std::sync::atomic::AtomicI8;
 use std::sync::{
     atomic::{AtomicU32, Ordering},
@@ -361,3 +367,386 @@ async fn assert_retry_on_connection_reset_by_peer() {
 
     assert_eq!(resp.status(), 200);
 }
+
+#[tokio::test]
+async fn assert_no_retry_past_deadline() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let reqwest_client = Client::builder().build().unwrap();
+    let client = ClientBuilder::new(reqwest_client)
+        .with(RetryTransientMiddleware::new_with_policy(
+            ExponentialBackoff::builder()
+                .retry_bounds(
+                    std::time::Duration::from_millis(500),
+                    std::time::Duration::from_millis(1000),
+                )
+                .build_with_max_retries(3),
+        ))
+        .build();
+
+    let resp = client
+        .get(&format!("{}/foo", server.uri()))
+        .with_extension(Deadline::after(std::time::Duration::from_millis(100)))
+        .send()
+        .await
+        .expect("call failed");
+
+    assert_eq!(resp.status(), 500);
+}
+
+#[tokio::test]
+async fn assert_error_on_expired_deadline() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let reqwest_client = Client::builder().build().unwrap();
+    let client = ClientBuilder::new(reqwest_client)
+        .with(RetryTransientMiddleware::new_with_policy(
+            ExponentialBackoff::builder().build_with_max_retries(3),
+        ))
+        .build();
+
+    let err = client
+        .get(&format!("{}/foo", server.uri()))
+        .with_extension(Deadline::at(std::time::SystemTime::now()))
+        .send()
+        .await
+        .expect_err("deadline should have expired");
+
+    assert!(err.is_middleware());
+}
+
+/// Records the [`ResendCount`] of each attempt.
+#[derive(Clone, Default)]
+struct ResendCounts(Arc<std::sync::Mutex<Vec<u32>>>);
+
+#[async_trait::async_trait]
+impl Middleware for ResendCounts {
+    async fn handle(
+        &self,
+        req: reqwest::Request,
+        extensions: &mut http::Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        self.0.lock().unwrap().push(ResendCount::get(extensions));
+        next.run(req, extensions).await
+    }
+}
+
+#[tokio::test]
+async fn assert_resend_count_is_incremented_on_retry() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(RetryResponder::new(3, 500))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let counts = ResendCounts::default();
+    let reqwest_client = Client::builder().build().unwrap();
+    let client = ClientBuilder::new(reqwest_client)
+        .with(RetryTransientMiddleware::new_with_policy(
+            ExponentialBackoff::builder()
+                .retry_bounds(
+                    std::time::Duration::from_millis(30),
+                    std::time::Duration::from_millis(100),
+                )
+                .build_with_max_retries(3),
+        ))
+        .with(counts.clone())
+        .build();
+
+    let resp = client
+        .get(&format!("{}/foo", server.uri()))
+        .send()
+        .await
+        .expect("call failed");
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(*counts.0.lock().unwrap(), vec![0, 1]);
+}
+
+macro_rules! assert_retry_streaming_body {
+    ($name:ident, $middleware:expr) => {
+        #[tokio::test]
+        async fn $name() {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/foo"))
+                .and(wiremock::matchers::body_string("hello world"))
+                .respond_with(RetryResponder::new(3, 500))
+                .expect(2)
+                .mount(&server)
+                .await;
+
+            let reqwest_client = Client::builder().build().unwrap();
+            let client = ClientBuilder::new(reqwest_client)
+                .with($middleware)
+                .with(RetryTransientMiddleware::new_with_policy(
+                    ExponentialBackoff::builder()
+                        .retry_bounds(
+                            std::time::Duration::from_millis(30),
+                            std::time::Duration::from_millis(100),
+                        )
+                        .build_with_max_retries(3),
+                ))
+                .build();
+
+            let chunks: Vec<Result<&'static str, std::io::Error>> = vec![Ok("hello"), Ok(" world")];
+            let resp = client
+                .post(&format!("{}/foo", server.uri()))
+                .body(reqwest::Body::wrap_stream(futures::stream::iter(chunks)))
+                .send()
+                .await
+                .expect("call failed");
+
+            assert_eq!(resp.status(), 200);
+        }
+    };
+}
+
+assert_retry_streaming_body!(
+    assert_retry_streaming_body_buffered_in_memory,
+    BufferBodyMiddleware::new(1024)
+);
+assert_retry_streaming_body!(
+    assert_retry_streaming_body_spilled_to_disk,
+    BufferBodyMiddleware::new(4).spill_to_disk()
+);
+
+#[tokio::test]
+async fn assert_hedged_request_wins_over_slow_request() {
+    let server = MockServer::start().await;
+    let calls = Arc::new(AtomicU32::new(0));
+    let responder_calls = calls.clone();
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(move |_: &wiremock::Request| {
+            if responder_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(5))
+            } else {
+                ResponseTemplate::new(200)
+            }
+        })
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let reqwest_client = Client::builder().build().unwrap();
+    let client = ClientBuilder::new(reqwest_client)
+        .with(HedgeMiddleware::new(HedgeDelay::Fixed(
+            std::time::Duration::from_millis(50),
+        )))
+        .build();
+
+    let start = std::time::Instant::now();
+    let resp = client
+        .get(&format!("{}/foo", server.uri()))
+        .send()
+        .await
+        .expect("call failed");
+
+    assert_eq!(resp.status(), 200);
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn assert_circuit_opens_after_consecutive_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let reqwest_client = Client::builder().build().unwrap();
+    let client = ClientBuilder::new(reqwest_client)
+        .with(CircuitBreakerMiddleware::new().with_consecutive_failures(3))
+        .build();
+
+    for _ in 0..3 {
+        let resp = client
+            .get(&format!("{}/foo", server.uri()))
+            .send()
+            .await
+            .expect("call failed");
+        assert_eq!(resp.status(), 503);
+    }
+
+    let err = client
+        .get(&format!("{}/foo", server.uri()))
+        .send()
+        .await
+        .expect_err("circuit should be open");
+    match err {
+        reqwest_middleware::Error::Middleware(err) => assert!(err.is::<CircuitOpen>()),
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[tokio::test]
+async fn assert_retry_waits_on_clock() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(RetryResponder::new(4, 500))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let start = clock.now();
+    let reqwest_client = Client::builder().build().unwrap();
+    let client = ClientBuilder::new(reqwest_client)
+        .with(
+            RetryTransientMiddleware::new_with_policy(
+                ExponentialBackoff::builder()
+                    .retry_bounds(
+                        std::time::Duration::from_secs(10),
+                        std::time::Duration::from_secs(10),
+                    )
+                    .jitter(reqwest_retry::Jitter::None)
+                    .build_with_max_retries(2),
+            )
+            .with_clock(clock.clone()),
+        )
+        .build();
+
+    let resp = client
+        .get(&format!("{}/foo", server.uri()))
+        .send()
+        .await
+        .expect("call failed");
+    assert_eq!(resp.status(), 200);
+    assert!(clock.now() - start >= std::time::Duration::from_secs(19));
+}
+
+#[tokio::test]
+async fn assert_deadline_follows_clock() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let reqwest_client = Client::builder().build().unwrap();
+    let client = ClientBuilder::new(reqwest_client)
+        .with(
+            RetryTransientMiddleware::new_with_policy(
+                ExponentialBackoff::builder()
+                    .retry_bounds(
+                        std::time::Duration::from_secs(10),
+                        std::time::Duration::from_secs(10),
+                    )
+                    .jitter(reqwest_retry::Jitter::None)
+                    .build_with_max_retries(5),
+            )
+            .with_clock(clock.clone()),
+        )
+        .build();
+
+    // Two waits of 10 seconds fit before the deadline, the third doesn't.
+    let resp = client
+        .get(&format!("{}/foo", server.uri()))
+        .with_extension(Deadline::at(
+            clock.system_time() + std::time::Duration::from_secs(25),
+        ))
+        .send()
+        .await
+        .expect("call failed");
+    assert_eq!(resp.status(), 500);
+}
+
+#[tokio::test]
+async fn assert_circuit_half_opens_on_clock() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let clock = Arc::new(MockClock::new());
+    let breaker = Arc::new(
+        CircuitBreakerMiddleware::new()
+            .with_consecutive_failures(1)
+            .with_open_duration(std::time::Duration::from_secs(30))
+            .with_clock(clock.clone()),
+    );
+    let client = ClientBuilder::new(Client::new())
+        .with_arc(breaker.clone())
+        .build();
+    let url = reqwest::Url::parse(&format!("{}/foo", server.uri())).unwrap();
+
+    client.get(url.clone()).send().await.expect("call failed");
+    assert_eq!(breaker.state(&url), CircuitState::Open);
+    clock.advance(std::time::Duration::from_secs(30));
+    assert_eq!(breaker.state(&url), CircuitState::HalfOpen);
+}
+
+#[tokio::test]
+async fn assert_retry_on_ensured_status_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(RetryResponder::new(3, 503))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("no such user"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(RetryTransientMiddleware::new_with_policy(
+            ExponentialBackoff::builder()
+                .retry_bounds(
+                    std::time::Duration::from_millis(30),
+                    std::time::Duration::from_millis(100),
+                )
+                .build_with_max_retries(3),
+        ))
+        .with(EnsureSuccessMiddleware::new())
+        .build();
+
+    let resp = client
+        .get(&format!("{}/foo", server.uri()))
+        .send()
+        .await
+        .expect("call failed");
+    assert_eq!(resp.status(), 200);
+
+    let err = client
+        .get(&format!("{}/missing", server.uri()))
+        .send()
+        .await
+        .expect_err("call succeeded");
+    assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+    match err {
+        Error::Middleware(err) => {
+            let err = err.downcast_ref::<StatusError>().unwrap();
+            assert_eq!(err.text(), "no such user");
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
@@ -0,0 +1,112 @@
+//! `LoadBalancerMiddleware` spreads requests across a pool of origins.
+use std::sync::Arc;
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+use reqwest_retry::{DefaultRetryableStrategy, Retryable, RetryableStrategy};
+
+use crate::origin::ServedBy;
+use crate::pool::{BalanceStrategy, OriginPool};
+
+/// `LoadBalancerMiddleware` distributes requests across the origins of an [`OriginPool`].
+///
+/// Requests addressed to any origin of the pool have their scheme, host and port rewritten to
+/// the origin picked by the [`BalanceStrategy`]; other requests are left alone. The origin that
+/// served the response is recorded as a [`ServedBy`] extension.
+///
+/// Outcomes classified as [`Retryable::Transient`] by the [`RetryableStrategy`] count as failures
+/// of the origin, which gets ejected from the pool if it keeps failing.
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_routing::{BalanceStrategy, LoadBalancerMiddleware, OriginPool};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = OriginPool::new([
+///     "https://api-1.example.com".parse()?,
+///     "https://api-2.example.com".parse()?,
+///     "https://api-3.example.com".parse()?,
+/// ])
+/// .with_max_failures(3)
+/// .with_cooldown(Duration::from_secs(10));
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(LoadBalancerMiddleware::new(Arc::new(pool), BalanceStrategy::LeastOutstanding))
+///     .build();
+///
+/// let req = client.get("https://api-1.example.com/users");
+/// # Ok(())
+/// # }
+/// ```
+pub struct LoadBalancerMiddleware<
+    R: RetryableStrategy + Send + Sync + 'static = DefaultRetryableStrategy,
+> {
+    pool: Arc<OriginPool>,
+    strategy: BalanceStrategy,
+    retryable_strategy: R,
+}
+
+impl LoadBalancerMiddleware<DefaultRetryableStrategy> {
+    /// Construct `LoadBalancerMiddleware` over `pool`.
+    pub fn new(pool: Arc<OriginPool>, strategy: BalanceStrategy) -> Self {
+        Self::new_with_strategy(pool, strategy, DefaultRetryableStrategy)
+    }
+}
+
+impl<R: RetryableStrategy + Send + Sync + 'static> LoadBalancerMiddleware<R> {
+    /// Construct `LoadBalancerMiddleware` over `pool`, with a
+    /// [retryable_strategy](RetryableStrategy) deciding which outcomes count as failures.
+    pub fn new_with_strategy(
+        pool: Arc<OriginPool>,
+        strategy: BalanceStrategy,
+        retryable_strategy: R,
+    ) -> Self {
+        Self {
+            pool,
+            strategy,
+            retryable_strategy,
+        }
+    }
+
+    /// The pool of origins requests are distributed across.
+    pub fn pool(&self) -> &Arc<OriginPool> {
+        &self.pool
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<R> Middleware for LoadBalancerMiddleware<R>
+where
+    R: RetryableStrategy + Send + Sync + 'static,
+{
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !self.pool.contains(req.url()) {
+            return next.run(req, extensions).await;
+        }
+        let guard = match self.pool.select(self.strategy) {
+            Some(guard) => guard,
+            None => return next.run(req, extensions).await,
+        };
+        guard.origin().apply(req.url_mut());
+
+        let result = next.run(req, extensions).await;
+        let failed = self.retryable_strategy.handle(&result) == Some(Retryable::Transient);
+        guard.record(!failed);
+
+        let served_by = ServedBy(guard.origin().clone());
+        extensions.insert(served_by.clone());
+        result.map(|mut res| {
+            res.extensions_mut().insert(served_by);
+            res
+        })
+    }
+}
@@ -0,0 +1,116 @@
+//! `FailoverMiddleware` sends requests to alternate origins when the preferred one fails.
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+use reqwest_retry::{DefaultRetryableStrategy, Retryable, RetryableStrategy};
+
+use crate::origin::{Origin, ServedBy};
+
+/// `FailoverMiddleware` retries requests against an ordered list of origins: if the first origin
+/// fails in a transient manner (see [`RetryableStrategy`]), the same request is sent to the
+/// second one, and so on until one succeeds or all of them have been tried.
+///
+/// Only requests addressed to one of the configured origins are affected. Every such request
+/// is first sent to the first origin in the list, regardless of which of the origins it was
+/// addressed to; only the scheme, host and port of the URL are rewritten. The origin that served
+/// the response is recorded as a [`ServedBy`] extension.
+///
+/// Requests with streaming bodies can't be replayed and are only sent to the first origin.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_routing::FailoverMiddleware;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let failover = FailoverMiddleware::new([
+///     "https://api-eu.example.com".parse()?,
+///     "https://api-us.example.com".parse()?,
+/// ]);
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(failover)
+///     .build();
+///
+/// // Sent to api-us.example.com if api-eu.example.com is down.
+/// let req = client.get("https://api-eu.example.com/users");
+/// # Ok(())
+/// # }
+/// ```
+pub struct FailoverMiddleware<
+    R: RetryableStrategy + Send + Sync + 'static = DefaultRetryableStrategy,
+> {
+    origins: Vec<Origin>,
+    retryable_strategy: R,
+}
+
+impl FailoverMiddleware<DefaultRetryableStrategy> {
+    /// Construct `FailoverMiddleware` with origins in order of preference.
+    pub fn new(origins: impl IntoIterator<Item = Origin>) -> Self {
+        Self::new_with_strategy(origins, DefaultRetryableStrategy)
+    }
+}
+
+impl<R: RetryableStrategy + Send + Sync + 'static> FailoverMiddleware<R> {
+    /// Construct `FailoverMiddleware` with origins in order of preference and a
+    /// [retryable_strategy](RetryableStrategy) deciding which outcomes trigger a failover.
+    pub fn new_with_strategy(
+        origins: impl IntoIterator<Item = Origin>,
+        retryable_strategy: R,
+    ) -> Self {
+        Self {
+            origins: origins.into_iter().collect(),
+            retryable_strategy,
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<R> Middleware for FailoverMiddleware<R>
+where
+    R: RetryableStrategy + Send + Sync + 'static,
+{
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !self.origins.iter().any(|origin| origin.matches(req.url())) {
+            return next.run(req, extensions).await;
+        }
+
+        let mut origins = self.origins.iter().peekable();
+        let mut req = Some(req);
+        while let Some(origin) = origins.next() {
+            let original = req.take().expect("request is present");
+            let mut attempt = match original.try_clone() {
+                Some(attempt) => {
+                    req = Some(original);
+                    attempt
+                }
+                None => original,
+            };
+            origin.apply(attempt.url_mut());
+
+            let result = next.clone().run(attempt, extensions).await;
+            let can_fail_over = req.is_some() && origins.peek().is_some();
+            if can_fail_over
+                && self.retryable_strategy.handle(&result) == Some(Retryable::Transient)
+            {
+                tracing::warn!(
+                    "Request to {} failed, failing over to the next origin",
+                    origin
+                );
+                continue;
+            }
+
+            let served_by = ServedBy(origin.clone());
+            extensions.insert(served_by.clone());
+            return result.map(|mut res| {
+                res.extensions_mut().insert(served_by);
+                res
+            });
+        }
+        unreachable!("a request matching an origin is always sent at least once")
+    }
+}
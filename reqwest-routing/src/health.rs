@@ -0,0 +1,137 @@
+//! `HealthCheck` actively probes the origins of an [`OriginPool`].
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+
+use crate::pool::OriginPool;
+
+/// `HealthCheck` periodically sends a `GET` request to a path on every origin of an
+/// [`OriginPool`], and marks origins healthy or unhealthy based on the response status.
+///
+/// An origin becomes unhealthy after `unhealthy_threshold` consecutive failed probes (any
+/// non-2xx status or transport error), and healthy again after `healthy_threshold` consecutive
+/// successful ones. Unhealthy origins are not picked by
+/// [`LoadBalancerMiddleware`](crate::LoadBalancerMiddleware). Use [`OriginPool::is_healthy`] or
+/// [`OriginPool::status`] to query the current health.
+///
+/// Probes are sent with a plain [`reqwest::Client`] rather than through the middleware stack, so
+/// they don't count towards the passive failure accounting of the pool.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use reqwest_routing::{HealthCheck, OriginPool};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = Arc::new(OriginPool::new([
+///     "https://api-1.example.com".parse()?,
+///     "https://api-2.example.com".parse()?,
+/// ]));
+///
+/// // Probes stop when the handle is dropped.
+/// let _handle = HealthCheck::new("/healthz")
+///     .with_interval(Duration::from_secs(5))
+///     .spawn(reqwest::Client::new(), pool.clone());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HealthCheck {
+    path: String,
+    interval: Duration,
+    timeout: Duration,
+    healthy_threshold: u32,
+    unhealthy_threshold: u32,
+}
+
+impl HealthCheck {
+    /// Construct `HealthCheck` probing `path` every 10 seconds with a 2 second timeout, flipping
+    /// health after 2 consecutive successes or 3 consecutive failures.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(2),
+            healthy_threshold: 2,
+            unhealthy_threshold: 3,
+        }
+    }
+
+    /// Set the time between probes.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the timeout of each probe.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the number of consecutive successful probes after which an origin is healthy.
+    pub fn with_healthy_threshold(mut self, threshold: u32) -> Self {
+        self.healthy_threshold = threshold.max(1);
+        self
+    }
+
+    /// Set the number of consecutive failed probes after which an origin is unhealthy.
+    pub fn with_unhealthy_threshold(mut self, threshold: u32) -> Self {
+        self.unhealthy_threshold = threshold.max(1);
+        self
+    }
+
+    /// Probes every origin of `pool` once.
+    pub async fn check(&self, client: &Client, pool: &OriginPool) {
+        let probes = pool.origins().iter().map(|origin| {
+            let url = format!("{}{}", origin, self.path);
+            let probe = client.get(url).timeout(self.timeout).send();
+            async move {
+                match probe.await {
+                    Ok(res) => res.status().is_success(),
+                    Err(e) => {
+                        tracing::debug!("Health check of {} failed: {}", origin, e);
+                        false
+                    }
+                }
+            }
+        });
+        let results = futures::future::join_all(probes).await;
+        for (index, success) in results.into_iter().enumerate() {
+            pool.record_probe(
+                index,
+                success,
+                self.healthy_threshold,
+                self.unhealthy_threshold,
+            );
+        }
+    }
+
+    /// Spawns a background task on the current Tokio runtime probing `pool` at the configured
+    /// interval, until the returned handle is dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn(self, client: Client, pool: Arc<OriginPool>) -> HealthCheckHandle {
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                self.check(&client, &pool).await;
+            }
+        });
+        HealthCheckHandle(task)
+    }
+}
+
+/// Handle to a background [`HealthCheck`] task, which is stopped when dropped.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct HealthCheckHandle(tokio::task::JoinHandle<()>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for HealthCheckHandle {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
@@ -0,0 +1,57 @@
+//! Middleware to route requests across multiple origins, built on [`reqwest_middleware`].
+//!
+//! Use [`FailoverMiddleware`] to send requests to alternate origins when the preferred one is
+//! failing, or [`LoadBalancerMiddleware`] to spread requests across a pool of origins, optionally
+//! kept up to date by an active [`HealthCheck`]. [`ProxyPoolMiddleware`] sends each request
+//! through one of a [`ProxyPool`] of proxies, and [`TransportRouterMiddleware`] sends requests
+//! with a different client per host. [`ResolveOverrideMiddleware`] sends the requests to some
+//! hosts to fixed addresses.
+//!
+//! ## Feature flags
+//!
+//! * `unix`: `UnixSocketMiddleware` to send requests over Unix domain sockets, on Unix
+//!   platforms.
+//!
+//! ## Example
+//!
+//! ```
+//! use reqwest_middleware::ClientBuilder;
+//! use reqwest_routing::FailoverMiddleware;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(FailoverMiddleware::new([
+//!         "https://api-eu.example.com".parse()?,
+//!         "https://api-us.example.com".parse()?,
+//!     ]))
+//!     .build();
+//! # Ok(())
+//! # }
+//! ```
+
+mod balance;
+mod failover;
+mod health;
+mod origin;
+mod pool;
+mod proxy;
+mod resolve;
+mod transport;
+#[cfg(all(unix, feature = "unix"))]
+mod unix;
+
+pub use balance::LoadBalancerMiddleware;
+pub use failover::FailoverMiddleware;
+pub use health::HealthCheck;
+#[cfg(not(target_arch = "wasm32"))]
+pub use health::HealthCheckHandle;
+pub use origin::{InvalidOrigin, Origin, ServedBy};
+pub use pool::{BalanceStrategy, OriginGuard, OriginPool, OriginStatus};
+pub use proxy::{
+    ProxyPool, ProxyPoolMiddleware, ProxySelection, ProxyStatus, ServedByProxy, UnknownProxy,
+    UseProxy,
+};
+pub use resolve::{ResolveOverrideMiddleware, ResolveTo};
+pub use transport::TransportRouterMiddleware;
+#[cfg(all(unix, feature = "unix"))]
+pub use unix::{UnixSocketMiddleware, UnknownSocket};
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::str::FromStr;
+
+use reqwest::Url;
+use thiserror::Error;
+
+/// The scheme, host and port that requests can be routed to.
+///
+/// ```
+/// use reqwest_routing::Origin;
+///
+/// let origin: Origin = "https://api-eu.example.com".parse().unwrap();
+/// assert_eq!(origin.to_string(), "https://api-eu.example.com:443");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Origin {
+    scheme: String,
+    host: String,
+    port: u16,
+}
+
+/// Error returned when parsing an [`Origin`].
+#[derive(Debug, Error)]
+pub enum InvalidOrigin {
+    #[error("Invalid origin URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("Origin URL must have a host and a known port: {0}")]
+    MissingHostOrPort(Url),
+}
+
+impl Origin {
+    /// Create an origin from the scheme, host and port of `url`.
+    pub fn from_url(url: &Url) -> Result<Self, InvalidOrigin> {
+        match (url.host_str(), url.port_or_known_default()) {
+            (Some(host), Some(port)) => Ok(Self {
+                scheme: url.scheme().to_owned(),
+                host: host.to_owned(),
+                port,
+            }),
+            _ => Err(InvalidOrigin::MissingHostOrPort(url.clone())),
+        }
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns true if `url` points at this origin.
+    pub fn matches(&self, url: &Url) -> bool {
+        url.scheme() == self.scheme
+            && url.host_str() == Some(self.host.as_str())
+            && url.port_or_known_default() == Some(self.port)
+    }
+
+    /// Rewrites the scheme, host and port of `url` to point at this origin.
+    pub fn apply(&self, url: &mut Url) {
+        // These can only fail for URLs that can't be used with reqwest anyway (e.g. `data:`).
+        let _ = url.set_scheme(&self.scheme);
+        let _ = url.set_host(Some(&self.host));
+        let _ = url.set_port(Some(self.port));
+    }
+}
+
+impl FromStr for Origin {
+    type Err = InvalidOrigin;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_url(&Url::parse(s)?)
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}:{}", self.scheme, self.host, self.port)
+    }
+}
+
+/// Extension inserted by the routing middleware (in both the request extensions and the
+/// [response extensions](reqwest::Response::extensions)) recording which origin served the
+/// response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServedBy(pub Origin);
@@ -0,0 +1,251 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+
+use crate::origin::Origin;
+
+/// How [`OriginPool::select`] picks between available origins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BalanceStrategy {
+    /// Cycle through the origins in order.
+    RoundRobin,
+    /// Pick the origin with the fewest requests in flight.
+    LeastOutstanding,
+}
+
+/// A snapshot of the state of an origin in an [`OriginPool`].
+#[derive(Clone, Debug)]
+pub struct OriginStatus {
+    pub origin: Origin,
+    /// Number of requests currently in flight.
+    pub outstanding: usize,
+    /// Number of consecutive failed requests.
+    pub consecutive_failures: u32,
+    /// Whether the origin is currently ejected from the pool.
+    pub ejected: bool,
+    /// The result of active health checks, or `None` if the origin hasn't been checked.
+    ///
+    /// See [`HealthCheck`](crate::HealthCheck).
+    pub healthy: Option<bool>,
+}
+
+/// A set of interchangeable origins shared by [`LoadBalancerMiddleware`], with per-origin
+/// health accounting.
+///
+/// Origins that fail `max_failures` times in a row are ejected from the pool and re-admitted
+/// once `cooldown` has elapsed. Origins reported unhealthy by a [`HealthCheck`] are also left
+/// out until they are reported healthy again. If no origin is available, requests are spread
+/// across all of them rather than failing outright.
+///
+/// [`HealthCheck`]: crate::HealthCheck
+/// [`LoadBalancerMiddleware`]: crate::LoadBalancerMiddleware
+#[derive(Debug)]
+pub struct OriginPool {
+    origins: Vec<Origin>,
+    states: Mutex<Vec<OriginState>>,
+    next: AtomicUsize,
+    max_failures: u32,
+    cooldown: Duration,
+}
+
+#[derive(Debug, Default)]
+struct OriginState {
+    outstanding: usize,
+    consecutive_failures: u32,
+    ejected_until: Option<Instant>,
+    health: Health,
+}
+
+/// Result of active health checks, with counters for the thresholds.
+#[derive(Debug, Default)]
+struct Health {
+    healthy: Option<bool>,
+    consecutive_successes: u32,
+    consecutive_failures: u32,
+}
+
+impl OriginState {
+    fn is_available(&mut self, now: Instant) -> bool {
+        if self.health.healthy == Some(false) {
+            return false;
+        }
+        match self.ejected_until {
+            Some(until) if until > now => false,
+            Some(_) => {
+                // The cool-down elapsed: give the origin another chance.
+                self.ejected_until = None;
+                self.consecutive_failures = 0;
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+impl OriginPool {
+    /// Create a pool ejecting origins after 5 consecutive failures for 30 seconds.
+    pub fn new(origins: impl IntoIterator<Item = Origin>) -> Self {
+        let origins: Vec<_> = origins.into_iter().collect();
+        let states = origins.iter().map(|_| OriginState::default()).collect();
+        Self {
+            origins,
+            states: Mutex::new(states),
+            next: AtomicUsize::new(0),
+            max_failures: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the number of consecutive failures after which an origin is ejected.
+    pub fn with_max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures.max(1);
+        self
+    }
+
+    /// Set how long an ejected origin stays out of the pool.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// The origins in this pool.
+    pub fn origins(&self) -> &[Origin] {
+        &self.origins
+    }
+
+    /// Returns true if `url` points at one of the origins in this pool.
+    pub fn contains(&self, url: &Url) -> bool {
+        self.origins.iter().any(|origin| origin.matches(url))
+    }
+
+    /// Returns a snapshot of the state of every origin.
+    pub fn status(&self) -> Vec<OriginStatus> {
+        let now = Instant::now();
+        let states = self.states.lock().expect("pool lock poisoned");
+        self.origins
+            .iter()
+            .zip(states.iter())
+            .map(|(origin, state)| OriginStatus {
+                origin: origin.clone(),
+                outstanding: state.outstanding,
+                consecutive_failures: state.consecutive_failures,
+                ejected: state.ejected_until.is_some_and(|until| until > now),
+                healthy: state.health.healthy,
+            })
+            .collect()
+    }
+
+    /// Returns the result of active health checks for `origin`, or `None` if it hasn't been
+    /// checked or isn't part of this pool.
+    pub fn is_healthy(&self, origin: &Origin) -> Option<bool> {
+        let index = self.origins.iter().position(|o| o == origin)?;
+        let states = self.states.lock().expect("pool lock poisoned");
+        states[index].health.healthy
+    }
+
+    /// Records the result of a health check probe of the origin at `index`, flipping its health
+    /// once the relevant threshold of consecutive results is reached.
+    pub(crate) fn record_probe(
+        &self,
+        index: usize,
+        success: bool,
+        healthy_threshold: u32,
+        unhealthy_threshold: u32,
+    ) {
+        let mut states = self.states.lock().expect("pool lock poisoned");
+        let health = &mut states[index].health;
+        if success {
+            health.consecutive_successes += 1;
+            health.consecutive_failures = 0;
+            if health.healthy != Some(true) && health.consecutive_successes >= healthy_threshold {
+                tracing::info!("Origin {} is healthy", self.origins[index]);
+                health.healthy = Some(true);
+            }
+        } else {
+            health.consecutive_failures += 1;
+            health.consecutive_successes = 0;
+            if health.healthy != Some(false) && health.consecutive_failures >= unhealthy_threshold {
+                tracing::warn!("Origin {} is unhealthy", self.origins[index]);
+                health.healthy = Some(false);
+            }
+        }
+    }
+
+    /// Picks an origin for a new request. The returned guard must be kept alive for as long as
+    /// the request is in flight, and used to report its outcome.
+    ///
+    /// Returns `None` if the pool is empty.
+    pub fn select(self: &Arc<Self>, strategy: BalanceStrategy) -> Option<OriginGuard> {
+        if self.origins.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut states = self.states.lock().expect("pool lock poisoned");
+        let len = states.len();
+        let mut candidates: Vec<usize> = (0..len)
+            .map(|i| (start + i) % len)
+            .filter(|&i| states[i].is_available(now))
+            .collect();
+        if candidates.is_empty() {
+            // Everything is ejected: better to try something than nothing.
+            candidates = (0..len).map(|i| (start + i) % len).collect();
+        }
+        let index = match strategy {
+            BalanceStrategy::RoundRobin => candidates[0],
+            BalanceStrategy::LeastOutstanding => candidates
+                .into_iter()
+                .min_by_key(|&i| states[i].outstanding)
+                .expect("candidates are not empty"),
+        };
+        states[index].outstanding += 1;
+        Some(OriginGuard {
+            pool: self.clone(),
+            index,
+        })
+    }
+
+    /// Records the outcome of a request to the origin at `index`.
+    fn record(&self, index: usize, success: bool) {
+        let mut states = self.states.lock().expect("pool lock poisoned");
+        let state = &mut states[index];
+        if success {
+            state.consecutive_failures = 0;
+        } else {
+            state.consecutive_failures += 1;
+            if state.consecutive_failures >= self.max_failures && state.ejected_until.is_none() {
+                tracing::warn!("Ejecting origin {} from the pool", self.origins[index]);
+                state.ejected_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+}
+
+/// An origin selected by [`OriginPool::select`], counted as having a request in flight until
+/// dropped.
+#[derive(Debug)]
+pub struct OriginGuard {
+    pool: Arc<OriginPool>,
+    index: usize,
+}
+
+impl OriginGuard {
+    /// The selected origin.
+    pub fn origin(&self) -> &Origin {
+        &self.pool.origins[self.index]
+    }
+
+    /// Reports whether the request succeeded.
+    pub fn record(&self, success: bool) {
+        self.pool.record(self.index, success);
+    }
+}
+
+impl Drop for OriginGuard {
+    fn drop(&mut self) {
+        let mut states = self.pool.states.lock().expect("pool lock poisoned");
+        states[self.index].outstanding -= 1;
+    }
+}
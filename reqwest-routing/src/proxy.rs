@@ -0,0 +1,308 @@
+//! `ProxyPoolMiddleware` sends each request through one of a pool of proxies.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::{Extensions, StatusCode};
+use reqwest::{Client, Request, Response};
+use reqwest_middleware::{DryRun, Error, Middleware, Next, Result};
+use thiserror::Error;
+
+/// How [`ProxyPoolMiddleware`] picks the proxy of a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProxySelection {
+    /// Cycle through the proxies in order.
+    RoundRobin,
+    /// Always send the requests to a host through the same proxy, while it's available.
+    StickyByHost,
+}
+
+/// A request extension sending the request through the proxy with the given name, whether it's
+/// banned or not.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UseProxy(pub String);
+
+/// An extension (both request and [response
+/// extensions](reqwest::Response::extensions)) recording the name of the proxy a request was
+/// sent through.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServedByProxy(pub String);
+
+/// Error returned by [`ProxyPoolMiddleware`] when a [`UseProxy`] extension names a proxy which
+/// isn't in the pool.
+#[derive(Debug, Error)]
+#[error("Unknown proxy `{0}`")]
+pub struct UnknownProxy(pub String);
+
+/// A snapshot of the state of a proxy in a [`ProxyPool`].
+#[derive(Clone, Debug)]
+pub struct ProxyStatus {
+    /// The name of the proxy.
+    pub name: String,
+    /// Number of requests sent through the proxy.
+    pub requests: u64,
+    /// Number of consecutive failed requests.
+    pub consecutive_failures: u32,
+    /// Whether the proxy is currently banned from the pool.
+    pub banned: bool,
+}
+
+#[derive(Debug, Default)]
+struct ProxyState {
+    requests: u64,
+    consecutive_failures: u32,
+    banned_until: Option<Instant>,
+}
+
+impl ProxyState {
+    fn is_available(&mut self, now: Instant) -> bool {
+        match self.banned_until {
+            Some(until) if until > now => false,
+            Some(_) => {
+                // The ban expired: give the proxy another chance.
+                self.banned_until = None;
+                self.consecutive_failures = 0;
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+/// A set of proxies shared by [`ProxyPoolMiddleware`], each with its own pre-built
+/// [`reqwest::Client`], since reqwest fixes proxies when clients are built.
+///
+/// Requests fail through a proxy when they fail to be sent, or when they get a response whose
+/// status suggests the proxy was blocked, `403 Forbidden`, `407 Proxy Authentication Required`
+/// and `429 Too Many Requests` by default. Proxies failing `max_failures` times in a row are
+/// banned from the pool for the ban duration. If all proxies are banned, requests are spread
+/// across all of them rather than failing outright.
+#[derive(Debug)]
+pub struct ProxyPool {
+    proxies: Vec<(String, Client)>,
+    states: Mutex<Vec<ProxyState>>,
+    next: AtomicUsize,
+    max_failures: u32,
+    ban_duration: Duration,
+    ban_statuses: Vec<StatusCode>,
+}
+
+impl Default for ProxyPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProxyPool {
+    /// Construct an empty `ProxyPool`, banning proxies for 5 minutes after 3 consecutive
+    /// failures.
+    pub fn new() -> Self {
+        Self {
+            proxies: Vec::new(),
+            states: Mutex::new(Vec::new()),
+            next: AtomicUsize::new(0),
+            max_failures: 3,
+            ban_duration: Duration::from_secs(5 * 60),
+            ban_statuses: vec![
+                StatusCode::FORBIDDEN,
+                StatusCode::PROXY_AUTHENTICATION_REQUIRED,
+                StatusCode::TOO_MANY_REQUESTS,
+            ],
+        }
+    }
+
+    /// Add a proxy called `name`, sending requests with `client`, which should be configured
+    /// with the proxy.
+    pub fn with_proxy(mut self, name: impl Into<String>, client: Client) -> Self {
+        self.proxies.push((name.into(), client));
+        self.states
+            .get_mut()
+            .expect("proxy states lock poisoned")
+            .push(ProxyState::default());
+        self
+    }
+
+    /// Add a proxy for all requests at `url`, named after it, with an otherwise default client.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_proxy_url(self, url: &str) -> reqwest::Result<Self> {
+        let client = Client::builder().proxy(reqwest::Proxy::all(url)?).build()?;
+        Ok(self.with_proxy(url, client))
+    }
+
+    /// Ban proxies after `max_failures` consecutive failures.
+    pub fn with_max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures.max(1);
+        self
+    }
+
+    /// Keep proxies out of the pool for `ban_duration` once banned.
+    pub fn with_ban_duration(mut self, ban_duration: Duration) -> Self {
+        self.ban_duration = ban_duration;
+        self
+    }
+
+    /// Count responses with one of `statuses` as failures of the proxy.
+    pub fn with_ban_statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.ban_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Ban the proxy called `name` right away, e.g. when a response shows it was blocked.
+    /// Returns false if there is no such proxy.
+    pub fn ban(&self, name: &str) -> bool {
+        let index = match self.index(name) {
+            Some(index) => index,
+            None => return false,
+        };
+        let mut states = self.states.lock().expect("proxy states lock poisoned");
+        states[index].banned_until = Some(Instant::now() + self.ban_duration);
+        true
+    }
+
+    /// The current state of each proxy.
+    pub fn statuses(&self) -> Vec<ProxyStatus> {
+        let now = Instant::now();
+        let states = self.states.lock().expect("proxy states lock poisoned");
+        self.proxies
+            .iter()
+            .zip(states.iter())
+            .map(|((name, _), state)| ProxyStatus {
+                name: name.clone(),
+                requests: state.requests,
+                consecutive_failures: state.consecutive_failures,
+                banned: state.banned_until.is_some_and(|until| until > now),
+            })
+            .collect()
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.proxies.iter().position(|(proxy, _)| proxy == name)
+    }
+
+    /// Pick the proxy for a request to `host`, preferring available ones.
+    fn select(&self, selection: ProxySelection, host: &str) -> Option<usize> {
+        let len = self.proxies.len();
+        if len == 0 {
+            return None;
+        }
+        let start = match selection {
+            ProxySelection::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % len,
+            ProxySelection::StickyByHost => {
+                let mut hasher = DefaultHasher::new();
+                host.hash(&mut hasher);
+                (hasher.finish() % len as u64) as usize
+            }
+        };
+        let now = Instant::now();
+        let mut states = self.states.lock().expect("proxy states lock poisoned");
+        let index = (0..len)
+            .map(|offset| (start + offset) % len)
+            .find(|&index| states[index].is_available(now))
+            .unwrap_or(start);
+        Some(index)
+    }
+
+    fn record(&self, index: usize, success: bool) {
+        let mut states = self.states.lock().expect("proxy states lock poisoned");
+        let state = &mut states[index];
+        state.requests += 1;
+        if success {
+            state.consecutive_failures = 0;
+            return;
+        }
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.max_failures && state.banned_until.is_none() {
+            tracing::warn!(
+                "Banning proxy `{}` after {} consecutive failures",
+                self.proxies[index].0,
+                state.consecutive_failures
+            );
+            state.banned_until = Some(Instant::now() + self.ban_duration);
+        }
+    }
+}
+
+/// `ProxyPoolMiddleware` sends each request through a proxy of a [`ProxyPool`], picked by the
+/// [`ProxySelection`] or a [`UseProxy`] extension, for per-request proxy rotation.
+///
+/// The request is sent with the client of the proxy rather than the one of the
+/// `ClientWithMiddleware`, so `ProxyPoolMiddleware` must be the last middleware: middleware
+/// added after it doesn't run. The proxy used is recorded as a [`ServedByProxy`] extension.
+/// Requests are sent with the client of the `ClientWithMiddleware` if the pool is empty.
+///
+/// ```
+/// use std::sync::Arc;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_routing::{ProxyPool, ProxyPoolMiddleware, ProxySelection};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = ProxyPool::new()
+///     .with_proxy_url("http://proxy-1.example.com:3128")?
+///     .with_proxy_url("http://proxy-2.example.com:3128")?;
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(ProxyPoolMiddleware::new(Arc::new(pool), ProxySelection::StickyByHost))
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProxyPoolMiddleware {
+    pool: Arc<ProxyPool>,
+    selection: ProxySelection,
+}
+
+impl ProxyPoolMiddleware {
+    /// Construct `ProxyPoolMiddleware` over `pool`.
+    pub fn new(pool: Arc<ProxyPool>, selection: ProxySelection) -> Self {
+        Self { pool, selection }
+    }
+
+    /// The pool of proxies requests are sent through.
+    pub fn pool(&self) -> &Arc<ProxyPool> {
+        &self.pool
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ProxyPoolMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if extensions.get::<DryRun>().is_some() {
+            return next.run(req, extensions).await;
+        }
+        let index = match extensions.get::<UseProxy>() {
+            Some(UseProxy(name)) => Some(
+                self.pool
+                    .index(name)
+                    .ok_or_else(|| Error::middleware(UnknownProxy(name.clone())))?,
+            ),
+            None => self
+                .pool
+                .select(self.selection, req.url().host_str().unwrap_or_default()),
+        };
+        let index = match index {
+            Some(index) => index,
+            None => return next.run(req, extensions).await,
+        };
+        let (name, client) = &self.pool.proxies[index];
+
+        let result = client.execute(req).await.map_err(Error::from);
+        let success = result
+            .as_ref()
+            .is_ok_and(|res| !self.pool.ban_statuses.contains(&res.status()));
+        self.pool.record(index, success);
+
+        let served_by = ServedByProxy(name.clone());
+        extensions.insert(served_by.clone());
+        result.map(|mut res| {
+            res.extensions_mut().insert(served_by);
+            res
+        })
+    }
+}
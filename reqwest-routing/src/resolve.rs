@@ -0,0 +1,101 @@
+//! `ResolveOverrideMiddleware` sends requests for some hosts to fixed addresses.
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use http::header::HOST;
+use http::{Extensions, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+/// A request extension sending the request to the given address, whatever its host, with
+/// [`ResolveOverrideMiddleware`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResolveTo(pub SocketAddr);
+
+/// `ResolveOverrideMiddleware` sends the requests to some hosts to fixed IP addresses and ports,
+/// e.g. to test the green deployment of a blue/green pair, or to reach a server behind a load
+/// balancer directly.
+///
+/// The host and port of the request URL are replaced by the address, and the `Host` header is
+/// set to the original host, unless the request already has one. Addresses are set for a host
+/// with [`with_override`], or for a single request with a [`ResolveTo`] extension, which takes
+/// precedence.
+///
+/// Since the URL is rewritten, `https` requests are checked against certificates for the IP
+/// address rather than the host; use [`reqwest::ClientBuilder::resolve`] for them instead. Later
+/// middleware and the response see the rewritten URL.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_routing::ResolveOverrideMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(ResolveOverrideMiddleware::new().with_override(
+///         "api.example.com",
+///         "10.0.3.7:8080".parse().unwrap(),
+///     ))
+///     .build();
+/// ```
+///
+/// [`with_override`]: Self::with_override
+#[derive(Clone, Debug, Default)]
+pub struct ResolveOverrideMiddleware {
+    overrides: HashMap<String, SocketAddr>,
+}
+
+impl ResolveOverrideMiddleware {
+    /// Construct `ResolveOverrideMiddleware` without overrides.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send requests to `host` to `addr`.
+    pub fn with_override(mut self, host: &str, addr: SocketAddr) -> Self {
+        self.overrides.insert(host.to_ascii_lowercase(), addr);
+        self
+    }
+
+    /// The address requests to `host` are sent to, if overridden.
+    pub fn address(&self, host: &str) -> Option<SocketAddr> {
+        self.overrides.get(&host.to_ascii_lowercase()).copied()
+    }
+}
+
+/// Point `req` at `addr`, keeping its original authority in the `Host` header.
+fn redirect_to(req: &mut Request, addr: SocketAddr) {
+    let url = req.url_mut();
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => return,
+    };
+    if url.set_ip_host(addr.ip()).is_err() || url.set_port(Some(addr.port())).is_err() {
+        return;
+    }
+    tracing::debug!("Sending request for `{}` to {}", host, addr);
+    if !req.headers().contains_key(HOST) {
+        if let Ok(host) = HeaderValue::from_str(&host) {
+            req.headers_mut().insert(HOST, host);
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ResolveOverrideMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let addr = match extensions.get::<ResolveTo>() {
+            Some(ResolveTo(addr)) => Some(*addr),
+            None => req.url().host_str().and_then(|host| self.address(host)),
+        };
+        if let Some(addr) = addr {
+            redirect_to(&mut req, addr);
+        }
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,107 @@
+//! `TransportRouterMiddleware` sends requests with a different client per host.
+use http::Extensions;
+use reqwest::{Client, Request, Response};
+use reqwest_middleware::{DryRun, Error, Middleware, Next, Result};
+
+#[derive(Debug)]
+struct Route {
+    host: String,
+    wildcard: bool,
+    client: Client,
+}
+
+impl Route {
+    fn matches(&self, host: &str) -> bool {
+        if !self.wildcard {
+            return host == self.host;
+        }
+        match host.strip_suffix(&self.host) {
+            Some(subdomain) => subdomain.len() > 1 && subdomain.ends_with('.'),
+            None => false,
+        }
+    }
+}
+
+/// `TransportRouterMiddleware` sends requests with a [`reqwest::Client`] chosen according to
+/// their host, so that one `ClientWithMiddleware` can use different TLS roots, client
+/// certificates, timeouts or proxies for different services, e.g. internal services requiring
+/// mutual TLS and the public internet.
+///
+/// Routes are registered for a host, such as `api.example.com`, or for all the subdomains of a
+/// domain with a `*.example.com` pattern, which doesn't match `example.com` itself. Exact hosts
+/// take precedence over patterns, and longer patterns over shorter ones, whatever the order
+/// they were added in. Requests matching no route are sent with the client of the
+/// `ClientWithMiddleware`.
+///
+/// Requests are sent with the client of their route rather than passed down the middleware
+/// stack, so `TransportRouterMiddleware` must be the last middleware.
+///
+/// ```
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_routing::TransportRouterMiddleware;
+///
+/// let internal = reqwest::Client::builder()
+///     .timeout(Duration::from_secs(2))
+///     .build()
+///     .unwrap();
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(TransportRouterMiddleware::new().with_route("*.internal.example.com", internal))
+///     .build();
+/// ```
+#[derive(Debug, Default)]
+pub struct TransportRouterMiddleware {
+    routes: Vec<Route>,
+}
+
+impl TransportRouterMiddleware {
+    /// Construct `TransportRouterMiddleware` without routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send requests to `host`, or matching the `*.domain` pattern, with `client`.
+    pub fn with_route(mut self, host: &str, client: Client) -> Self {
+        let host = host.to_ascii_lowercase();
+        let (host, wildcard) = match host.strip_prefix("*.") {
+            Some(domain) => (domain.to_owned(), true),
+            None => (host, false),
+        };
+        self.routes.push(Route {
+            host,
+            wildcard,
+            client,
+        });
+        self
+    }
+
+    /// The client requests to `host` are sent with, or `None` if they are sent with the client
+    /// of the `ClientWithMiddleware`.
+    pub fn route(&self, host: &str) -> Option<&Client> {
+        let host = host.to_ascii_lowercase();
+        self.routes
+            .iter()
+            .filter(|route| route.matches(&host))
+            .max_by_key(|route| (!route.wildcard, route.host.len()))
+            .map(|route| &route.client)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for TransportRouterMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if extensions.get::<DryRun>().is_some() {
+            return next.run(req, extensions).await;
+        }
+        match self.route(req.url().host_str().unwrap_or_default()) {
+            Some(client) => client.execute(req).await.map_err(Error::from),
+            None => next.run(req, extensions).await,
+        }
+    }
+}
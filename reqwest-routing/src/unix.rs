@@ -0,0 +1,118 @@
+//! `UnixSocketMiddleware` sends requests over Unix domain sockets.
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+use http::header::HOST;
+use http::{Extensions, HeaderValue};
+use hyper_util::rt::TokioIo;
+use reqwest::{Body, Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{DryRun, Error, Middleware, Next, Result};
+use thiserror::Error;
+use tokio::net::UnixStream;
+
+/// Error returned by [`UnixSocketMiddleware`] for `unix` requests to a host without socket.
+#[derive(Debug, Error)]
+#[error("No Unix socket for host `{0}`")]
+pub struct UnknownSocket(pub String);
+
+/// `UnixSocketMiddleware` sends the requests to some hosts over HTTP/1.1 on a Unix domain socket,
+/// e.g. to talk to the Docker daemon or to sidecars, while the rest of the middleware stack still
+/// applies to them.
+///
+/// Sockets are registered for a host name, which requests address with the `unix` scheme, such
+/// as `unix://docker/v1.43/containers/json`, or with any other scheme, so that middleware
+/// expecting `http` URLs keep working. Requests with the `unix` scheme to other hosts fail with
+/// [`UnknownSocket`], and the others are sent with the client of the `ClientWithMiddleware`.
+/// The `Host` header of requests sent over a socket defaults to the host of their URL.
+///
+/// Requests are sent over the socket rather than passed down the middleware stack, so
+/// `UnixSocketMiddleware` must be the last middleware.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_routing::UnixSocketMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(UnixSocketMiddleware::new().with_socket("docker", "/var/run/docker.sock"))
+///     .build();
+/// let request = client.get("unix://docker/v1.43/containers/json");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UnixSocketMiddleware {
+    sockets: HashMap<String, PathBuf>,
+}
+
+impl UnixSocketMiddleware {
+    /// Construct `UnixSocketMiddleware` without sockets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send requests to `host` over the socket at `path`.
+    pub fn with_socket(mut self, host: &str, path: impl Into<PathBuf>) -> Self {
+        self.sockets.insert(host.to_ascii_lowercase(), path.into());
+        self
+    }
+
+    /// The path of the socket requests to `host` are sent over, if any.
+    pub fn socket(&self, host: &str) -> Option<&Path> {
+        self.sockets
+            .get(&host.to_ascii_lowercase())
+            .map(PathBuf::as_path)
+    }
+
+    async fn send(&self, path: &Path, req: Request) -> Result<Response> {
+        let url = req.url().clone();
+        let mut req = http::Request::try_from(req)?;
+        *req.uri_mut() = url[url::Position::BeforePath..]
+            .parse()
+            .map_err(Error::middleware)?;
+        if !req.headers().contains_key(HOST) {
+            let host = HeaderValue::from_str(url.host_str().unwrap_or_default())
+                .map_err(Error::middleware)?;
+            req.headers_mut().insert(HOST, host);
+        }
+
+        let stream = UnixStream::connect(path).await.map_err(Error::middleware)?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(Error::middleware)?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                tracing::debug!("Unix socket connection failed: {}", err);
+            }
+        });
+        let res = sender.send_request(req).await.map_err(Error::middleware)?;
+
+        let (parts, body) = res.into_parts();
+        let mut res = http::Response::builder()
+            .url(url)
+            .body(Body::wrap(body))
+            .expect("response is valid");
+        *res.status_mut() = parts.status;
+        *res.version_mut() = parts.version;
+        *res.headers_mut() = parts.headers;
+        Ok(Response::from(res))
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for UnixSocketMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if extensions.get::<DryRun>().is_some() {
+            return next.run(req, extensions).await;
+        }
+        let host = req.url().host_str().unwrap_or_default().to_owned();
+        match self.socket(&host) {
+            Some(path) => self.send(path, req).await,
+            None if req.url().scheme() == "unix" => Err(Error::middleware(UnknownSocket(host))),
+            None => next.run(req, extensions).await,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use reqwest::Client;
+use reqwest_middleware::ClientBuilder;
+use reqwest_routing::{BalanceStrategy, LoadBalancerMiddleware, OriginPool};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_round_robin_across_origins() {
+    let first = MockServer::start().await;
+    let second = MockServer::start().await;
+    for server in [&first, &second] {
+        Mock::given(method("GET"))
+            .and(path("/foo"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(server)
+            .await;
+    }
+
+    let pool = OriginPool::new([first.uri().parse().unwrap(), second.uri().parse().unwrap()]);
+    let client = ClientBuilder::new(Client::new())
+        .with(LoadBalancerMiddleware::new(
+            Arc::new(pool),
+            BalanceStrategy::RoundRobin,
+        ))
+        .build();
+
+    for _ in 0..4 {
+        let resp = client
+            .get(format!("{}/foo", first.uri()))
+            .send()
+            .await
+            .expect("call failed");
+        assert_eq!(resp.status(), 200);
+    }
+}
+
+#[tokio::test]
+async fn assert_failing_origin_is_ejected() {
+    let failing = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&failing)
+        .await;
+    let healthy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(4)
+        .mount(&healthy)
+        .await;
+
+    let pool = Arc::new(
+        OriginPool::new([
+            failing.uri().parse().unwrap(),
+            healthy.uri().parse().unwrap(),
+        ])
+        .with_max_failures(1),
+    );
+    let client = ClientBuilder::new(Client::new())
+        .with(LoadBalancerMiddleware::new(
+            pool.clone(),
+            BalanceStrategy::RoundRobin,
+        ))
+        .build();
+
+    for _ in 0..5 {
+        let _ = client.get(format!("{}/foo", healthy.uri())).send().await;
+    }
+
+    let status = pool.status();
+    assert!(status[0].ejected);
+    assert!(!status[1].ejected);
+}
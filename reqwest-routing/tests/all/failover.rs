@@ -0,0 +1,75 @@
+use reqwest::Client;
+use reqwest_middleware::ClientBuilder;
+use reqwest_routing::{FailoverMiddleware, Origin, ServedBy};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_failover_to_next_origin() {
+    let primary = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    let secondary = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&secondary)
+        .await;
+
+    let secondary_origin: Origin = secondary.uri().parse().unwrap();
+    let client = ClientBuilder::new(Client::new())
+        .with(FailoverMiddleware::new([
+            primary.uri().parse().unwrap(),
+            secondary_origin.clone(),
+        ]))
+        .build();
+
+    let resp = client
+        .get(format!("{}/foo", primary.uri()))
+        .send()
+        .await
+        .expect("call failed");
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.extensions().get::<ServedBy>(),
+        Some(&ServedBy(secondary_origin))
+    );
+}
+
+#[tokio::test]
+async fn assert_no_failover_on_fatal_error() {
+    let primary = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/foo"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    let secondary = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&secondary)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(FailoverMiddleware::new([
+            primary.uri().parse().unwrap(),
+            secondary.uri().parse().unwrap(),
+        ]))
+        .build();
+
+    let resp = client
+        .get(format!("{}/foo", primary.uri()))
+        .send()
+        .await
+        .expect("call failed");
+
+    assert_eq!(resp.status(), 404);
+}
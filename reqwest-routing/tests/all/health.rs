@@ -0,0 +1,36 @@
+use reqwest::Client;
+use reqwest_routing::{HealthCheck, Origin, OriginPool};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_health_check_thresholds() {
+    let healthy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/healthz"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&healthy)
+        .await;
+    let unhealthy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/healthz"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&unhealthy)
+        .await;
+
+    let healthy_origin: Origin = healthy.uri().parse().unwrap();
+    let unhealthy_origin: Origin = unhealthy.uri().parse().unwrap();
+    let pool = OriginPool::new([healthy_origin.clone(), unhealthy_origin.clone()]);
+    let check = HealthCheck::new("/healthz")
+        .with_healthy_threshold(2)
+        .with_unhealthy_threshold(2);
+    let client = Client::new();
+
+    check.check(&client, &pool).await;
+    assert_eq!(pool.is_healthy(&healthy_origin), None);
+    assert_eq!(pool.is_healthy(&unhealthy_origin), None);
+
+    check.check(&client, &pool).await;
+    assert_eq!(pool.is_healthy(&healthy_origin), Some(true));
+    assert_eq!(pool.is_healthy(&unhealthy_origin), Some(false));
+}
@@ -0,0 +1,8 @@
+mod balance;
+mod failover;
+mod health;
+mod proxy;
+mod resolve;
+mod transport;
+#[cfg(all(unix, feature = "unix"))]
+mod unix;
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use reqwest_routing::{
+    ProxyPool, ProxyPoolMiddleware, ProxySelection, ServedByProxy, UnknownProxy, UseProxy,
+};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn pool() -> ProxyPool {
+    // Plain clients stand in for clients configured with proxies.
+    ProxyPool::new()
+        .with_proxy("a", reqwest::Client::new())
+        .with_proxy("b", reqwest::Client::new())
+        .with_max_failures(2)
+}
+
+fn client(pool: Arc<ProxyPool>, selection: ProxySelection) -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(ProxyPoolMiddleware::new(pool, selection))
+        .build()
+}
+
+async fn served_by(client: &ClientWithMiddleware, url: &str) -> String {
+    let res = client.get(url).send().await.unwrap();
+    res.extensions().get::<ServedByProxy>().unwrap().0.clone()
+}
+
+#[tokio::test]
+async fn rotates_proxies() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let pool = Arc::new(pool());
+    let client = client(pool.clone(), ProxySelection::RoundRobin);
+
+    let mut names = Vec::new();
+    for _ in 0..4 {
+        names.push(served_by(&client, &server.uri()).await);
+    }
+    assert_eq!(names, ["a", "b", "a", "b"]);
+    assert!(pool.statuses().iter().all(|status| status.requests == 2));
+}
+
+#[tokio::test]
+async fn sticks_to_a_proxy_per_host() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let client = client(Arc::new(pool()), ProxySelection::StickyByHost);
+
+    let first = served_by(&client, &server.uri()).await;
+    for _ in 0..3 {
+        assert_eq!(served_by(&client, &server.uri()).await, first);
+    }
+}
+
+#[tokio::test]
+async fn bans_failing_proxies() {
+    let server = MockServer::start().await;
+    Mock::given(path("/blocked"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+    Mock::given(path("/ok"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let pool = Arc::new(pool());
+    let client = client(pool.clone(), ProxySelection::RoundRobin);
+
+    for _ in 0..2 {
+        let req = client
+            .get(format!("{}/blocked", server.uri()))
+            .with_extension(UseProxy("a".to_owned()));
+        assert_eq!(req.send().await.unwrap().status(), 429);
+    }
+    let statuses = pool.statuses();
+    assert!(statuses[0].banned);
+    assert!(!statuses[1].banned);
+
+    let url = format!("{}/ok", server.uri());
+    for _ in 0..3 {
+        assert_eq!(served_by(&client, &url).await, "b");
+    }
+
+    assert!(pool.ban("b"));
+    assert!(!pool.ban("c"));
+    assert!(pool.statuses().iter().all(|status| status.banned));
+}
+
+#[tokio::test]
+async fn fails_for_unknown_proxies() {
+    let client = client(Arc::new(pool()), ProxySelection::RoundRobin);
+    let err = client
+        .get("http://localhost")
+        .with_extension(UseProxy("c".to_owned()))
+        .send()
+        .await
+        .unwrap_err();
+    match err {
+        Error::Middleware(err) => assert!(err.is::<UnknownProxy>()),
+        err => panic!("unexpected error: {}", err),
+    }
+}
@@ -0,0 +1,55 @@
+use reqwest_middleware::ClientBuilder;
+use reqwest_routing::{ResolveOverrideMiddleware, ResolveTo};
+use wiremock::matchers::{header, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn sends_overridden_hosts_to_address() {
+    let server = MockServer::start().await;
+    Mock::given(path("/users"))
+        .and(header("host", "api.example.com"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(ResolveOverrideMiddleware::new().with_override("API.example.com", *server.address()))
+        .build();
+
+    let res = client
+        .get("http://api.example.com/users")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.url().port(), Some(server.address().port()));
+}
+
+#[tokio::test]
+async fn keeps_port_of_original_host() {
+    let server = MockServer::start().await;
+    Mock::given(header("host", "blue.example.com:8443"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(ResolveOverrideMiddleware::new())
+        .build();
+
+    let res = client
+        .get("http://blue.example.com:8443/")
+        .with_extension(ResolveTo(*server.address()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 204);
+}
+
+#[test]
+fn looks_up_addresses_case_insensitively() {
+    let addr = "10.0.3.7:8080".parse().unwrap();
+    let resolve = ResolveOverrideMiddleware::new().with_override("api.example.com", addr);
+    assert_eq!(resolve.address("Api.Example.com"), Some(addr));
+    assert_eq!(resolve.address("example.com"), None);
+}
@@ -0,0 +1,54 @@
+use http::{HeaderMap, HeaderValue};
+use reqwest_middleware::ClientBuilder;
+use reqwest_routing::TransportRouterMiddleware;
+use wiremock::matchers::header;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn tagged_client(tag: &'static str) -> reqwest::Client {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-client", HeaderValue::from_static(tag));
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn prefers_exact_and_longer_routes() {
+    let router = TransportRouterMiddleware::new()
+        .with_route("*.example.com", reqwest::Client::new())
+        .with_route("*.internal.example.com", reqwest::Client::new())
+        .with_route("api.internal.example.com", reqwest::Client::new());
+    let route = |host| router.route(host).map(|client| client as *const _);
+    let routes = [
+        route("www.example.com"),
+        route("db.internal.example.com"),
+        route("API.internal.example.com"),
+    ];
+    assert!(routes.iter().all(Option::is_some));
+    assert_ne!(routes[0], routes[1]);
+    assert_ne!(routes[1], routes[2]);
+    assert!(route("example.com").is_none());
+}
+
+#[tokio::test]
+async fn routes_requests_by_host() {
+    let server = MockServer::start().await;
+    Mock::given(header("x-client", "internal"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(header("x-client", "default"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = ClientBuilder::new(tagged_client("default"))
+        .with(TransportRouterMiddleware::new().with_route("localhost", tagged_client("internal")))
+        .build();
+
+    let url = server.uri().replace("127.0.0.1", "localhost");
+    assert_eq!(client.get(url).send().await.unwrap().status(), 200);
+    assert_eq!(client.get(server.uri()).send().await.unwrap().status(), 204);
+}
@@ -0,0 +1,65 @@
+use reqwest_middleware::{ClientBuilder, Error};
+use reqwest_routing::{UnixSocketMiddleware, UnknownSocket};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixListener;
+
+/// Serves one request on a socket, answering with the request head as body.
+async fn echo_server(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "reqwest-routing-{}-{}.sock",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        let mut buf = [0; 1024];
+        while !head.ends_with(b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            head.extend_from_slice(&buf[..n]);
+        }
+        let res = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            head.len()
+        );
+        stream.write_all(res.as_bytes()).await.unwrap();
+        stream.write_all(&head).await.unwrap();
+    });
+    path
+}
+
+#[tokio::test]
+async fn sends_requests_over_socket() {
+    let path = echo_server("send").await;
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(UnixSocketMiddleware::new().with_socket("docker", &path))
+        .build();
+
+    let res = client
+        .get("unix://docker/v1.43/containers/json?all=true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.url().as_str(),
+        "unix://docker/v1.43/containers/json?all=true"
+    );
+    let head = res.text().await.unwrap().to_ascii_lowercase();
+    assert!(head.starts_with("get /v1.43/containers/json?all=true http/1.1\r\n"));
+    assert!(head.contains("host: docker\r\n"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn rejects_unix_requests_to_unknown_hosts() {
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(UnixSocketMiddleware::new().with_socket("docker", "/nonexistent.sock"))
+        .build();
+
+    let err = client.get("unix://podman/info").send().await.unwrap_err();
+    assert!(matches!(err, Error::Middleware(err) if err.is::<UnknownSocket>()));
+}
@@ -0,0 +1,339 @@
+//! `CassetteMiddleware` records exchanges to a cassette file and replays them in tests.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use http::header::{HeaderName, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION};
+use http::{Extensions, HeaderMap, HeaderValue, Method, StatusCode};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use serde::{Deserialize, Serialize};
+
+/// The request matched no interaction of the cassette being replayed.
+#[derive(Debug, thiserror::Error)]
+#[error("No interaction of the cassette matches {method} {url}")]
+pub struct UnexpectedRequest {
+    /// The method of the request.
+    pub method: Method,
+    /// The URL of the request.
+    pub url: Url,
+}
+
+/// What a request must have in common with a recorded one to be replayed its response, see
+/// [`CassetteMiddleware::with_matchers`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Match {
+    /// The method.
+    Method,
+    /// The whole URL.
+    Url,
+    /// The path of the URL.
+    Path,
+    /// The query of the URL.
+    Query,
+    /// The body.
+    Body,
+    /// The values of a header.
+    Header(HeaderName),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Body>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Body,
+}
+
+/// A body, as text when it is UTF-8 to keep cassettes readable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Body {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Body {
+    fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_owned()),
+            Err(_) => Self::Bytes(bytes.to_vec()),
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+fn recorded_headers(headers: &HeaderMap, redact: bool) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if redact
+                && (value.is_sensitive()
+                    || [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE].contains(name))
+            {
+                "[REDACTED]".to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+#[derive(Debug)]
+struct State {
+    cassette: Cassette,
+    replayed: Vec<bool>,
+}
+
+/// `CassetteMiddleware` records the requests of a client and their responses to a cassette, a
+/// JSON file, and replays them, so that tests don't depend on real APIs.
+///
+/// When recording, requests are sent, and each of them is added to the cassette along with its
+/// response. The values of the `Authorization`, `Proxy-Authorization` and `Cookie` headers are
+/// not recorded.
+///
+/// When replaying, requests aren't sent: the response of the first interaction of the cassette
+/// which matches them, and wasn't replayed yet, is returned instead. Requests match the
+/// interactions with the same method and URL by default, see
+/// [`with_matchers`](Self::with_matchers). Requests matching no interaction fail with an
+/// [`UnexpectedRequest`] error.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_testing::CassetteMiddleware;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// // Records the cassette on the first run, and replays it afterwards.
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(CassetteMiddleware::auto("tests/cassettes/users.json")?)
+///     .build();
+/// let users = client.get("https://api.example.com/users").send().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CassetteMiddleware {
+    path: PathBuf,
+    mode: Mode,
+    matchers: Vec<Match>,
+    playback_repeats: bool,
+    state: Mutex<State>,
+}
+
+impl CassetteMiddleware {
+    /// Construct `CassetteMiddleware` recording a new cassette to `path`, replacing any existing
+    /// one.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self::new(path.into(), Mode::Record, Cassette::default())
+    }
+
+    /// Construct `CassetteMiddleware` replaying the cassette at `path`.
+    pub fn replay(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let cassette = serde_json::from_slice(&fs::read(&path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Self::new(path, Mode::Replay, cassette))
+    }
+
+    /// Construct `CassetteMiddleware` replaying the cassette at `path` if it exists, recording it
+    /// otherwise.
+    pub fn auto(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path))
+        }
+    }
+
+    fn new(path: PathBuf, mode: Mode, cassette: Cassette) -> Self {
+        let replayed = vec![false; cassette.interactions.len()];
+        Self {
+            path,
+            mode,
+            matchers: vec![Match::Method, Match::Url],
+            playback_repeats: false,
+            state: Mutex::new(State { cassette, replayed }),
+        }
+    }
+
+    /// Replay the interactions matching requests on `matchers`, instead of their method and URL.
+    pub fn with_matchers(mut self, matchers: impl IntoIterator<Item = Match>) -> Self {
+        self.matchers = matchers.into_iter().collect();
+        self
+    }
+
+    /// Replay interactions any number of times, rather than once.
+    pub fn with_playback_repeats(mut self) -> Self {
+        self.playback_repeats = true;
+        self
+    }
+
+    fn matches(&self, req: &Request, recorded: &RecordedRequest) -> bool {
+        let recorded_url = match Url::parse(&recorded.url) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        self.matchers.iter().all(|matcher| match matcher {
+            Match::Method => req.method().as_str() == recorded.method,
+            Match::Url => *req.url() == recorded_url,
+            Match::Path => req.url().path() == recorded_url.path(),
+            Match::Query => req.url().query() == recorded_url.query(),
+            Match::Body => {
+                let body = req.body().and_then(|body| body.as_bytes());
+                body.unwrap_or_default()
+                    == recorded
+                        .body
+                        .as_ref()
+                        .map(Body::as_bytes)
+                        .unwrap_or_default()
+            }
+            Match::Header(name) => req
+                .headers()
+                .get_all(name)
+                .iter()
+                .map(|value| value.as_bytes())
+                .eq(recorded
+                    .headers
+                    .iter()
+                    .filter(|(recorded, _)| recorded == name.as_str())
+                    .map(|(_, value)| value.as_bytes())),
+        })
+    }
+
+    fn replay_response(&self, req: &Request) -> Result<Response> {
+        let mut state = self.state.lock().expect("cassette lock poisoned");
+        let State { cassette, replayed } = &mut *state;
+        let index = cassette
+            .interactions
+            .iter()
+            .zip(replayed.iter())
+            .position(|(interaction, replayed)| {
+                (self.playback_repeats || !replayed) && self.matches(req, &interaction.request)
+            })
+            .ok_or_else(|| {
+                Error::middleware(UnexpectedRequest {
+                    method: req.method().clone(),
+                    url: req.url().clone(),
+                })
+            })?;
+        replayed[index] = true;
+
+        let recorded = &cassette.interactions[index].response;
+        let mut res = http::Response::builder()
+            .url(req.url().clone())
+            .body(recorded.body.as_bytes().to_vec())
+            .expect("response is valid");
+        *res.status_mut() = StatusCode::from_u16(recorded.status).map_err(Error::middleware)?;
+        for (name, value) in &recorded.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(Error::middleware)?;
+            let value = HeaderValue::from_str(value).map_err(Error::middleware)?;
+            res.headers_mut().append(name, value);
+        }
+        Ok(Response::from(res))
+    }
+
+    async fn record_response(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let request = RecordedRequest {
+            method: req.method().to_string(),
+            url: req.url().to_string(),
+            headers: recorded_headers(req.headers(), true),
+            body: req.body().and_then(|body| body.as_bytes()).map(Body::new),
+        };
+
+        let mut res = next.run(req, extensions).await?;
+        let url = res.url().clone();
+        let status = res.status();
+        let version = res.version();
+        let headers = res.headers().clone();
+        let res_extensions = std::mem::take(res.extensions_mut());
+        let body = res.bytes().await?;
+
+        let interaction = Interaction {
+            request,
+            response: RecordedResponse {
+                status: status.as_u16(),
+                headers: recorded_headers(&headers, false),
+                body: Body::new(&body),
+            },
+        };
+        {
+            let mut state = self.state.lock().expect("cassette lock poisoned");
+            state.cassette.interactions.push(interaction);
+            save(&self.path, &state.cassette).map_err(Error::middleware)?;
+        }
+
+        let mut res = http::Response::builder()
+            .url(url)
+            .body(body)
+            .expect("response is valid");
+        *res.status_mut() = status;
+        *res.version_mut() = version;
+        *res.headers_mut() = headers;
+        res.extensions_mut().extend(res_extensions);
+        Ok(Response::from(res))
+    }
+}
+
+fn save(path: &Path, cassette: &Cassette) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_vec_pretty(cassette)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    fs::write(path, json)
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for CassetteMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        match self.mode {
+            Mode::Record => self.record_response(req, extensions, next).await,
+            Mode::Replay => self.replay_response(&req),
+        }
+    }
+}
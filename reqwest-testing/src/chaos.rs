@@ -0,0 +1,233 @@
+//! `ChaosMiddleware` injects faults into requests, to test the resilience of clients.
+use std::sync::Mutex;
+use std::time::Duration;
+
+use http::{Extensions, StatusCode};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use reqwest::{Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+
+/// Extension subjecting a request to the faults of a [`ChaosMiddleware`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnableChaos;
+
+/// The error of requests failed by a [`ChaosMiddleware`], as if their connection was reset.
+#[derive(Debug, thiserror::Error)]
+#[error("Connection reset by chaos middleware")]
+pub struct InjectedConnectionReset;
+
+/// `ChaosMiddleware` injects faults into requests, with configurable probabilities, to test how
+/// clients and middleware stacks cope with unreliable servers:
+///
+/// - latency, added before sending requests, see [`with_latency`](Self::with_latency),
+/// - [`InjectedConnectionReset`] errors instead of sending requests, see
+///   [`with_connection_resets`](Self::with_connection_resets),
+/// - error responses, e.g. `500` or `429`, instead of sending requests, see
+///   [`with_status`](Self::with_status),
+/// - response bodies cut short, see [`with_truncated_bodies`](Self::with_truncated_bodies).
+///
+/// Only the requests with the [`EnableChaos`] extension are affected, so that the traffic of a
+/// test can be singled out, unless [`with_all_requests`](Self::with_all_requests) is set. The
+/// faults are drawn from a random generator which can be seeded, see
+/// [`with_seed`](Self::with_seed), to reproduce a run.
+///
+/// Note that the injected errors are [`Error::Middleware`] errors, which reqwest-retry doesn't
+/// retry by default.
+///
+/// ```
+/// use http::StatusCode;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_testing::{ChaosMiddleware, EnableChaos};
+/// use std::time::Duration;
+///
+/// # async fn example() {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(
+///         ChaosMiddleware::new()
+///             .with_seed(42)
+///             .with_latency(0.5, Duration::from_millis(200))
+///             .with_status(0.1, StatusCode::SERVICE_UNAVAILABLE),
+///     )
+///     .build();
+/// let res = client
+///     .get("https://api.example.com/users")
+///     .with_extension(EnableChaos)
+///     .send()
+///     .await;
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ChaosMiddleware {
+    rng: Mutex<StdRng>,
+    all_requests: bool,
+    latency: Option<(f64, Duration)>,
+    connection_resets: f64,
+    statuses: Vec<(f64, StatusCode)>,
+    truncated_bodies: f64,
+}
+
+impl ChaosMiddleware {
+    /// Construct `ChaosMiddleware` injecting no fault, with a randomly seeded generator.
+    pub fn new() -> Self {
+        Self {
+            rng: Mutex::new(StdRng::from_entropy()),
+            all_requests: false,
+            latency: None,
+            connection_resets: 0.0,
+            statuses: Vec::new(),
+            truncated_bodies: 0.0,
+        }
+    }
+
+    /// Seed the random generator with `seed`, so that the same faults are injected on each run
+    /// with the same requests.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    /// Affect all requests, not only the ones with the [`EnableChaos`] extension.
+    pub fn with_all_requests(mut self) -> Self {
+        self.all_requests = true;
+        self
+    }
+
+    /// Delay requests by `latency` with `probability`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn with_latency(mut self, probability: f64, latency: Duration) -> Self {
+        self.latency = Some((check_probability(probability), latency));
+        self
+    }
+
+    /// Fail requests with an [`InjectedConnectionReset`] error with `probability`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn with_connection_resets(mut self, probability: f64) -> Self {
+        self.connection_resets = check_probability(probability);
+        self
+    }
+
+    /// Answer requests with an empty `status` response with `probability`. Can be called
+    /// several times, for different statuses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn with_status(mut self, probability: f64, status: StatusCode) -> Self {
+        self.statuses.push((check_probability(probability), status));
+        self
+    }
+
+    /// Cut the bodies of responses short, to a random length, with `probability`. Their headers
+    /// are left untouched, including `Content-Length`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn with_truncated_bodies(mut self, probability: f64) -> Self {
+        self.truncated_bodies = check_probability(probability);
+        self
+    }
+
+    fn draw(&self, probability: f64) -> bool {
+        probability > 0.0
+            && self
+                .rng
+                .lock()
+                .expect("chaos lock poisoned")
+                .gen_bool(probability)
+    }
+}
+
+impl Default for ChaosMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn check_probability(probability: f64) -> f64 {
+    assert!(
+        (0.0..=1.0).contains(&probability),
+        "probability {} isn't between 0 and 1",
+        probability
+    );
+    probability
+}
+
+async fn sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(duration).await;
+    #[cfg(target_arch = "wasm32")]
+    wasm_timer::Delay::new(duration)
+        .await
+        .expect("failed sleeping");
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ChaosMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !self.all_requests && extensions.get::<EnableChaos>().is_none() {
+            return next.run(req, extensions).await;
+        }
+
+        if let Some((probability, latency)) = self.latency {
+            if self.draw(probability) {
+                sleep(latency).await;
+            }
+        }
+        if self.draw(self.connection_resets) {
+            return Err(Error::middleware(InjectedConnectionReset));
+        }
+        for &(probability, status) in &self.statuses {
+            if self.draw(probability) {
+                let mut res = http::Response::builder()
+                    .url(req.url().clone())
+                    .body(Vec::new())
+                    .expect("response is valid");
+                *res.status_mut() = status;
+                return Ok(Response::from(res));
+            }
+        }
+
+        let mut res = next.run(req, extensions).await?;
+        if !self.draw(self.truncated_bodies) {
+            return Ok(res);
+        }
+        let url = res.url().clone();
+        let status = res.status();
+        let version = res.version();
+        let headers = res.headers().clone();
+        let res_extensions = std::mem::take(res.extensions_mut());
+        let mut body = res.bytes().await?;
+        if !body.is_empty() {
+            let len = self
+                .rng
+                .lock()
+                .expect("chaos lock poisoned")
+                .gen_range(0..body.len());
+            body.truncate(len);
+        }
+
+        let mut res = http::Response::builder()
+            .url(url)
+            .body(body)
+            .expect("response is valid");
+        *res.status_mut() = status;
+        *res.version_mut() = version;
+        *res.headers_mut() = headers;
+        res.extensions_mut().extend(res_extensions);
+        Ok(Response::from(res))
+    }
+}
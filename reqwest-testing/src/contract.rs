@@ -0,0 +1,614 @@
+//! `ContractMiddleware` checks requests and responses against an OpenAPI document.
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use http::header::CONTENT_TYPE;
+use http::{Extensions, HeaderMap, Method, StatusCode};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use serde_json::{Map, Value};
+
+/// How deep `$ref`s and combined schemas are followed, to stop on circular documents.
+const MAX_DEPTH: usize = 64;
+
+/// An [OpenAPI 3.x](https://spec.openapis.org/oas/v3.1.0) document, in JSON, describing the API
+/// checked by a [`ContractMiddleware`].
+#[derive(Clone, Debug)]
+pub struct OpenApiSpec {
+    document: Arc<Value>,
+    base_path: String,
+}
+
+impl OpenApiSpec {
+    /// Parse an OpenAPI document from `json`.
+    pub fn from_json(json: &[u8]) -> io::Result<Self> {
+        let document: Value = serde_json::from_slice(json)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let version = document.get("openapi").and_then(Value::as_str);
+        if !version.is_some_and(|version| version.starts_with("3.")) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an OpenAPI 3.x document",
+            ));
+        }
+        // Paths are relative to the first server, which may have a path of its own.
+        let base_path = document
+            .pointer("/servers/0/url")
+            .and_then(Value::as_str)
+            .map(|url| match Url::parse(url) {
+                Ok(url) => url.path().to_owned(),
+                Err(_) => url.to_owned(),
+            })
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_owned();
+        Ok(Self {
+            document: Arc::new(document),
+            base_path,
+        })
+    }
+
+    /// Read the OpenAPI document in the JSON file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&fs::read(path)?)
+    }
+
+    /// Finds the operation of `method` requests to `path`, along with the path item and the
+    /// values of its path parameters.
+    fn operation<'a>(
+        &'a self,
+        method: &Method,
+        path: &str,
+    ) -> std::result::Result<Operation<'a>, String> {
+        let path = path.strip_prefix(&self.base_path).unwrap_or(path);
+        let paths = self
+            .document
+            .get("paths")
+            .and_then(Value::as_object)
+            .ok_or_else(|| "the document has no paths".to_owned())?;
+        // Concrete paths take precedence over templated ones, e.g. `/users/me` over
+        // `/users/{id}`.
+        let (template, item, path_params) = paths
+            .iter()
+            .filter_map(|(template, item)| {
+                match_template(template, path).map(|params| (template, item, params))
+            })
+            .min_by_key(|(_, _, params)| params.len())
+            .ok_or_else(|| format!("no path of the document matches {}", path))?;
+        let item = self.resolve(item);
+        let operation = item
+            .get(method.as_str().to_lowercase().as_str())
+            .map(|operation| self.resolve(operation))
+            .ok_or_else(|| format!("{} isn't allowed on {}", method, template))?;
+        Ok(Operation {
+            template,
+            item,
+            operation,
+            path_params,
+        })
+    }
+
+    /// Follows `value`'s `$ref`, if any.
+    fn resolve<'a>(&'a self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_DEPTH {
+            let target = value
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix('#'))
+                .and_then(|pointer| self.document.pointer(pointer));
+            match target {
+                Some(target) => value = target,
+                None => break,
+            }
+        }
+        value
+    }
+
+    fn check_request(&self, req: &Request) -> Vec<String> {
+        let mut violations = Vec::new();
+        let op = match self.operation(req.method(), req.url().path()) {
+            Ok(op) => op,
+            Err(violation) => return vec![violation],
+        };
+
+        let parameters = op
+            .item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .chain(op.operation.get("parameters").and_then(Value::as_array))
+            .flatten()
+            .map(|parameter| self.resolve(parameter));
+        for parameter in parameters {
+            let name = parameter.get("name").and_then(Value::as_str).unwrap_or("");
+            let location = parameter.get("in").and_then(Value::as_str).unwrap_or("");
+            let value = match location {
+                "path" => op
+                    .path_params
+                    .iter()
+                    .find(|(param, _)| param == name)
+                    .map(|(_, value)| value.clone()),
+                "query" => req
+                    .url()
+                    .query_pairs()
+                    .find(|(param, _)| param == name)
+                    .map(|(_, value)| value.into_owned()),
+                "header" => req
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_owned),
+                _ => continue,
+            };
+            let required = location == "path"
+                || parameter
+                    .get("required")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+            match (value, parameter.get("schema")) {
+                (None, _) if required => violations.push(format!(
+                    "the required {} parameter `{}` is missing",
+                    location, name
+                )),
+                (Some(value), Some(schema)) => {
+                    let location = format!("{} parameter `{}`", location, name);
+                    self.check_parameter(schema, &value, &location, &mut violations);
+                }
+                _ => {}
+            }
+        }
+
+        match op
+            .operation
+            .get("requestBody")
+            .map(|body| self.resolve(body))
+        {
+            Some(body_spec) => {
+                let body = req.body().and_then(|body| body.as_bytes());
+                let required = body_spec
+                    .get("required")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                match body {
+                    None | Some([]) if required => {
+                        violations.push("the required request body is missing".to_owned())
+                    }
+                    None | Some([]) => {}
+                    Some(body) => self.check_body(
+                        body_spec,
+                        req.headers(),
+                        body,
+                        "request body",
+                        &mut violations,
+                    ),
+                }
+            }
+            None => {
+                if req.body().and_then(|body| body.as_bytes()).is_some() {
+                    violations.push(format!(
+                        "{} {} doesn't take a request body",
+                        req.method(),
+                        op.template
+                    ));
+                }
+            }
+        }
+        violations
+    }
+
+    fn check_response(
+        &self,
+        method: &Method,
+        url: &Url,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Vec<String> {
+        let mut violations = Vec::new();
+        let op = match self.operation(method, url.path()) {
+            Ok(op) => op,
+            // Already reported for the request.
+            Err(_) => return violations,
+        };
+        let responses = op.operation.get("responses").and_then(Value::as_object);
+        let response = responses.and_then(|responses| {
+            let class = format!("{}XX", status.as_u16() / 100);
+            responses
+                .get(status.as_str())
+                .or_else(|| responses.get(&class))
+                .or_else(|| responses.get(&class.to_lowercase()))
+                .or_else(|| responses.get("default"))
+        });
+        match response.map(|response| self.resolve(response)) {
+            Some(response) if response.get("content").is_some() && !body.is_empty() => {
+                self.check_body(response, headers, body, "response body", &mut violations)
+            }
+            Some(_) => {}
+            None => violations.push(format!(
+                "the {} status isn't documented for {} {}",
+                status.as_u16(),
+                method,
+                op.template
+            )),
+        }
+        violations
+    }
+
+    /// Checks `body` against the `content` of a request body or response.
+    fn check_body(
+        &self,
+        spec: &Value,
+        headers: &HeaderMap,
+        body: &[u8],
+        location: &str,
+        violations: &mut Vec<String>,
+    ) {
+        let content = match spec.get("content").and_then(Value::as_object) {
+            Some(content) => content,
+            None => return,
+        };
+        let media_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        let media = content.get(&media_type).or_else(|| {
+            let wildcard = format!("{}/*", media_type.split('/').next().unwrap_or_default());
+            content.get(&wildcard).or_else(|| content.get("*/*"))
+        });
+        let media = match media {
+            Some(media) => media,
+            None => {
+                violations.push(format!(
+                    "the {} has the undocumented content type `{}`",
+                    location, media_type
+                ));
+                return;
+            }
+        };
+        let is_json = media_type == "application/json" || media_type.ends_with("+json");
+        if let (true, Some(schema)) = (is_json, media.get("schema")) {
+            match serde_json::from_slice::<Value>(body) {
+                Ok(value) => self.check_value(schema, &value, location, violations, 0),
+                Err(err) => violations.push(format!("the {} isn't valid JSON: {}", location, err)),
+            }
+        }
+    }
+
+    /// Checks the value of a parameter, parsed from text according to the type of `schema`.
+    fn check_parameter(
+        &self,
+        schema: &Value,
+        value: &str,
+        location: &str,
+        violations: &mut Vec<String>,
+    ) {
+        let schema = self.resolve(schema);
+        let parsed = match schema.get("type").and_then(Value::as_str) {
+            Some("integer") | Some("number") => serde_json::from_str::<Value>(value).ok(),
+            Some("boolean") => match value {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            // Arrays and objects are serialized in styles too many to check here.
+            Some("array") | Some("object") => return,
+            _ => Some(Value::String(value.to_owned())),
+        };
+        match parsed {
+            Some(parsed) => self.check_value(schema, &parsed, location, violations, 0),
+            None => violations.push(format!(
+                "the {} isn't a valid {}: `{}`",
+                location,
+                schema
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+                value
+            )),
+        }
+    }
+
+    /// Checks `value` against a JSON `schema`, reporting violations at `location`.
+    fn check_value(
+        &self,
+        schema: &Value,
+        value: &Value,
+        location: &str,
+        violations: &mut Vec<String>,
+        depth: usize,
+    ) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let schema = self.resolve(schema);
+        let schema = match schema.as_object() {
+            Some(schema) => schema,
+            None => return,
+        };
+
+        if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+            for schema in all_of {
+                self.check_value(schema, value, location, violations, depth + 1);
+            }
+        }
+        for (keyword, expected) in [("anyOf", None), ("oneOf", Some(1))] {
+            if let Some(schemas) = schema.get(keyword).and_then(Value::as_array) {
+                let matching = schemas
+                    .iter()
+                    .filter(|schema| {
+                        let mut errors = Vec::new();
+                        self.check_value(schema, value, location, &mut errors, depth + 1);
+                        errors.is_empty()
+                    })
+                    .count();
+                if expected.map_or(matching == 0, |expected| matching != expected) {
+                    violations.push(format!(
+                        "{} matches {} of the `{}` schemas",
+                        location, matching, keyword
+                    ));
+                }
+            }
+        }
+
+        if value.is_null() && schema.get("nullable").and_then(Value::as_bool) == Some(true) {
+            return;
+        }
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(ty) => vec![ty.as_str()],
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+                violations.push(format!(
+                    "{} should be of type {} but is {}",
+                    location,
+                    types.join(" or "),
+                    Kind(value)
+                ));
+                return;
+            }
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !values.contains(value) {
+                violations.push(format!("{} isn't one of the allowed values", location));
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, location, violations, depth),
+            Value::Array(items) => {
+                if let Some(items_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        let location = format!("{}[{}]", location, i);
+                        self.check_value(items_schema, item, &location, violations, depth + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        location: &str,
+        violations: &mut Vec<String>,
+        depth: usize,
+    ) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(name) {
+                violations.push(format!(
+                    "{} is missing the required property `{}`",
+                    location, name
+                ));
+            }
+        }
+        for (name, value) in object {
+            let location = format!("{}.{}", location, name);
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(schema) => self.check_value(schema, value, &location, violations, depth + 1),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        violations.push(format!("{} isn't an allowed property", location))
+                    }
+                    Some(schema @ Value::Object(_)) => {
+                        self.check_value(schema, value, &location, violations, depth + 1)
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+}
+
+struct Operation<'a> {
+    template: &'a str,
+    item: &'a Value,
+    operation: &'a Value,
+    path_params: Vec<(String, String)>,
+}
+
+/// Matches `path` against a path `template` such as `/users/{id}`, returning the values of its
+/// parameters.
+fn match_template(template: &str, path: &str) -> Option<Vec<(String, String)>> {
+    let mut params = Vec::new();
+    let mut template_segments = template.trim_end_matches('/').split('/');
+    let mut path_segments = path.trim_end_matches('/').split('/');
+    loop {
+        match (template_segments.next(), path_segments.next()) {
+            (None, None) => return Some(params),
+            (Some(template), Some(segment)) => {
+                if let Some(name) = template
+                    .strip_prefix('{')
+                    .and_then(|name| name.strip_suffix('}'))
+                {
+                    if segment.is_empty() {
+                        return None;
+                    }
+                    params.push((name.to_owned(), segment.to_owned()));
+                } else if template != segment {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match (ty, value) {
+        ("null", Value::Null)
+        | ("boolean", Value::Bool(_))
+        | ("number", Value::Number(_))
+        | ("string", Value::String(_))
+        | ("array", Value::Array(_))
+        | ("object", Value::Object(_)) => true,
+        ("integer", Value::Number(number)) => {
+            number.is_i64() || number.is_u64() || number.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => false,
+    }
+}
+
+/// Displays the JSON type of a value.
+struct Kind<'a>(&'a Value);
+
+impl fmt::Display for Kind<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.0 {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object",
+        })
+    }
+}
+
+/// The request or its response doesn't comply with the OpenAPI document of a
+/// [`ContractMiddleware`].
+#[derive(Debug, thiserror::Error)]
+#[error("{method} {url} violates the API contract: {}", .violations.join("; "))]
+pub struct ContractViolation {
+    /// The method of the request.
+    pub method: Method,
+    /// The URL of the request.
+    pub url: Url,
+    /// What doesn't comply with the document.
+    pub violations: Vec<String>,
+}
+
+/// `ContractMiddleware` checks the requests of a client, and their responses, against an
+/// [`OpenApiSpec`], to catch drift between the client and the API it calls, e.g. in CI.
+///
+/// Requests are checked for their path and method, parameters and JSON body, and responses for
+/// their status and JSON body. The JSON schemas of the document are checked for their types,
+/// enums, required and additional properties, items and combinations.
+///
+/// By default, non-compliant requests fail with a [`ContractViolation`] error before being sent,
+/// and non-compliant responses are replaced with one. Violations can be logged as warnings
+/// instead, see [`with_warnings`](Self::with_warnings). Response bodies are buffered to be
+/// checked.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_testing::{ContractMiddleware, OpenApiSpec};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let spec = OpenApiSpec::from_file("openapi.json")?;
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(ContractMiddleware::new(spec))
+///     .build();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ContractMiddleware {
+    spec: OpenApiSpec,
+    warn_only: bool,
+}
+
+impl ContractMiddleware {
+    /// Construct `ContractMiddleware` checking requests against `spec`.
+    pub fn new(spec: OpenApiSpec) -> Self {
+        Self {
+            spec,
+            warn_only: false,
+        }
+    }
+
+    /// Log violations as warnings, rather than failing requests.
+    pub fn with_warnings(mut self) -> Self {
+        self.warn_only = true;
+        self
+    }
+
+    fn report(&self, method: &Method, url: &Url, violations: Vec<String>) -> Result<()> {
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let violation = ContractViolation {
+            method: method.clone(),
+            url: url.clone(),
+            violations,
+        };
+        if self.warn_only {
+            tracing::warn!("{}", violation);
+            Ok(())
+        } else {
+            Err(Error::middleware(violation))
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ContractMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let method = req.method().clone();
+        let url = req.url().clone();
+        self.report(&method, &url, self.spec.check_request(&req))?;
+
+        let mut res = next.run(req, extensions).await?;
+        let res_url = res.url().clone();
+        let status = res.status();
+        let version = res.version();
+        let headers = res.headers().clone();
+        let res_extensions = std::mem::take(res.extensions_mut());
+        let body = res.bytes().await?;
+        let violations = self
+            .spec
+            .check_response(&method, &url, status, &headers, &body);
+        self.report(&method, &url, violations)?;
+
+        let mut res = http::Response::builder()
+            .url(res_url)
+            .body(body)
+            .expect("response is valid");
+        *res.status_mut() = status;
+        *res.version_mut() = version;
+        *res.headers_mut() = headers;
+        res.extensions_mut().extend(res_extensions);
+        Ok(Response::from(res))
+    }
+}
@@ -0,0 +1,153 @@
+//! `InMemoryTransport` serves requests with in-process HTTP services over in-memory streams.
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+use http::header::HOST;
+use http::{Extensions, HeaderValue};
+use hyper::body::Incoming;
+use hyper::service::Service;
+use hyper_util::rt::TokioIo;
+use reqwest::{Body, Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{DryRun, Error, Middleware, Next, Result};
+use tokio::io::DuplexStream;
+
+type Serve = Arc<dyn Fn(DuplexStream) + Send + Sync + 'static>;
+
+/// `InMemoryTransport` sends the requests to some hosts to HTTP services running in the test
+/// process, over HTTP/1.1 on in-memory [duplex streams](tokio::io::duplex), so that clients and
+/// their middleware stacks can be tested against real server code, such as an `axum` router,
+/// without binding ports.
+///
+/// Services are [`hyper` services](hyper::service::Service), which `tower` services can be
+/// adapted to with `hyper_util::service::TowerToHyperService`. Each request is served on a new
+/// connection. Requests to hosts without service are sent with the client of the
+/// `ClientWithMiddleware`.
+///
+/// Requests are served rather than passed down the middleware stack, so `InMemoryTransport`
+/// must be the last middleware.
+///
+/// ```
+/// use std::convert::Infallible;
+/// use http_body_util::Full;
+/// use hyper::body::{Bytes, Incoming};
+/// use hyper::service::service_fn;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_testing::InMemoryTransport;
+///
+/// let service = service_fn(|_req: http::Request<Incoming>| async {
+///     Ok::<_, Infallible>(http::Response::new(Full::new(Bytes::from("pong"))))
+/// });
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(InMemoryTransport::new().with_service("api.test", service))
+///     .build();
+/// ```
+#[derive(Clone, Default)]
+pub struct InMemoryTransport {
+    services: HashMap<String, Serve>,
+}
+
+impl fmt::Debug for InMemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryTransport")
+            .field("hosts", &self.services.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl InMemoryTransport {
+    /// Construct `InMemoryTransport` without services.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve requests to `host` with `service`.
+    pub fn with_service<S, B>(mut self, host: &str, service: S) -> Self
+    where
+        S: Service<http::Request<Incoming>, Response = http::Response<B>>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Future: Send + 'static,
+        S::Error: Into<Box<dyn StdError + Send + Sync>>,
+        B: http_body::Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        let serve = move |io: DuplexStream| {
+            let connection = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(io), service.clone());
+            tokio::spawn(async move {
+                // Failures are reported to the client side of the connection.
+                let _ = connection.await;
+            });
+        };
+        self.services
+            .insert(host.to_ascii_lowercase(), Arc::new(serve));
+        self
+    }
+
+    async fn send(&self, serve: &Serve, req: Request) -> Result<Response> {
+        let url = req.url().clone();
+        let mut req = http::Request::try_from(req)?;
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_owned(),
+        };
+        *req.uri_mut() = path.parse().map_err(Error::middleware)?;
+        if !req.headers().contains_key(HOST) {
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+                None => url.host_str().unwrap_or_default().to_owned(),
+            };
+            let host = HeaderValue::from_str(&host).map_err(Error::middleware)?;
+            req.headers_mut().insert(HOST, host);
+        }
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        serve(server);
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(client))
+            .await
+            .map_err(Error::middleware)?;
+        tokio::spawn(async move {
+            let _ = connection.await;
+        });
+        let res = sender.send_request(req).await.map_err(Error::middleware)?;
+
+        let (parts, body) = res.into_parts();
+        let mut res = http::Response::builder()
+            .url(url)
+            .body(Body::wrap(body))
+            .expect("response is valid");
+        *res.status_mut() = parts.status;
+        *res.version_mut() = parts.version;
+        *res.headers_mut() = parts.headers;
+        Ok(Response::from(res))
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for InMemoryTransport {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if extensions.get::<DryRun>().is_some() {
+            return next.run(req, extensions).await;
+        }
+        let host = req
+            .url()
+            .host_str()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match self.services.get(&host) {
+            Some(serve) => self.send(serve, req).await,
+            None => next.run(req, extensions).await,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Utilities to test clients built on [`reqwest_middleware`], and the middleware themselves.
+//!
+//! [`CassetteMiddleware`] records the exchanges of a client with real APIs to a cassette, and
+//! replays them in later runs, so that tests don't depend on those APIs. [`MockService`] answers
+//! requests in process from expectations, to unit test middleware stacks without an HTTP server.
+//! [`RecorderMiddleware`] keeps a copy of the requests sent by a client, to assert on them, and
+//! [`ChaosMiddleware`] injects faults into requests, to test how clients cope with them.
+//!
+//! ## Feature flags
+//!
+//! * `duplex`: `InMemoryTransport` to serve requests with in-process HTTP services, such as
+//!   `axum` routers, over in-memory streams.
+//! * `openapi`: `ContractMiddleware` to check requests and responses against an OpenAPI
+//!   document.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use reqwest_middleware::ClientBuilder;
+//! use reqwest_testing::CassetteMiddleware;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(CassetteMiddleware::replay("tests/cassettes/users.json")?)
+//!     .build();
+//! # Ok(())
+//! # }
+//! ```
+
+mod cassette;
+mod chaos;
+#[cfg(feature = "openapi")]
+mod contract;
+#[cfg(all(feature = "duplex", not(target_arch = "wasm32")))]
+mod duplex;
+mod mock;
+mod recorder;
+
+pub use cassette::{CassetteMiddleware, Match, UnexpectedRequest};
+pub use chaos::{ChaosMiddleware, EnableChaos, InjectedConnectionReset};
+#[cfg(feature = "openapi")]
+pub use contract::{ContractMiddleware, ContractViolation, OpenApiSpec};
+#[cfg(all(feature = "duplex", not(target_arch = "wasm32")))]
+pub use duplex::InMemoryTransport;
+pub use mock::{Expectation, MockResponse, MockService, UnmatchedRequest};
+pub use recorder::{Outcome, RecordedRequest, Recorder, RecorderMiddleware};
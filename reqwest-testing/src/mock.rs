@@ -0,0 +1,354 @@
+//! `MockService` answers requests in process, from expectations set by tests.
+use std::sync::{Arc, Mutex};
+
+use http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use http::{Extensions, HeaderMap, Method, StatusCode};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+
+/// The request matched no expectation of a [`MockService`].
+#[derive(Debug, thiserror::Error)]
+#[error("No expectation of the mock matches {method} {url}")]
+pub struct UnmatchedRequest {
+    /// The method of the request.
+    pub method: Method,
+    /// The URL of the request.
+    pub url: Url,
+}
+
+/// A response returned by a [`MockService`].
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// Create a new [`MockResponse`] with `status` and an empty body.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Add the `name` header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` isn't a valid header value.
+    pub fn with_header(mut self, name: HeaderName, value: &str) -> Self {
+        let value = HeaderValue::from_str(value).expect("invalid header value");
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Set the body.
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set the body to `json`, with a `Content-Type: application/json` header.
+    pub fn with_json(mut self, json: &serde_json::Value) -> Self {
+        self.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.body = json.to_string().into_bytes();
+        self
+    }
+
+    fn to_response(&self, url: &Url) -> Response {
+        let mut res = http::Response::builder()
+            .url(url.clone())
+            .body(self.body.clone())
+            .expect("response is valid");
+        *res.status_mut() = self.status;
+        *res.headers_mut() = self.headers.clone();
+        Response::from(res)
+    }
+}
+
+impl From<StatusCode> for MockResponse {
+    fn from(status: StatusCode) -> Self {
+        Self::new(status)
+    }
+}
+
+impl From<serde_json::Value> for MockResponse {
+    fn from(json: serde_json::Value) -> Self {
+        Self::new(StatusCode::OK).with_json(&json)
+    }
+}
+
+type ResponderFn = dyn Fn(&Request) -> MockResponse + Send + Sync + 'static;
+
+struct ExpectationState {
+    method: Method,
+    path: String,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: Option<Vec<u8>>,
+    responses: Vec<MockResponse>,
+    responder: Option<Arc<ResponderFn>>,
+    times: Option<usize>,
+    calls: usize,
+}
+
+impl ExpectationState {
+    fn matches(&self, req: &Request) -> bool {
+        let url = req.url();
+        let path_matches = match self.path.split_once('?') {
+            Some((path, query)) => url.path() == path && url.query() == Some(query),
+            None => url.path() == self.path,
+        };
+        *req.method() == self.method
+            && path_matches
+            && self
+                .headers
+                .iter()
+                .all(|(name, value)| req.headers().get_all(name).iter().any(|v| v == value))
+            && match &self.body {
+                Some(body) => req.body().and_then(|b| b.as_bytes()) == Some(body.as_slice()),
+                None => true,
+            }
+    }
+
+    fn exhausted(&self) -> bool {
+        matches!(self.times, Some(times) if self.calls >= times)
+    }
+
+    /// Whether the expectation no longer needs calls, for [`MockService::in_sequence`].
+    fn satisfied(&self) -> bool {
+        self.calls >= self.times.unwrap_or(1)
+    }
+
+    fn respond(&mut self, req: &Request) -> Response {
+        let call = self.calls;
+        self.calls += 1;
+        let response = match &self.responder {
+            Some(responder) => responder(req),
+            None => self
+                .responses
+                .get(call)
+                .or_else(|| self.responses.last())
+                .cloned()
+                .unwrap_or_else(|| MockResponse::new(StatusCode::OK)),
+        };
+        response.to_response(req.url())
+    }
+
+    fn describe(&self) -> String {
+        match self.times {
+            Some(times) => format!(
+                "{} {} expected {} times, called {} times",
+                self.method, self.path, times, self.calls
+            ),
+            None => format!(
+                "{} {} expected at least once, never called",
+                self.method, self.path
+            ),
+        }
+    }
+}
+
+struct Inner {
+    expectations: Mutex<Vec<ExpectationState>>,
+    in_sequence: bool,
+}
+
+impl Inner {
+    fn unmet(&self) -> Vec<String> {
+        self.expectations
+            .lock()
+            .expect("mock lock poisoned")
+            .iter()
+            .filter(|expectation| !expectation.satisfied())
+            .map(ExpectationState::describe)
+            .collect()
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let unmet = self.unmet();
+        if !unmet.is_empty() && !std::thread::panicking() {
+            panic!("Unmet mock expectations:\n{}", unmet.join("\n"));
+        }
+    }
+}
+
+/// `MockService` answers requests in process, as the innermost middleware of a client, so that
+/// middleware stacks and clients can be tested without an HTTP server.
+///
+/// Requests are answered by the first expectation they match, set with
+/// [`expect`](Self::expect), and fail with an [`UnmatchedRequest`] error when there is none.
+/// Expectations are checked once the `MockService` and all its clones are dropped, or with
+/// [`verify`](Self::verify): each one must have been called as many times as set with
+/// [`Expectation::times`], or at least once.
+///
+/// ```
+/// use http::Method;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_testing::MockService;
+/// use serde_json::json;
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let mock = MockService::new();
+/// mock.expect(Method::GET, "/users")
+///     .returning(json!([{ "name": "alice" }]))
+///     .times(2);
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(mock.clone())
+///     .build();
+/// for _ in 0..2 {
+///     let users = client.get("http://api.test/users").send().await?;
+///     assert_eq!(users.status(), 200);
+/// }
+/// mock.verify();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MockService {
+    inner: Arc<Inner>,
+}
+
+impl MockService {
+    /// Create a new [`MockService`], without expectations.
+    pub fn new() -> Self {
+        Self::with_sequence(false)
+    }
+
+    /// Create a new [`MockService`] whose expectations must be met in the order they are set: a
+    /// request only matches an expectation once the previous ones are met.
+    pub fn in_sequence() -> Self {
+        Self::with_sequence(true)
+    }
+
+    fn with_sequence(in_sequence: bool) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                expectations: Mutex::default(),
+                in_sequence,
+            }),
+        }
+    }
+
+    /// Expect `method` requests to `path`, which may include a query, e.g. `/users?page=2`.
+    ///
+    /// They are answered with an empty `200 OK` response unless set otherwise on the returned
+    /// [`Expectation`].
+    pub fn expect(&self, method: Method, path: &str) -> Expectation {
+        let mut expectations = self.inner.expectations.lock().expect("mock lock poisoned");
+        expectations.push(ExpectationState {
+            method,
+            path: path.to_owned(),
+            headers: Vec::new(),
+            body: None,
+            responses: Vec::new(),
+            responder: None,
+            times: None,
+            calls: 0,
+        });
+        Expectation {
+            inner: self.inner.clone(),
+            index: expectations.len() - 1,
+        }
+    }
+
+    /// Panics if some expectations aren't met.
+    pub fn verify(&self) {
+        let unmet = self.inner.unmet();
+        assert!(
+            unmet.is_empty(),
+            "Unmet mock expectations:\n{}",
+            unmet.join("\n")
+        );
+    }
+
+    fn respond(&self, req: &Request) -> Result<Response> {
+        let mut expectations = self.inner.expectations.lock().expect("mock lock poisoned");
+        for expectation in expectations.iter_mut() {
+            if !expectation.exhausted() && expectation.matches(req) {
+                return Ok(expectation.respond(req));
+            }
+            if self.inner.in_sequence && !expectation.satisfied() {
+                break;
+            }
+        }
+        Err(Error::middleware(UnmatchedRequest {
+            method: req.method().clone(),
+            url: req.url().clone(),
+        }))
+    }
+}
+
+impl Default for MockService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An expectation of a [`MockService`], to set the requests it matches and how they are answered.
+pub struct Expectation {
+    inner: Arc<Inner>,
+    index: usize,
+}
+
+impl Expectation {
+    fn update(self, f: impl FnOnce(&mut ExpectationState)) -> Self {
+        f(&mut self.inner.expectations.lock().expect("mock lock poisoned")[self.index]);
+        self
+    }
+
+    /// Only match the requests with the `name` header set to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` isn't a valid header value.
+    pub fn with_header(self, name: HeaderName, value: &str) -> Self {
+        let value = HeaderValue::from_str(value).expect("invalid header value");
+        self.update(|expectation| expectation.headers.push((name, value)))
+    }
+
+    /// Only match the requests with `body`.
+    pub fn with_body(self, body: impl Into<Vec<u8>>) -> Self {
+        let body = body.into();
+        self.update(|expectation| expectation.body = Some(body))
+    }
+
+    /// Answer with `response`. When called several times, the responses are returned in turn,
+    /// the last one repeatedly.
+    pub fn returning(self, response: impl Into<MockResponse>) -> Self {
+        let response = response.into();
+        self.update(|expectation| expectation.responses.push(response))
+    }
+
+    /// Answer with the response built by `responder` from the request.
+    pub fn respond_with<F>(self, responder: F) -> Self
+    where
+        F: Fn(&Request) -> MockResponse + Send + Sync + 'static,
+    {
+        self.update(|expectation| expectation.responder = Some(Arc::new(responder)))
+    }
+
+    /// Expect exactly `times` matching requests, further ones are left to other expectations.
+    pub fn times(self, times: usize) -> Self {
+        self.update(|expectation| expectation.times = Some(times))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for MockService {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut Extensions,
+        _next: Next<'_>,
+    ) -> Result<Response> {
+        self.respond(&req)
+    }
+}
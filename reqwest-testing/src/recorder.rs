@@ -0,0 +1,197 @@
+//! `RecorderMiddleware` keeps a copy of the requests of a client, to assert on them in tests.
+use std::sync::{Arc, Mutex};
+
+use http::{Extensions, HeaderMap, Method, StatusCode, Version};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next, Result};
+
+/// How a [`RecordedRequest`] ended.
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    /// A response was received, whose head is kept.
+    Response {
+        /// The status of the response.
+        status: StatusCode,
+        /// The headers of the response.
+        headers: HeaderMap,
+    },
+    /// The request failed, with this error message.
+    Error(String),
+}
+
+/// A copy of a request recorded by a [`RecorderMiddleware`].
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    /// The method of the request.
+    pub method: Method,
+    /// The URL of the request.
+    pub url: Url,
+    /// The HTTP version of the request.
+    pub version: Version,
+    /// The headers of the request.
+    pub headers: HeaderMap,
+    /// The body of the request, when it was buffered rather than streamed.
+    pub body: Option<Vec<u8>>,
+    /// How the request ended, `None` while it is in flight.
+    pub outcome: Option<Outcome>,
+}
+
+impl RecordedRequest {
+    /// The status of the response, if one was received.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self.outcome {
+            Some(Outcome::Response { status, .. }) => Some(status),
+            _ => None,
+        }
+    }
+
+    /// The body of the request as text, if it was buffered and is UTF-8.
+    pub fn text(&self) -> Option<&str> {
+        self.body
+            .as_deref()
+            .and_then(|body| std::str::from_utf8(body).ok())
+    }
+}
+
+/// Log of the requests recorded by a [`RecorderMiddleware`], in the order they were sent.
+///
+/// Clones share the same log, so that one can be kept by the test while the other is given to
+/// the middleware.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl Recorder {
+    /// Create a new, empty, [`Recorder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The recorded requests.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests
+            .lock()
+            .expect("recorder lock poisoned")
+            .clone()
+    }
+
+    /// The first recorded request for which `predicate` returns `true`.
+    pub fn find<P>(&self, mut predicate: P) -> Option<RecordedRequest>
+    where
+        P: FnMut(&RecordedRequest) -> bool,
+    {
+        self.requests
+            .lock()
+            .expect("recorder lock poisoned")
+            .iter()
+            .find(|request| predicate(request))
+            .cloned()
+    }
+
+    /// The number of requests recorded.
+    pub fn len(&self) -> usize {
+        self.requests.lock().expect("recorder lock poisoned").len()
+    }
+
+    /// Whether no request was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget the recorded requests.
+    pub fn clear(&self) {
+        self.requests
+            .lock()
+            .expect("recorder lock poisoned")
+            .clear();
+    }
+
+    fn push(&self, request: RecordedRequest) -> usize {
+        let mut requests = self.requests.lock().expect("recorder lock poisoned");
+        requests.push(request);
+        requests.len() - 1
+    }
+
+    fn set_outcome(&self, index: usize, outcome: Outcome) {
+        let mut requests = self.requests.lock().expect("recorder lock poisoned");
+        // The log may have been cleared while the request was in flight.
+        if let Some(request) = requests.get_mut(index) {
+            request.outcome = Some(outcome);
+        }
+    }
+}
+
+/// `RecorderMiddleware` records a copy of every request going through it, and how it ended, to a
+/// [`Recorder`], so that tests can assert on what a client actually sent.
+///
+/// The heads of requests are always recorded, and their bodies when they are buffered. Only the
+/// heads of responses are recorded, their bodies are left untouched. The requests are recorded as
+/// they are at the position of the middleware in the stack: add it last to see them as they are
+/// sent.
+///
+/// ```
+/// use http::Method;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_testing::{MockService, Recorder, RecorderMiddleware};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let mock = MockService::new();
+/// mock.expect(Method::POST, "/users");
+/// let recorder = Recorder::new();
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(RecorderMiddleware::new(recorder.clone()))
+///     .with(mock)
+///     .build();
+///
+/// client.post("http://api.test/users").body("alice").send().await?;
+/// let request = recorder.find(|r| r.method == Method::POST).unwrap();
+/// assert_eq!(request.text(), Some("alice"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RecorderMiddleware {
+    recorder: Recorder,
+}
+
+impl RecorderMiddleware {
+    /// Construct `RecorderMiddleware` recording requests to `recorder`.
+    pub fn new(recorder: Recorder) -> Self {
+        Self { recorder }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RecorderMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let index = self.recorder.push(RecordedRequest {
+            method: req.method().clone(),
+            url: req.url().clone(),
+            version: req.version(),
+            headers: req.headers().clone(),
+            body: req
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(<[u8]>::to_vec),
+            outcome: None,
+        });
+
+        let result = next.run(req, extensions).await;
+        let outcome = match &result {
+            Ok(res) => Outcome::Response {
+                status: res.status(),
+                headers: res.headers().clone(),
+            },
+            Err(err) => Outcome::Error(err.to_string()),
+        };
+        self.recorder.set_outcome(index, outcome);
+        result
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use reqwest::Client;
+use reqwest_middleware::ClientBuilder;
+use reqwest_testing::{CassetteMiddleware, Match, UnexpectedRequest};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("reqwest-testing-{}", std::process::id()))
+        .join(name)
+}
+
+#[tokio::test]
+async fn assert_recorded_cassette_is_replayed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users"))
+        .respond_with(
+            ResponseTemplate::new(201)
+                .insert_header("x-user-id", "42")
+                .set_body_string("created"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let cassette = cassette_path("replayed.json");
+    let url = format!("{}/users", server.uri());
+
+    let client = ClientBuilder::new(Client::new())
+        .with(CassetteMiddleware::record(&cassette))
+        .build();
+    let res = client
+        .post(&url)
+        .header("authorization", "Bearer secret")
+        .body("alice")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "created");
+    let recorded = std::fs::read_to_string(&cassette).unwrap();
+    assert!(!recorded.contains("secret"));
+
+    // The server only expects one request, the replayed one isn't sent.
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            CassetteMiddleware::replay(&cassette)
+                .unwrap()
+                .with_matchers([Match::Method, Match::Path, Match::Body]),
+        )
+        .build();
+    let res = client.post(&url).body("alice").send().await.unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.headers()["x-user-id"], "42");
+    assert_eq!(res.text().await.unwrap(), "created");
+
+    // Interactions are replayed once, and other requests are unexpected.
+    let err = client.post(&url).body("alice").send().await.unwrap_err();
+    match err {
+        reqwest_middleware::Error::Middleware(err) => assert!(err
+            .downcast_ref::<UnexpectedRequest>()
+            .unwrap()
+            .to_string()
+            .starts_with("No interaction of the cassette matches POST")),
+        _ => panic!("unexpected error {:?}", err),
+    }
+    let err = client.post(&url).body("bob").send().await.unwrap_err();
+    match err {
+        reqwest_middleware::Error::Middleware(err) => {
+            assert!(err.downcast_ref::<UnexpectedRequest>().is_some())
+        }
+        _ => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn assert_auto_records_missing_cassettes() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let cassette = cassette_path("auto.json");
+    let _ = std::fs::remove_file(&cassette);
+
+    for _ in 0..2 {
+        let client = ClientBuilder::new(Client::new())
+            .with(
+                CassetteMiddleware::auto(&cassette)
+                    .unwrap()
+                    .with_playback_repeats(),
+            )
+            .build();
+        let res = client.get(server.uri()).send().await.unwrap();
+        assert_eq!(res.text().await.unwrap(), "hello");
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+use http::{Method, StatusCode};
+use reqwest::Client;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use reqwest_testing::{
+    ChaosMiddleware, EnableChaos, InjectedConnectionReset, MockResponse, MockService,
+};
+
+fn chaos_client(chaos: ChaosMiddleware) -> ClientWithMiddleware {
+    let mock = MockService::new();
+    mock.expect(Method::GET, "/")
+        .returning(MockResponse::new(StatusCode::OK).with_body("0123456789"));
+    ClientBuilder::new(Client::new())
+        .with(chaos)
+        .with(mock)
+        .build()
+}
+
+#[tokio::test]
+async fn assert_only_enabled_requests_are_affected() {
+    let client =
+        chaos_client(ChaosMiddleware::new().with_status(1.0, StatusCode::INTERNAL_SERVER_ERROR));
+
+    let res = client.get("http://api.test/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = client
+        .get("http://api.test/")
+        .with_extension(EnableChaos)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn assert_faults_are_injected() {
+    let client = chaos_client(ChaosMiddleware::new().with_connection_resets(1.0));
+    let err = client
+        .get("http://api.test/")
+        .with_extension(EnableChaos)
+        .send()
+        .await
+        .unwrap_err();
+    match err {
+        Error::Middleware(err) => assert!(err.downcast_ref::<InjectedConnectionReset>().is_some()),
+        err => panic!("unexpected error: {}", err),
+    }
+    client.get("http://api.test/").send().await.unwrap();
+
+    let client = chaos_client(
+        ChaosMiddleware::new()
+            .with_all_requests()
+            .with_latency(1.0, Duration::from_millis(50))
+            .with_truncated_bodies(1.0),
+    );
+    let start = Instant::now();
+    let res = client.get("http://api.test/").send().await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.bytes().await.unwrap().len() < 10);
+}
+
+#[tokio::test]
+async fn assert_seeded_faults_are_reproducible() {
+    async fn statuses(seed: u64) -> Vec<StatusCode> {
+        let client = chaos_client(
+            ChaosMiddleware::new()
+                .with_all_requests()
+                .with_seed(seed)
+                .with_status(0.5, StatusCode::TOO_MANY_REQUESTS),
+        );
+        let mut statuses = Vec::new();
+        for _ in 0..20 {
+            let res = client.get("http://api.test/").send().await.unwrap();
+            statuses.push(res.status());
+        }
+        statuses
+    }
+
+    let first = statuses(7).await;
+    assert_eq!(first, statuses(7).await);
+    assert!(first.contains(&StatusCode::OK));
+    assert!(first.contains(&StatusCode::TOO_MANY_REQUESTS));
+}
@@ -0,0 +1,179 @@
+use http::{Method, StatusCode};
+use reqwest::Client;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error};
+use reqwest_testing::{
+    ContractMiddleware, ContractViolation, MockResponse, MockService, OpenApiSpec,
+};
+use serde_json::json;
+
+const SPEC: &str = r##"{
+  "openapi": "3.0.3",
+  "info": { "title": "Users", "version": "1.0.0" },
+  "servers": [{ "url": "http://api.test/v1" }],
+  "paths": {
+    "/users": {
+      "post": {
+        "requestBody": {
+          "required": true,
+          "content": {
+            "application/json": { "schema": { "$ref": "#/components/schemas/NewUser" } }
+          }
+        },
+        "responses": {
+          "201": {
+            "description": "Created",
+            "content": {
+              "application/json": { "schema": { "$ref": "#/components/schemas/User" } }
+            }
+          }
+        }
+      }
+    },
+    "/users/{id}": {
+      "parameters": [
+        { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+      ],
+      "get": {
+        "parameters": [
+          { "name": "fields", "in": "query", "schema": { "type": "string", "enum": ["all", "name"] } }
+        ],
+        "responses": {
+          "200": {
+            "description": "The user",
+            "content": {
+              "application/json": { "schema": { "$ref": "#/components/schemas/User" } }
+            }
+          },
+          "404": { "description": "Not found" }
+        }
+      }
+    }
+  },
+  "components": {
+    "schemas": {
+      "NewUser": {
+        "type": "object",
+        "required": ["name"],
+        "properties": { "name": { "type": "string" } },
+        "additionalProperties": false
+      },
+      "User": {
+        "type": "object",
+        "required": ["id", "name"],
+        "properties": {
+          "id": { "type": "integer" },
+          "name": { "$ref": "#/components/schemas/NewUser/properties/name" }
+        }
+      }
+    }
+  }
+}"##;
+
+fn client(mock: &MockService) -> ClientWithMiddleware {
+    let spec = OpenApiSpec::from_json(SPEC.as_bytes()).unwrap();
+    ClientBuilder::new(Client::new())
+        .with(ContractMiddleware::new(spec))
+        .with(mock.clone())
+        .build()
+}
+
+fn violations(err: Error) -> Vec<String> {
+    match err {
+        Error::Middleware(err) => err.downcast::<ContractViolation>().unwrap().violations,
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[tokio::test]
+async fn assert_compliant_exchanges_pass() {
+    let mock = MockService::new();
+    mock.expect(Method::POST, "/v1/users").returning(
+        MockResponse::new(StatusCode::CREATED).with_json(&json!({ "id": 1, "name": "alice" })),
+    );
+    mock.expect(Method::GET, "/v1/users/1")
+        .returning(StatusCode::NOT_FOUND);
+
+    let client = client(&mock);
+    let res = client
+        .post("http://api.test/v1/users")
+        .header("content-type", "application/json")
+        .body(r#"{"name":"alice"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), r#"{"id":1,"name":"alice"}"#);
+    client
+        .get("http://api.test/v1/users/1?fields=all")
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn assert_request_violations_are_errors() {
+    let mock = MockService::new();
+    let client = client(&mock);
+
+    let err = client
+        .get("http://api.test/v1/users/alice?fields=everything")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        violations(err),
+        [
+            "the path parameter `id` isn't a valid integer: `alice`",
+            "query parameter `fields` isn't one of the allowed values",
+        ]
+    );
+
+    let err = client
+        .post("http://api.test/v1/users")
+        .header("content-type", "application/json")
+        .body(r#"{"name":1,"admin":true}"#)
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        violations(err),
+        [
+            "request body.admin isn't an allowed property",
+            "request body.name should be of type string but is a number",
+        ]
+    );
+
+    let err = client
+        .delete("http://api.test/v1/users/1")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(violations(err), ["DELETE isn't allowed on /users/{id}"]);
+}
+
+#[tokio::test]
+async fn assert_response_violations_are_errors() {
+    let mock = MockService::new();
+    mock.expect(Method::GET, "/v1/users/1")
+        .returning(json!({ "name": "alice" }))
+        .returning(StatusCode::INTERNAL_SERVER_ERROR);
+    let client = client(&mock);
+
+    let err = client
+        .get("http://api.test/v1/users/1")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        violations(err),
+        ["response body is missing the required property `id`"]
+    );
+    let err = client
+        .get("http://api.test/v1/users/1")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        violations(err),
+        ["the 500 status isn't documented for GET /users/{id}"]
+    );
+}
@@ -0,0 +1,62 @@
+use std::convert::Infallible;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::service::service_fn;
+use reqwest_middleware::ClientBuilder;
+use reqwest_testing::{InMemoryTransport, Recorder, RecorderMiddleware};
+
+async fn echo(req: http::Request<Incoming>) -> Result<http::Response<Full<Bytes>>, Infallible> {
+    let head = format!(
+        "{} {} {}",
+        req.method(),
+        req.uri(),
+        req.headers()["host"].to_str().unwrap()
+    );
+    let body = req.into_body().collect().await.unwrap().to_bytes();
+    let mut echoed = head.into_bytes();
+    echoed.push(b'\n');
+    echoed.extend_from_slice(&body);
+    Ok(http::Response::builder()
+        .status(201)
+        .body(Full::new(Bytes::from(echoed)))
+        .unwrap())
+}
+
+#[tokio::test]
+async fn serves_requests_in_memory() {
+    let recorder = Recorder::new();
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(RecorderMiddleware::new(recorder.clone()))
+        .with(InMemoryTransport::new().with_service("api.test", service_fn(echo)))
+        .build();
+
+    let res = client
+        .post("http://api.test:8080/users?page=2")
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.url().as_str(), "http://api.test:8080/users?page=2");
+    assert_eq!(
+        res.text().await.unwrap(),
+        "POST /users?page=2 api.test:8080\nhello"
+    );
+    assert_eq!(recorder.len(), 1);
+}
+
+#[tokio::test]
+async fn passes_other_hosts_down_the_stack() {
+    let server = wiremock::MockServer::start().await;
+    wiremock::Mock::given(wiremock::matchers::any())
+        .respond_with(wiremock::ResponseTemplate::new(204))
+        .mount(&server)
+        .await;
+    let client = ClientBuilder::new(reqwest::Client::new())
+        .with(InMemoryTransport::new().with_service("api.test", service_fn(echo)))
+        .build();
+
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(res.status(), 204);
+}
@@ -0,0 +1,8 @@
+mod cassette;
+mod chaos;
+#[cfg(feature = "openapi")]
+mod contract;
+#[cfg(feature = "duplex")]
+mod duplex;
+mod mock;
+mod recorder;
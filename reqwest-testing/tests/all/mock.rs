@@ -0,0 +1,90 @@
+use http::{Method, StatusCode};
+use reqwest::Client;
+use reqwest_middleware::ClientBuilder;
+use reqwest_testing::{MockResponse, MockService, UnmatchedRequest};
+use serde_json::json;
+
+fn client(mock: &MockService) -> reqwest_middleware::ClientWithMiddleware {
+    ClientBuilder::new(Client::new()).with(mock.clone()).build()
+}
+
+#[tokio::test]
+async fn assert_expectations_are_answered() {
+    let mock = MockService::new();
+    mock.expect(Method::GET, "/users")
+        .returning(json!([{ "name": "alice" }]))
+        .times(2);
+    mock.expect(Method::POST, "/users")
+        .with_body("bob")
+        .returning(MockResponse::new(StatusCode::CREATED).with_body("created"));
+
+    let client = client(&mock);
+    for _ in 0..2 {
+        let res = client.get("http://api.test/users").send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(res.text().await.unwrap(), r#"[{"name":"alice"}]"#);
+    }
+    let res = client
+        .post("http://api.test/users")
+        .body("bob")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    mock.verify();
+
+    // The third request exceeds the expected count.
+    let err = client
+        .get("http://api.test/users")
+        .send()
+        .await
+        .unwrap_err();
+    match err {
+        reqwest_middleware::Error::Middleware(err) => {
+            assert!(err.downcast_ref::<UnmatchedRequest>().is_some())
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[tokio::test]
+async fn assert_responses_are_sequenced() {
+    let mock = MockService::new();
+    mock.expect(Method::GET, "/status")
+        .returning(StatusCode::SERVICE_UNAVAILABLE)
+        .returning(StatusCode::OK);
+
+    let client = client(&mock);
+    let statuses = [
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::OK,
+        StatusCode::OK,
+    ];
+    for status in statuses {
+        let res = client.get("http://api.test/status").send().await.unwrap();
+        assert_eq!(res.status(), status);
+    }
+}
+
+#[tokio::test]
+async fn assert_in_sequence_expectations_are_ordered() {
+    let mock = MockService::in_sequence();
+    mock.expect(Method::POST, "/login").times(1);
+    mock.expect(Method::GET, "/me").respond_with(|req| {
+        MockResponse::new(StatusCode::OK).with_body(req.url().query().unwrap_or_default())
+    });
+
+    let client = client(&mock);
+    assert!(client.get("http://api.test/me").send().await.is_err());
+    client.post("http://api.test/login").send().await.unwrap();
+    let res = client.get("http://api.test/me?id=7").send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "id=7");
+}
+
+#[test]
+#[should_panic(expected = "GET /users expected 2 times, called 0 times")]
+fn assert_unmet_expectations_panic_on_drop() {
+    let mock = MockService::new();
+    mock.expect(Method::GET, "/users").times(2);
+}
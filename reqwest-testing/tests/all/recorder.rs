@@ -0,0 +1,50 @@
+use http::{Method, StatusCode};
+use reqwest::Client;
+use reqwest_middleware::ClientBuilder;
+use reqwest_testing::{MockResponse, MockService, Outcome, Recorder, RecorderMiddleware};
+
+#[tokio::test]
+async fn assert_requests_are_recorded() {
+    let mock = MockService::new();
+    mock.expect(Method::POST, "/users").returning(
+        MockResponse::new(StatusCode::CREATED).with_header(http::header::LOCATION, "/users/42"),
+    );
+    let recorder = Recorder::new();
+    let client = ClientBuilder::new(Client::new())
+        .with(RecorderMiddleware::new(recorder.clone()))
+        .with(mock)
+        .build();
+
+    client
+        .post("http://api.test/users")
+        .header("x-request-id", "1")
+        .body("alice")
+        .send()
+        .await
+        .unwrap();
+    client
+        .get("http://api.test/missing")
+        .send()
+        .await
+        .unwrap_err();
+
+    let requests = recorder.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].method, Method::POST);
+    assert_eq!(requests[0].headers["x-request-id"], "1");
+    assert_eq!(requests[0].text(), Some("alice"));
+    assert_eq!(requests[0].status(), Some(StatusCode::CREATED));
+    match &requests[0].outcome {
+        Some(Outcome::Response { headers, .. }) => assert_eq!(headers["location"], "/users/42"),
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+
+    let failed = recorder
+        .find(|r| r.url.path() == "/missing")
+        .expect("request is recorded");
+    assert!(failed.body.is_none());
+    assert!(matches!(failed.outcome, Some(Outcome::Error(_))));
+
+    recorder.clear();
+    assert!(recorder.is_empty());
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use http::header::WARNING;
+use http::{Extensions, HeaderMap, HeaderName, HeaderValue, Method};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+use crate::reqwest_otel_span_builder::remove_credentials;
+
+/// The `Deprecation` header, see RFC 9745.
+pub const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+/// The `Sunset` header, see RFC 8594.
+pub const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// A response of a deprecated endpoint, reported by [`DeprecationMiddleware`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeprecationNotice {
+    /// The request method.
+    pub method: Method,
+    /// The request URL, without credentials.
+    pub url: String,
+    /// Whether the response has a `Deprecation` header.
+    pub deprecated: bool,
+    /// The date of the `Deprecation` header, if it has one.
+    pub deprecated_at: Option<SystemTime>,
+    /// The date of the `Sunset` header, after which the endpoint may stop working.
+    pub sunset: Option<SystemTime>,
+    /// The values of the `Warning` headers.
+    pub warnings: Vec<String>,
+}
+
+impl DeprecationNotice {
+    /// Build the notice for the response with `headers`, or `None` if it has none of the
+    /// `Deprecation`, `Sunset` and `Warning` headers.
+    pub fn from_headers(method: Method, url: String, headers: &HeaderMap) -> Option<Self> {
+        let deprecation = headers.get(DEPRECATION);
+        let sunset = headers.get(SUNSET);
+        let warnings: Vec<_> = headers
+            .get_all(WARNING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(str::to_owned)
+            .collect();
+        if deprecation.is_none() && sunset.is_none() && warnings.is_empty() {
+            return None;
+        }
+        Some(Self {
+            method,
+            url,
+            deprecated: deprecation.is_some(),
+            deprecated_at: deprecation.and_then(parse_date),
+            sunset: sunset.and_then(parse_date),
+            warnings,
+        })
+    }
+}
+
+/// Parses an HTTP date, or a structured field date such as `@1688169599`.
+fn parse_date(value: &HeaderValue) -> Option<SystemTime> {
+    let value = value.to_str().ok()?.trim();
+    match value.strip_prefix('@') {
+        Some(seconds) => Some(UNIX_EPOCH + Duration::from_secs(seconds.parse().ok()?)),
+        None => httpdate::parse_http_date(value).ok(),
+    }
+}
+
+type CallbackFn = dyn Fn(&DeprecationNotice) + Send + Sync + 'static;
+
+/// `DeprecationMiddleware` reports the responses with `Deprecation`, `Sunset` or `Warning`
+/// headers, so that teams learn about deprecated upstream endpoints before they break.
+///
+/// Notices are logged at the `WARN` level by default, at most once per endpoint, i.e. method
+/// and URL without query, per hour, see [`with_log_interval`](Self::with_log_interval). They
+/// can instead be passed to the callback set with [`with_callback`](Self::with_callback), e.g.
+/// to count them, which is called for every response.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::DeprecationMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(DeprecationMiddleware::new())
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct DeprecationMiddleware {
+    log_interval: Duration,
+    callback: Option<Arc<CallbackFn>>,
+    logged: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl Default for DeprecationMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeprecationMiddleware {
+    /// Construct `DeprecationMiddleware` logging notices at most once per endpoint per hour.
+    pub fn new() -> Self {
+        Self {
+            log_interval: Duration::from_secs(60 * 60),
+            callback: None,
+            logged: Arc::default(),
+        }
+    }
+
+    /// Log notices at most once per endpoint per `log_interval`.
+    pub fn with_log_interval(mut self, log_interval: Duration) -> Self {
+        self.log_interval = log_interval;
+        self
+    }
+
+    /// Pass notices to `callback` rather than logging them.
+    pub fn with_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&DeprecationNotice) + Send + Sync + 'static,
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    fn report(&self, endpoint: String, notice: &DeprecationNotice) {
+        if let Some(callback) = &self.callback {
+            callback(notice);
+            return;
+        }
+        let now = Instant::now();
+        {
+            let mut logged = self.logged.lock().expect("deprecation lock poisoned");
+            if logged
+                .get(&endpoint)
+                .is_some_and(|at| now.duration_since(*at) < self.log_interval)
+            {
+                return;
+            }
+            logged.retain(|_, at| now.duration_since(*at) < self.log_interval);
+            logged.insert(endpoint, now);
+        }
+        let sunset = notice.sunset.map(httpdate::fmt_http_date);
+        tracing::warn!(
+            method = %notice.method,
+            url = %notice.url,
+            deprecated = notice.deprecated,
+            sunset = sunset.as_deref(),
+            warnings = ?notice.warnings,
+            "Deprecated endpoint"
+        );
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for DeprecationMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let method = req.method().clone();
+        let mut endpoint = req.url().clone();
+        endpoint.set_query(None);
+        endpoint.set_fragment(None);
+        let endpoint = format!("{} {}", method, remove_credentials(&endpoint));
+        let url = remove_credentials(req.url()).into_owned();
+
+        let res = next.run(req, extensions).await?;
+        if let Some(notice) = DeprecationNotice::from_headers(method, url, res.headers()) {
+            self.report(endpoint, &notice);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn parses_structured_and_http_dates() {
+        let headers: HeaderMap = vec![
+            (DEPRECATION, HeaderValue::from_static("@1688169599")),
+            (
+                SUNSET,
+                HeaderValue::from_static("Sun, 30 Jun 2024 23:59:59 GMT"),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let notice = DeprecationNotice::from_headers(
+            Method::GET,
+            "https://api.example.com/v1".to_owned(),
+            &headers,
+        )
+        .unwrap();
+        assert!(notice.deprecated);
+        assert_eq!(
+            notice.deprecated_at,
+            Some(UNIX_EPOCH + Duration::from_secs(1688169599))
+        );
+        assert_eq!(
+            notice.sunset,
+            Some(UNIX_EPOCH + Duration::from_secs(1719791999))
+        );
+        assert!(
+            DeprecationNotice::from_headers(Method::GET, String::new(), &HeaderMap::new())
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn deprecated_responses_are_reported() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::path("/v1/users"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("deprecation", "true")
+                    .insert_header("warning", "299 - \"Use /v2/users\""),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(wiremock::matchers::path("/v2/users"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(DeprecationMiddleware::new().with_callback({
+                let reported = reported.clone();
+                move |notice| reported.lock().unwrap().push(notice.clone())
+            }))
+            .build();
+        for path in ["/v1/users", "/v2/users"] {
+            client
+                .get(format!("{}{}", server.uri(), path))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0].url.ends_with("/v1/users"));
+        assert!(reported[0].deprecated);
+        assert_eq!(reported[0].deprecated_at, None);
+        assert_eq!(reported[0].warnings, ["299 - \"Use /v2/users\""]);
+    }
+}
@@ -0,0 +1,386 @@
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http::{Extensions, HeaderMap};
+use reqwest::{Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{Middleware, Next, RedactionPolicy, Result, REDACTED};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+use crate::reqwest_otel_span_builder::redaction_policy;
+
+/// Extension dumping a single request with a [`DumpMiddleware`], even while its environment
+/// variable toggle is off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnableDump;
+
+/// Extension disabling [`DumpMiddleware`] for a single request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DisableDump;
+
+type Writer = Pin<Box<dyn AsyncWrite + Send>>;
+
+#[derive(Clone)]
+enum Sink {
+    Writer(Arc<Mutex<Writer>>),
+    Directory(PathBuf),
+}
+
+/// `DumpMiddleware` writes the requests and responses of a client, heads and bodies, to a writer
+/// or to a directory, to debug integrations without capturing the traffic.
+///
+/// The exchanges are written as text, with the request lines prefixed by `>` and the response
+/// ones by `<`, followed by their bodies up to [`with_max_body_size`](Self::with_max_body_size),
+/// 64KiB by default. Each exchange gets its own file with [`to_directory`](Self::to_directory).
+/// URLs, headers and bodies are redacted by the [`RedactionPolicy`] or [`SensitiveHeaders`]
+/// extension of requests, or by the policy set with
+/// [`with_redaction_policy`](Self::with_redaction_policy) without either.
+///
+/// Dumping can be toggled at runtime with an environment variable, see
+/// [`with_env_toggle`](Self::with_env_toggle), and for single requests with the [`EnableDump`]
+/// and [`DisableDump`] extensions.
+///
+/// The bodies of dumped responses are buffered, which makes this middleware unsuited to large
+/// downloads.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::DumpMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(DumpMiddleware::to_directory("/tmp/http-dumps").with_env_toggle("HTTP_DUMP"))
+///     .build();
+/// ```
+///
+/// [`SensitiveHeaders`]: crate::SensitiveHeaders
+#[derive(Clone)]
+pub struct DumpMiddleware {
+    sink: Sink,
+    max_body_size: usize,
+    env_toggle: Option<String>,
+    policy: RedactionPolicy,
+    counter: Arc<AtomicU64>,
+}
+
+impl DumpMiddleware {
+    /// Construct `DumpMiddleware` writing the exchanges to `writer`, e.g. `tokio::io::stderr()`.
+    pub fn to_writer<W: AsyncWrite + Send + 'static>(writer: W) -> Self {
+        Self::new(Sink::Writer(Arc::new(Mutex::new(Box::pin(writer)))))
+    }
+
+    /// Construct `DumpMiddleware` writing each exchange to a file of the `directory`, named after
+    /// the time it was sent.
+    pub fn to_directory(directory: impl Into<PathBuf>) -> Self {
+        Self::new(Sink::Directory(directory.into()))
+    }
+
+    fn new(sink: Sink) -> Self {
+        Self {
+            sink,
+            max_body_size: 64 * 1024,
+            env_toggle: None,
+            policy: RedactionPolicy::new(),
+            counter: Arc::default(),
+        }
+    }
+
+    /// Write at most `max_body_size` bytes of each body.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Only dump requests while the `var` environment variable is set, to anything but `0` or
+    /// `false`. It is read for each request, so that dumping can be toggled at runtime.
+    pub fn with_env_toggle(mut self, var: impl Into<String>) -> Self {
+        self.env_toggle = Some(var.into());
+        self
+    }
+
+    /// Redact the exchanges of requests without a redaction extension by `policy`.
+    pub fn with_redaction_policy(mut self, policy: RedactionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    fn enabled(&self, extensions: &Extensions) -> bool {
+        if extensions.get::<DisableDump>().is_some() {
+            return false;
+        }
+        if extensions.get::<EnableDump>().is_some() {
+            return true;
+        }
+        match &self.env_toggle {
+            Some(var) => std::env::var(var)
+                .map(|value| !matches!(value.as_str(), "" | "0" | "false"))
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    fn write_head(
+        &self,
+        dump: &mut Vec<u8>,
+        prefix: &str,
+        line: &str,
+        headers: &HeaderMap,
+        policy: &RedactionPolicy,
+    ) {
+        dump.extend_from_slice(format!("{} {}\n", prefix, line).as_bytes());
+        for (name, value) in headers {
+            dump.extend_from_slice(format!("{} {}: ", prefix, name).as_bytes());
+            if policy.is_redacted(name, value) {
+                dump.extend_from_slice(REDACTED.as_bytes());
+            } else {
+                dump.extend_from_slice(value.as_bytes());
+            }
+            dump.push(b'\n');
+        }
+        dump.extend_from_slice(format!("{}\n", prefix).as_bytes());
+    }
+
+    fn write_body(&self, dump: &mut Vec<u8>, body: &[u8], policy: &RedactionPolicy) {
+        let body = policy.redact_body(body);
+        if body.len() > self.max_body_size {
+            dump.extend_from_slice(&body[..self.max_body_size]);
+            dump.extend_from_slice(
+                format!("\n[{} more bytes]", body.len() - self.max_body_size).as_bytes(),
+            );
+        } else {
+            dump.extend_from_slice(&body);
+        }
+        if !body.is_empty() {
+            dump.extend_from_slice(b"\n");
+        }
+    }
+
+    async fn flush(&self, dump: &[u8]) -> io::Result<()> {
+        match &self.sink {
+            Sink::Writer(writer) => {
+                let mut writer = writer.lock().await;
+                writer.write_all(dump).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await
+            }
+            Sink::Directory(directory) => {
+                let millis = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis();
+                let n = self.counter.fetch_add(1, Ordering::Relaxed);
+                let path = directory.join(format!("{}-{}.http", millis, n));
+                tokio::fs::write(path, dump).await
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for DumpMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !self.enabled(extensions) {
+            return next.run(req, extensions).await;
+        }
+        let policy = redaction_policy(extensions, &self.policy);
+
+        let mut dump = Vec::new();
+        let line = format!(
+            "{} {} {:?}",
+            req.method(),
+            policy.redact_url(req.url()),
+            req.version()
+        );
+        self.write_head(&mut dump, ">", &line, req.headers(), &policy);
+        match req.body().map(|body| body.as_bytes()) {
+            Some(Some(body)) => self.write_body(&mut dump, body, &policy),
+            Some(None) => dump.extend_from_slice(b"[streaming body]\n"),
+            None => {}
+        }
+        dump.push(b'\n');
+
+        let result = next.run(req, extensions).await;
+        let result = match result {
+            Ok(mut res) => {
+                let line = format!("{:?} {}", res.version(), res.status());
+                self.write_head(&mut dump, "<", &line, res.headers(), &policy);
+                let url = res.url().clone();
+                let status = res.status();
+                let version = res.version();
+                let headers = res.headers().clone();
+                let res_extensions = std::mem::take(res.extensions_mut());
+                match res.bytes().await {
+                    Ok(body) => {
+                        self.write_body(&mut dump, &body, &policy);
+                        let mut res = http::Response::builder()
+                            .url(url)
+                            .body(body)
+                            .expect("response is valid");
+                        *res.status_mut() = status;
+                        *res.version_mut() = version;
+                        *res.headers_mut() = headers;
+                        res.extensions_mut().extend(res_extensions);
+                        Ok(Response::from(res))
+                    }
+                    Err(err) => {
+                        dump.extend_from_slice(format!("[body error: {}]\n", err).as_bytes());
+                        Err(err.into())
+                    }
+                }
+            }
+            Err(err) => {
+                dump.extend_from_slice(format!("< [error: {}]\n", err).as_bytes());
+                Err(err)
+            }
+        };
+
+        if let Err(err) = self.flush(&dump).await {
+            tracing::warn!("Failed to dump request: {}", err);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::task::{Context, Poll};
+
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl AsyncWrite for Buffer {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn exchanges_are_dumped() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(201).set_body_string("0123456789"))
+            .mount(&server)
+            .await;
+
+        let buffer = Buffer::default();
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(DumpMiddleware::to_writer(buffer.clone()).with_max_body_size(4))
+            .build();
+        let res = client
+            .post(server.uri())
+            .header("authorization", "Bearer secret")
+            .body("ping")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.url().as_str(), format!("{}/", server.uri()));
+        assert_eq!(res.text().await.unwrap(), "0123456789");
+        client
+            .get(server.uri())
+            .with_extension(DisableDump)
+            .send()
+            .await
+            .unwrap();
+
+        let dump = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(dump.starts_with(&format!("> POST {}/ HTTP/1.1\n", server.uri())));
+        assert!(dump.contains("> authorization: [REDACTED]\n"));
+        assert!(dump.contains(">\nping\n\n< HTTP/1.1 201 Created\n"));
+        assert!(dump.contains("<\n0123\n[6 more bytes]\n"));
+        assert_eq!(
+            dump.matches("> POST").count() + dump.matches("> GET").count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn redaction_policy_extension_is_honoured() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"token":"abc"}"#))
+            .mount(&server)
+            .await;
+
+        let buffer = Buffer::default();
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(DumpMiddleware::to_writer(buffer.clone()))
+            .build();
+        let res = client
+            .post(format!("{}/login?access_token=secret", server.uri()))
+            .header("x-api-key", "secret")
+            .body(r#"{"user":"ana","password":"secret"}"#)
+            .with_extension(
+                RedactionPolicy::new()
+                    .with_header(http::HeaderName::from_static("x-api-key"))
+                    .with_json_pointer("/password")
+                    .with_json_pointer("/token"),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().await.unwrap(), r#"{"token":"abc"}"#);
+
+        let dump = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(dump.contains("/login?access_token=[REDACTED] HTTP/1.1\n"));
+        assert!(dump.contains("> x-api-key: [REDACTED]\n"));
+        assert!(dump.contains(r#""password":"[REDACTED]""#));
+        assert!(dump.contains(r#""user":"ana""#));
+        assert!(dump.contains(r#"{"token":"[REDACTED]"}"#));
+        assert!(!dump.contains("secret"));
+        assert!(!dump.contains("abc"));
+    }
+
+    #[tokio::test]
+    async fn env_toggle_disables_dumps() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let buffer = Buffer::default();
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(
+                DumpMiddleware::to_writer(buffer.clone())
+                    .with_env_toggle("REQWEST_TRACING_TEST_DUMP_UNSET"),
+            )
+            .build();
+        client.get(server.uri()).send().await.unwrap();
+        assert!(buffer.0.lock().unwrap().is_empty());
+
+        client
+            .get(server.uri())
+            .with_extension(EnableDump)
+            .send()
+            .await
+            .unwrap();
+        assert!(!buffer.0.lock().unwrap().is_empty());
+    }
+}
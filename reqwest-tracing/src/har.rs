@@ -0,0 +1,470 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use http::{Extensions, HeaderMap, Version};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Middleware, Next, RedactionPolicy, Result, REDACTED};
+use serde::Serialize;
+
+use crate::reqwest_otel_span_builder::redaction_policy;
+
+#[derive(Serialize)]
+struct Log<'a> {
+    log: LogContent<'a>,
+}
+
+#[derive(Serialize)]
+struct LogContent<'a> {
+    version: &'static str,
+    creator: Creator,
+    entries: &'a [Entry],
+}
+
+#[derive(Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: Cache,
+    timings: Timings,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Cache {}
+
+#[derive(Serialize)]
+struct Timings {
+    blocked: f64,
+    dns: f64,
+    connect: f64,
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+/// Records the traffic of a client as an [HTTP Archive](http://www.softwareishard.com/blog/har-12-spec/)
+/// 1.2, with a [`HarMiddleware`], to analyse it in the developer tools of browsers or share it.
+///
+/// Bodies are recorded up to [`with_max_body_size`](Self::with_max_body_size), 1MiB by default,
+/// and when they are UTF-8. URLs, headers and bodies are redacted by the [`RedactionPolicy`] or
+/// [`SensitiveHeaders`] extension of requests, or by the policy set with
+/// [`with_redaction_policy`](Self::with_redaction_policy) without either.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{HarMiddleware, HarRecorder};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let recorder = HarRecorder::new();
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(HarMiddleware::new(recorder.clone()))
+///     .build();
+/// client.get("https://truelayer.com").send().await?;
+/// recorder.flush_to_file("session.har")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`SensitiveHeaders`]: crate::SensitiveHeaders
+#[derive(Clone)]
+pub struct HarRecorder {
+    entries: Arc<Mutex<Vec<Entry>>>,
+    max_body_size: usize,
+    redact_query: bool,
+    policy: RedactionPolicy,
+}
+
+impl HarRecorder {
+    /// Create a new, empty, [`HarRecorder`].
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::default(),
+            max_body_size: 1024 * 1024,
+            redact_query: false,
+            policy: RedactionPolicy::new(),
+        }
+    }
+
+    /// Record at most `max_body_size` bytes of each body.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Replace the values of all the query parameters of URLs with `[REDACTED]`, whatever the
+    /// redaction policy.
+    pub fn with_redacted_query(mut self) -> Self {
+        self.redact_query = true;
+        self
+    }
+
+    /// Redact the requests without a redaction extension by `policy`.
+    pub fn with_redaction_policy(mut self, policy: RedactionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The number of requests recorded.
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("HAR lock poisoned").len()
+    }
+
+    /// Whether no request was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the recorded requests to `writer` as a HAR document.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let entries = self.entries.lock().expect("HAR lock poisoned");
+        let log = Log {
+            log: LogContent {
+                version: "1.2",
+                creator: Creator {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries: &entries,
+            },
+        };
+        serde_json::to_writer_pretty(writer, &log).map_err(io::Error::from)
+    }
+
+    /// Writes the recorded requests to the file at `path`, and forgets them.
+    pub fn flush_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        self.entries.lock().expect("HAR lock poisoned").clear();
+        Ok(())
+    }
+
+    /// The text of `body` and a comment explaining why it is missing or truncated.
+    fn text(&self, body: &[u8]) -> (Option<String>, Option<String>) {
+        let truncated = body.len() > self.max_body_size;
+        let recorded = &body[..body.len().min(self.max_body_size)];
+        let text = match std::str::from_utf8(recorded) {
+            Ok(text) => text,
+            // The body was cut in the middle of a character.
+            Err(err) if truncated && err.error_len().is_none() => {
+                std::str::from_utf8(&recorded[..err.valid_up_to()]).expect("valid prefix")
+            }
+            Err(_) => return (None, Some("binary body omitted".to_owned())),
+        };
+        let comment = truncated.then(|| format!("truncated to {} bytes", text.len()));
+        (Some(text.to_owned()), comment)
+    }
+}
+
+impl Default for HarRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `url` without fragment, redacted by `policy`.
+fn url(url: &Url, policy: &RedactionPolicy) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    policy.redact_url(&url).into_owned()
+}
+
+fn query_string(url: &Url, policy: &RedactionPolicy) -> Vec<NameValue> {
+    url.query_pairs()
+        .map(|(name, value)| NameValue {
+            value: if policy.is_sensitive_query_param(&name) {
+                REDACTED.to_owned()
+            } else {
+                value.into_owned()
+            },
+            name: name.into_owned(),
+        })
+        .collect()
+}
+
+fn headers(headers: &HeaderMap, policy: &RedactionPolicy) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| NameValue {
+            name: name.to_string(),
+            value: if policy.is_redacted(name, value) {
+                REDACTED.to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            },
+        })
+        .collect()
+}
+
+fn mime_type(headers: &HeaderMap) -> String {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn http_version(version: Version) -> String {
+    format!("{:?}", version)
+}
+
+fn millis(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// `HarMiddleware` records the requests of a client, and their responses, in a [`HarRecorder`].
+///
+/// Requests failing without a response aren't recorded. The bodies of responses are buffered to
+/// be recorded, which makes this middleware unsuited to large downloads.
+#[derive(Clone)]
+pub struct HarMiddleware {
+    recorder: HarRecorder,
+}
+
+impl HarMiddleware {
+    /// Construct `HarMiddleware` recording in `recorder`.
+    pub fn new(recorder: HarRecorder) -> Self {
+        Self { recorder }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for HarMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let recorder = &self.recorder;
+        let mut policy = redaction_policy(extensions, &recorder.policy);
+        if recorder.redact_query {
+            policy = policy.with_all_query_params();
+        }
+        let post_data = req.body().map(|body| match body.as_bytes() {
+            Some(bytes) => {
+                let (text, comment) = recorder.text(&policy.redact_body(bytes));
+                PostData {
+                    mime_type: mime_type(req.headers()),
+                    text,
+                    comment,
+                }
+            }
+            None => PostData {
+                mime_type: mime_type(req.headers()),
+                text: None,
+                comment: Some("streaming body omitted".to_owned()),
+            },
+        });
+        let request = HarRequest {
+            method: req.method().to_string(),
+            url: url(req.url(), &policy),
+            http_version: http_version(req.version()),
+            cookies: Vec::new(),
+            headers: headers(req.headers(), &policy),
+            query_string: query_string(req.url(), &policy),
+            post_data,
+            headers_size: -1,
+            body_size: match req.body() {
+                Some(body) => body.as_bytes().map_or(-1, |bytes| bytes.len() as i64),
+                None => 0,
+            },
+        };
+
+        let started_date_time: DateTime<Utc> = Utc::now();
+        let started_at = Instant::now();
+        let mut res = next.run(req, extensions).await?;
+        let wait = started_at.elapsed();
+
+        let url = res.url().clone();
+        let status = res.status();
+        let version = res.version();
+        let res_headers = res.headers().clone();
+        let res_extensions = std::mem::take(res.extensions_mut());
+        let body = res.bytes().await?;
+        let receive = started_at.elapsed() - wait;
+
+        let (text, comment) = recorder.text(&policy.redact_body(&body));
+        let response = HarResponse {
+            status: status.as_u16(),
+            status_text: status.canonical_reason().unwrap_or_default().to_owned(),
+            http_version: http_version(version),
+            cookies: Vec::new(),
+            headers: headers(&res_headers, &policy),
+            content: Content {
+                size: body.len() as i64,
+                mime_type: mime_type(&res_headers),
+                text,
+                comment,
+            },
+            redirect_url: res_headers
+                .get(http::header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_owned(),
+            headers_size: -1,
+            body_size: body.len() as i64,
+        };
+        recorder
+            .entries
+            .lock()
+            .expect("HAR lock poisoned")
+            .push(Entry {
+                started_date_time: started_date_time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                time: millis(wait + receive),
+                request,
+                response,
+                cache: Cache {},
+                timings: Timings {
+                    blocked: -1.0,
+                    dns: -1.0,
+                    connect: -1.0,
+                    send: 0.0,
+                    wait: millis(wait),
+                    receive: millis(receive),
+                },
+            });
+
+        let mut res = http::Response::builder()
+            .url(url)
+            .body(body)
+            .expect("response is valid");
+        *res.status_mut() = status;
+        *res.version_mut() = version;
+        *res.headers_mut() = res_headers;
+        res.extensions_mut().extend(res_extensions);
+        Ok(Response::from(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn traffic_is_recorded_as_har() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "application/json")
+                    .set_body_string(r#"{"id":"0123456789"}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let recorder = HarRecorder::new()
+            .with_max_body_size(8)
+            .with_redacted_query();
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(HarMiddleware::new(recorder.clone()))
+            .build();
+        let res = client
+            .post(format!("{}/items?token=secret", server.uri()))
+            .header("authorization", "Bearer secret")
+            .body("ping")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            res.url().as_str(),
+            format!("{}/items?token=secret", server.uri())
+        );
+        assert_eq!(res.text().await.unwrap(), r#"{"id":"0123456789"}"#);
+        assert_eq!(recorder.len(), 1);
+
+        let mut har = Vec::new();
+        recorder.write_to(&mut har).unwrap();
+        let har: serde_json::Value = serde_json::from_slice(&har).unwrap();
+        let entry = &har["log"]["entries"][0];
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(
+            entry["request"]["url"],
+            format!("{}/items?token=[REDACTED]", server.uri())
+        );
+        assert_eq!(entry["request"]["queryString"][0]["value"], "[REDACTED]");
+        assert_eq!(entry["request"]["headers"][0]["value"], "[REDACTED]");
+        assert_eq!(entry["request"]["postData"]["text"], "ping");
+        assert_eq!(entry["response"]["status"], 200);
+        assert_eq!(entry["response"]["content"]["size"], 19);
+        assert_eq!(entry["response"]["content"]["text"], r#"{"id":"0"#);
+        assert_eq!(
+            entry["response"]["content"]["comment"],
+            "truncated to 8 bytes"
+        );
+        assert!(!har.to_string().contains("secret"));
+    }
+}
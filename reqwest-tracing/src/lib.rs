@@ -20,6 +20,26 @@
 //! # }
 //! ```
 //!
+//! With an OpenTelemetry feature enabled, the OpenTelemetry context is propagated to servers
+//! through the global text map propagator. [`PropagationMiddleware`] sets the W3C Trace Context,
+//! B3 or Jaeger headers whatever the propagator.
+//!
+//! [`RequestIdMiddleware`] gives requests an `x-request-id`, to correlate the logs of clients and
+//! servers, and [`PropagateHeadersMiddleware`] forwards selected headers of the incoming
+//! request, such as a tenant identifier.
+//!
+//! [`LoggingMiddleware`] logs requests and their outcome, at a level depending on their status,
+//! and [`CurlMiddleware`] logs them as `curl` commands to reproduce them. [`DumpMiddleware`]
+//! writes whole requests and responses to a file or a directory, and with the `har` feature,
+//! [`HarMiddleware`] records them as an HTTP Archive.
+//! [`TimingMiddleware`] breaks the latency of requests down, into the time queued behind the
+//! other middleware and that of each attempt, and [`SlowRequestMiddleware`] reports the requests
+//! taking longer than a threshold. [`DeprecationMiddleware`] warns about responses with
+//! `Deprecation`, `Sunset` or `Warning` headers.
+//!
+//! With the `sentry` feature, [`SentryMiddleware`] records requests as Sentry breadcrumbs, and
+//! can capture the failed ones.
+//!
 //! To customise the span names use [`OtelName`].
 //! ```no_run
 //! # use reqwest_middleware::Result;
@@ -82,6 +102,28 @@
 //!     .build();
 //! ```
 
+/// Emits an event at a level only known at runtime.
+macro_rules! event_at {
+    ($level:expr, $($args:tt)+) => {
+        match $level {
+            tracing::Level::ERROR => tracing::error!($($args)+),
+            tracing::Level::WARN => tracing::warn!($($args)+),
+            tracing::Level::INFO => tracing::info!($($args)+),
+            tracing::Level::DEBUG => tracing::debug!($($args)+),
+            _ => tracing::trace!($($args)+),
+        }
+    };
+}
+
+mod curl;
+#[cfg(not(target_arch = "wasm32"))]
+mod deprecation;
+#[cfg(not(target_arch = "wasm32"))]
+mod dump;
+#[cfg(all(feature = "har", not(target_arch = "wasm32")))]
+mod har;
+#[cfg(not(target_arch = "wasm32"))]
+mod logging;
 mod middleware;
 #[cfg(any(
     feature = "opentelemetry_0_20",
@@ -90,15 +132,59 @@ mod middleware;
     feature = "opentelemetry_0_23",
 ))]
 mod otel;
+mod propagate_headers;
+#[cfg(any(
+    feature = "opentelemetry_0_20",
+    feature = "opentelemetry_0_21",
+    feature = "opentelemetry_0_22",
+    feature = "opentelemetry_0_23",
+))]
+mod propagation;
+mod request_id;
 mod reqwest_otel_span_builder;
+#[cfg(feature = "sentry")]
+mod sentry;
+#[cfg(not(target_arch = "wasm32"))]
+mod slow_request;
+#[cfg(not(target_arch = "wasm32"))]
+mod timing;
+pub use curl::{curl_command, CurlMiddleware};
+#[cfg(not(target_arch = "wasm32"))]
+pub use deprecation::{DeprecationMiddleware, DeprecationNotice, DEPRECATION, SUNSET};
+#[cfg(not(target_arch = "wasm32"))]
+pub use dump::{DisableDump, DumpMiddleware, EnableDump};
+#[cfg(all(feature = "har", not(target_arch = "wasm32")))]
+pub use har::{HarMiddleware, HarRecorder};
+#[cfg(not(target_arch = "wasm32"))]
+pub use logging::LoggingMiddleware;
 pub use middleware::TracingMiddleware;
+pub use propagate_headers::{PropagateHeadersMiddleware, PropagatedHeaders};
+#[cfg(any(
+    feature = "opentelemetry_0_20",
+    feature = "opentelemetry_0_21",
+    feature = "opentelemetry_0_22",
+    feature = "opentelemetry_0_23",
+))]
+pub use propagation::{
+    B3Multi, B3Single, JaegerTraceId, PropagationFormat, PropagationMiddleware, TraceContext,
+    TraceFormat, W3cTraceContext,
+};
+pub use request_id::{RequestId, RequestIdMiddleware};
 pub use reqwest_otel_span_builder::{
     default_on_request_end, default_on_request_failure, default_on_request_success,
     default_span_name, DefaultSpanBackend, DisableOtelPropagation, OtelName, OtelPathNames,
-    ReqwestOtelSpanBackend, SpanBackendWithUrl, ERROR_CAUSE_CHAIN, ERROR_MESSAGE,
-    HTTP_REQUEST_METHOD, HTTP_RESPONSE_STATUS_CODE, OTEL_KIND, OTEL_NAME, OTEL_STATUS_CODE,
-    SERVER_ADDRESS, SERVER_PORT, URL_FULL, URL_SCHEME, USER_AGENT_ORIGINAL,
+    RedactedHeaders, ReqwestOtelSpanBackend, SensitiveHeaders, SpanBackendWithHeaders,
+    SpanBackendWithUrl, ERROR_CAUSE_CHAIN, ERROR_MESSAGE, HTTP_REQUEST_HEADERS,
+    HTTP_REQUEST_METHOD, HTTP_REQUEST_RESEND_COUNT, HTTP_RESPONSE_HEADERS,
+    HTTP_RESPONSE_STATUS_CODE, OTEL_KIND, OTEL_NAME, OTEL_STATUS_CODE, SERVER_ADDRESS, SERVER_PORT,
+    URL_FULL, URL_SCHEME, USER_AGENT_ORIGINAL,
 };
+#[cfg(feature = "sentry")]
+pub use sentry::SentryMiddleware;
+#[cfg(not(target_arch = "wasm32"))]
+pub use slow_request::{SlowRequest, SlowRequestMiddleware};
+#[cfg(not(target_arch = "wasm32"))]
+pub use timing::{AttemptTiming, RequestTimings, TimingMiddleware};
 
 #[doc(hidden)]
 pub mod reqwest_otel_span_macro;
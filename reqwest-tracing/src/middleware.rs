@@ -1,11 +1,16 @@
 use http::Extensions;
 use reqwest::{Request, Response};
-use reqwest_middleware::{Middleware, Next, Result};
+use reqwest_middleware::{Middleware, Next, ResendCount, Result};
 use tracing::Instrument;
 
-use crate::{DefaultSpanBackend, ReqwestOtelSpanBackend};
+use crate::{DefaultSpanBackend, ReqwestOtelSpanBackend, HTTP_REQUEST_RESEND_COUNT};
 
 /// Middleware for tracing requests using the current Opentelemetry Context.
+///
+/// Each request gets a span following the OpenTelemetry semantic conventions for HTTP clients,
+/// created and completed by the [`ReqwestOtelSpanBackend`], which can be replaced to customise
+/// its name and attributes. Added after a retry middleware, each attempt gets its own span,
+/// with the `http.request.resend_count` attribute of the [`ResendCount`] of retries.
 pub struct TracingMiddleware<S: ReqwestOtelSpanBackend> {
     span_backend: std::marker::PhantomData<S>,
 }
@@ -43,6 +48,10 @@ where
         next: Next<'_>,
     ) -> Result<Response> {
         let request_span = ReqwestOtelSpan::on_request_start(&req, extensions);
+        let resend_count = ResendCount::get(extensions);
+        if resend_count > 0 {
+            request_span.record(HTTP_REQUEST_RESEND_COUNT, resend_count);
+        }
 
         let outcome_future = async {
             #[cfg(any(
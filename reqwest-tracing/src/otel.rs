@@ -3,6 +3,8 @@ use reqwest::Request;
 use std::str::FromStr;
 use tracing::Span;
 
+use crate::TraceContext;
+
 /// Injects the given OpenTelemetry Context into a reqwest::Request headers to allow propagation downstream.
 pub fn inject_opentelemetry_context_into_request(mut request: Request) -> Request {
     #[cfg(feature = "opentelemetry_0_20")]
@@ -36,6 +38,47 @@ pub fn inject_opentelemetry_context_into_request(mut request: Request) -> Reques
     request
 }
 
+/// Returns the OpenTelemetry context of the current span, if it is part of a trace.
+pub(crate) fn current_trace_context() -> Option<TraceContext> {
+    macro_rules! current_trace_context {
+        ($otel:ident, $tracing_otel:ident) => {{
+            use $otel::baggage::BaggageExt;
+            use $otel::trace::TraceContextExt;
+            use $tracing_otel::OpenTelemetrySpanExt;
+
+            let context = Span::current().context();
+            let span = context.span();
+            let span_context = span.span_context();
+            if span_context.is_valid() {
+                return Some(TraceContext {
+                    trace_id: u128::from_be_bytes(span_context.trace_id().to_bytes()),
+                    span_id: u64::from_be_bytes(span_context.span_id().to_bytes()),
+                    sampled: span_context.is_sampled(),
+                    trace_state: span_context.trace_state().header(),
+                    baggage: context
+                        .baggage()
+                        .iter()
+                        .map(|(key, (value, _))| {
+                            (key.as_str().to_owned(), value.as_str().into_owned())
+                        })
+                        .collect(),
+                });
+            }
+        }};
+    }
+
+    #[cfg(feature = "opentelemetry_0_20")]
+    current_trace_context!(opentelemetry_0_20_pkg, tracing_opentelemetry_0_21_pkg);
+    #[cfg(feature = "opentelemetry_0_21")]
+    current_trace_context!(opentelemetry_0_21_pkg, tracing_opentelemetry_0_22_pkg);
+    #[cfg(feature = "opentelemetry_0_22")]
+    current_trace_context!(opentelemetry_0_22_pkg, tracing_opentelemetry_0_23_pkg);
+    #[cfg(feature = "opentelemetry_0_23")]
+    current_trace_context!(opentelemetry_0_23_pkg, tracing_opentelemetry_0_24_pkg);
+
+    None
+}
+
 // "traceparent" => https://www.w3.org/TR/trace-context/#trace-context-http-headers-format
 
 /// Injector used via opentelemetry propagator to tell the extractor how to insert the "traceparent" header value
@@ -0,0 +1,162 @@
+use http::Extensions;
+use reqwest::header::{HeaderMap, HeaderName};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+/// The headers of the incoming request being handled, from which [`PropagateHeadersMiddleware`]
+/// copies the selected ones onto outgoing requests.
+///
+/// They are taken from the extensions of the outgoing request, or else from the task-local set
+/// by [`PropagatedHeaders::scope`].
+#[derive(Clone, Debug, Default)]
+pub struct PropagatedHeaders(pub HeaderMap);
+
+#[cfg(not(target_arch = "wasm32"))]
+tokio::task_local! {
+    static CURRENT_HEADERS: PropagatedHeaders;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PropagatedHeaders {
+    /// Runs `f` as part of the handling of an incoming request with `headers`, which the
+    /// requests sent by a [`PropagateHeadersMiddleware`] then forward.
+    pub async fn scope<F: std::future::Future>(headers: HeaderMap, f: F) -> F::Output {
+        CURRENT_HEADERS.scope(Self(headers), f).await
+    }
+
+    /// The headers of the incoming request being handled, if within [`PropagatedHeaders::scope`].
+    pub fn current() -> Option<Self> {
+        CURRENT_HEADERS.try_with(Self::clone).ok()
+    }
+}
+
+/// `PropagateHeadersMiddleware` forwards selected headers of the incoming request being handled,
+/// such as a tenant identifier, a locale or feature flags, on every outgoing request.
+///
+/// Servers give the headers of incoming requests with [`PropagatedHeaders::scope`], or as a
+/// [`PropagatedHeaders`] extension of outgoing requests. Only the headers the middleware is
+/// configured with are copied, all their values, and those already set on the outgoing request
+/// are left untouched.
+///
+/// ```no_run
+/// use http::{HeaderMap, HeaderName};
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{PropagateHeadersMiddleware, PropagatedHeaders};
+///
+/// # async fn handle_incoming_request(incoming: HeaderMap) -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(PropagateHeadersMiddleware::new([
+///         HeaderName::from_static("x-tenant-id"),
+///         HeaderName::from_static("accept-language"),
+///     ]))
+///     .build();
+/// PropagatedHeaders::scope(incoming, async {
+///     client.get("https://truelayer.com").send().await
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PropagateHeadersMiddleware {
+    names: Vec<HeaderName>,
+}
+
+impl PropagateHeadersMiddleware {
+    /// Construct `PropagateHeadersMiddleware` forwarding the headers called `names`.
+    pub fn new(names: impl IntoIterator<Item = HeaderName>) -> Self {
+        Self {
+            names: names.into_iter().collect(),
+        }
+    }
+
+    fn propagate(&self, incoming: &HeaderMap, outgoing: &mut HeaderMap) {
+        for name in &self.names {
+            if outgoing.contains_key(name) {
+                continue;
+            }
+            for value in incoming.get_all(name) {
+                outgoing.append(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for PropagateHeadersMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        match extensions.get::<PropagatedHeaders>() {
+            Some(incoming) => self.propagate(&incoming.0, req.headers_mut()),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => {
+                let _ = CURRENT_HEADERS
+                    .try_with(|incoming| self.propagate(&incoming.0, req.headers_mut()));
+            }
+            #[cfg(target_arch = "wasm32")]
+            None => {}
+        }
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest::header::HeaderValue;
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::matchers::header;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn only_selected_missing_headers_are_propagated() {
+        let mut incoming = HeaderMap::new();
+        incoming.append("x-flag", HeaderValue::from_static("a"));
+        incoming.append("x-flag", HeaderValue::from_static("b"));
+        incoming.insert("x-tenant-id", HeaderValue::from_static("incoming"));
+        incoming.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        let mut outgoing = HeaderMap::new();
+        outgoing.insert("x-tenant-id", HeaderValue::from_static("outgoing"));
+
+        PropagateHeadersMiddleware::new([
+            HeaderName::from_static("x-flag"),
+            HeaderName::from_static("x-tenant-id"),
+        ])
+        .propagate(&incoming, &mut outgoing);
+
+        assert_eq!(
+            outgoing.get_all("x-flag").iter().collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(outgoing["x-tenant-id"], "outgoing");
+        assert!(!outgoing.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn task_local_headers_are_propagated() {
+        let server = MockServer::start().await;
+        Mock::given(header("x-tenant-id", "acme"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(PropagateHeadersMiddleware::new([HeaderName::from_static(
+                "x-tenant-id",
+            )]))
+            .build();
+        let mut incoming = HeaderMap::new();
+        incoming.insert("x-tenant-id", HeaderValue::from_static("acme"));
+        let res = PropagatedHeaders::scope(incoming, client.get(server.uri()).send())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+    }
+}
@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use http::Extensions;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+use crate::DisableOtelPropagation;
+
+/// The OpenTelemetry context of the current span, as propagated by [`PropagationMiddleware`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    pub(crate) trace_id: u128,
+    pub(crate) span_id: u64,
+    pub(crate) sampled: bool,
+    pub(crate) trace_state: String,
+    pub(crate) baggage: Vec<(String, String)>,
+}
+
+impl TraceContext {
+    /// The identifier of the trace.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// The identifier of the current span, the parent of the spans of the server.
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// Whether the trace is sampled.
+    pub fn sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// The vendor specific trace state, in its `tracestate` header format.
+    pub fn trace_state(&self) -> &str {
+        &self.trace_state
+    }
+
+    /// The baggage entries of the context.
+    pub fn baggage(&self) -> &[(String, String)] {
+        &self.baggage
+    }
+}
+
+/// A header format to propagate a [`TraceContext`] with, for [`PropagationMiddleware`].
+///
+/// [`W3cTraceContext`], [`B3Single`], [`B3Multi`] and [`JaegerTraceId`] are provided, other
+/// formats can be supported by implementing this trait.
+pub trait TraceFormat: 'static + Send + Sync {
+    /// Adds the headers of `context` to `headers`.
+    fn inject(&self, context: &TraceContext, headers: &mut HeaderMap);
+}
+
+/// The [W3C Trace Context](https://www.w3.org/TR/trace-context/) `traceparent` and
+/// `tracestate` headers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct W3cTraceContext;
+
+impl TraceFormat for W3cTraceContext {
+    fn inject(&self, context: &TraceContext, headers: &mut HeaderMap) {
+        let traceparent = format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            context.trace_id, context.span_id, context.sampled as u8
+        );
+        insert(headers, "traceparent", &traceparent);
+        if !context.trace_state.is_empty() {
+            insert(headers, "tracestate", &context.trace_state);
+        }
+    }
+}
+
+/// The single `b3` header of [Zipkin's B3](https://github.com/openzipkin/b3-propagation).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct B3Single;
+
+impl TraceFormat for B3Single {
+    fn inject(&self, context: &TraceContext, headers: &mut HeaderMap) {
+        let b3 = format!(
+            "{:032x}-{:016x}-{}",
+            context.trace_id, context.span_id, context.sampled as u8
+        );
+        insert(headers, "b3", &b3);
+    }
+}
+
+/// The `X-B3-TraceId`, `X-B3-SpanId` and `X-B3-Sampled` headers of [Zipkin's
+/// B3](https://github.com/openzipkin/b3-propagation).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct B3Multi;
+
+impl TraceFormat for B3Multi {
+    fn inject(&self, context: &TraceContext, headers: &mut HeaderMap) {
+        insert(
+            headers,
+            "x-b3-traceid",
+            &format!("{:032x}", context.trace_id),
+        );
+        insert(headers, "x-b3-spanid", &format!("{:016x}", context.span_id));
+        insert(
+            headers,
+            "x-b3-sampled",
+            if context.sampled { "1" } else { "0" },
+        );
+    }
+}
+
+/// The `uber-trace-id` header of
+/// [Jaeger](https://www.jaegertracing.io/docs/1.57/client-libraries/#propagation-format).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JaegerTraceId;
+
+impl TraceFormat for JaegerTraceId {
+    fn inject(&self, context: &TraceContext, headers: &mut HeaderMap) {
+        // The parent span identifier is deprecated, and always `0`.
+        let uber_trace_id = format!(
+            "{:032x}:{:016x}:0:{}",
+            context.trace_id, context.span_id, context.sampled as u8
+        );
+        insert(headers, "uber-trace-id", &uber_trace_id);
+    }
+}
+
+/// Extension choosing the [`TraceFormat`] of a [`PropagationMiddleware`] for a client or a
+/// single request.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{B3Multi, PropagationFormat, PropagationMiddleware};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(PropagationMiddleware::new())
+///     .build();
+/// // This service still expects B3 headers.
+/// let resp = client
+///     .get("https://legacy.example.com")
+///     .with_extension(PropagationFormat::new(B3Multi))
+///     .send()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PropagationFormat(pub Arc<dyn TraceFormat>);
+
+impl PropagationFormat {
+    /// Construct `PropagationFormat` propagating with `format`.
+    pub fn new<F: TraceFormat>(format: F) -> Self {
+        Self(Arc::new(format))
+    }
+}
+
+/// `PropagationMiddleware` adds the trace context headers of the current OpenTelemetry span to
+/// requests, so that the spans of the servers join the trace.
+///
+/// The [W3C Trace Context](https://www.w3.org/TR/trace-context/) headers, `traceparent` and
+/// `tracestate`, are used by default. Another [`TraceFormat`], such as [`B3Single`],
+/// [`B3Multi`] or [`JaegerTraceId`], can be set with [`with_format`](Self::with_format), or for
+/// a client or a single request with the [`PropagationFormat`] extension.
+///
+/// Unlike [`TracingMiddleware`](crate::TracingMiddleware), which goes through the global text
+/// map propagator of OpenTelemetry, the headers are set whatever propagator is installed. The
+/// [W3C Baggage](https://www.w3.org/TR/baggage/) of the context can be sent as well, in the
+/// `baggage` header, with [`with_baggage`](Self::with_baggage).
+///
+/// Added after a `TracingMiddleware`, the span of the request is the parent of those of the
+/// server. Requests without a current span, or with the [`DisableOtelPropagation`] extension,
+/// are left untouched.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{B3Single, PropagationMiddleware, TracingMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(TracingMiddleware::default())
+///     .with(PropagationMiddleware::new().with_format(B3Single).with_baggage())
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct PropagationMiddleware {
+    format: Arc<dyn TraceFormat>,
+    baggage: bool,
+}
+
+impl PropagationMiddleware {
+    /// Construct `PropagationMiddleware` adding the W3C Trace Context headers.
+    pub fn new() -> Self {
+        Self {
+            format: Arc::new(W3cTraceContext),
+            baggage: false,
+        }
+    }
+
+    /// Propagate the trace context with `format` rather than W3C Trace Context.
+    pub fn with_format<F: TraceFormat>(mut self, format: F) -> Self {
+        self.format = Arc::new(format);
+        self
+    }
+
+    /// Add the `baggage` header as well.
+    pub fn with_baggage(mut self) -> Self {
+        self.baggage = true;
+        self
+    }
+}
+
+impl Default for PropagationMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adds the W3C Baggage header of `context` to `headers`.
+fn inject_baggage(context: &TraceContext, headers: &mut HeaderMap) {
+    if context.baggage.is_empty() {
+        return;
+    }
+    let baggage = context
+        .baggage
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, encode_baggage_value(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+    insert(headers, "baggage", &baggage);
+}
+
+fn insert(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    // Values from the tracing context may not be valid headers, they are skipped then.
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
+
+/// Percent-encodes the characters of `value` not allowed in baggage values.
+fn encode_baggage_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'!' | b'#'..=b'+' | b'-'..=b':' | b'<'..=b'[' | b']'..=b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for PropagationMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if extensions.get::<DisableOtelPropagation>().is_none() {
+            if let Some(context) = crate::otel::current_trace_context() {
+                let format = match extensions.get::<PropagationFormat>() {
+                    Some(PropagationFormat(format)) => format.as_ref(),
+                    None => self.format.as_ref(),
+                };
+                format.inject(&context, req.headers_mut());
+                if self.baggage {
+                    inject_baggage(&context, req.headers_mut());
+                }
+            }
+        }
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> TraceContext {
+        TraceContext {
+            trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736,
+            span_id: 0x00f067aa0ba902b7,
+            sampled: true,
+            trace_state: "congo=t61rcWkgMzE".to_owned(),
+            baggage: vec![
+                ("userId".to_owned(), "alice".to_owned()),
+                ("region".to_owned(), "eu west,1".to_owned()),
+            ],
+        }
+    }
+
+    #[test]
+    fn trace_context_headers_are_injected() {
+        let mut headers = HeaderMap::new();
+        W3cTraceContext.inject(&context(), &mut headers);
+        assert_eq!(
+            headers["traceparent"],
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+        assert_eq!(headers["tracestate"], "congo=t61rcWkgMzE");
+        assert!(!headers.contains_key("baggage"));
+    }
+
+    #[test]
+    fn b3_and_jaeger_headers_are_injected() {
+        let mut headers = HeaderMap::new();
+        B3Single.inject(&context(), &mut headers);
+        B3Multi.inject(&context(), &mut headers);
+        JaegerTraceId.inject(&context(), &mut headers);
+        assert_eq!(
+            headers["b3"],
+            "4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-1"
+        );
+        assert_eq!(headers["x-b3-traceid"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(headers["x-b3-spanid"], "00f067aa0ba902b7");
+        assert_eq!(headers["x-b3-sampled"], "1");
+        assert_eq!(
+            headers["uber-trace-id"],
+            "4bf92f3577b34da6a3ce929d0e0e4736:00f067aa0ba902b7:0:1"
+        );
+    }
+
+    #[test]
+    fn baggage_values_are_encoded() {
+        let mut headers = HeaderMap::new();
+        inject_baggage(&context(), &mut headers);
+        assert_eq!(headers["baggage"], "userId=alice,region=eu%20west%2C1");
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+use std::sync::Arc;
+
+use http::Extensions;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+
+/// The identifier of a request, set by [`RequestIdMiddleware`].
+///
+/// It is kept in the extensions of the request, for the middleware after
+/// `RequestIdMiddleware`, and of the response, to correlate it with the logs of the server:
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{RequestId, RequestIdMiddleware};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(RequestIdMiddleware::new())
+///     .build();
+/// let resp = client.get("https://truelayer.com").send().await?;
+/// if let Some(request_id) = resp.extensions().get::<RequestId>() {
+///     tracing::info!(%request_id, status = %resp.status(), "Request completed");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(pub String);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RequestId {
+    /// Runs `f` as part of the handling of the incoming request `id`, which the requests sent
+    /// by a [`RequestIdMiddleware`] with [`propagate_current`] then reuse.
+    ///
+    /// Servers call this where they handle requests, with the identifier of the incoming
+    /// request, so that the logs of the services involved can be correlated.
+    ///
+    /// [`propagate_current`]: RequestIdMiddleware::propagate_current
+    pub async fn scope<F: std::future::Future>(id: impl Into<String>, f: F) -> F::Output {
+        CURRENT_REQUEST_ID.scope(Self(id.into()), f).await
+    }
+
+    /// The identifier of the incoming request being handled, if within [`RequestId::scope`].
+    pub fn current() -> Option<Self> {
+        CURRENT_REQUEST_ID.try_with(Self::clone).ok()
+    }
+}
+
+type GeneratorFn = dyn Fn() -> String + Send + Sync + 'static;
+
+/// `RequestIdMiddleware` gives requests an identifier, in the `x-request-id` header by default,
+/// to correlate the logs of the client and the server.
+///
+/// Requests which already have the header keep their identifier, as do requests with a
+/// [`RequestId`] extension, whose identifier is used for the header. Otherwise a random UUID is
+/// generated, or an identifier from the generator set with [`with_generator`]. With
+/// [`propagate_current`], the identifier of the incoming request set by [`RequestId::scope`] is
+/// used instead, should there be one.
+///
+/// The identifier is added to the extensions of the request and of the response as a
+/// [`RequestId`]. Retried requests keep the same identifier when the middleware is added before
+/// the retry middleware.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{RequestId, RequestIdMiddleware};
+///
+/// # async fn handle_incoming_request(incoming_id: String) -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(RequestIdMiddleware::new().propagate_current())
+///     .build();
+/// RequestId::scope(incoming_id, async {
+///     // Sent with the `x-request-id` of the incoming request.
+///     client.get("https://truelayer.com").send().await
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`with_generator`]: Self::with_generator
+/// [`propagate_current`]: Self::propagate_current
+#[derive(Clone)]
+pub struct RequestIdMiddleware {
+    header: HeaderName,
+    generator: Arc<GeneratorFn>,
+    propagate_current: bool,
+}
+
+impl RequestIdMiddleware {
+    /// Construct `RequestIdMiddleware` setting the `x-request-id` header to random UUIDs.
+    pub fn new() -> Self {
+        Self {
+            header: HeaderName::from_static("x-request-id"),
+            generator: Arc::new(uuid_v4),
+            propagate_current: false,
+        }
+    }
+
+    /// Set the header holding the identifier.
+    pub fn with_header(mut self, name: HeaderName) -> Self {
+        self.header = name;
+        self
+    }
+
+    /// Generate identifiers with `generator`, e.g. to use ULIDs.
+    pub fn with_generator<F>(mut self, generator: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.generator = Arc::new(generator);
+        self
+    }
+
+    /// Reuse the identifier of the incoming request being handled, see [`RequestId::scope`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn propagate_current(mut self) -> Self {
+        self.propagate_current = true;
+        self
+    }
+
+    fn request_id(&self, req: &Request, extensions: &Extensions) -> RequestId {
+        if let Some(id) = req
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+        {
+            return RequestId(id.to_owned());
+        }
+        if let Some(id) = extensions.get::<RequestId>() {
+            return id.clone();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.propagate_current {
+            if let Some(id) = RequestId::current() {
+                return id;
+            }
+        }
+        RequestId((self.generator)())
+    }
+}
+
+impl Default for RequestIdMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A random, version 4, UUID.
+fn uuid_v4() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("failed to generate a random request id");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for RequestIdMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let id = self.request_id(&req, extensions);
+        let value = HeaderValue::from_str(&id.0).map_err(Error::middleware)?;
+        req.headers_mut().insert(self.header.clone(), value);
+        extensions.insert(id.clone());
+        let mut res = next.run(req, extensions).await?;
+        res.extensions_mut().insert(id);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::matchers::{header, header_exists};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn generated_ids_are_uuids() {
+        let id = uuid_v4();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(id, uuid_v4());
+    }
+
+    #[tokio::test]
+    async fn request_id_is_added_and_exposed_on_response() {
+        let server = MockServer::start().await;
+        Mock::given(header_exists("x-request-id"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(RequestIdMiddleware::new())
+            .build();
+        let res = client.get(server.uri()).send().await.unwrap();
+        assert_eq!(res.extensions().get::<RequestId>().unwrap().0.len(), 36);
+    }
+
+    #[tokio::test]
+    async fn current_request_id_is_propagated() {
+        let server = MockServer::start().await;
+        Mock::given(header("x-request-id", "incoming-1"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(RequestIdMiddleware::new().propagate_current())
+            .build();
+        let res = RequestId::scope("incoming-1", client.get(server.uri()).send())
+            .await
+            .unwrap();
+        assert_eq!(
+            res.extensions().get::<RequestId>(),
+            Some(&RequestId("incoming-1".to_owned()))
+        );
+    }
+}
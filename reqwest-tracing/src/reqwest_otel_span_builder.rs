@@ -1,9 +1,10 @@
 use std::borrow::Cow;
 
-use http::Extensions;
+use http::{Extensions, HeaderMap, HeaderName};
 use matchit::Router;
 use reqwest::{Request, Response, StatusCode as RequestStatusCode, Url};
-use reqwest_middleware::{Error, Result};
+pub use reqwest_middleware::RedactedHeaders;
+use reqwest_middleware::{Error, RedactionPolicy, Result};
 use tracing::{warn, Span};
 
 use crate::reqwest_otel_span;
@@ -28,10 +29,16 @@ pub const OTEL_NAME: &str = "otel.name";
 pub const OTEL_STATUS_CODE: &str = "otel.status_code";
 /// The `http.response.status_code` field added to the span by [`reqwest_otel_span`]
 pub const HTTP_RESPONSE_STATUS_CODE: &str = "http.response.status_code";
+/// The `http.request.resend_count` field added to the span by [`reqwest_otel_span`]
+pub const HTTP_REQUEST_RESEND_COUNT: &str = "http.request.resend_count";
 /// The `error.message` field added to the span by [`reqwest_otel_span`]
 pub const ERROR_MESSAGE: &str = "error.message";
 /// The `error.cause_chain` field added to the span by [`reqwest_otel_span`]
 pub const ERROR_CAUSE_CHAIN: &str = "error.cause_chain";
+/// The `http.request.headers` field added to the span by [`SpanBackendWithHeaders`]
+pub const HTTP_REQUEST_HEADERS: &str = "http.request.headers";
+/// The `http.response.headers` field added to the span by [`SpanBackendWithHeaders`]
+pub const HTTP_RESPONSE_HEADERS: &str = "http.response.headers";
 
 /// [`ReqwestOtelSpanBackend`] allows you to customise the span attached by
 /// [`TracingMiddleware`] to incoming requests.
@@ -119,7 +126,8 @@ impl ReqwestOtelSpanBackend for DefaultSpanBackend {
     }
 }
 
-/// Similar to [`DefaultSpanBackend`] but also adds the `url.full` attribute to request spans.
+/// Similar to [`DefaultSpanBackend`] but also adds the `url.full` attribute to request spans,
+/// redacted by the [`RedactionPolicy`] or [`SensitiveHeaders`] extension of requests.
 ///
 /// [`TracingMiddleware`]: crate::middleware::TracingMiddleware
 pub struct SpanBackendWithUrl;
@@ -127,7 +135,8 @@ pub struct SpanBackendWithUrl;
 impl ReqwestOtelSpanBackend for SpanBackendWithUrl {
     fn on_request_start(req: &Request, ext: &mut Extensions) -> Span {
         let name = default_span_name(req, ext);
-        reqwest_otel_span!(name = name, req, url.full = %remove_credentials(req.url()))
+        let policy = redaction_policy(ext, &RedactionPolicy::new());
+        reqwest_otel_span!(name = name, req, url.full = %policy.redact_url(req.url()))
     }
 
     fn on_request_end(span: &Span, outcome: &Result<Response>, _: &mut Extensions) {
@@ -135,6 +144,37 @@ impl ReqwestOtelSpanBackend for SpanBackendWithUrl {
     }
 }
 
+/// Similar to [`DefaultSpanBackend`] but also adds the `http.request.headers` and
+/// `http.response.headers` attributes to request spans, with the sensitive values redacted by the
+/// [`RedactionPolicy`] or [`SensitiveHeaders`] extension of requests.
+///
+/// [`TracingMiddleware`]: crate::middleware::TracingMiddleware
+pub struct SpanBackendWithHeaders;
+
+impl ReqwestOtelSpanBackend for SpanBackendWithHeaders {
+    fn on_request_start(req: &Request, ext: &mut Extensions) -> Span {
+        let name = default_span_name(req, ext);
+        let policy = redaction_policy(ext, &RedactionPolicy::new());
+        reqwest_otel_span!(
+            name = name,
+            req,
+            http.request.headers = ?policy.redact_headers(req.headers()),
+            http.response.headers = tracing::field::Empty
+        )
+    }
+
+    fn on_request_end(span: &Span, outcome: &Result<Response>, ext: &mut Extensions) {
+        if let Ok(res) = outcome {
+            let policy = redaction_policy(ext, &RedactionPolicy::new());
+            span.record(
+                HTTP_RESPONSE_HEADERS,
+                tracing::field::debug(policy.redact_headers(res.headers())),
+            );
+        }
+        default_on_request_end(span, outcome)
+    }
+}
+
 /// HTTP Mapping <https://github.com/open-telemetry/opentelemetry-specification/blob/main/specification/trace/semantic_conventions/http.md#status>
 ///
 /// Maps the the http status to an Opentelemetry span status following the the specified convention above.
@@ -303,8 +343,77 @@ impl OtelPathNames {
 #[derive(Clone)]
 pub struct DisableOtelPropagation;
 
+/// [`SensitiveHeaders`] lists the headers whose values are redacted in the spans created by
+/// [`SpanBackendWithHeaders`].
+///
+/// `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie` are always redacted, as are
+/// values marked as sensitive with [`HeaderValue::set_sensitive`](http::HeaderValue::set_sensitive).
+/// Other headers carrying secrets, such as API keys, can be added:
+///
+/// ```no_run
+/// use http::HeaderName;
+/// use reqwest_middleware::{ClientBuilder, Extension};
+/// use reqwest_tracing::{SensitiveHeaders, SpanBackendWithHeaders, TracingMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with_init(Extension(
+///         SensitiveHeaders::new().with(HeaderName::from_static("x-api-key")),
+///     ))
+///     .with(TracingMiddleware::<SpanBackendWithHeaders>::new())
+///     .build();
+/// ```
+///
+/// A [`RedactionPolicy`] extension, which redacts query parameters and bodies too, takes
+/// precedence over this one in all the middleware of this crate.
+#[derive(Clone, Debug, Default)]
+pub struct SensitiveHeaders(RedactionPolicy);
+
+impl SensitiveHeaders {
+    /// Create a new [`SensitiveHeaders`] redacting only the default headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redact the values of the `name` header as well.
+    pub fn with(self, name: HeaderName) -> Self {
+        Self(self.0.with_header(name))
+    }
+
+    /// Whether the values of the `name` header are redacted.
+    pub fn is_sensitive(&self, name: &HeaderName) -> bool {
+        self.0.is_sensitive_header(name)
+    }
+
+    /// Returns `headers` for display, with sensitive values replaced by `[REDACTED]`.
+    pub fn redact<'a>(&'a self, headers: &'a HeaderMap) -> RedactedHeaders<'a> {
+        self.0.redact_headers(headers)
+    }
+}
+
+impl From<SensitiveHeaders> for RedactionPolicy {
+    fn from(sensitive: SensitiveHeaders) -> Self {
+        sensitive.0
+    }
+}
+
+/// The redaction policy of a request: its [`RedactionPolicy`] extension, or the policy of its
+/// [`SensitiveHeaders`] extension, or `default` without either.
+pub(crate) fn redaction_policy(
+    extensions: &Extensions,
+    default: &RedactionPolicy,
+) -> RedactionPolicy {
+    match (
+        extensions.get::<RedactionPolicy>(),
+        extensions.get::<SensitiveHeaders>(),
+    ) {
+        (Some(policy), _) => policy.clone(),
+        (None, Some(SensitiveHeaders(policy))) => policy.clone(),
+        (None, None) => default.clone(),
+    }
+}
+
 /// Removes the username and/or password parts of the url, if present.
-fn remove_credentials(url: &Url) -> Cow<'_, str> {
+pub(crate) fn remove_credentials(url: &Url) -> Cow<'_, str> {
     if !url.username().is_empty() || url.password().is_some() {
         let mut url = url.clone();
         // Errors settings username/password are set when the URL can't have credentials, so
@@ -366,4 +475,21 @@ mod tests {
         let clean = remove_credentials(&url);
         assert_eq!(clean, "http://both.com/");
     }
+
+    #[test]
+    fn sensitive_headers_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept", HeaderValue::from_static("*/*"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer token"));
+        headers.insert("x-api-key", HeaderValue::from_static("key"));
+        let mut marked = HeaderValue::from_static("marked");
+        marked.set_sensitive(true);
+        headers.insert("x-marked", marked);
+
+        let sensitive = SensitiveHeaders::new().with(HeaderName::from_static("x-api-key"));
+        assert_eq!(
+            format!("{:?}", sensitive.redact(&headers)),
+            r#"{"accept": "*/*", "authorization": "[REDACTED]", "x-api-key": "[REDACTED]", "x-marked": "[REDACTED]"}"#
+        );
+    }
 }
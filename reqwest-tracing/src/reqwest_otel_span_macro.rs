@@ -12,6 +12,7 @@
 /// - otel.status_code
 /// - user_agent.original
 /// - http.response.status_code
+/// - http.request.resend_count, recorded by [`TracingMiddleware`] for requests sent again
 /// - error.message
 /// - error.cause_chain
 ///
@@ -98,6 +99,7 @@
 ///
 ///
 /// [`DefaultSpanBackend`]: crate::reqwest_otel_span_builder::DefaultSpanBackend
+/// [`TracingMiddleware`]: crate::TracingMiddleware
 /// [`SpanBackendWithUrl`]: crate::reqwest_otel_span_builder::DefaultSpanBackend
 /// [`default_on_request_success`]: crate::reqwest_otel_span_builder::default_on_request_success
 /// [`default_on_request_failure`]: crate::reqwest_otel_span_builder::default_on_request_failure
@@ -141,6 +143,7 @@ macro_rules! reqwest_otel_span {
                         otel.name = %otel_name,
                         otel.status_code = tracing::field::Empty,
                         http.response.status_code = tracing::field::Empty,
+                        http.request.resend_count = tracing::field::Empty,
                         error.message = tracing::field::Empty,
                         error.cause_chain = tracing::field::Empty,
                         $($field)*
@@ -0,0 +1,188 @@
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, RedactionPolicy, Result, REDACTED};
+use sentry_core::protocol::{self, Breadcrumb, Event, Level, Map, Value};
+
+use crate::reqwest_otel_span_builder::{redaction_policy, remove_credentials};
+
+/// `SentryMiddleware` records requests as [Sentry](https://sentry.io) breadcrumbs, so that the
+/// HTTP calls leading to an error are attached to it.
+///
+/// Each request adds an `http` breadcrumb with its method, URL and status code, at the `info`
+/// level, `warning` for `4xx` responses and `error` for `5xx` ones and for requests failing
+/// without a response. With [`with_capture_failures`](Self::with_capture_failures), those `5xx`
+/// responses and failed requests are captured as events too.
+///
+/// URLs are recorded without credentials and query, and the headers of captured events are
+/// redacted by the [`RedactionPolicy`] or [`SensitiveHeaders`] extension of requests, or the
+/// default policy. Bodies are never recorded.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::SentryMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(SentryMiddleware::new().with_capture_failures())
+///     .build();
+/// ```
+///
+/// [`SensitiveHeaders`]: crate::SensitiveHeaders
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SentryMiddleware {
+    capture_failures: bool,
+}
+
+impl SentryMiddleware {
+    /// Construct `SentryMiddleware` recording breadcrumbs only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture `5xx` responses and requests failing without a response as events.
+    pub fn with_capture_failures(mut self) -> Self {
+        self.capture_failures = true;
+        self
+    }
+}
+
+/// The URL of `req` to record, without its credentials, query and fragment.
+fn sanitized_url(req: &Request) -> String {
+    let mut url = req.url().clone();
+    url.set_query(None);
+    url.set_fragment(None);
+    remove_credentials(&url).into_owned()
+}
+
+/// The details of `req` for an event, with its sensitive headers redacted.
+fn event_request(req: &Request, url: &str, extensions: &Extensions) -> protocol::Request {
+    let policy = redaction_policy(extensions, &RedactionPolicy::new());
+    let headers = req
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = if policy.is_redacted(name, value) {
+                REDACTED.to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect();
+    protocol::Request {
+        url: url.parse().ok(),
+        method: Some(req.method().to_string()),
+        headers,
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for SentryMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let url = sanitized_url(&req);
+        let method = req.method().to_string();
+        let request = self
+            .capture_failures
+            .then(|| event_request(&req, &url, extensions));
+
+        let result = next.run(req, extensions).await;
+
+        let mut data = Map::new();
+        data.insert("method".to_owned(), Value::from(method.as_str()));
+        data.insert("url".to_owned(), Value::from(url.as_str()));
+        let (level, failure) = match &result {
+            Ok(res) => {
+                let status = res.status();
+                data.insert("status_code".to_owned(), Value::from(status.as_u16()));
+                if let Some(reason) = status.canonical_reason() {
+                    data.insert("reason".to_owned(), Value::from(reason));
+                }
+                match status.as_u16() {
+                    500..=599 => (Level::Error, Some(format!("status {}", status))),
+                    400..=499 => (Level::Warning, None),
+                    _ => (Level::Info, None),
+                }
+            }
+            Err(err) => (Level::Error, Some(err.to_string())),
+        };
+        sentry_core::add_breadcrumb(Breadcrumb {
+            ty: "http".to_owned(),
+            category: Some("http".to_owned()),
+            level,
+            data,
+            ..Default::default()
+        });
+
+        if let (Some(request), Some(failure)) = (request, failure) {
+            sentry_core::capture_event(Event {
+                level: Level::Error,
+                message: Some(format!("{} {} failed: {}", method, url, failure)),
+                request: Some(request),
+                ..Default::default()
+            });
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::matchers::path;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn failures_are_captured_with_breadcrumbs() {
+        let events = sentry_core::test::with_captured_events(|| {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let server = MockServer::start().await;
+                Mock::given(path("/ok"))
+                    .respond_with(ResponseTemplate::new(200))
+                    .mount(&server)
+                    .await;
+                Mock::given(path("/down"))
+                    .respond_with(ResponseTemplate::new(503))
+                    .mount(&server)
+                    .await;
+
+                let client = ClientBuilder::new(reqwest::Client::new())
+                    .with(SentryMiddleware::new().with_capture_failures())
+                    .build();
+                client
+                    .get(format!("{}/ok?token=secret", server.uri()))
+                    .send()
+                    .await
+                    .unwrap();
+                client
+                    .get(format!("{}/down", server.uri()))
+                    .header("authorization", "Bearer secret")
+                    .send()
+                    .await
+                    .unwrap();
+            });
+        });
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        let request = event.request.as_ref().unwrap();
+        assert!(request.url.as_ref().unwrap().path().ends_with("/down"));
+        assert_eq!(request.headers["authorization"], "[REDACTED]");
+        let breadcrumbs = &event.breadcrumbs.values;
+        assert_eq!(breadcrumbs.len(), 2);
+        assert_eq!(breadcrumbs[0].level, Level::Info);
+        assert!(!breadcrumbs[0].data["url"].to_string().contains("secret"));
+        assert_eq!(breadcrumbs[1].data["status_code"], 503);
+    }
+}
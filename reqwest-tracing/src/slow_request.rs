@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http::{Extensions, Method, StatusCode};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, ResendCount, Result};
+use tracing::Level;
+
+use crate::reqwest_otel_span_builder::remove_credentials;
+use crate::RequestTimings;
+
+/// A request which took longer than the threshold of a [`SlowRequestMiddleware`].
+#[derive(Clone, Debug)]
+pub struct SlowRequest {
+    /// The request method.
+    pub method: Method,
+    /// The request URL, without credentials.
+    pub url: String,
+    /// The time until the response headers were received, or the request failed.
+    pub duration: Duration,
+    /// The status of the response, `None` if the request failed.
+    pub status: Option<StatusCode>,
+    /// The number of attempts to send the request, more than one when it was retried.
+    pub attempts: u32,
+    /// The timing breakdown of the request, when measured by a [`TimingMiddleware`] added after
+    /// the `SlowRequestMiddleware`.
+    ///
+    /// [`TimingMiddleware`]: crate::TimingMiddleware
+    pub timings: Option<RequestTimings>,
+}
+
+type CallbackFn = dyn Fn(&SlowRequest) + Send + Sync + 'static;
+
+/// `SlowRequestMiddleware` reports the requests taking longer than a threshold, to catch tail
+/// latency in production.
+///
+/// Slow requests are logged at the `WARN` level by default, whatever the level of the other
+/// logs, or passed to the callback set with [`with_callback`](Self::with_callback), e.g. to count
+/// them. The report includes the number of attempts and, with a [`TimingMiddleware`] added after
+/// this middleware, the timing breakdown of the request.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{SlowRequestMiddleware, TimingMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(SlowRequestMiddleware::new(Duration::from_secs(2)))
+///     .with(TimingMiddleware::new())
+///     // The retry middleware goes here.
+///     .with(TimingMiddleware::attempts())
+///     .build();
+/// ```
+///
+/// [`TimingMiddleware`]: crate::TimingMiddleware
+#[derive(Clone)]
+pub struct SlowRequestMiddleware {
+    threshold: Duration,
+    level: Level,
+    callback: Option<Arc<CallbackFn>>,
+}
+
+impl SlowRequestMiddleware {
+    /// Construct `SlowRequestMiddleware` logging the requests taking longer than `threshold`.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            level: Level::WARN,
+            callback: None,
+        }
+    }
+
+    /// Log slow requests at `level`.
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Pass slow requests to `callback` rather than logging them.
+    pub fn with_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&SlowRequest) + Send + Sync + 'static,
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    fn report(&self, slow: &SlowRequest) {
+        if let Some(callback) = &self.callback {
+            callback(slow);
+            return;
+        }
+        let status = slow.status.map(|status| status.as_u16());
+        let queued = slow.timings.as_ref().map(|timings| timings.queued());
+        let time_to_first_byte = slow
+            .timings
+            .as_ref()
+            .and_then(|timings| timings.time_to_first_byte());
+        event_at!(
+            self.level,
+            method = %slow.method,
+            url = %slow.url,
+            duration = ?slow.duration,
+            status,
+            attempts = slow.attempts,
+            queued = ?queued,
+            time_to_first_byte = ?time_to_first_byte,
+            "Slow request"
+        );
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for SlowRequestMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let method = req.method().clone();
+        let url = remove_credentials(req.url()).into_owned();
+        let started_at = Instant::now();
+        let result = next.run(req, extensions).await;
+        let duration = started_at.elapsed();
+        if duration > self.threshold {
+            self.report(&SlowRequest {
+                method,
+                url,
+                duration,
+                status: result.as_ref().ok().map(Response::status),
+                attempts: ResendCount::get(extensions) + 1,
+                timings: extensions.get::<RequestTimings>().cloned(),
+            });
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn only_slow_requests_are_reported() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::path("/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+            .mount(&server)
+            .await;
+        Mock::given(wiremock::matchers::path("/fast"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(
+                SlowRequestMiddleware::new(Duration::from_millis(100)).with_callback({
+                    let reported = reported.clone();
+                    move |slow| reported.lock().unwrap().push(slow.clone())
+                }),
+            )
+            .with(crate::TimingMiddleware::new())
+            .build();
+        client
+            .get(format!("{}/fast", server.uri()))
+            .send()
+            .await
+            .unwrap();
+        client
+            .get(format!("{}/slow", server.uri()))
+            .send()
+            .await
+            .unwrap();
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0].url.ends_with("/slow"));
+        assert!(reported[0].duration >= Duration::from_millis(200));
+        assert_eq!(reported[0].status, Some(StatusCode::OK));
+        assert_eq!(reported[0].attempts, 1);
+        assert!(reported[0].timings.is_some());
+    }
+}
@@ -0,0 +1,246 @@
+use std::time::{Duration, Instant};
+
+use http::{Extensions, StatusCode};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+
+/// The timing of an attempt to send a request, see [`RequestTimings::attempts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttemptTiming {
+    /// The time between the start of the request and that of the attempt.
+    pub started_after: Duration,
+    /// The time until the response headers were received, or the attempt failed.
+    pub duration: Duration,
+    /// The status of the response, `None` if the attempt failed.
+    pub status: Option<StatusCode>,
+}
+
+/// The phases of a request measured by [`TimingMiddleware`], from the extensions of its
+/// response.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_tracing::{RequestTimings, TimingMiddleware};
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(TimingMiddleware::new())
+///     // The retry and rate limiting middleware go here.
+///     .with(TimingMiddleware::attempts())
+///     .build();
+/// let resp = client.get("https://truelayer.com").send().await?;
+/// if let Some(timings) = resp.extensions().get::<RequestTimings>() {
+///     tracing::info!(
+///         queued = ?timings.queued(),
+///         ttfb = ?timings.time_to_first_byte(),
+///         total = ?timings.total(),
+///         attempts = timings.attempts().len(),
+///     );
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestTimings {
+    started_at: Instant,
+    attempts: Vec<AttemptTiming>,
+    total: Option<Duration>,
+}
+
+impl RequestTimings {
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            attempts: Vec::new(),
+            total: None,
+        }
+    }
+
+    /// The time spent in the middleware before the first attempt, e.g. waiting for a rate
+    /// limiter. Zero without a [`TimingMiddleware::attempts`].
+    pub fn queued(&self) -> Duration {
+        self.attempts
+            .first()
+            .map(|attempt| attempt.started_after)
+            .unwrap_or_default()
+    }
+
+    /// The time from the start of the last attempt until its response headers were received.
+    pub fn time_to_first_byte(&self) -> Option<Duration> {
+        self.attempts
+            .last()
+            .filter(|attempt| attempt.status.is_some())
+            .map(|attempt| attempt.duration)
+    }
+
+    /// The time from the start of the request until its response headers were received,
+    /// including the attempts and the waits in between.
+    pub fn total(&self) -> Duration {
+        self.total.unwrap_or_else(|| self.started_at.elapsed())
+    }
+
+    /// The timings of the attempts to send the request, one per retry, measured by a
+    /// [`TimingMiddleware::attempts`].
+    pub fn attempts(&self) -> &[AttemptTiming] {
+        &self.attempts
+    }
+}
+
+/// `TimingMiddleware` measures the phases of requests for latency debugging, and adds them to the
+/// extensions of their response as [`RequestTimings`].
+///
+/// The middleware built with [`new`](Self::new) goes first, to time the whole request, and the
+/// one built with [`attempts`](Self::attempts) last, to time each attempt to send it. The time
+/// spent in the middleware in between, such as rate limiters and retry back-offs, is then known
+/// too.
+///
+/// The times are those until the response headers are received: reading the response body is
+/// not included.
+#[derive(Clone, Copy, Debug)]
+pub struct TimingMiddleware {
+    attempts: bool,
+}
+
+impl TimingMiddleware {
+    /// Construct `TimingMiddleware` timing whole requests, to add before the other middleware.
+    pub fn new() -> Self {
+        Self { attempts: false }
+    }
+
+    /// Construct `TimingMiddleware` timing each attempt to send requests, to add after the other
+    /// middleware, such as the retry one.
+    pub fn attempts() -> Self {
+        Self { attempts: true }
+    }
+}
+
+impl Default for TimingMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for TimingMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        // Without a `TimingMiddleware::new`, attempts are timed from the first one.
+        if !self.attempts || extensions.get::<RequestTimings>().is_none() {
+            extensions.insert(RequestTimings::new());
+        }
+        let attempt_started_at = Instant::now();
+
+        let result = next.run(req, extensions).await;
+        let timings = extensions
+            .get_mut::<RequestTimings>()
+            .expect("RequestTimings inserted above");
+        if self.attempts {
+            timings.attempts.push(AttemptTiming {
+                started_after: attempt_started_at - timings.started_at,
+                duration: attempt_started_at.elapsed(),
+                status: result.as_ref().ok().map(Response::status),
+            });
+        } else {
+            timings.total = Some(timings.started_at.elapsed());
+        }
+        let timings = timings.clone();
+        result.map(|mut res| {
+            res.extensions_mut().insert(timings);
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use reqwest_middleware::ClientBuilder;
+    use wiremock::{Mock, MockServer, Respond, ResponseTemplate};
+
+    struct Delay(Duration);
+
+    #[async_trait::async_trait]
+    impl Middleware for Delay {
+        async fn handle(
+            &self,
+            req: Request,
+            extensions: &mut Extensions,
+            next: Next<'_>,
+        ) -> Result<Response> {
+            tokio::time::sleep(self.0).await;
+            next.run(req, extensions).await
+        }
+    }
+
+    /// Resends requests until they succeed, in place of the retry middleware.
+    struct Resend;
+
+    #[async_trait::async_trait]
+    impl Middleware for Resend {
+        async fn handle(
+            &self,
+            req: Request,
+            extensions: &mut Extensions,
+            next: Next<'_>,
+        ) -> Result<Response> {
+            let retry = req.try_clone().unwrap();
+            let res = next.clone().run(req, extensions).await?;
+            if res.status().is_success() {
+                return Ok(res);
+            }
+            next.run(retry, extensions).await
+        }
+    }
+
+    struct FailOnce(Arc<AtomicU32>);
+
+    impl Respond for FailOnce {
+        fn respond(&self, _: &wiremock::Request) -> ResponseTemplate {
+            match self.0.fetch_add(1, Ordering::SeqCst) {
+                0 => ResponseTemplate::new(503),
+                _ => ResponseTemplate::new(200),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn phases_and_attempts_are_timed() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(FailOnce(Arc::default()))
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(TimingMiddleware::new())
+            .with(Delay(Duration::from_millis(50)))
+            .with(Resend)
+            .with(TimingMiddleware::attempts())
+            .build();
+        let res = client.get(server.uri()).send().await.unwrap();
+
+        let timings = res.extensions().get::<RequestTimings>().unwrap();
+        assert!(timings.queued() >= Duration::from_millis(50));
+        let statuses: Vec<_> = timings
+            .attempts()
+            .iter()
+            .map(|attempt| attempt.status.unwrap())
+            .collect();
+        assert_eq!(statuses, [503, 200]);
+        assert!(timings.attempts()[1].started_after >= timings.attempts()[0].started_after);
+        assert_eq!(
+            timings.time_to_first_byte(),
+            Some(timings.attempts()[1].duration)
+        );
+        assert!(timings.total() >= timings.queued() + timings.time_to_first_byte().unwrap());
+    }
+}
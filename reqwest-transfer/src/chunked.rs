@@ -0,0 +1,278 @@
+use bytes::Bytes;
+use futures::TryStreamExt;
+use reqwest_middleware::{ClientWithMiddleware, Clock, Result, SystemClock};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::Progress;
+
+/// The hooks of a multipart upload API driven by a [`ChunkedUploader`], such as S3's
+/// `CreateMultipartUpload`, `UploadPart` and `CompleteMultipartUpload`.
+///
+/// Each hook gets the client of the uploader, so that its requests go through its middleware.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait MultipartUpload: Send + Sync {
+    /// The state of an upload, e.g. S3's `UploadId`.
+    type Upload: Send + Sync;
+    /// The receipt of an uploaded part, e.g. its `ETag`.
+    type Part: Send;
+
+    /// Starts an upload.
+    async fn initiate(&self, client: &ClientWithMiddleware) -> Result<Self::Upload>;
+
+    /// Uploads the part `number` of `upload`, numbered from 1. It may be called again for the
+    /// same part when it fails.
+    async fn upload_part(
+        &self,
+        client: &ClientWithMiddleware,
+        upload: &Self::Upload,
+        number: usize,
+        data: Bytes,
+    ) -> Result<Self::Part>;
+
+    /// Completes `upload` from the receipts of all its parts, in order.
+    async fn complete(
+        &self,
+        client: &ClientWithMiddleware,
+        upload: Self::Upload,
+        parts: Vec<Self::Part>,
+    ) -> Result<()>;
+
+    /// Aborts `upload` after a part failed, to free the parts uploaded. Does nothing by default.
+    async fn abort(&self, client: &ClientWithMiddleware, upload: Self::Upload) -> Result<()> {
+        let _ = (client, upload);
+        Ok(())
+    }
+}
+
+type CallbackFn = dyn Fn(Progress) + Send + Sync + 'static;
+
+/// Uploads large bodies with multipart upload APIs, splitting them into parts uploaded
+/// concurrently, and driving the API with the hooks of a [`MultipartUpload`].
+///
+/// Parts are read from the body as others are uploaded, so at most `concurrency` parts are held
+/// in memory. A failed part is retried, with an exponential backoff, up to a maximum number of
+/// attempts; if it still fails, the upload is aborted and the error returned.
+///
+/// ```no_run
+/// use bytes::Bytes;
+/// use reqwest_middleware::{ClientWithMiddleware, Result};
+/// use reqwest_transfer::{ChunkedUploader, MultipartUpload};
+///
+/// struct Blobs;
+///
+/// #[async_trait::async_trait]
+/// impl MultipartUpload for Blobs {
+///     type Upload = String;
+///     type Part = String;
+///
+///     async fn initiate(&self, client: &ClientWithMiddleware) -> Result<String> {
+///         Ok(client.post("https://blobs.example.com/uploads").send().await?.text().await?)
+///     }
+///
+///     async fn upload_part(
+///         &self,
+///         client: &ClientWithMiddleware,
+///         upload: &String,
+///         number: usize,
+///         data: Bytes,
+///     ) -> Result<String> {
+///         let url = format!("https://blobs.example.com/uploads/{}/{}", upload, number);
+///         Ok(client.put(url).body(data).send().await?.text().await?)
+///     }
+///
+///     async fn complete(
+///         &self,
+///         client: &ClientWithMiddleware,
+///         upload: String,
+///         parts: Vec<String>,
+///     ) -> Result<()> {
+///         let url = format!("https://blobs.example.com/uploads/{}", upload);
+///         client.post(url).body(parts.join(",")).send().await?;
+///         Ok(())
+///     }
+/// }
+///
+/// # async fn example() -> Result<()> {
+/// let file = tokio::fs::File::open("backup.tar").await.unwrap();
+/// ChunkedUploader::new(ClientWithMiddleware::from(reqwest::Client::new()))
+///     .with_concurrency(8)
+///     .upload(&Blobs, file)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ChunkedUploader {
+    client: ClientWithMiddleware,
+    part_size: usize,
+    concurrency: usize,
+    max_attempts: u32,
+    backoff: Duration,
+    clock: Arc<dyn Clock>,
+    progress: Option<Arc<CallbackFn>>,
+}
+
+impl ChunkedUploader {
+    /// The size of parts by default, 8 MiB.
+    pub const DEFAULT_PART_SIZE: usize = 8 * 1024 * 1024;
+
+    /// Construct a `ChunkedUploader` sending its requests with `client`, uploading parts of
+    /// [`DEFAULT_PART_SIZE`](Self::DEFAULT_PART_SIZE), 4 at a time, in up to 3 attempts.
+    pub fn new(client: ClientWithMiddleware) -> Self {
+        Self {
+            client,
+            part_size: Self::DEFAULT_PART_SIZE,
+            concurrency: 4,
+            max_attempts: 3,
+            backoff: Duration::from_secs(1),
+            clock: Arc::new(SystemClock),
+            progress: None,
+        }
+    }
+
+    /// Split bodies into parts of `part_size` bytes, the last one being smaller.
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size.max(1);
+        self
+    }
+
+    /// Upload up to `concurrency` parts at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Try uploading each part up to `max_attempts` times, waiting `backoff`, doubled after each
+    /// failure, between attempts. Defaults to 3 attempts from 1 second.
+    pub fn with_retries(mut self, max_attempts: u32, backoff: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.backoff = backoff;
+        self
+    }
+
+    /// Wait between attempts with `clock`, [`SystemClock`] by default.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Pass the number of bytes of the parts uploaded to `callback`, after each part.
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Uploads `data` with the hooks of `upload`, and returns its size.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if a hook fails, once out of attempts for parts, or if `data` can't be
+    /// read.
+    pub async fn upload<U, R>(&self, upload: &U, data: R) -> Result<u64>
+    where
+        U: MultipartUpload,
+        R: AsyncRead + Unpin + Send,
+    {
+        let state = upload.initiate(&self.client).await?;
+        match self.upload_parts(upload, &state, data).await {
+            Ok((parts, size)) => {
+                upload.complete(&self.client, state, parts).await?;
+                Ok(size)
+            }
+            Err(err) => {
+                if let Err(abort_err) = upload.abort(&self.client, state).await {
+                    tracing::warn!("Failed to abort multipart upload: {}", abort_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn upload_parts<U, R>(
+        &self,
+        upload: &U,
+        state: &U::Upload,
+        data: R,
+    ) -> Result<(Vec<U::Part>, u64)>
+    where
+        U: MultipartUpload,
+        R: AsyncRead + Unpin + Send,
+    {
+        let part_size = self.part_size;
+        let parts = futures::stream::try_unfold((data, 1), move |(mut data, number)| async move {
+            let mut part = Vec::with_capacity(part_size);
+            (&mut data)
+                .take(part_size as u64)
+                .read_to_end(&mut part)
+                .await
+                .map_err(reqwest_middleware::Error::middleware)?;
+            if part.is_empty() && number > 1 {
+                return Ok(None);
+            }
+            Ok(Some(((number, Bytes::from(part)), (data, number + 1))))
+        });
+
+        let uploaded = AtomicU64::new(0);
+        let parts = parts
+            .map_ok(|(number, part)| self.upload_part(upload, state, number, part, &uploaded))
+            .try_buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok((parts, uploaded.into_inner()))
+    }
+
+    async fn upload_part<U: MultipartUpload>(
+        &self,
+        upload: &U,
+        state: &U::Upload,
+        number: usize,
+        data: Bytes,
+        uploaded: &AtomicU64,
+    ) -> Result<U::Part> {
+        let len = data.len() as u64;
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        let part = loop {
+            match upload
+                .upload_part(&self.client, state, number, data.clone())
+                .await
+            {
+                Ok(part) => break part,
+                Err(err) if attempt < self.max_attempts => {
+                    tracing::debug!("Retrying part {} after error: {}", number, err);
+                    self.clock.sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        let transferred = uploaded.fetch_add(len, Ordering::Relaxed) + len;
+        if let Some(callback) = &self.progress {
+            callback(Progress {
+                transferred,
+                total: None,
+            });
+        }
+        Ok(part)
+    }
+}
+
+impl fmt::Debug for ChunkedUploader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkedUploader")
+            .field("part_size", &self.part_size)
+            .field("concurrency", &self.concurrency)
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .finish_non_exhaustive()
+    }
+}
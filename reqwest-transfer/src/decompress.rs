@@ -0,0 +1,170 @@
+//! `DecompressMiddleware` decodes the content codings reqwest doesn't.
+use http::Extensions;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use reqwest::{Request, Response};
+use reqwest_middleware::{map_response_body, BodyStream, Middleware, Next, Result};
+#[cfg(any(
+    feature = "zstd",
+    feature = "brotli",
+    feature = "gzip",
+    feature = "deflate"
+))]
+use {
+    futures::TryStreamExt,
+    reqwest_middleware::BoxError,
+    tokio_util::io::{ReaderStream, StreamReader},
+};
+
+/// A content coding decoded by [`DecompressMiddleware`], as enabled by the crate features.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "brotli")]
+    Brotli,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "deflate")]
+    Deflate,
+}
+
+impl Encoding {
+    /// The supported encodings, in order of preference.
+    const ALL: &'static [Encoding] = &[
+        #[cfg(feature = "zstd")]
+        Encoding::Zstd,
+        #[cfg(feature = "brotli")]
+        Encoding::Brotli,
+        #[cfg(feature = "gzip")]
+        Encoding::Gzip,
+        #[cfg(feature = "deflate")]
+        Encoding::Deflate,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => "zstd",
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => "br",
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip => "gzip",
+            #[cfg(feature = "deflate")]
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?.trim();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|encoding| value.eq_ignore_ascii_case(encoding.name()))
+    }
+
+    fn decode(self, body: BodyStream) -> BodyStream {
+        #[cfg(any(
+            feature = "zstd",
+            feature = "brotli",
+            feature = "gzip",
+            feature = "deflate"
+        ))]
+        let reader = StreamReader::new(body.map_err(std::io::Error::other));
+        // Without any coding there is no `Encoding` to decode with.
+        #[cfg(not(any(
+            feature = "zstd",
+            feature = "brotli",
+            feature = "gzip",
+            feature = "deflate"
+        )))]
+        let _ = body;
+        match self {
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => Box::pin(
+                ReaderStream::new(async_compression::tokio::bufread::ZstdDecoder::new(reader))
+                    .map_err(BoxError::from),
+            ),
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => Box::pin(
+                ReaderStream::new(async_compression::tokio::bufread::BrotliDecoder::new(
+                    reader,
+                ))
+                .map_err(BoxError::from),
+            ),
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip => Box::pin(
+                ReaderStream::new(async_compression::tokio::bufread::GzipDecoder::new(reader))
+                    .map_err(BoxError::from),
+            ),
+            #[cfg(feature = "deflate")]
+            Encoding::Deflate => Box::pin(
+                ReaderStream::new(async_compression::tokio::bufread::ZlibDecoder::new(reader))
+                    .map_err(BoxError::from),
+            ),
+        }
+    }
+}
+
+/// `DecompressMiddleware` advertises the content codings enabled by the crate features in the
+/// `Accept-Encoding` header of requests, and transparently decodes the bodies of responses using
+/// them.
+///
+/// It fills the gaps of reqwest's own decompression: `zstd`, enabled by default, and the `gzip`,
+/// `brotli` and `deflate` features for clients built without the reqwest features of the same
+/// names. Codings decoded by reqwest never reach the middleware, so both can be enabled, but as
+/// reqwest doesn't advertise its codings when the middleware did, the features of both should
+/// match.
+///
+/// Decoded responses lose their `Content-Encoding` and `Content-Length` headers, which describe
+/// the encoded body. The `Accept-Encoding` header is left untouched when it is already set, and
+/// responses with other or several codings are returned as they are.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_transfer::DecompressMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(DecompressMiddleware)
+///     .build();
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecompressMiddleware;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for DecompressMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if !Encoding::ALL.is_empty() && !req.headers().contains_key(ACCEPT_ENCODING) {
+            let accept = Encoding::ALL
+                .iter()
+                .map(|encoding| encoding.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let accept = HeaderValue::from_str(&accept).expect("codings are valid header values");
+            req.headers_mut().insert(ACCEPT_ENCODING, accept);
+        }
+
+        let res = next.run(req, extensions).await?;
+        let encoding = match res
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(Encoding::from_header)
+        {
+            Some(encoding) => encoding,
+            None => return Ok(res),
+        };
+        Ok(map_response_body(
+            res,
+            move |headers: &mut HeaderMap, body| {
+                headers.remove(CONTENT_ENCODING);
+                headers.remove(CONTENT_LENGTH);
+                encoding.decode(body)
+            },
+        ))
+    }
+}
@@ -0,0 +1,473 @@
+use futures::{StreamExt, TryStreamExt};
+use reqwest::header::{HeaderValue, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::{Response, StatusCode};
+use reqwest_middleware::{Error, RequestBuilder, Result, StatusError};
+use std::fmt;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+use crate::Progress;
+
+/// The error of a download whose body doesn't have the size of its `Content-Length` header.
+#[derive(Debug, thiserror::Error)]
+#[error("Downloaded {actual} bytes, expected {expected}")]
+pub struct ContentLengthMismatch {
+    /// The size of the body announced by the response.
+    pub expected: u64,
+    /// The size of the body received.
+    pub actual: u64,
+}
+
+/// The error of a segmented download whose server didn't answer a range request with the range.
+#[derive(Debug, thiserror::Error)]
+#[error("Server didn't return the range bytes={start}-{end}")]
+pub struct InvalidRangeResponse {
+    /// The first byte of the range requested.
+    pub start: u64,
+    /// The last byte of the range requested, inclusive.
+    pub end: u64,
+}
+
+/// The error of a download whose body doesn't have the expected checksum.
+///
+/// # Optional
+///
+/// This requires the optional `checksum` feature enabled.
+#[cfg(feature = "checksum")]
+#[cfg_attr(docsrs, doc(cfg(feature = "checksum")))]
+#[derive(Debug, thiserror::Error)]
+#[error("Downloaded body has SHA-256 {actual}, expected {expected}")]
+pub struct ChecksumMismatch {
+    /// The expected SHA-256 digest, in lowercase hex.
+    pub expected: String,
+    /// The SHA-256 digest of the body received, in lowercase hex.
+    pub actual: String,
+}
+
+type CallbackFn = dyn Fn(Progress) + Send + Sync + 'static;
+
+/// Distinguishes the temporary files of concurrent downloads to the same path.
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Streams response bodies to files, atomically: the body is written to a temporary file next to
+/// the destination, which is only renamed to it once complete and verified, so the destination
+/// never holds a partial download.
+///
+/// Downloads fail with [`ContentLengthMismatch`] when the body doesn't have the size of the
+/// `Content-Length` header, and with the `checksum` feature, with a `ChecksumMismatch` when it
+/// doesn't have the digest set with `with_sha256`. The temporary file is removed when a download
+/// fails. See [`DownloadExt`] for downloads with the default settings.
+///
+/// [`fetch`](Self::fetch) sends the request itself, which lets it split large files into
+/// segments downloaded concurrently, see [`with_segments`](Self::with_segments).
+///
+/// ```no_run
+/// use reqwest_middleware::ClientWithMiddleware;
+/// use reqwest_transfer::Downloader;
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let res = ClientWithMiddleware::from(reqwest::Client::new())
+///     .get("https://example.com/release.tar.gz")
+///     .send()
+///     .await?
+///     .error_for_status()?;
+/// Downloader::new()
+///     .with_progress(|progress| eprintln!("{:?}", progress.fraction()))
+///     .download(res, "release.tar.gz")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Downloader {
+    #[cfg(feature = "checksum")]
+    sha256: Option<String>,
+    progress: Option<Arc<CallbackFn>>,
+    segments: usize,
+    min_segment_size: u64,
+}
+
+impl Downloader {
+    /// The minimum size of segments by default, 1 MiB.
+    pub const DEFAULT_MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+
+    /// Construct a `Downloader` without verification beyond `Content-Length` nor progress
+    /// reporting, downloading files in a single request.
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "checksum")]
+            sha256: None,
+            progress: None,
+            segments: 1,
+            min_segment_size: Self::DEFAULT_MIN_SEGMENT_SIZE,
+        }
+    }
+
+    /// Verify that downloaded bodies have the SHA-256 digest `hex`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `checksum` feature enabled.
+    #[cfg(feature = "checksum")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "checksum")))]
+    pub fn with_sha256(mut self, hex: impl Into<String>) -> Self {
+        self.sha256 = Some(hex.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Pass the progress of downloads to `callback`, after each chunk written.
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Split the files downloaded by [`fetch`](Self::fetch) into up to `segments` ranges,
+    /// downloaded concurrently. Files are only split into segments of at least
+    /// [`with_min_segment_size`](Self::with_min_segment_size), and downloaded in a single
+    /// request when the server doesn't support range requests.
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(1);
+        self
+    }
+
+    /// Only split files into segments of at least `size` bytes.
+    pub fn with_min_segment_size(mut self, size: u64) -> Self {
+        self.min_segment_size = size.max(1);
+        self
+    }
+
+    /// Streams the body of `res` to the file at `path`, replacing it, and returns its size.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the body can't be read or written, or doesn't pass verification.
+    pub async fn download(&self, res: Response, path: impl AsRef<Path>) -> Result<u64> {
+        let path = path.as_ref();
+        let temp = temp_path(path);
+        let result = self.write(res, &temp).await;
+        finish(result, &temp, path).await
+    }
+
+    /// Sends the request of `request` and streams the body of its response to the file at
+    /// `path`, replacing it, and returns its size.
+    ///
+    /// With [`with_segments`](Self::with_segments), the request is sent with an open-ended
+    /// `Range` header, and if the server supports ranges and the file is large enough, the other
+    /// segments are requested concurrently with clones of the request, with an `If-Range` header
+    /// so that they fail rather than mix versions of a file changing meanwhile.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if a request fails or has a status which isn't a success, surfaced as a
+    /// [`StatusError`], if a segment isn't returned as requested, or as
+    /// [`download`](Self::download) does.
+    pub async fn fetch(&self, request: RequestBuilder, path: impl AsRef<Path>) -> Result<u64> {
+        let path = path.as_ref();
+        let temp = temp_path(path);
+        let result = self.fetch_to(request, &temp).await;
+        finish(result, &temp, path).await
+    }
+
+    async fn fetch_to(&self, request: RequestBuilder, temp: &Path) -> Result<u64> {
+        let probe = match request.try_clone() {
+            Some(probe) if self.segments > 1 => probe,
+            _ => return self.write(send(request).await?, temp).await,
+        };
+        let res = send(probe.header(RANGE, "bytes=0-")).await?;
+        let total = match content_range(&res) {
+            Some((0, total)) if res.status() == StatusCode::PARTIAL_CONTENT => total,
+            _ => return self.write(res, temp).await,
+        };
+        let ranges = self.ranges(total);
+        if ranges.len() < 2 {
+            return self.write(res, temp).await;
+        }
+
+        let file = tokio::fs::File::create(temp)
+            .await
+            .map_err(Error::middleware)?;
+        file.set_len(total).await.map_err(Error::middleware)?;
+        let validator = res
+            .headers()
+            .get(ETAG)
+            .or_else(|| res.headers().get(LAST_MODIFIED))
+            .cloned();
+        let transferred = AtomicU64::new(0);
+        let segment = Segment {
+            request: &request,
+            validator: validator.as_ref(),
+            temp,
+            total,
+            transferred: &transferred,
+        };
+        let mut first = Some(res);
+        let segments = self.segments;
+        futures::stream::iter(ranges)
+            .map(|range| self.write_segment(&segment, first.take(), range))
+            .buffer_unordered(segments)
+            .try_collect::<Vec<()>>()
+            .await?;
+
+        #[cfg(feature = "checksum")]
+        if self.sha256.is_some() {
+            self.verify_checksum(digest_file(temp).await?)?;
+        }
+        Ok(total)
+    }
+
+    /// The inclusive byte ranges of the segments of a file of `total` bytes.
+    fn ranges(&self, total: u64) -> Vec<(u64, u64)> {
+        let segments = self.segments as u64;
+        let size = total.div_ceil(segments).max(self.min_segment_size);
+        (0..total)
+            .step_by(size as usize)
+            .map(|start| (start, (start + size).min(total) - 1))
+            .collect()
+    }
+
+    /// Writes the range `start..=end` to the temporary file, from `res` when it starts with it,
+    /// or else from a new range request.
+    async fn write_segment(
+        &self,
+        segment: &Segment<'_>,
+        res: Option<Response>,
+        (start, end): (u64, u64),
+    ) -> Result<()> {
+        let res = match res {
+            Some(res) => res,
+            None => {
+                let mut request = segment
+                    .request
+                    .try_clone()
+                    .expect("probed request is cloneable")
+                    .header(RANGE, format!("bytes={}-{}", start, end));
+                if let Some(validator) = segment.validator {
+                    request = request.header(IF_RANGE, validator.clone());
+                }
+                let res = send(request).await?;
+                let range_start = content_range(&res).map(|(start, _)| start);
+                if res.status() != StatusCode::PARTIAL_CONTENT || range_start != Some(start) {
+                    return Err(Error::middleware(InvalidRangeResponse { start, end }));
+                }
+                res
+            }
+        };
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(segment.temp)
+            .await
+            .map_err(Error::middleware)?;
+        file.seek(SeekFrom::Start(start))
+            .await
+            .map_err(Error::middleware)?;
+        let len = end + 1 - start;
+        let mut written = 0;
+        let mut body = res.bytes_stream();
+        while written < len {
+            let chunk = match body.next().await {
+                Some(chunk) => chunk?,
+                None => {
+                    return Err(Error::middleware(ContentLengthMismatch {
+                        expected: len,
+                        actual: written,
+                    }))
+                }
+            };
+            let chunk = &chunk[..chunk.len().min((len - written) as usize)];
+            file.write_all(chunk).await.map_err(Error::middleware)?;
+            written += chunk.len() as u64;
+            let transferred = segment
+                .transferred
+                .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            if let Some(callback) = &self.progress {
+                callback(Progress {
+                    transferred: transferred + chunk.len() as u64,
+                    total: Some(segment.total),
+                });
+            }
+        }
+        file.sync_all().await.map_err(Error::middleware)
+    }
+
+    async fn write(&self, res: Response, temp: &Path) -> Result<u64> {
+        let mut progress = Progress {
+            transferred: 0,
+            total: res.content_length(),
+        };
+        #[cfg(feature = "checksum")]
+        let mut digest = ring::digest::Context::new(&ring::digest::SHA256);
+
+        let mut file = tokio::fs::File::create(temp)
+            .await
+            .map_err(Error::middleware)?;
+        let mut body = res.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk).await.map_err(Error::middleware)?;
+            #[cfg(feature = "checksum")]
+            digest.update(&chunk);
+            progress.transferred += chunk.len() as u64;
+            if let Some(callback) = &self.progress {
+                callback(progress);
+            }
+        }
+        file.sync_all().await.map_err(Error::middleware)?;
+
+        if let Some(expected) = progress.total.filter(|len| *len != progress.transferred) {
+            return Err(Error::middleware(ContentLengthMismatch {
+                expected,
+                actual: progress.transferred,
+            }));
+        }
+        #[cfg(feature = "checksum")]
+        self.verify_checksum(digest.finish())?;
+        Ok(progress.transferred)
+    }
+
+    #[cfg(feature = "checksum")]
+    fn verify_checksum(&self, digest: ring::digest::Digest) -> Result<()> {
+        let expected = match &self.sha256 {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let actual: String = digest
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        if *expected != actual {
+            return Err(Error::middleware(ChecksumMismatch {
+                expected: expected.clone(),
+                actual,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The state shared by the segments of a download.
+struct Segment<'a> {
+    request: &'a RequestBuilder,
+    validator: Option<&'a HeaderValue>,
+    temp: &'a Path,
+    total: u64,
+    transferred: &'a AtomicU64,
+}
+
+/// Moves the temporary file of a successful download to `path`, or removes it.
+async fn finish(result: Result<u64>, temp: &Path, path: &Path) -> Result<u64> {
+    let result = match result {
+        Ok(size) => tokio::fs::rename(temp, path)
+            .await
+            .map(|()| size)
+            .map_err(Error::middleware),
+        Err(err) => Err(err),
+    };
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(temp).await;
+    }
+    result
+}
+
+async fn send(request: RequestBuilder) -> Result<Response> {
+    let res = request.send().await?;
+    if !res.status().is_success() {
+        let err = StatusError::from_response(res, StatusError::DEFAULT_MAX_BODY_SIZE).await;
+        return Err(Error::middleware(err));
+    }
+    Ok(res)
+}
+
+/// The first byte and the total size of the `Content-Range` of `res`, e.g. `bytes 0-99/1000`.
+fn content_range(res: &Response) -> Option<(u64, u64)> {
+    let value = res.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
+#[cfg(feature = "checksum")]
+async fn digest_file(path: &Path) -> Result<ring::digest::Digest> {
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(Error::middleware)?;
+    let mut digest = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).await.map_err(Error::middleware)?;
+        if read == 0 {
+            return Ok(digest.finish());
+        }
+        digest.update(&buf[..read]);
+    }
+}
+
+impl fmt::Debug for Downloader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Downloader")
+            .field("segments", &self.segments)
+            .field("min_segment_size", &self.min_segment_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The temporary file next to `path`, e.g. `.release.tar.gz.1234-0.part`.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let id = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}-{}.part", name, std::process::id(), id))
+}
+
+/// Extension trait downloading the bodies of responses to files, with a default [`Downloader`].
+///
+/// ```no_run
+/// use reqwest_middleware::ClientWithMiddleware;
+/// use reqwest_transfer::DownloadExt;
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// ClientWithMiddleware::from(reqwest::Client::new())
+///     .get("https://example.com/data.csv")
+///     .send()
+///     .await?
+///     .download_to("data.csv")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait DownloadExt {
+    /// Streams the body of the response to the file at `path`, atomically, and returns its size.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the body can't be read or written, or doesn't have the size of its
+    /// `Content-Length` header.
+    async fn download_to<P: AsRef<Path> + Send>(self, path: P) -> Result<u64>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl DownloadExt for Response {
+    async fn download_to<P: AsRef<Path> + Send>(self, path: P) -> Result<u64> {
+        Downloader::new().download(self, path).await
+    }
+}
@@ -0,0 +1,52 @@
+//! Middleware transferring the bodies of requests and responses, built on
+//! [`reqwest_middleware`].
+//!
+//! Use [`DecompressMiddleware`] to decode response bodies in content codings reqwest doesn't
+//! handle, such as `zstd`, and [`ProgressMiddleware`] and [`UploadProgressMiddleware`], or
+//! [`ProgressBody`], to report the progress of downloads and uploads. [`ThrottleMiddleware`]
+//! limits the bandwidth they use, and [`MaxResponseSizeMiddleware`] the size of response bodies.
+//! [`Downloader`] streams response bodies to files atomically, optionally in concurrent segments,
+//! and with the `tus` feature, `TusClient` uploads files resumably. [`ChunkedUploader`] drives
+//! multipart upload APIs, uploading the parts of large bodies concurrently.
+//!
+//! The middleware of this crate transform bodies as they stream, with
+//! [`reqwest_middleware::map_response_body`], which isn't available on `wasm32`.
+//!
+//! ## Feature flags
+//!
+//! * `zstd` (default): decode `zstd` response bodies.
+//! * `gzip`, `brotli`, `deflate`: decode `gzip`, `br` and `deflate` response bodies, for clients
+//!   built without the reqwest features of the same names.
+//! * `checksum`: verify the SHA-256 digest of downloads with [`Downloader`].
+//! * `tus`: resumable uploads with the tus protocol, see `TusClient`.
+//!
+//! ## Example
+//!
+//! ```
+//! use reqwest_middleware::ClientBuilder;
+//! use reqwest_transfer::DecompressMiddleware;
+//!
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(DecompressMiddleware)
+//!     .build();
+//! ```
+
+mod chunked;
+mod decompress;
+mod download;
+mod max_size;
+mod progress;
+mod throttle;
+#[cfg(feature = "tus")]
+mod tus;
+
+pub use chunked::{ChunkedUploader, MultipartUpload};
+pub use decompress::DecompressMiddleware;
+#[cfg(feature = "checksum")]
+pub use download::ChecksumMismatch;
+pub use download::{ContentLengthMismatch, DownloadExt, Downloader, InvalidRangeResponse};
+pub use max_size::{MaxResponseSizeMiddleware, ResponseTooLarge};
+pub use progress::{Progress, ProgressBody, ProgressMiddleware, UploadProgressMiddleware};
+pub use throttle::{Throttle, ThrottleMiddleware};
+#[cfg(feature = "tus")]
+pub use tus::{InvalidTusResponse, TusClient, UploadOffset};
@@ -0,0 +1,98 @@
+use futures::StreamExt;
+use http::Extensions;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Request, Response};
+use reqwest_middleware::{map_response_body, BoxError, Error, Middleware, Next, Result};
+
+/// The error of a response whose body is larger than the limit of a
+/// [`MaxResponseSizeMiddleware`].
+#[derive(Debug, thiserror::Error)]
+#[error("Response body exceeds the maximum size of {limit} bytes")]
+pub struct ResponseTooLarge {
+    /// The maximum size of response bodies, in bytes.
+    pub limit: u64,
+    /// The size of the body from its `Content-Length` header, `None` if the limit was exceeded
+    /// while streaming it.
+    pub content_length: Option<u64>,
+}
+
+/// `MaxResponseSizeMiddleware` aborts responses whose bodies are larger than a limit, as a
+/// protection against malicious or buggy servers.
+///
+/// Responses with a larger `Content-Length` fail right away with [`ResponseTooLarge`]. Other
+/// bodies are counted as they stream, and fail with a body error whose source is a
+/// [`ResponseTooLarge`] once over the limit, before the rest is read.
+///
+/// Added before a [`DecompressMiddleware`], the limit applies to decoded bodies, which also
+/// protects against decompression bombs.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_transfer::{DecompressMiddleware, MaxResponseSizeMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(MaxResponseSizeMiddleware::new(10 * 1024 * 1024))
+///     .with(DecompressMiddleware)
+///     .build();
+/// ```
+///
+/// [`DecompressMiddleware`]: crate::DecompressMiddleware
+#[derive(Clone, Copy, Debug)]
+pub struct MaxResponseSizeMiddleware {
+    limit: u64,
+}
+
+impl MaxResponseSizeMiddleware {
+    /// Construct `MaxResponseSizeMiddleware` failing responses with bodies larger than `limit`
+    /// bytes.
+    pub fn new(limit: u64) -> Self {
+        Self { limit }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for MaxResponseSizeMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let res = next.run(req, extensions).await?;
+        let limit = self.limit;
+        let content_length = res
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if let Some(content_length) = content_length.filter(|len| *len > limit) {
+            return Err(Error::middleware(ResponseTooLarge {
+                limit,
+                content_length: Some(content_length),
+            }));
+        }
+
+        Ok(map_response_body(res, move |_headers, body| {
+            let mut size = 0u64;
+            body.scan(false, move |exceeded, chunk| {
+                if *exceeded {
+                    return futures::future::ready(None);
+                }
+                let chunk = chunk.and_then(|chunk| {
+                    size += chunk.len() as u64;
+                    if size > limit {
+                        *exceeded = true;
+                        Err(BoxError::from(ResponseTooLarge {
+                            limit,
+                            content_length: None,
+                        }))
+                    } else {
+                        Ok(chunk)
+                    }
+                });
+                futures::future::ready(Some(chunk))
+            })
+        }))
+    }
+}
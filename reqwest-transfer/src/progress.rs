@@ -0,0 +1,282 @@
+use bytes::Bytes;
+use futures::{ready, StreamExt};
+use http::Extensions;
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use reqwest::header::{HeaderMap, CONTENT_LENGTH};
+use reqwest::{Body, Request, Response, Url};
+use reqwest_middleware::{map_response_body, Middleware, Next, Result};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// The progress of the transfer of a body, see [`ProgressMiddleware`] and [`ProgressBody`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of bytes transferred so far.
+    pub transferred: u64,
+    /// The size of the body, `None` when it isn't known in advance.
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// The fraction of the body transferred, from 0 to 1, `None` when the size of the body isn't
+    /// known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.transferred as f64 / total as f64).min(1.0)),
+            None => None,
+        }
+    }
+}
+
+type CallbackFn = dyn Fn(&Url, Progress) + Send + Sync + 'static;
+
+/// `ProgressMiddleware` reports the progress of the download of response bodies to a callback, as
+/// they are read, so that CLIs and GUIs can render progress bars.
+///
+/// The callback is called with the URL of the response once its headers are received, then after
+/// each chunk of the body. The total is taken from the `Content-Length` header, so the
+/// middleware should be added before a [`DecompressMiddleware`], to report the progress of the
+/// encoded body, which has one. To feed a channel, e.g. a `tokio::sync::watch` one, send the
+/// progress from the callback.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_transfer::{DecompressMiddleware, ProgressMiddleware};
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(ProgressMiddleware::new(|url, progress| {
+///         if let Some(fraction) = progress.fraction() {
+///             eprintln!("{}: {:.0}%", url, fraction * 100.0);
+///         }
+///     }))
+///     .with(DecompressMiddleware)
+///     .build();
+/// ```
+///
+/// [`DecompressMiddleware`]: crate::DecompressMiddleware
+#[derive(Clone)]
+pub struct ProgressMiddleware {
+    callback: Arc<CallbackFn>,
+}
+
+impl ProgressMiddleware {
+    /// Construct `ProgressMiddleware` passing the progress of downloads to `callback`.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&Url, Progress) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ProgressMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let res = next.run(req, extensions).await?;
+        let url = res.url().clone();
+        let total = content_length(res.headers());
+        let mut progress = Progress {
+            transferred: 0,
+            total,
+        };
+        (self.callback)(&url, progress);
+
+        let callback = self.callback.clone();
+        Ok(map_response_body(res, move |_headers, body| {
+            body.inspect(move |chunk| {
+                if let Ok(chunk) = chunk {
+                    progress.transferred += chunk.len() as u64;
+                    callback(&url, progress);
+                }
+            })
+        }))
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+type BodyCallbackFn = dyn Fn(Progress) + Send + Sync + 'static;
+
+/// A request body reporting the progress of its upload to a callback, as it is sent, since
+/// reqwest doesn't expose it.
+///
+/// The callback is called after each chunk of the body is read by the client. The total is the
+/// size of the wrapped body when it is known, e.g. for bytes or strings, or set with
+/// [`with_total`](Self::with_total). Use an [`UploadProgressMiddleware`] to report the progress
+/// of bodies built by reqwest, such as multipart forms.
+///
+/// A `ProgressBody` can't be cloned, so requests with one can't be retried.
+///
+/// ```no_run
+/// use reqwest_transfer::ProgressBody;
+///
+/// # async fn example() -> Result<(), reqwest::Error> {
+/// let body = ProgressBody::new(vec![0u8; 1 << 20], |progress| {
+///     eprintln!("{} of {:?} bytes sent", progress.transferred, progress.total);
+/// });
+/// reqwest::Client::new()
+///     .post("https://example.com/upload")
+///     .body(body)
+///     .send()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProgressBody {
+    inner: Body,
+    progress: Progress,
+    callback: Arc<BodyCallbackFn>,
+}
+
+impl ProgressBody {
+    /// Construct a `ProgressBody` sending `body`, and passing the progress of its upload to
+    /// `callback`.
+    pub fn new<F>(body: impl Into<Body>, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        Self::with_callback(body.into(), Arc::new(callback))
+    }
+
+    fn with_callback(inner: Body, callback: Arc<BodyCallbackFn>) -> Self {
+        let total = HttpBody::size_hint(&inner).exact();
+        Self {
+            inner,
+            progress: Progress {
+                transferred: 0,
+                total,
+            },
+            callback,
+        }
+    }
+
+    /// Report `total` as the size of the body, for streams whose size is known otherwise.
+    pub fn with_total(mut self, total: u64) -> Self {
+        self.progress.total = Some(total);
+        self
+    }
+}
+
+impl fmt::Debug for ProgressBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressBody")
+            .field("progress", &self.progress)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HttpBody for ProgressBody {
+    type Data = Bytes;
+    type Error = reqwest::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, reqwest::Error>>> {
+        let this = self.get_mut();
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(Frame::data_ref)
+        {
+            this.progress.transferred += data.len() as u64;
+            (this.callback)(this.progress);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl From<ProgressBody> for Body {
+    fn from(body: ProgressBody) -> Self {
+        Body::wrap(body)
+    }
+}
+
+/// `UploadProgressMiddleware` reports the progress of the upload of request bodies to a
+/// callback, by wrapping them in [`ProgressBody`]s, including the bodies built by reqwest such
+/// as multipart forms.
+///
+/// The callback is called with the URL of the request before it is sent, then after each chunk
+/// of the body. The total is the size of the body, or its `Content-Length` header.
+///
+/// Wrapped bodies can't be cloned, so the middleware should be added after a retry middleware,
+/// which then reports the progress of each attempt.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_transfer::UploadProgressMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     // The retry middleware goes here.
+///     .with(UploadProgressMiddleware::new(|url, progress| {
+///         eprintln!("{}: {} bytes sent", url, progress.transferred);
+///     }))
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct UploadProgressMiddleware {
+    callback: Arc<CallbackFn>,
+}
+
+impl UploadProgressMiddleware {
+    /// Construct `UploadProgressMiddleware` passing the progress of uploads to `callback`.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&Url, Progress) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for UploadProgressMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if let Some(body) = req.body_mut().take() {
+            let url = req.url().clone();
+            let callback = self.callback.clone();
+            let mut body = ProgressBody::with_callback(
+                body,
+                Arc::new(move |progress| callback(&url, progress)),
+            );
+            if let Some(total) = content_length(req.headers()) {
+                body = body.with_total(total);
+            }
+            (self.callback)(req.url(), body.progress);
+            *req.body_mut() = Some(body.into());
+        }
+        next.run(req, extensions).await
+    }
+}
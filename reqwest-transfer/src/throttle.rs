@@ -0,0 +1,205 @@
+use bytes::Bytes;
+use futures::{ready, Stream, StreamExt};
+use http::Extensions;
+use http_body::Body as HttpBody;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Body, Request, Response};
+use reqwest_middleware::{map_response_body, Clock, Middleware, Next, Result, SystemClock};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+/// A request extension overriding the rates of a [`ThrottleMiddleware`] for a single request,
+/// which then isn't counted against the rates of the middleware.
+///
+/// ```
+/// use reqwest_transfer::Throttle;
+///
+/// // Limit downloads to 64 KiB/s, and don't limit uploads.
+/// let throttle = Throttle {
+///     download: Some(64 * 1024),
+///     upload: None,
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Throttle {
+    /// The maximum rate at which the response body is read, in bytes per second.
+    pub download: Option<u64>,
+    /// The maximum rate at which the request body is sent, in bytes per second.
+    pub upload: Option<u64>,
+}
+
+/// Paces chunks of bytes to a rate, keeping the time at which the bytes sent so far conform to
+/// it.
+#[derive(Debug)]
+struct Limiter {
+    bytes_per_second: u64,
+    tat: Mutex<Option<Instant>>,
+}
+
+impl Limiter {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second.max(1),
+            tat: Mutex::new(None),
+        }
+    }
+
+    /// Returns how long to wait after transferring `len` bytes to conform to the rate.
+    fn acquire(&self, len: usize, now: Instant) -> Duration {
+        let mut tat = self.tat.lock().expect("throttle lock poisoned");
+        let start = tat.map_or(now, |tat| tat.max(now));
+        let next = start + Duration::from_secs_f64(len as f64 / self.bytes_per_second as f64);
+        *tat = Some(next);
+        next.saturating_duration_since(now)
+    }
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = std::result::Result<Bytes, reqwest::Error>> + Send>>;
+
+/// Delays each chunk of `body` after it is read, as long as `limiter` requires.
+fn throttle<S, E>(
+    body: S,
+    limiter: Arc<Limiter>,
+    clock: Arc<dyn Clock>,
+) -> impl Stream<Item = std::result::Result<Bytes, E>>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>>,
+{
+    body.then(move |chunk| {
+        let limiter = limiter.clone();
+        let clock = clock.clone();
+        async move {
+            if let Ok(chunk) = &chunk {
+                let wait = limiter.acquire(chunk.len(), clock.now());
+                if !wait.is_zero() {
+                    clock.sleep(wait).await;
+                }
+            }
+            chunk
+        }
+    })
+}
+
+/// The data frames of a request body, as a stream.
+fn body_stream(mut body: Body) -> ByteStream {
+    Box::pin(futures::stream::poll_fn(move |cx| loop {
+        match ready!(Pin::new(&mut body).poll_frame(cx)) {
+            Some(Ok(frame)) => {
+                if let Ok(data) = frame.into_data() {
+                    return Poll::Ready(Some(Ok(data)));
+                }
+            }
+            Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+            None => return Poll::Ready(None),
+        }
+    }))
+}
+
+/// `ThrottleMiddleware` limits the bandwidth used by request and response bodies, in bytes per
+/// second, e.g. so that background jobs don't saturate the links they share.
+///
+/// The rates are shared by all the requests going through the middleware, and its clones, or
+/// overridden for a request with a [`Throttle`] extension. Bodies are paced chunk by chunk as
+/// they stream, so the download of a response body is slowed down by reading it slower.
+///
+/// Throttled request bodies can't be cloned, so the middleware should be added after a retry
+/// middleware.
+///
+/// ```
+/// use reqwest_middleware::ClientBuilder;
+/// use reqwest_transfer::ThrottleMiddleware;
+///
+/// let client = ClientBuilder::new(reqwest::Client::new())
+///     .with(
+///         ThrottleMiddleware::new()
+///             .with_download_rate(1024 * 1024)
+///             .with_upload_rate(256 * 1024),
+///     )
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct ThrottleMiddleware {
+    download: Option<Arc<Limiter>>,
+    upload: Option<Arc<Limiter>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl ThrottleMiddleware {
+    /// Construct `ThrottleMiddleware` without any limit; set some with
+    /// [`with_download_rate`](Self::with_download_rate) and
+    /// [`with_upload_rate`](Self::with_upload_rate).
+    pub fn new() -> Self {
+        Self {
+            download: None,
+            upload: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Read response bodies at most `bytes_per_second`.
+    pub fn with_download_rate(mut self, bytes_per_second: u64) -> Self {
+        self.download = Some(Arc::new(Limiter::new(bytes_per_second)));
+        self
+    }
+
+    /// Send request bodies at most `bytes_per_second`.
+    pub fn with_upload_rate(mut self, bytes_per_second: u64) -> Self {
+        self.upload = Some(Arc::new(Limiter::new(bytes_per_second)));
+        self
+    }
+
+    /// Use `clock` to measure time and wait.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl Default for ThrottleMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl Middleware for ThrottleMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let (download, upload) = match extensions.get::<Throttle>() {
+            Some(throttle) => (
+                throttle.download.map(|rate| Arc::new(Limiter::new(rate))),
+                throttle.upload.map(|rate| Arc::new(Limiter::new(rate))),
+            ),
+            None => (self.download.clone(), self.upload.clone()),
+        };
+
+        if let Some(limiter) = upload {
+            if let Some(body) = req.body_mut().take() {
+                // The wrapped body loses its size, which reqwest would send as `Content-Length`.
+                if let Some(len) = HttpBody::size_hint(&body).exact() {
+                    req.headers_mut()
+                        .entry(CONTENT_LENGTH)
+                        .or_insert(len.into());
+                }
+                let body = throttle(body_stream(body), limiter, self.clock.clone());
+                *req.body_mut() = Some(Body::wrap_stream(body));
+            }
+        }
+
+        let res = next.run(req, extensions).await?;
+        Ok(match download {
+            Some(limiter) => {
+                let clock = self.clock.clone();
+                map_response_body(res, move |_headers, body| throttle(body, limiter, clock))
+            }
+            None => res,
+        })
+    }
+}
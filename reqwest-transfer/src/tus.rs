@@ -0,0 +1,233 @@
+use base64::Engine;
+use reqwest::header::{HeaderName, HeaderValue, CONTENT_TYPE, LOCATION};
+use reqwest::{IntoUrl, Method, Response, Url};
+use reqwest_middleware::{ClientWithMiddleware, Error, RequestBuilder, Result, StatusError};
+use std::fmt;
+use std::io::SeekFrom;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::Progress;
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const UPLOAD_CHECKSUM: HeaderName = HeaderName::from_static("upload-checksum");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+
+/// The version of the tus protocol implemented by [`TusClient`].
+const TUS_VERSION: HeaderValue = HeaderValue::from_static("1.0.0");
+
+/// The error of a tus server response missing a header of the protocol, or with an invalid one.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid tus response: missing or invalid {0} header")]
+pub struct InvalidTusResponse(pub HeaderName);
+
+/// The state of an upload, as returned by [`TusClient::offset`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadOffset {
+    /// The number of bytes received by the server.
+    pub offset: u64,
+    /// The size of the upload, `None` if it was deferred.
+    pub length: Option<u64>,
+}
+
+type CallbackFn = dyn Fn(Progress) + Send + Sync + 'static;
+
+/// A client of the [tus](https://tus.io/protocols/resumable-upload) 1.0 resumable upload
+/// protocol, sending its requests through a [`ClientWithMiddleware`].
+///
+/// Uploads are created with [`create`](Self::create), then sent in chunks with
+/// [`upload`](Self::upload), which first asks the server for the offset it has received, so
+/// that calling it again after an interruption resumes the upload rather than restarting it.
+/// With [`with_checksum`](Self::with_checksum), chunks carry their SHA-1 digest, as per the
+/// checksum extension, so that servers discard corrupted ones.
+///
+/// Responses with a status which isn't a success fail with a [`StatusError`], e.g. `409
+/// Conflict` when the offset is out of date.
+///
+/// # Optional
+///
+/// This requires the optional `tus` feature enabled.
+///
+/// ```no_run
+/// use reqwest_middleware::ClientWithMiddleware;
+/// use reqwest_transfer::TusClient;
+///
+/// # async fn example() -> reqwest_middleware::Result<()> {
+/// let tus = TusClient::new(ClientWithMiddleware::from(reqwest::Client::new()));
+/// let file = tokio::fs::File::open("video.mp4").await.unwrap();
+/// let length = file.metadata().await.unwrap().len();
+/// let upload = tus
+///     .create("https://tus.example.com/files/", length, &[("filename", "video.mp4")])
+///     .await?;
+/// // Store `upload` to resume the upload later.
+/// tus.upload(&upload, file).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TusClient {
+    client: ClientWithMiddleware,
+    chunk_size: usize,
+    checksum: bool,
+    progress: Option<Arc<CallbackFn>>,
+}
+
+impl TusClient {
+    /// The size of the chunks sent by default, 4 MiB.
+    pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+    /// Construct a `TusClient` sending its requests with `client`.
+    pub fn new(client: ClientWithMiddleware) -> Self {
+        Self {
+            client,
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            checksum: false,
+            progress: None,
+        }
+    }
+
+    /// Send uploads in chunks of `chunk_size` bytes, each in a `PATCH` request.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Send the SHA-1 digest of each chunk in an `Upload-Checksum` header, which the server must
+    /// support.
+    pub fn with_checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Pass the progress of uploads to `callback`, after each chunk acknowledged by the server.
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Creates an upload of `length` bytes at `endpoint`, with `metadata` key-value pairs, and
+    /// returns its URL.
+    pub async fn create<U: IntoUrl>(
+        &self,
+        endpoint: U,
+        length: u64,
+        metadata: &[(&str, &str)],
+    ) -> Result<Url> {
+        let endpoint = endpoint.into_url()?;
+        let mut request = self
+            .request(Method::POST, endpoint.clone())
+            .header(UPLOAD_LENGTH, length);
+        if !metadata.is_empty() {
+            let metadata = metadata
+                .iter()
+                .map(|(key, value)| {
+                    let value = base64::engine::general_purpose::STANDARD.encode(value);
+                    format!("{} {}", key, value)
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            request = request.header(UPLOAD_METADATA, metadata);
+        }
+        let res = send(request).await?;
+        res.headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| endpoint.join(location).ok())
+            .ok_or_else(|| Error::middleware(InvalidTusResponse(LOCATION)))
+    }
+
+    /// Asks the server how much of the upload at `upload` it has received.
+    pub async fn offset(&self, upload: &Url) -> Result<UploadOffset> {
+        let res = send(self.request(Method::HEAD, upload.clone())).await?;
+        Ok(UploadOffset {
+            offset: offset_header(&res)?,
+            length: header(&res, &UPLOAD_LENGTH),
+        })
+    }
+
+    /// Sends the rest of the upload at `upload` from `data`, which holds the whole upload and is
+    /// read from the offset received by the server, and returns the size of the upload.
+    pub async fn upload<R>(&self, upload: &Url, mut data: R) -> Result<u64>
+    where
+        R: AsyncRead + AsyncSeek + Unpin + Send,
+    {
+        let UploadOffset { mut offset, length } = self.offset(upload).await?;
+        data.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(Error::middleware)?;
+
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+        loop {
+            if length.is_some_and(|length| offset >= length) {
+                return Ok(offset);
+            }
+            chunk.clear();
+            (&mut data)
+                .take(self.chunk_size as u64)
+                .read_to_end(&mut chunk)
+                .await
+                .map_err(Error::middleware)?;
+            if chunk.is_empty() {
+                return Ok(offset);
+            }
+
+            let mut request = self
+                .request(Method::PATCH, upload.clone())
+                .header(UPLOAD_OFFSET, offset)
+                .header(CONTENT_TYPE, "application/offset+octet-stream");
+            if self.checksum {
+                let digest = ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, &chunk);
+                let digest = base64::engine::general_purpose::STANDARD.encode(digest);
+                request = request.header(UPLOAD_CHECKSUM, format!("sha1 {}", digest));
+            }
+            let res = send(request.body(chunk.clone())).await?;
+            offset = offset_header(&res)?;
+            if let Some(callback) = &self.progress {
+                callback(Progress {
+                    transferred: offset,
+                    total: length,
+                });
+            }
+        }
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+    }
+}
+
+impl fmt::Debug for TusClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TusClient")
+            .field("chunk_size", &self.chunk_size)
+            .field("checksum", &self.checksum)
+            .finish_non_exhaustive()
+    }
+}
+
+async fn send(request: RequestBuilder) -> Result<Response> {
+    let res = request.send().await?;
+    if !res.status().is_success() {
+        let err = StatusError::from_response(res, StatusError::DEFAULT_MAX_BODY_SIZE).await;
+        return Err(Error::middleware(err));
+    }
+    Ok(res)
+}
+
+fn header(res: &Response, name: &HeaderName) -> Option<u64> {
+    res.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+fn offset_header(res: &Response) -> Result<u64> {
+    header(res, &UPLOAD_OFFSET).ok_or_else(|| Error::middleware(InvalidTusResponse(UPLOAD_OFFSET)))
+}
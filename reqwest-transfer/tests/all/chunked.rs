@@ -0,0 +1,108 @@
+use bytes::Bytes;
+use reqwest::Client;
+use reqwest_middleware::{ClientWithMiddleware, Error, MockClock, Result};
+use reqwest_transfer::{ChunkedUploader, MultipartUpload};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+#[error("part failed")]
+struct PartFailed;
+
+/// Stores parts in memory, failing the first attempt of part 2, or all the attempts.
+#[derive(Default)]
+struct MemoryUpload {
+    fail_always: bool,
+    attempts: Mutex<BTreeMap<usize, u32>>,
+    parts: Mutex<BTreeMap<usize, Bytes>>,
+    completed: Mutex<Option<(Vec<usize>, Vec<u8>)>>,
+    aborted: Mutex<bool>,
+}
+
+#[async_trait::async_trait]
+impl MultipartUpload for MemoryUpload {
+    type Upload = ();
+    type Part = usize;
+
+    async fn initiate(&self, _client: &ClientWithMiddleware) -> Result<()> {
+        Ok(())
+    }
+
+    async fn upload_part(
+        &self,
+        _client: &ClientWithMiddleware,
+        _upload: &(),
+        number: usize,
+        data: Bytes,
+    ) -> Result<usize> {
+        let mut attempts = self.attempts.lock().unwrap();
+        let attempt = attempts.entry(number).or_default();
+        *attempt += 1;
+        if number == 2 && (self.fail_always || *attempt == 1) {
+            return Err(Error::middleware(PartFailed));
+        }
+        self.parts.lock().unwrap().insert(number, data);
+        Ok(number)
+    }
+
+    async fn complete(
+        &self,
+        _client: &ClientWithMiddleware,
+        _upload: (),
+        parts: Vec<usize>,
+    ) -> Result<()> {
+        let data = self
+            .parts
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(|part| part.to_vec())
+            .collect();
+        *self.completed.lock().unwrap() = Some((parts, data));
+        Ok(())
+    }
+
+    async fn abort(&self, _client: &ClientWithMiddleware, _upload: ()) -> Result<()> {
+        *self.aborted.lock().unwrap() = true;
+        Ok(())
+    }
+}
+
+fn uploader() -> ChunkedUploader {
+    ChunkedUploader::new(ClientWithMiddleware::from(Client::new()))
+        .with_part_size(4)
+        .with_concurrency(2)
+        .with_clock(Arc::new(MockClock::new()))
+}
+
+#[tokio::test]
+async fn assert_parts_are_uploaded_and_completed_in_order() {
+    let upload = MemoryUpload::default();
+    let size = uploader()
+        .upload(&upload, &b"abcdefghij"[..])
+        .await
+        .unwrap();
+
+    assert_eq!(size, 10);
+    let completed = upload.completed.lock().unwrap().take().unwrap();
+    assert_eq!(completed, (vec![1, 2, 3], b"abcdefghij".to_vec()));
+    assert_eq!(upload.attempts.lock().unwrap()[&2], 2);
+}
+
+#[tokio::test]
+async fn assert_failed_upload_is_aborted() {
+    let upload = MemoryUpload {
+        fail_always: true,
+        ..MemoryUpload::default()
+    };
+    uploader()
+        .with_retries(3, Duration::from_secs(1))
+        .upload(&upload, &b"abcdefghij"[..])
+        .await
+        .unwrap_err();
+
+    assert!(*upload.aborted.lock().unwrap());
+    assert!(upload.completed.lock().unwrap().is_none());
+    assert_eq!(upload.attempts.lock().unwrap()[&2], 3);
+}
@@ -0,0 +1,63 @@
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+use reqwest::Client;
+use reqwest_middleware::ClientBuilder;
+use reqwest_transfer::DecompressMiddleware;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_zstd_bodies_are_decoded() {
+    let server = MockServer::start().await;
+    let body = zstd::encode_all(&b"hello, compressed world"[..], 3).unwrap();
+    Mock::given(method("GET"))
+        // Other codings are accepted too with the `gzip`, `brotli` and `deflate` features.
+        .and(|req: &wiremock::Request| {
+            req.headers
+                .get(ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.split(',').any(|coding| coding.trim() == "zstd"))
+        })
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(CONTENT_ENCODING, "zstd")
+                .set_body_bytes(body),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(DecompressMiddleware)
+        .build();
+    let res = client.get(server.uri()).send().await.unwrap();
+
+    assert!(res.headers().get(CONTENT_ENCODING).is_none());
+    assert_eq!(res.text().await.unwrap(), "hello, compressed world");
+}
+
+#[tokio::test]
+async fn assert_other_codings_are_left_alone() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(CONTENT_ENCODING, "identity")
+                .set_body_string("plain"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(DecompressMiddleware)
+        .build();
+    let res = client
+        .get(server.uri())
+        .header(ACCEPT_ENCODING, "identity")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.headers()[CONTENT_ENCODING], "identity");
+    assert_eq!(res.text().await.unwrap(), "plain");
+}
@@ -0,0 +1,150 @@
+use reqwest::Client;
+use reqwest_middleware::ClientWithMiddleware;
+use reqwest_transfer::{DownloadExt, Downloader};
+use std::path::PathBuf;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+fn dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("reqwest-transfer-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("hello, file"))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn assert_body_is_downloaded_to_file() {
+    let server = server().await;
+    let dir = dir("download");
+    let path = dir.join("hello.txt");
+
+    let res = Client::new().get(server.uri()).send().await.unwrap();
+    let size = res.download_to(&path).await.unwrap();
+
+    assert_eq!(size, 11);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello, file");
+    // Only the destination is left behind.
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "checksum")]
+#[tokio::test]
+async fn assert_checksum_mismatch_keeps_destination() {
+    let server = server().await;
+    let dir = dir("checksum");
+    let path = dir.join("hello.txt");
+    std::fs::write(&path, "previous").unwrap();
+
+    let res = Client::new().get(server.uri()).send().await.unwrap();
+    let err = Downloader::new()
+        .with_sha256("00")
+        .download(res, &path)
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("SHA-256"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn assert_download_progress_is_reported() {
+    let server = server().await;
+    let dir = dir("progress");
+    let path = dir.join("hello.txt");
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let res = Client::new().get(server.uri()).send().await.unwrap();
+    Downloader::new()
+        .with_progress(move |progress| sender.send(progress).unwrap())
+        .download(res, &path)
+        .await
+        .unwrap();
+
+    let last = receiver.try_iter().last().unwrap();
+    assert_eq!(last.transferred, 11);
+    assert_eq!(last.total, Some(11));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+
+/// Serves the alphabet, honouring single byte ranges.
+struct RangeResponder;
+
+impl Respond for RangeResponder {
+    fn respond(&self, req: &Request) -> ResponseTemplate {
+        let range = match req.headers.get("range") {
+            Some(range) => range.to_str().unwrap(),
+            None => return ResponseTemplate::new(200).set_body_bytes(ALPHABET),
+        };
+        let (start, end) = range
+            .strip_prefix("bytes=")
+            .unwrap()
+            .split_once('-')
+            .unwrap();
+        let start: usize = start.parse().unwrap();
+        let end: usize = end.parse().unwrap_or(ALPHABET.len() - 1);
+        ResponseTemplate::new(206)
+            .insert_header(
+                "content-range",
+                format!("bytes {}-{}/{}", start, end, ALPHABET.len()).as_str(),
+            )
+            .set_body_bytes(&ALPHABET[start..=end])
+    }
+}
+
+#[tokio::test]
+async fn assert_segmented_download_is_reassembled() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(RangeResponder)
+        .expect(3)
+        .mount(&server)
+        .await;
+    let dir = dir("segments");
+    let path = dir.join("alphabet.txt");
+
+    let client = ClientWithMiddleware::from(Client::new());
+    let size = Downloader::new()
+        .with_segments(3)
+        .with_min_segment_size(4)
+        .fetch(client.get(server.uri()), &path)
+        .await
+        .unwrap();
+
+    assert_eq!(size, 26);
+    assert_eq!(std::fs::read(&path).unwrap(), ALPHABET);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn assert_segmented_download_falls_back_without_ranges() {
+    let server = server().await;
+    let dir = dir("no-ranges");
+    let path = dir.join("hello.txt");
+
+    let client = ClientWithMiddleware::from(Client::new());
+    let size = Downloader::new()
+        .with_segments(4)
+        .with_min_segment_size(1)
+        .fetch(client.get(server.uri()), &path)
+        .await
+        .unwrap();
+
+    assert_eq!(size, 11);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello, file");
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,8 @@
+mod chunked;
+mod decompress;
+mod download;
+mod max_size;
+mod progress;
+mod throttle;
+#[cfg(feature = "tus")]
+mod tus;
@@ -0,0 +1,87 @@
+use http::Extensions;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Client, Request, Response};
+use reqwest_middleware::{ClientBuilder, Middleware, Next};
+use reqwest_transfer::{MaxResponseSizeMiddleware, ResponseTooLarge};
+use std::error::Error as _;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_large_content_length_is_rejected() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 1000]))
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(MaxResponseSizeMiddleware::new(100))
+        .build();
+    let err = client.get(server.uri()).send().await.unwrap_err();
+    let err = match err {
+        reqwest_middleware::Error::Middleware(err) => err,
+        err => panic!("unexpected error {:?}", err),
+    };
+    let err = err.downcast_ref::<ResponseTooLarge>().unwrap();
+    assert_eq!(err.limit, 100);
+    assert_eq!(err.content_length, Some(1000));
+}
+
+/// Removes the `Content-Length` header of responses, as if they were chunked.
+struct RemoveContentLength;
+
+#[async_trait::async_trait]
+impl Middleware for RemoveContentLength {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let mut res = next.run(req, extensions).await?;
+        res.headers_mut().remove(CONTENT_LENGTH);
+        Ok(res)
+    }
+}
+
+#[tokio::test]
+async fn assert_large_streamed_body_is_aborted() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/small"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 100]))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/large"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 1000]))
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::new(Client::new())
+        .with(MaxResponseSizeMiddleware::new(100))
+        .with(RemoveContentLength)
+        .build();
+    let small = client
+        .get(format!("{}/small", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(small.bytes().await.unwrap().len(), 100);
+
+    let large = client
+        .get(format!("{}/large", server.uri()))
+        .send()
+        .await
+        .unwrap();
+    let err = large.bytes().await.unwrap_err();
+    let mut source = err.source();
+    while let Some(err) = source {
+        if err.downcast_ref::<ResponseTooLarge>().is_some() {
+            return;
+        }
+        source = err.source();
+    }
+    panic!("no ResponseTooLarge in {:?}", err);
+}
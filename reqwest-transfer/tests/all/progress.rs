@@ -0,0 +1,114 @@
+use reqwest::Client;
+use reqwest_middleware::ClientBuilder;
+use reqwest_transfer::{Progress, ProgressBody, ProgressMiddleware, UploadProgressMiddleware};
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_download_progress_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 1000]))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let client = ClientBuilder::new(Client::new())
+        .with(ProgressMiddleware::new({
+            let reports = reports.clone();
+            move |_url, progress| reports.lock().unwrap().push(progress)
+        }))
+        .build();
+    let res = client.get(server.uri()).send().await.unwrap();
+    assert_eq!(
+        *reports.lock().unwrap(),
+        vec![Progress {
+            transferred: 0,
+            total: Some(1000)
+        }]
+    );
+
+    assert_eq!(res.url().as_str(), format!("{}/", server.uri()));
+    assert_eq!(res.bytes().await.unwrap().len(), 1000);
+    let reports = reports.lock().unwrap();
+    let last = reports.last().unwrap();
+    assert_eq!(last.transferred, 1000);
+    assert_eq!(last.fraction(), Some(1.0));
+    assert!(reports
+        .windows(2)
+        .all(|pair| pair[0].transferred < pair[1].transferred));
+}
+
+#[tokio::test]
+async fn assert_upload_progress_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let client = ClientBuilder::new(Client::new())
+        .with(UploadProgressMiddleware::new({
+            let reports = reports.clone();
+            move |_url, progress| reports.lock().unwrap().push(progress)
+        }))
+        .build();
+    client
+        .post(server.uri())
+        .body(vec![0u8; 1000])
+        .send()
+        .await
+        .unwrap();
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(
+        reports.first(),
+        Some(&Progress {
+            transferred: 0,
+            total: Some(1000)
+        })
+    );
+    assert_eq!(
+        reports.last(),
+        Some(&Progress {
+            transferred: 1000,
+            total: Some(1000)
+        })
+    );
+}
+
+#[tokio::test]
+async fn assert_progress_body_reports_progress() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let body = ProgressBody::new("hello", {
+        let reports = reports.clone();
+        move |progress| reports.lock().unwrap().push(progress)
+    });
+    Client::new()
+        .put(server.uri())
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *reports.lock().unwrap(),
+        vec![Progress {
+            transferred: 5,
+            total: Some(5)
+        }]
+    );
+    let received = &server.received_requests().await.unwrap()[0];
+    assert_eq!(received.body, b"hello");
+}
@@ -0,0 +1,82 @@
+use reqwest::Client;
+use reqwest_middleware::{ClientBuilder, Clock, MockClock};
+use reqwest_transfer::{Throttle, ThrottleMiddleware};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 1000]))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn assert_downloads_are_throttled() {
+    let server = server().await;
+    let clock = Arc::new(MockClock::new());
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            ThrottleMiddleware::new()
+                .with_download_rate(100)
+                .with_clock(clock.clone()),
+        )
+        .build();
+
+    let start = clock.now();
+    let res = client.post(server.uri()).send().await.unwrap();
+    assert_eq!(res.bytes().await.unwrap().len(), 1000);
+    assert!(clock.now() - start >= Duration::from_millis(9990));
+}
+
+#[tokio::test]
+async fn assert_uploads_are_throttled() {
+    let server = server().await;
+    let clock = Arc::new(MockClock::new());
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            ThrottleMiddleware::new()
+                .with_upload_rate(100)
+                .with_clock(clock.clone()),
+        )
+        .build();
+
+    let start = clock.now();
+    client
+        .post(server.uri())
+        .body(vec![0u8; 500])
+        .send()
+        .await
+        .unwrap();
+    assert!(clock.now() - start >= Duration::from_millis(4990));
+    let received = &server.received_requests().await.unwrap()[0];
+    assert_eq!(received.headers["content-length"], "500");
+    assert_eq!(received.body.len(), 500);
+}
+
+#[tokio::test]
+async fn assert_throttle_extension_overrides_rates() {
+    let server = server().await;
+    let clock = Arc::new(MockClock::new());
+    let client = ClientBuilder::new(Client::new())
+        .with(
+            ThrottleMiddleware::new()
+                .with_download_rate(100)
+                .with_clock(clock.clone()),
+        )
+        .build();
+
+    let start = clock.now();
+    let res = client
+        .post(server.uri())
+        .with_extension(Throttle::default())
+        .send()
+        .await
+        .unwrap();
+    res.bytes().await.unwrap();
+    assert_eq!(clock.now(), start);
+}
@@ -0,0 +1,82 @@
+use reqwest::Client;
+use reqwest_middleware::ClientWithMiddleware;
+use reqwest_transfer::{TusClient, UploadOffset};
+use std::io::Cursor;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn assert_upload_is_created_and_resumed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/"))
+        .and(header("tus-resumable", "1.0.0"))
+        .and(header("upload-length", "11"))
+        .and(header("upload-metadata", "filename aGVsbG8udHh0"))
+        .respond_with(ResponseTemplate::new(201).insert_header("location", "/files/abc"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .and(path("/files/abc"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("upload-offset", "5")
+                .insert_header("upload-length", "11"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/files/abc"))
+        .and(header("upload-offset", "5"))
+        .and(header("content-type", "application/offset+octet-stream"))
+        .respond_with(ResponseTemplate::new(204).insert_header("upload-offset", "11"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let tus = TusClient::new(ClientWithMiddleware::from(Client::new()));
+    let upload = tus
+        .create(
+            format!("{}/files/", server.uri()),
+            11,
+            &[("filename", "hello.txt")],
+        )
+        .await
+        .unwrap();
+    assert_eq!(upload.as_str(), format!("{}/files/abc", server.uri()));
+
+    let size = tus
+        .upload(&upload, Cursor::new(b"hello, file".to_vec()))
+        .await
+        .unwrap();
+    assert_eq!(size, 11);
+    let patch = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|req| req.method.as_str() == "PATCH")
+        .unwrap();
+    assert_eq!(patch.body, b", file");
+}
+
+#[tokio::test]
+async fn assert_offset_is_probed() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(200).insert_header("upload-offset", "3"))
+        .mount(&server)
+        .await;
+
+    let tus = TusClient::new(ClientWithMiddleware::from(Client::new()));
+    let offset = tus.offset(&server.uri().parse().unwrap()).await.unwrap();
+    assert_eq!(
+        offset,
+        UploadOffset {
+            offset: 3,
+            length: None
+        }
+    );
+}